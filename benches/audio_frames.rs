@@ -0,0 +1,87 @@
+// Per-frame cost of the capture and playback hot paths, the way they were (a fresh Vec
+// per callback and per packet) against the frame pool and in-place decoding.
+//
+//     cargo bench --bench audio_frames
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::hint::black_box;
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+use webrtc_client::audio::{decode_frame, encode_frame, FramePool, PlaybackBuffer};
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+// 20 ms of 48 kHz stereo
+const FRAME_SAMPLES: usize = 1920;
+const WARMUP: usize = 1_000;
+const FRAMES: usize = 100_000;
+
+fn run(name: &str, mut frame: impl FnMut()) {
+    for _ in 0..WARMUP {
+        frame();
+    }
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let started = Instant::now();
+    for _ in 0..FRAMES {
+        frame();
+    }
+    let elapsed = started.elapsed();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - allocations;
+    println!(
+        "{:<24} {:>8.0} ns/frame {:>6.2} allocations/frame",
+        name,
+        elapsed.as_nanos() as f64 / FRAMES as f64,
+        allocations as f64 / FRAMES as f64
+    );
+}
+
+fn main() {
+    // What an i16 device hands the capture callback
+    let device: Vec<i16> = (0..FRAME_SAMPLES).map(|i| (i as i16).wrapping_mul(17)).collect();
+    let to_sample = |sample: &i16| *sample as f32 / 32768.0;
+
+    run("capture, per callback", || {
+        let samples: Vec<f32> = device.iter().map(to_sample).collect();
+        black_box(encode_frame(&samples));
+    });
+    let mut samples = Vec::new();
+    let mut pool = FramePool::default();
+    run("capture, pooled", || {
+        samples.clear();
+        samples.extend(device.iter().map(to_sample));
+        black_box(pool.encode(&samples));
+    });
+
+    let packet = encode_frame(&[0.25; FRAME_SAMPLES]);
+    let mut output = Vec::with_capacity(FRAME_SAMPLES);
+    let mut buffer = PlaybackBuffer::new(96_000, Arc::new(AtomicU32::new(0)));
+    run("playback, per packet", || {
+        buffer.push(&decode_frame(&packet));
+        output.clear();
+        buffer.pop_into(&mut output, FRAME_SAMPLES);
+        black_box(&output);
+    });
+    let mut buffer = PlaybackBuffer::new(96_000, Arc::new(AtomicU32::new(0)));
+    run("playback, in place", || {
+        buffer.push_encoded(&packet);
+        output.clear();
+        buffer.pop_into(&mut output, FRAME_SAMPLES);
+        black_box(&output);
+    });
+}
@@ -0,0 +1,122 @@
+// Throughput of each stage a 20 ms frame goes through, so a change that slows the
+// audio path down shows up before a release does. Criterion reports the time per frame
+// (the latency a stage adds) and samples per second.
+//
+//     cargo bench --bench audio_pipeline
+use bytes::Bytes;
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use std::sync::atomic::{AtomicBool, AtomicU32};
+use std::sync::Arc;
+use webrtc_client::audio::codec::{resample, PLAYBACK_SAMPLE_RATE};
+use webrtc_client::audio::{
+    decode_frame, encode_frame, pan_to_stereo_into, AudioCodec, FramePool, LoudnessNormalizer, PlaybackBuffer, Sidetone,
+};
+
+// 20 ms at 48 kHz, mono as captured and stereo as played
+const MONO_SAMPLES: usize = 960;
+const STEREO_SAMPLES: usize = MONO_SAMPLES * 2;
+
+// Speech-like enough that the normalizer sees a level above its silence gate
+fn speech(samples: usize) -> Vec<f32> {
+    (0..samples)
+        .map(|i| 0.3 * (i as f32 * 2.0 * std::f32::consts::PI * 220.0 / PLAYBACK_SAMPLE_RATE as f32).sin())
+        .collect()
+}
+
+fn encode(c: &mut Criterion) {
+    let samples = speech(MONO_SAMPLES);
+    let frame = encode_frame(&samples);
+    let mut group = c.benchmark_group("encode");
+    group.throughput(Throughput::Elements(MONO_SAMPLES as u64));
+
+    group.bench_function("encode_frame", |b| b.iter(|| encode_frame(black_box(&samples))));
+    let mut pool = FramePool::default();
+    group.bench_function("frame_pool", |b| b.iter(|| pool.encode(black_box(&samples))));
+    group.bench_function("decode_frame", |b| b.iter(|| decode_frame(black_box(&frame))));
+    for codec in AudioCodec::ALL {
+        let payload = codec.encode(&frame, PLAYBACK_SAMPLE_RATE);
+        group.bench_with_input(BenchmarkId::new("codec_encode", format!("{:?}", codec)), &frame, |b, frame| {
+            b.iter(|| codec.encode(black_box(frame), PLAYBACK_SAMPLE_RATE))
+        });
+        group.bench_with_input(BenchmarkId::new("codec_decode", format!("{:?}", codec)), &payload, |b, payload: &Bytes| {
+            b.iter(|| codec.decode(black_box(payload)))
+        });
+    }
+    group.finish();
+}
+
+fn resampling(c: &mut Criterion) {
+    let mut group = c.benchmark_group("resample");
+    // The G.711 paths: down to 8 kHz to send, back up to play
+    for (from, to) in [(48_000, 8_000), (8_000, 48_000), (44_100, 48_000)] {
+        let samples = speech((from / 50) as usize);
+        group.throughput(Throughput::Elements(samples.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(format!("{}-{}", from, to)), &samples, |b, samples| {
+            b.iter(|| resample(black_box(samples), from, to))
+        });
+    }
+    group.finish();
+}
+
+fn mixer(c: &mut Criterion) {
+    let mut group = c.benchmark_group("mixer");
+    group.throughput(Throughput::Elements(MONO_SAMPLES as u64));
+
+    let samples = speech(MONO_SAMPLES);
+    let mut normalizer = LoudnessNormalizer::new(Arc::new(AtomicBool::new(true)));
+    let mut frame = samples.clone();
+    group.bench_function("normalize", |b| {
+        b.iter(|| {
+            frame.copy_from_slice(&samples);
+            normalizer.process(black_box(&mut frame));
+        })
+    });
+
+    let mut stereo = Vec::new();
+    group.bench_function("pan", |b| b.iter(|| pan_to_stereo_into(black_box(&samples), -0.4, &mut stereo)));
+
+    let sidetone = Sidetone::default();
+    sidetone.set_level(0.5);
+    let capture = speech(STEREO_SAMPLES);
+    let mut output = vec![0.0; STEREO_SAMPLES];
+    group.bench_function("sidetone", |b| {
+        b.iter(|| {
+            sidetone.push_capture(black_box(&capture), 2);
+            sidetone.mix_into(black_box(&mut output), 2);
+        })
+    });
+    group.finish();
+}
+
+fn ring_buffer(c: &mut Criterion) {
+    let mut group = c.benchmark_group("playback_buffer");
+    group.throughput(Throughput::Elements(STEREO_SAMPLES as u64));
+    let samples = speech(STEREO_SAMPLES);
+    let frame = encode_frame(&samples);
+    let samples_per_second = PLAYBACK_SAMPLE_RATE as usize * 2;
+
+    // One frame in and one device buffer's worth out, as a call runs
+    for delay_ms in [0, 60] {
+        let mut buffer = PlaybackBuffer::new(samples_per_second, Arc::new(AtomicU32::new(delay_ms)));
+        let mut output = Vec::with_capacity(STEREO_SAMPLES);
+        group.bench_function(BenchmarkId::new("push_pop", delay_ms), |b| {
+            b.iter(|| {
+                buffer.push(black_box(&samples));
+                output.clear();
+                buffer.pop_into(&mut output, STEREO_SAMPLES);
+            })
+        });
+        let mut buffer = PlaybackBuffer::new(samples_per_second, Arc::new(AtomicU32::new(delay_ms)));
+        group.bench_function(BenchmarkId::new("push_encoded_pop", delay_ms), |b| {
+            b.iter(|| {
+                buffer.push_encoded(black_box(&frame));
+                output.clear();
+                buffer.pop_into(&mut output, STEREO_SAMPLES);
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, encode, resampling, mixer, ring_buffer);
+criterion_main!(benches);
@@ -0,0 +1,16 @@
+// Only does anything for the ffi feature: regenerates the C header from src/ffi.rs
+fn main() {
+    #[cfg(feature = "ffi")]
+    write_header();
+}
+
+#[cfg(feature = "ffi")]
+fn write_header() {
+    let crate_dir = std::env::var("CARGO_MANIFEST_DIR").expect("cargo sets CARGO_MANIFEST_DIR");
+    println!("cargo:rerun-if-changed=src/ffi.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+    let config = cbindgen::Config::from_file(format!("{}/cbindgen.toml", crate_dir)).expect("cbindgen.toml is valid");
+    cbindgen::generate_with_config(&crate_dir, config)
+        .expect("src/ffi.rs should translate to C")
+        .write_to_file(format!("{}/include/webrtc_client.h", crate_dir));
+}
@@ -0,0 +1,22 @@
+# Argument checks only; nothing here needs a signaling server
+import math
+
+import pytest
+from webrtc_client import CallEngine
+
+
+@pytest.fixture
+def engine():
+    with CallEngine("ws://127.0.0.1:9", "qa", "alice", audio_backend="Mock") as engine:
+        yield engine
+
+
+@pytest.mark.parametrize("timeout", [-1.0, math.nan, math.inf, 1e300])
+def test_bad_timeouts_raise_value_error(engine, timeout):
+    with pytest.raises(ValueError):
+        engine.next_event(timeout)
+    with pytest.raises(ValueError):
+        engine.wait_for("call_active", timeout=timeout)
+    with pytest.raises(ValueError):
+        engine.wait_for_quality(lambda quality: True, timeout=timeout)
+
@@ -0,0 +1,30 @@
+# A two-party scenario against a running signaling server:
+#   SIGNALING_URL=ws://localhost:8080 pytest python/tests
+import os
+
+import pytest
+from webrtc_client import CallEngine
+
+SIGNALING_URL = os.environ.get("SIGNALING_URL")
+
+pytestmark = pytest.mark.skipif(not SIGNALING_URL, reason="SIGNALING_URL is not set")
+
+
+def test_call_connects_with_good_quality():
+    with CallEngine(SIGNALING_URL, "qa", "alice", audio_backend="Mock") as alice, \
+            CallEngine(SIGNALING_URL, "qa", "bob", auto_answer=True, audio_backend="Mock") as bob:
+        alice.connect()
+        bob.connect()
+        alice.wait_for("peer_list", lambda event: "bob" in event["peers"])
+
+        alice.call(["bob"])
+        alice.wait_for("remote_audio_started")
+        bob.wait_for("remote_audio_started")
+
+        quality = alice.wait_for_quality(lambda quality: quality["bitrate"] > 0)
+        assert quality is not None
+        assert quality["packet_loss_rate"] < 1.0
+
+        alice.hang_up()
+        bob.wait_for("call_ended")
+        assert alice.quality() is None
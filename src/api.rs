@@ -0,0 +1,217 @@
+use anyhow::{anyhow, Result};
+use hyper::header::{AUTHORIZATION, CONTENT_TYPE, HOST, ORIGIN};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+
+// Local HTTP+JSON API so kiosks and other software can drive the client
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ApiConfig {
+    pub enabled: bool,
+    // Anyone who can reach it can place calls, so anything but loopback needs the token
+    pub listen: SocketAddr,
+    // Required as "Authorization: Bearer <token>" when set
+    pub token: Option<String>,
+}
+
+impl Default for ApiConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            listen: SocketAddr::from(([127, 0, 0, 1], 7878)),
+            token: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ApiCommand {
+    Status,
+    // Connects to the signaling server, switching rooms first when one is given
+    Join { room: Option<String> },
+    Call { peers: Vec<String> },
+    HangUp,
+    SetMuted(bool),
+}
+
+// What the client is doing, returned by every endpoint
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ApiStatus {
+    pub connected: bool,
+    pub room: String,
+    pub peer_id: String,
+    // Others in the room
+    pub peers: Vec<String>,
+    pub in_call: bool,
+    pub participants: Vec<String>,
+    pub muted: bool,
+}
+
+// Handled by whoever owns the call, which answers with the status after acting on it.
+// Actions only start there; callers poll /status to see them finish.
+pub struct ApiRequest {
+    pub command: ApiCommand,
+    pub reply: oneshot::Sender<Result<ApiStatus, String>>,
+}
+
+#[derive(Deserialize)]
+struct JoinBody {
+    room: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct CallBody {
+    peers: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct MuteBody {
+    muted: bool,
+}
+
+// GET  /status
+// POST /join    {"room": "lobby"}   room optional
+// POST /call    {"peers": ["bob"]}
+// POST /hangup
+// POST /mute    {"muted": true}
+pub struct ApiServer {
+    local_addr: SocketAddr,
+    task: JoinHandle<()>,
+}
+
+impl ApiServer {
+    pub fn bind(config: &ApiConfig) -> Result<(Self, mpsc::Receiver<ApiRequest>)> {
+        let token: Option<Arc<str>> = config.token.as_deref().filter(|token| !token.is_empty()).map(Arc::from);
+        if token.is_none() && !config.listen.ip().is_loopback() {
+            return Err(anyhow!(
+                "Refusing to start the control API on {} without a token; set one or listen on loopback",
+                config.listen
+            ));
+        }
+        let (requests, requests_rx) = mpsc::channel(16);
+        let make_service = make_service_fn(move |_| {
+            let requests = requests.clone();
+            let token = token.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request| {
+                    handle(request, requests.clone(), token.clone())
+                }))
+            }
+        });
+        let server = Server::try_bind(&config.listen)
+            .map_err(|e| anyhow!("Could not start the control API on {}: {}", config.listen, e))?
+            .serve(make_service);
+        let local_addr = server.local_addr();
+        let task = tokio::spawn(async move {
+            if let Err(e) = server.await {
+                eprintln!("Control API stopped: {}", e);
+            }
+        });
+        Ok((Self { local_addr, task }, requests_rx))
+    }
+
+    // The bound address, for a configured port of 0
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+}
+
+impl Drop for ApiServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn handle(
+    request: Request<Body>,
+    requests: mpsc::Sender<ApiRequest>,
+    token: Option<Arc<str>>,
+) -> Result<Response<Body>, Infallible> {
+    // Browsers send an Origin with cross-site requests; kiosks and scripts don't, and a
+    // web page has no business here
+    if request.headers().contains_key(ORIGIN) {
+        return Ok(error(StatusCode::FORBIDDEN, "Requests from web pages are not accepted"));
+    }
+    // Without a token only loopback is served, so a loopback Host is the only honest one.
+    // Anything else is a page that rebound its own name to 127.0.0.1.
+    if token.is_none() && !request.headers().get(HOST).map_or(false, is_loopback_host) {
+        return Ok(error(StatusCode::FORBIDDEN, "Host is not a loopback address"));
+    }
+    if let Some(token) = token {
+        let authorized = request
+            .headers()
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .map_or(false, |given| given == &*token);
+        if !authorized {
+            return Ok(error(StatusCode::UNAUTHORIZED, "Missing or wrong bearer token"));
+        }
+    }
+
+    let (method, path) = (request.method().clone(), request.uri().path().to_string());
+    let content_type = request.headers().get(CONTENT_TYPE).cloned();
+    let body = match hyper::body::to_bytes(request.into_body()).await {
+        Ok(body) => body,
+        Err(e) => return Ok(error(StatusCode::BAD_REQUEST, &e.to_string())),
+    };
+    // Forms and text/plain are what a page can post without a preflight
+    let is_json = content_type.as_ref().map(|value| value.to_str().unwrap_or_default()).map(|value| {
+        value.split(';').next().unwrap_or_default().trim().eq_ignore_ascii_case("application/json")
+    });
+    if is_json == Some(false) || (is_json.is_none() && !body.is_empty()) {
+        return Ok(error(StatusCode::UNSUPPORTED_MEDIA_TYPE, "Request bodies must be application/json"));
+    }
+    let command = match (method, path.as_str()) {
+        (Method::GET, "/status") => Ok(ApiCommand::Status),
+        (Method::POST, "/join") if body.is_empty() => Ok(ApiCommand::Join { room: None }),
+        (Method::POST, "/join") => serde_json::from_slice::<JoinBody>(&body).map(|body| ApiCommand::Join { room: body.room }),
+        (Method::POST, "/call") => serde_json::from_slice::<CallBody>(&body).map(|body| ApiCommand::Call { peers: body.peers }),
+        (Method::POST, "/hangup") => Ok(ApiCommand::HangUp),
+        (Method::POST, "/mute") => serde_json::from_slice::<MuteBody>(&body).map(|body| ApiCommand::SetMuted(body.muted)),
+        _ => return Ok(error(StatusCode::NOT_FOUND, "No such endpoint")),
+    };
+    let command = match command {
+        Ok(command) => command,
+        Err(e) => return Ok(error(StatusCode::BAD_REQUEST, &format!("Invalid request body: {}", e))),
+    };
+
+    let accepted = if command == ApiCommand::Status { StatusCode::OK } else { StatusCode::ACCEPTED };
+    let (reply, reply_rx) = oneshot::channel();
+    if requests.send(ApiRequest { command, reply }).await.is_err() {
+        return Ok(error(StatusCode::SERVICE_UNAVAILABLE, "The client is shutting down"));
+    }
+    Ok(match reply_rx.await {
+        Ok(Ok(status)) => json(accepted, &status),
+        Ok(Err(e)) => error(StatusCode::CONFLICT, &e),
+        Err(_) => error(StatusCode::SERVICE_UNAVAILABLE, "The client is shutting down"),
+    })
+}
+
+fn is_loopback_host(host: &hyper::header::HeaderValue) -> bool {
+    let Ok(host) = host.to_str() else { return false };
+    // Without the port; IPv6 addresses come in brackets
+    let name = match host.strip_prefix('[') {
+        Some(rest) => rest.split(']').next().unwrap_or_default(),
+        None => host.rsplit_once(':').map_or(host, |(name, _)| name),
+    };
+    name.eq_ignore_ascii_case("localhost") || name.parse::<std::net::IpAddr>().map_or(false, |ip| ip.is_loopback())
+}
+
+fn json(status: StatusCode, body: &impl Serialize) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header(CONTENT_TYPE, "application/json")
+        .body(Body::from(serde_json::to_vec(body).unwrap_or_default()))
+        .unwrap()
+}
+
+fn error(status: StatusCode, message: &str) -> Response<Body> {
+    json(status, &serde_json::json!({ "error": message }))
+}
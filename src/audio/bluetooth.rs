@@ -0,0 +1,130 @@
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use super::StreamDirection;
+
+// Name fragments the platforms give Bluetooth audio devices: PipeWire/PulseAudio (bluez),
+// Windows ("Hands-Free AG Audio") and the odd vendor
+const BLUETOOTH_MARKERS: [&str; 4] = ["bluez", "bluetooth", "airpods", "a2dp"];
+const HANDS_FREE_MARKERS: [&str; 5] = ["hands-free", "handsfree", "head_unit", "hfp", "hsp"];
+// No ordinary sound card runs this slow; a headset in hands-free mode does
+const HANDS_FREE_MAX_RATE: u32 = 16_000;
+
+// What a Bluetooth headset is running as. A headset can't do both at once: as soon as
+// its mic is in use it drops to hands-free.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BluetoothProfile {
+    // HFP/HSP: mic and speaker, but mono and narrowband (8 or 16 kHz)
+    HandsFree,
+    // A2DP: full quality, playback only
+    HighFidelity,
+}
+
+impl fmt::Display for BluetoothProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BluetoothProfile::HandsFree => write!(f, "HFP"),
+            BluetoothProfile::HighFidelity => write!(f, "A2DP"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BluetoothDevice {
+    pub name: String,
+    pub profile: BluetoothProfile,
+    pub sample_rate: u32,
+}
+
+// From what the audio API tells us about an open device; None when it doesn't look
+// like Bluetooth. macOS names headsets by product only, but the sample rate still
+// gives hands-free away.
+pub fn detect(name: &str, sample_rate: u32) -> Option<BluetoothDevice> {
+    let lower = name.to_lowercase();
+    let hands_free = HANDS_FREE_MARKERS.iter().any(|marker| lower.contains(marker))
+        || (sample_rate > 0 && sample_rate <= HANDS_FREE_MAX_RATE);
+    let profile = if hands_free {
+        BluetoothProfile::HandsFree
+    } else if BLUETOOTH_MARKERS.iter().any(|marker| lower.contains(marker)) {
+        BluetoothProfile::HighFidelity
+    } else {
+        return None;
+    };
+    Some(BluetoothDevice {
+        name: name.to_string(),
+        profile,
+        sample_rate,
+    })
+}
+
+// The Bluetooth devices a backend's call streams run on, updated as they open or move
+#[derive(Clone, Default)]
+pub struct BluetoothTracker {
+    devices: Arc<Mutex<[Option<BluetoothDevice>; 2]>>,
+}
+
+impl BluetoothTracker {
+    pub fn opened(&self, direction: StreamDirection, name: &str, sample_rate: u32) {
+        let slot = match direction {
+            StreamDirection::Capture => 0,
+            StreamDirection::Playback => 1,
+        };
+        if let Ok(mut devices) = self.devices.lock() {
+            devices[slot] = detect(name, sample_rate);
+        }
+    }
+
+    // Hands-free first, since that's the one worth warning about
+    pub fn current(&self) -> Option<BluetoothDevice> {
+        let devices = self.devices.lock().ok()?;
+        let mut found: Vec<&BluetoothDevice> = devices.iter().flatten().collect();
+        found.sort_by_key(|device| device.profile != BluetoothProfile::HandsFree);
+        found.first().map(|device| (*device).clone())
+    }
+}
+
+// Only PipeWire and PulseAudio let an application pick the profile; elsewhere it's up
+// to the OS and which of the headset's streams are open
+pub fn can_switch_profile() -> bool {
+    cfg!(target_os = "linux")
+}
+
+// Every connected Bluetooth card, so it works without knowing the card behind a device
+#[cfg(target_os = "linux")]
+pub fn switch_profile(profile: BluetoothProfile) -> Result<()> {
+    use std::process::Command;
+
+    // The PipeWire/PulseAudio card profile
+    let card_profile = match profile {
+        BluetoothProfile::HandsFree => "headset-head-unit",
+        BluetoothProfile::HighFidelity => "a2dp-sink",
+    };
+    let cards = Command::new("pactl").args(["list", "cards", "short"]).output()?;
+    if !cards.status.success() {
+        return Err(anyhow!("pactl could not list sound cards"));
+    }
+    let cards: Vec<String> = String::from_utf8_lossy(&cards.stdout)
+        .lines()
+        .filter_map(|line| line.split_whitespace().nth(1))
+        .filter(|card| card.starts_with("bluez_card."))
+        .map(str::to_string)
+        .collect();
+    if cards.is_empty() {
+        return Err(anyhow!("No Bluetooth audio device found"));
+    }
+    for card in cards {
+        let status = Command::new("pactl").args(["set-card-profile", &card, card_profile]).status()?;
+        if !status.success() {
+            return Err(anyhow!("Could not switch {} to {}", card, profile));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn switch_profile(profile: BluetoothProfile) -> Result<()> {
+    Err(anyhow!("Switching to {} isn't supported on this system", profile))
+}
@@ -0,0 +1,238 @@
+#[cfg(not(target_arch = "wasm32"))]
+use anyhow::Result;
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+#[cfg(not(target_arch = "wasm32"))]
+use webrtc::api::media_engine::MediaEngine;
+#[cfg(not(target_arch = "wasm32"))]
+use webrtc::rtp_transceiver::rtp_codec::{RTCRtpCodecCapability, RTCRtpCodecParameters, RTPCodecType};
+
+use super::g722::{G722Decoder, G722Encoder};
+use super::{decode_frame, encode_frame};
+
+// The same strings as webrtc-rs' MIME_TYPE_* constants, spelled out so the browser
+// build, which has no webrtc-rs, can name codecs too
+const MIME_TYPE_OPUS: &str = "audio/opus";
+const MIME_TYPE_PCMU: &str = "audio/PCMU";
+const MIME_TYPE_PCMA: &str = "audio/PCMA";
+const MIME_TYPE_G722: &str = "audio/G722";
+
+const G711_SAMPLE_RATE: u32 = 8_000;
+const G722_SAMPLE_RATE: u32 = 16_000;
+// Rate G.711 audio is upsampled to for playback; frames carry no rate of their own
+pub const PLAYBACK_SAMPLE_RATE: u32 = 48_000;
+
+// Audio codecs we can both send and receive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum AudioCodec {
+    Opus,
+    G722,
+    Pcmu,
+    Pcma,
+}
+
+impl AudioCodec {
+    // Offer order when no preference is configured: wideband G.722 ahead of G.711
+    pub const ALL: [AudioCodec; 4] = [AudioCodec::Opus, AudioCodec::G722, AudioCodec::Pcmu, AudioCodec::Pcma];
+
+    pub fn from_mime(mime_type: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|codec| codec.mime_type().eq_ignore_ascii_case(mime_type))
+    }
+
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            AudioCodec::Opus => MIME_TYPE_OPUS,
+            AudioCodec::Pcmu => MIME_TYPE_PCMU,
+            AudioCodec::Pcma => MIME_TYPE_PCMA,
+            AudioCodec::G722 => MIME_TYPE_G722,
+        }
+    }
+
+    // The RTP clock, which is what SDP and timestamps use. G.722 samples at 16 kHz but
+    // RFC 3551 fixes its clock at 8000 because an early spec got it wrong; every
+    // implementation has kept the mistake, so timestamps advance half as fast as samples.
+    pub fn clock_rate(self) -> u32 {
+        match self {
+            AudioCodec::Opus => 48_000,
+            AudioCodec::Pcmu | AudioCodec::Pcma | AudioCodec::G722 => G711_SAMPLE_RATE,
+        }
+    }
+
+    // The rate the codec actually samples audio at
+    pub fn sample_rate(self) -> u32 {
+        match self {
+            AudioCodec::G722 => G722_SAMPLE_RATE,
+            codec => codec.clock_rate(),
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn parameters(self) -> RTCRtpCodecParameters {
+        let (payload_type, channels, sdp_fmtp_line) = match self {
+            AudioCodec::Opus => (111, 2, "minptime=10;useinbandfec=1"),
+            AudioCodec::Pcmu => (0, 0, ""),
+            AudioCodec::Pcma => (8, 0, ""),
+            AudioCodec::G722 => (9, 0, ""),
+        };
+        RTCRtpCodecParameters {
+            capability: RTCRtpCodecCapability {
+                mime_type: self.mime_type().to_owned(),
+                clock_rate: self.clock_rate(),
+                channels,
+                sdp_fmtp_line: sdp_fmtp_line.to_owned(),
+                rtcp_feedback: vec![],
+            },
+            payload_type,
+            ..Default::default()
+        }
+    }
+
+    // A single frame with fresh codec state. Streams go through a StreamEncoder, since
+    // G.722 codes each frame against the ones before it.
+    pub fn encode(self, frame: &Bytes, sample_rate: u32) -> Bytes {
+        StreamEncoder::default().encode(self, frame, sample_rate)
+    }
+
+    pub fn decode(self, payload: &Bytes) -> Bytes {
+        StreamDecoder::default().decode(self, payload)
+    }
+}
+
+// Codec state of one outgoing stream; only G.722 has any
+#[derive(Debug, Clone, Default)]
+pub struct StreamEncoder {
+    g722: G722Encoder,
+}
+
+impl StreamEncoder {
+    // Opus frames travel in the internal frame format unchanged
+    pub fn encode(&mut self, codec: AudioCodec, frame: &Bytes, sample_rate: u32) -> Bytes {
+        if codec == AudioCodec::Opus {
+            return frame.clone();
+        }
+        let samples = resample(&decode_frame(frame), sample_rate, codec.sample_rate());
+        let samples = samples.iter().map(|&sample| to_i16(sample));
+        match codec {
+            AudioCodec::Pcmu => samples.map(linear_to_ulaw).collect::<Vec<u8>>().into(),
+            AudioCodec::Pcma => samples.map(linear_to_alaw).collect::<Vec<u8>>().into(),
+            _ => self.g722.encode(&samples.collect::<Vec<i16>>()).into(),
+        }
+    }
+}
+
+// Codec state of one incoming stream
+#[derive(Debug, Clone, Default)]
+pub struct StreamDecoder {
+    g722: G722Decoder,
+}
+
+impl StreamDecoder {
+    // Back to the internal frame format that playback backends consume
+    pub fn decode(&mut self, codec: AudioCodec, payload: &Bytes) -> Bytes {
+        let samples: Vec<i16> = match codec {
+            AudioCodec::Opus => return payload.clone(),
+            AudioCodec::Pcmu => payload.iter().map(|&byte| ulaw_to_linear(byte)).collect(),
+            AudioCodec::Pcma => payload.iter().map(|&byte| alaw_to_linear(byte)).collect(),
+            AudioCodec::G722 => self.g722.decode(payload),
+        };
+        let samples: Vec<f32> = samples.iter().map(|&sample| sample as f32 / 32768.0).collect();
+        encode_frame(&resample(&samples, codec.sample_rate(), PLAYBACK_SAMPLE_RATE))
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn register_audio_codecs(media_engine: &mut MediaEngine) -> Result<()> {
+    for codec in AudioCodec::ALL {
+        media_engine.register_codec(codec.parameters(), RTPCodecType::Audio)?;
+    }
+    Ok(())
+}
+
+fn to_i16(sample: f32) -> i16 {
+    (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16
+}
+
+// Linear interpolation; good enough for speech going to and from 8 and 16 kHz
+pub fn resample(samples: &[f32], from_rate: u32, to_rate: u32) -> Vec<f32> {
+    if from_rate == to_rate || samples.is_empty() || from_rate == 0 {
+        return samples.to_vec();
+    }
+    let out_len = (samples.len() as u64 * to_rate as u64 / from_rate as u64) as usize;
+    let step = from_rate as f64 / to_rate as f64;
+    (0..out_len)
+        .map(|i| {
+            let pos = i as f64 * step;
+            let index = pos as usize;
+            let frac = (pos - index as f64) as f32;
+            let current = samples[index.min(samples.len() - 1)];
+            let next = samples[(index + 1).min(samples.len() - 1)];
+            current + (next - current) * frac
+        })
+        .collect()
+}
+
+// ITU-T G.711 companding, as in the reference implementation
+const ULAW_BIAS: i32 = 0x84;
+const ULAW_CLIP: i32 = 32635;
+
+pub fn linear_to_ulaw(sample: i16) -> u8 {
+    let mut pcm = sample as i32;
+    let sign = if pcm < 0 {
+        pcm = -pcm;
+        0x80
+    } else {
+        0
+    };
+    pcm = pcm.min(ULAW_CLIP) + ULAW_BIAS;
+    let exponent = (7 - (pcm as u16).leading_zeros().saturating_sub(1).min(7)) as i32;
+    let mantissa = (pcm >> (exponent + 3)) & 0x0F;
+    !(sign | (exponent << 4) | mantissa) as u8
+}
+
+pub fn ulaw_to_linear(byte: u8) -> i16 {
+    let byte = !byte as i32;
+    let exponent = (byte >> 4) & 0x07;
+    let mantissa = byte & 0x0F;
+    let magnitude = (((mantissa << 3) + ULAW_BIAS) << exponent) - ULAW_BIAS;
+    if byte & 0x80 != 0 {
+        -magnitude as i16
+    } else {
+        magnitude as i16
+    }
+}
+
+pub fn linear_to_alaw(sample: i16) -> u8 {
+    let mut pcm = sample as i32;
+    let sign = if pcm >= 0 {
+        0x80
+    } else {
+        pcm = -pcm - 1;
+        0
+    };
+    pcm = pcm.min(32767);
+    let encoded = if pcm < 256 {
+        pcm >> 4
+    } else {
+        let exponent = 15 - (pcm as u16).leading_zeros() as i32 - 7;
+        (exponent << 4) | ((pcm >> (exponent + 3)) & 0x0F)
+    };
+    ((sign | encoded) ^ 0x55) as u8
+}
+
+pub fn alaw_to_linear(byte: u8) -> i16 {
+    let byte = (byte ^ 0x55) as i32;
+    let exponent = (byte >> 4) & 0x07;
+    let mantissa = byte & 0x0F;
+    let magnitude = if exponent == 0 {
+        (mantissa << 4) + 8
+    } else {
+        ((mantissa << 4) + 0x108) << (exponent - 1)
+    };
+    if byte & 0x80 != 0 {
+        magnitude as i16
+    } else {
+        -magnitude as i16
+    }
+}
@@ -0,0 +1,510 @@
+use anyhow::Result;
+use bytes::Bytes;
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{BufferSize, HostId, Sample, SizedSample};
+#[cfg(target_os = "ios")]
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::mpsc as std_mpsc;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc};
+use webrtc::media::Sample as MediaSample;
+use cpal::SampleFormat;
+
+use super::bluetooth::{BluetoothDevice, BluetoothTracker};
+#[cfg(target_os = "ios")]
+use super::ios_session::AudioSession;
+use super::{
+    fallback_device, routed_device, AudioBackend, AudioLatency, AudioStreamHandle, AudioTrack, DeviceEvent, FramePool,
+    LatencyTracker, OutputRouting, PlaybackBuffer, Sidetone, SoundKind, StreamDirection, MAX_PLAYBACK_DELAY_MS,
+};
+
+pub struct CpalBackend {
+    // None is the platform default host (WASAPI shared mode, CoreAudio, ALSA)
+    host: Option<HostId>,
+    // Device buffer size in frames; None leaves it to the driver
+    buffer_frames: Option<u32>,
+    latency: LatencyTracker,
+    sidetone: Sidetone,
+    device_events: broadcast::Sender<DeviceEvent>,
+    routing: Arc<Mutex<OutputRouting>>,
+    // Milliseconds of extra playback buffering
+    playback_delay: Arc<AtomicU32>,
+    bluetooth: BluetoothTracker,
+    // Mode of the AVAudioSession held while streams run
+    #[cfg(target_os = "ios")]
+    voice_processing: Arc<AtomicBool>,
+}
+
+impl CpalBackend {
+    pub fn new() -> Self {
+        Self::with_options(None, None)
+    }
+
+    pub fn with_options(host: Option<HostId>, buffer_frames: Option<u32>) -> Self {
+        Self {
+            host,
+            buffer_frames,
+            latency: LatencyTracker::default(),
+            sidetone: Sidetone::default(),
+            device_events: broadcast::channel(16).0,
+            routing: Arc::new(Mutex::new(OutputRouting::default())),
+            playback_delay: Arc::new(AtomicU32::new(0)),
+            bluetooth: BluetoothTracker::default(),
+            #[cfg(target_os = "ios")]
+            voice_processing: Arc::new(AtomicBool::new(true)),
+        }
+    }
+
+    // ASIO drivers bypass the Windows mixer entirely; needs the asio feature and an
+    // installed ASIO driver
+    #[cfg(all(windows, feature = "asio"))]
+    pub fn asio(buffer_frames: Option<u32>) -> Self {
+        Self::with_options(Some(HostId::Asio), buffer_frames)
+    }
+}
+
+impl AudioBackend for CpalBackend {
+    fn name(&self) -> &'static str {
+        if self.host.is_some() {
+            "cpal-asio"
+        } else {
+            "cpal"
+        }
+    }
+
+    fn latency(&self) -> AudioLatency {
+        self.latency.get()
+    }
+
+    fn sidetone(&self) -> Option<Sidetone> {
+        Some(self.sidetone.clone())
+    }
+
+    fn bluetooth(&self) -> Option<BluetoothDevice> {
+        self.bluetooth.current()
+    }
+
+    #[cfg(target_os = "ios")]
+    fn set_voice_processing(&self, enabled: bool) {
+        self.voice_processing.store(enabled, Ordering::Relaxed);
+    }
+
+    fn device_events(&self) -> Option<broadcast::Receiver<DeviceEvent>> {
+        Some(self.device_events.subscribe())
+    }
+
+    fn set_output_routing(&self, routing: OutputRouting) {
+        if let Ok(mut current) = self.routing.lock() {
+            *current = routing;
+        }
+    }
+
+    fn set_playback_delay(&self, delay: Duration) {
+        let delay_ms = (delay.as_millis() as u32).min(MAX_PLAYBACK_DELAY_MS);
+        self.playback_delay.store(delay_ms, Ordering::Relaxed);
+    }
+
+    fn output_devices(&self) -> Vec<String> {
+        let Ok(host) = open_host(self.host) else { return Vec::new() };
+        match host.output_devices() {
+            Ok(devices) => devices.filter_map(|device| device.name().ok()).collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    fn start_capture(&self, track: Arc<AudioTrack>) -> Result<AudioStreamHandle> {
+        // iOS won't open the mic until the session allows recording
+        #[cfg(target_os = "ios")]
+        let session = AudioSession::activate(self.voice_processing.load(Ordering::Relaxed))?;
+        let (frame_tx, mut frame_rx) = mpsc::channel::<MediaSample>(64);
+
+        // The device callback must not block, so frames are handed to a task for writing
+        let writer = tokio::spawn(async move {
+            while let Some(sample) = frame_rx.recv().await {
+                if let Err(e) = track.write_sample(&sample).await {
+                    eprintln!("Failed to write audio sample: {}", e);
+                }
+            }
+        });
+
+        let (host, buffer_frames, latency, sidetone) = (self.host, self.buffer_frames, self.latency.clone(), self.sidetone.clone());
+        let direction = StreamDirection::Capture;
+        let bluetooth = self.bluetooth.clone();
+        let handle = run_on_audio_thread("audio-capture", direction, self.device_events.clone(), move |failed, errors| {
+            let host = open_host(host)?;
+            let device = pick_device(&host, direction, None, failed)?;
+            let name = device.name()?;
+            let sample_rate = device.default_input_config().map_or(0, |config| config.sample_rate().0);
+            bluetooth.opened(direction, &name, sample_rate);
+            let capture = AudioCapture::new(&device, buffer_frames, frame_tx.clone(), latency.clone(), sidetone.clone(), errors)?;
+            Ok((capture, name))
+        })?;
+        Ok(AudioStreamHandle::new(move || {
+            drop(handle);
+            writer.abort();
+            #[cfg(target_os = "ios")]
+            drop(session);
+        }))
+    }
+
+    fn start_playback(&self, packets: broadcast::Receiver<Bytes>) -> Result<AudioStreamHandle> {
+        self.start_playback_for(SoundKind::Call, packets)
+    }
+
+    // Each kind gets its own stream, so a ringtone and a call can play on different devices
+    fn start_playback_for(&self, kind: SoundKind, mut packets: broadcast::Receiver<Bytes>) -> Result<AudioStreamHandle> {
+        #[cfg(target_os = "ios")]
+        let session = AudioSession::activate(self.voice_processing.load(Ordering::Relaxed))?;
+        let (sample_tx, sample_rx) = std_mpsc::sync_channel(64);
+        // Shared so a stream rebuilt on another device picks up the same queue
+        let sample_rx = Arc::new(Mutex::new(sample_rx));
+
+        // Hand packets from the remote track over as they arrive; the playback callback
+        // decodes them straight into its queue
+        let decoder = tokio::spawn(async move {
+            loop {
+                match packets.recv().await {
+                    // If playback falls behind the frame is dropped rather than queued as latency
+                    Ok(payload) => {
+                        let _ = sample_tx.try_send(payload);
+                    }
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+
+        let (host, buffer_frames, latency, sidetone) = (self.host, self.buffer_frames, self.latency.clone(), self.sidetone.clone());
+        // Alerts are short and start on time; only call audio gets the cushion
+        let playback_delay = match kind {
+            SoundKind::Call => self.playback_delay.clone(),
+            SoundKind::Alert => Arc::new(AtomicU32::new(0)),
+        };
+        let direction = StreamDirection::Playback;
+        let routed = self.routing.lock().ok().and_then(|routing| routing.device_for(kind).map(str::to_string));
+        let bluetooth = (kind == SoundKind::Call).then(|| self.bluetooth.clone());
+        let handle = run_on_audio_thread("audio-playback", direction, self.device_events.clone(), move |failed, errors| {
+            let host = open_host(host)?;
+            let device = pick_device(&host, direction, routed.as_deref(), failed)?;
+            let name = device.name()?;
+            if let Some(ref bluetooth) = bluetooth {
+                let sample_rate = device.default_output_config().map_or(0, |config| config.sample_rate().0);
+                bluetooth.opened(direction, &name, sample_rate);
+            }
+            let playback = AudioPlayback::new(
+                &device,
+                buffer_frames,
+                sample_rx.clone(),
+                latency.clone(),
+                sidetone.clone(),
+                playback_delay.clone(),
+                errors,
+            )?;
+            Ok((playback, name))
+        })?;
+        Ok(AudioStreamHandle::new(move || {
+            drop(handle);
+            decoder.abort();
+            #[cfg(target_os = "ios")]
+            drop(session);
+        }))
+    }
+}
+
+fn open_host(host: Option<HostId>) -> Result<cpal::Host> {
+    match host {
+        Some(id) => Ok(cpal::host_from_id(id)?),
+        None => Ok(cpal::default_host()),
+    }
+}
+
+fn stream_config(config: &cpal::SupportedStreamConfig, buffer_frames: Option<u32>) -> cpal::StreamConfig {
+    let mut stream_config: cpal::StreamConfig = config.clone().into();
+    if let Some(frames) = buffer_frames {
+        stream_config.buffer_size = BufferSize::Fixed(frames);
+    }
+    stream_config
+}
+
+fn pick_device(host: &cpal::Host, direction: StreamDirection, routed: Option<&str>, failed: &[String]) -> Result<cpal::Device> {
+    let (default, devices) = match direction {
+        StreamDirection::Capture => (host.default_input_device(), host.input_devices()?.collect::<Vec<_>>()),
+        StreamDirection::Playback => (host.default_output_device(), host.output_devices()?.collect::<Vec<_>>()),
+    };
+    let default_name = default.as_ref().and_then(|device| device.name().ok());
+    let names: Vec<String> = devices.iter().map(|device| device.name().unwrap_or_default()).collect();
+    let available: Vec<&str> = names.iter().map(String::as_str).collect();
+
+    let preferred = routed_device(routed, default_name.as_deref(), &available);
+    let device = match fallback_device(preferred, &available, failed) {
+        Some(name) if Some(name) == default_name.as_deref() => default,
+        Some(name) => names.iter().position(|n| n == name).map(|index| devices[index].clone()),
+        None => None,
+    };
+    device.ok_or_else(|| match direction {
+        StreamDirection::Capture => anyhow::anyhow!("No input device available"),
+        StreamDirection::Playback => anyhow::anyhow!("No output device available"),
+    })
+}
+
+enum AudioThreadMessage {
+    Stop,
+    // Tagged with the stream it came from; a dying stream often reports more than once
+    DeviceError { generation: u64, error: String },
+}
+
+// Handed to each stream's error callback
+#[derive(Clone)]
+pub struct StreamErrors {
+    tx: std_mpsc::Sender<AudioThreadMessage>,
+    generation: u64,
+    label: &'static str,
+}
+
+impl StreamErrors {
+    fn report(&self, error: cpal::StreamError) {
+        eprintln!("An error occurred on the {} audio stream: {}", self.label, error);
+        let _ = self.tx.send(AudioThreadMessage::DeviceError {
+            generation: self.generation,
+            error: error.to_string(),
+        });
+    }
+}
+
+// cpal streams are !Send, so each one lives on its own thread until the handle is dropped.
+// When the device errors the thread rebuilds the stream on the next device that hasn't
+// failed yet and reports the move on `events`.
+fn run_on_audio_thread<F, S>(
+    name: &str,
+    direction: StreamDirection,
+    events: broadcast::Sender<DeviceEvent>,
+    mut build: F,
+) -> Result<AudioStreamHandle>
+where
+    F: FnMut(&[String], StreamErrors) -> Result<(S, String)> + Send + 'static,
+    S: 'static,
+{
+    let (ready_tx, ready_rx) = std_mpsc::sync_channel(1);
+    let (message_tx, message_rx) = std_mpsc::channel::<AudioThreadMessage>();
+    let stop_tx = message_tx.clone();
+    let label = match direction {
+        StreamDirection::Capture => "input",
+        StreamDirection::Playback => "output",
+    };
+
+    std::thread::Builder::new()
+        .name(name.to_string())
+        .spawn(move || {
+            let mut generation = 0;
+            let errors = |generation| StreamErrors { tx: message_tx.clone(), generation, label };
+            let mut failed = Vec::new();
+            let (stream, mut device) = match build(&failed, errors(generation)) {
+                Ok(built) => {
+                    let _ = ready_tx.send(Ok(()));
+                    built
+                }
+                Err(e) => {
+                    let _ = ready_tx.send(Err(e));
+                    return;
+                }
+            };
+            let mut stream = Some(stream);
+
+            // Returns on an explicit stop or when the handle is dropped
+            while let Ok(AudioThreadMessage::DeviceError { generation: from, error }) = message_rx.recv() {
+                if from != generation {
+                    continue;
+                }
+                // Released first; an exclusive-mode device can't be reopened while held
+                stream = None;
+                failed.push(device.clone());
+                generation += 1;
+                match build(&failed, errors(generation)) {
+                    Ok((rebuilt, to)) => {
+                        println!("Audio {} moved from {} to {} after: {}", label, device, to, error);
+                        let _ = events.send(DeviceEvent::Switched { direction, from: device, to: to.clone() });
+                        stream = Some(rebuilt);
+                        device = to;
+                    }
+                    Err(e) => {
+                        eprintln!("No {} device to fall back to: {}", label, e);
+                        let _ = events.send(DeviceEvent::Lost { direction, device });
+                        break;
+                    }
+                }
+            }
+            drop(stream);
+        })?;
+
+    ready_rx
+        .recv()
+        .map_err(|_| anyhow::anyhow!("Audio thread {} exited during setup", name))??;
+
+    Ok(AudioStreamHandle::new(move || {
+        let _ = stop_tx.send(AudioThreadMessage::Stop);
+    }))
+}
+
+pub struct AudioCapture {
+    input_stream: cpal::Stream,
+}
+
+impl AudioCapture {
+    pub fn new(
+        input_device: &cpal::Device,
+        buffer_frames: Option<u32>,
+        frame_tx: mpsc::Sender<MediaSample>,
+        latency: LatencyTracker,
+        sidetone: Sidetone,
+        errors: StreamErrors,
+    ) -> Result<Self> {
+        let config = input_device.default_input_config()?;
+        println!("Input config: {:?}, buffer: {:?} frames", config, buffer_frames);
+        let stream_config = stream_config(&config, buffer_frames);
+
+        let input_stream = match config.sample_format() {
+            SampleFormat::F32 => Self::build_input_stream::<f32>(input_device, &stream_config, frame_tx, latency, sidetone, errors)?,
+            SampleFormat::I16 => Self::build_input_stream::<i16>(input_device, &stream_config, frame_tx, latency, sidetone, errors)?,
+            SampleFormat::U16 => Self::build_input_stream::<u16>(input_device, &stream_config, frame_tx, latency, sidetone, errors)?,
+            sample_format => return Err(anyhow::anyhow!("Unsupported sample format: {:?}", sample_format)),
+        };
+
+        input_stream.play()?;
+
+        Ok(Self {
+            input_stream,
+        })
+    }
+
+    fn build_input_stream<T>(
+        device: &cpal::Device,
+        config: &cpal::StreamConfig,
+        frame_tx: mpsc::Sender<MediaSample>,
+        latency: LatencyTracker,
+        sidetone: Sidetone,
+        playback_delay: Arc<AtomicU32>,
+        errors: StreamErrors,
+    ) -> Result<cpal::Stream>
+    where
+        T: SizedSample + Sample + Send + 'static,
+        f32: cpal::FromSample<T>,
+    {
+        let err_fn = move |err| errors.report(err);
+        let samples_per_second = config.sample_rate.0 as f64 * config.channels as f64;
+        let channels = config.channels as usize;
+        // Both grow to the device's buffer size on the first callbacks and are reused after
+        let mut samples = Vec::<f32>::new();
+        let mut pool = FramePool::default();
+
+        let stream = device.build_input_stream(
+            config,
+            move |data: &[T], info: &cpal::InputCallbackInfo| {
+                // How long the oldest sample in this buffer sat in the device
+                let timestamp = info.timestamp();
+                if let Some(delay) = timestamp.callback.duration_since(&timestamp.capture) {
+                    latency.set_capture(delay);
+                }
+
+                samples.clear();
+                samples.extend(data.iter().map(|sample| sample.to_sample::<f32>()));
+                sidetone.push_capture(&samples, channels);
+
+                let sample = MediaSample {
+                    data: pool.encode(&samples),
+                    duration: Duration::from_secs_f64(samples.len() as f64 / samples_per_second),
+                    ..Default::default()
+                };
+                let _ = frame_tx.try_send(sample);
+            },
+            err_fn,
+            None,
+        )?;
+
+        Ok(stream)
+    }
+}
+
+pub struct AudioPlayback {
+    output_stream: cpal::Stream,
+}
+
+impl AudioPlayback {
+    pub fn new(
+        output_device: &cpal::Device,
+        buffer_frames: Option<u32>,
+        sample_rx: Arc<Mutex<std_mpsc::Receiver<Bytes>>>,
+        latency: LatencyTracker,
+        sidetone: Sidetone,
+        playback_delay: Arc<AtomicU32>,
+        errors: StreamErrors,
+    ) -> Result<Self> {
+        let config = output_device.default_output_config()?;
+        println!("Output config: {:?}, buffer: {:?} frames", config, buffer_frames);
+        let stream_config = stream_config(&config, buffer_frames);
+
+        let output_stream = match config.sample_format() {
+            SampleFormat::F32 => Self::build_output_stream::<f32>(output_device, &stream_config, sample_rx, latency, sidetone, playback_delay, errors)?,
+            SampleFormat::I16 => Self::build_output_stream::<i16>(output_device, &stream_config, sample_rx, latency, sidetone, playback_delay, errors)?,
+            SampleFormat::U16 => Self::build_output_stream::<u16>(output_device, &stream_config, sample_rx, latency, sidetone, playback_delay, errors)?,
+            sample_format => return Err(anyhow::anyhow!("Unsupported sample format: {:?}", sample_format)),
+        };
+
+        output_stream.play()?;
+
+        Ok(Self {
+            output_stream,
+        })
+    }
+
+    fn build_output_stream<T>(
+        device: &cpal::Device,
+        config: &cpal::StreamConfig,
+        sample_rx: Arc<Mutex<std_mpsc::Receiver<Bytes>>>,
+        latency: LatencyTracker,
+        sidetone: Sidetone,
+        playback_delay: Arc<AtomicU32>,
+        errors: StreamErrors,
+    ) -> Result<cpal::Stream>
+    where
+        T: SizedSample + Sample + Send + 'static + cpal::FromSample<f32>,
+    {
+        let err_fn = move |err| errors.report(err);
+        let samples_per_second = config.sample_rate.0 as f64 * config.channels as f64;
+        let mut pending = PlaybackBuffer::new(samples_per_second as usize, playback_delay);
+        let channels = config.channels as usize;
+        let mut mixed = Vec::<f32>::new();
+
+        let stream = device.build_output_stream(
+            config,
+            move |data: &mut [T], info: &cpal::OutputCallbackInfo| {
+                // How long until what we write now reaches the speaker
+                let timestamp = info.timestamp();
+                if let Some(delay) = timestamp.playback.duration_since(&timestamp.callback) {
+                    latency.set_playback(delay);
+                }
+
+                // Only contended for the moment a replacement stream starts
+                if let Ok(sample_rx) = sample_rx.try_lock() {
+                    while let Ok(payload) = sample_rx.try_recv() {
+                        pending.push_encoded(&payload);
+                    }
+                }
+                latency.set_buffered(pending.len(), samples_per_second);
+
+                // Silence while nothing is available or the cushion is still filling
+                mixed.clear();
+                pending.pop_into(&mut mixed, data.len());
+                sidetone.mix_into(&mut mixed, channels);
+                for (output, &value) in data.iter_mut().zip(&mixed) {
+                    *output = T::from_sample(value);
+                }
+            },
+            err_fn,
+            None,
+        )?;
+
+        Ok(stream)
+    }
+}
@@ -0,0 +1,204 @@
+use anyhow::{anyhow, Result};
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::net::UdpSocket;
+use tokio::sync::broadcast;
+use tokio::time::{interval, MissedTickBehavior};
+use webrtc::media::Sample;
+
+use super::bluetooth::BluetoothDevice;
+use super::codec::PLAYBACK_SAMPLE_RATE;
+use super::{
+    encode_frame, AudioBackend, AudioLatency, AudioStreamHandle, AudioTrack, DeviceEvent, OutputRouting, SoundKind,
+    FRAME_DURATION,
+};
+
+// External programs send signed 16-bit little-endian mono PCM at this rate
+pub const EXTERNAL_SAMPLE_RATE: u32 = PLAYBACK_SAMPLE_RATE;
+const FRAME_BYTES: usize = (EXTERNAL_SAMPLE_RATE as u128 * FRAME_DURATION.as_millis() / 1000) as usize * 2;
+
+// Where call audio comes from when a program rather than a microphone is talking:
+// a TTS engine, a media server, a test harness
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CaptureSource {
+    Stdin,
+    // A named pipe (FIFO), or a plain file played once
+    Pipe(PathBuf),
+    // Datagrams of PCM sent to this local address
+    Udp(SocketAddr),
+}
+
+// "-" or "stdin", "udp:<address>", and anything else is a path
+impl FromStr for CaptureSource {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value {
+            "" => Err(anyhow!("Capture source is empty")),
+            "-" | "stdin" => Ok(CaptureSource::Stdin),
+            _ => match value.strip_prefix("udp:") {
+                Some(address) => Ok(CaptureSource::Udp(
+                    address.parse().map_err(|e| anyhow!("Bad UDP capture address \"{}\": {}", address, e))?,
+                )),
+                None => Ok(CaptureSource::Pipe(PathBuf::from(value.strip_prefix("pipe:").unwrap_or(value)))),
+            },
+        }
+    }
+}
+
+impl fmt::Display for CaptureSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CaptureSource::Stdin => write!(f, "stdin"),
+            CaptureSource::Pipe(path) => write!(f, "pipe:{}", path.display()),
+            CaptureSource::Udp(address) => write!(f, "udp:{}", address),
+        }
+    }
+}
+
+enum Reader {
+    Stream(Box<dyn AsyncRead + Unpin + Send>),
+    Datagrams(UdpSocket),
+}
+
+impl Reader {
+    async fn open(source: &CaptureSource) -> Result<Self> {
+        Ok(match source {
+            CaptureSource::Stdin => Reader::Stream(Box::new(tokio::io::stdin())),
+            // Blocks until a writer opens the other end of a FIFO
+            CaptureSource::Pipe(path) => Reader::Stream(Box::new(tokio::fs::File::open(path).await?)),
+            CaptureSource::Udp(address) => Reader::Datagrams(UdpSocket::bind(address).await?),
+        })
+    }
+
+    // None at the end of the input; datagram sources never end
+    async fn read(&mut self, buffer: &mut [u8]) -> Result<Option<usize>> {
+        match self {
+            Reader::Stream(reader) => match reader.read(buffer).await? {
+                0 => Ok(None),
+                read => Ok(Some(read)),
+            },
+            Reader::Datagrams(socket) => Ok(Some(socket.recv(buffer).await?)),
+        }
+    }
+}
+
+pub fn pcm_to_samples(pcm: &[u8]) -> Vec<f32> {
+    pcm.chunks_exact(2)
+        .map(|bytes| i16::from_le_bytes([bytes[0], bytes[1]]) as f32 / 32768.0)
+        .collect()
+}
+
+// Takes capture from `source` and leaves playback to the wrapped backend, so a bot
+// can still be heard through the speakers (or the mock sink) while it talks
+pub struct ExternalCaptureBackend {
+    source: CaptureSource,
+    playback: Arc<dyn AudioBackend>,
+}
+
+impl ExternalCaptureBackend {
+    pub fn new(source: CaptureSource, playback: Arc<dyn AudioBackend>) -> Self {
+        Self { source, playback }
+    }
+
+    pub fn wrap(backend: Arc<dyn AudioBackend>, source: Option<CaptureSource>) -> Arc<dyn AudioBackend> {
+        match source {
+            Some(source) => Arc::new(Self::new(source, backend)),
+            None => backend,
+        }
+    }
+}
+
+impl AudioBackend for ExternalCaptureBackend {
+    fn name(&self) -> &'static str {
+        "external"
+    }
+
+    fn start_capture(&self, track: Arc<AudioTrack>) -> Result<AudioStreamHandle> {
+        let source = self.source.clone();
+        let task = tokio::spawn(async move {
+            let mut reader = match Reader::open(&source).await {
+                Ok(reader) => reader,
+                Err(e) => {
+                    eprintln!("Failed to open capture source {}: {}", source, e);
+                    return;
+                }
+            };
+            // Producers often write faster than real time (a TTS engine dumping a whole
+            // sentence); frames go out at the pace they'd be spoken
+            let mut ticker = interval(FRAME_DURATION);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            let mut pending = Vec::new();
+            let mut buffer = vec![0u8; 64 * 1024];
+            loop {
+                while pending.len() < FRAME_BYTES {
+                    match reader.read(&mut buffer).await {
+                        Ok(Some(read)) => pending.extend_from_slice(&buffer[..read]),
+                        Ok(None) => {
+                            println!("Capture source {} ended", source);
+                            return;
+                        }
+                        Err(e) => {
+                            eprintln!("Failed to read capture source {}: {}", source, e);
+                            return;
+                        }
+                    }
+                }
+                ticker.tick().await;
+                let frame: Vec<u8> = pending.drain(..FRAME_BYTES).collect();
+                let sample = Sample {
+                    data: encode_frame(&pcm_to_samples(&frame)),
+                    duration: FRAME_DURATION,
+                    ..Default::default()
+                };
+                if track.write_sample(&sample).await.is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(AudioStreamHandle::new(move || task.abort()))
+    }
+
+    fn start_playback(&self, packets: broadcast::Receiver<Bytes>) -> Result<AudioStreamHandle> {
+        self.playback.start_playback(packets)
+    }
+
+    fn latency(&self) -> AudioLatency {
+        AudioLatency {
+            capture: None,
+            ..self.playback.latency()
+        }
+    }
+
+    fn device_events(&self) -> Option<broadcast::Receiver<DeviceEvent>> {
+        self.playback.device_events()
+    }
+
+    // Capture is another program's, so only the playback side can be a headset
+    fn bluetooth(&self) -> Option<BluetoothDevice> {
+        self.playback.bluetooth()
+    }
+
+    fn set_output_routing(&self, routing: OutputRouting) {
+        self.playback.set_output_routing(routing);
+    }
+
+    fn set_playback_delay(&self, delay: Duration) {
+        self.playback.set_playback_delay(delay);
+    }
+
+    fn start_playback_for(&self, kind: SoundKind, packets: broadcast::Receiver<Bytes>) -> Result<AudioStreamHandle> {
+        self.playback.start_playback_for(kind, packets)
+    }
+
+    fn output_devices(&self) -> Vec<String> {
+        self.playback.output_devices()
+    }
+}
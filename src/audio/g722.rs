@@ -0,0 +1,226 @@
+// ITU-T G.722 at 64 kbit/s: 16 kHz audio split by a QMF into two sub-bands, each coded
+// with adaptive differential PCM. One byte carries a pair of samples, 6 bits for the low
+// band and 2 for the high one. Fixed point throughout, as in the reference implementation.
+
+const QMF_COEFFS: [i32; 12] = [3, -11, 12, 32, -210, 951, 3876, -805, 362, -156, 53, -11];
+
+const Q6: [i32; 32] = [
+    0, 35, 72, 110, 150, 190, 233, 276, 323, 370, 422, 473, 530, 587, 650, 714, 786, 858, 940, 1023, 1121, 1219, 1339,
+    1458, 1612, 1765, 1980, 2195, 2557, 2919, 0, 0,
+];
+const ILN: [i32; 32] = [
+    0, 63, 62, 31, 30, 29, 28, 27, 26, 25, 24, 23, 22, 21, 20, 19, 18, 17, 16, 15, 14, 13, 12, 11, 10, 9, 8, 7, 6, 5, 4, 0,
+];
+const ILP: [i32; 32] = [
+    0, 61, 60, 59, 58, 57, 56, 55, 54, 53, 52, 51, 50, 49, 48, 47, 46, 45, 44, 43, 42, 41, 40, 39, 38, 37, 36, 35, 34, 33,
+    32, 0,
+];
+const QM6: [i32; 64] = [
+    -136, -136, -136, -136, -24808, -21904, -19008, -16704, -14984, -13512, -12280, -11192, -10232, -9360, -8576, -7856,
+    -7192, -6576, -6000, -5456, -4944, -4464, -4008, -3576, -3168, -2776, -2400, -2032, -1688, -1360, -1040, -728, 24808,
+    21904, 19008, 16704, 14984, 13512, 12280, 11192, 10232, 9360, 8576, 7856, 7192, 6576, 6000, 5456, 4944, 4464, 4008,
+    3576, 3168, 2776, 2400, 2032, 1688, 1360, 1040, 728, 432, 136, -432, -136,
+];
+const QM4: [i32; 16] = [
+    0, -20456, -12896, -8968, -6288, -4240, -2584, -1200, 20456, 12896, 8968, 6288, 4240, 2584, 1200, 0,
+];
+const RL42: [usize; 16] = [0, 7, 6, 5, 4, 3, 2, 1, 7, 6, 5, 4, 3, 2, 1, 0];
+const WL: [i32; 8] = [-60, -30, 58, 172, 334, 538, 1198, 3042];
+const ILB: [i32; 32] = [
+    2048, 2093, 2139, 2186, 2233, 2282, 2332, 2383, 2435, 2489, 2543, 2599, 2656, 2714, 2774, 2834, 2896, 2960, 3025,
+    3091, 3158, 3228, 3298, 3371, 3444, 3520, 3597, 3676, 3756, 3838, 3922, 4008,
+];
+const QM2: [i32; 4] = [-7408, -1616, 7408, 1616];
+const IHN: [i32; 3] = [0, 1, 0];
+const IHP: [i32; 3] = [0, 3, 2];
+const RH2: [usize; 4] = [2, 1, 2, 1];
+const WH: [i32; 3] = [0, -214, 798];
+
+fn saturate(value: i32) -> i32 {
+    value.clamp(i16::MIN as i32, i16::MAX as i32)
+}
+
+// Predictor and step size of one sub-band; encoder and decoder keep identical copies
+#[derive(Debug, Clone)]
+struct Band {
+    s: i32,
+    sp: i32,
+    sz: i32,
+    r: [i32; 3],
+    a: [i32; 3],
+    ap: [i32; 3],
+    p: [i32; 3],
+    d: [i32; 7],
+    b: [i32; 7],
+    bp: [i32; 7],
+    nb: i32,
+    det: i32,
+}
+
+impl Band {
+    fn new(det: i32) -> Self {
+        Self { s: 0, sp: 0, sz: 0, r: [0; 3], a: [0; 3], ap: [0; 3], p: [0; 3], d: [0; 7], b: [0; 7], bp: [0; 7], nb: 0, det }
+    }
+
+    // Blocks 3L/3H: adapt the step size to the last code
+    fn scale(&mut self, weight: i32, max_nb: i32, shift: i32) {
+        self.nb = (((self.nb * 127) >> 7) + weight).clamp(0, max_nb);
+        let wd1 = ILB[((self.nb >> 6) & 31) as usize];
+        let wd2 = shift - (self.nb >> 11);
+        let wd3 = if wd2 < 0 { wd1 << -wd2 } else { wd1 >> wd2 };
+        self.det = wd3 << 2;
+    }
+
+    // Block 4: update the pole-zero predictor with the quantized difference
+    fn adapt(&mut self, dx: i32) {
+        self.d[0] = dx;
+        self.r[0] = saturate(self.s + dx);
+        self.p[0] = saturate(self.sz + dx);
+
+        // UPPOL2
+        let sg: [i32; 3] = [self.p[0] >> 15, self.p[1] >> 15, self.p[2] >> 15];
+        let wd1 = saturate(self.a[1] << 2);
+        let wd2 = if sg[0] == sg[1] { -wd1 } else { wd1 }.min(32767);
+        let wd3 = if sg[0] == sg[2] { 128 } else { -128 } + (wd2 >> 7) + ((self.a[2] * 32512) >> 15);
+        self.ap[2] = wd3.clamp(-12288, 12288);
+
+        // UPPOL1
+        let wd1 = if sg[0] == sg[1] { 192 } else { -192 };
+        let wd2 = (self.a[1] * 32640) >> 15;
+        let limit = saturate(15360 - self.ap[2]);
+        self.ap[1] = saturate(wd1 + wd2).clamp(-limit, limit);
+
+        // UPZERO
+        let wd1 = if dx == 0 { 0 } else { 128 };
+        let sign = dx >> 15;
+        for i in 1..7 {
+            let wd2 = if self.d[i] >> 15 == sign { wd1 } else { -wd1 };
+            self.bp[i] = saturate(wd2 + ((self.b[i] * 32640) >> 15));
+        }
+
+        // DELAYA
+        for i in (1..7).rev() {
+            self.d[i] = self.d[i - 1];
+            self.b[i] = self.bp[i];
+        }
+        for i in (1..3).rev() {
+            self.r[i] = self.r[i - 1];
+            self.p[i] = self.p[i - 1];
+            self.a[i] = self.ap[i];
+        }
+
+        // FILTEP, FILTEZ, PREDIC
+        let wd1 = (self.a[1] * saturate(self.r[1] + self.r[1])) >> 15;
+        let wd2 = (self.a[2] * saturate(self.r[2] + self.r[2])) >> 15;
+        self.sp = saturate(wd1 + wd2);
+        self.sz = saturate((1..7).map(|i| (self.b[i] * saturate(self.d[i] + self.d[i])) >> 15).sum());
+        self.s = saturate(self.sp + self.sz);
+    }
+
+    fn adapt_low(&mut self, code: i32) {
+        let ril = (code >> 2) as usize;
+        let dlow = (self.det * QM4[ril]) >> 15;
+        self.scale(WL[RL42[ril]], 18432, 8);
+        self.adapt(dlow);
+    }
+
+    fn adapt_high(&mut self, code: i32) {
+        let dhigh = (self.det * QM2[code as usize]) >> 15;
+        self.scale(WH[RH2[code as usize]], 22528, 10);
+        self.adapt(dhigh);
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct G722Encoder {
+    x: [i32; 24],
+    low: Band,
+    high: Band,
+}
+
+impl Default for G722Encoder {
+    fn default() -> Self {
+        Self { x: [0; 24], low: Band::new(32), high: Band::new(8) }
+    }
+}
+
+impl G722Encoder {
+    // 16 kHz samples in, one byte per pair out; an odd last sample is dropped
+    pub fn encode(&mut self, samples: &[i16]) -> Vec<u8> {
+        samples.chunks_exact(2).map(|pair| self.encode_pair(pair[0], pair[1])).collect()
+    }
+
+    fn encode_pair(&mut self, first: i16, second: i16) -> u8 {
+        // Transmit QMF, keeping every other output
+        self.x.copy_within(2.., 0);
+        self.x[22] = first as i32;
+        self.x[23] = second as i32;
+        let (mut sum_odd, mut sum_even) = (0, 0);
+        for i in 0..12 {
+            sum_odd += self.x[2 * i] * QMF_COEFFS[i];
+            sum_even += self.x[2 * i + 1] * QMF_COEFFS[11 - i];
+        }
+        let xlow = (sum_even + sum_odd) >> 14;
+        let xhigh = (sum_even - sum_odd) >> 14;
+
+        // Low band: 6 bit quantizer
+        let el = saturate(xlow - self.low.s);
+        let wd = if el >= 0 { el } else { -(el + 1) };
+        let level = (1..30).find(|&i| wd < (Q6[i] * self.low.det) >> 12).unwrap_or(30);
+        let ilow = if el < 0 { ILN[level] } else { ILP[level] };
+        self.low.adapt_low(ilow);
+
+        // High band: 2 bit quantizer
+        let eh = saturate(xhigh - self.high.s);
+        let wd = if eh >= 0 { eh } else { -(eh + 1) };
+        let mih = if wd >= (564 * self.high.det) >> 12 { 2 } else { 1 };
+        let ihigh = if eh < 0 { IHN[mih] } else { IHP[mih] };
+        self.high.adapt_high(ihigh);
+
+        ((ihigh << 6) | ilow) as u8
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct G722Decoder {
+    x: [i32; 24],
+    low: Band,
+    high: Band,
+}
+
+impl Default for G722Decoder {
+    fn default() -> Self {
+        Self { x: [0; 24], low: Band::new(32), high: Band::new(8) }
+    }
+}
+
+impl G722Decoder {
+    // Two 16 kHz samples per byte
+    pub fn decode(&mut self, payload: &[u8]) -> Vec<i16> {
+        let mut samples = Vec::with_capacity(payload.len() * 2);
+        for &byte in payload {
+            let code = byte as i32;
+            let ilow = code & 0x3F;
+            let ihigh = (code >> 6) & 0x03;
+
+            let rlow = (self.low.s + ((self.low.det * QM6[ilow as usize]) >> 15)).clamp(-16384, 16383);
+            self.low.adapt_low(ilow);
+
+            let rhigh = (self.high.s + ((self.high.det * QM2[ihigh as usize]) >> 15)).clamp(-16384, 16383);
+            self.high.adapt_high(ihigh);
+
+            // Receive QMF
+            self.x.copy_within(2.., 0);
+            self.x[22] = rlow + rhigh;
+            self.x[23] = rlow - rhigh;
+            let (mut out_first, mut out_second) = (0, 0);
+            for i in 0..12 {
+                out_second += self.x[2 * i] * QMF_COEFFS[i];
+                out_first += self.x[2 * i + 1] * QMF_COEFFS[11 - i];
+            }
+            samples.push(saturate(out_first >> 11) as i16);
+            samples.push(saturate(out_second >> 11) as i16);
+        }
+        samples
+    }
+}
@@ -0,0 +1,81 @@
+use anyhow::{anyhow, Result};
+use objc2::runtime::AnyObject;
+use objc2::{class, msg_send};
+use objc2_foundation::{NSError, NSString};
+use std::sync::Mutex;
+
+#[link(name = "AVFoundation", kind = "framework")]
+extern "C" {}
+
+// AVAudioSessionCategoryOptions
+const ALLOW_BLUETOOTH: usize = 0x4;
+const DEFAULT_TO_SPEAKER: usize = 0x8;
+// AVAudioSessionSetActiveOptions
+const NOTIFY_OTHERS_ON_DEACTIVATION: usize = 0x1;
+
+// Streams holding the session active; the last one to stop hands audio back to other apps
+static ACTIVE: Mutex<usize> = Mutex::new(0);
+
+// iOS only lets an app record, and mixes it with other audio, once its AVAudioSession
+// says so. cpal opens the streams; this is held alongside each one.
+pub struct AudioSession;
+
+impl AudioSession {
+    // PlayAndRecord in VoiceChat mode turns on Apple's echo canceller and routes to the
+    // receiver or a headset; Default mode leaves the mic unprocessed, for music mode
+    pub fn activate(voice_processing: bool) -> Result<Self> {
+        let mut active = ACTIVE.lock().map_err(|_| anyhow!("Audio session lock poisoned"))?;
+        if *active == 0 {
+            let mode = if voice_processing { "AVAudioSessionModeVoiceChat" } else { "AVAudioSessionModeDefault" };
+            configure("AVAudioSessionCategoryPlayAndRecord", mode, ALLOW_BLUETOOTH | DEFAULT_TO_SPEAKER)?;
+            set_active(true)?;
+        }
+        *active += 1;
+        Ok(Self)
+    }
+}
+
+impl Drop for AudioSession {
+    fn drop(&mut self) {
+        let Ok(mut active) = ACTIVE.lock() else { return };
+        *active -= 1;
+        if *active == 0 {
+            if let Err(e) = set_active(false) {
+                eprintln!("Failed to deactivate the audio session: {}", e);
+            }
+        }
+    }
+}
+
+// The category and mode constants are NSStrings whose values are their own names
+fn configure(category: &str, mode: &str, options: usize) -> Result<()> {
+    let category = NSString::from_str(category);
+    let mode = NSString::from_str(mode);
+    let mut error: *mut NSError = std::ptr::null_mut();
+    // Safety: the selector and argument types are AVAudioSession's documented API
+    let ok: bool = unsafe {
+        let session: *mut AnyObject = msg_send![class!(AVAudioSession), sharedInstance];
+        msg_send![session, setCategory: &*category, mode: &*mode, options: options, error: &mut error]
+    };
+    check(ok, error, "set the audio session category")
+}
+
+fn set_active(active: bool) -> Result<()> {
+    let options = if active { 0 } else { NOTIFY_OTHERS_ON_DEACTIVATION };
+    let mut error: *mut NSError = std::ptr::null_mut();
+    // Safety: as above
+    let ok: bool = unsafe {
+        let session: *mut AnyObject = msg_send![class!(AVAudioSession), sharedInstance];
+        msg_send![session, setActive: active, withOptions: options, error: &mut error]
+    };
+    check(ok, error, if active { "activate the audio session" } else { "deactivate the audio session" })
+}
+
+fn check(ok: bool, error: *mut NSError, action: &str) -> Result<()> {
+    if ok {
+        return Ok(());
+    }
+    // Safety: on failure AVAudioSession sets error to an autoreleased NSError or leaves it nil
+    let description = unsafe { error.as_ref() }.map(|error| error.localizedDescription().to_string());
+    Err(anyhow!("Failed to {}: {}", action, description.unwrap_or_else(|| "unknown error".to_string())))
+}
@@ -0,0 +1,127 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+// Below this the input is digital silence: a mic muted in the OS, not a quiet room
+pub const SILENCE_DB: f32 = -70.0;
+pub const SILENCE_WARNING_AFTER: Duration = Duration::from_secs(5);
+// Samples this close to full scale count as clipped
+const CLIP_THRESHOLD: f32 = 0.99;
+// About a second of 20 ms frames; warn when at least CLIPPING_FRAMES of them clipped
+const CLIPPING_WINDOW: usize = 50;
+const CLIPPING_FRAMES: usize = 5;
+// Louder than this is taken for speech. Crude, but the mic is close and only the user's
+// own voice matters.
+pub const SPEECH_DB: f32 = -40.0;
+// Pauses between words shorter than this don't end the speech
+const SPEECH_HANGOVER: Duration = Duration::from_millis(300);
+// How long someone talks into a muted mic before they're reminded
+pub const MUTED_SPEECH_AFTER: Duration = Duration::from_secs(1);
+// And how long the reminder stays once they stop
+const MUTED_SPEECH_LINGER: Duration = Duration::from_secs(3);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputWarning {
+    // Nothing but silence for a while; usually muted in the OS or the wrong device
+    Silent,
+    // Repeatedly hitting full scale; the gain is too high
+    Clipping,
+}
+
+// RMS of a frame in dBFS, -100 for digital silence
+pub fn level_dbfs(samples: &[f32]) -> f32 {
+    if samples.is_empty() {
+        return -100.0;
+    }
+    let power = samples.iter().map(|sample| sample * sample).sum::<f32>() / samples.len() as f32;
+    10.0 * power.max(1e-10).log10()
+}
+
+// Capture-level meter, fed every outgoing frame before encoding
+#[derive(Clone, Default)]
+pub struct InputMeter {
+    state: Arc<Mutex<MeterState>>,
+}
+
+#[derive(Default)]
+struct MeterState {
+    level_db: Option<f32>,
+    silent_since: Option<Instant>,
+    // Whether each recent frame clipped, oldest first
+    clipped: VecDeque<bool>,
+    // Start of the current stretch of speech and its latest loud frame
+    speech_since: Option<Instant>,
+    last_speech: Option<Instant>,
+}
+
+impl InputMeter {
+    pub fn measure(&self, samples: &[f32], now: Instant) {
+        if samples.is_empty() {
+            return;
+        }
+        let rms = (samples.iter().map(|sample| sample * sample).sum::<f32>() / samples.len() as f32).sqrt();
+        let level_db = if rms > 0.0 { (20.0 * rms.log10()).max(-127.0) } else { -127.0 };
+        let clipped_samples = samples.iter().filter(|sample| sample.abs() >= CLIP_THRESHOLD).count();
+
+        let mut state = self.state.lock().unwrap();
+        state.level_db = Some(level_db);
+        if level_db < SILENCE_DB {
+            state.silent_since.get_or_insert(now);
+        } else {
+            state.silent_since = None;
+        }
+        // A frame clips when over 1% of it sits at full scale; single overs are inaudible
+        state.clipped.push_back(clipped_samples * 100 > samples.len());
+        if state.clipped.len() > CLIPPING_WINDOW {
+            state.clipped.pop_front();
+        }
+        if level_db >= SPEECH_DB {
+            state.speech_since.get_or_insert(now);
+            state.last_speech = Some(now);
+        } else if state.last_speech.map_or(true, |last| now.duration_since(last) >= SPEECH_HANGOVER) {
+            state.speech_since = None;
+        }
+    }
+
+    // RMS of the latest frame in dBFS; None before the first frame
+    pub fn level_db(&self) -> Option<f32> {
+        self.state.lock().unwrap().level_db
+    }
+
+    // How long the user has been talking; zero when they aren't
+    pub fn speaking_for(&self, now: Instant) -> Duration {
+        let state = self.state.lock().unwrap();
+        state.speech_since.map_or(Duration::ZERO, |since| now.duration_since(since))
+    }
+
+    pub fn warning(&self, now: Instant) -> Option<InputWarning> {
+        let state = self.state.lock().unwrap();
+        if state.clipped.iter().filter(|&&clipped| clipped).count() >= CLIPPING_FRAMES {
+            return Some(InputWarning::Clipping);
+        }
+        match state.silent_since {
+            Some(since) if now.duration_since(since) >= SILENCE_WARNING_AFTER => Some(InputWarning::Silent),
+            _ => None,
+        }
+    }
+}
+
+// Whether to tell a muted user they're talking to nobody: from a second into their
+// speech until they unmute or have been quiet for a few seconds
+#[derive(Debug, Default)]
+pub struct MutedSpeechBanner {
+    shown_until: Option<Instant>,
+}
+
+impl MutedSpeechBanner {
+    pub fn check(&mut self, muted: bool, speaking_for: Duration, now: Instant) -> bool {
+        if !muted {
+            self.shown_until = None;
+            return false;
+        }
+        if speaking_for >= MUTED_SPEECH_AFTER {
+            self.shown_until = Some(now + MUTED_SPEECH_LINGER);
+        }
+        self.shown_until.map_or(false, |until| now < until)
+    }
+}
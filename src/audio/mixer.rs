@@ -0,0 +1,280 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+
+use super::frame_samples;
+
+// Speech level every remote peer is steered toward, RMS
+pub const TARGET_LOUDNESS_DBFS: f32 = -23.0;
+// Beyond this the source is broken rather than just loud or quiet
+const MAX_GAIN_DB: f32 = 12.0;
+// Quieter frames are pauses between words and don't count toward the level
+const SILENCE_DBFS: f32 = -50.0;
+// Per 20 ms frame: the level follows over a couple of seconds, the gain within one
+const LEVEL_SMOOTHING: f32 = 0.99;
+const GAIN_SMOOTHING: f32 = 0.9;
+
+// How far the automatic layout spreads peers toward the sides; fully hard-panned voices
+// sound unnatural on headphones
+const AUTO_PAN_SPREAD: f32 = 0.8;
+
+// At 48 kHz; anything older is dropped so the sidetone never lags behind the voice
+const MAX_SIDETONE_SAMPLES: usize = 480;
+
+// The most extra playback buffering a user can ask for
+pub const MAX_PLAYBACK_DELAY_MS: u32 = 200;
+
+// Local mic mixed into playback so headset users hear themselves. The capture callback
+// feeds it and the playback callback drains it directly, skipping the network path
+// entirely. Assumes both devices run at the same rate, which the defaults usually do;
+// otherwise the cap above keeps it from drifting.
+#[derive(Clone, Default)]
+pub struct Sidetone {
+    // f32 bits; 0.0 is off
+    level: Arc<AtomicU32>,
+    // Mono, downmixed from the capture device
+    samples: Arc<Mutex<VecDeque<f32>>>,
+}
+
+impl Sidetone {
+    pub fn set_level(&self, level: f32) {
+        self.level.store(level.clamp(0.0, 1.0).to_bits(), Ordering::Relaxed);
+        if level <= 0.0 {
+            if let Ok(mut samples) = self.samples.lock() {
+                samples.clear();
+            }
+        }
+    }
+
+    pub fn level(&self) -> f32 {
+        f32::from_bits(self.level.load(Ordering::Relaxed))
+    }
+
+    // Called from the capture callback with interleaved samples
+    pub fn push_capture(&self, samples: &[f32], channels: usize) {
+        if self.level() <= 0.0 || channels == 0 {
+            return;
+        }
+        // Never block an audio callback; a missed buffer is inaudible
+        let Ok(mut buffer) = self.samples.try_lock() else { return };
+        buffer.extend(samples.chunks(channels).map(|frame| frame.iter().sum::<f32>() / frame.len() as f32));
+        let overflow = buffer.len().saturating_sub(MAX_SIDETONE_SAMPLES);
+        buffer.drain(..overflow);
+    }
+
+    // Called from the playback callback; adds the sidetone to every output channel
+    pub fn mix_into(&self, output: &mut [f32], channels: usize) {
+        let level = self.level();
+        if level <= 0.0 || channels == 0 {
+            return;
+        }
+        let Ok(mut buffer) = self.samples.try_lock() else { return };
+        for frame in output.chunks_mut(channels) {
+            let Some(sample) = buffer.pop_front() else { break };
+            for value in frame {
+                *value = (*value + sample * level).clamp(-1.0, 1.0);
+            }
+        }
+    }
+}
+
+// What waits for the output device. With an extra delay set it holds that much back
+// before playing, and again whenever it runs dry, so an output that stalls now and then
+// (Bluetooth headsets do) plays from a cushion instead of stuttering.
+pub struct PlaybackBuffer {
+    pending: VecDeque<f32>,
+    // Interleaved samples per second on the device
+    samples_per_second: usize,
+    // Milliseconds; read on every callback so a change applies to a running stream
+    delay_ms: Arc<AtomicU32>,
+    filling: bool,
+}
+
+impl PlaybackBuffer {
+    pub fn new(samples_per_second: usize, delay_ms: Arc<AtomicU32>) -> Self {
+        Self {
+            pending: VecDeque::new(),
+            samples_per_second,
+            delay_ms,
+            filling: true,
+        }
+    }
+
+    fn delay_samples(&self) -> usize {
+        let delay_ms = self.delay_ms.load(Ordering::Relaxed).min(MAX_PLAYBACK_DELAY_MS) as usize;
+        self.samples_per_second * delay_ms / 1000
+    }
+
+    pub fn push(&mut self, samples: &[f32]) {
+        self.pending.extend(samples);
+        self.trim();
+    }
+
+    // A frame still in the wire format, decoded straight into the queue so the playback
+    // callback never allocates for it
+    pub fn push_encoded(&mut self, payload: &[u8]) {
+        self.pending.extend(frame_samples(payload));
+        self.trim();
+    }
+
+    fn trim(&mut self) {
+        // Frames rarely line up with device buffers, so some slack on top of the cushion;
+        // anything beyond that is latency nobody asked for
+        let max_pending = self.samples_per_second / 5 + self.delay_samples();
+        let overflow = self.pending.len().saturating_sub(max_pending);
+        self.pending.drain(..overflow);
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    // Appends `count` samples to `output`, silence where there are none to play
+    pub fn pop_into(&mut self, output: &mut Vec<f32>, count: usize) {
+        let delay = self.delay_samples();
+        if self.filling && self.pending.len() >= delay {
+            self.filling = false;
+        }
+        let available = if self.filling { 0 } else { count.min(self.pending.len()) };
+        output.extend(self.pending.drain(..available));
+        output.extend(std::iter::repeat(0.0).take(count - available));
+        if available < count && !self.filling {
+            self.filling = delay > 0;
+        }
+    }
+}
+
+// One remote peer's playback gain, so a shouty participant and a quiet one end up at
+// similar levels. Measures continuously; `enabled` only decides whether the gain is
+// applied.
+pub struct LoudnessNormalizer {
+    enabled: Arc<AtomicBool>,
+    // Smoothed mean square of speech frames; 0 until the peer has said something
+    power: f32,
+    gain_db: f32,
+}
+
+impl LoudnessNormalizer {
+    pub fn new(enabled: Arc<AtomicBool>) -> Self {
+        Self {
+            enabled,
+            power: 0.0,
+            gain_db: 0.0,
+        }
+    }
+
+    pub fn loudness_dbfs(&self) -> Option<f32> {
+        (self.power > 0.0).then(|| power_dbfs(self.power))
+    }
+
+    pub fn gain_db(&self) -> f32 {
+        self.gain_db
+    }
+
+    pub fn process(&mut self, samples: &mut [f32]) {
+        if samples.is_empty() {
+            return;
+        }
+        let frame_power = samples.iter().map(|sample| sample * sample).sum::<f32>() / samples.len() as f32;
+        if power_dbfs(frame_power) > SILENCE_DBFS {
+            self.power = if self.power == 0.0 {
+                frame_power
+            } else {
+                LEVEL_SMOOTHING * self.power + (1.0 - LEVEL_SMOOTHING) * frame_power
+            };
+        }
+        if !self.enabled.load(Ordering::Relaxed) {
+            self.gain_db = 0.0;
+            return;
+        }
+        let Some(loudness) = self.loudness_dbfs() else { return };
+        let target = (TARGET_LOUDNESS_DBFS - loudness).clamp(-MAX_GAIN_DB, MAX_GAIN_DB);
+        self.gain_db += (target - self.gain_db) * (1.0 - GAIN_SMOOTHING);
+        let gain = 10f32.powf(self.gain_db / 20.0);
+        for sample in samples {
+            *sample = (*sample * gain).clamp(-1.0, 1.0);
+        }
+    }
+}
+
+fn power_dbfs(power: f32) -> f32 {
+    10.0 * power.max(1e-10).log10()
+}
+
+// Where each remote peer sits in the stereo field, from -1.0 (left) to 1.0 (right).
+// Peers are spread evenly in the order they joined unless the user placed them.
+#[derive(Debug, Clone, Default)]
+pub struct StereoLayout {
+    enabled: bool,
+    peers: Vec<String>,
+    placed: HashMap<String, f32>,
+}
+
+impl StereoLayout {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            ..Default::default()
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    pub fn add_peer(&mut self, peer: &str) {
+        if !self.peers.iter().any(|known| known == peer) {
+            self.peers.push(peer.to_string());
+        }
+    }
+
+    // The others spread out again over the freed space. A placement the user made is
+    // kept for when the peer comes back.
+    pub fn remove_peer(&mut self, peer: &str) {
+        self.peers.retain(|known| known != peer);
+    }
+
+    pub fn set_pan(&mut self, peer: &str, pan: f32) {
+        self.add_peer(peer);
+        self.placed.insert(peer.to_string(), pan.clamp(-1.0, 1.0));
+    }
+
+    // None while spatial placement is off, so audio stays as it was sent
+    pub fn pan(&self, peer: &str) -> Option<f32> {
+        if !self.enabled {
+            return None;
+        }
+        if let Some(&pan) = self.placed.get(peer) {
+            return Some(pan);
+        }
+        let index = self.peers.iter().position(|known| known == peer)?;
+        Some(match self.peers.len() {
+            1 => 0.0,
+            count => -AUTO_PAN_SPREAD + 2.0 * AUTO_PAN_SPREAD * index as f32 / (count - 1) as f32,
+        })
+    }
+}
+
+// Mono in, interleaved stereo out, with a constant-power pan law so a voice keeps its
+// loudness as it moves across
+pub fn pan_to_stereo(samples: &[f32], pan: f32) -> Vec<f32> {
+    let mut stereo = Vec::with_capacity(samples.len() * 2);
+    pan_to_stereo_into(samples, pan, &mut stereo);
+    stereo
+}
+
+// pan_to_stereo into a buffer the caller keeps
+pub fn pan_to_stereo_into(samples: &[f32], pan: f32, stereo: &mut Vec<f32>) {
+    let angle = (pan.clamp(-1.0, 1.0) + 1.0) * std::f32::consts::FRAC_PI_4;
+    let (left, right) = (angle.cos(), angle.sin());
+    stereo.clear();
+    stereo.extend(samples.iter().flat_map(|&sample| [sample * left, sample * right]));
+}
@@ -0,0 +1,102 @@
+use anyhow::Result;
+use bytes::Bytes;
+use std::f32::consts::TAU;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::sync::broadcast;
+use tokio::time::interval;
+use webrtc::media::Sample;
+
+use super::{encode_frame, AudioBackend, AudioStreamHandle, AudioTrack, FRAME_DURATION};
+
+pub const MOCK_SAMPLE_RATE: u32 = 16_000;
+
+// Deterministic backend for machines without sound hardware: capture is a sine
+// generator and playback is a sink that only counts what it receives.
+pub struct MockBackend {
+    frequency: f32,
+    frames_captured: Arc<AtomicU64>,
+    frames_played: Arc<AtomicU64>,
+}
+
+impl Default for MockBackend {
+    fn default() -> Self {
+        Self::new(440.0)
+    }
+}
+
+impl MockBackend {
+    pub fn new(frequency: f32) -> Self {
+        Self {
+            frequency,
+            frames_captured: Arc::new(AtomicU64::new(0)),
+            frames_played: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    pub fn frames_captured(&self) -> u64 {
+        self.frames_captured.load(Ordering::Relaxed)
+    }
+
+    pub fn frames_played(&self) -> u64 {
+        self.frames_played.load(Ordering::Relaxed)
+    }
+}
+
+// Frame `index` of a continuous sine tone, so output is identical run to run
+pub fn sine_frame(frequency: f32, index: u64) -> Vec<f32> {
+    let samples_per_frame = (MOCK_SAMPLE_RATE as u128 * FRAME_DURATION.as_millis() / 1000) as u64;
+    let start = index * samples_per_frame;
+    (start..start + samples_per_frame)
+        .map(|n| (TAU * frequency * n as f32 / MOCK_SAMPLE_RATE as f32).sin() * 0.5)
+        .collect()
+}
+
+impl AudioBackend for MockBackend {
+    fn name(&self) -> &'static str {
+        "mock"
+    }
+
+    fn start_capture(&self, track: Arc<AudioTrack>) -> Result<AudioStreamHandle> {
+        let frequency = self.frequency;
+        let frames_captured = self.frames_captured.clone();
+
+        let task = tokio::spawn(async move {
+            let mut ticker = interval(FRAME_DURATION);
+            let mut index = 0;
+            loop {
+                ticker.tick().await;
+                let sample = Sample {
+                    data: encode_frame(&sine_frame(frequency, index)),
+                    duration: FRAME_DURATION,
+                    ..Default::default()
+                };
+                if track.write_sample(&sample).await.is_err() {
+                    break;
+                }
+                index += 1;
+                frames_captured.fetch_add(1, Ordering::Relaxed);
+            }
+        });
+
+        Ok(AudioStreamHandle::new(move || task.abort()))
+    }
+
+    fn start_playback(&self, mut packets: broadcast::Receiver<Bytes>) -> Result<AudioStreamHandle> {
+        let frames_played = self.frames_played.clone();
+
+        let task = tokio::spawn(async move {
+            loop {
+                match packets.recv().await {
+                    Ok(_) => {
+                        frames_played.fetch_add(1, Ordering::Relaxed);
+                    }
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+
+        Ok(AudioStreamHandle::new(move || task.abort()))
+    }
+}
@@ -0,0 +1,417 @@
+#[cfg(not(target_arch = "wasm32"))]
+use anyhow::Result;
+use bytes::{BufMut, Bytes, BytesMut};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+#[cfg(not(target_arch = "wasm32"))]
+use tokio::sync::broadcast;
+
+#[cfg(not(target_arch = "wasm32"))]
+use bluetooth::BluetoothDevice;
+
+// The browser build only gets the device-independent parts: codecs, frames, meters and
+// mixing. Devices and tracks are the browser's there.
+#[cfg(not(target_arch = "wasm32"))]
+pub mod bluetooth;
+pub mod codec;
+#[cfg(not(target_arch = "wasm32"))]
+mod cpal_backend;
+#[cfg(not(target_arch = "wasm32"))]
+mod external;
+mod g722;
+#[cfg(target_os = "ios")]
+mod ios_session;
+mod meter;
+mod mixer;
+#[cfg(not(target_arch = "wasm32"))]
+mod mock;
+#[cfg(all(target_os = "android", feature = "oboe"))]
+mod oboe_backend;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod tap;
+#[cfg(all(target_os = "linux", feature = "pipewire"))]
+mod pipewire_backend;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod tones;
+#[cfg(not(target_arch = "wasm32"))]
+mod track;
+pub mod wav;
+#[cfg(all(windows, feature = "wasapi-exclusive"))]
+mod wasapi_backend;
+
+pub use codec::AudioCodec;
+#[cfg(not(target_arch = "wasm32"))]
+pub use cpal_backend::{AudioCapture, AudioPlayback, CpalBackend};
+#[cfg(not(target_arch = "wasm32"))]
+pub use external::{pcm_to_samples, CaptureSource, ExternalCaptureBackend, EXTERNAL_SAMPLE_RATE};
+pub use meter::{level_dbfs, InputMeter, InputWarning, MutedSpeechBanner, MUTED_SPEECH_AFTER};
+pub use mixer::{
+    pan_to_stereo, pan_to_stereo_into, LoudnessNormalizer, PlaybackBuffer, Sidetone, StereoLayout, MAX_PLAYBACK_DELAY_MS,
+    TARGET_LOUDNESS_DBFS,
+};
+#[cfg(not(target_arch = "wasm32"))]
+pub use mock::MockBackend;
+#[cfg(all(target_os = "android", feature = "oboe"))]
+pub use oboe_backend::OboeBackend;
+#[cfg(all(target_os = "linux", feature = "pipewire"))]
+pub use pipewire_backend::PipeWireBackend;
+#[cfg(not(target_arch = "wasm32"))]
+pub use track::AudioTrack;
+#[cfg(all(windows, feature = "wasapi-exclusive"))]
+pub use wasapi_backend::WasapiExclusiveBackend;
+
+pub const FRAME_DURATION: Duration = Duration::from_millis(20);
+
+// Where call audio comes from and goes to. Implementations own their device threads;
+// the returned handles stop the stream when dropped.
+#[cfg(not(target_arch = "wasm32"))]
+pub trait AudioBackend: Send + Sync {
+    fn name(&self) -> &'static str;
+    fn start_capture(&self, track: Arc<AudioTrack>) -> Result<AudioStreamHandle>;
+    fn start_playback(&self, packets: broadcast::Receiver<Bytes>) -> Result<AudioStreamHandle>;
+
+    // Device-side buffering of the running streams, for the diagnostics panel
+    fn latency(&self) -> AudioLatency {
+        AudioLatency::default()
+    }
+
+    // The Bluetooth headset the call runs on, if the backend can tell
+    fn bluetooth(&self) -> Option<BluetoothDevice> {
+        None
+    }
+
+    // None for backends that can't loop the mic into playback
+    fn sidetone(&self) -> Option<Sidetone> {
+        None
+    }
+
+    // Extra buffering before call audio plays, up to MAX_PLAYBACK_DELAY_MS, for outputs
+    // that stall. Backends that hand audio straight to the device ignore this.
+    fn set_playback_delay(&self, _delay: Duration) {}
+
+    // Whether the platform may run its call processing (noise suppression, AGC, echo
+    // cancellation) on our streams. Backends without a say in it ignore this.
+    fn set_voice_processing(&self, _enabled: bool) {}
+
+    // None for backends that don't switch devices on their own
+    fn device_events(&self) -> Option<broadcast::Receiver<DeviceEvent>> {
+        None
+    }
+
+    // Which output each kind of sound plays on. Backends that can't pick devices
+    // play everything on their one output.
+    fn set_output_routing(&self, _routing: OutputRouting) {}
+
+    fn start_playback_for(&self, _kind: SoundKind, packets: broadcast::Receiver<Bytes>) -> Result<AudioStreamHandle> {
+        self.start_playback(packets)
+    }
+
+    // Names to offer for routing; empty when the backend can't pick
+    fn output_devices(&self) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SoundKind {
+    // Remote peers, echo test
+    Call,
+    // Ringtones and notifications
+    Alert,
+}
+
+// Output device names per kind of sound, e.g. ringing on the laptop speakers while
+// calls go to the headset. None is the system default.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OutputRouting {
+    pub call: Option<String>,
+    pub alerts: Option<String>,
+}
+
+impl OutputRouting {
+    pub fn device_for(&self, kind: SoundKind) -> Option<&str> {
+        match kind {
+            SoundKind::Call => self.call.as_deref(),
+            SoundKind::Alert => self.alerts.as_deref(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamDirection {
+    Capture,
+    Playback,
+}
+
+// Raised when a running stream's device fails (unplugged, taken by an exclusive-mode
+// application) and the backend moves the stream elsewhere
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeviceEvent {
+    Switched { direction: StreamDirection, from: String, to: String },
+    // Nothing left to move to; the stream stays stopped until the next call
+    Lost { direction: StreamDirection, device: String },
+}
+
+// The routed device while it's plugged in, otherwise the system default
+pub fn routed_device<'a>(routed: Option<&str>, default: Option<&'a str>, available: &[&'a str]) -> Option<&'a str> {
+    routed
+        .and_then(|routed| available.iter().copied().find(|name| *name == routed))
+        .or(default)
+}
+
+// The device to rebuild a stream on after `failed` devices errored: the system default
+// if it's still good, otherwise the first other device that is
+pub fn fallback_device<'a>(default: Option<&'a str>, available: &[&'a str], failed: &[String]) -> Option<&'a str> {
+    let usable = |name: &&str| !failed.iter().any(|failed| failed == name);
+    default.filter(usable).or_else(|| available.iter().copied().find(usable))
+}
+
+// None until the backend has a running stream and can tell
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct AudioLatency {
+    pub capture: Option<Duration>,
+    pub playback: Option<Duration>,
+    // Decoded audio waiting in the backend's queue for the device
+    pub buffered: Option<Duration>,
+}
+
+// Lock-free so device callbacks can update it on every buffer
+#[derive(Clone, Default)]
+pub struct LatencyTracker {
+    // Microseconds, 0 = not measured yet
+    capture_us: Arc<AtomicU64>,
+    playback_us: Arc<AtomicU64>,
+    buffered_us: Arc<AtomicU64>,
+}
+
+impl LatencyTracker {
+    pub fn set_capture(&self, latency: Duration) {
+        self.capture_us.store(latency.as_micros().max(1) as u64, Ordering::Relaxed);
+    }
+
+    pub fn set_playback(&self, latency: Duration) {
+        self.playback_us.store(latency.as_micros().max(1) as u64, Ordering::Relaxed);
+    }
+
+    pub fn set_buffered(&self, samples: usize, samples_per_second: f64) {
+        let latency = Duration::from_secs_f64(samples as f64 / samples_per_second);
+        self.buffered_us.store(latency.as_micros().max(1) as u64, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> AudioLatency {
+        let read = |value: &AtomicU64| match value.load(Ordering::Relaxed) {
+            0 => None,
+            us => Some(Duration::from_micros(us)),
+        };
+        AudioLatency {
+            capture: read(&self.capture_us),
+            playback: read(&self.playback_us),
+            buffered: read(&self.buffered_us),
+        }
+    }
+}
+
+// Every variant exists in every build so settings files move between machines; kinds
+// this build can't provide fall back to cpal when created
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AudioBackendKind {
+    Cpal,
+    // Native PipeWire streams on Linux, for systems where cpal's ALSA path contends
+    // with other applications for the device
+    PipeWire,
+    // Low-latency Windows paths for sub-20 ms capture: cpal over an ASIO driver, or
+    // WASAPI in exclusive mode (takes the device away from every other application)
+    Asio,
+    WasapiExclusive,
+    // Android's low-latency path (AAudio, or OpenSL ES on older releases)
+    Oboe,
+    Mock,
+}
+
+impl Default for AudioBackendKind {
+    // Builds with the mock-audio feature (CI containers) never touch sound hardware
+    fn default() -> Self {
+        if cfg!(feature = "mock-audio") {
+            AudioBackendKind::Mock
+        } else if cfg!(all(target_os = "android", feature = "oboe")) {
+            AudioBackendKind::Oboe
+        } else {
+            AudioBackendKind::Cpal
+        }
+    }
+}
+
+impl AudioBackendKind {
+    pub const ALL: [AudioBackendKind; 6] = [
+        AudioBackendKind::Cpal,
+        AudioBackendKind::PipeWire,
+        AudioBackendKind::Asio,
+        AudioBackendKind::WasapiExclusive,
+        AudioBackendKind::Oboe,
+        AudioBackendKind::Mock,
+    ];
+
+    pub fn is_available(self) -> bool {
+        match self {
+            AudioBackendKind::PipeWire => cfg!(all(target_os = "linux", feature = "pipewire")),
+            AudioBackendKind::Asio => cfg!(all(windows, feature = "asio")),
+            AudioBackendKind::WasapiExclusive => cfg!(all(windows, feature = "wasapi-exclusive")),
+            AudioBackendKind::Oboe => cfg!(all(target_os = "android", feature = "oboe")),
+            AudioBackendKind::Cpal | AudioBackendKind::Mock => true,
+        }
+    }
+
+    // Choices to offer in settings
+    pub fn available() -> Vec<AudioBackendKind> {
+        Self::ALL.into_iter().filter(|kind| kind.is_available()).collect()
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn create(self) -> Arc<dyn AudioBackend> {
+        self.create_with_buffer(None)
+    }
+
+    // buffer_frames asks the device for a fixed buffer size; smaller means less latency
+    // and more risk of dropouts. Ignored by backends that size their own buffers.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn create_with_buffer(self, buffer_frames: Option<u32>) -> Arc<dyn AudioBackend> {
+        match self {
+            AudioBackendKind::Cpal => Arc::new(CpalBackend::with_options(None, buffer_frames)),
+            #[cfg(all(target_os = "linux", feature = "pipewire"))]
+            AudioBackendKind::PipeWire => Arc::new(PipeWireBackend::new()),
+            #[cfg(not(all(target_os = "linux", feature = "pipewire")))]
+            AudioBackendKind::PipeWire => {
+                eprintln!("PipeWire audio is not available in this build, using cpal");
+                Arc::new(CpalBackend::with_options(None, buffer_frames))
+            }
+            #[cfg(all(windows, feature = "asio"))]
+            AudioBackendKind::Asio => Arc::new(CpalBackend::asio(buffer_frames)),
+            #[cfg(not(all(windows, feature = "asio")))]
+            AudioBackendKind::Asio => {
+                eprintln!("ASIO is not available in this build, using cpal");
+                Arc::new(CpalBackend::with_options(None, buffer_frames))
+            }
+            #[cfg(all(windows, feature = "wasapi-exclusive"))]
+            AudioBackendKind::WasapiExclusive => Arc::new(WasapiExclusiveBackend::new()),
+            #[cfg(not(all(windows, feature = "wasapi-exclusive")))]
+            AudioBackendKind::WasapiExclusive => {
+                eprintln!("WASAPI exclusive mode is not available in this build, using cpal");
+                Arc::new(CpalBackend::with_options(None, buffer_frames))
+            }
+            #[cfg(all(target_os = "android", feature = "oboe"))]
+            AudioBackendKind::Oboe => Arc::new(OboeBackend::new()),
+            #[cfg(not(all(target_os = "android", feature = "oboe")))]
+            AudioBackendKind::Oboe => {
+                eprintln!("oboe is not available in this build, using cpal");
+                Arc::new(CpalBackend::with_options(None, buffer_frames))
+            }
+            AudioBackendKind::Mock => Arc::new(MockBackend::default()),
+        }
+    }
+}
+
+pub struct AudioStreamHandle {
+    stop: Option<Box<dyn FnOnce() + Send>>,
+}
+
+impl AudioStreamHandle {
+    pub fn new(stop: impl FnOnce() + Send + 'static) -> Self {
+        Self {
+            stop: Some(Box::new(stop)),
+        }
+    }
+}
+
+impl Drop for AudioStreamHandle {
+    fn drop(&mut self) {
+        if let Some(stop) = self.stop.take() {
+            stop();
+        }
+    }
+}
+
+// Wire format for call audio frames: little-endian f32 samples
+pub fn encode_frame(samples: &[f32]) -> Bytes {
+    samples
+        .iter()
+        .flat_map(|sample| sample.to_le_bytes())
+        .collect::<Vec<u8>>()
+        .into()
+}
+
+pub fn decode_frame(payload: &[u8]) -> Vec<f32> {
+    frame_samples(payload).collect()
+}
+
+// decode_frame into a buffer the caller keeps, for paths that run once per frame
+pub fn decode_frame_into(payload: &[u8], samples: &mut Vec<f32>) {
+    samples.clear();
+    samples.extend(frame_samples(payload));
+}
+
+// The samples of a frame, decoded as they're read
+pub fn frame_samples(payload: &[u8]) -> impl ExactSizeIterator<Item = f32> + '_ {
+    payload
+        .chunks_exact(4)
+        .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+}
+
+// 20 ms of 48 kHz stereo, the largest frame a device normally delivers
+const POOL_FRAME_SAMPLES: usize = 1920;
+const POOL_FRAMES: usize = 16;
+
+// Backing store for encoded frames. Each frame is split off one shared allocation, and
+// once every frame handed out has been dropped the next one starts over at its beginning,
+// so a capture or decode loop whose frames are consumed promptly allocates nothing in the
+// steady state. Frames that are held longer (a broadcast channel's backlog) just mean a
+// fresh block every POOL_FRAMES frames instead of one per frame.
+pub struct FramePool {
+    buffer: BytesMut,
+    block: usize,
+}
+
+impl Default for FramePool {
+    fn default() -> Self {
+        Self::new(POOL_FRAME_SAMPLES)
+    }
+}
+
+impl FramePool {
+    pub fn new(frame_samples: usize) -> Self {
+        let block = frame_samples.max(1) * 4 * POOL_FRAMES;
+        Self {
+            buffer: BytesMut::with_capacity(block),
+            block,
+        }
+    }
+
+    // Same bytes as encode_frame
+    pub fn encode(&mut self, samples: &[f32]) -> Bytes {
+        self.encode_iter(samples.iter().copied())
+    }
+
+    pub fn encode_iter(&mut self, samples: impl ExactSizeIterator<Item = f32>) -> Bytes {
+        self.make_room(samples.len() * 4);
+        for sample in samples {
+            self.buffer.put_f32_le(sample);
+        }
+        self.buffer.split().freeze()
+    }
+
+    // A frame that's already in the wire format
+    pub fn copy(&mut self, payload: &[u8]) -> Bytes {
+        self.make_room(payload.len());
+        self.buffer.put_slice(payload);
+        self.buffer.split().freeze()
+    }
+
+    fn make_room(&mut self, length: usize) {
+        if self.buffer.capacity() < length {
+            // Takes back the whole block when nothing split off is alive any more
+            self.buffer.reserve(self.block.max(length));
+        }
+    }
+}
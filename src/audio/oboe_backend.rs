@@ -0,0 +1,227 @@
+use anyhow::{anyhow, Result};
+use bytes::Bytes;
+use oboe::{
+    AudioInputCallback, AudioInputStreamSafe, AudioOutputCallback, AudioOutputStreamSafe, AudioStream, AudioStreamBase,
+    AudioStreamBuilder, ContentType, DataCallbackResult, InputPreset, Mono, PerformanceMode, SharingMode, Usage,
+};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc as std_mpsc;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc};
+use webrtc::media::Sample as MediaSample;
+
+use super::codec::PLAYBACK_SAMPLE_RATE;
+use super::{frame_samples, AudioBackend, AudioLatency, AudioStreamHandle, AudioTrack, FramePool, LatencyTracker};
+
+// Same cap as the other backends: a stalled reader must not turn into latency
+const MAX_PLAYBACK_BUFFER: usize = PLAYBACK_SAMPLE_RATE as usize / 5;
+
+// Android through oboe, which picks AAudio where the device has it and OpenSL ES on
+// older releases. Streams are low-latency mono at the playback rate, so Android does
+// any resampling.
+pub struct OboeBackend {
+    latency: LatencyTracker,
+    voice_processing: Arc<AtomicBool>,
+}
+
+impl OboeBackend {
+    pub fn new() -> Self {
+        Self {
+            latency: LatencyTracker::default(),
+            voice_processing: Arc::new(AtomicBool::new(true)),
+        }
+    }
+}
+
+struct Capture {
+    frames: mpsc::Sender<MediaSample>,
+    pool: FramePool,
+}
+
+impl AudioInputCallback for Capture {
+    type FrameType = (f32, Mono);
+
+    fn on_audio_ready(&mut self, _stream: &mut dyn AudioInputStreamSafe, samples: &[f32]) -> DataCallbackResult {
+        if !samples.is_empty() {
+            let _ = self.frames.try_send(MediaSample {
+                duration: Duration::from_secs_f64(samples.len() as f64 / PLAYBACK_SAMPLE_RATE as f64),
+                data: self.pool.encode(samples),
+                ..Default::default()
+            });
+        }
+        DataCallbackResult::Continue
+    }
+}
+
+struct Playback {
+    samples: std_mpsc::Receiver<Bytes>,
+    pending: VecDeque<f32>,
+    latency: LatencyTracker,
+}
+
+impl AudioOutputCallback for Playback {
+    type FrameType = (f32, Mono);
+
+    fn on_audio_ready(&mut self, _stream: &mut dyn AudioOutputStreamSafe, out: &mut [f32]) -> DataCallbackResult {
+        while let Ok(payload) = self.samples.try_recv() {
+            self.pending.extend(frame_samples(&payload));
+        }
+        let overflow = self.pending.len().saturating_sub(MAX_PLAYBACK_BUFFER);
+        self.pending.drain(..overflow);
+        self.latency.set_buffered(self.pending.len(), PLAYBACK_SAMPLE_RATE as f64);
+        for sample in out.iter_mut() {
+            // Silence while nothing has arrived
+            *sample = self.pending.pop_front().unwrap_or(0.0);
+        }
+        DataCallbackResult::Continue
+    }
+}
+
+impl AudioBackend for OboeBackend {
+    fn name(&self) -> &'static str {
+        "oboe"
+    }
+
+    fn latency(&self) -> AudioLatency {
+        self.latency.get()
+    }
+
+    fn set_voice_processing(&self, enabled: bool) {
+        self.voice_processing.store(enabled, Ordering::Relaxed);
+    }
+
+    fn start_capture(&self, track: Arc<AudioTrack>) -> Result<AudioStreamHandle> {
+        let (frame_tx, mut frame_rx) = mpsc::channel::<MediaSample>(64);
+
+        // The data callback runs on oboe's realtime thread and must not block
+        let writer = tokio::spawn(async move {
+            while let Some(sample) = frame_rx.recv().await {
+                if let Err(e) = track.write_sample(&sample).await {
+                    eprintln!("Failed to write audio sample: {}", e);
+                }
+            }
+        });
+
+        // VoiceCommunication turns on the platform's echo canceller and noise
+        // suppressor; Unprocessed is the raw mic, for music mode
+        let preset = if self.voice_processing.load(Ordering::Relaxed) {
+            InputPreset::VoiceCommunication
+        } else {
+            InputPreset::Unprocessed
+        };
+        let latency = self.latency.clone();
+        let handle = run_stream_thread("oboe-capture", move || {
+            let mut stream = AudioStreamBuilder::default()
+                .set_performance_mode(PerformanceMode::LowLatency)
+                .set_sharing_mode(SharingMode::Shared)
+                .set_sample_rate(PLAYBACK_SAMPLE_RATE as i32)
+                .set_input_preset(preset)
+                .set_input()
+                .set_format::<f32>()
+                .set_channel_count::<Mono>()
+                .set_callback(Capture { frames: frame_tx, pool: FramePool::default() })
+                .open_stream()
+                .map_err(oboe_error)?;
+            latency.set_capture(burst_duration(stream.get_frames_per_burst()));
+            stream.start().map_err(oboe_error)?;
+            Ok(stream)
+        })?;
+
+        Ok(AudioStreamHandle::new(move || {
+            drop(handle);
+            writer.abort();
+        }))
+    }
+
+    fn start_playback(&self, mut packets: broadcast::Receiver<Bytes>) -> Result<AudioStreamHandle> {
+        let (sample_tx, sample_rx) = std_mpsc::sync_channel::<Bytes>(64);
+
+        let decoder = tokio::spawn(async move {
+            loop {
+                match packets.recv().await {
+                    Ok(payload) => {
+                        let _ = sample_tx.try_send(payload);
+                    }
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+
+        // VoiceCommunication routes to the earpiece or headset and follows the call
+        // volume; Media is the loudspeaker at media volume
+        let (usage, content_type) = if self.voice_processing.load(Ordering::Relaxed) {
+            (Usage::VoiceCommunication, ContentType::Speech)
+        } else {
+            (Usage::Media, ContentType::Music)
+        };
+        let latency = self.latency.clone();
+        let handle = run_stream_thread("oboe-playback", move || {
+            let playback = Playback {
+                samples: sample_rx,
+                pending: VecDeque::new(),
+                latency: latency.clone(),
+            };
+            let mut stream = AudioStreamBuilder::default()
+                .set_performance_mode(PerformanceMode::LowLatency)
+                .set_sharing_mode(SharingMode::Shared)
+                .set_sample_rate(PLAYBACK_SAMPLE_RATE as i32)
+                .set_usage(usage)
+                .set_content_type(content_type)
+                .set_output()
+                .set_format::<f32>()
+                .set_channel_count::<Mono>()
+                .set_callback(playback)
+                .open_stream()
+                .map_err(oboe_error)?;
+            latency.set_playback(burst_duration(stream.get_frames_per_burst()));
+            stream.start().map_err(oboe_error)?;
+            Ok(stream)
+        })?;
+
+        Ok(AudioStreamHandle::new(move || {
+            drop(handle);
+            decoder.abort();
+        }))
+    }
+}
+
+fn burst_duration(frames: i32) -> Duration {
+    Duration::from_secs_f64(frames.max(0) as f64 / PLAYBACK_SAMPLE_RATE as f64)
+}
+
+fn oboe_error(error: oboe::Error) -> anyhow::Error {
+    anyhow!("oboe: {:?}", error)
+}
+
+// Each stream lives on its own thread until the handle is dropped, like the other
+// backends; setup errors are reported back before this returns
+fn run_stream_thread<F, S>(name: &str, open: F) -> Result<AudioStreamHandle>
+where
+    F: FnOnce() -> Result<S> + Send + 'static,
+    S: 'static,
+{
+    let (ready_tx, ready_rx) = std_mpsc::sync_channel(1);
+    let (quit_tx, quit_rx) = std_mpsc::channel::<()>();
+
+    std::thread::Builder::new().name(name.to_string()).spawn(move || match open() {
+        Ok(_stream) => {
+            let _ = ready_tx.send(Ok(()));
+            // Until the handle is dropped; the stream stops when it goes out of scope
+            let _ = quit_rx.recv();
+        }
+        Err(e) => {
+            let _ = ready_tx.send(Err(e));
+        }
+    })?;
+
+    ready_rx
+        .recv()
+        .map_err(|_| anyhow!("Audio thread {} exited during setup", name))??;
+
+    Ok(AudioStreamHandle::new(move || {
+        let _ = quit_tx.send(());
+    }))
+}
@@ -0,0 +1,247 @@
+use anyhow::{anyhow, Result};
+use bytes::Bytes;
+use pipewire as pw;
+use pw::spa;
+use pw::spa::param::audio::{AudioFormat, AudioInfoRaw};
+use pw::spa::pod::serialize::PodSerializer;
+use pw::spa::pod::{Object, Pod, Value};
+use pw::stream::{Stream, StreamFlags};
+use std::collections::VecDeque;
+use std::io::Cursor;
+use std::mem::size_of;
+use std::sync::mpsc as std_mpsc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc};
+use webrtc::media::Sample as MediaSample;
+
+use super::codec::PLAYBACK_SAMPLE_RATE;
+use super::{frame_samples, AudioBackend, AudioLatency, AudioStreamHandle, AudioTrack, FramePool, LatencyTracker};
+
+// Mono float at the playback rate both ways; PipeWire converts to whatever the device
+// wants, so the node never fights other clients for the hardware like ALSA does
+const CHANNELS: u32 = 1;
+// Capped so a stalled reader can't grow playback latency without bound
+const MAX_PLAYBACK_BUFFER: usize = PLAYBACK_SAMPLE_RATE as usize / 5;
+
+pub struct PipeWireBackend {
+    latency: LatencyTracker,
+    voice_processing: Arc<AtomicBool>,
+}
+
+impl PipeWireBackend {
+    pub fn new() -> Self {
+        Self {
+            latency: LatencyTracker::default(),
+            voice_processing: Arc::new(AtomicBool::new(true)),
+        }
+    }
+
+    // The session manager routes Communication streams through its echo-cancel and
+    // noise-suppression filters; Music streams are left alone
+    fn role(&self) -> &'static str {
+        if self.voice_processing.load(Ordering::Relaxed) {
+            "Communication"
+        } else {
+            "Music"
+        }
+    }
+}
+
+impl AudioBackend for PipeWireBackend {
+    fn name(&self) -> &'static str {
+        "pipewire"
+    }
+
+    // Only the queue depth; the graph's own latency is the session manager's business
+    fn latency(&self) -> AudioLatency {
+        self.latency.get()
+    }
+
+    fn set_voice_processing(&self, enabled: bool) {
+        self.voice_processing.store(enabled, Ordering::Relaxed);
+    }
+
+    fn start_capture(&self, track: Arc<AudioTrack>) -> Result<AudioStreamHandle> {
+        let (frame_tx, mut frame_rx) = mpsc::channel::<MediaSample>(64);
+
+        // The process callback runs on PipeWire's realtime thread and must not block
+        let writer = tokio::spawn(async move {
+            while let Some(sample) = frame_rx.recv().await {
+                if let Err(e) = track.write_sample(&sample).await {
+                    eprintln!("Failed to write audio sample: {}", e);
+                }
+            }
+        });
+
+        let role = self.role();
+        let handle = run_main_loop("pipewire-capture", move |core| {
+            let stream = Stream::new(core, "webrtc-client-capture", stream_properties("Capture", role))?;
+            let listener = stream
+                .add_local_listener_with_user_data((frame_tx, FramePool::default()))
+                .process(|stream, (frame_tx, pool)| {
+                    let Some(mut buffer) = stream.dequeue_buffer() else { return };
+                    let Some(data) = buffer.datas_mut().first_mut() else { return };
+                    let size = data.chunk().size() as usize;
+                    let Some(bytes) = data.data() else { return };
+                    // Already the wire format, so it's only copied into the pool
+                    let payload = &bytes[..size.min(bytes.len())];
+                    if payload.len() < size_of::<f32>() {
+                        return;
+                    }
+                    let _ = frame_tx.try_send(MediaSample {
+                        duration: Duration::from_secs_f64((payload.len() / size_of::<f32>()) as f64 / PLAYBACK_SAMPLE_RATE as f64),
+                        data: pool.copy(payload),
+                        ..Default::default()
+                    });
+                })
+                .register()?;
+            connect_stream(&stream, spa::utils::Direction::Input)?;
+            Ok((stream, listener))
+        })?;
+
+        Ok(AudioStreamHandle::new(move || {
+            drop(handle);
+            writer.abort();
+        }))
+    }
+
+    fn start_playback(&self, mut packets: broadcast::Receiver<Bytes>) -> Result<AudioStreamHandle> {
+        let (sample_tx, sample_rx) = std_mpsc::sync_channel::<Bytes>(64);
+
+        let decoder = tokio::spawn(async move {
+            loop {
+                match packets.recv().await {
+                    Ok(payload) => {
+                        let _ = sample_tx.try_send(payload);
+                    }
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+
+        let (latency, role) = (self.latency.clone(), self.role());
+        let handle = run_main_loop("pipewire-playback", move |core| {
+            let stream = Stream::new(core, "webrtc-client-playback", stream_properties("Playback", role))?;
+            let listener = stream
+                .add_local_listener_with_user_data((sample_rx, VecDeque::<f32>::new()))
+                .process(|stream, (sample_rx, pending)| {
+                    while let Ok(payload) = sample_rx.try_recv() {
+                        pending.extend(frame_samples(&payload));
+                    }
+                    let overflow = pending.len().saturating_sub(MAX_PLAYBACK_BUFFER);
+                    pending.drain(..overflow);
+                    latency.set_buffered(pending.len(), PLAYBACK_SAMPLE_RATE as f64);
+
+                    let Some(mut buffer) = stream.dequeue_buffer() else { return };
+                    let Some(data) = buffer.datas_mut().first_mut() else { return };
+                    let stride = size_of::<f32>() * CHANNELS as usize;
+                    let frames = match data.data() {
+                        Some(bytes) => {
+                            let frames = bytes.len() / stride;
+                            for chunk in bytes[..frames * stride].chunks_exact_mut(stride) {
+                                // Silence while nothing has arrived
+                                let sample = pending.pop_front().unwrap_or(0.0);
+                                chunk.copy_from_slice(&sample.to_le_bytes());
+                            }
+                            frames
+                        }
+                        None => 0,
+                    };
+                    let chunk = data.chunk_mut();
+                    *chunk.offset_mut() = 0;
+                    *chunk.stride_mut() = stride as i32;
+                    *chunk.size_mut() = (frames * stride) as u32;
+                })
+                .register()?;
+            connect_stream(&stream, spa::utils::Direction::Output)?;
+            Ok((stream, listener))
+        })?;
+
+        Ok(AudioStreamHandle::new(move || {
+            drop(handle);
+            decoder.abort();
+        }))
+    }
+}
+
+fn stream_properties(category: &str, role: &str) -> pw::properties::Properties {
+    pw::properties::properties! {
+        *pw::keys::MEDIA_TYPE => "Audio",
+        *pw::keys::MEDIA_CATEGORY => category,
+        // Lets the session manager apply call policies (ducking, echo-cancel routing)
+        *pw::keys::MEDIA_ROLE => role,
+        *pw::keys::APP_NAME => "webrtc-client",
+    }
+}
+
+fn connect_stream(stream: &Stream, direction: spa::utils::Direction) -> Result<()> {
+    let mut info = AudioInfoRaw::new();
+    info.set_format(AudioFormat::F32LE);
+    info.set_rate(PLAYBACK_SAMPLE_RATE);
+    info.set_channels(CHANNELS);
+    let format = Value::Object(Object {
+        type_: spa::utils::SpaTypes::ObjectParamFormat.as_raw(),
+        id: spa::param::ParamType::EnumFormat.as_raw(),
+        properties: info.into(),
+    });
+    let bytes = PodSerializer::serialize(Cursor::new(Vec::new()), &format)
+        .map_err(|e| anyhow!("Failed to build PipeWire format: {:?}", e))?
+        .0
+        .into_inner();
+    let pod = Pod::from_bytes(&bytes).ok_or_else(|| anyhow!("Invalid PipeWire format pod"))?;
+
+    stream.connect(
+        direction,
+        None,
+        StreamFlags::AUTOCONNECT | StreamFlags::MAP_BUFFERS | StreamFlags::RT_PROCESS,
+        &mut [pod],
+    )?;
+    Ok(())
+}
+
+// PipeWire objects are !Send and need their main loop running, so each stream gets a
+// thread that runs the loop until the handle asks it to quit. Like the cpal backend,
+// setup errors are reported back before this returns.
+fn run_main_loop<F, S>(name: &str, build: F) -> Result<AudioStreamHandle>
+where
+    F: FnOnce(&pw::core::Core) -> Result<S> + Send + 'static,
+    S: 'static,
+{
+    let (ready_tx, ready_rx) = std_mpsc::sync_channel(1);
+    let (quit_tx, quit_rx) = pw::channel::channel::<()>();
+
+    std::thread::Builder::new().name(name.to_string()).spawn(move || {
+        let setup = || -> Result<_> {
+            pw::init();
+            let main_loop = pw::main_loop::MainLoop::new(None)?;
+            let context = pw::context::Context::new(&main_loop)?;
+            let core = context.connect(None)?;
+            let stream = build(&core)?;
+            Ok((main_loop, context, core, stream))
+        };
+        match setup() {
+            Ok((main_loop, _context, _core, _stream)) => {
+                let _quit = quit_rx.attach(main_loop.loop_(), {
+                    let main_loop = main_loop.clone();
+                    move |_| main_loop.quit()
+                });
+                let _ = ready_tx.send(Ok(()));
+                main_loop.run();
+            }
+            Err(e) => {
+                let _ = ready_tx.send(Err(e));
+            }
+        }
+    })?;
+
+    ready_rx
+        .recv()
+        .map_err(|_| anyhow!("Audio thread {} exited during setup", name))??;
+
+    Ok(AudioStreamHandle::new(move || {
+        let _ = quit_tx.send(());
+    }))
+}
@@ -0,0 +1,149 @@
+use anyhow::Result;
+use std::fmt;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
+use tokio::net::UdpSocket;
+use tokio::sync::broadcast;
+
+use super::wav::to_i16;
+use super::AudioStreamHandle;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TapSource {
+    // Our own microphone, as sent
+    Local,
+    // One remote peer, decoded, before loudness normalization and panning
+    Remote(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct TapFrame {
+    pub source: TapSource,
+    pub sample_rate: u32,
+    // Interleaved f32 as the source produced it
+    pub samples: Arc<[f32]>,
+}
+
+impl TapFrame {
+    // Pipe and UDP format, one frame per write or datagram: source (0 local, 1 remote),
+    // peer id length and bytes, sample rate as u32 LE, then s16le samples
+    pub fn encode(&self) -> Vec<u8> {
+        let (kind, peer) = match self.source {
+            TapSource::Local => (0u8, ""),
+            TapSource::Remote(ref peer) => (1u8, peer.as_str()),
+        };
+        let peer = &peer.as_bytes()[..peer.len().min(u8::MAX as usize)];
+        let mut bytes = Vec::with_capacity(6 + peer.len() + self.samples.len() * 2);
+        bytes.push(kind);
+        bytes.push(peer.len() as u8);
+        bytes.extend_from_slice(peer);
+        bytes.extend_from_slice(&self.sample_rate.to_le_bytes());
+        for sample in self.samples.iter() {
+            bytes.extend_from_slice(&to_i16(*sample).to_le_bytes());
+        }
+        bytes
+    }
+}
+
+// Where tapped frames go. Callbacks run on a runtime task and should hand frames off
+// rather than do heavy work there.
+#[derive(Clone)]
+pub enum TapSink {
+    Callback(Arc<dyn Fn(&TapFrame) + Send + Sync>),
+    Pipe(PathBuf),
+    Udp(SocketAddr),
+}
+
+impl fmt::Debug for TapSink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TapSink::Callback(_) => write!(f, "Callback"),
+            TapSink::Pipe(path) => write!(f, "Pipe({})", path.display()),
+            TapSink::Udp(address) => write!(f, "Udp({})", address),
+        }
+    }
+}
+
+// Call audio for transcription, analytics and the like, in real time
+#[derive(Debug, Clone)]
+pub struct TapConfig {
+    pub sink: TapSink,
+    pub include_local: bool,
+}
+
+enum Writer {
+    Callback(Arc<dyn Fn(&TapFrame) + Send + Sync>),
+    Pipe(tokio::fs::File),
+    Udp(UdpSocket, SocketAddr),
+}
+
+impl Writer {
+    async fn open(sink: &TapSink) -> Result<Self> {
+        Ok(match sink {
+            TapSink::Callback(callback) => Writer::Callback(callback.clone()),
+            // Blocks until a reader opens the other end of a FIFO
+            TapSink::Pipe(path) => Writer::Pipe(tokio::fs::OpenOptions::new().write(true).create(true).open(path).await?),
+            TapSink::Udp(address) => {
+                let local: SocketAddr = if address.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" }.parse()?;
+                Writer::Udp(UdpSocket::bind(local).await?, *address)
+            }
+        })
+    }
+
+    async fn write(&mut self, frame: &TapFrame) -> Result<()> {
+        match self {
+            Writer::Callback(callback) => callback(frame),
+            Writer::Pipe(file) => file.write_all(&frame.encode()).await?,
+            // A consumer that isn't listening yet just misses frames
+            Writer::Udp(socket, address) => {
+                let _ = socket.send_to(&frame.encode(), *address).await;
+            }
+        }
+        Ok(())
+    }
+}
+
+// Forwards frames to the sink until dropped. A consumer that falls behind loses frames
+// rather than holding up the call.
+pub fn start_tap(
+    sink: TapSink,
+    mut remote: broadcast::Receiver<TapFrame>,
+    mut local: Option<broadcast::Receiver<TapFrame>>,
+) -> AudioStreamHandle {
+    let task = tokio::spawn(async move {
+        let mut writer = match Writer::open(&sink).await {
+            Ok(writer) => writer,
+            Err(e) => {
+                eprintln!("Failed to open audio tap {:?}: {}", sink, e);
+                return;
+            }
+        };
+        loop {
+            let received = tokio::select! {
+                frame = remote.recv() => frame,
+                Some(frame) = next_frame(&mut local) => frame,
+            };
+            let frame = match received {
+                Ok(frame) => frame,
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            };
+            if let Err(e) = writer.write(&frame).await {
+                eprintln!("Audio tap {:?} closed: {}", sink, e);
+                break;
+            }
+        }
+    });
+    AudioStreamHandle::new(move || task.abort())
+}
+
+async fn next_frame(
+    receiver: &mut Option<broadcast::Receiver<TapFrame>>,
+) -> Option<Result<TapFrame, broadcast::error::RecvError>> {
+    match receiver {
+        Some(receiver) => Some(receiver.recv().await),
+        None => std::future::pending().await,
+    }
+}
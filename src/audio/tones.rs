@@ -0,0 +1,202 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::TAU;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::time::interval;
+
+use super::codec::{resample, PLAYBACK_SAMPLE_RATE};
+use super::wav::read_wav;
+use super::{encode_frame, AudioBackend, AudioStreamHandle, SoundKind, FRAME_DURATION};
+use crate::call::CallCue;
+
+const FRAME_SAMPLES: usize = (PLAYBACK_SAMPLE_RATE as u128 * FRAME_DURATION.as_millis() / 1000) as usize;
+
+// Generated rather than shipped as files, so there are no assets to find at runtime
+fn tone(frequencies: &[f32], duration: Duration, level: f32) -> Vec<f32> {
+    let samples = (duration.as_secs_f32() * PLAYBACK_SAMPLE_RATE as f32) as usize;
+    // Short fades so the tone doesn't click on and off
+    let fade = PLAYBACK_SAMPLE_RATE as usize / 200;
+    (0..samples)
+        .map(|n| {
+            let t = n as f32 / PLAYBACK_SAMPLE_RATE as f32;
+            let sum: f32 = frequencies.iter().map(|f| (TAU * f * t).sin()).sum();
+            let envelope = (n.min(samples - n) as f32 / fade as f32).min(1.0);
+            sum / frequencies.len() as f32 * level * envelope
+        })
+        .collect()
+}
+
+fn silence(duration: Duration) -> Vec<f32> {
+    vec![0.0; (duration.as_secs_f32() * PLAYBACK_SAMPLE_RATE as f32) as usize]
+}
+
+// One ring cadence: two short bursts, then a pause
+pub fn ringtone() -> Vec<f32> {
+    let mut samples = tone(&[440.0, 480.0], Duration::from_millis(400), 0.4);
+    samples.extend(silence(Duration::from_millis(200)));
+    samples.extend(tone(&[440.0, 480.0], Duration::from_millis(400), 0.4));
+    samples.extend(silence(Duration::from_millis(2000)));
+    samples
+}
+
+// The ringing tone a caller hears, one cadence of it, in the conventions people know
+// from their phones
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RingbackStyle {
+    // 440+480 Hz, 2 s on, 4 s off
+    #[default]
+    NorthAmerica,
+    // 425 Hz, 1 s on, 4 s off
+    Europe,
+    // 400+450 Hz, two short bursts, then 2 s off
+    Uk,
+}
+
+impl RingbackStyle {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RingbackStyle::NorthAmerica => "north_america",
+            RingbackStyle::Europe => "europe",
+            RingbackStyle::Uk => "uk",
+        }
+    }
+}
+
+pub fn ringback(style: RingbackStyle) -> Vec<f32> {
+    match style {
+        RingbackStyle::NorthAmerica => {
+            let mut samples = tone(&[440.0, 480.0], Duration::from_millis(2000), 0.3);
+            samples.extend(silence(Duration::from_millis(4000)));
+            samples
+        }
+        RingbackStyle::Europe => {
+            let mut samples = tone(&[425.0], Duration::from_millis(1000), 0.3);
+            samples.extend(silence(Duration::from_millis(4000)));
+            samples
+        }
+        RingbackStyle::Uk => {
+            let mut samples = tone(&[400.0, 450.0], Duration::from_millis(400), 0.3);
+            samples.extend(silence(Duration::from_millis(200)));
+            samples.extend(tone(&[400.0, 450.0], Duration::from_millis(400), 0.3));
+            samples.extend(silence(Duration::from_millis(2000)));
+            samples
+        }
+    }
+}
+
+// Everyone declined: 480+620 Hz, half a second on and off, for a few seconds
+pub fn busy() -> Vec<f32> {
+    let mut samples = Vec::new();
+    for _ in 0..4 {
+        samples.extend(tone(&[480.0, 620.0], Duration::from_millis(500), 0.3));
+        samples.extend(silence(Duration::from_millis(500)));
+    }
+    samples
+}
+
+// The call couldn't be put through: the rising three-tone intercept signal
+pub fn failed() -> Vec<f32> {
+    let mut samples = Vec::new();
+    for frequency in [950.0, 1400.0, 1800.0] {
+        samples.extend(tone(&[frequency], Duration::from_millis(330), 0.3));
+    }
+    samples
+}
+
+// The tones an outgoing call plays while it dials and if it doesn't go through
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CallTones {
+    pub enabled: bool,
+    pub ringback: RingbackStyle,
+    // WAV files to play instead of the built-in tones
+    pub ringback_sound: Option<PathBuf>,
+    pub busy_sound: Option<PathBuf>,
+    pub failed_sound: Option<PathBuf>,
+}
+
+impl Default for CallTones {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            ringback: RingbackStyle::default(),
+            ringback_sound: None,
+            busy_sound: None,
+            failed_sound: None,
+        }
+    }
+}
+
+impl CallTones {
+    // A custom sound that can't be read falls back to the built-in one
+    pub fn sound(&self, cue: CallCue) -> Vec<f32> {
+        let custom = match cue {
+            CallCue::Ringback => &self.ringback_sound,
+            CallCue::Busy => &self.busy_sound,
+            CallCue::Failed => &self.failed_sound,
+        };
+        custom
+            .as_deref()
+            .and_then(|path| load(path).map_err(|e| eprintln!("Failed to load {}: {}", path.display(), e)).ok())
+            .unwrap_or_else(|| match cue {
+                CallCue::Ringback => ringback(self.ringback),
+                CallCue::Busy => busy(),
+                CallCue::Failed => failed(),
+            })
+    }
+}
+
+// Incoming chat and the like
+pub fn notification() -> Vec<f32> {
+    let mut samples = tone(&[880.0], Duration::from_millis(80), 0.3);
+    samples.extend(tone(&[1320.0], Duration::from_millis(120), 0.3));
+    samples
+}
+
+// Someone came into the room: a short rising pair
+pub fn joined() -> Vec<f32> {
+    let mut samples = tone(&[660.0], Duration::from_millis(90), 0.25);
+    samples.extend(tone(&[990.0], Duration::from_millis(110), 0.25));
+    samples
+}
+
+// And left it: the same pair falling
+pub fn left() -> Vec<f32> {
+    let mut samples = tone(&[990.0], Duration::from_millis(90), 0.25);
+    samples.extend(tone(&[660.0], Duration::from_millis(110), 0.25));
+    samples
+}
+
+// A user's own WAV file in place of a built-in sound
+pub fn load(path: &Path) -> Result<Vec<f32>> {
+    let (sample_rate, samples) = read_wav(path)?;
+    Ok(resample(&samples, sample_rate, PLAYBACK_SAMPLE_RATE))
+}
+
+// Plays `sound` on the alert output, over and over if `repeat`, until the handle is
+// dropped
+pub fn play_alert(backend: &Arc<dyn AudioBackend>, sound: Vec<f32>, repeat: bool) -> Result<AudioStreamHandle> {
+    let (frames, packets) = broadcast::channel(64);
+    let playback = backend.start_playback_for(SoundKind::Alert, packets)?;
+    let task = tokio::spawn(async move {
+        // Stops with the task, whether it finished or was aborted
+        let _playback = playback;
+        let mut ticker = interval(FRAME_DURATION);
+        loop {
+            for frame in sound.chunks(FRAME_SAMPLES) {
+                ticker.tick().await;
+                let _ = frames.send(encode_frame(frame));
+            }
+            if !repeat {
+                break;
+            }
+        }
+        // Let the device play out what it has buffered
+        tokio::time::sleep(FRAME_DURATION * 5).await;
+    });
+    Ok(AudioStreamHandle::new(move || task.abort()))
+}
@@ -0,0 +1,275 @@
+use async_trait::async_trait;
+use std::any::Any;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::{broadcast, Mutex};
+use webrtc::media::Sample;
+use webrtc::rtp::header::Header;
+use webrtc::rtp::packet::Packet;
+use webrtc::rtp_transceiver::rtp_codec::{RTCRtpCodecParameters, RTPCodecType};
+use webrtc::track::track_local::{TrackLocal, TrackLocalContext, TrackLocalWriter};
+
+use super::codec::{resample, AudioCodec, StreamEncoder, PLAYBACK_SAMPLE_RATE};
+use super::tap::{TapFrame, TapSource};
+use super::{decode_frame_into, FramePool, InputMeter};
+use crate::latency::LatencyProbe;
+use crate::plugins;
+use crate::red::{RedEncoder, RedFormat};
+
+// Outgoing audio track that binds to whichever supported codec the peer negotiated
+// and encodes frames for it, so a peer without Opus still gets G.711 audio.
+// Backends write frames in the internal format, exactly as to a static sample track.
+pub struct AudioTrack {
+    id: String,
+    stream_id: String,
+    bindings: Mutex<Vec<Binding>>,
+    latency_probe: LatencyProbe,
+    input_meter: InputMeter,
+    taps: broadcast::Sender<TapFrame>,
+    // Queued by announce, at PLAYBACK_SAMPLE_RATE
+    announcements: std::sync::Mutex<VecDeque<f32>>,
+    muted: AtomicBool,
+    redundancy: AtomicBool,
+    scratch: std::sync::Mutex<FrameScratch>,
+}
+
+// Reused from frame to frame so writing one doesn't allocate
+#[derive(Default)]
+struct FrameScratch {
+    samples: Vec<f32>,
+    pool: FramePool,
+}
+
+struct Binding {
+    context_id: String,
+    ssrc: u32,
+    payload_type: u8,
+    codec: AudioCodec,
+    encoder: StreamEncoder,
+    // Set when the peer negotiated RED for this codec
+    red_payload_type: Option<u8>,
+    red: RedEncoder,
+    write_stream: Arc<dyn TrackLocalWriter + Send + Sync>,
+    sequence_number: u16,
+    timestamp: u32,
+}
+
+impl AudioTrack {
+    pub fn new(id: String, stream_id: String) -> Self {
+        Self::with_latency_probe(id, stream_id, LatencyProbe::default())
+    }
+
+    // Encode timings and frame sizes are reported to `latency_probe`
+    pub fn with_latency_probe(id: String, stream_id: String, latency_probe: LatencyProbe) -> Self {
+        Self {
+            id,
+            stream_id,
+            bindings: Mutex::new(Vec::new()),
+            latency_probe,
+            input_meter: InputMeter::default(),
+            taps: broadcast::channel(64).0,
+            announcements: std::sync::Mutex::new(VecDeque::new()),
+            muted: AtomicBool::new(false),
+            redundancy: AtomicBool::new(false),
+            scratch: std::sync::Mutex::new(FrameScratch::default()),
+        }
+    }
+
+    pub fn latency_probe(&self) -> LatencyProbe {
+        self.latency_probe.clone()
+    }
+
+    // Every frame written, for audio taps
+    pub fn subscribe_frames(&self) -> broadcast::Receiver<TapFrame> {
+        self.taps.subscribe()
+    }
+
+    // Level and clipping/silence of what the mic delivers, whichever backend it is
+    pub fn input_meter(&self) -> InputMeter {
+        self.input_meter.clone()
+    }
+
+    // Mixes `samples` into the outgoing audio as the next frames are written, after
+    // anything already queued. For greetings and other announcements.
+    pub fn announce(&self, samples: &[f32], sample_rate: u32) {
+        if let Ok(mut queue) = self.announcements.lock() {
+            queue.extend(resample(samples, sample_rate, PLAYBACK_SAMPLE_RATE));
+        }
+    }
+
+    // False when nothing is queued and the frame goes out untouched
+    fn mix_announcement(&self, samples: &mut [f32], sample_rate: u32) -> bool {
+        let Ok(mut queue) = self.announcements.lock() else { return false };
+        if queue.is_empty() {
+            return false;
+        }
+        let wanted = (samples.len() as u64 * PLAYBACK_SAMPLE_RATE as u64 / sample_rate as u64) as usize;
+        let chunk: Vec<f32> = queue.drain(..wanted.min(queue.len())).collect();
+        for (sample, announced) in samples.iter_mut().zip(resample(&chunk, PLAYBACK_SAMPLE_RATE, sample_rate)) {
+            *sample = (*sample + announced).clamp(-1.0, 1.0);
+        }
+        true
+    }
+
+    // Sends silence instead of the microphone; the input meter keeps measuring so a
+    // muted speaker can be told
+    pub fn set_muted(&self, muted: bool) {
+        self.muted.store(muted, Ordering::Relaxed);
+    }
+
+    pub fn is_muted(&self) -> bool {
+        self.muted.load(Ordering::Relaxed)
+    }
+
+    // Wraps frames in RED (RFC 2198) for peers that negotiated it, repeating the previous
+    // ones in every packet. Peers without RED keep getting plain frames.
+    pub fn set_redundancy(&self, redundancy: bool) {
+        self.redundancy.store(redundancy, Ordering::Relaxed);
+    }
+
+    pub fn redundancy(&self) -> bool {
+        self.redundancy.load(Ordering::Relaxed)
+    }
+
+    // Whether switching redundancy on would change anything: some peer negotiated RED
+    // for the codec it gets
+    pub async fn carries_redundancy(&self) -> bool {
+        self.bindings.lock().await.iter().any(|binding| binding.red_payload_type.is_some())
+    }
+
+    // The codec the first peer connection settled on, once negotiation is done
+    pub async fn codec(&self) -> Option<AudioCodec> {
+        self.bindings.lock().await.first().map(|binding| binding.codec)
+    }
+
+    pub async fn write_sample(&self, sample: &Sample) -> webrtc::error::Result<()> {
+        let frame_samples = sample.data.len() / 4;
+        let seconds = sample.duration.as_secs_f64();
+        if frame_samples == 0 || seconds <= 0.0 {
+            return Ok(());
+        }
+        let sample_rate = (frame_samples as f64 / seconds).round() as u32;
+        let data = {
+            // A std lock, so it has to be released before the first await below
+            let mut scratch = self.scratch.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            let FrameScratch { samples, pool } = &mut *scratch;
+            decode_frame_into(&sample.data, samples);
+            // Before the announcement goes in: the meter is about the microphone
+            self.input_meter.measure(samples, Instant::now());
+            let muted = self.is_muted();
+            if muted {
+                samples.fill(0.0);
+            }
+            let processed = plugins::host().process_audio(&TapSource::Local, samples, sample_rate);
+            let data = if self.mix_announcement(samples, sample_rate) || processed || muted {
+                pool.encode(samples)
+            } else {
+                sample.data.clone()
+            };
+            if self.taps.receiver_count() > 0 {
+                let _ = self.taps.send(TapFrame {
+                    source: TapSource::Local,
+                    sample_rate,
+                    samples: samples.as_slice().into(),
+                });
+            }
+            data
+        };
+
+        let redundancy = self.redundancy();
+        let mut bindings = self.bindings.lock().await;
+        for binding in bindings.iter_mut() {
+            let encode_started = LatencyProbe::mark();
+            let payload = binding.encoder.encode(binding.codec, &data, sample_rate);
+            self.latency_probe.record_encode(encode_started, sample.duration);
+            let (payload_type, payload) = match binding.red_payload_type.filter(|_| redundancy) {
+                Some(red_payload_type) => (red_payload_type, binding.red.encode(binding.payload_type, binding.timestamp, payload)),
+                None => {
+                    binding.red.reset();
+                    (binding.payload_type, payload)
+                }
+            };
+            let packet = Packet {
+                header: Header {
+                    version: 2,
+                    payload_type,
+                    sequence_number: binding.sequence_number,
+                    timestamp: binding.timestamp,
+                    ssrc: binding.ssrc,
+                    ..Default::default()
+                },
+                payload,
+            };
+            binding.sequence_number = binding.sequence_number.wrapping_add(1);
+            binding.timestamp = binding
+                .timestamp
+                .wrapping_add((binding.codec.clock_rate() as f64 * seconds).round() as u32);
+            // One failed peer connection must not stop audio to the others
+            if let Err(e) = binding.write_stream.write_rtp(&packet).await {
+                eprintln!("Failed to write audio to {}: {}", binding.context_id, e);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl TrackLocal for AudioTrack {
+    async fn bind(&self, t: &TrackLocalContext) -> webrtc::error::Result<RTCRtpCodecParameters> {
+        // Negotiated codecs arrive in preference order
+        let (parameters, codec) = t
+            .codec_parameters()
+            .iter()
+            .find_map(|parameters| {
+                AudioCodec::from_mime(&parameters.capability.mime_type).map(|codec| (parameters.clone(), codec))
+            })
+            .ok_or(webrtc::error::Error::ErrUnsupportedCodec)?;
+        let write_stream = t
+            .write_stream()
+            .ok_or_else(|| webrtc::error::Error::new("track binding has no write stream".to_owned()))?;
+
+        let red_payload_type = RedFormat::negotiated(&t.codec_parameters())
+            .into_iter()
+            .find(|red| red.primary_payload_type == parameters.payload_type)
+            .map(|red| red.payload_type);
+
+        println!("Audio track bound with {}", codec.mime_type());
+        self.bindings.lock().await.push(Binding {
+            context_id: t.id(),
+            ssrc: t.ssrc(),
+            payload_type: parameters.payload_type,
+            codec,
+            encoder: StreamEncoder::default(),
+            red_payload_type,
+            red: RedEncoder::default(),
+            write_stream,
+            sequence_number: rand::random(),
+            timestamp: rand::random(),
+        });
+        Ok(parameters)
+    }
+
+    async fn unbind(&self, t: &TrackLocalContext) -> webrtc::error::Result<()> {
+        let id = t.id();
+        self.bindings.lock().await.retain(|binding| binding.context_id != id);
+        Ok(())
+    }
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn stream_id(&self) -> &str {
+        &self.stream_id
+    }
+
+    fn kind(&self) -> RTPCodecType {
+        RTPCodecType::Audio
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
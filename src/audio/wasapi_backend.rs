@@ -0,0 +1,198 @@
+use anyhow::{anyhow, Result};
+use bytes::Bytes;
+use std::collections::VecDeque;
+use std::mem::size_of;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc as std_mpsc;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc};
+use wasapi::{Direction, SampleType, ShareMode, WaveFormat};
+use webrtc::media::Sample as MediaSample;
+
+use super::codec::PLAYBACK_SAMPLE_RATE;
+use super::{frame_samples, AudioBackend, AudioLatency, AudioStreamHandle, AudioTrack, FramePool, LatencyTracker};
+
+const CHANNELS: usize = 1;
+const BLOCK_ALIGN: usize = size_of::<f32>() * CHANNELS;
+// Same cap as the other backends: a stalled reader must not turn into latency
+const MAX_PLAYBACK_BUFFER: usize = PLAYBACK_SAMPLE_RATE as usize / 5;
+// Event waits time out so the thread notices a stop request even on a dead device
+const EVENT_TIMEOUT_MS: u32 = 200;
+
+// WASAPI in exclusive mode: no Windows mixer, the device runs at its minimum period.
+// Other applications lose the device while a call is up.
+pub struct WasapiExclusiveBackend {
+    latency: LatencyTracker,
+}
+
+impl WasapiExclusiveBackend {
+    pub fn new() -> Self {
+        Self {
+            latency: LatencyTracker::default(),
+        }
+    }
+}
+
+impl AudioBackend for WasapiExclusiveBackend {
+    fn name(&self) -> &'static str {
+        "wasapi-exclusive"
+    }
+
+    fn latency(&self) -> AudioLatency {
+        self.latency.get()
+    }
+
+    fn start_capture(&self, track: Arc<AudioTrack>) -> Result<AudioStreamHandle> {
+        let (frame_tx, mut frame_rx) = mpsc::channel::<MediaSample>(64);
+
+        let writer = tokio::spawn(async move {
+            while let Some(sample) = frame_rx.recv().await {
+                if let Err(e) = track.write_sample(&sample).await {
+                    eprintln!("Failed to write audio sample: {}", e);
+                }
+            }
+        });
+
+        let latency = self.latency.clone();
+        let handle = run_stream_thread("wasapi-capture", Direction::Capture, move |client, event, stop, period| {
+            latency.set_capture(period);
+            let capture = client.get_audiocaptureclient().map_err(wasapi_error)?;
+            let mut pending = VecDeque::<u8>::new();
+            let mut pool = FramePool::default();
+            while !stop.load(Ordering::Relaxed) {
+                if event.wait_for_event(EVENT_TIMEOUT_MS).is_err() {
+                    continue;
+                }
+                capture.read_from_device_to_deque(&mut pending).map_err(wasapi_error)?;
+                let complete = pending.len() / BLOCK_ALIGN * BLOCK_ALIGN;
+                if complete == 0 {
+                    continue;
+                }
+                // Already the wire format, so it's only copied into the pool
+                let data = pool.copy(&pending.make_contiguous()[..complete]);
+                pending.drain(..complete);
+                let _ = frame_tx.try_send(MediaSample {
+                    duration: Duration::from_secs_f64((complete / size_of::<f32>()) as f64 / PLAYBACK_SAMPLE_RATE as f64),
+                    data,
+                    ..Default::default()
+                });
+            }
+            Ok(())
+        })?;
+
+        Ok(AudioStreamHandle::new(move || {
+            drop(handle);
+            writer.abort();
+        }))
+    }
+
+    fn start_playback(&self, mut packets: broadcast::Receiver<Bytes>) -> Result<AudioStreamHandle> {
+        let (sample_tx, sample_rx) = std_mpsc::sync_channel::<Bytes>(64);
+
+        let decoder = tokio::spawn(async move {
+            loop {
+                match packets.recv().await {
+                    Ok(payload) => {
+                        let _ = sample_tx.try_send(payload);
+                    }
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+
+        let latency = self.latency.clone();
+        let handle = run_stream_thread("wasapi-playback", Direction::Render, move |client, event, stop, period| {
+            latency.set_playback(period);
+            let render = client.get_audiorenderclient().map_err(wasapi_error)?;
+            let mut pending = VecDeque::<f32>::new();
+            let mut data = Vec::new();
+            while !stop.load(Ordering::Relaxed) {
+                if event.wait_for_event(EVENT_TIMEOUT_MS).is_err() {
+                    continue;
+                }
+                while let Ok(payload) = sample_rx.try_recv() {
+                    pending.extend(frame_samples(&payload));
+                }
+                let overflow = pending.len().saturating_sub(MAX_PLAYBACK_BUFFER);
+                pending.drain(..overflow);
+                latency.set_buffered(pending.len(), PLAYBACK_SAMPLE_RATE as f64);
+
+                let frames = client.get_available_space_in_frames().map_err(wasapi_error)? as usize;
+                data.clear();
+                for _ in 0..frames {
+                    // Silence while nothing has arrived
+                    data.extend_from_slice(&pending.pop_front().unwrap_or(0.0).to_le_bytes());
+                }
+                render
+                    .write_to_device(frames, BLOCK_ALIGN, &data, None)
+                    .map_err(wasapi_error)?;
+            }
+            Ok(())
+        })?;
+
+        Ok(AudioStreamHandle::new(move || {
+            drop(handle);
+            decoder.abort();
+        }))
+    }
+}
+
+fn wasapi_error(e: impl std::fmt::Display) -> anyhow::Error {
+    anyhow!("WASAPI: {}", e)
+}
+
+// COM objects stay on the thread that created them, so each stream gets its own thread
+// that opens the default device in exclusive mode and then runs `pump` until stopped.
+// Setup errors (device busy, format not supported exclusively) are reported back before
+// this returns, like the cpal backend does.
+fn run_stream_thread<F>(name: &str, direction: Direction, pump: F) -> Result<AudioStreamHandle>
+where
+    F: FnOnce(&wasapi::AudioClient, &wasapi::Handle, &AtomicBool, Duration) -> Result<()> + Send + 'static,
+{
+    let (ready_tx, ready_rx) = std_mpsc::sync_channel(1);
+    let stop = Arc::new(AtomicBool::new(false));
+    let thread_stop = stop.clone();
+    let thread_name = name.to_string();
+
+    std::thread::Builder::new().name(thread_name.clone()).spawn(move || {
+        let setup = || -> Result<_> {
+            wasapi::initialize_mta().ok().map_err(wasapi_error)?;
+            let device = wasapi::get_default_device(&direction).map_err(wasapi_error)?;
+            let mut client = device.get_iaudioclient().map_err(wasapi_error)?;
+            let format = WaveFormat::new(32, 32, &SampleType::Float, PLAYBACK_SAMPLE_RATE as usize, CHANNELS, None);
+            client
+                .is_supported(&format, &ShareMode::Exclusive)
+                .map_err(|e| anyhow!("Device does not support {} Hz mono float in exclusive mode: {}", PLAYBACK_SAMPLE_RATE, e))?;
+            // Periods are in 100 ns units; exclusive mode can run at the device minimum
+            let (_, min_period) = client.get_periods().map_err(wasapi_error)?;
+            client
+                .initialize_client(&format, min_period, &direction, &ShareMode::Exclusive, false)
+                .map_err(wasapi_error)?;
+            let event = client.set_get_eventhandle().map_err(wasapi_error)?;
+            client.start_stream().map_err(wasapi_error)?;
+            Ok((client, event, Duration::from_nanos(min_period as u64 * 100)))
+        };
+        match setup() {
+            Ok((client, event, period)) => {
+                let _ = ready_tx.send(Ok(()));
+                if let Err(e) = pump(&client, &event, &thread_stop, period) {
+                    eprintln!("{} stopped: {}", thread_name, e);
+                }
+                let _ = client.stop_stream();
+            }
+            Err(e) => {
+                let _ = ready_tx.send(Err(e));
+            }
+        }
+    })?;
+
+    ready_rx
+        .recv()
+        .map_err(|_| anyhow!("Audio thread {} exited during setup", name))??;
+
+    Ok(AudioStreamHandle::new(move || {
+        stop.store(true, Ordering::Relaxed);
+    }))
+}
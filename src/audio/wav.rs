@@ -0,0 +1,106 @@
+use anyhow::{anyhow, Result};
+use std::fs::File;
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+use std::path::Path;
+
+const HEADER_LEN: u32 = 44;
+
+// 16-bit mono PCM header for `samples` samples
+pub fn wav_header(sample_rate: u32, samples: u32) -> Vec<u8> {
+    let data_len = samples * 2;
+    let mut header = Vec::with_capacity(HEADER_LEN as usize);
+    header.extend_from_slice(b"RIFF");
+    header.extend_from_slice(&(HEADER_LEN - 8 + data_len).to_le_bytes());
+    header.extend_from_slice(b"WAVEfmt ");
+    header.extend_from_slice(&16u32.to_le_bytes());
+    // PCM, one channel
+    header.extend_from_slice(&1u16.to_le_bytes());
+    header.extend_from_slice(&1u16.to_le_bytes());
+    header.extend_from_slice(&sample_rate.to_le_bytes());
+    header.extend_from_slice(&(sample_rate * 2).to_le_bytes());
+    header.extend_from_slice(&2u16.to_le_bytes());
+    header.extend_from_slice(&16u16.to_le_bytes());
+    header.extend_from_slice(b"data");
+    header.extend_from_slice(&data_len.to_le_bytes());
+    header
+}
+
+pub fn to_i16(sample: f32) -> i16 {
+    (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16
+}
+
+// Streams samples to a WAV file; the header gets its lengths on finish
+pub struct WavWriter {
+    file: BufWriter<File>,
+    sample_rate: u32,
+    samples: u32,
+}
+
+impl WavWriter {
+    pub fn create(path: &Path, sample_rate: u32) -> Result<Self> {
+        let mut file = BufWriter::new(File::create(path)?);
+        file.write_all(&wav_header(sample_rate, 0))?;
+        Ok(Self {
+            file,
+            sample_rate,
+            samples: 0,
+        })
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    pub fn write(&mut self, samples: &[f32]) -> Result<()> {
+        for sample in samples {
+            self.file.write_all(&to_i16(*sample).to_le_bytes())?;
+        }
+        self.samples += samples.len() as u32;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<()> {
+        self.file.seek(SeekFrom::Start(0))?;
+        self.file.write_all(&wav_header(self.sample_rate, self.samples))?;
+        self.file.flush()?;
+        Ok(())
+    }
+}
+
+// 16-bit PCM WAV as mono samples and the file's rate; other channel counts are downmixed
+pub fn read_wav(path: &Path) -> Result<(u32, Vec<f32>)> {
+    let bytes = std::fs::read(path)?;
+    if bytes.len() < 12 || &bytes[..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+        return Err(anyhow!("{} is not a WAV file", path.display()));
+    }
+    let mut format = None;
+    let mut offset = 12;
+    while offset + 8 <= bytes.len() {
+        let id = &bytes[offset..offset + 4];
+        let len = u32::from_le_bytes(bytes[offset + 4..offset + 8].try_into()?) as usize;
+        let body = &bytes[offset + 8..(offset + 8 + len).min(bytes.len())];
+        if id == b"fmt " && body.len() >= 16 {
+            let kind = u16::from_le_bytes([body[0], body[1]]);
+            let channels = u16::from_le_bytes([body[2], body[3]]) as usize;
+            let sample_rate = u32::from_le_bytes(body[4..8].try_into()?);
+            let bits = u16::from_le_bytes([body[14], body[15]]);
+            if kind != 1 || bits != 16 || channels == 0 {
+                return Err(anyhow!("{} is not 16-bit PCM", path.display()));
+            }
+            format = Some((channels, sample_rate));
+        } else if id == b"data" {
+            let (channels, sample_rate) = format.ok_or_else(|| anyhow!("{} has no format chunk", path.display()))?;
+            let samples = body
+                .chunks_exact(2 * channels)
+                .map(|frame| {
+                    let sum: f32 = frame.chunks_exact(2).map(|s| i16::from_le_bytes([s[0], s[1]]) as f32 / i16::MAX as f32).sum();
+                    sum / channels as f32
+                })
+                .collect();
+            return Ok((sample_rate, samples));
+        }
+        // Chunks are padded to an even length
+        offset += 8 + len + len % 2;
+    }
+    Err(anyhow!("{} has no audio data", path.display()))
+}
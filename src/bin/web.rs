@@ -0,0 +1,464 @@
+// Browser front end: join a room, call a peer and talk. The browser does the media;
+// signaling is the same protocol the desktop client speaks, so the two can call each
+// other. Open the page with ?server=<ws url>&room=<room id> (the query of an invite
+// link) to fill in the form.
+#![allow(non_snake_case)]
+
+use anyhow::{anyhow, Result};
+use dioxus::prelude::*;
+use futures::StreamExt;
+use std::rc::Rc;
+use url::Url;
+use webrtc_client::call::{format_duration, CallSession, IncomingCall};
+use webrtc_client::i18n::{self, tr, tr_args};
+use webrtc_client::identity::Identity;
+use webrtc_client::metrics::ConnectionQuality;
+use webrtc_client::protocol::{check_protocol_version, SignalingMessage, MIN_PROTOCOL_VERSION, PROTOCOL_VERSION};
+use webrtc_client::server_config::IceServerConfig;
+use webrtc_client::web::{WebCall, WebSignalingClient};
+
+struct WebState {
+    signaling: Option<WebSignalingClient>,
+    // Generated per page load; there is nowhere to keep a key between sessions
+    identity: Identity,
+    server: String,
+    room_id: String,
+    peers: Vec<String>,
+    ice_servers: Vec<IceServerConfig>,
+    call: Option<Rc<WebCall>>,
+    session: Option<CallSession>,
+    remote_peer: Option<String>,
+    incoming_call: Option<IncomingCall>,
+    quality: Option<ConnectionQuality>,
+    is_muted: bool,
+    error: Option<String>,
+}
+
+impl WebState {
+    fn new() -> Self {
+        let (server, room_id) = page_invite();
+        Self {
+            signaling: None,
+            identity: Identity::generate(),
+            server,
+            room_id,
+            peers: Vec::new(),
+            ice_servers: vec![IceServerConfig {
+                urls: vec!["stun:stun.l.google.com:19302".to_string()],
+                ..Default::default()
+            }],
+            call: None,
+            session: None,
+            remote_peer: None,
+            incoming_call: None,
+            quality: None,
+            is_muted: false,
+            error: None,
+        }
+    }
+
+    fn peer_id(&self) -> String {
+        self.identity.peer_id()
+    }
+
+    fn send(&self, msg: SignalingMessage) -> Result<()> {
+        self.signaling
+            .as_ref()
+            .ok_or_else(|| anyhow!("Not connected"))?
+            .send(msg)
+    }
+
+    fn teardown(&mut self) {
+        // Dropping the call closes the connection and releases the microphone
+        self.call = None;
+        self.session = None;
+        self.remote_peer = None;
+        self.quality = None;
+        self.is_muted = false;
+    }
+}
+
+fn main() {
+    let language = web_sys::window().and_then(|window| window.navigator().language());
+    i18n::init(language.as_deref().and_then(|language| language.split('-').next()));
+    dioxus_web::launch(App);
+}
+
+// Server and room from the page's query string, in invite link form
+fn page_invite() -> (String, String) {
+    let mut server = "ws://127.0.0.1:8080".to_string();
+    let mut room = "default".to_string();
+    let href = web_sys::window().and_then(|window| window.location().href().ok());
+    if let Some(url) = href.and_then(|href| Url::parse(&href).ok()) {
+        for (key, value) in url.query_pairs() {
+            match key.as_ref() {
+                "server" => server = value.into_owned(),
+                "room" => room = value.into_owned(),
+                _ => {}
+            }
+        }
+    }
+    (server, room)
+}
+
+fn report(state: &UseRef<WebState>, result: Result<()>) {
+    if let Err(e) = result {
+        state.write().error = Some(e.to_string());
+    }
+}
+
+async fn connect(state: UseRef<WebState>) -> Result<()> {
+    let (server, room_id, identity) = state.with(|s| (s.server.clone(), s.room_id.clone(), s.identity.clone()));
+    let (mut signaling, mut messages) = WebSignalingClient::connect(&server).await?;
+    let peer_id = identity.peer_id();
+    signaling.set_identity(identity);
+    signaling.send(SignalingMessage::Join {
+        room_id,
+        peer_id,
+        protocol_version: PROTOCOL_VERSION,
+    })?;
+    state.with_mut(|s| {
+        s.signaling = Some(signaling);
+        s.error = None;
+    });
+
+    while let Some(msg) = messages.next().await {
+        let result = handle_message(&state, msg).await;
+        report(&state, result);
+    }
+    state.with_mut(|s| {
+        s.teardown();
+        s.signaling = None;
+        s.peers.clear();
+    });
+    Err(anyhow!("Signaling connection closed"))
+}
+
+async fn handle_message(state: &UseRef<WebState>, msg: SignalingMessage) -> Result<()> {
+    let own_id = state.read().peer_id();
+    match msg {
+        SignalingMessage::PeerList { peers } => {
+            state.write().peers = peers.into_iter().filter(|peer| *peer != own_id).collect();
+        }
+        SignalingMessage::ServerConfig { config } => {
+            if !config.ice_servers.is_empty() {
+                state.write().ice_servers = config.ice_servers;
+            }
+        }
+        SignalingMessage::CallRequest { room_id, from_peer, to_peers, protocol_version } => {
+            if !to_peers.contains(&own_id) {
+                return Ok(());
+            }
+            if let Err(e) = check_protocol_version(&from_peer, protocol_version) {
+                state.read().send(SignalingMessage::ProtocolMismatch {
+                    from_peer: own_id,
+                    to_peer: from_peer,
+                    protocol_version: PROTOCOL_VERSION,
+                    min_protocol_version: MIN_PROTOCOL_VERSION,
+                })?;
+                return Err(e);
+            }
+            state.write().incoming_call = Some(IncomingCall { room_id, from_peer });
+        }
+        SignalingMessage::CallResponse { from_peer, accepted, .. } => {
+            if state.read().remote_peer.as_deref() != Some(from_peer.as_str()) {
+                return Ok(());
+            }
+            if !accepted {
+                state.write().teardown();
+                return Err(anyhow!("{} declined the call", from_peer));
+            }
+            // Callee accepted: we are the offerer
+            let Some(call) = state.read().call.clone() else { return Ok(()) };
+            let sdp = call.create_offer().await?;
+            let room_id = state.read().room_id.clone();
+            state.read().send(SignalingMessage::Offer {
+                room_id,
+                sdp,
+                from_peer: own_id,
+                to_peer: from_peer,
+            })?;
+        }
+        SignalingMessage::Offer { room_id, sdp, from_peer, .. } => {
+            let Some(call) = state.read().call.clone() else { return Ok(()) };
+            let answer = call.handle_offer(sdp).await?;
+            state.read().send(SignalingMessage::Answer {
+                room_id,
+                sdp: answer,
+                from_peer: own_id,
+                to_peer: from_peer,
+            })?;
+            mark_active(state);
+        }
+        SignalingMessage::Answer { sdp, .. } => {
+            let Some(call) = state.read().call.clone() else { return Ok(()) };
+            call.handle_answer(sdp).await?;
+            mark_active(state);
+        }
+        SignalingMessage::IceCandidate { candidate, from_peer, .. } => {
+            if state.read().remote_peer.as_deref() != Some(from_peer.as_str()) {
+                return Ok(());
+            }
+            let Some(call) = state.read().call.clone() else { return Ok(()) };
+            call.add_ice_candidate(candidate).await?;
+        }
+        SignalingMessage::EndCall { peer_id, .. } => {
+            if state.read().remote_peer.as_deref() == Some(peer_id.as_str()) {
+                state.write().teardown();
+            }
+        }
+        SignalingMessage::Error { message } => return Err(anyhow!(message)),
+        _ => {}
+    }
+    Ok(())
+}
+
+fn mark_active(state: &UseRef<WebState>) {
+    if let Some(session) = state.write().session.as_mut() {
+        session.mark_active();
+    }
+}
+
+// The peer connection for a call with `peer`, trickling our candidates to it
+async fn start_call(state: &UseRef<WebState>, peer: String) -> Result<Rc<WebCall>> {
+    let (signaling, room_id, own_id, ice_servers) = state.with(|s| (s.signaling.clone(), s.room_id.clone(), s.peer_id(), s.ice_servers.clone()));
+    let signaling = signaling.ok_or_else(|| anyhow!("Not connected"))?;
+    let to_peer = peer.clone();
+    let call = WebCall::start(&ice_servers, move |candidate| {
+        let _ = signaling.send(SignalingMessage::IceCandidate {
+            room_id: room_id.clone(),
+            candidate,
+            from_peer: own_id.clone(),
+            to_peer: to_peer.clone(),
+        });
+    })
+    .await?;
+    let call = Rc::new(call);
+    state.with_mut(|s| {
+        s.call = Some(call.clone());
+        s.session = Some(CallSession::new(s.room_id.clone(), vec![peer.clone()]));
+        s.remote_peer = Some(peer);
+    });
+    Ok(call)
+}
+
+async fn call_peer(state: UseRef<WebState>, peer: String) -> Result<()> {
+    start_call(&state, peer.clone()).await?;
+    let (room_id, own_id) = state.with(|s| (s.room_id.clone(), s.peer_id()));
+    state.read().send(SignalingMessage::CallRequest {
+        room_id,
+        from_peer: own_id,
+        to_peers: vec![peer],
+        protocol_version: PROTOCOL_VERSION,
+    })
+}
+
+async fn answer(state: UseRef<WebState>, call: IncomingCall, accepted: bool) -> Result<()> {
+    state.write().incoming_call = None;
+    if accepted {
+        start_call(&state, call.from_peer.clone()).await?;
+    }
+    let own_id = state.read().peer_id();
+    state.read().send(SignalingMessage::CallResponse {
+        room_id: call.room_id,
+        from_peer: own_id,
+        to_peer: call.from_peer,
+        accepted,
+    })
+}
+
+fn hang_up(state: &UseRef<WebState>) -> Result<()> {
+    let (room_id, peer_id) = state.with(|s| (s.room_id.clone(), s.peer_id()));
+    let result = state.read().send(SignalingMessage::EndCall { room_id, peer_id, to_peers: None });
+    state.write().teardown();
+    result
+}
+
+// window.setTimeout as a future
+async fn sleep(ms: i32) {
+    let promise = js_sys::Promise::new(&mut |resolve, _| {
+        if let Some(window) = web_sys::window() {
+            let _ = window.set_timeout_with_callback_and_timeout_and_arguments_0(&resolve, ms);
+        }
+    });
+    let _ = wasm_bindgen_futures::JsFuture::from(promise).await;
+}
+
+fn App(cx: Scope) -> Element {
+    let state = use_ref(cx, WebState::new);
+
+    // Quality once a second, which also keeps the call timer current
+    use_future(cx, (), |_| {
+        let state = state.clone();
+        async move {
+            loop {
+                sleep(1000).await;
+                let Some(call) = state.read().call.clone() else { continue };
+                match call.quality().await {
+                    Ok(quality) => state.write().quality = Some(quality),
+                    Err(e) => web_sys::console::warn_1(&format!("Failed to read call stats: {}", e).into()),
+                }
+            }
+        }
+    });
+
+    let is_connected = state.read().signaling.is_some();
+    let in_call = state.read().call.is_some();
+
+    cx.render(rsx! {
+        style { include_str!("../style.css") }
+        div { class: "app",
+            h1 { {tr("app.title")} }
+
+            {state.read().error.clone().map(|error| rsx!(
+                div { class: "status status-error",
+                    role: "alert",
+                    {tr_args("status.error", &[("error", &error)])}
+                }
+            ))}
+
+            {state.read().session.clone().map(|session| {
+                let elapsed = format_duration(session.elapsed());
+                let is_muted = state.read().is_muted;
+                rsx!(
+                    div { class: "call-header",
+                        role: "region",
+                        aria_label: tr("a11y.call_header"),
+                        div { class: "call-header-info",
+                            span { class: "call-header-room", "{session.room_id}" }
+                            span { class: "call-header-state", "{session.state}" }
+                            span { class: "call-header-timer", role: "timer", "{elapsed}" }
+                        }
+                        div { class: "call-header-actions",
+                            button {
+                                aria_pressed: "{is_muted}",
+                                onclick: move |_| {
+                                    let mut s = state.write();
+                                    s.is_muted = !s.is_muted;
+                                    if let Some(call) = s.call.as_ref() {
+                                        call.set_muted(s.is_muted);
+                                    }
+                                },
+                                {if is_muted { tr("call.unmute") } else { tr("call.mute") }}
+                            }
+                            button {
+                                class: "end-call",
+                                onclick: move |_| report(state, hang_up(state)),
+                                {tr("call.end")}
+                            }
+                        }
+                    }
+                )
+            })}
+
+            {state.read().quality.clone().map(|quality| rsx!(
+                div { class: "control-panel",
+                    h3 { {tr("quality.title")} }
+                    div { {tr("quality.score")} "{quality.quality_score}" }
+                    div { {tr("quality.rtt")} "{quality.round_trip_time:.0} ms" }
+                    div { {tr("quality.packet_loss")} "{quality.packet_loss_rate:.1}%" }
+                    div { {tr("quality.bitrate")} "{quality.bitrate:.0} kbps" }
+                }
+            ))}
+
+            {state.read().incoming_call.clone().map(|call| {
+                let declined = call.clone();
+                rsx!(
+                    div { class: "incoming-call",
+                        role: "alertdialog",
+                        aria_live: "assertive",
+                        span { {tr_args("call.incoming", &[("peer", &call.from_peer), ("room", &call.room_id)])} }
+                        button {
+                            onclick: move |_| {
+                                let state = state.clone();
+                                let call = call.clone();
+                                cx.spawn(async move {
+                                    let result = answer(state.clone(), call, true).await;
+                                    report(&state, result);
+                                });
+                            },
+                            {tr("call.accept")}
+                        }
+                        button {
+                            class: "end-call",
+                            onclick: move |_| {
+                                let state = state.clone();
+                                let call = declined.clone();
+                                cx.spawn(async move {
+                                    let result = answer(state.clone(), call, false).await;
+                                    report(&state, result);
+                                });
+                            },
+                            {tr("call.decline")}
+                        }
+                    }
+                )
+            })}
+
+            div { class: "control-panel",
+                h3 { {tr("connection.settings")} }
+                div {
+                    label { r#for: "server", {tr("connection.server")} }
+                    input {
+                        id: "server",
+                        value: "{state.read().server}",
+                        disabled: "{is_connected}",
+                        oninput: move |evt| state.write().server = evt.value.clone()
+                    }
+                    label { r#for: "roomId", {tr("connection.room_id")} }
+                    input {
+                        id: "roomId",
+                        value: "{state.read().room_id}",
+                        disabled: "{is_connected}",
+                        oninput: move |evt| state.write().room_id = evt.value.clone()
+                    }
+                    label { r#for: "peerId", {tr("connection.peer_id")} }
+                    input {
+                        id: "peerId",
+                        readonly: "true",
+                        value: "{state.read().peer_id()}"
+                    }
+                }
+                button {
+                    disabled: "{is_connected}",
+                    onclick: move |_| {
+                        let state = state.clone();
+                        cx.spawn(async move {
+                            let result = connect(state.clone()).await;
+                            report(&state, result);
+                        });
+                    },
+                    {tr("connection.connect")}
+                }
+            }
+
+            div { class: "control-panel",
+                h3 { {tr("peers.title")} }
+                div { class: "peer-list",
+                    role: "group",
+                    aria_label: tr("peers.title"),
+                    state.read().peers.iter().map(|peer_id| {
+                        let peer = peer_id.clone();
+                        rsx! {
+                            div { key: "{peer_id}", class: "peer-item",
+                                span { "{peer_id}" }
+                                button {
+                                    disabled: "{in_call}",
+                                    onclick: move |_| {
+                                        let state = state.clone();
+                                        let peer = peer.clone();
+                                        cx.spawn(async move {
+                                            let result = call_peer(state.clone(), peer).await;
+                                            report(&state, result);
+                                        });
+                                    },
+                                    {tr("contacts.call")}
+                                }
+                            }
+                        }
+                    })
+                }
+            }
+        }
+    })
+}
@@ -0,0 +1,271 @@
+use futures::stream::{self, Stream, StreamExt};
+use std::fmt;
+use std::future::Future;
+use std::time::Duration;
+// std's clocks panic in the browser
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+#[cfg(target_arch = "wasm32")]
+use web_time::Instant;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CallState {
+    Dialing,
+    Active,
+    // Put aside for another call: the connection stays up but we send silence
+    Held,
+    // Everyone we called declined
+    Busy,
+    // Never connected
+    Failed,
+    Ended,
+}
+
+impl CallState {
+    // What the caller hears in this state; None is silence
+    pub fn cue(&self) -> Option<CallCue> {
+        match self {
+            CallState::Dialing => Some(CallCue::Ringback),
+            CallState::Busy => Some(CallCue::Busy),
+            CallState::Failed => Some(CallCue::Failed),
+            CallState::Active | CallState::Held | CallState::Ended => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CallCue {
+    Ringback,
+    Busy,
+    Failed,
+}
+
+impl CallCue {
+    // Ringback runs until the call moves on; the others play once, past the call's end
+    pub fn repeats(self) -> bool {
+        self == CallCue::Ringback
+    }
+}
+
+impl fmt::Display for CallState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CallState::Dialing => write!(f, "Dialing"),
+            CallState::Active => write!(f, "Active"),
+            CallState::Held => write!(f, "On hold"),
+            CallState::Busy => write!(f, "Busy"),
+            CallState::Failed => write!(f, "Failed"),
+            CallState::Ended => write!(f, "Ended"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct IncomingCall {
+    pub room_id: String,
+    pub from_peer: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CallSession {
+    pub room_id: String,
+    pub participants: Vec<String>,
+    pub state: CallState,
+    started_at: Instant,
+    ended_at: Option<Instant>,
+}
+
+impl CallSession {
+    pub fn new(room_id: String, participants: Vec<String>) -> Self {
+        Self {
+            room_id,
+            participants,
+            state: CallState::Dialing,
+            started_at: Instant::now(),
+            ended_at: None,
+        }
+    }
+
+    pub fn mark_active(&mut self) {
+        if self.state == CallState::Dialing {
+            self.state = CallState::Active;
+        }
+    }
+
+    // A call that never connected; one that dropped later just ends
+    pub fn fail(&mut self) {
+        if self.state == CallState::Dialing {
+            self.state = CallState::Failed;
+            self.ended_at = Some(Instant::now());
+        }
+    }
+
+    // Busy once the last one we were still ringing says no
+    pub fn decline(&mut self, peer_id: &str) {
+        self.remove_participant(peer_id);
+        if self.state == CallState::Dialing && self.participants.is_empty() {
+            self.state = CallState::Busy;
+            self.ended_at = Some(Instant::now());
+        }
+    }
+
+    // Whether it got past dialing
+    pub fn answered(&self) -> bool {
+        matches!(self.state, CallState::Active | CallState::Held | CallState::Ended)
+    }
+
+    pub fn hold(&mut self) {
+        if self.state == CallState::Active {
+            self.state = CallState::Held;
+        }
+    }
+
+    pub fn resume(&mut self) {
+        if self.state == CallState::Held {
+            self.state = CallState::Active;
+        }
+    }
+
+    pub fn end(&mut self) {
+        self.state = CallState::Ended;
+        self.ended_at = Some(Instant::now());
+    }
+
+    pub fn add_participant(&mut self, peer_id: String) {
+        if !self.participants.contains(&peer_id) {
+            self.participants.push(peer_id);
+        }
+    }
+
+    pub fn remove_participant(&mut self, peer_id: &str) {
+        self.participants.retain(|id| id != peer_id);
+    }
+
+    // Participants plus ourselves
+    pub fn participant_count(&self) -> usize {
+        self.participants.len() + 1
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.ended_at.unwrap_or_else(Instant::now) - self.started_at
+    }
+}
+
+// How long the peer merging two calls waits for everyone to reach the conference room
+// before calling whoever made it
+pub const MERGE_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Two calls being pulled into one: everyone in them moves to a fresh room, where the
+// host calls them all at once
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConferenceMerge {
+    pub room_id: String,
+    pub host: String,
+    pub peers: Vec<String>,
+}
+
+impl ConferenceMerge {
+    // The room is named after the one the calls were in, so it's still recognisable
+    pub fn new(from_room: &str, host: String, peers: Vec<String>) -> Self {
+        Self {
+            room_id: format!("{}-conference-{:08x}", from_room, rand::random::<u32>()),
+            host,
+            peers,
+        }
+    }
+
+    // From the room's peer list
+    pub fn arrived(&self, in_room: &[String]) -> Vec<String> {
+        self.peers.iter().filter(|peer| in_room.contains(peer)).cloned().collect()
+    }
+
+    pub fn everyone_arrived(&self, in_room: &[String]) -> bool {
+        self.arrived(in_room).len() == self.peers.len()
+    }
+}
+
+// Connections to the callees of a mesh call set up at once; more just wait their turn
+pub const MAX_PARALLEL_SETUPS: usize = 4;
+
+// How far the connection to one callee of an outgoing call got
+#[derive(Debug, Clone, PartialEq)]
+pub enum PeerSetup {
+    // Building its peer connection and offer
+    Preparing,
+    // The offer is ready for when they pick up
+    Ringing,
+    Connected,
+    Declined,
+    Failed(String),
+}
+
+// Per-callee progress of a call's setup, in the order they were called
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CallSetup {
+    peers: Vec<(String, PeerSetup)>,
+}
+
+impl CallSetup {
+    pub fn new(peers: &[String]) -> Self {
+        Self {
+            peers: peers.iter().map(|peer| (peer.clone(), PeerSetup::Preparing)).collect(),
+        }
+    }
+
+    // Peers that weren't called are ignored
+    pub fn update(&mut self, peer_id: &str, setup: PeerSetup) {
+        if let Some((_, current)) = self.peers.iter_mut().find(|(peer, _)| peer == peer_id) {
+            *current = setup;
+        }
+    }
+
+    pub fn get(&self, peer_id: &str) -> Option<&PeerSetup> {
+        self.peers.iter().find(|(peer, _)| peer == peer_id).map(|(_, setup)| setup)
+    }
+
+    pub fn peers(&self) -> &[(String, PeerSetup)] {
+        &self.peers
+    }
+
+    pub fn connected(&self) -> usize {
+        self.peers.iter().filter(|(_, setup)| *setup == PeerSetup::Connected).count()
+    }
+
+    // Everyone who isn't in the call after all, for "retry failed peers"
+    pub fn failed(&self) -> Vec<String> {
+        self.peers
+            .iter()
+            .filter(|(_, setup)| matches!(setup, PeerSetup::Declined | PeerSetup::Failed(_)))
+            .map(|(peer, _)| peer.clone())
+            .collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.peers.is_empty()
+    }
+}
+
+// Runs setup for every peer, at most `limit` at a time. Results come out as each one
+// finishes, so a slow peer doesn't hold up the others.
+pub fn set_up_peers<T, F, Fut>(peers: Vec<String>, limit: usize, setup: F) -> impl Stream<Item = (String, anyhow::Result<T>)>
+where
+    F: Fn(String) -> Fut,
+    Fut: Future<Output = anyhow::Result<T>>,
+{
+    stream::iter(peers)
+        .map(move |peer| {
+            let result = setup(peer.clone());
+            async move { (peer, result.await) }
+        })
+        .buffer_unordered(limit.max(1))
+}
+
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let (hours, minutes, seconds) = (secs / 3600, (secs % 3600) / 60, secs % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{:02}:{:02}", minutes, seconds)
+    }
+}
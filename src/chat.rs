@@ -0,0 +1,22 @@
+// std's clocks panic in the browser
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::SystemTime;
+#[cfg(target_arch = "wasm32")]
+use web_time::SystemTime;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChatEntry {
+    pub from_peer: String,
+    pub text: String,
+    pub sent_at: SystemTime,
+}
+
+impl ChatEntry {
+    pub fn new(from_peer: String, text: String) -> Self {
+        Self {
+            from_peer,
+            text,
+            sent_at: SystemTime::now(),
+        }
+    }
+}
@@ -0,0 +1,302 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::api::ApiConfig;
+use crate::audio::tones::CallTones;
+use crate::audio::{AudioBackend, AudioBackendKind, CaptureSource, ExternalCaptureBackend, OutputRouting};
+use crate::failover::FailoverConfig;
+use crate::presence::IdleConfig;
+use crate::publisher::PublisherConfig;
+use crate::reconnect::ReconnectPolicy;
+use crate::recording::RecordingConfig;
+use crate::room::{ConsentPolicy, RosterCues};
+use crate::signaling::ChannelLimits;
+use crate::telemetry::TelemetryConfig;
+use crate::transcription::TranscriptionConfig;
+use crate::webrtc::{IceServerConfig, WebRTCConfig};
+
+const CONFIG_FILE: &str = "config.json";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    // A ws:// or wss:// URL, an https:// one for WebTransport, a domain to discover the
+    // server of, or lan:// for calls on the local network without one
+    pub signaling_url: String,
+    // Bearer token for signaling servers that require one
+    pub signaling_token: Option<String>,
+    pub auto_answer: bool,
+    // Empty means every caller is auto-answered
+    pub auto_answer_allowlist: Vec<String>,
+    // None follows the OS locale
+    pub locale: Option<String>,
+    pub high_contrast: bool,
+    // Store chat and call history in the local database
+    pub keep_history: bool,
+    pub audio_backend: AudioBackendKind,
+    // Fixed device buffer size in frames for low-latency setups; None lets the driver pick
+    pub audio_buffer_frames: Option<u32>,
+    // How much of the mic to play back in the headset, 0.0 (off) to 1.0
+    pub sidetone_level: f32,
+    // Extra playback buffering, 0 to 200 ms: more delay, fewer dropouts on headsets
+    // that stall
+    pub playback_delay_ms: u32,
+    // Every call starts muted until the user unmutes
+    pub join_muted: bool,
+    // Point it out when the user talks while muted
+    pub muted_speech_reminder: bool,
+    // Where calls and alerts play
+    pub output_routing: OutputRouting,
+    // Send audio another program produces instead of the microphone's. Given on the
+    // command line for one run, never saved.
+    #[serde(skip)]
+    pub capture_source: Option<CaptureSource>,
+    // Who has to agree when we ask to record a call
+    pub recording_consent: ConsentPolicy,
+    // Sounds and toasts when someone joins or leaves the room
+    pub roster_cues: RosterCues,
+    // Ringback, busy and failed tones for outgoing calls
+    pub call_tones: CallTones,
+    // Where recordings go and whether they follow speech
+    pub recording: RecordingConfig,
+    pub webrtc: WebRTCConfig,
+    pub reconnect: ReconnectPolicy,
+    // Queue sizes on the signaling connection
+    pub signaling_channels: ChannelLimits,
+    // Other signaling servers of the same deployment, for when signaling_url is down
+    pub signaling_failover: FailoverConfig,
+    pub telemetry: TelemetryConfig,
+    // Local HTTP API for kiosks and automation
+    pub api: ApiConfig,
+    // Call events for home automation and monitoring
+    pub events: PublisherConfig,
+    // Live captions and the speech-to-text engine behind them
+    pub transcription: TranscriptionConfig,
+    // Loaded at startup: native libraries (.so, .dylib, .dll) or .wasm modules
+    pub plugins: Vec<PathBuf>,
+    // A Rhai script of call automation hooks, loaded at startup
+    pub scripts: Option<PathBuf>,
+    pub profiles: BTreeMap<String, Profile>,
+    // The profile the fields above were loaded from, if any
+    pub active_profile: Option<String>,
+    pub bookmarks: Vec<RoomBookmark>,
+    // Going away after inactivity, and leaving empty rooms then
+    pub idle: IdleConfig,
+}
+
+// A room to get back to quickly, on the server it was bookmarked on
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoomBookmark {
+    pub server: String,
+    pub room_id: String,
+    // Connect and join it at launch, for standing team channels. At most one bookmark
+    // has it.
+    #[serde(default)]
+    pub auto_join: bool,
+}
+
+// A named server/identity setup, e.g. work and home. The active profile's values live in
+// the top-level settings fields, so the rest of the app never looks at profiles;
+// switching copies them out and back in.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Profile {
+    pub signaling_url: String,
+    pub signaling_token: Option<String>,
+    pub signaling_failover: FailoverConfig,
+    pub ice_servers: Vec<IceServerConfig>,
+    pub audio_backend: AudioBackendKind,
+    pub audio_buffer_frames: Option<u32>,
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Settings::default().current_profile()
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            signaling_url: "ws://127.0.0.1:8080".to_string(),
+            signaling_token: None,
+            auto_answer: false,
+            auto_answer_allowlist: Vec::new(),
+            locale: None,
+            high_contrast: false,
+            keep_history: true,
+            audio_backend: AudioBackendKind::default(),
+            audio_buffer_frames: None,
+            sidetone_level: 0.0,
+            playback_delay_ms: 0,
+            join_muted: false,
+            muted_speech_reminder: true,
+            output_routing: OutputRouting::default(),
+            capture_source: None,
+            recording_consent: ConsentPolicy::default(),
+            roster_cues: RosterCues::default(),
+            call_tones: CallTones::default(),
+            recording: RecordingConfig::default(),
+            webrtc: WebRTCConfig::default(),
+            reconnect: ReconnectPolicy::default(),
+            signaling_channels: ChannelLimits::default(),
+            signaling_failover: FailoverConfig::default(),
+            telemetry: TelemetryConfig::default(),
+            api: ApiConfig::default(),
+            events: PublisherConfig::default(),
+            transcription: TranscriptionConfig::default(),
+            plugins: Vec::new(),
+            scripts: None,
+            profiles: BTreeMap::new(),
+            active_profile: None,
+            bookmarks: Vec::new(),
+            idle: IdleConfig::default(),
+        }
+    }
+}
+
+impl Settings {
+    pub fn create_audio_backend(&self) -> Arc<dyn AudioBackend> {
+        let backend = self.audio_backend.create_with_buffer(self.audio_buffer_frames);
+        if let Some(sidetone) = backend.sidetone() {
+            sidetone.set_level(self.sidetone_level);
+        }
+        backend.set_output_routing(self.output_routing.clone());
+        backend.set_playback_delay(Duration::from_millis(self.playback_delay_ms as u64));
+        ExternalCaptureBackend::wrap(backend, self.capture_source.clone())
+    }
+
+    pub fn current_profile(&self) -> Profile {
+        Profile {
+            signaling_url: self.signaling_url.clone(),
+            signaling_token: self.signaling_token.clone(),
+            signaling_failover: self.signaling_failover.clone(),
+            ice_servers: self.webrtc.ice_servers.clone(),
+            audio_backend: self.audio_backend,
+            audio_buffer_frames: self.audio_buffer_frames,
+        }
+    }
+
+    // Stores the current server, credentials and devices under `name` and makes it active
+    pub fn save_profile(&mut self, name: &str) -> Result<()> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(anyhow!("Profile name is empty"));
+        }
+        self.profiles.insert(name.to_string(), self.current_profile());
+        self.active_profile = Some(name.to_string());
+        Ok(())
+    }
+
+    // Keeps any edits made under the previous profile, then loads `name`
+    pub fn switch_profile(&mut self, name: &str) -> Result<()> {
+        let profile = self
+            .profiles
+            .get(name)
+            .cloned()
+            .ok_or_else(|| anyhow!("No profile named \"{}\"", name))?;
+        if let Some(previous) = self.active_profile.take() {
+            if previous != name && self.profiles.contains_key(&previous) {
+                self.profiles.insert(previous, self.current_profile());
+            }
+        }
+        self.signaling_url = profile.signaling_url;
+        self.signaling_token = profile.signaling_token;
+        self.signaling_failover = profile.signaling_failover;
+        self.webrtc.ice_servers = profile.ice_servers;
+        self.audio_backend = profile.audio_backend;
+        self.audio_buffer_frames = profile.audio_buffer_frames;
+        self.active_profile = Some(name.to_string());
+        Ok(())
+    }
+
+    // The current values stay as they are, just no longer tied to a profile
+    pub fn delete_profile(&mut self, name: &str) {
+        self.profiles.remove(name);
+        if self.active_profile.as_deref() == Some(name) {
+            self.active_profile = None;
+        }
+    }
+
+    // On the server the room is on, which an invite or bookmark may have taken us to
+    pub fn bookmark_room(&mut self, server: &str, room_id: &str) -> Result<()> {
+        let room_id = room_id.trim();
+        if room_id.is_empty() {
+            return Err(anyhow!("Room name is empty"));
+        }
+        if !self.is_bookmarked(server, room_id) {
+            self.bookmarks.push(RoomBookmark {
+                server: server.to_string(),
+                room_id: room_id.to_string(),
+                auto_join: false,
+            });
+        }
+        Ok(())
+    }
+
+    pub fn is_bookmarked(&self, server: &str, room_id: &str) -> bool {
+        self.bookmarks.iter().any(|bookmark| bookmark.server == server && bookmark.room_id == room_id)
+    }
+
+    pub fn remove_bookmark(&mut self, server: &str, room_id: &str) {
+        self.bookmarks.retain(|bookmark| !(bookmark.server == server && bookmark.room_id == room_id));
+    }
+
+    // Turning it on for one room turns it off for the rest
+    pub fn set_auto_join(&mut self, server: &str, room_id: &str, auto_join: bool) {
+        for bookmark in &mut self.bookmarks {
+            if bookmark.server == server && bookmark.room_id == room_id {
+                bookmark.auto_join = auto_join;
+            } else if auto_join {
+                bookmark.auto_join = false;
+            }
+        }
+    }
+
+    pub fn auto_join_bookmark(&self) -> Option<&RoomBookmark> {
+        self.bookmarks.iter().find(|bookmark| bookmark.auto_join)
+    }
+
+    pub fn load() -> Self {
+        match fs::read_to_string(config_path()) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+                eprintln!("Failed to parse settings, using defaults: {}", e);
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = config_path();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn should_auto_answer(&self, from_peer: &str) -> bool {
+        self.auto_answer
+            && (self.auto_answer_allowlist.is_empty()
+                || self.auto_answer_allowlist.iter().any(|id| id == from_peer))
+    }
+}
+
+pub fn config_dir() -> PathBuf {
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("APPDATA").map(PathBuf::from))
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .unwrap_or_else(|| PathBuf::from("."));
+    base.join("webrtc-client")
+}
+
+fn config_path() -> PathBuf {
+    config_dir().join(CONFIG_FILE)
+}
@@ -1,11 +1,15 @@
 use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
 use std::fmt;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
 use tokio::sync::watch;
 use webrtc::peer_connection::peer_connection_state::RTCPeerConnectionState;
 use webrtc::ice_transport::ice_connection_state::RTCIceConnectionState;
 use webrtc::peer_connection::signaling_state::RTCSignalingState;
 
+use crate::audio::DeviceEvent;
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum ConnectionState {
     Disconnected,
@@ -27,13 +31,15 @@ impl fmt::Display for ConnectionState {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct ConnectionStatus {
     pub state: ConnectionState,
     pub signaling_state: RTCSignalingState,
     pub ice_state: RTCIceConnectionState,
     pub peer_state: RTCPeerConnectionState,
     pub last_error: Option<String>,
+    // The most recent audio device switch during this connection
+    pub device_event: Option<DeviceEvent>,
 }
 
 impl Default for ConnectionStatus {
@@ -44,14 +50,26 @@ impl Default for ConnectionStatus {
             ice_state: RTCIceConnectionState::New,
             peer_state: RTCPeerConnectionState::New,
             last_error: None,
+            device_event: None,
         }
     }
 }
 
+// Entries kept in the connection event log; older ones fall off
+pub const EVENT_LOG_LEN: usize = 100;
+
+// Something that happened to the connection, in words, for diagnostics
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConnectionEvent {
+    pub at: SystemTime,
+    pub message: String,
+}
+
 #[derive(Clone)]
 pub struct ConnectionMonitor {
     status: Arc<watch::Sender<ConnectionStatus>>,
     receiver: watch::Receiver<ConnectionStatus>,
+    events: Arc<Mutex<VecDeque<ConnectionEvent>>>,
 }
 
 impl ConnectionMonitor {
@@ -60,9 +78,27 @@ impl ConnectionMonitor {
         Self {
             status: Arc::new(status),
             receiver,
+            events: Arc::new(Mutex::new(VecDeque::new())),
+        }
+    }
+
+    // Printed too, so the console keeps showing the same story
+    pub fn log_event(&self, message: impl Into<String>) {
+        let message = message.into();
+        println!("{}", message);
+        if let Ok(mut events) = self.events.lock() {
+            if events.len() == EVENT_LOG_LEN {
+                events.pop_front();
+            }
+            events.push_back(ConnectionEvent { at: SystemTime::now(), message });
         }
     }
 
+    // Oldest first
+    pub fn events(&self) -> Vec<ConnectionEvent> {
+        self.events.lock().map(|events| events.iter().cloned().collect()).unwrap_or_default()
+    }
+
     pub fn update_state(&self, state: ConnectionState) {
         let _ = self.status.send_modify(|status| {
             status.state = state;
@@ -101,6 +137,12 @@ impl ConnectionMonitor {
         }
     }
 
+    pub fn report_device_event(&self, event: DeviceEvent) {
+        let _ = self.status.send_modify(|status| {
+            status.device_event = Some(event);
+        });
+    }
+
     pub fn subscribe(&self) -> watch::Receiver<ConnectionStatus> {
         self.receiver.clone()
     }
@@ -0,0 +1,93 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+use crate::config::config_dir;
+use crate::storage::Storage;
+
+// Where contacts lived before the history database; only read to import them
+const LEGACY_CONTACTS_FILE: &str = "contacts.json";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Contact {
+    pub name: String,
+    pub peer_id: String,
+    #[serde(default)]
+    pub favorite: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ContactBook {
+    contacts: Vec<Contact>,
+}
+
+impl ContactBook {
+    pub fn load() -> Self {
+        match Storage::open_default().and_then(|storage| storage.load_contacts()) {
+            Ok(book) => book,
+            Err(e) => {
+                eprintln!("Failed to load contacts: {}", e);
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save(&self) -> Result<()> {
+        Storage::open_default()?.save_contacts(self)
+    }
+
+    pub(crate) fn load_legacy() -> Option<Self> {
+        let contents = fs::read_to_string(config_dir().join(LEGACY_CONTACTS_FILE)).ok()?;
+        serde_json::from_str(&contents)
+            .map_err(|e| eprintln!("Failed to parse {}: {}", LEGACY_CONTACTS_FILE, e))
+            .ok()
+    }
+
+    pub fn from_contacts(contacts: Vec<Contact>) -> Self {
+        Self { contacts }
+    }
+
+    pub fn contacts(&self) -> &[Contact] {
+        &self.contacts
+    }
+
+    // Adding an existing peer ID renames it rather than duplicating it
+    pub fn add(&mut self, name: String, peer_id: String) {
+        match self.contacts.iter_mut().find(|c| c.peer_id == peer_id) {
+            Some(contact) => contact.name = name,
+            None => self.contacts.push(Contact {
+                name,
+                peer_id,
+                favorite: false,
+            }),
+        }
+    }
+
+    pub fn remove(&mut self, peer_id: &str) {
+        self.contacts.retain(|c| c.peer_id != peer_id);
+    }
+
+    pub fn toggle_favorite(&mut self, peer_id: &str) {
+        if let Some(contact) = self.contacts.iter_mut().find(|c| c.peer_id == peer_id) {
+            contact.favorite = !contact.favorite;
+        }
+    }
+
+    pub fn name_for(&self, peer_id: &str) -> Option<&str> {
+        self.contacts
+            .iter()
+            .find(|c| c.peer_id == peer_id)
+            .map(|c| c.name.as_str())
+    }
+
+    // Favorites first, then alphabetical
+    pub fn sorted(&self) -> Vec<Contact> {
+        let mut contacts = self.contacts.clone();
+        contacts.sort_by(|a, b| {
+            b.favorite
+                .cmp(&a.favorite)
+                .then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase()))
+        });
+        contacts
+    }
+}
@@ -0,0 +1,77 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::broadcast;
+use webrtc::data_channel::data_channel_init::RTCDataChannelInit;
+use webrtc::data_channel::data_channel_message::DataChannelMessage;
+use webrtc::data_channel::data_channel_state::RTCDataChannelState;
+use webrtc::data_channel::RTCDataChannel;
+use webrtc::peer_connection::RTCPeerConnection;
+
+// Pre-negotiated on both sides, so it opens with the connection without an extra
+// round of signaling
+const CONTROL_CHANNEL_ID: u16 = 0;
+const CONTROL_LABEL: &str = "control";
+
+// What the two ends of a call need to renegotiate between themselves, for when the
+// signaling server is down or the change concerns only the media
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ControlMessage {
+    Offer { sdp: String },
+    Answer { sdp: String },
+    IceCandidate { candidate: String },
+    // From the answerer: please send a fresh offer, e.g. to apply a new quality rung
+    Renegotiate,
+}
+
+// A data channel riding on the call's own transport. As long as the media path is up,
+// ICE restarts can be negotiated over it without the server.
+#[derive(Clone)]
+pub struct ControlChannel {
+    channel: Arc<RTCDataChannel>,
+    incoming: broadcast::Sender<ControlMessage>,
+}
+
+impl ControlChannel {
+    pub async fn open(peer_connection: &RTCPeerConnection) -> Result<Self> {
+        let channel = peer_connection
+            .create_data_channel(
+                CONTROL_LABEL,
+                Some(RTCDataChannelInit {
+                    ordered: Some(true),
+                    negotiated: Some(CONTROL_CHANNEL_ID),
+                    ..Default::default()
+                }),
+            )
+            .await?;
+        let (incoming, _) = broadcast::channel(32);
+        let incoming_tx = incoming.clone();
+        channel.on_message(Box::new(move |msg: DataChannelMessage| {
+            match serde_json::from_slice::<ControlMessage>(&msg.data) {
+                Ok(message) => {
+                    let _ = incoming_tx.send(message);
+                }
+                Err(e) => eprintln!("Ignoring malformed control message: {}", e),
+            }
+            Box::pin(async {})
+        }));
+        Ok(Self { channel, incoming })
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.channel.ready_state() == RTCDataChannelState::Open
+    }
+
+    pub async fn send(&self, message: &ControlMessage) -> Result<()> {
+        if !self.is_open() {
+            return Err(anyhow!("Control channel is not open"));
+        }
+        self.channel.send_text(serde_json::to_string(message)?).await?;
+        Ok(())
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<ControlMessage> {
+        self.incoming.subscribe()
+    }
+}
@@ -0,0 +1,126 @@
+use anyhow::{anyhow, Result};
+use hickory_resolver::TokioAsyncResolver;
+use serde::Deserialize;
+use std::time::Duration;
+
+use crate::failover::FailoverConfig;
+use crate::lan::is_lan;
+use crate::signaling::is_webtransport;
+
+// Lets users enter just "example.com" as the server: the signaling URLs come from
+// https://example.com/.well-known/webrtc-client, or failing that from the domain's
+// _webrtc-client._tcp SRV records.
+pub const WELL_KNOWN_PATH: &str = "/.well-known/webrtc-client";
+pub const SRV_SERVICE: &str = "_webrtc-client._tcp";
+
+const WELL_KNOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+// {"signaling_url": "wss://signal.example.com/ws", "fallback_urls": ["wss://signal2.example.com/ws"]}
+// where an https:// URL is a WebTransport server
+#[derive(Debug, Deserialize)]
+struct WellKnown {
+    signaling_url: String,
+    #[serde(default)]
+    fallback_urls: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SrvRecord {
+    pub priority: u16,
+    pub weight: u16,
+    pub port: u16,
+    pub target: String,
+}
+
+// Anything without a scheme is taken for a domain
+pub fn is_domain(server: &str) -> bool {
+    let server = server.trim();
+    !server.is_empty() && !server.contains("://") && !is_lan(server)
+}
+
+// The signaling URL and failover settings to connect with: as configured for a URL, or
+// with what the domain points to first and the configured fallbacks after it
+pub async fn resolve(server: &str, failover: &FailoverConfig) -> Result<(String, FailoverConfig)> {
+    if !is_domain(server) {
+        return Ok((server.to_string(), failover.clone()));
+    }
+    let mut urls = discover(server).await?.into_iter();
+    let primary = urls.next().ok_or_else(|| anyhow!("No signaling server found for {}", server))?;
+    let mut failover = failover.clone();
+    failover.fallback_urls.splice(0..0, urls);
+    Ok((primary, failover))
+}
+
+// The domain's signaling URLs, most preferred first
+pub async fn discover(domain: &str) -> Result<Vec<String>> {
+    let domain = domain.trim().trim_end_matches('/');
+    let well_known = match well_known(domain).await {
+        Ok(urls) => return Ok(urls),
+        Err(e) => e,
+    };
+    srv(domain)
+        .await
+        .map_err(|e| anyhow!("Could not find the signaling server for {}: {}; {}", domain, well_known, e))
+}
+
+async fn well_known(domain: &str) -> Result<Vec<String>> {
+    let body = reqwest::Client::new()
+        .get(format!("https://{}{}", domain, WELL_KNOWN_PATH))
+        .timeout(WELL_KNOWN_TIMEOUT)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| anyhow!("{} lookup failed: {}", WELL_KNOWN_PATH, e))?
+        .text()
+        .await?;
+    parse_well_known(&body)
+}
+
+pub fn parse_well_known(body: &str) -> Result<Vec<String>> {
+    let well_known: WellKnown =
+        serde_json::from_str(body).map_err(|e| anyhow!("Unexpected {} contents: {}", WELL_KNOWN_PATH, e))?;
+    let urls: Vec<String> = std::iter::once(well_known.signaling_url).chain(well_known.fallback_urls).collect();
+    let usable = |url: &String| url.starts_with("ws://") || url.starts_with("wss://") || is_webtransport(url);
+    if let Some(url) = urls.iter().find(|url| !usable(url)) {
+        return Err(anyhow!("{} names {}, which is not a ws://, wss:// or https:// URL", WELL_KNOWN_PATH, url));
+    }
+    Ok(urls)
+}
+
+async fn srv(domain: &str) -> Result<Vec<String>> {
+    let resolver = TokioAsyncResolver::tokio_from_system_conf()?;
+    let lookup = resolver
+        .srv_lookup(format!("{}.{}.", SRV_SERVICE, domain))
+        .await
+        .map_err(|e| anyhow!("SRV lookup failed: {}", e))?;
+    let records: Vec<SrvRecord> = lookup
+        .iter()
+        .map(|srv| SrvRecord {
+            priority: srv.priority(),
+            weight: srv.weight(),
+            port: srv.port(),
+            target: srv.target().to_utf8(),
+        })
+        .collect();
+    let urls = srv_urls(&records);
+    if urls.is_empty() {
+        return Err(anyhow!("{}.{} has no usable SRV records", SRV_SERVICE, domain));
+    }
+    Ok(urls)
+}
+
+// Lowest priority first and, within one, the heaviest first; the rest become fallbacks
+// rather than being picked at random. Signaling is always TLS when found this way; a
+// plain ws:// server has to be named in full or in the well-known file.
+pub fn srv_urls(records: &[SrvRecord]) -> Vec<String> {
+    let mut records: Vec<&SrvRecord> = records
+        .iter()
+        // "." means the service isn't offered at this domain
+        .filter(|record| !record.target.trim_end_matches('.').is_empty())
+        .collect();
+    records.sort_by_key(|record| (record.priority, std::cmp::Reverse(record.weight)));
+    records
+        .into_iter()
+        .map(|record| format!("wss://{}:{}", record.target.trim_end_matches('.'), record.port))
+        .collect()
+}
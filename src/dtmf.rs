@@ -0,0 +1,289 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use bytes::{BufMut, Bytes, BytesMut};
+use std::sync::atomic::{AtomicU16, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::time::sleep;
+use webrtc::api::media_engine::MediaEngine;
+use webrtc::interceptor::stream_info::StreamInfo;
+use webrtc::interceptor::{
+    Attributes, Interceptor, InterceptorBuilder, RTCPReader, RTCPWriter, RTPReader, RTPWriter,
+};
+use webrtc::rtp::header::Header;
+use webrtc::rtp::packet::Packet;
+use webrtc::rtp_transceiver::rtp_codec::{RTCRtpCodecCapability, RTCRtpCodecParameters, RTPCodecType};
+
+pub const TELEPHONE_EVENT_MIME: &str = "audio/telephone-event";
+// Events must use the clock of the audio codec they accompany: one entry for Opus and one
+// for G.711. The payload types are only what we offer; negotiated ones are looked up per call.
+const TELEPHONE_EVENT_VARIANTS: [(u32, u8); 2] = [(48_000, 101), (8_000, 126)];
+
+pub const TONE_DURATION: Duration = Duration::from_millis(100);
+pub const INTER_TONE_GAP: Duration = Duration::from_millis(70);
+// A comma in the tone string pauses like it does on a phone's dial pad
+pub const COMMA_PAUSE: Duration = Duration::from_secs(2);
+const PACKET_INTERVAL: Duration = Duration::from_millis(20);
+const END_PACKET_REPEATS: usize = 3;
+const VOLUME_DBM0: u8 = 10;
+
+pub fn telephone_event_parameters() -> Vec<RTCRtpCodecParameters> {
+    TELEPHONE_EVENT_VARIANTS
+        .into_iter()
+        .map(|(clock_rate, payload_type)| RTCRtpCodecParameters {
+            capability: RTCRtpCodecCapability {
+                mime_type: TELEPHONE_EVENT_MIME.to_owned(),
+                clock_rate,
+                channels: 1,
+                sdp_fmtp_line: "0-15".to_owned(),
+                rtcp_feedback: vec![],
+            },
+            payload_type,
+            ..Default::default()
+        })
+        .collect()
+}
+
+pub fn register_telephone_event(media_engine: &mut MediaEngine) -> Result<()> {
+    for parameters in telephone_event_parameters() {
+        media_engine.register_codec(parameters, RTPCodecType::Audio)?;
+    }
+    Ok(())
+}
+
+// RFC 4733 event codes for the 16 DTMF tones
+pub fn event_code(tone: char) -> Option<u8> {
+    match tone.to_ascii_uppercase() {
+        digit @ '0'..='9' => Some(digit as u8 - b'0'),
+        '*' => Some(10),
+        '#' => Some(11),
+        letter @ 'A'..='D' => Some(letter as u8 - b'A' + 12),
+        _ => None,
+    }
+}
+
+pub fn validate_tones(tones: &str) -> Result<()> {
+    match tones.chars().find(|&tone| tone != ',' && event_code(tone).is_none()) {
+        Some(tone) => Err(anyhow!("'{}' is not a DTMF tone", tone)),
+        None => Ok(()),
+    }
+}
+
+// Telephone events share the audio stream's SSRC and sequence space, so they are injected
+// by an interceptor on the outgoing audio stream rather than sent on a track of their own
+#[derive(Clone, Default)]
+pub struct DtmfSender {
+    stream: Arc<Mutex<Option<Arc<AudioStream>>>>,
+    sending: Arc<tokio::sync::Mutex<()>>,
+}
+
+impl DtmfSender {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn interceptor_builder(&self) -> DtmfInterceptorBuilder {
+        DtmfInterceptorBuilder {
+            stream: self.stream.clone(),
+        }
+    }
+
+    // Plays the tones in order; concurrent calls queue behind each other. payload_types
+    // are the negotiated (clock rate, payload type) pairs for telephone-event.
+    pub async fn insert_dtmf(&self, tones: &str, payload_types: &[(u32, u8)]) -> Result<()> {
+        validate_tones(tones)?;
+        let _sending = self.sending.lock().await;
+
+        for tone in tones.chars() {
+            let Some(code) = event_code(tone) else {
+                sleep(COMMA_PAUSE).await;
+                continue;
+            };
+            let stream = self
+                .stream
+                .lock()
+                .unwrap()
+                .clone()
+                .ok_or_else(|| anyhow!("No audio is being sent yet"))?;
+            let payload_type = payload_types
+                .iter()
+                .find(|(clock_rate, _)| *clock_rate == stream.clock_rate)
+                .map(|(_, payload_type)| *payload_type)
+                .ok_or_else(|| anyhow!("The remote peer does not accept DTMF with this audio codec"))?;
+            stream.send_event(code, payload_type).await?;
+            sleep(INTER_TONE_GAP).await;
+        }
+        Ok(())
+    }
+}
+
+struct AudioStream {
+    ssrc: u32,
+    clock_rate: u32,
+    next: Arc<dyn RTPWriter + Send + Sync>,
+    next_sequence: AtomicU16,
+    // Timestamp and wall time of the newest audio packet, to place events on the audio clock
+    last_audio: Mutex<Option<(u32, Instant)>>,
+}
+
+impl AudioStream {
+    async fn write(&self, mut header: Header, payload: Bytes, attributes: &Attributes) -> webrtc::interceptor::Result<usize> {
+        header.sequence_number = self.next_sequence.fetch_add(1, Ordering::SeqCst);
+        self.next.write(&Packet { header, payload }, attributes).await
+    }
+
+    fn current_timestamp(&self) -> Option<u32> {
+        self.last_audio.lock().unwrap().map(|(timestamp, at)| {
+            let elapsed = at.elapsed().as_millis() as u32 * (self.clock_rate / 1000);
+            timestamp.wrapping_add(elapsed)
+        })
+    }
+
+    async fn send_event(&self, code: u8, payload_type: u8) -> Result<()> {
+        let timestamp = self
+            .current_timestamp()
+            .ok_or_else(|| anyhow!("No audio is being sent yet"))?;
+        let attributes = Attributes::new();
+        let header = |marker| Header {
+            version: 2,
+            marker,
+            payload_type,
+            timestamp,
+            ssrc: self.ssrc,
+            ..Default::default()
+        };
+        let units_per_packet = PACKET_INTERVAL.as_millis() as u32 * (self.clock_rate / 1000);
+        let packets = (TONE_DURATION.as_millis() / PACKET_INTERVAL.as_millis()) as u32;
+
+        // Every packet of one event carries the start timestamp and the duration so far
+        for i in 1..=packets {
+            let payload = event_payload(code, false, (i * units_per_packet) as u16);
+            self.write(header(i == 1), payload, &attributes).await?;
+            sleep(PACKET_INTERVAL).await;
+        }
+        // The end packet is repeated in case one is lost
+        let end = event_payload(code, true, (packets * units_per_packet) as u16);
+        for _ in 0..END_PACKET_REPEATS {
+            self.write(header(false), end.clone(), &attributes).await?;
+        }
+        Ok(())
+    }
+}
+
+fn event_payload(code: u8, end: bool, duration: u16) -> Bytes {
+    let mut payload = BytesMut::with_capacity(4);
+    payload.put_u8(code);
+    payload.put_u8(if end { 0x80 } else { 0 } | VOLUME_DBM0);
+    payload.put_u16(duration);
+    payload.freeze()
+}
+
+pub struct DtmfInterceptorBuilder {
+    stream: Arc<Mutex<Option<Arc<AudioStream>>>>,
+}
+
+impl InterceptorBuilder for DtmfInterceptorBuilder {
+    fn build(&self, _id: &str) -> webrtc::interceptor::Result<Arc<dyn Interceptor + Send + Sync>> {
+        Ok(Arc::new(DtmfInterceptor {
+            stream: self.stream.clone(),
+        }))
+    }
+}
+
+// The DTMF interceptor builder of an API shared by several connections. Interceptors
+// are built as a connection is created, so its creator puts its sender in just before;
+// see WebRTCFactory.
+#[derive(Clone, Default)]
+pub struct DtmfSlot {
+    next: Arc<Mutex<Option<DtmfInterceptorBuilder>>>,
+}
+
+impl DtmfSlot {
+    pub fn fill(&self, sender: &DtmfSender) {
+        *self.next.lock().unwrap() = Some(sender.interceptor_builder());
+    }
+}
+
+impl InterceptorBuilder for DtmfSlot {
+    // A connection nobody filled the slot for can't send DTMF
+    fn build(&self, id: &str) -> webrtc::interceptor::Result<Arc<dyn Interceptor + Send + Sync>> {
+        let builder = self.next.lock().unwrap().take().unwrap_or_else(|| DtmfSender::new().interceptor_builder());
+        builder.build(id)
+    }
+}
+
+struct DtmfInterceptor {
+    stream: Arc<Mutex<Option<Arc<AudioStream>>>>,
+}
+
+#[async_trait]
+impl Interceptor for DtmfInterceptor {
+    async fn bind_rtcp_reader(
+        &self,
+        reader: Arc<dyn RTCPReader + Send + Sync>,
+    ) -> Arc<dyn RTCPReader + Send + Sync> {
+        reader
+    }
+
+    async fn bind_rtcp_writer(
+        &self,
+        writer: Arc<dyn RTCPWriter + Send + Sync>,
+    ) -> Arc<dyn RTCPWriter + Send + Sync> {
+        writer
+    }
+
+    async fn bind_local_stream(
+        &self,
+        info: &StreamInfo,
+        writer: Arc<dyn RTPWriter + Send + Sync>,
+    ) -> Arc<dyn RTPWriter + Send + Sync> {
+        if !info.mime_type.to_lowercase().starts_with("audio/") {
+            return writer;
+        }
+        let stream = Arc::new(AudioStream {
+            ssrc: info.ssrc,
+            clock_rate: info.clock_rate,
+            next: writer,
+            next_sequence: AtomicU16::new(rand::random()),
+            last_audio: Mutex::new(None),
+        });
+        *self.stream.lock().unwrap() = Some(stream.clone());
+        Arc::new(AudioWriter { stream })
+    }
+
+    async fn unbind_local_stream(&self, info: &StreamInfo) {
+        let mut stream = self.stream.lock().unwrap();
+        if stream.as_ref().map_or(false, |s| s.ssrc == info.ssrc) {
+            *stream = None;
+        }
+    }
+
+    async fn bind_remote_stream(
+        &self,
+        _info: &StreamInfo,
+        reader: Arc<dyn RTPReader + Send + Sync>,
+    ) -> Arc<dyn RTPReader + Send + Sync> {
+        reader
+    }
+
+    async fn unbind_remote_stream(&self, _info: &StreamInfo) {}
+
+    async fn close(&self) -> webrtc::interceptor::Result<()> {
+        Ok(())
+    }
+}
+
+// Renumbers outgoing audio so injected events fit into the same sequence space
+struct AudioWriter {
+    stream: Arc<AudioStream>,
+}
+
+#[async_trait]
+impl RTPWriter for AudioWriter {
+    async fn write(&self, pkt: &Packet, attributes: &Attributes) -> webrtc::interceptor::Result<usize> {
+        *self.stream.last_audio.lock().unwrap() = Some((pkt.header.timestamp, Instant::now()));
+        self.stream
+            .write(pkt.header.clone(), pkt.payload.clone(), attributes)
+            .await
+    }
+}
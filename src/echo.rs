@@ -0,0 +1,126 @@
+use anyhow::{anyhow, Result};
+use bytes::Bytes;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc};
+use tokio::task::JoinHandle;
+use tokio::time::{sleep_until, timeout, Instant};
+use webrtc::media::Sample;
+use webrtc::peer_connection::sdp::session_description::RTCSessionDescription;
+use webrtc::peer_connection::RTCPeerConnection;
+
+use crate::audio::{AudioBackend, AudioBackendKind, AudioStreamHandle, FRAME_DURATION};
+use crate::factory::WebRTCFactory;
+use crate::webrtc::{WebRTCClient, WebRTCConfig};
+
+pub const ECHO_DELAY: Duration = Duration::from_millis(1500);
+const GATHER_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Echo test call: the user's side is a normal WebRTCClient on the real audio backend, the
+// bot is a second in-process peer that plays everything it receives back after ECHO_DELAY
+pub struct EchoTest {
+    user: Arc<WebRTCClient>,
+    bot: Arc<WebRTCClient>,
+    _capture: AudioStreamHandle,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl EchoTest {
+    pub async fn start(config: &WebRTCConfig, backend: Arc<dyn AudioBackend>) -> Result<Self> {
+        // Both peers live in this process, so host candidates are all ICE needs
+        let mut config = config.clone();
+        config.ice_servers.clear();
+
+        let factory = Arc::new(WebRTCFactory::new(&config)?);
+        let client = |backend: Arc<dyn AudioBackend>| {
+            WebRTCClient::builder().config(&config).audio_backend(backend).factory(factory.clone()).build()
+        };
+        let user = Arc::new(client(backend.clone()).await?);
+        // The bot never plays anything itself; the mock sink just drains its playback feed
+        let bot = Arc::new(client(AudioBackendKind::Mock.create()).await?);
+
+        let tasks = echo_back(&bot);
+        connect(&user.peer_connection, &bot.peer_connection).await?;
+        let capture = backend.start_capture(user.audio_track.clone())?;
+
+        Ok(Self {
+            user,
+            bot,
+            _capture: capture,
+            tasks,
+        })
+    }
+
+    pub async fn stop(self) {
+        self.user.close().await;
+        self.bot.close().await;
+    }
+}
+
+impl Drop for EchoTest {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+fn echo_back(bot: &WebRTCClient) -> Vec<JoinHandle<()>> {
+    let mut received = bot.subscribe_remote_audio();
+    let (delayed_tx, mut delayed_rx) = mpsc::unbounded_channel::<(Instant, Bytes)>();
+
+    let queue = tokio::spawn(async move {
+        loop {
+            match received.recv().await {
+                Ok(payload) => {
+                    if delayed_tx.send((Instant::now() + ECHO_DELAY, payload)).is_err() {
+                        break;
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    });
+
+    let track = bot.audio_track.clone();
+    let replay = tokio::spawn(async move {
+        while let Some((due, payload)) = delayed_rx.recv().await {
+            sleep_until(due).await;
+            let sample = Sample {
+                data: payload,
+                duration: FRAME_DURATION,
+                ..Default::default()
+            };
+            if track.write_sample(&sample).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    vec![queue, replay]
+}
+
+// Offer/answer without trickle: each side waits for gathering so the SDP is complete
+async fn connect(caller: &RTCPeerConnection, callee: &RTCPeerConnection) -> Result<()> {
+    let offer = caller.create_offer(None).await?;
+    let offer = complete_local_description(caller, offer).await?;
+    callee.set_remote_description(offer).await?;
+
+    let answer = callee.create_answer(None).await?;
+    let answer = complete_local_description(callee, answer).await?;
+    caller.set_remote_description(answer).await?;
+    Ok(())
+}
+
+async fn complete_local_description(
+    pc: &RTCPeerConnection,
+    description: RTCSessionDescription,
+) -> Result<RTCSessionDescription> {
+    let mut gathered = pc.gathering_complete_promise().await;
+    pc.set_local_description(description).await?;
+    let _ = timeout(GATHER_TIMEOUT, gathered.recv()).await;
+    pc.local_description()
+        .await
+        .ok_or_else(|| anyhow!("No local description after gathering"))
+}
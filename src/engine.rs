@@ -0,0 +1,2475 @@
+use anyhow::{anyhow, Result};
+use futures::StreamExt;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, mpsc, watch};
+use webrtc::ice_transport::ice_candidate::RTCIceCandidate;
+use webrtc::ice_transport::ice_connection_state::RTCIceConnectionState;
+use webrtc::ice_transport::ice_gathering_state::RTCIceGatheringState;
+use webrtc::peer_connection::signaling_state::RTCSignalingState;
+
+use crate::audio::bluetooth::BluetoothDevice;
+use crate::audio::tap::TapConfig;
+use crate::audio::wav::read_wav;
+use crate::audio::{AudioBackend, AudioBackendKind, AudioStreamHandle, AudioTrack, CaptureSource, ExternalCaptureBackend, OutputRouting};
+use crate::call::{
+    set_up_peers, CallSession, CallSetup, CallState, ConferenceMerge, IncomingCall, PeerSetup, MAX_PARALLEL_SETUPS, MERGE_TIMEOUT,
+};
+use crate::chat::ChatEntry;
+use crate::connection::ConnectionStatus;
+use crate::factory::WebRTCFactory;
+use crate::failover::{self, FailoverConfig, SignalingServers};
+use crate::control::ControlMessage;
+use crate::discovery;
+use crate::identity::Identity;
+use crate::ladder::{LadderStep, QualityLadder, QualityRung, RedundancySwitch};
+use crate::latency::LatencyBreakdown;
+use crate::metrics::{ConnectionQuality, QualityHistory};
+use crate::negotiation::{Direction, NegotiationKind, NegotiationLog};
+use crate::netwatch::{NetworkChange, NetworkWatcher, DEFAULT_POLL_INTERVAL};
+use crate::one_way::{OneWayAudio, OneWayDetector, OneWayVerdict, PacketCounters};
+use crate::presence::{IdleConfig, PresenceStatus, PresenceTracker};
+use crate::publisher::{CallEvent, QualityAlarm};
+use crate::server_config::ServerConfig;
+use crate::setup_time::CallSetupTime;
+use crate::turn::TurnCredentialProvider;
+use crate::reconnect::ReconnectPolicy;
+use crate::recording::{CallRecorder, RecordingConfig, RecordingSegment};
+use crate::room::{ConsentPolicy, ConsentRequest, MediaRelays, RecordingConsent, RoomRoster, TrackOwners};
+use crate::scripting::{ScriptAction, ScriptHooks};
+use crate::sdp_hooks::SdpLog;
+use crate::security::CallSecurity;
+use crate::telemetry::{self, CallRole, CallTrace};
+use crate::transcription::{Caption, Transcriber, TranscriptionConfig};
+use crate::signaling::{check_protocol_version, ChannelLimits, SignalingClient, SignalingMessage, MIN_PROTOCOL_VERSION, PROTOCOL_VERSION};
+use crate::watchdog::{FailureKind, HealthSample, MediaHealth, Verdict, Watchdog, WATCHDOG_INTERVAL};
+use crate::warmstart::{RouteCache, WarmRoute};
+use crate::webrtc::{RemoteTrackEvent, WebRTCClient, WebRTCConfig};
+
+#[derive(Debug, Clone)]
+pub struct EngineConfig {
+    pub signaling_url: String,
+    // For servers that require one
+    pub signaling_token: Option<String>,
+    pub room_id: String,
+    pub peer_id: String,
+    // Signs what we send, for a peer_id derived from it
+    pub identity: Option<Identity>,
+    pub auto_answer: bool,
+    // Who auto_answer answers; empty is everyone
+    pub auto_answer_allowlist: Vec<String>,
+    pub audio_backend: AudioBackendKind,
+    pub audio_buffer_frames: Option<u32>,
+    pub sidetone_level: f32,
+    pub playback_delay_ms: u32,
+    pub output_routing: OutputRouting,
+    // For bots: call audio comes from another program instead of a microphone
+    pub capture_source: Option<CaptureSource>,
+    // Record calls here once our recording request is granted. None leaves recording to
+    // the embedder.
+    pub recording: Option<RecordingConfig>,
+    // Live captions of each call, as EngineEvent::Caption
+    pub transcription: TranscriptionConfig,
+    // Away after this long without EngineCommand::Activity, and out of a room we were
+    // left alone in
+    pub idle: IdleConfig,
+    // Automation hooks; a script's answer() or decline() overrides auto_answer
+    pub scripts: Option<ScriptHooks>,
+    pub webrtc: WebRTCConfig,
+    pub reconnect: ReconnectPolicy,
+    pub signaling_channels: ChannelLimits,
+    // Servers to fail over to when signaling_url is down
+    pub signaling_failover: FailoverConfig,
+    // Poll the OS for network changes and resume across them. Embedders with their own
+    // change notifications can leave this off and send EngineCommand::NetworkChanged.
+    pub watch_network: bool,
+}
+
+#[derive(Debug)]
+pub enum EngineCommand {
+    Connect,
+    Call(Vec<String>),
+    Answer { call: IncomingCall, accepted: bool },
+    HangUp,
+    // Blind transfer: the other side of a 1:1 call is asked to call this peer, and we hang up
+    Transfer(String),
+    // Pulls the current call and the one on hold into a conference
+    Merge,
+    SendChat(String),
+    SendDtmf(String),
+    // Sends silence instead of the microphone, in this call and the ones after it
+    SetMuted(bool),
+    // Asks the other participants whether the call may be recorded
+    RequestRecording(ConsentPolicy),
+    RespondRecording { request: ConsentRequest, granted: bool },
+    // The network under us changed; reconnect signaling and restart ICE right away
+    NetworkChanged,
+    // Renegotiates the call's ICE, e.g. after the watchdog reported NoAudio
+    RestartIce,
+    // Streams call audio to another program from this call on; None stops it
+    SetAudioTap(Option<TapConfig>),
+    // The user did something; we're no longer away
+    Activity,
+    // Calls the callees of the current mesh call who declined or failed once more
+    RetryFailed,
+    // Where a peer sits between left (-1.0) and right (1.0) while spatial audio is on
+    SetPan { peer_id: String, pan: f32 },
+    // Stops updating the diagnostics panels follow, e.g. while nobody can see them. Stats
+    // are still read for the ladder, RED and the watchdogs.
+    PauseStats(bool),
+    // Audio levels and the idle timeout apply right away, the rest from the next call.
+    // Server and room only change while disconnected.
+    Reconfigure(Box<EngineConfig>),
+    Shutdown,
+}
+
+#[derive(Debug, Clone)]
+pub enum EngineEvent {
+    Connected,
+    Disconnected,
+    PeerList(Vec<String>),
+    // From the difference between two peer lists
+    PeerJoined(String),
+    PeerLeft(String),
+    IncomingCall(IncomingCall),
+    CallStarted(CallSession),
+    // The current call after a callee answered, declined, failed or dropped out
+    CallUpdated(CallSession),
+    CallDeclined { peer_id: String },
+    // Per-callee progress of an outgoing mesh call
+    CallSetup(CallSetup),
+    // The other side hung up on us and asked us to call target, which we are doing
+    Transferred { by: String, target: String },
+    // Placing or answering another call put this one on hold
+    CallHeld(CallSession),
+    // The current call ended and the one on hold took its place
+    CallRetrieved(CallSession),
+    HeldCallEnded,
+    // On the way to a conference room, where host calls everyone from both calls
+    Merging { host: String, room_id: String },
+    CallActive,
+    RemoteAudioStarted,
+    // Right after RemoteAudioStarted: how long the call took to get there
+    SetupTime(CallSetupTime),
+    // A peer's audio track stopped: RTCP BYE, a stopped transceiver or the connection
+    // closing. Its playback is already torn down.
+    RemoteTrackEnded(String),
+    // Signaling or the call's media path dropped and is being re-established
+    Reconnecting,
+    CallResumed,
+    // The quality ladder moved the call to another rung
+    QualityRung(QualityRung),
+    // The watchdog's diagnosis of an outage, for telling the user what broke
+    Failure(FailureKind),
+    // Signaling and media both work again after a Failure
+    Recovered,
+    // Audio with this peer only gets through one way; None once it flows both ways again
+    OneWayAudio { peer_id: String, direction: Option<OneWayAudio> },
+    CallEnded,
+    Caption(Caption),
+    Chat(ChatEntry),
+    Presence { peer_id: String, status: PresenceStatus },
+    // We were away and alone in the room for idle.leave_empty_room_after, and left it
+    LeftRoom(String),
+    // The server pushed settings; they apply from the next call
+    ServerConfig(ServerConfig),
+    RecordingConsentRequested(ConsentRequest),
+    // Our recording request after each answer; record only once it is granted
+    RecordingConsent(RecordingConsent),
+    // A recording file was closed
+    RecordingSaved(RecordingSegment),
+    Error(String),
+}
+
+// Events raised from webrtc-rs callbacks that need to be handled on the engine task
+enum InternalEvent {
+    LocalCandidate(String),
+    RemoteAudioStarted,
+    RemoteTrackEnded { call: u64, peer_id: String },
+    // Tagged with the call it came from; a closed connection can still report late
+    IceState { call: u64, state: RTCIceConnectionState },
+    // The same for the connections to a mesh call's other callees
+    LegCandidate { call: u64, peer_id: String, candidate: String },
+    LegIceState { call: u64, peer_id: String, state: RTCIceConnectionState },
+    // From the peer over the call's control channel
+    Control { call: u64, message: ControlMessage },
+    NetworkChanged(NetworkChange),
+    RetrySignaling { attempt: u32 },
+    // Restart offers get lost while the other side is still reconnecting to signaling
+    RetryIceRestart { episode: u64, attempt: u32 },
+    ResumeDeadline { episode: u64 },
+    // Our offer in the given call has been waiting this many sends for an answer
+    AnswerTimeout { call: u64, attempt: u32 },
+    IceTimeout { call: u64 },
+    // Calls whoever made it to the conference room by now
+    MergeDeadline { room_id: String },
+    // Queued from places that can't send, like teardown
+    PublishPresence,
+    // Each second's stats of the call's main connection (peer_id None) or of the mesh
+    // leg to peer_id
+    Quality { call: u64, peer_id: Option<String>, quality: ConnectionQuality },
+    WatchdogTick,
+    CheckServers,
+    // Each signaling server's probe result
+    ServerHealth(Vec<(String, bool)>),
+    // How a closed connection got through, or None if it never did; settled tells
+    // whether it got far enough for None to mean the route doesn't work
+    RouteSettled { peer_id: String, route: Option<WarmRoute>, settled: bool },
+}
+
+// What the call's diagnostics panels show, refreshed with its stats at most every
+// stats.ui_refresh and not while paused
+#[derive(Clone, Default)]
+pub struct CallDiagnostics {
+    pub status: ConnectionStatus,
+    pub security: Option<CallSecurity>,
+    // Follows the call's audio to whichever device it moved to
+    pub bluetooth: Option<BluetoothDevice>,
+    pub latency: Option<LatencyBreakdown>,
+    pub history: QualityHistory,
+    // These three stay past the end of the call, for its report
+    pub setup: CallSetupTime,
+    pub sdp: SdpLog,
+    pub negotiation: NegotiationLog,
+    // Each peer's place in the stereo field; empty while spatial audio is off
+    pub pans: Vec<(String, f32)>,
+}
+
+// Workers of the runtime spawn_on_thread makes: one for the engine loop and one for
+// webrtc-rs' transport tasks is plenty for one call at a time
+const ENGINE_THREADS: usize = 2;
+
+#[derive(Clone)]
+pub struct EngineHandle {
+    commands: mpsc::UnboundedSender<EngineCommand>,
+    events: broadcast::Sender<EngineEvent>,
+    local_track: watch::Receiver<Option<Arc<AudioTrack>>>,
+    quality: watch::Receiver<Option<ConnectionQuality>>,
+    peer_qualities: watch::Receiver<HashMap<String, ConnectionQuality>>,
+    diagnostics: watch::Receiver<CallDiagnostics>,
+    stopped: watch::Receiver<bool>,
+    // The runtime the engine task runs on, for shutting down from outside any runtime
+    runtime: tokio::runtime::Handle,
+}
+
+impl EngineHandle {
+    pub fn send(&self, command: EngineCommand) -> Result<()> {
+        self.commands
+            .send(command)
+            .map_err(|_| anyhow!("Call engine has shut down"))
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<EngineEvent> {
+        self.events.subscribe()
+    }
+
+    // Ends any call (the remote side gets EndCall), closes the peer connection and stops
+    // audio, then resolves once the engine task has finished
+    pub async fn shutdown(&self) {
+        let _ = self.send(EngineCommand::Shutdown);
+        let mut stopped = self.stopped.clone();
+        let _ = stopped.wait_for(|stopped| *stopped).await;
+    }
+
+    // shutdown for a thread without a runtime, like a window's close handler. Gives up
+    // waiting after timeout; the engine still stops on its own runtime.
+    pub fn shutdown_blocking(&self, timeout: Duration) {
+        if *self.stopped.borrow() {
+            return;
+        }
+        let (done_tx, done_rx) = std::sync::mpsc::channel();
+        let handle = self.clone();
+        self.runtime.spawn(async move {
+            handle.shutdown().await;
+            let _ = done_tx.send(());
+        });
+        // Also returns once the engine's own runtime has gone, dropping the sender
+        let _ = done_rx.recv_timeout(timeout);
+    }
+
+    // The outgoing audio track of the current call, once one exists
+    pub fn local_track(&self) -> watch::Receiver<Option<Arc<AudioTrack>>> {
+        self.local_track.clone()
+    }
+
+    // The current call's latest stats, updated every second: the worst peer's in a mesh
+    // call. None between calls.
+    pub fn quality(&self) -> watch::Receiver<Option<ConnectionQuality>> {
+        self.quality.clone()
+    }
+
+    // Each participant's latest reading, from the connection their audio comes over: their
+    // own in a mesh call, the shared one otherwise. Empty between calls.
+    pub fn peer_qualities(&self) -> watch::Receiver<HashMap<String, ConnectionQuality>> {
+        self.peer_qualities.clone()
+    }
+
+    pub fn diagnostics(&self) -> watch::Receiver<CallDiagnostics> {
+        self.diagnostics.clone()
+    }
+}
+
+// A call put on hold for another one. Its connection stays up but nothing is captured
+// for it; its events are ignored until it's the current call again.
+struct HeldCall {
+    session: CallSession,
+    webrtc: Arc<WebRTCClient>,
+    remote_peer: Option<String>,
+    media_peer: Option<String>,
+    offerer: bool,
+    call_id: u64,
+}
+
+// Headless call core: owns signaling and the peer connection and is driven by commands,
+// so it can run without the UI (tests, embedding).
+pub struct CallEngine {
+    config: EngineConfig,
+    signaling: Option<SignalingClient>,
+    // signaling_url and its fallbacks
+    servers: SignalingServers,
+    webrtc: Option<Arc<WebRTCClient>>,
+    // A mesh call's connections to every callee but the first, who is on webrtc
+    legs: HashMap<String, Arc<WebRTCClient>>,
+    // Per-callee progress of an outgoing mesh call; empty otherwise
+    call_setup: CallSetup,
+    // Shared by the call's connections
+    negotiation: NegotiationLog,
+    // How recent calls got through, to try first on the next call to the same peer
+    routes: RouteCache,
+    // Built with the first call and reused until the interceptor settings change
+    factory: Option<Arc<WebRTCFactory>>,
+    audio_backend: Arc<dyn AudioBackend>,
+    audio_capture: Option<AudioStreamHandle>,
+    audio_tap_config: Option<TapConfig>,
+    audio_tap: Option<AudioStreamHandle>,
+    session: Option<CallSession>,
+    // There is room for one call on hold
+    held: Option<HeldCall>,
+    // A conference merge we are hosting or were pulled into, until its call starts
+    merge: Option<ConferenceMerge>,
+    remote_peer: Option<String>,
+    // Where the call's offers and candidates go: remote_peer, or the room's relay when
+    // the server designated one as the call started
+    media_peer: Option<String>,
+    media_relays: MediaRelays,
+    // Streams a relay forwards under IDs of its own; handed to each new peer connection
+    track_owners: TrackOwners,
+    // To tell who joined and left from the room's peer lists
+    roster: RoomRoster,
+    // We sent the call's first offer; the offerer also sends every ICE restart, so the
+    // two sides never offer at the same time
+    offerer: bool,
+    // The call's offer until its answer arrives, kept to send again
+    awaiting_answer: Option<String>,
+    call_id: u64,
+    // Ids are handed out from here; call_id goes back to an older one when a held call
+    // is retrieved
+    last_call_id: u64,
+    // The interruption currently being recovered from, if any
+    resuming: Option<u64>,
+    resume_episodes: u64,
+    // Connect was requested, so a lost signaling connection is re-established
+    stay_connected: bool,
+    signaling_lost_at: Option<Instant>,
+    watchdog: Watchdog,
+    one_way: OneWayDetector,
+    // Open until the first ICE connection of the call; renegotiations aren't traced
+    setup_trace: Option<CallTrace>,
+    // Without EngineCommand::Activity we are away once idle.away_after passes
+    presence: PresenceTracker,
+    // Layered over config.webrtc for each new peer connection
+    server_config: Option<ServerConfig>,
+    turn_credentials: Option<TurnCredentialProvider>,
+    // Our own request to record the current call
+    recording_consent: Option<RecordingConsent>,
+    recorder: Option<CallRecorder>,
+    transcriber: Option<Transcriber>,
+    muted: bool,
+    stats_paused: bool,
+    last_diagnostics: Option<Instant>,
+    // Restarted with each peer connection
+    ladder: QualityLadder,
+    redundancy: RedundancySwitch,
+    _network_watcher: Option<NetworkWatcher>,
+    events: broadcast::Sender<EngineEvent>,
+    local_track: watch::Sender<Option<Arc<AudioTrack>>>,
+    quality: watch::Sender<Option<ConnectionQuality>>,
+    peer_qualities: watch::Sender<HashMap<String, ConnectionQuality>>,
+    // For the on_quality_degraded hook; once per bad stretch of the worst peer
+    quality_alarm: QualityAlarm,
+    diagnostics: watch::Sender<CallDiagnostics>,
+    internal_tx: mpsc::UnboundedSender<InternalEvent>,
+    internal_rx: mpsc::UnboundedReceiver<InternalEvent>,
+}
+
+impl CallEngine {
+    pub fn spawn(config: EngineConfig) -> EngineHandle {
+        let (commands_tx, commands_rx) = mpsc::unbounded_channel();
+        let (events, _) = broadcast::channel(64);
+        let (local_track, local_track_rx) = watch::channel(None);
+        let (quality, quality_rx) = watch::channel(None);
+        let (peer_qualities, peer_qualities_rx) = watch::channel(HashMap::new());
+        let (diagnostics, diagnostics_rx) = watch::channel(CallDiagnostics::default());
+        let (internal_tx, internal_rx) = mpsc::unbounded_channel();
+
+        let network_watcher = config.watch_network.then(|| {
+            let (watcher, mut changes) = NetworkWatcher::spawn(DEFAULT_POLL_INTERVAL);
+            let internal_tx = internal_tx.clone();
+            tokio::spawn(async move {
+                while let Some(change) = changes.recv().await {
+                    if internal_tx.send(InternalEvent::NetworkChanged(change)).is_err() {
+                        break;
+                    }
+                }
+            });
+            watcher
+        });
+
+        // Stops once the engine is gone
+        let ticks = internal_tx.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(WATCHDOG_INTERVAL);
+            loop {
+                interval.tick().await;
+                if ticks.send(InternalEvent::WatchdogTick).is_err() {
+                    break;
+                }
+            }
+        });
+
+        // The servers are only known once a domain has been looked up, so the probes are
+        // started from the engine on each tick
+        if let Some(every) = config.signaling_failover.health_check_interval() {
+            let ticks = internal_tx.clone();
+            tokio::spawn(async move {
+                let mut interval = tokio::time::interval(every);
+                loop {
+                    interval.tick().await;
+                    if ticks.send(InternalEvent::CheckServers).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        let servers = SignalingServers::new(&config.signaling_url, &config.signaling_failover);
+
+        let turn_credentials = config.webrtc.turn_rest.clone().map(TurnCredentialProvider::spawn);
+        let ladder = QualityLadder::new(config.webrtc.quality_ladder);
+        let redundancy = RedundancySwitch::new(config.webrtc.redundant_audio, config.webrtc.quality_ladder);
+        let engine = Self {
+            audio_backend: create_audio_backend(&config),
+            presence: PresenceTracker::new(config.idle.away_after()),
+            config,
+            signaling: None,
+            servers,
+            webrtc: None,
+            legs: HashMap::new(),
+            call_setup: CallSetup::default(),
+            negotiation: NegotiationLog::new(),
+            routes: RouteCache::default(),
+            factory: None,
+            audio_capture: None,
+            audio_tap_config: None,
+            audio_tap: None,
+            session: None,
+            held: None,
+            merge: None,
+            remote_peer: None,
+            media_peer: None,
+            media_relays: MediaRelays::default(),
+            track_owners: TrackOwners::default(),
+            roster: RoomRoster::default(),
+            offerer: false,
+            awaiting_answer: None,
+            call_id: 0,
+            last_call_id: 0,
+            resuming: None,
+            resume_episodes: 0,
+            stay_connected: false,
+            signaling_lost_at: None,
+            watchdog: Watchdog::default(),
+            one_way: OneWayDetector::default(),
+            setup_trace: None,
+            server_config: None,
+            turn_credentials,
+            recording_consent: None,
+            recorder: None,
+            transcriber: None,
+            muted: false,
+            stats_paused: false,
+            last_diagnostics: None,
+            ladder,
+            redundancy,
+            _network_watcher: network_watcher,
+            events: events.clone(),
+            local_track,
+            quality,
+            peer_qualities,
+            quality_alarm: QualityAlarm::default(),
+            diagnostics,
+            internal_tx,
+            internal_rx,
+        };
+        let (stopped, stopped_rx) = watch::channel(false);
+        tokio::spawn(async move {
+            engine.run(commands_rx).await;
+            let _ = stopped.send(true);
+        });
+
+        EngineHandle {
+            commands: commands_tx,
+            events,
+            local_track: local_track_rx,
+            quality: quality_rx,
+            peer_qualities: peer_qualities_rx,
+            diagnostics: diagnostics_rx,
+            stopped: stopped_rx,
+            runtime: tokio::runtime::Handle::current(),
+        }
+    }
+
+    // For hosts whose own thread has to stay responsive, like a UI event loop: signaling,
+    // WebRTC and audio all run on a runtime of the engine's own, and the handle's channels
+    // are all the host shares with it. The runtime goes once the engine has shut down.
+    pub fn spawn_on_thread(config: EngineConfig) -> Result<EngineHandle> {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(ENGINE_THREADS)
+            .thread_name("call-engine")
+            .enable_all()
+            .build()?;
+        let handle = {
+            let _context = runtime.enter();
+            Self::spawn(config)
+        };
+        let mut stopped = handle.stopped.clone();
+        std::thread::Builder::new()
+            .name("call-engine".to_string())
+            .spawn(move || {
+                runtime.block_on(async move {
+                    let _ = stopped.wait_for(|stopped| *stopped).await;
+                });
+            })?;
+        Ok(handle)
+    }
+
+    async fn run(mut self, mut commands: mpsc::UnboundedReceiver<EngineCommand>) {
+        loop {
+            tokio::select! {
+                command = commands.recv() => match command {
+                    Some(EngineCommand::Shutdown) | None => break,
+                    Some(command) => {
+                        let result = self.handle_command(command).await;
+                        self.report(result);
+                    }
+                },
+                Some(event) = self.internal_rx.recv() => {
+                    let result = self.handle_internal(event).await;
+                    self.report(result);
+                }
+                message = next_message(&mut self.signaling) => match message {
+                    Some(message) => {
+                        let result = self.handle_message(message).await;
+                        self.report(result);
+                    }
+                    None => {
+                        self.signaling = None;
+                        self.emit(EngineEvent::Disconnected);
+                        // Reconnects go to another server first, if there is one
+                        if let Some(url) = self.servers.current().map(str::to_string) {
+                            self.servers.mark_down(&url, Instant::now());
+                        }
+                        if self.stay_connected {
+                            self.signaling_lost_at = Some(Instant::now());
+                            // Only the server went away; the call renegotiates over its
+                            // control channel if the media path needs it
+                            if !self.control_open() {
+                                self.begin_resume();
+                            }
+                            self.schedule(InternalEvent::RetrySignaling { attempt: 1 }, self.config.reconnect.delay(1));
+                        }
+                    }
+                },
+            }
+        }
+
+        // Closed here rather than in the background like on hang-up: the process may be
+        // about to exit
+        let webrtc = self.webrtc.clone();
+        self.end_held_call().await;
+        self.end_call().await;
+        if let Some(webrtc) = webrtc {
+            webrtc.close().await;
+        }
+        if self.signaling.is_some() {
+            let _ = self.send(SignalingMessage::Disconnect {
+                room_id: self.config.room_id.clone(),
+                peer_id: self.config.peer_id.clone(),
+            }).await;
+        }
+        if let Some(mut signaling) = self.signaling.take() {
+            signaling.close().await;
+        }
+    }
+
+    async fn handle_command(&mut self, command: EngineCommand) -> Result<()> {
+        match command {
+            EngineCommand::Connect => self.connect().await,
+            EngineCommand::Call(peers) => self.call(peers).await,
+            EngineCommand::Answer { call, accepted } => self.answer(call, accepted).await,
+            EngineCommand::HangUp => {
+                self.end_call().await;
+                Ok(())
+            }
+            EngineCommand::Transfer(target) => self.transfer(target).await,
+            EngineCommand::Merge => self.merge_calls().await,
+            EngineCommand::SendChat(text) => {
+                self.send(SignalingMessage::ChatMessage {
+                    room_id: self.config.room_id.clone(),
+                    from_peer: self.config.peer_id.clone(),
+                    text: text.clone(),
+                }).await?;
+                self.emit(EngineEvent::Chat(ChatEntry::new(self.config.peer_id.clone(), text)));
+                Ok(())
+            }
+            EngineCommand::NetworkChanged => self.network_changed(NetworkChange::AddressChanged).await,
+            EngineCommand::RestartIce => {
+                self.begin_resume();
+                self.restart_ice().await
+            }
+            EngineCommand::SetAudioTap(config) => {
+                self.audio_tap = None;
+                self.audio_tap_config = config;
+                if let Some(webrtc) = &self.webrtc {
+                    self.audio_tap = self.audio_tap_config.as_ref().map(|config| webrtc.start_tap(config));
+                }
+                Ok(())
+            }
+            EngineCommand::SendDtmf(tones) => {
+                crate::dtmf::validate_tones(&tones)?;
+                let webrtc = self.webrtc.clone().ok_or_else(|| anyhow!("Not in a call"))?;
+                let events = self.events.clone();
+                let tasks = webrtc.tasks().clone();
+                // Tones take ~170ms each; don't hold up signaling while they play
+                tasks.spawn(async move {
+                    if let Err(e) = webrtc.send_dtmf(&tones).await {
+                        eprintln!("Failed to send DTMF: {}", e);
+                        let _ = events.send(EngineEvent::Error(e.to_string()));
+                    }
+                });
+                Ok(())
+            }
+            EngineCommand::SetMuted(muted) => {
+                self.muted = muted;
+                if let Some(webrtc) = &self.webrtc {
+                    webrtc.audio_track.set_muted(muted);
+                }
+                Ok(())
+            }
+            EngineCommand::RequestRecording(policy) => self.request_recording(policy).await,
+            EngineCommand::RespondRecording { request, granted } => self.respond_recording(request, granted).await,
+            EngineCommand::Activity => {
+                self.presence.activity(Instant::now());
+                self.publish_presence().await
+            }
+            EngineCommand::RetryFailed => self.retry_failed().await,
+            EngineCommand::SetPan { peer_id, pan } => {
+                for webrtc in self.webrtc.iter().chain(self.legs.values()) {
+                    webrtc.set_pan(&peer_id, pan);
+                }
+                Ok(())
+            }
+            EngineCommand::PauseStats(paused) => {
+                self.stats_paused = paused;
+                for webrtc in self.webrtc.iter().chain(self.legs.values()) {
+                    webrtc.quality_monitor.set_paused(paused);
+                }
+                Ok(())
+            }
+            EngineCommand::Reconfigure(config) => {
+                self.reconfigure(*config);
+                Ok(())
+            }
+            EngineCommand::Shutdown => Ok(()),
+        }
+    }
+
+    async fn handle_internal(&mut self, event: InternalEvent) -> Result<()> {
+        match event {
+            InternalEvent::LocalCandidate(candidate) => {
+                if let Some(webrtc) = &self.webrtc {
+                    let to_peer = self.media_peer.as_deref();
+                    webrtc.negotiation_log().record_from(Direction::Sent, NegotiationKind::Candidate, to_peer, &candidate);
+                }
+                if self.signaling.is_none() && self.control_open() {
+                    self.send_control(ControlMessage::IceCandidate { candidate }).await?;
+                } else if let Some(to_peer) = self.media_peer.clone() {
+                    self.send(SignalingMessage::IceCandidate {
+                        room_id: self.config.room_id.clone(),
+                        candidate,
+                        from_peer: self.config.peer_id.clone(),
+                        to_peer,
+                    }).await?;
+                }
+            }
+            InternalEvent::RemoteAudioStarted => {
+                self.emit(EngineEvent::RemoteAudioStarted);
+                if let Some(webrtc) = &self.webrtc {
+                    let setup = webrtc.setup_time();
+                    self.diagnostics.send_modify(|diagnostics| diagnostics.setup = setup);
+                    self.emit(EngineEvent::SetupTime(setup));
+                }
+            }
+            InternalEvent::RemoteTrackEnded { call, peer_id } if call == self.call_id => {
+                self.emit(EngineEvent::RemoteTrackEnded(peer_id));
+            }
+            InternalEvent::RemoteTrackEnded { .. } => {}
+            InternalEvent::IceState { call, state } if call == self.call_id => {
+                if let Some(webrtc) = &self.webrtc {
+                    let status = webrtc.connection_monitor.subscribe().borrow().clone();
+                    self.diagnostics.send_modify(|diagnostics| diagnostics.status = status);
+                }
+                self.ice_state_changed(state).await?;
+            }
+            InternalEvent::IceState { .. } => {}
+            InternalEvent::LegCandidate { call, peer_id, candidate } if call == self.call_id => {
+                if let Some(leg) = self.legs.get(&peer_id) {
+                    leg.negotiation_log().record_from(Direction::Sent, NegotiationKind::Candidate, Some(&peer_id), &candidate);
+                }
+                self.send(SignalingMessage::IceCandidate {
+                    room_id: self.config.room_id.clone(),
+                    candidate,
+                    from_peer: self.config.peer_id.clone(),
+                    to_peer: peer_id,
+                }).await?;
+            }
+            InternalEvent::LegCandidate { .. } => {}
+            InternalEvent::LegIceState { call, peer_id, state } if call == self.call_id && self.legs.contains_key(&peer_id) => {
+                match state {
+                    RTCIceConnectionState::Connected | RTCIceConnectionState::Completed => {
+                        self.call_setup.update(&peer_id, PeerSetup::Connected);
+                        self.emit(EngineEvent::CallSetup(self.call_setup.clone()));
+                    }
+                    RTCIceConnectionState::Failed => {
+                        self.drop_peer(&peer_id, PeerSetup::Failed("ICE failed".to_string())).await;
+                    }
+                    _ => {}
+                }
+            }
+            InternalEvent::LegIceState { .. } => {}
+            InternalEvent::Control { call, message } if call == self.call_id => {
+                self.handle_control(message).await?;
+            }
+            InternalEvent::Control { .. } => {}
+            InternalEvent::NetworkChanged(change) => self.network_changed(change).await?,
+            InternalEvent::RetrySignaling { attempt } => self.retry_signaling(attempt).await?,
+            InternalEvent::RetryIceRestart { episode, attempt } if self.resuming == Some(episode) => {
+                self.restart_ice().await?;
+                if attempt < self.config.reconnect.max_attempts {
+                    let attempt = attempt + 1;
+                    self.schedule(InternalEvent::RetryIceRestart { episode, attempt }, self.config.reconnect.delay(attempt));
+                }
+            }
+            InternalEvent::RetryIceRestart { .. } => {}
+            InternalEvent::ResumeDeadline { episode } if self.resuming == Some(episode) => {
+                self.end_call().await;
+                return Err(anyhow!("The call could not be resumed after the connection dropped"));
+            }
+            InternalEvent::ResumeDeadline { .. } => {}
+            InternalEvent::AnswerTimeout { call, attempt } if call == self.call_id && self.awaiting_answer.is_some() => {
+                if attempt >= self.config.webrtc.offer_attempts {
+                    let peer = self.remote_peer.clone().unwrap_or_default();
+                    self.fail_call();
+                    self.end_call().await;
+                    return Err(anyhow!("{} did not respond to the call", peer));
+                }
+                self.deliver_offer(attempt + 1).await?;
+            }
+            InternalEvent::AnswerTimeout { .. } => {}
+            InternalEvent::IceTimeout { call } if call == self.call_id => self.ice_timed_out().await?,
+            InternalEvent::IceTimeout { .. } => {}
+            InternalEvent::MergeDeadline { room_id } => self.merge_deadline(room_id).await?,
+            InternalEvent::PublishPresence => self.publish_presence().await?,
+            // A last reading can arrive after the call was torn down
+            InternalEvent::Quality { call, peer_id, quality } if call == self.call_id && self.webrtc.is_some() => {
+                self.record_quality(peer_id.as_deref(), &quality);
+                // Legs only add their readings; the main connection's move the rest along
+                if peer_id.is_none() {
+                    self.follow_quality(quality).await?;
+                }
+            }
+            InternalEvent::Quality { .. } => {}
+            InternalEvent::WatchdogTick => self.watchdog_tick().await?,
+            InternalEvent::CheckServers => self.check_servers(),
+            InternalEvent::ServerHealth(results) => self.servers.apply_health(&results, Instant::now()),
+            InternalEvent::RouteSettled { peer_id, route, settled } => match route {
+                Some(route) => self.routes.remember(&peer_id, route),
+                None if settled => self.routes.forget(&peer_id),
+                None => {}
+            },
+        }
+        Ok(())
+    }
+
+    // The peer-to-peer counterpart of Offer/Answer/IceCandidate, used while the
+    // signaling server is unreachable
+    async fn handle_control(&mut self, message: ControlMessage) -> Result<()> {
+        let Some(webrtc) = self.webrtc.clone() else { return Ok(()) };
+        match message {
+            ControlMessage::Offer { sdp } => {
+                webrtc.set_ice_servers(&self.webrtc_config().ice_servers).await?;
+                let sdp = webrtc.handle_offer(sdp).await?;
+                self.send_control(ControlMessage::Answer { sdp }).await?;
+                self.renegotiated_directly();
+            }
+            ControlMessage::Answer { sdp } => {
+                webrtc.handle_answer(sdp).await?;
+                self.renegotiated_directly();
+            }
+            ControlMessage::IceCandidate { candidate } => {
+                let from_peer = self.media_peer.clone().unwrap_or_default();
+                webrtc.add_ice_candidate(&from_peer, candidate).await?;
+            }
+            ControlMessage::Renegotiate => self.renegotiate().await?,
+        }
+        Ok(())
+    }
+
+    // Messages crossing the control channel prove the media path works, so an
+    // interrupted call is back even if ICE never left Connected
+    fn renegotiated_directly(&mut self) {
+        let connected = self.webrtc.as_ref().map_or(false, |webrtc| {
+            matches!(
+                webrtc.peer_connection.ice_connection_state(),
+                RTCIceConnectionState::Connected | RTCIceConnectionState::Completed
+            )
+        });
+        if connected && self.resuming.take().is_some() {
+            self.emit(EngineEvent::CallResumed);
+        }
+    }
+
+    async fn handle_message(&mut self, msg: SignalingMessage) -> Result<()> {
+        if self.handle_held_media(&msg).await? {
+            return Ok(());
+        }
+        match msg {
+            SignalingMessage::PeerList { peers } => {
+                let peers: Vec<String> = peers.into_iter().filter(|p| *p != self.config.peer_id).collect();
+                let diff = self.roster.update(&self.config.room_id, &peers);
+                let merge_ready = self.hosted_merge().map_or(false, |merge| merge.everyone_arrived(&peers));
+                self.emit(EngineEvent::PeerList(peers));
+                for peer_id in &diff.left {
+                    self.emit(EngineEvent::PeerLeft(peer_id.clone()));
+                }
+                self.presence.set_alone(peers.is_empty(), Instant::now());
+                // Someone who just joined hasn't heard our status yet
+                self.presence.reset();
+                self.publish_presence().await?;
+                let room = self.config.room_id.clone();
+                for peer_id in diff.joined {
+                    self.emit(EngineEvent::PeerJoined(peer_id.clone()));
+                    let actions = self.script_actions(|scripts| scripts.peer_joined(&room, &peer_id));
+                    self.run_script_actions(actions).await?;
+                }
+                if merge_ready {
+                    if let Some(merge) = self.merge.take() {
+                        self.call(merge.peers).await?;
+                    }
+                }
+            }
+            SignalingMessage::CallRequest { room_id, from_peer, to_peers, protocol_version } => {
+                if !to_peers.contains(&self.config.peer_id) {
+                    return Ok(());
+                }
+                if let Err(e) = check_protocol_version(&from_peer, protocol_version) {
+                    self.send(SignalingMessage::ProtocolMismatch {
+                        from_peer: self.config.peer_id.clone(),
+                        to_peer: from_peer,
+                        protocol_version: PROTOCOL_VERSION,
+                        min_protocol_version: MIN_PROTOCOL_VERSION,
+                    }).await?;
+                    return Err(e);
+                }
+                let call = IncomingCall { room_id, from_peer };
+                // The call we moved rooms for
+                if self.merge.as_ref().map_or(false, |merge| merge.host == call.from_peer && merge.room_id == call.room_id) {
+                    self.merge = None;
+                    return self.answer(call, true).await;
+                }
+                let actions = self.script_actions(|scripts| scripts.incoming_call(&call));
+                let decision = actions.iter().rev().find_map(|action| match action {
+                    ScriptAction::Answer => Some(true),
+                    ScriptAction::Decline => Some(false),
+                    _ => None,
+                });
+                match decision.or(self.auto_answers(&call.from_peer).then_some(true)) {
+                    Some(accepted) => self.answer(call, accepted).await?,
+                    None => self.emit(EngineEvent::IncomingCall(call)),
+                }
+                self.run_script_actions(actions).await?;
+            }
+            SignalingMessage::CallResponse { from_peer, accepted, .. } => {
+                if !accepted {
+                    self.emit(EngineEvent::CallDeclined { peer_id: from_peer.clone() });
+                    self.drop_peer(&from_peer, PeerSetup::Declined).await;
+                    return Ok(());
+                }
+
+                // One of a mesh call's other callees: their own connection offers
+                if let Some(leg) = self.legs.get(&from_peer).cloned() {
+                    let sdp = leg.create_offer().await?;
+                    return self.send(SignalingMessage::Offer {
+                        room_id: self.config.room_id.clone(),
+                        sdp,
+                        from_peer: self.config.peer_id.clone(),
+                        to_peer: from_peer,
+                    }).await;
+                }
+
+                // Callee accepted: we are the offerer
+                if self.webrtc.is_some() {
+                    self.remote_peer = Some(from_peer.clone());
+                    self.media_peer = Some(self.media_relays.media_peer(&self.config.room_id, &from_peer));
+                    self.send_offer().await?;
+                }
+            }
+            SignalingMessage::Offer { sdp, from_peer, room_id, .. } => {
+                if let Some(leg) = self.legs.get(&from_peer).cloned() {
+                    let answer = leg.handle_offer(sdp).await?;
+                    return self.send(SignalingMessage::Answer {
+                        room_id,
+                        sdp: answer,
+                        from_peer: self.config.peer_id.clone(),
+                        to_peer: from_peer,
+                    }).await;
+                }
+                if let Some(webrtc) = self.webrtc.clone() {
+                    // A renegotiation, most likely an ICE restart: use fresh TURN credentials
+                    if self.call_established() {
+                        webrtc.set_ice_servers(&self.webrtc_config().ice_servers).await?;
+                    }
+                    // The relay carries the media but isn't who we are talking to
+                    if !self.media_relays.is_relay(&room_id, &from_peer) {
+                        self.remote_peer = Some(from_peer.clone());
+                    }
+                    self.media_peer = Some(from_peer.clone());
+                    self.trace_phase("call.answer");
+                    let answer = webrtc.handle_offer(sdp).await?;
+                    self.send(SignalingMessage::Answer {
+                        room_id,
+                        sdp: answer,
+                        from_peer: self.config.peer_id.clone(),
+                        to_peer: from_peer,
+                    }).await?;
+                    self.trace_phase("call.ice");
+                    self.mark_active();
+                }
+            }
+            SignalingMessage::Answer { sdp, from_peer, .. } => {
+                if let Some(leg) = self.legs.get(&from_peer).cloned() {
+                    if leg.peer_connection.signaling_state() != RTCSignalingState::Stable {
+                        leg.handle_answer(sdp).await?;
+                        self.mark_active();
+                    }
+                    return Ok(());
+                }
+                if let Some(webrtc) = self.webrtc.clone() {
+                    // An offer sent again can be answered twice
+                    if webrtc.peer_connection.signaling_state() == RTCSignalingState::Stable {
+                        return Ok(());
+                    }
+                    self.awaiting_answer = None;
+                    webrtc.handle_answer(sdp).await?;
+                    self.trace_phase("call.ice");
+                    self.mark_active();
+                }
+            }
+            SignalingMessage::IceCandidate { candidate, from_peer, .. } => {
+                if let Some(webrtc) = self.legs.get(&from_peer).or(self.webrtc.as_ref()) {
+                    webrtc.add_ice_candidate(&from_peer, candidate).await?;
+                }
+            }
+            SignalingMessage::EndCall { peer_id, .. } => {
+                if self.in_mesh_call(&peer_id) {
+                    self.drop_peer(&peer_id, PeerSetup::Failed("hung up".to_string())).await;
+                } else if self.remote_peer.as_deref() == Some(peer_id.as_str()) {
+                    self.teardown();
+                } else if self.held_with(&peer_id) {
+                    self.drop_held();
+                }
+            }
+            // Only whoever we're talking to can hand us on
+            SignalingMessage::Transfer { from_peer, target, .. } => {
+                if self.remote_peer.as_deref() == Some(from_peer.as_str()) && target != self.config.peer_id {
+                    self.end_call().await;
+                    self.emit(EngineEvent::Transferred { by: from_peer, target: target.clone() });
+                    self.call(vec![target]).await?;
+                }
+            }
+            // Only whoever we're talking to can pull us into a conference, and not while
+            // we have another call on hold
+            SignalingMessage::MergeCalls { from_peer, to_peers, target_room, .. } => {
+                if self.remote_peer.as_deref() == Some(from_peer.as_str())
+                    && self.held.is_none()
+                    && to_peers.contains(&self.config.peer_id)
+                {
+                    // The host already hung up on their side
+                    self.teardown();
+                    self.emit(EngineEvent::Merging { host: from_peer.clone(), room_id: target_room.clone() });
+                    self.merge = Some(ConferenceMerge { room_id: target_room.clone(), host: from_peer, peers: to_peers });
+                    self.enter_room(target_room).await?;
+                }
+            }
+            // Losing signaling doesn't end an established call: the peer may just be
+            // switching networks and come back within the resume deadline
+            SignalingMessage::ConnectionLost { peer_id } => {
+                let ours = |peer: &Option<String>| peer.as_deref() == Some(peer_id.as_str());
+                if self.in_mesh_call(&peer_id) {
+                    // Mesh calls aren't resumed; the others carry on
+                    self.drop_peer(&peer_id, PeerSetup::Failed("disconnected".to_string())).await;
+                } else if ours(&self.remote_peer) || ours(&self.media_peer) {
+                    if self.call_established() && self.config.reconnect.resume_calls {
+                        self.begin_resume();
+                    } else {
+                        self.teardown();
+                    }
+                } else if self.held_with(&peer_id) {
+                    // Held calls aren't resumed
+                    self.drop_held();
+                }
+            }
+            SignalingMessage::ChatMessage { from_peer, text, .. } => {
+                self.emit(EngineEvent::Chat(ChatEntry::new(from_peer, text)));
+            }
+            SignalingMessage::MediaRelay { room_id, relay_peer } => {
+                // Calls already set up keep their route
+                let relay_peer = relay_peer.filter(|relay| *relay != self.config.peer_id);
+                self.media_relays.assign(&room_id, relay_peer);
+            }
+            // Only the relay speaks for streams it forwards; anyone else only for their own
+            SignalingMessage::TrackInfo { room_id, from_peer, stream_id, peer_id, .. }
+                if peer_id == from_peer || self.media_relays.is_relay(&room_id, &from_peer) =>
+            {
+                self.track_owners.assign(&stream_id, &peer_id);
+                if let Some(ref webrtc) = self.webrtc {
+                    webrtc.assign_track(&stream_id, &peer_id);
+                }
+            }
+            SignalingMessage::ServerConfig { config } => {
+                self.server_config = Some(config.clone());
+                self.emit(EngineEvent::ServerConfig(config));
+            }
+            SignalingMessage::Presence { peer_id, status, .. } if peer_id != self.config.peer_id => {
+                self.emit(EngineEvent::Presence { peer_id, status });
+            }
+            SignalingMessage::RecordingConsentRequest { room_id, from_peer, request_id, policy, .. } => {
+                let request = ConsentRequest { room_id, from_peer, request_id, policy };
+                if self.remote_peer.as_deref() == Some(request.from_peer.as_str()) {
+                    self.emit(EngineEvent::RecordingConsentRequested(request));
+                } else {
+                    // Nobody gets to record a call we aren't in
+                    self.respond_recording(request, false).await?;
+                }
+            }
+            SignalingMessage::RecordingConsentResponse { from_peer, request_id, granted, .. } => {
+                if let Some(consent) = self.recording_consent.as_mut().filter(|consent| consent.request_id() == request_id) {
+                    if consent.respond(&from_peer, granted) {
+                        let consent = consent.clone();
+                        self.emit(EngineEvent::RecordingConsent(consent));
+                        self.update_recorder();
+                    }
+                }
+            }
+            SignalingMessage::ProtocolMismatch { from_peer, min_protocol_version, .. } => {
+                // Only abandon a call that was still waiting on this peer
+                if self.session.is_some() && self.remote_peer.is_none() {
+                    self.fail_call();
+                    self.teardown();
+                }
+                return Err(anyhow!(
+                    "{} requires signaling protocol v{} or newer, but this client speaks v{}; please update",
+                    from_peer, min_protocol_version, PROTOCOL_VERSION
+                ));
+            }
+            SignalingMessage::Error { message } => {
+                return Err(anyhow!("Signaling error: {}", message));
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    async fn connect(&mut self) -> Result<()> {
+        self.stay_connected = true;
+        let started = Instant::now();
+        let result = self.join().await;
+        let error = result.as_ref().err().map(|e| e.to_string());
+        telemetry::record_span(
+            "signaling.join",
+            started,
+            &[("room.id", self.config.room_id.clone()), ("server.url", self.servers.current().unwrap_or(&self.config.signaling_url).to_string())],
+            error.as_deref(),
+        );
+        result?;
+        self.emit(EngineEvent::Connected);
+        Ok(())
+    }
+
+    async fn join(&mut self) -> Result<()> {
+        // A domain is looked up again on every connect, in case its servers moved
+        let (url, failover_config) = discovery::resolve(&self.config.signaling_url, &self.config.signaling_failover).await?;
+        self.servers.configure(&url, &failover_config);
+        let (mut client, _) =
+            failover::connect_any(&mut self.servers, self.config.signaling_token.as_deref(), self.config.signaling_channels).await?;
+        if let Some(ref identity) = self.config.identity {
+            client.set_identity(identity.clone());
+        }
+        self.signaling = Some(client);
+        self.send_join().await
+    }
+
+    fn reconfigure(&mut self, mut config: EngineConfig) {
+        if self.signaling.is_some() {
+            // Whoever we are in the room we are in stays that way until we leave it
+            config.signaling_url = self.config.signaling_url.clone();
+            config.signaling_token = self.config.signaling_token.clone();
+            config.signaling_failover = self.config.signaling_failover.clone();
+            config.room_id = self.config.room_id.clone();
+            config.peer_id = self.config.peer_id.clone();
+            config.identity = self.config.identity.clone();
+        } else if config.signaling_url != self.config.signaling_url || config.signaling_failover != self.config.signaling_failover {
+            self.servers = SignalingServers::new(&config.signaling_url, &config.signaling_failover);
+        }
+        if config.webrtc.turn_rest != self.config.webrtc.turn_rest {
+            self.turn_credentials = config.webrtc.turn_rest.clone().map(TurnCredentialProvider::spawn);
+        }
+        if config.audio_backend != self.config.audio_backend
+            || config.audio_buffer_frames != self.config.audio_buffer_frames
+            || config.capture_source != self.config.capture_source
+        {
+            // The current call keeps its devices
+            self.audio_backend = create_audio_backend(&config);
+        }
+        self.presence.set_idle_timeout(config.idle.away_after());
+        let delay = Duration::from_millis(config.playback_delay_ms as u64);
+        let current = self.webrtc.as_ref().map(|webrtc| webrtc.audio_backend.clone());
+        for backend in std::iter::once(self.audio_backend.clone()).chain(current) {
+            backend.set_output_routing(config.output_routing.clone());
+            backend.set_playback_delay(delay);
+            if let Some(sidetone) = backend.sidetone() {
+                sidetone.set_level(config.sidetone_level);
+            }
+        }
+        for webrtc in self.webrtc.iter().chain(self.legs.values()) {
+            webrtc.set_loudness_normalization(config.webrtc.normalize_loudness);
+            webrtc.set_spatial_audio(config.webrtc.spatial_audio);
+        }
+        self.config = config;
+    }
+
+    fn check_servers(&self) {
+        let urls = self.servers.urls();
+        if urls.len() < 2 {
+            return;
+        }
+        let within = self.servers.config().connect_timeout();
+        let internal_tx = self.internal_tx.clone();
+        tokio::spawn(async move {
+            let results = failover::check_health(urls, within).await;
+            let _ = internal_tx.send(InternalEvent::ServerHealth(results));
+        });
+    }
+
+    // Moves to another room on the same signaling connection
+    async fn enter_room(&mut self, room_id: String) -> Result<()> {
+        self.config.room_id = room_id;
+        self.send_join().await
+    }
+
+    async fn send_join(&mut self) -> Result<()> {
+        // The server tells us again after Join, and the relay about its streams
+        self.media_relays.clear();
+        self.track_owners.clear();
+        self.send(SignalingMessage::Join {
+            room_id: self.config.room_id.clone(),
+            peer_id: self.config.peer_id.clone(),
+            protocol_version: PROTOCOL_VERSION,
+        }).await?;
+        self.presence.reset();
+        self.publish_presence().await
+    }
+
+    async fn publish_presence(&mut self) -> Result<()> {
+        if self.signaling.is_none() {
+            return Ok(());
+        }
+        let Some(status) = self.presence.take_change(Instant::now()) else { return Ok(()) };
+        self.send(SignalingMessage::Presence {
+            room_id: self.config.room_id.clone(),
+            peer_id: self.config.peer_id.clone(),
+            status,
+        }).await
+    }
+
+    async fn call(&mut self, peers: Vec<String>) -> Result<()> {
+        if peers.is_empty() {
+            return Err(anyhow!("No peers selected"));
+        }
+        self.hold_current()?;
+
+        // Without a relay to send everything through, each callee gets a connection of
+        // their own
+        let relay = self.media_relays.relay_for(&self.config.room_id).map(str::to_string);
+        let mesh = relay.is_none() && peers.len() > 1;
+        let mut trace = CallTrace::start(CallRole::Caller, &self.config.room_id, &peers);
+        trace.phase("call.peer_connection");
+        if let Err(e) = self.create_peer_connection(Some(relay.as_deref().unwrap_or(&peers[0]))).await {
+            trace.fail(&e.to_string());
+            return Err(e);
+        }
+        let peers = if mesh {
+            self.call_setup = CallSetup::new(&peers);
+            self.call_setup.update(&peers[0], PeerSetup::Ringing);
+            self.add_legs(&peers[1..]).await?;
+            self.emit(EngineEvent::CallSetup(self.call_setup.clone()));
+            // Only those with a connection get rung
+            peers.into_iter().filter(|peer| !matches!(self.call_setup.get(peer), Some(PeerSetup::Failed(_)))).collect()
+        } else {
+            peers
+        };
+        // Until a callee accepts
+        trace.phase("call.request");
+        self.setup_trace = Some(trace);
+        let session = CallSession::new(self.config.room_id.clone(), peers.clone());
+        self.emit(EngineEvent::CallStarted(session.clone()));
+        self.session = Some(session);
+
+        self.send(SignalingMessage::CallRequest {
+            room_id: self.config.room_id.clone(),
+            from_peer: self.config.peer_id.clone(),
+            to_peers: peers,
+            protocol_version: PROTOCOL_VERSION,
+        }).await?;
+        self.presence.set_in_call(true);
+        self.publish_presence().await
+    }
+
+    async fn answer(&mut self, call: IncomingCall, accepted: bool) -> Result<()> {
+        if accepted {
+            if let Err(e) = self.hold_current() {
+                self.send(SignalingMessage::CallResponse {
+                    room_id: call.room_id,
+                    from_peer: self.config.peer_id.clone(),
+                    to_peer: call.from_peer,
+                    accepted: false,
+                }).await?;
+                return Err(e);
+            }
+            let mut trace = CallTrace::start(CallRole::Callee, &call.room_id, &[call.from_peer.clone()]);
+            trace.phase("call.peer_connection");
+            let media_peer = self.media_relays.media_peer(&call.room_id, &call.from_peer);
+            if let Err(e) = self.create_peer_connection(Some(&media_peer)).await {
+                trace.fail(&e.to_string());
+                return Err(e);
+            }
+            // Until the caller's offer arrives
+            trace.phase("call.offer");
+            self.setup_trace = Some(trace);
+            let session = CallSession::new(call.room_id.clone(), vec![call.from_peer.clone()]);
+            self.emit(EngineEvent::CallStarted(session.clone()));
+            self.session = Some(session);
+            self.remote_peer = Some(call.from_peer.clone());
+            self.media_peer = Some(media_peer);
+            self.presence.set_in_call(true);
+        }
+
+        self.send(SignalingMessage::CallResponse {
+            room_id: call.room_id,
+            from_peer: self.config.peer_id.clone(),
+            to_peer: call.from_peer.clone(),
+            accepted,
+        }).await?;
+        // The relay never calls anyone, so each side offers to it on its own
+        if accepted && self.media_peer.as_deref() != Some(call.from_peer.as_str()) {
+            self.send_offer().await?;
+        }
+        self.publish_presence().await
+    }
+
+    fn auto_answers(&self, from_peer: &str) -> bool {
+        self.config.auto_answer
+            && (self.config.auto_answer_allowlist.is_empty() || self.config.auto_answer_allowlist.iter().any(|peer| peer == from_peer))
+    }
+
+    async fn send_offer(&mut self) -> Result<()> {
+        let (Some(webrtc), Some(_)) = (self.webrtc.clone(), self.media_peer.as_ref()) else {
+            return Ok(());
+        };
+        self.offerer = true;
+        self.trace_phase("call.offer");
+        self.awaiting_answer = Some(webrtc.create_offer().await?);
+        self.deliver_offer(1).await?;
+        self.trace_phase("call.answer");
+        Ok(())
+    }
+
+    // A lost message would otherwise leave the call dialing forever
+    async fn deliver_offer(&mut self, attempt: u32) -> Result<()> {
+        let (Some(sdp), Some(to_peer)) = (self.awaiting_answer.clone(), self.media_peer.clone()) else {
+            return Ok(());
+        };
+        // Before sending, so a send that fails is retried too
+        self.schedule(InternalEvent::AnswerTimeout { call: self.call_id, attempt }, self.config.webrtc.answer_timeout());
+        self.send(SignalingMessage::Offer {
+            room_id: self.config.room_id.clone(),
+            sdp,
+            from_peer: self.config.peer_id.clone(),
+            to_peer,
+        }).await
+    }
+
+    // Connections for a mesh call's callees past the first, sharing its microphone track.
+    // Whoever's can't be set up is marked failed and the rest carry on.
+    async fn add_legs(&mut self, peers: &[String]) -> Result<()> {
+        let Some(primary) = self.webrtc.clone() else { return Ok(()) };
+        let config = self.webrtc_config();
+        let factory = self.factory_for(&config)?;
+        let routes: HashMap<String, WarmRoute> =
+            peers.iter().filter_map(|peer| Some((peer.clone(), self.routes.get(peer)?.clone()))).collect();
+        let backend = self.audio_backend.clone();
+        let negotiation = self.negotiation.clone();
+        let mut results = set_up_peers(peers.to_vec(), MAX_PARALLEL_SETUPS, |peer| {
+            let mut builder = WebRTCClient::builder()
+                .config(&config)
+                .audio_backend(backend.clone())
+                .audio_track(primary.audio_track.clone())
+                .factory(factory.clone())
+                .negotiation_log(negotiation.for_peer(&peer));
+            if let Some(route) = routes.get(&peer) {
+                builder = builder.warm_route(route.clone());
+            }
+            async move { builder.build().await }
+        });
+        while let Some((peer, result)) = results.next().await {
+            match result {
+                Ok(leg) => {
+                    let leg = Arc::new(leg);
+                    self.follow_leg(&peer, &leg).await;
+                    self.legs.insert(peer.clone(), leg);
+                    self.call_setup.update(&peer, PeerSetup::Ringing);
+                }
+                Err(e) => {
+                    eprintln!("Could not set up the connection to {}: {}", peer, e);
+                    self.call_setup.update(&peer, PeerSetup::Failed(e.to_string()));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Candidates, ICE states and stats of one leg, through the engine task
+    async fn follow_leg(&self, peer_id: &str, leg: &WebRTCClient) {
+        let call = self.call_id;
+        let internal_tx = self.internal_tx.clone();
+        let peer = peer_id.to_string();
+        leg.peer_connection.on_ice_candidate(Box::new(move |candidate: Option<RTCIceCandidate>| {
+            let internal_tx = internal_tx.clone();
+            let peer_id = peer.clone();
+            Box::pin(async move {
+                if let Some(init) = candidate.and_then(|candidate| candidate.to_json().ok()) {
+                    let _ = internal_tx.send(InternalEvent::LegCandidate { call, peer_id, candidate: init.candidate });
+                }
+            })
+        }));
+
+        let mut status = leg.connection_monitor.subscribe();
+        let internal_tx = self.internal_tx.clone();
+        let peer_id = peer_id.to_string();
+        leg.tasks().spawn(async move {
+            let mut last = status.borrow().ice_state;
+            while status.changed().await.is_ok() {
+                let state = status.borrow().ice_state;
+                if state != last {
+                    last = state;
+                    if internal_tx.send(InternalEvent::LegIceState { call, peer_id: peer_id.clone(), state }).is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+        self.watch_quality(leg, Some(peer_id.to_string())).await;
+    }
+
+    // Each reading of the connection as InternalEvent::Quality; peer_id None for the
+    // call's main connection
+    async fn watch_quality(&self, webrtc: &WebRTCClient, peer_id: Option<String>) {
+        webrtc.quality_monitor.start_monitoring().await;
+        webrtc.quality_monitor.set_paused(self.stats_paused);
+        let mut quality = webrtc.quality_monitor.subscribe();
+        let internal_tx = self.internal_tx.clone();
+        let call = self.call_id;
+        webrtc.tasks().spawn(async move {
+            while quality.changed().await.is_ok() {
+                let reading = quality.borrow().clone();
+                if internal_tx.send(InternalEvent::Quality { call, peer_id: peer_id.clone(), quality: reading }).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+
+    // Everyone whose audio comes over the call's main connection rather than a leg of
+    // their own; through a relay that is the whole call
+    fn main_connection_peers(&self) -> Vec<String> {
+        self.session
+            .as_ref()
+            .map(|session| session.participants.iter().filter(|peer| !self.legs.contains_key(*peer)).cloned().collect())
+            .unwrap_or_default()
+    }
+
+    // A leg's reading is its peer's; the main connection's is everyone's it carries
+    fn record_quality(&self, peer_id: Option<&str>, quality: &ConnectionQuality) {
+        let peers: Vec<String> = match peer_id {
+            Some(peer) if self.legs.contains_key(peer) => vec![peer.to_string()],
+            // From a leg that was dropped since
+            Some(_) => return,
+            None => self.main_connection_peers(),
+        };
+        self.peer_qualities.send_modify(|readings| {
+            for peer in peers {
+                readings.insert(peer, quality.clone());
+            }
+        });
+    }
+
+    // Once per reading of the main connection. What we send goes to every peer, so the
+    // ladder, RED and the alarm follow the worst of them.
+    async fn follow_quality(&mut self, quality: ConnectionQuality) -> Result<()> {
+        let worst = ConnectionQuality::worst(self.peer_qualities.borrow().values()).unwrap_or_else(|| quality.clone());
+        let now = Instant::now();
+        let step = self.ladder.observe(&worst, now);
+        if let Some(on) = self.redundancy.observe(&worst, now) {
+            self.set_redundancy(on, worst.packet_loss_rate).await;
+        }
+        self.refresh_diagnostics(&quality).await;
+        self.quality.send_replace(Some(worst.clone()));
+        self.check_one_way_audio().await;
+        let room = self.config.room_id.clone();
+        let alarm_hooked = self.config.scripts.as_ref().map_or(false, |scripts| scripts.has_hook("on_quality_degraded"));
+        if alarm_hooked && matches!(self.quality_alarm.check(&room, &worst), Some(CallEvent::QualityAlert { .. })) {
+            let actions = self.script_actions(|scripts| scripts.quality_degraded(&room, &worst));
+            self.run_script_actions(actions).await?;
+        }
+        if let Some(step) = step {
+            self.step_quality(step).await?;
+        }
+        Ok(())
+    }
+
+    fn in_mesh_call(&self, peer_id: &str) -> bool {
+        self.legs.contains_key(peer_id) || (!self.legs.is_empty() && self.remote_peer.as_deref() == Some(peer_id))
+    }
+
+    // One callee declined, failed to connect or dropped out. The call goes on with
+    // everyone else and only ends when nobody is left.
+    async fn drop_peer(&mut self, peer_id: &str, setup: PeerSetup) {
+        if let Some(leg) = self.legs.remove(peer_id) {
+            self.close_connection(Some(peer_id.to_string()), leg);
+        } else if self.remote_peer.as_deref() == Some(peer_id) {
+            // The first callee's connection carries the call's microphone track; it stays
+            // open, idle, until the call ends
+            self.remote_peer = None;
+            self.media_peer = None;
+            self.awaiting_answer = None;
+        }
+        self.peer_qualities.send_modify(|readings| {
+            readings.remove(peer_id);
+        });
+        self.one_way.remove(peer_id);
+        let declined = setup == PeerSetup::Declined;
+        if !self.call_setup.is_empty() {
+            self.call_setup.update(peer_id, setup);
+            self.emit(EngineEvent::CallSetup(self.call_setup.clone()));
+        }
+        let Some(session) = self.session.as_mut() else { return };
+        if !session.participants.iter().any(|peer| peer == peer_id) {
+            return;
+        }
+        if declined {
+            session.decline(peer_id);
+        } else {
+            session.remove_participant(peer_id);
+            if session.participants.is_empty() {
+                session.fail();
+            }
+        }
+        let session = session.clone();
+        self.emit(EngineEvent::CallUpdated(session.clone()));
+        if session.participants.is_empty() {
+            self.end_call().await;
+        }
+    }
+
+    // Calls everyone who declined or failed once more, on new connections next to the
+    // ones still up
+    async fn retry_failed(&mut self) -> Result<()> {
+        let peers = self.call_setup.failed();
+        if peers.is_empty() || self.session.is_none() {
+            return Ok(());
+        }
+        for peer in &peers {
+            self.call_setup.update(peer, PeerSetup::Preparing);
+        }
+        self.add_legs(&peers).await?;
+        self.emit(EngineEvent::CallSetup(self.call_setup.clone()));
+        let ready: Vec<String> = peers.into_iter().filter(|peer| self.legs.contains_key(peer)).collect();
+        if ready.is_empty() {
+            return Ok(());
+        }
+        if let Some(session) = self.session.as_mut() {
+            for peer in &ready {
+                session.add_participant(peer.clone());
+            }
+            let session = session.clone();
+            self.emit(EngineEvent::CallUpdated(session));
+        }
+        self.send(SignalingMessage::CallRequest {
+            room_id: self.config.room_id.clone(),
+            from_peer: self.config.peer_id.clone(),
+            to_peers: ready,
+            protocol_version: PROTOCOL_VERSION,
+        }).await
+    }
+
+    async fn transfer(&mut self, target: String) -> Result<()> {
+        let peer = self.remote_peer.clone().ok_or_else(|| anyhow!("Not in a call"))?;
+        if target == peer || target == self.config.peer_id {
+            return Err(anyhow!("Can't transfer the call with {} to {}", peer, target));
+        }
+        self.send(SignalingMessage::Transfer {
+            room_id: self.config.room_id.clone(),
+            from_peer: self.config.peer_id.clone(),
+            to_peer: peer,
+            target,
+        }).await?;
+        self.end_call().await;
+        Ok(())
+    }
+
+    // Placing or answering another call puts the current one on hold
+    fn hold_current(&mut self) -> Result<()> {
+        if self.session.is_none() {
+            return Ok(());
+        }
+        if self.held.is_some() {
+            return Err(anyhow!("Already in two calls; hang one up first"));
+        }
+        if !self.call_established() {
+            return Err(anyhow!("Only a connected call can be put on hold"));
+        }
+        if !self.legs.is_empty() {
+            return Err(anyhow!("A call with several connections can't be put on hold"));
+        }
+        let (Some(mut session), Some(webrtc)) = (self.session.take(), self.webrtc.take()) else { return Ok(()) };
+        session.hold();
+        self.emit(EngineEvent::CallHeld(session.clone()));
+        self.held = Some(HeldCall {
+            session,
+            webrtc,
+            remote_peer: self.remote_peer.take(),
+            media_peer: self.media_peer.take(),
+            offerer: std::mem::take(&mut self.offerer),
+            call_id: self.call_id,
+        });
+        self.audio_capture = None;
+        self.audio_tap = None;
+        self.resuming = None;
+        self.recording_consent = None;
+        self.recorder = None;
+        self.transcriber = None;
+        if let Some(trace) = self.setup_trace.take() {
+            trace.fail("call put on hold before it connected");
+        }
+        let _ = self.local_track.send(None);
+        self.quality.send_replace(None);
+        self.peer_qualities.send_replace(HashMap::new());
+        Ok(())
+    }
+
+    // With the current call gone, the one on hold takes its place
+    fn retrieve_held(&mut self) {
+        let Some(HeldCall { mut session, webrtc, remote_peer, media_peer, offerer, call_id }) = self.held.take() else {
+            return;
+        };
+        session.resume();
+        self.call_id = call_id;
+        self.remote_peer = remote_peer;
+        self.media_peer = media_peer;
+        self.offerer = offerer;
+        self.ladder = QualityLadder::new(self.config.webrtc.quality_ladder);
+        self.redundancy = RedundancySwitch::new(self.config.webrtc.redundant_audio, self.config.webrtc.quality_ladder);
+        self.one_way.clear();
+        self.quality_alarm = QualityAlarm::default();
+        // The switch starts over, and so does what we send
+        webrtc.audio_track.set_redundancy(false);
+        webrtc.audio_track.set_muted(self.muted);
+        match self.audio_backend.start_capture(webrtc.audio_track.clone()) {
+            Ok(capture) => self.audio_capture = Some(capture),
+            Err(e) => self.emit(EngineEvent::Error(format!("Audio capture unavailable: {}", e))),
+        }
+        self.audio_tap = self.audio_tap_config.as_ref().map(|config| webrtc.start_tap(config));
+        self.start_captions(&webrtc);
+        let _ = self.local_track.send(Some(webrtc.audio_track.clone()));
+        self.presence.set_in_call(true);
+        let _ = self.internal_tx.send(InternalEvent::PublishPresence);
+        self.emit(EngineEvent::CallRetrieved(session.clone()));
+        self.session = Some(session);
+        self.webrtc = Some(webrtc);
+    }
+
+    fn held_with(&self, peer_id: &str) -> bool {
+        self.held.as_ref().map_or(false, |held| held.remote_peer.as_deref() == Some(peer_id))
+    }
+
+    fn drop_held(&mut self) {
+        if let Some(held) = self.held.take() {
+            tokio::spawn(async move {
+                held.webrtc.close().await;
+            });
+            self.emit(EngineEvent::HeldCallEnded);
+        }
+    }
+
+    async fn end_held_call(&mut self) {
+        let Some(held) = self.held.as_ref() else { return };
+        let _ = self.send(SignalingMessage::EndCall {
+            room_id: self.config.room_id.clone(),
+            peer_id: self.config.peer_id.clone(),
+            to_peers: Some(held.session.participants.clone()),
+        }).await;
+        self.drop_held();
+    }
+
+    // The held call's connection stays up, so its peer may still renegotiate (an ICE
+    // restart) or trickle candidates. Handled here unless the current call shares the
+    // peer, like a relay.
+    async fn handle_held_media(&mut self, msg: &SignalingMessage) -> Result<bool> {
+        let Some(held) = self.held.as_ref() else { return Ok(false) };
+        let from_held = msg.sender().map_or(false, |peer| {
+            held.media_peer.as_deref() == Some(peer) && self.media_peer.as_deref() != Some(peer)
+        });
+        if !from_held {
+            return Ok(false);
+        }
+        let webrtc = held.webrtc.clone();
+        match msg {
+            SignalingMessage::Offer { sdp, from_peer, room_id, .. } => {
+                let answer = webrtc.handle_offer(sdp.clone()).await?;
+                self.send(SignalingMessage::Answer {
+                    room_id: room_id.clone(),
+                    sdp: answer,
+                    from_peer: self.config.peer_id.clone(),
+                    to_peer: from_peer.clone(),
+                }).await?;
+            }
+            SignalingMessage::Answer { sdp, .. } => {
+                if webrtc.peer_connection.signaling_state() != RTCSignalingState::Stable {
+                    webrtc.handle_answer(sdp.clone()).await?;
+                }
+            }
+            SignalingMessage::IceCandidate { candidate, from_peer, .. } => {
+                webrtc.add_ice_candidate(from_peer, candidate.clone()).await?;
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    // Both calls end here and everyone in them moves to a new room, where we call them
+    // all once they have arrived
+    async fn merge_calls(&mut self) -> Result<()> {
+        let held = self.held.as_ref().ok_or_else(|| anyhow!("No call on hold to merge with"))?;
+        let session = self.session.as_ref().ok_or_else(|| anyhow!("Not in a call"))?;
+        let mut peers = held.session.participants.clone();
+        for peer in &session.participants {
+            if !peers.contains(peer) {
+                peers.push(peer.clone());
+            }
+        }
+        let merge = ConferenceMerge::new(&self.config.room_id, self.config.peer_id.clone(), peers);
+        self.send(SignalingMessage::MergeCalls {
+            room_id: self.config.room_id.clone(),
+            from_peer: self.config.peer_id.clone(),
+            to_peers: merge.peers.clone(),
+            target_room: merge.room_id.clone(),
+        }).await?;
+        // No EndCall: they hang up themselves when the merge arrives
+        self.drop_held();
+        self.teardown();
+        self.emit(EngineEvent::Merging { host: merge.host.clone(), room_id: merge.room_id.clone() });
+        self.schedule(InternalEvent::MergeDeadline { room_id: merge.room_id.clone() }, MERGE_TIMEOUT);
+        let room_id = merge.room_id.clone();
+        self.merge = Some(merge);
+        self.enter_room(room_id).await
+    }
+
+    fn hosted_merge(&self) -> Option<&ConferenceMerge> {
+        self.merge
+            .as_ref()
+            .filter(|merge| merge.host == self.config.peer_id && merge.room_id == self.config.room_id)
+    }
+
+    // Not everyone made it; the conference goes ahead with whoever did
+    async fn merge_deadline(&mut self, room_id: String) -> Result<()> {
+        let Some(merge) = self.hosted_merge().filter(|merge| merge.room_id == room_id) else { return Ok(()) };
+        let arrived = merge.arrived(self.roster.peers(&room_id));
+        self.merge = None;
+        if arrived.is_empty() {
+            return Err(anyhow!("Nobody from the merged calls reached the conference"));
+        }
+        self.call(arrived).await
+    }
+
+    async fn request_recording(&mut self, policy: ConsentPolicy) -> Result<()> {
+        let peers = self.session.as_ref().map(|session| session.participants.clone()).ok_or_else(|| anyhow!("Not in a call"))?;
+        let consent = RecordingConsent::new(peers.clone(), policy);
+        self.send(SignalingMessage::RecordingConsentRequest {
+            room_id: self.config.room_id.clone(),
+            from_peer: self.config.peer_id.clone(),
+            to_peers: peers,
+            request_id: consent.request_id().to_string(),
+            policy,
+        }).await?;
+        self.recording_consent = Some(consent.clone());
+        self.emit(EngineEvent::RecordingConsent(consent));
+        self.update_recorder();
+        Ok(())
+    }
+
+    // Records while our request is granted, if the embedder asked us to record
+    fn update_recorder(&mut self) {
+        let granted = self.recording_consent.as_ref().map_or(false, |consent| consent.is_granted());
+        let (Some(config), Some(webrtc)) = (self.config.recording.as_ref().filter(|_| granted), self.webrtc.as_ref()) else {
+            self.recorder = None;
+            return;
+        };
+        if self.recorder.is_some() {
+            return;
+        }
+        let recorder = match CallRecorder::start(webrtc, config) {
+            Ok(recorder) => recorder,
+            Err(e) => {
+                self.emit(EngineEvent::Error(format!("Failed to start recording: {}", e)));
+                return;
+            }
+        };
+        let mut segments = recorder.subscribe();
+        let events = self.events.clone();
+        tokio::spawn(async move {
+            loop {
+                match segments.recv().await {
+                    Ok(segment) => {
+                        let _ = events.send(EngineEvent::RecordingSaved(segment));
+                    }
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+        self.recorder = Some(recorder);
+    }
+
+    async fn respond_recording(&mut self, request: ConsentRequest, granted: bool) -> Result<()> {
+        self.send(SignalingMessage::RecordingConsentResponse {
+            room_id: request.room_id,
+            from_peer: self.config.peer_id.clone(),
+            to_peer: request.from_peer,
+            request_id: request.request_id,
+            granted,
+        }).await
+    }
+
+    async fn end_call(&mut self) {
+        if self.session.is_none() {
+            return;
+        }
+        // The peer of the call on hold mustn't take it for theirs
+        let to_peers = self.held.as_ref().and(self.session.as_ref()).map(|session| session.participants.clone());
+        let _ = self.send(SignalingMessage::EndCall {
+            room_id: self.config.room_id.clone(),
+            peer_id: self.config.peer_id.clone(),
+            to_peers,
+        }).await;
+        self.teardown();
+    }
+
+    fn teardown(&mut self) {
+        if let Some(webrtc) = self.webrtc.take() {
+            self.close_connection(self.media_peer.clone(), webrtc);
+        }
+        for (peer_id, leg) in std::mem::take(&mut self.legs) {
+            self.close_connection(Some(peer_id), leg);
+        }
+        self.call_setup = CallSetup::default();
+        self.transcriber = None;
+        self.last_diagnostics = None;
+        // The report still wants the setup and the negotiation
+        self.diagnostics.send_modify(|diagnostics| {
+            *diagnostics = CallDiagnostics {
+                setup: diagnostics.setup,
+                sdp: diagnostics.sdp.clone(),
+                negotiation: diagnostics.negotiation.clone(),
+                ..CallDiagnostics::default()
+            }
+        });
+        self.audio_capture = None;
+        self.audio_tap = None;
+        self.session = None;
+        self.remote_peer = None;
+        self.media_peer = None;
+        self.offerer = false;
+        self.awaiting_answer = None;
+        self.resuming = None;
+        self.recording_consent = None;
+        self.recorder = None;
+        if let Some(trace) = self.setup_trace.take() {
+            trace.fail("call ended before it connected");
+        }
+        let _ = self.local_track.send(None);
+        self.quality.send_replace(None);
+        self.peer_qualities.send_replace(HashMap::new());
+        self.presence.set_in_call(false);
+        let _ = self.internal_tx.send(InternalEvent::PublishPresence);
+        self.emit(EngineEvent::CallEnded);
+        self.retrieve_held();
+    }
+
+    // Closed in the background. How it got through is kept for the next call to the
+    // peer at its other end.
+    fn close_connection(&self, peer_id: Option<String>, webrtc: Arc<WebRTCClient>) {
+        let settled = self.session.as_ref().map_or(false, |session| session.answered() || session.state == CallState::Failed);
+        let internal_tx = self.internal_tx.clone();
+        tokio::spawn(async move {
+            if let Some(peer_id) = peer_id {
+                let route = webrtc.warm_route().await;
+                let _ = internal_tx.send(InternalEvent::RouteSettled { peer_id, route, settled });
+            }
+            webrtc.close().await;
+        });
+    }
+
+    fn factory_for(&mut self, config: &WebRTCConfig) -> Result<Arc<WebRTCFactory>> {
+        if let Some(factory) = self.factory.clone().filter(|factory| factory.serves(config)) {
+            return Ok(factory);
+        }
+        let factory = Arc::new(WebRTCFactory::new(config)?);
+        self.factory = Some(factory.clone());
+        Ok(factory)
+    }
+
+    // route_peer is who the connection goes to, for trying the last route to them first
+    async fn create_peer_connection(&mut self, route_peer: Option<&str>) -> Result<()> {
+        if self.webrtc.is_some() {
+            return Ok(());
+        }
+
+        let config = self.webrtc_config();
+        let factory = self.factory_for(&config)?;
+        self.negotiation = NegotiationLog::new();
+        let mut builder = WebRTCClient::builder()
+            .config(&config)
+            .audio_backend(self.audio_backend.clone())
+            .factory(factory)
+            .negotiation_log(match route_peer {
+                Some(peer) => self.negotiation.for_peer(peer),
+                None => self.negotiation.clone(),
+            });
+        if let Some(route) = route_peer.and_then(|peer| self.routes.get(peer)) {
+            println!("Warm-starting ICE: {:?}", route);
+            builder = builder.warm_route(route.clone());
+        }
+        let webrtc = Arc::new(builder.build().await?);
+        self.last_call_id += 1;
+        self.call_id = self.last_call_id;
+        self.ladder = QualityLadder::new(self.config.webrtc.quality_ladder);
+        self.redundancy = RedundancySwitch::new(self.config.webrtc.redundant_audio, self.config.webrtc.quality_ladder);
+        self.one_way.clear();
+        self.quality_alarm = QualityAlarm::default();
+        self.last_diagnostics = None;
+        let negotiation = self.negotiation.clone();
+        self.diagnostics.send_replace(CallDiagnostics { negotiation, ..CallDiagnostics::default() });
+        for (stream_id, peer_id) in self.track_owners.iter() {
+            webrtc.assign_track(stream_id, peer_id);
+        }
+
+        // Trickle our candidates to the remote peer through the engine task
+        let internal_tx = self.internal_tx.clone();
+        webrtc.peer_connection.on_ice_candidate(Box::new(move |candidate: Option<RTCIceCandidate>| {
+            let internal_tx = internal_tx.clone();
+            Box::pin(async move {
+                if let Some(candidate) = candidate {
+                    if let Ok(init) = candidate.to_json() {
+                        let _ = internal_tx.send(InternalEvent::LocalCandidate(init.candidate));
+                    }
+                }
+            })
+        }));
+
+        // ICE drops drive call resumption. Everything spawned for the call goes in the
+        // client's tasks, so close() stops it.
+        let mut status = webrtc.connection_monitor.subscribe();
+        let internal_tx = self.internal_tx.clone();
+        let call = self.call_id;
+        webrtc.tasks().spawn(async move {
+            let mut last = status.borrow().ice_state;
+            while status.changed().await.is_ok() {
+                let state = status.borrow().ice_state;
+                if state != last {
+                    last = state;
+                    if internal_tx.send(InternalEvent::IceState { call, state }).is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+
+        let mut control = webrtc.control.subscribe();
+        let internal_tx = self.internal_tx.clone();
+        webrtc.tasks().spawn(async move {
+            loop {
+                match control.recv().await {
+                    Ok(message) => {
+                        if internal_tx.send(InternalEvent::Control { call, message }).is_err() {
+                            break;
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+
+        let mut remote_audio = webrtc.subscribe_remote_audio();
+        let internal_tx = self.internal_tx.clone();
+        webrtc.tasks().spawn(async move {
+            if remote_audio.recv().await.is_ok() {
+                let _ = internal_tx.send(InternalEvent::RemoteAudioStarted);
+            }
+        });
+
+        let mut remote_tracks = webrtc.subscribe_remote_tracks();
+        let internal_tx = self.internal_tx.clone();
+        webrtc.tasks().spawn(async move {
+            loop {
+                match remote_tracks.recv().await {
+                    Ok(RemoteTrackEvent::Ended(peer_id)) => {
+                        if internal_tx.send(InternalEvent::RemoteTrackEnded { call, peer_id }).is_err() {
+                            break;
+                        }
+                    }
+                    Ok(RemoteTrackEvent::Started(_)) | Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+
+        webrtc.audio_track.set_muted(self.muted);
+        // A missing microphone shouldn't prevent the call; we just send nothing
+        match self.audio_backend.start_capture(webrtc.audio_track.clone()) {
+            Ok(capture) => self.audio_capture = Some(capture),
+            Err(e) => self.emit(EngineEvent::Error(format!("Audio capture unavailable: {}", e))),
+        }
+
+        self.watch_quality(&webrtc, None).await;
+
+        self.audio_tap = self.audio_tap_config.as_ref().map(|config| webrtc.start_tap(config));
+        self.start_captions(&webrtc);
+        let _ = self.local_track.send(Some(webrtc.audio_track.clone()));
+        self.webrtc = Some(webrtc);
+        Ok(())
+    }
+
+    // Until the transcriber is dropped with the call
+    fn start_captions(&mut self, webrtc: &WebRTCClient) {
+        if !self.config.transcription.enabled {
+            return;
+        }
+        let backend = match self.config.transcription.create_backend() {
+            Ok(backend) => backend,
+            Err(e) => {
+                self.emit(EngineEvent::Error(format!("Live captions unavailable: {}", e)));
+                return;
+            }
+        };
+        let transcriber = Transcriber::start(webrtc, backend, self.config.transcription.include_local);
+        let mut captions = transcriber.subscribe();
+        let events = self.events.clone();
+        tokio::spawn(async move {
+            loop {
+                match captions.recv().await {
+                    Ok(caption) => {
+                        let _ = events.send(EngineEvent::Caption(caption));
+                    }
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+        self.transcriber = Some(transcriber);
+    }
+
+    // A failing script shows up as an error event and does nothing
+    fn script_actions(&self, hook: impl FnOnce(&ScriptHooks) -> Result<Vec<ScriptAction>>) -> Vec<ScriptAction> {
+        let Some(ref scripts) = self.config.scripts else { return Vec::new() };
+        hook(scripts).unwrap_or_else(|e| {
+            self.emit(EngineEvent::Error(e.to_string()));
+            Vec::new()
+        })
+    }
+
+    // Answer and decline are decided where the call comes in
+    async fn run_script_actions(&mut self, actions: Vec<ScriptAction>) -> Result<()> {
+        for action in actions {
+            match action {
+                ScriptAction::Answer | ScriptAction::Decline => {}
+                ScriptAction::Call(peers) => self.call(peers).await?,
+                ScriptAction::HangUp => self.end_call().await,
+                ScriptAction::SendChat(text) => self.handle_command(EngineCommand::SendChat(text)).await?,
+                ScriptAction::SendDtmf(tones) => self.handle_command(EngineCommand::SendDtmf(tones)).await?,
+                ScriptAction::Play(path) => {
+                    let webrtc = self.webrtc.clone().ok_or_else(|| anyhow!("Not in a call"))?;
+                    let (sample_rate, samples) = read_wav(&path)?;
+                    webrtc.audio_track.announce(&samples, sample_rate);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Renegotiations (ICE restarts) also complete an offer/answer, but only the first counts
+    fn mark_active(&mut self) {
+        let Some(session) = self.session.as_mut().filter(|session| session.state == CallState::Dialing) else { return };
+        session.mark_active();
+        let session = session.clone();
+        self.emit(EngineEvent::CallActive);
+        self.emit(EngineEvent::CallUpdated(session));
+        self.schedule(InternalEvent::IceTimeout { call: self.call_id }, self.config.webrtc.ice_timeout());
+    }
+
+    // A call that never got through, as opposed to one that ended
+    fn fail_call(&mut self) {
+        let Some(session) = self.session.as_mut() else { return };
+        session.fail();
+        let session = session.clone();
+        self.emit(EngineEvent::CallUpdated(session));
+    }
+
+    fn call_established(&self) -> bool {
+        self.remote_peer.is_some() && self.session.as_ref().map_or(false, |s| s.state == CallState::Active)
+    }
+
+    // The offer/answer went through but ICE never got the call connected
+    async fn ice_timed_out(&mut self) -> Result<()> {
+        let Some(webrtc) = self.webrtc.clone() else { return Ok(()) };
+        if !matches!(
+            webrtc.peer_connection.ice_connection_state(),
+            RTCIceConnectionState::New | RTCIceConnectionState::Checking
+        ) {
+            return Ok(());
+        }
+        let peer = self.remote_peer.clone().unwrap_or_default();
+        let gathered = webrtc.peer_connection.ice_gathering_state() == RTCIceGatheringState::Complete;
+        self.fail_call();
+        self.end_call().await;
+        if !gathered {
+            return Err(anyhow!("Timed out gathering network candidates for the call with {}", peer));
+        }
+        Err(anyhow!("Could not establish a media connection with {}", peer))
+    }
+
+    async fn network_changed(&mut self, change: NetworkChange) -> Result<()> {
+        // Candidates belong to the network they were gathered on
+        self.routes.clear();
+        if !self.stay_connected {
+            return Ok(());
+        }
+        println!("Re-establishing signaling and media after network change: {:?}", change);
+        // The old socket may be bound to an address we no longer have; don't wait for
+        // it to time out
+        self.signaling = None;
+        self.signaling_lost_at = Some(Instant::now());
+        self.begin_resume();
+        if self.resuming.is_none() {
+            self.emit(EngineEvent::Reconnecting);
+        }
+        self.retry_signaling(1).await
+    }
+
+    async fn watchdog_tick(&mut self) -> Result<()> {
+        self.check_idle().await?;
+        let media = self.webrtc.as_ref().filter(|_| self.call_established()).map(|webrtc| MediaHealth {
+            ice_connected: matches!(
+                webrtc.peer_connection.ice_connection_state(),
+                RTCIceConnectionState::Connected | RTCIceConnectionState::Completed
+            ),
+            inbound_packets: webrtc.inbound_packets(),
+        });
+        let sample = HealthSample {
+            // Not being connected only counts if we mean to be
+            signaling_up: self.signaling.is_some() || !self.stay_connected,
+            media,
+        };
+        let kind = match self.watchdog.observe(sample, Instant::now()) {
+            Some(Verdict::Failed(kind)) => kind,
+            Some(Verdict::Recovered) => {
+                self.emit(EngineEvent::Recovered);
+                return Ok(());
+            }
+            None => return Ok(()),
+        };
+        println!("Connection watchdog: {:?}", kind);
+        self.emit(EngineEvent::Failure(kind));
+        match kind {
+            // The reconnect loop is already on it, and the call itself is fine: a resume
+            // started when the socket dropped would only end it at the deadline
+            FailureKind::ServerDown => {
+                if self.resuming.take().is_some() {
+                    self.emit(EngineEvent::CallResumed);
+                }
+                Ok(())
+            }
+            // ICE can stay Connected for a while after the peer's side went away; don't
+            // wait for it to notice. If the peer is really gone the deadline ends the call.
+            FailureKind::PeerGone => {
+                self.begin_resume();
+                self.restart_ice().await
+            }
+            // Nothing can get through until our network is back; hold the call until
+            // then. Signaling retries and restarts ICE once it reconnects.
+            FailureKind::LocalNetwork => {
+                self.begin_resume();
+                Ok(())
+            }
+            // Restarting ICE may find a path the media gets through, but it can as well
+            // be the codec; the user decides with RestartIce
+            FailureKind::NoAudio => Ok(()),
+        }
+    }
+
+    // Going away is noticed here; once away, and alone in the room for long enough, we
+    // leave it
+    async fn check_idle(&mut self) -> Result<()> {
+        self.publish_presence().await?;
+        let Some(after) = self.config.idle.leave_empty_room_after() else { return Ok(()) };
+        if self.signaling.is_none() || !self.presence.should_leave_room(after, Instant::now()) {
+            return Ok(());
+        }
+        println!("Leaving {}: away and alone for {:?}", self.config.room_id, after);
+        self.stay_connected = false;
+        let _ = self.send(SignalingMessage::Disconnect {
+            room_id: self.config.room_id.clone(),
+            peer_id: self.config.peer_id.clone(),
+        }).await;
+        if let Some(mut signaling) = self.signaling.take() {
+            signaling.close().await;
+        }
+        self.presence.set_alone(false, Instant::now());
+        self.emit(EngineEvent::LeftRoom(self.config.room_id.clone()));
+        self.emit(EngineEvent::Disconnected);
+        Ok(())
+    }
+
+    async fn refresh_diagnostics(&mut self, quality: &ConnectionQuality) {
+        let Some(webrtc) = self.webrtc.clone() else { return };
+        let now = Instant::now();
+        if self.stats_paused || self.last_diagnostics.map_or(false, |last| now - last < self.config.webrtc.stats.ui_refresh()) {
+            return;
+        }
+        self.last_diagnostics = Some(now);
+        // Rechecked each time: DTLS finishes after the call starts and the candidate pair
+        // can move to or from TURN
+        let mut security = webrtc.security().await;
+        security.media_relay = self.media_relays.relay_for(&self.config.room_id).map(str::to_string);
+        let layout = webrtc.stereo_layout();
+        let pans = if layout.is_enabled() {
+            webrtc
+                .remote_peers()
+                .into_iter()
+                .map(|peer| {
+                    let pan = layout.pan(&peer).unwrap_or(0.0);
+                    (peer, pan)
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+        let status = webrtc.connection_monitor.subscribe().borrow().clone();
+        self.diagnostics.send_replace(CallDiagnostics {
+            status,
+            security: Some(security),
+            bluetooth: webrtc.audio_backend.bluetooth(),
+            // Sampled on the stats tick, which is also when the RTCP round trip updates
+            latency: Some(webrtc.latency_estimate(quality.round_trip_time)),
+            history: webrtc.quality_monitor.history(),
+            setup: webrtc.setup_time(),
+            sdp: webrtc.last_sdp(),
+            negotiation: self.negotiation.clone(),
+            pans,
+        });
+    }
+
+    // Every peer of the call against the latest stats of the connection their audio comes
+    // over: the main one, or their own leg in a mesh call
+    async fn check_one_way_audio(&mut self) {
+        let Some(webrtc) = self.webrtc.clone() else { return };
+        if !self.call_established() {
+            return;
+        }
+        let mut connections = vec![(self.main_connection_peers(), webrtc)];
+        connections.extend(self.legs.iter().map(|(peer, leg)| (vec![peer.clone()], leg.clone())));
+        let now = Instant::now();
+        for (peers, connection) in connections {
+            let Some(report) = connection.quality_monitor.get_current_stats().await else { continue };
+            let streams = connection.remote_streams();
+            for peer_id in &peers {
+                let ssrcs: Vec<u32> = streams.iter().filter(|(_, owner)| *owner == peer_id).map(|(ssrc, _)| *ssrc).collect();
+                let counters = match (ssrcs.is_empty(), peers.len()) {
+                    (false, _) => PacketCounters::for_streams(&report, &ssrcs),
+                    // Nothing of theirs has arrived yet; alone on the connection, everything
+                    // on it is theirs
+                    (true, 1) => PacketCounters::from_stats(&report),
+                    (true, _) => continue,
+                };
+                let direction = match self.one_way.observe(peer_id, counters, now) {
+                    Some(OneWayVerdict::Detected(direction)) => Some(direction),
+                    Some(OneWayVerdict::Cleared) => None,
+                    None => continue,
+                };
+                println!("One-way audio with {}: {:?}", peer_id, direction);
+                self.emit(EngineEvent::OneWayAudio { peer_id: peer_id.clone(), direction });
+            }
+        }
+    }
+
+    async fn retry_signaling(&mut self, attempt: u32) -> Result<()> {
+        if self.signaling.is_some() || !self.stay_connected {
+            return Ok(());
+        }
+        let policy = self.config.reconnect;
+        let down_for = self.signaling_lost_at.map_or(Duration::ZERO, |since| since.elapsed());
+        match self.connect().await {
+            Ok(()) => {
+                self.signaling_lost_at = None;
+                self.restart_ice().await
+            }
+            Err(e) if policy.allows(attempt + 1, down_for) => {
+                eprintln!("Signaling reconnect attempt {} failed: {}", attempt, e);
+                self.signaling = None;
+                self.schedule(InternalEvent::RetrySignaling { attempt: attempt + 1 }, policy.delay(attempt + 1));
+                Ok(())
+            }
+            Err(e) => {
+                self.signaling = None;
+                self.stay_connected = false;
+                Err(anyhow!("Gave up reconnecting to the signaling server: {}", e))
+            }
+        }
+    }
+
+    async fn ice_state_changed(&mut self, state: RTCIceConnectionState) -> Result<()> {
+        match state {
+            RTCIceConnectionState::Connected | RTCIceConnectionState::Completed => {
+                if let Some(trace) = self.setup_trace.take() {
+                    trace.finish();
+                }
+                if self.resuming.take().is_some() {
+                    self.emit(EngineEvent::CallResumed);
+                }
+                // The first callee of a mesh call
+                if let Some(peer) = self.remote_peer.clone().filter(|peer| self.call_setup.get(peer).is_some()) {
+                    self.call_setup.update(&peer, PeerSetup::Connected);
+                    self.emit(EngineEvent::CallSetup(self.call_setup.clone()));
+                }
+            }
+            // Disconnected often heals by itself, but restarting costs little and is much
+            // faster than waiting for Failed. A restart of our own can pass through
+            // Disconnected too, so only the first one in an episode triggers it.
+            RTCIceConnectionState::Disconnected if self.resuming.is_none() && self.call_established() => {
+                self.begin_resume();
+                self.restart_ice().await?;
+            }
+            RTCIceConnectionState::Failed if self.call_established() => {
+                self.begin_resume();
+                self.restart_ice().await?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn begin_resume(&mut self) {
+        if !self.config.reconnect.resume_calls || !self.call_established() || self.resuming.is_some() {
+            return;
+        }
+        self.resume_episodes += 1;
+        let episode = self.resume_episodes;
+        self.resuming = Some(episode);
+        self.emit(EngineEvent::Reconnecting);
+        let policy = self.config.reconnect;
+        if self.offerer {
+            self.schedule(InternalEvent::RetryIceRestart { episode, attempt: 1 }, policy.delay(1));
+        }
+        self.schedule(InternalEvent::ResumeDeadline { episode }, policy.deadline());
+    }
+
+    // Only the offerer restarts; the answerer's resumption is answering that offer.
+    // Without signaling the offer goes straight to the peer over the control channel.
+    async fn restart_ice(&mut self) -> Result<()> {
+        let direct = self.signaling.is_none();
+        if !self.offerer || self.resuming.is_none() || (direct && !self.control_open()) {
+            return Ok(());
+        }
+        let (Some(webrtc), Some(to_peer)) = (self.webrtc.clone(), self.media_peer.clone()) else {
+            return Ok(());
+        };
+        webrtc.set_ice_servers(&self.webrtc_config().ice_servers).await?;
+        let sdp = webrtc.restart_ice().await?;
+        if direct {
+            return self.send_control(ControlMessage::Offer { sdp }).await;
+        }
+        self.send(SignalingMessage::Offer {
+            room_id: self.config.room_id.clone(),
+            sdp,
+            from_peer: self.config.peer_id.clone(),
+            to_peer,
+        }).await
+    }
+
+    // Only our sending changes, so nothing is renegotiated; a no-op unless RED was negotiated
+    async fn set_redundancy(&self, on: bool, loss: f64) {
+        let Some(webrtc) = self.webrtc.as_ref() else { return };
+        if !webrtc.audio_track.carries_redundancy().await {
+            return;
+        }
+        webrtc.audio_track.set_redundancy(on);
+        let state = if on { "on" } else { "off" };
+        webrtc
+            .connection_monitor
+            .log_event(format!("Redundant audio {} ({:.1}% packet loss)", state, loss));
+    }
+
+    async fn step_quality(&mut self, step: LadderStep) -> Result<()> {
+        let Some(webrtc) = self.webrtc.clone() else { return Ok(()) };
+        webrtc.set_quality_rung(step.to);
+        webrtc
+            .connection_monitor
+            .log_event(format!("Quality ladder: {} -> {} ({})", step.from, step.to, step.reason));
+        self.emit(EngineEvent::QualityRung(step.to));
+        if self.offerer {
+            self.renegotiate().await
+        } else if self.control_open() {
+            // Only the offerer offers; ask it to
+            self.send_control(ControlMessage::Renegotiate).await
+        } else {
+            // Applies with the next offer we answer
+            Ok(())
+        }
+    }
+
+    // A fresh offer with the same ICE credentials, so the new rung's SDP reaches the other
+    // side. Goes over the call's own control channel when it can. Left to the restart
+    // while a resume is under way.
+    async fn renegotiate(&mut self) -> Result<()> {
+        if !self.offerer || self.resuming.is_some() {
+            return Ok(());
+        }
+        let (Some(webrtc), Some(to_peer)) = (self.webrtc.clone(), self.media_peer.clone()) else {
+            return Ok(());
+        };
+        let sdp = webrtc.create_offer().await?;
+        if self.control_open() {
+            return self.send_control(ControlMessage::Offer { sdp }).await;
+        }
+        self.send(SignalingMessage::Offer {
+            room_id: self.config.room_id.clone(),
+            sdp,
+            from_peer: self.config.peer_id.clone(),
+            to_peer,
+        }).await
+    }
+
+    // Our settings plus what the server pushed and the current TURN credentials
+    fn webrtc_config(&self) -> WebRTCConfig {
+        let mut config = self.config.webrtc.clone();
+        config.stream_id = self.config.peer_id.clone();
+        if let Some(ref server_config) = self.server_config {
+            server_config.apply(&mut config);
+        }
+        if let Some(ref turn_credentials) = self.turn_credentials {
+            turn_credentials.apply(&mut config);
+        }
+        config
+    }
+
+    fn trace_phase(&mut self, name: &'static str) {
+        if let Some(ref mut trace) = self.setup_trace {
+            trace.phase(name);
+        }
+    }
+
+    fn schedule(&self, event: InternalEvent, delay: Duration) {
+        let internal_tx = self.internal_tx.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+            let _ = internal_tx.send(event);
+        });
+    }
+
+    async fn send(&mut self, msg: SignalingMessage) -> Result<()> {
+        match self.signaling {
+            Some(ref mut signaling) => signaling.send(msg).await,
+            None => Err(anyhow!("Not connected to the signaling server")),
+        }
+    }
+
+    fn control_open(&self) -> bool {
+        self.webrtc.as_ref().map_or(false, |webrtc| webrtc.control.is_open())
+    }
+
+    async fn send_control(&self, message: ControlMessage) -> Result<()> {
+        match self.webrtc {
+            Some(ref webrtc) => webrtc.control.send(&message).await,
+            None => Err(anyhow!("Not in a call")),
+        }
+    }
+
+    fn emit(&self, event: EngineEvent) {
+        let _ = self.events.send(event);
+    }
+
+    fn report(&self, result: Result<()>) {
+        if let Err(e) = result {
+            eprintln!("Call engine error: {}", e);
+            self.emit(EngineEvent::Error(e.to_string()));
+        }
+    }
+}
+
+// What Settings::create_audio_backend makes of the same settings
+fn create_audio_backend(config: &EngineConfig) -> Arc<dyn AudioBackend> {
+    let backend = config.audio_backend.create_with_buffer(config.audio_buffer_frames);
+    if let Some(sidetone) = backend.sidetone() {
+        sidetone.set_level(config.sidetone_level);
+    }
+    backend.set_output_routing(config.output_routing.clone());
+    backend.set_playback_delay(Duration::from_millis(config.playback_delay_ms as u64));
+    ExternalCaptureBackend::wrap(backend, config.capture_source.clone())
+}
+
+async fn next_message(signaling: &mut Option<SignalingClient>) -> Option<SignalingMessage> {
+    match signaling {
+        Some(client) => client.receive().await.ok().flatten(),
+        None => std::future::pending().await,
+    }
+}
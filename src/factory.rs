@@ -0,0 +1,168 @@
+use anyhow::{anyhow, Result};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use webrtc::api::interceptor_registry::{configure_nack, configure_rtcp_reports, configure_twcc};
+use webrtc::api::media_engine::MediaEngine;
+use webrtc::api::setting_engine::SettingEngine;
+use webrtc::api::{APIBuilder, API};
+use webrtc::ice::udp_mux::{UDPMuxDefault, UDPMuxParams};
+use webrtc::ice::udp_network::{EphemeralUDP, UDPNetwork};
+use webrtc::interceptor::registry::Registry;
+use webrtc::interceptor::{Interceptor, InterceptorBuilder};
+use webrtc::peer_connection::configuration::RTCConfiguration;
+use webrtc::peer_connection::RTCPeerConnection;
+
+use crate::audio::codec::register_audio_codecs;
+use crate::dtmf::{register_telephone_event, DtmfSender, DtmfSlot};
+use crate::impairment::{ImpairmentConfig, ImpairmentInterceptorBuilder};
+use crate::red::register_red;
+use crate::webrtc::{CustomInterceptors, IceTransportConfig, InterceptorConfig, WebRTCConfig};
+
+pub type MediaEngineCustomizer = Box<dyn FnOnce(&mut MediaEngine) -> Result<()> + Send>;
+pub type RegistryBuilder = Box<dyn FnOnce(Registry, &mut MediaEngine) -> Result<Registry> + Send>;
+
+// Builds the webrtc-rs API (codecs, interceptors) once and makes every peer connection
+// from it, so a group call or a run of calls doesn't register codecs and interceptors
+// again for each one
+pub struct WebRTCFactory {
+    api: API,
+    dtmf: DtmfSlot,
+    // One connection at a time, so each picks up its own DTMF sender
+    creating: Mutex<()>,
+    // What the API was built from; None when an embedder customized it
+    built_from: Option<BuiltFrom>,
+}
+
+#[derive(PartialEq)]
+struct BuiltFrom {
+    interceptors: InterceptorConfig,
+    impairment: ImpairmentConfig,
+    custom_interceptors: CustomInterceptors,
+    ice_transport: IceTransportConfig,
+}
+
+impl BuiltFrom {
+    fn new(config: &WebRTCConfig) -> Self {
+        Self {
+            interceptors: config.interceptors,
+            impairment: config.impairment,
+            custom_interceptors: config.custom_interceptors.clone(),
+            ice_transport: config.ice_transport,
+        }
+    }
+}
+
+impl WebRTCFactory {
+    // From the config's interceptor and ICE transport settings. Per-call settings (ICE
+    // servers, codec order, bitrate, SDP hooks) stay with each client.
+    pub fn new(config: &WebRTCConfig) -> Result<Self> {
+        Self::build(config, Vec::new(), None)
+    }
+
+    pub(crate) fn build(
+        config: &WebRTCConfig,
+        customizers: Vec<MediaEngineCustomizer>,
+        registry: Option<RegistryBuilder>,
+    ) -> Result<Self> {
+        let built_from = (customizers.is_empty() && registry.is_none()).then(|| BuiltFrom::new(config));
+
+        // Audio-only client: just the codecs AudioTrack can encode
+        let mut media_engine = MediaEngine::default();
+        register_audio_codecs(&mut media_engine)?;
+        register_telephone_event(&mut media_engine)?;
+        register_red(&mut media_engine)?;
+        for customize in customizers {
+            customize(&mut media_engine)?;
+        }
+
+        let mut registry = match registry {
+            Some(build) => build(Registry::new(), &mut media_engine)?,
+            None => stock_registry(config, &mut media_engine)?,
+        };
+        // Outside the stock interceptors so NACK and reports see the renumbered audio
+        let dtmf = DtmfSlot::default();
+        registry.add(Box::new(dtmf.clone()));
+        for builder in config.custom_interceptors.iter() {
+            registry.add(Box::new(SharedInterceptorBuilder(builder.clone())));
+        }
+
+        let api = APIBuilder::new()
+            .with_media_engine(media_engine)
+            .with_interceptor_registry(registry)
+            .with_setting_engine(setting_engine(&config.ice_transport)?)
+            .build();
+        Ok(Self {
+            api,
+            dtmf,
+            creating: Mutex::new(()),
+            built_from,
+        })
+    }
+
+    // Whether a client with this config gets the interceptors and ICE transport it asks
+    // for from here. Changing them in settings needs a new factory.
+    pub fn serves(&self, config: &WebRTCConfig) -> bool {
+        self.built_from.as_ref().map_or(false, |built_from| *built_from == BuiltFrom::new(config))
+    }
+
+    // `dtmf` sends its tones on the new connection's audio
+    pub async fn new_peer_connection(&self, configuration: RTCConfiguration, dtmf: &DtmfSender) -> Result<Arc<RTCPeerConnection>> {
+        let _creating = self.creating.lock().await;
+        self.dtmf.fill(dtmf);
+        Ok(Arc::new(self.api.new_peer_connection(configuration).await?))
+    }
+}
+
+fn setting_engine(ice: &IceTransportConfig) -> Result<SettingEngine> {
+    let mut engine = SettingEngine::default();
+    engine.set_ice_timeouts(
+        Some(Duration::from_millis(ice.disconnected_timeout_ms)),
+        Some(Duration::from_millis(ice.failed_timeout_ms)),
+        Some(Duration::from_millis(ice.keepalive_interval_ms)),
+    );
+    if let Some(port) = ice.udp_mux_port {
+        // Bound here so a taken port fails the factory, not some later call
+        let socket = std::net::UdpSocket::bind(("0.0.0.0", port))
+            .map_err(|e| anyhow!("Can't bind the ICE UDP port {}: {}", port, e))?;
+        socket.set_nonblocking(true)?;
+        let socket = tokio::net::UdpSocket::from_std(socket)?;
+        engine.set_udp_network(UDPNetwork::Muxed(UDPMuxDefault::new(UDPMuxParams::new(socket))));
+    } else if ice.port_min != 0 || ice.port_max != 0 {
+        let range = EphemeralUDP::new(ice.port_min, ice.port_max)
+            .map_err(|e| anyhow!("Invalid ICE port range {}-{}: {}", ice.port_min, ice.port_max, e))?;
+        engine.set_udp_network(UDPNetwork::Ephemeral(range));
+    }
+    Ok(engine)
+}
+
+// The stock interceptors the config asks for
+fn stock_registry(config: &WebRTCConfig, media_engine: &mut MediaEngine) -> Result<Registry> {
+    let mut registry = Registry::new();
+    // Registered first so it sits closest to the transport: NACK retransmissions and
+    // reports then see the simulated loss like they would on a real network
+    if config.impairment.enabled {
+        println!("Network impairment enabled: {:?}", config.impairment);
+        registry.add(Box::new(ImpairmentInterceptorBuilder::new(config.impairment)));
+    }
+    let interceptors = config.interceptors;
+    if interceptors.nack {
+        registry = configure_nack(registry, media_engine);
+    }
+    if interceptors.rtcp_reports {
+        registry = configure_rtcp_reports(registry);
+    }
+    if interceptors.twcc {
+        registry = configure_twcc(registry, media_engine)?;
+    }
+    Ok(registry)
+}
+
+// Registry takes ownership of its builders, so shared ones go in behind this adapter
+struct SharedInterceptorBuilder(Arc<dyn InterceptorBuilder + Send + Sync>);
+
+impl InterceptorBuilder for SharedInterceptorBuilder {
+    fn build(&self, id: &str) -> webrtc::interceptor::Result<Arc<dyn Interceptor + Send + Sync>> {
+        self.0.build(id)
+    }
+}
@@ -0,0 +1,220 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+use tokio::net::TcpStream;
+use url::Url;
+
+use crate::lan::is_lan;
+use crate::signaling::{is_webtransport, ChannelLimits, SignalingClient};
+
+// Redundant signaling servers: signaling_url is the primary, and these are tried in order
+// when it can't be reached
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FailoverConfig {
+    pub fallback_urls: Vec<String>,
+    // A server that failed is tried after the others for this long
+    pub hold_down_ms: u64,
+    // How often every server is probed, so one that came back is tried first again and one
+    // that died isn't waited on; 0 turns it off
+    pub health_check_ms: u64,
+    // For each connection attempt and probe; a server that drops packets would otherwise
+    // stall failover until the OS gives up
+    pub connect_timeout_ms: u64,
+    // Stay on the server that last worked, even once the primary is back. Off, every
+    // connect starts over from the primary.
+    pub sticky: bool,
+}
+
+impl Default for FailoverConfig {
+    fn default() -> Self {
+        Self {
+            fallback_urls: Vec::new(),
+            hold_down_ms: 60_000,
+            health_check_ms: 30_000,
+            connect_timeout_ms: 5_000,
+            sticky: true,
+        }
+    }
+}
+
+impl FailoverConfig {
+    pub fn hold_down(&self) -> Duration {
+        Duration::from_millis(self.hold_down_ms)
+    }
+
+    pub fn health_check_interval(&self) -> Option<Duration> {
+        (self.health_check_ms > 0).then(|| Duration::from_millis(self.health_check_ms))
+    }
+
+    pub fn connect_timeout(&self) -> Duration {
+        Duration::from_millis(self.connect_timeout_ms)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Server {
+    url: String,
+    down_until: Option<Instant>,
+}
+
+// The configured servers, which of them are down and which one we are on
+#[derive(Debug, Clone)]
+pub struct SignalingServers {
+    servers: Vec<Server>,
+    config: FailoverConfig,
+    // The one we last got connected to
+    current: Option<String>,
+}
+
+impl SignalingServers {
+    pub fn new(primary: &str, config: &FailoverConfig) -> Self {
+        let mut servers = Self {
+            servers: Vec::new(),
+            config: config.clone(),
+            current: None,
+        };
+        servers.configure(primary, config);
+        servers
+    }
+
+    // After a settings change; what is known about servers still in the list is kept
+    pub fn configure(&mut self, primary: &str, config: &FailoverConfig) {
+        let mut servers: Vec<Server> = Vec::new();
+        for url in std::iter::once(primary).chain(config.fallback_urls.iter().map(String::as_str)) {
+            let url = url.trim();
+            if url.is_empty() || servers.iter().any(|server| server.url == url) {
+                continue;
+            }
+            let down_until = self.find(url).and_then(|server| server.down_until);
+            servers.push(Server { url: url.to_string(), down_until });
+        }
+        self.current = self.current.take().filter(|current| servers.iter().any(|server| server.url == *current));
+        self.servers = servers;
+        self.config = config.clone();
+    }
+
+    pub fn config(&self) -> &FailoverConfig {
+        &self.config
+    }
+
+    pub fn urls(&self) -> Vec<String> {
+        self.servers.iter().map(|server| server.url.clone()).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.servers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.servers.is_empty()
+    }
+
+    pub fn current(&self) -> Option<&str> {
+        self.current.as_deref()
+    }
+
+    pub fn is_down(&self, url: &str, now: Instant) -> bool {
+        self.find(url).is_some_and(|server| server.down_until.is_some_and(|until| now < until))
+    }
+
+    // In the order to try them: the server we stick to, then the rest as configured.
+    // Servers that failed recently go last instead of being left out; with all of them
+    // down, one may still be back before the others.
+    pub fn candidates(&self, now: Instant) -> Vec<String> {
+        let sticky = self.current.as_deref().filter(|_| self.config.sticky);
+        let mut order: Vec<&Server> = self.servers.iter().collect();
+        order.sort_by_key(|server| {
+            let down = server.down_until.is_some_and(|until| now < until);
+            (down, Some(server.url.as_str()) != sticky)
+        });
+        order.into_iter().map(|server| server.url.clone()).collect()
+    }
+
+    pub fn mark_up(&mut self, url: &str) {
+        if let Some(server) = self.find_mut(url) {
+            server.down_until = None;
+        }
+        self.current = Some(url.to_string());
+    }
+
+    // Also when the connection to it drops, so the reconnect goes elsewhere
+    pub fn mark_down(&mut self, url: &str, now: Instant) {
+        let until = now + self.config.hold_down();
+        if let Some(server) = self.find_mut(url) {
+            server.down_until = Some(until);
+        }
+        if self.current.as_deref() == Some(url) {
+            self.current = None;
+        }
+    }
+
+    // Probe results only move servers up or down the list; which one we are on stays
+    pub fn apply_health(&mut self, results: &[(String, bool)], now: Instant) {
+        let until = now + self.config.hold_down();
+        for (url, up) in results {
+            if let Some(server) = self.find_mut(url) {
+                server.down_until = if *up { None } else { Some(until) };
+            }
+        }
+    }
+
+    fn find(&self, url: &str) -> Option<&Server> {
+        self.servers.iter().find(|server| server.url == url)
+    }
+
+    fn find_mut(&mut self, url: &str) -> Option<&mut Server> {
+        self.servers.iter_mut().find(|server| server.url == url)
+    }
+}
+
+// A TCP connect to the server's port: cheap enough to repeat every few seconds, and it
+// fails when the server or its host is gone. WebTransport runs over UDP, where only a
+// whole session shows the server is there. There is nothing to probe in LAN mode.
+pub async fn probe(url: &str, within: Duration) -> bool {
+    if is_lan(url) {
+        return true;
+    }
+    if is_webtransport(url) {
+        return matches!(tokio::time::timeout(within, SignalingClient::connect(url)).await, Ok(Ok(_)));
+    }
+    let Ok(url) = Url::parse(url) else { return false };
+    let (Some(host), Some(port)) = (url.host_str(), url.port_or_known_default()) else { return false };
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    matches!(tokio::time::timeout(within, TcpStream::connect((host, port))).await, Ok(Ok(_)))
+}
+
+// Probes every server at once
+pub async fn check_health(urls: Vec<String>, within: Duration) -> Vec<(String, bool)> {
+    futures::future::join_all(urls.into_iter().map(|url| async move {
+        let up = probe(&url, within).await;
+        (url, up)
+    }))
+    .await
+}
+
+// Tries the candidates in turn; the client comes back with the URL it connected to
+pub async fn connect_any(
+    servers: &mut SignalingServers,
+    token: Option<&str>,
+    limits: ChannelLimits,
+) -> Result<(SignalingClient, String)> {
+    let within = servers.config.connect_timeout();
+    let mut last_error = None;
+    for url in servers.candidates(Instant::now()) {
+        let error = match tokio::time::timeout(within, SignalingClient::connect_with_options(&url, token, limits)).await {
+            Ok(Ok(client)) => {
+                servers.mark_up(&url);
+                return Ok((client, url));
+            }
+            Ok(Err(e)) => e,
+            Err(_) => anyhow!("Timed out after {:?}", within),
+        };
+        if servers.len() > 1 {
+            eprintln!("Signaling server {} unavailable: {}", url, error);
+        }
+        servers.mark_down(&url, Instant::now());
+        last_error = Some(error);
+    }
+    Err(last_error.unwrap_or_else(|| anyhow!("No signaling server configured")))
+}
@@ -0,0 +1,532 @@
+// C ABI over CallEngine, so apps not written in Rust get the same calling stack: mobile
+// shells (Swift through a bridging header, Kotlin through JNA or a small JNI shim) and
+// desktop apps in C, C++ or anything with a C FFI. Building with the ffi feature writes
+// the header to include/webrtc_client.h (see build.rs). Build the library with, e.g.
+//
+//     cargo rustc --lib --release --no-default-features --features ffi \
+//         --crate-type cdylib
+//     cargo rustc --lib --release --no-default-features --features ffi,oboe \
+//         --target aarch64-linux-android --crate-type cdylib
+//     cargo rustc --lib --release --no-default-features --features ffi \
+//         --target aarch64-apple-ios --crate-type staticlib
+//
+// Configuration and events cross as UTF-8 JSON, so new fields don't change the ABI;
+// commands have a function each, plus webrtc_client_engine_send for everything else.
+// Strings handed out must be released with webrtc_client_string_free.
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::ffi::{c_char, c_void, CStr, CString};
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::runtime::Runtime;
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+use tokio::time::timeout;
+
+use crate::audio::{AudioBackendKind, OutputRouting};
+use crate::call::IncomingCall;
+use crate::engine::{CallEngine, EngineCommand, EngineConfig, EngineEvent, EngineHandle};
+use crate::failover::FailoverConfig;
+use crate::metrics::ConnectionQuality;
+use crate::one_way::OneWayAudio;
+use crate::presence::{IdleConfig, PresenceStatus};
+use crate::reconnect::ReconnectPolicy;
+use crate::shutdown::SHUTDOWN_TIMEOUT;
+use crate::signaling::ChannelLimits;
+use crate::transcription::TranscriptionConfig;
+use crate::watchdog::FailureKind;
+use crate::webrtc::WebRTCConfig;
+
+/// Bumped on any incompatible change to the functions below or the JSON they take.
+pub const WEBRTC_CLIENT_ABI_VERSION: u32 = 1;
+
+// What an embedding app can set; the rest of EngineConfig keeps its defaults
+#[derive(Debug, Clone, Deserialize)]
+pub struct FfiConfig {
+    pub signaling_url: String,
+    pub room_id: String,
+    pub peer_id: String,
+    #[serde(default)]
+    pub auto_answer: bool,
+    #[serde(default)]
+    pub audio_backend: AudioBackendKind,
+    #[serde(default)]
+    pub webrtc: WebRTCConfig,
+    #[serde(default)]
+    pub reconnect: ReconnectPolicy,
+    #[serde(default)]
+    pub signaling_failover: FailoverConfig,
+    // Off on phones, which have their own connectivity callbacks; apps send network_changed
+    #[serde(default)]
+    pub watch_network: bool,
+}
+
+impl From<FfiConfig> for EngineConfig {
+    fn from(config: FfiConfig) -> Self {
+        EngineConfig {
+            signaling_url: config.signaling_url,
+            signaling_token: None,
+            room_id: config.room_id,
+            peer_id: config.peer_id,
+            identity: None,
+            auto_answer: config.auto_answer,
+            auto_answer_allowlist: Vec::new(),
+            audio_backend: config.audio_backend,
+            audio_buffer_frames: None,
+            sidetone_level: 0.0,
+            playback_delay_ms: 0,
+            output_routing: OutputRouting::default(),
+            capture_source: None,
+            recording: None,
+            transcription: TranscriptionConfig::default(),
+            // Embedders have no user input to go idle on
+            idle: IdleConfig { away_after_mins: 0, leave_empty_room_after_mins: 0 },
+            scripts: None,
+            webrtc: config.webrtc,
+            reconnect: config.reconnect,
+            signaling_channels: ChannelLimits::default(),
+            signaling_failover: config.signaling_failover,
+            watch_network: config.watch_network,
+        }
+    }
+}
+
+// {"command": "call", "peers": ["bob"]}
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum FfiCommand {
+    Connect,
+    Call { peers: Vec<String> },
+    Answer { room_id: String, from_peer: String, accepted: bool },
+    HangUp,
+    Transfer { target: String },
+    Merge,
+    SetMuted { muted: bool },
+    SendChat { text: String },
+    SendDtmf { digits: String },
+    NetworkChanged,
+    RestartIce,
+}
+
+impl From<FfiCommand> for EngineCommand {
+    fn from(command: FfiCommand) -> Self {
+        match command {
+            FfiCommand::Connect => EngineCommand::Connect,
+            FfiCommand::Call { peers } => EngineCommand::Call(peers),
+            FfiCommand::Answer { room_id, from_peer, accepted } => EngineCommand::Answer {
+                call: IncomingCall { room_id, from_peer },
+                accepted,
+            },
+            FfiCommand::HangUp => EngineCommand::HangUp,
+            FfiCommand::Transfer { target } => EngineCommand::Transfer(target),
+            FfiCommand::Merge => EngineCommand::Merge,
+            FfiCommand::SetMuted { muted } => EngineCommand::SetMuted(muted),
+            FfiCommand::SendChat { text } => EngineCommand::SendChat(text),
+            FfiCommand::SendDtmf { digits } => EngineCommand::SendDtmf(digits),
+            FfiCommand::NetworkChanged => EngineCommand::NetworkChanged,
+            FfiCommand::RestartIce => EngineCommand::RestartIce,
+        }
+    }
+}
+
+// {"event": "incoming_call", "room_id": "lobby", "from_peer": "alice"}
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum FfiEvent {
+    Connected,
+    Disconnected,
+    PeerList { peers: Vec<String> },
+    PeerJoined { peer_id: String },
+    PeerLeft { peer_id: String },
+    IncomingCall { room_id: String, from_peer: String },
+    CallStarted { room_id: String, participants: Vec<String> },
+    CallDeclined { peer_id: String },
+    Transferred { by: String, target: String },
+    CallHeld { room_id: String, participants: Vec<String> },
+    CallRetrieved { room_id: String, participants: Vec<String> },
+    HeldCallEnded,
+    Merging { host: String, room_id: String },
+    CallActive,
+    RemoteAudioStarted,
+    RemoteTrackEnded { peer_id: String },
+    Reconnecting,
+    CallResumed,
+    // kind is server_down, peer_gone, local_network or no_audio
+    Failure { kind: FailureKind },
+    Recovered,
+    // direction is peer_cannot_hear_us or we_cannot_hear_peer; null once audio flows both ways
+    OneWayAudio { peer_id: String, direction: Option<OneWayAudio> },
+    CallEnded,
+    Chat { from_peer: String, text: String },
+    Presence { peer_id: String, status: PresenceStatus },
+    Error { message: String },
+}
+
+impl FfiEvent {
+    // None for what embedding apps have no use for yet (recording, server settings, quality
+    // rungs, setup times)
+    pub fn from_engine(event: &EngineEvent) -> Option<Self> {
+        Some(match event {
+            EngineEvent::Connected => FfiEvent::Connected,
+            EngineEvent::Disconnected => FfiEvent::Disconnected,
+            EngineEvent::PeerList(peers) => FfiEvent::PeerList { peers: peers.clone() },
+            EngineEvent::PeerJoined(peer_id) => FfiEvent::PeerJoined { peer_id: peer_id.clone() },
+            EngineEvent::PeerLeft(peer_id) => FfiEvent::PeerLeft { peer_id: peer_id.clone() },
+            EngineEvent::IncomingCall(call) => FfiEvent::IncomingCall {
+                room_id: call.room_id.clone(),
+                from_peer: call.from_peer.clone(),
+            },
+            EngineEvent::CallStarted(session) => FfiEvent::CallStarted {
+                room_id: session.room_id.clone(),
+                participants: session.participants.clone(),
+            },
+            EngineEvent::CallDeclined { peer_id } => FfiEvent::CallDeclined { peer_id: peer_id.clone() },
+            EngineEvent::Transferred { by, target } => FfiEvent::Transferred {
+                by: by.clone(),
+                target: target.clone(),
+            },
+            EngineEvent::CallHeld(session) => FfiEvent::CallHeld {
+                room_id: session.room_id.clone(),
+                participants: session.participants.clone(),
+            },
+            EngineEvent::CallRetrieved(session) => FfiEvent::CallRetrieved {
+                room_id: session.room_id.clone(),
+                participants: session.participants.clone(),
+            },
+            EngineEvent::HeldCallEnded => FfiEvent::HeldCallEnded,
+            EngineEvent::Merging { host, room_id } => FfiEvent::Merging {
+                host: host.clone(),
+                room_id: room_id.clone(),
+            },
+            EngineEvent::CallActive => FfiEvent::CallActive,
+            EngineEvent::RemoteAudioStarted => FfiEvent::RemoteAudioStarted,
+            EngineEvent::RemoteTrackEnded(peer_id) => FfiEvent::RemoteTrackEnded { peer_id: peer_id.clone() },
+            EngineEvent::Reconnecting => FfiEvent::Reconnecting,
+            EngineEvent::CallResumed => FfiEvent::CallResumed,
+            EngineEvent::Failure(kind) => FfiEvent::Failure { kind: *kind },
+            EngineEvent::Recovered => FfiEvent::Recovered,
+            EngineEvent::OneWayAudio { peer_id, direction } => FfiEvent::OneWayAudio {
+                peer_id: peer_id.clone(),
+                direction: *direction,
+            },
+            EngineEvent::CallEnded => FfiEvent::CallEnded,
+            EngineEvent::Chat(chat) => FfiEvent::Chat {
+                from_peer: chat.from_peer.clone(),
+                text: chat.text.clone(),
+            },
+            EngineEvent::Presence { peer_id, status } => FfiEvent::Presence {
+                peer_id: peer_id.clone(),
+                status: *status,
+            },
+            EngineEvent::Error(message) => FfiEvent::Error { message: message.clone() },
+            EngineEvent::ServerConfig(_)
+            | EngineEvent::CallUpdated(_)
+            | EngineEvent::CallSetup(_)
+            | EngineEvent::Caption(_)
+            | EngineEvent::LeftRoom(_)
+            | EngineEvent::QualityRung(_)
+            | EngineEvent::SetupTime(_)
+            | EngineEvent::RecordingConsentRequested(_)
+            | EngineEvent::RecordingConsent(_)
+            | EngineEvent::RecordingSaved(_) => return None,
+        })
+    }
+}
+
+/// Receives each event as JSON, with the user_data it was registered with. Runs on an
+/// engine thread: hand the event to your own thread, and don't free the engine or change
+/// the callback from inside it.
+pub type WebrtcClientEventCallback = extern "C" fn(event_json: *const c_char, user_data: *mut c_void);
+
+// The app promises user_data may be used from the engine's threads
+struct UserData(*mut c_void);
+
+unsafe impl Send for UserData {}
+
+// The engine with a runtime of its own; the host app has no tokio
+pub struct FfiEngine {
+    runtime: Runtime,
+    handle: EngineHandle,
+    events: Mutex<broadcast::Receiver<EngineEvent>>,
+    callback: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl FfiEngine {
+    pub fn start(config: FfiConfig) -> Result<Self> {
+        let runtime = Runtime::new()?;
+        let handle = {
+            let _context = runtime.enter();
+            CallEngine::spawn(config.into())
+        };
+        let events = Mutex::new(handle.subscribe());
+        Ok(Self { runtime, handle, events, callback: Mutex::new(None) })
+    }
+
+    pub fn send(&self, command: FfiCommand) -> Result<()> {
+        self.handle.send(command.into())
+    }
+
+    // Blocks the calling thread for up to `wait`; None when nothing happened by then
+    pub fn next_event(&self, wait: Duration) -> Option<FfiEvent> {
+        let mut events = self.events.lock().ok()?;
+        self.runtime.block_on(async {
+            timeout(wait, async {
+                loop {
+                    match events.recv().await {
+                        Ok(event) => {
+                            if let Some(event) = FfiEvent::from_engine(&event) {
+                                return Some(event);
+                            }
+                        }
+                        // Missed ones are gone; carry on with the newest
+                        Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                        Err(broadcast::error::RecvError::Closed) => return None,
+                    }
+                }
+            })
+            .await
+            .ok()
+            .flatten()
+        })
+    }
+
+    // The current call's latest stats
+    pub fn quality(&self) -> Option<ConnectionQuality> {
+        self.handle.quality().borrow().clone()
+    }
+
+    // Replaces any earlier callback; None just removes it. Returns once the old one
+    // can no longer be called, so its user_data may be freed.
+    fn set_callback(&self, callback: Option<(WebrtcClientEventCallback, UserData)>) {
+        let Ok(mut current) = self.callback.lock() else { return };
+        if let Some(task) = current.take() {
+            task.abort();
+            let _ = self.runtime.block_on(task);
+        }
+        let Some((callback, user_data)) = callback else { return };
+        let mut events = self.handle.subscribe();
+        *current = Some(self.runtime.spawn(async move {
+            // The whole wrapper moves in, not just its raw pointer
+            let user_data = user_data;
+            loop {
+                match events.recv().await {
+                    Ok(event) => {
+                        let json = FfiEvent::from_engine(&event)
+                            .and_then(|event| serde_json::to_string(&event).ok())
+                            .and_then(|json| CString::new(json).ok());
+                        if let Some(json) = json {
+                            callback(json.as_ptr(), user_data.0);
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        }));
+    }
+}
+
+impl Drop for FfiEngine {
+    fn drop(&mut self) {
+        self.set_callback(None);
+        let _ = self.runtime.block_on(timeout(SHUTDOWN_TIMEOUT, self.handle.shutdown()));
+    }
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(error: anyhow::Error) {
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(error.to_string()));
+}
+
+unsafe fn read_str<'a>(text: *const c_char) -> Result<&'a str> {
+    if text.is_null() {
+        return Err(anyhow!("Null string"));
+    }
+    Ok(CStr::from_ptr(text).to_str()?)
+}
+
+fn into_c_string(text: String) -> *mut c_char {
+    // JSON escapes NULs, so this only fails on text we never hand out
+    CString::new(text).map_or(std::ptr::null_mut(), CString::into_raw)
+}
+
+// 0 on success; -1 with the error kept for webrtc_client_last_error
+unsafe fn send_command(engine: *const FfiEngine, command: impl FnOnce() -> Result<FfiCommand>) -> i32 {
+    let Some(engine) = engine.as_ref() else {
+        set_last_error(anyhow!("Null engine"));
+        return -1;
+    };
+    match command().and_then(|command| engine.send(command)) {
+        Ok(()) => 0,
+        Err(e) => {
+            set_last_error(e);
+            -1
+        }
+    }
+}
+
+/// WEBRTC_CLIENT_ABI_VERSION of the library actually loaded.
+#[no_mangle]
+pub extern "C" fn webrtc_client_abi_version() -> u32 {
+    WEBRTC_CLIENT_ABI_VERSION
+}
+
+/// Starts an engine from a JSON FfiConfig. Returns null on failure; see
+/// webrtc_client_last_error.
+///
+/// # Safety
+/// `config_json` must be a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn webrtc_client_engine_new(config_json: *const c_char) -> *mut FfiEngine {
+    let engine = read_str(config_json)
+        .and_then(|json| Ok(serde_json::from_str::<FfiConfig>(json)?))
+        .and_then(FfiEngine::start);
+    match engine {
+        Ok(engine) => Box::into_raw(Box::new(engine)),
+        Err(e) => {
+            set_last_error(e);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Connects to the signaling server and joins the configured room.
+///
+/// # Safety
+/// `engine` must come from webrtc_client_engine_new and not be freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn webrtc_client_engine_join(engine: *const FfiEngine) -> i32 {
+    send_command(engine, || Ok(FfiCommand::Connect))
+}
+
+/// Calls one peer in the room.
+///
+/// # Safety
+/// `engine` must come from webrtc_client_engine_new and not be freed yet; `peer_id`
+/// must be a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn webrtc_client_engine_call(engine: *const FfiEngine, peer_id: *const c_char) -> i32 {
+    send_command(engine, || Ok(FfiCommand::Call { peers: vec![read_str(peer_id)?.to_string()] }))
+}
+
+/// Accepts or declines the incoming call from `from_peer`.
+///
+/// # Safety
+/// `engine` must come from webrtc_client_engine_new and not be freed yet; `room_id`
+/// and `from_peer` must be valid NUL-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn webrtc_client_engine_answer(
+    engine: *const FfiEngine,
+    room_id: *const c_char,
+    from_peer: *const c_char,
+    accepted: bool,
+) -> i32 {
+    send_command(engine, || {
+        Ok(FfiCommand::Answer {
+            room_id: read_str(room_id)?.to_string(),
+            from_peer: read_str(from_peer)?.to_string(),
+            accepted,
+        })
+    })
+}
+
+/// # Safety
+/// `engine` must come from webrtc_client_engine_new and not be freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn webrtc_client_engine_hang_up(engine: *const FfiEngine) -> i32 {
+    send_command(engine, || Ok(FfiCommand::HangUp))
+}
+
+/// Sends silence instead of the microphone; it carries over to later calls.
+///
+/// # Safety
+/// `engine` must come from webrtc_client_engine_new and not be freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn webrtc_client_engine_set_muted(engine: *const FfiEngine, muted: bool) -> i32 {
+    send_command(engine, || Ok(FfiCommand::SetMuted { muted }))
+}
+
+/// Sends a JSON FfiCommand, for commands without a function of their own. Returns 0
+/// on success and -1 on failure; see webrtc_client_last_error.
+///
+/// # Safety
+/// `engine` must come from webrtc_client_engine_new and not be freed yet;
+/// `command_json` must be a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn webrtc_client_engine_send(engine: *const FfiEngine, command_json: *const c_char) -> i32 {
+    send_command(engine, || Ok(serde_json::from_str::<FfiCommand>(read_str(command_json)?)?))
+}
+
+/// Calls `callback` with every event from now on, replacing any earlier callback; a
+/// null callback removes it. Once this returns the old callback won't be called again.
+///
+/// # Safety
+/// `engine` must come from webrtc_client_engine_new and not be freed yet; `user_data`
+/// must stay valid, and usable from another thread, until the callback is replaced or
+/// the engine freed.
+#[no_mangle]
+pub unsafe extern "C" fn webrtc_client_engine_set_event_callback(
+    engine: *const FfiEngine,
+    callback: Option<WebrtcClientEventCallback>,
+    user_data: *mut c_void,
+) {
+    if let Some(engine) = engine.as_ref() {
+        engine.set_callback(callback.map(|callback| (callback, UserData(user_data))));
+    }
+}
+
+/// The next event as JSON, waiting up to `timeout_ms`; null if none arrived. For apps
+/// that would rather poll from a thread of their own than take callbacks.
+///
+/// # Safety
+/// `engine` must come from webrtc_client_engine_new and not be freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn webrtc_client_engine_next_event(engine: *const FfiEngine, timeout_ms: u32) -> *mut c_char {
+    let Some(engine) = engine.as_ref() else { return std::ptr::null_mut() };
+    match engine.next_event(Duration::from_millis(timeout_ms.into())) {
+        Some(event) => serde_json::to_string(&event).map_or(std::ptr::null_mut(), into_c_string),
+        None => std::ptr::null_mut(),
+    }
+}
+
+/// The current call's latest stats as JSON, refreshed every second; null between
+/// calls.
+///
+/// # Safety
+/// `engine` must come from webrtc_client_engine_new and not be freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn webrtc_client_engine_quality(engine: *const FfiEngine) -> *mut c_char {
+    let Some(quality) = engine.as_ref().and_then(FfiEngine::quality) else { return std::ptr::null_mut() };
+    serde_json::to_string(&quality).map_or(std::ptr::null_mut(), into_c_string)
+}
+
+/// Hangs up, says goodbye to the server and frees the engine.
+///
+/// # Safety
+/// `engine` must come from webrtc_client_engine_new, or be null. It is invalid
+/// afterwards. Not from inside the event callback.
+#[no_mangle]
+pub unsafe extern "C" fn webrtc_client_engine_free(engine: *mut FfiEngine) {
+    if !engine.is_null() {
+        drop(Box::from_raw(engine));
+    }
+}
+
+/// The last error on this thread, or null.
+#[no_mangle]
+pub extern "C" fn webrtc_client_last_error() -> *mut c_char {
+    LAST_ERROR.with(|last| last.borrow().clone()).map_or(std::ptr::null_mut(), into_c_string)
+}
+
+/// # Safety
+/// `text` must be a string returned by this library, or null.
+#[no_mangle]
+pub unsafe extern "C" fn webrtc_client_string_free(text: *mut c_char) {
+    if !text.is_null() {
+        drop(CString::from_raw(text));
+    }
+}
@@ -0,0 +1,123 @@
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+const FALLBACK_LOCALE: &str = "en";
+
+// Catalogs are compiled in so a missing locales/ directory can never leave the UI blank
+const CATALOGS: &[(&str, &str)] = &[
+    ("en", include_str!("../locales/en.json")),
+    ("de", include_str!("../locales/de.json")),
+];
+
+struct Translations {
+    locale: String,
+    strings: HashMap<String, String>,
+    fallback: HashMap<String, String>,
+}
+
+static TRANSLATIONS: OnceLock<Translations> = OnceLock::new();
+
+// Pick the locale once at startup; later calls are ignored
+pub fn init(locale: Option<&str>) {
+    let requested = locale.map(str::to_string).unwrap_or_else(detect_locale);
+    let locale = if catalog_source(&requested).is_some() {
+        requested
+    } else {
+        FALLBACK_LOCALE.to_string()
+    };
+
+    let _ = TRANSLATIONS.set(Translations {
+        strings: parse_catalog(&locale),
+        fallback: parse_catalog(FALLBACK_LOCALE),
+        locale,
+    });
+}
+
+pub fn current_locale() -> &'static str {
+    translations().locale.as_str()
+}
+
+pub fn available_locales() -> Vec<&'static str> {
+    CATALOGS.iter().map(|(locale, _)| *locale).collect()
+}
+
+pub fn tr(key: &'static str) -> &'static str {
+    let translations = translations();
+    translations
+        .strings
+        .get(key)
+        .or_else(|| translations.fallback.get(key))
+        .map(|value| value.as_str())
+        .unwrap_or(key)
+}
+
+// Substitutes {name} placeholders in the translated string
+pub fn tr_args(key: &'static str, args: &[(&str, &str)]) -> String {
+    args.iter().fold(tr(key).to_string(), |text, (name, value)| {
+        text.replace(&format!("{{{}}}", name), value)
+    })
+}
+
+// Template for translators: every key with its English source text
+pub fn export_template() -> String {
+    let mut entries: Vec<_> = parse_catalog(FALLBACK_LOCALE).into_iter().collect();
+    entries.sort();
+    let map: serde_json::Map<String, serde_json::Value> = entries
+        .into_iter()
+        .map(|(key, value)| (key, serde_json::Value::String(value)))
+        .collect();
+    serde_json::to_string_pretty(&map).unwrap_or_default()
+}
+
+// Keys present in the English catalog but not yet translated for `locale`
+pub fn missing_keys(locale: &str) -> Vec<String> {
+    let translated = parse_catalog(locale);
+    let mut missing: Vec<String> = parse_catalog(FALLBACK_LOCALE)
+        .into_keys()
+        .filter(|key| !translated.contains_key(key))
+        .collect();
+    missing.sort();
+    missing
+}
+
+fn translations() -> &'static Translations {
+    TRANSLATIONS.get_or_init(|| Translations {
+        locale: FALLBACK_LOCALE.to_string(),
+        strings: parse_catalog(FALLBACK_LOCALE),
+        fallback: parse_catalog(FALLBACK_LOCALE),
+    })
+}
+
+fn catalog_source(locale: &str) -> Option<&'static str> {
+    CATALOGS
+        .iter()
+        .find(|(name, _)| *name == locale)
+        .map(|(_, source)| *source)
+}
+
+fn parse_catalog(locale: &str) -> HashMap<String, String> {
+    catalog_source(locale)
+        .and_then(|source| match serde_json::from_str(source) {
+            Ok(strings) => Some(strings),
+            Err(e) => {
+                eprintln!("Failed to parse {} translations: {}", locale, e);
+                None
+            }
+        })
+        .unwrap_or_default()
+}
+
+// LC_ALL > LC_MESSAGES > LANG, reduced to the language part ("de_DE.UTF-8" -> "de")
+fn detect_locale() -> String {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|value| !value.is_empty() && value != "C" && value != "POSIX")
+        .and_then(|value| {
+            value
+                .split(|c| c == '_' || c == '.' || c == '-')
+                .next()
+                .map(|lang| lang.to_lowercase())
+        })
+        .unwrap_or_else(|| FALLBACK_LOCALE.to_string())
+}
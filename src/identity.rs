@@ -0,0 +1,132 @@
+use anyhow::{anyhow, Result};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+#[cfg(not(target_arch = "wasm32"))]
+use std::fs;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::config::config_dir;
+
+#[cfg(not(target_arch = "wasm32"))]
+const KEY_FILE: &str = "identity.key";
+// Peer IDs of this form are derived from a key, so messages claiming one must be signed
+pub const PEER_ID_PREFIX: &str = "id-";
+// 128 bits of the public key, enough that nobody can make a key matching someone's ID
+const FINGERPRINT_BYTES: usize = 16;
+
+// This installation's long-lived keypair. The peer ID is derived from the public key,
+// so the same ID across sessions means the same key, and a signature proves it.
+#[derive(Clone)]
+pub struct Identity {
+    key: SigningKey,
+}
+
+impl Identity {
+    pub fn generate() -> Self {
+        Self {
+            key: SigningKey::from_bytes(&rand::random::<[u8; 32]>()),
+        }
+    }
+
+    // The key next to config.json, created on first run. The browser build has no
+    // config directory; it generates a key per session.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load_or_create() -> Result<Self> {
+        let dir = config_dir();
+        fs::create_dir_all(&dir)?;
+        Self::load_or_create_at(&dir.join(KEY_FILE))
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load_or_create_at(path: &Path) -> Result<Self> {
+        if path.exists() {
+            let bytes = fs::read(path)?;
+            let seed: [u8; 32] = bytes
+                .as_slice()
+                .try_into()
+                .map_err(|_| anyhow!("{} is not an Ed25519 key ({} bytes)", path.display(), bytes.len()))?;
+            return Ok(Self {
+                key: SigningKey::from_bytes(&seed),
+            });
+        }
+        let identity = Self::generate();
+        write_private(path, identity.key.as_bytes())?;
+        println!("Created a new identity {}", identity.peer_id());
+        Ok(identity)
+    }
+
+    pub fn peer_id(&self) -> String {
+        peer_id_for(&self.key.verifying_key())
+    }
+
+    pub fn public_key_hex(&self) -> String {
+        to_hex(self.key.verifying_key().as_bytes())
+    }
+
+    pub fn sign(&self, payload: &[u8]) -> String {
+        to_hex(&self.key.sign(payload).to_bytes())
+    }
+}
+
+// Only the public side; the key itself never ends up in a log
+impl std::fmt::Debug for Identity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Identity").field("peer_id", &self.peer_id()).finish()
+    }
+}
+
+pub fn peer_id_for(key: &VerifyingKey) -> String {
+    format!("{}{}", PEER_ID_PREFIX, to_hex(&key.as_bytes()[..FINGERPRINT_BYTES]))
+}
+
+pub fn is_identity_peer_id(peer_id: &str) -> bool {
+    peer_id
+        .strip_prefix(PEER_ID_PREFIX)
+        .map_or(false, |hex| hex.len() == FINGERPRINT_BYTES * 2 && hex.bytes().all(|b| b.is_ascii_hexdigit()))
+}
+
+// Checks that `signature` over `payload` was made by `public_key` and that the key is
+// the one `peer_id` was derived from
+pub fn verify(peer_id: &str, public_key: &str, signature: &str, payload: &[u8]) -> Result<()> {
+    let key_bytes: [u8; 32] = from_hex(public_key)?
+        .try_into()
+        .map_err(|_| anyhow!("Public key has the wrong length"))?;
+    let key = VerifyingKey::from_bytes(&key_bytes).map_err(|e| anyhow!("Invalid public key: {}", e))?;
+    if peer_id_for(&key) != peer_id {
+        return Err(anyhow!("Key does not belong to {}", peer_id));
+    }
+    let signature_bytes: [u8; 64] = from_hex(signature)?
+        .try_into()
+        .map_err(|_| anyhow!("Signature has the wrong length"))?;
+    key.verify(payload, &Signature::from_bytes(&signature_bytes))
+        .map_err(|_| anyhow!("Bad signature from {}", peer_id))
+}
+
+#[cfg(unix)]
+fn write_private(path: &Path, bytes: &[u8]) -> Result<()> {
+    use std::io::Write;
+    use std::os::unix::fs::OpenOptionsExt;
+    let mut file = fs::OpenOptions::new().write(true).create_new(true).mode(0o600).open(path)?;
+    file.write_all(bytes)?;
+    Ok(())
+}
+
+#[cfg(not(any(unix, target_arch = "wasm32")))]
+fn write_private(path: &Path, bytes: &[u8]) -> Result<()> {
+    Ok(fs::write(path, bytes)?)
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(text: &str) -> Result<Vec<u8>> {
+    if !text.is_ascii() || text.len() % 2 != 0 {
+        return Err(anyhow!("Not a hex string"));
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&text[i..i + 2], 16).map_err(|e| anyhow!("Invalid hex: {}", e)))
+        .collect()
+}
@@ -0,0 +1,147 @@
+use async_trait::async_trait;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+use webrtc::interceptor::stream_info::StreamInfo;
+use webrtc::interceptor::{
+    Attributes, Interceptor, InterceptorBuilder, RTCPReader, RTCPWriter, RTPReader, RTPWriter,
+};
+use webrtc::rtp::packet::Packet;
+use webrtc::util::MarshalSize;
+
+// Developer-mode network impairment for outgoing RTP. Lets jitter-buffer and quality
+// adaptation be exercised on a clean local network.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ImpairmentConfig {
+    pub enabled: bool,
+    pub latency_ms: u64,
+    // Each packet's delay varies uniformly by up to +/- this much
+    pub jitter_ms: u64,
+    pub loss_percent: f64,
+}
+
+impl Default for ImpairmentConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            latency_ms: 0,
+            jitter_ms: 0,
+            loss_percent: 0.0,
+        }
+    }
+}
+
+impl ImpairmentConfig {
+    fn should_drop(&self) -> bool {
+        self.loss_percent > 0.0 && rand::thread_rng().gen_range(0.0..100.0) < self.loss_percent
+    }
+
+    fn next_delay(&self) -> Duration {
+        let jitter = if self.jitter_ms > 0 {
+            rand::thread_rng().gen_range(-(self.jitter_ms as i64)..=self.jitter_ms as i64)
+        } else {
+            0
+        };
+        Duration::from_millis((self.latency_ms as i64 + jitter).max(0) as u64)
+    }
+}
+
+pub struct ImpairmentInterceptorBuilder {
+    config: ImpairmentConfig,
+}
+
+impl ImpairmentInterceptorBuilder {
+    pub fn new(config: ImpairmentConfig) -> Self {
+        Self { config }
+    }
+}
+
+impl InterceptorBuilder for ImpairmentInterceptorBuilder {
+    fn build(&self, _id: &str) -> webrtc::interceptor::Result<Arc<dyn Interceptor + Send + Sync>> {
+        Ok(Arc::new(ImpairmentInterceptor {
+            config: self.config,
+        }))
+    }
+}
+
+pub struct ImpairmentInterceptor {
+    config: ImpairmentConfig,
+}
+
+#[async_trait]
+impl Interceptor for ImpairmentInterceptor {
+    async fn bind_rtcp_reader(
+        &self,
+        reader: Arc<dyn RTCPReader + Send + Sync>,
+    ) -> Arc<dyn RTCPReader + Send + Sync> {
+        reader
+    }
+
+    async fn bind_rtcp_writer(
+        &self,
+        writer: Arc<dyn RTCPWriter + Send + Sync>,
+    ) -> Arc<dyn RTCPWriter + Send + Sync> {
+        writer
+    }
+
+    async fn bind_local_stream(
+        &self,
+        _info: &StreamInfo,
+        writer: Arc<dyn RTPWriter + Send + Sync>,
+    ) -> Arc<dyn RTPWriter + Send + Sync> {
+        Arc::new(ImpairedWriter {
+            config: self.config,
+            next: writer,
+        })
+    }
+
+    async fn unbind_local_stream(&self, _info: &StreamInfo) {}
+
+    async fn bind_remote_stream(
+        &self,
+        _info: &StreamInfo,
+        reader: Arc<dyn RTPReader + Send + Sync>,
+    ) -> Arc<dyn RTPReader + Send + Sync> {
+        reader
+    }
+
+    async fn unbind_remote_stream(&self, _info: &StreamInfo) {}
+
+    async fn close(&self) -> webrtc::interceptor::Result<()> {
+        Ok(())
+    }
+}
+
+struct ImpairedWriter {
+    config: ImpairmentConfig,
+    next: Arc<dyn RTPWriter + Send + Sync>,
+}
+
+#[async_trait]
+impl RTPWriter for ImpairedWriter {
+    async fn write(&self, pkt: &Packet, attributes: &Attributes) -> webrtc::interceptor::Result<usize> {
+        let size = pkt.marshal_size();
+
+        // Dropped packets report success so the sender behaves as if the network lost them
+        if self.config.should_drop() {
+            return Ok(size);
+        }
+
+        let delay = self.config.next_delay();
+        if delay.is_zero() {
+            return self.next.write(pkt, attributes).await;
+        }
+
+        // Delayed packets are written from their own task, so jitter can reorder them
+        let next = self.next.clone();
+        let pkt = pkt.clone();
+        let attributes = attributes.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+            let _ = next.write(&pkt, &attributes).await;
+        });
+        Ok(size)
+    }
+}
@@ -0,0 +1,67 @@
+use anyhow::{anyhow, Result};
+use url::Url;
+
+pub const INVITE_SCHEME: &str = "webrtc-client";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Invite {
+    pub server: String,
+    pub room: String,
+}
+
+impl Invite {
+    pub fn new(server: String, room: String) -> Self {
+        Self { server, room }
+    }
+
+    // webrtc-client://join?server=<ws url>&room=<room id>
+    pub fn to_link(&self) -> String {
+        let mut url = Url::parse(&format!("{}://join", INVITE_SCHEME)).expect("static invite url");
+        url.query_pairs_mut()
+            .append_pair("server", &self.server)
+            .append_pair("room", &self.room);
+        url.to_string()
+    }
+
+    pub fn parse(link: &str) -> Result<Self> {
+        let url = Url::parse(link.trim())?;
+        if url.scheme() != INVITE_SCHEME {
+            return Err(anyhow!("Not an invite link: unexpected scheme {}", url.scheme()));
+        }
+        if url.host_str() != Some("join") {
+            return Err(anyhow!("Unsupported invite action: {}", url.host_str().unwrap_or("")));
+        }
+
+        let mut server = None;
+        let mut room = None;
+        for (key, value) in url.query_pairs() {
+            match key.as_ref() {
+                "server" => server = Some(value.into_owned()),
+                "room" => room = Some(value.into_owned()),
+                _ => {}
+            }
+        }
+
+        let server = server.ok_or_else(|| anyhow!("Invite link is missing the server"))?;
+        let room = room.ok_or_else(|| anyhow!("Invite link is missing the room"))?;
+        if !(server.starts_with("ws://") || server.starts_with("wss://")) {
+            return Err(anyhow!("Invite server must be a ws:// or wss:// URL"));
+        }
+
+        Ok(Self { server, room })
+    }
+
+    // The OS hands the link over as a plain argument when the URL scheme is registered
+    pub fn from_args() -> Option<Self> {
+        std::env::args()
+            .skip(1)
+            .find(|arg| arg.starts_with(&format!("{}:", INVITE_SCHEME)))
+            .and_then(|arg| match Self::parse(&arg) {
+                Ok(invite) => Some(invite),
+                Err(e) => {
+                    eprintln!("Ignoring invalid invite link: {}", e);
+                    None
+                }
+            })
+    }
+}
@@ -0,0 +1,235 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use crate::metrics::ConnectionQuality;
+
+// A rung's bitrate plus this much again for packet overhead and estimate noise must fit
+// in the bandwidth estimate
+const HEADROOM: f64 = 1.25;
+
+// What a call asks the other side to send, from best to worst
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QualityRung {
+    // 48 kbps fullband stereo; where every call starts
+    FullbandStereo,
+    // 32 kbps wideband mono
+    WidebandMono,
+    // 16 kbps narrowband, telephone quality
+    Narrowband,
+}
+
+impl QualityRung {
+    pub fn kbps(self) -> u32 {
+        match self {
+            QualityRung::FullbandStereo => 48,
+            QualityRung::WidebandMono => 32,
+            QualityRung::Narrowband => 16,
+        }
+    }
+
+    pub fn stereo(self) -> bool {
+        self == QualityRung::FullbandStereo
+    }
+
+    // Opus maxplaybackrate: the audio bandwidth worth encoding
+    pub fn max_playback_rate(self) -> u32 {
+        match self {
+            QualityRung::FullbandStereo => 48_000,
+            QualityRung::WidebandMono => 16_000,
+            QualityRung::Narrowband => 8_000,
+        }
+    }
+
+    pub fn down(self) -> Option<Self> {
+        match self {
+            QualityRung::FullbandStereo => Some(QualityRung::WidebandMono),
+            QualityRung::WidebandMono => Some(QualityRung::Narrowband),
+            QualityRung::Narrowband => None,
+        }
+    }
+
+    pub fn up(self) -> Option<Self> {
+        match self {
+            QualityRung::FullbandStereo => None,
+            QualityRung::WidebandMono => Some(QualityRung::FullbandStereo),
+            QualityRung::Narrowband => Some(QualityRung::WidebandMono),
+        }
+    }
+}
+
+impl fmt::Display for QualityRung {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QualityRung::FullbandStereo => write!(f, "48k stereo"),
+            QualityRung::WidebandMono => write!(f, "32k mono"),
+            QualityRung::Narrowband => write!(f, "16k narrowband"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LadderConfig {
+    pub enabled: bool,
+    // Loss at or above this, or a bandwidth estimate below the rung, for this long steps
+    // down one rung
+    pub step_down_loss_percent: f64,
+    pub step_down_after_ms: u64,
+    // Loss below this with room for the next rung up, for this long, steps back up. Much
+    // longer than stepping down, so a marginal link doesn't flap.
+    pub step_up_loss_percent: f64,
+    pub step_up_after_ms: u64,
+    // With redundant audio negotiated: loss at or above this sends RED right away, and
+    // loss under half of it for redundancy_off_after_ms goes back to plain audio
+    pub redundancy_loss_percent: f64,
+    pub redundancy_off_after_ms: u64,
+}
+
+impl Default for LadderConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            step_down_loss_percent: 5.0,
+            step_down_after_ms: 5_000,
+            step_up_loss_percent: 1.0,
+            step_up_after_ms: 20_000,
+            redundancy_loss_percent: 10.0,
+            redundancy_off_after_ms: 20_000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LadderStep {
+    pub from: QualityRung,
+    pub to: QualityRung,
+    // For the connection event log
+    pub reason: String,
+}
+
+// Walks a call down the rungs while the link is lossy or short of bandwidth and back up
+// once it has been clean for a while. Fed the call's stats once a second.
+#[derive(Debug, Clone)]
+pub struct QualityLadder {
+    config: LadderConfig,
+    rung: QualityRung,
+    down_since: Option<Instant>,
+    up_since: Option<Instant>,
+}
+
+impl QualityLadder {
+    pub fn new(config: LadderConfig) -> Self {
+        Self {
+            config,
+            rung: QualityRung::FullbandStereo,
+            down_since: None,
+            up_since: None,
+        }
+    }
+
+    pub fn rung(&self) -> QualityRung {
+        self.rung
+    }
+
+    pub fn observe(&mut self, quality: &ConnectionQuality, now: Instant) -> Option<LadderStep> {
+        if !self.config.enabled {
+            return None;
+        }
+        let loss = quality.packet_loss_rate;
+        // Zero until the transport has an estimate; loss alone decides until then
+        let bandwidth = quality.available_bitrate;
+        let fits = |rung: QualityRung| bandwidth <= 0.0 || bandwidth >= rung.kbps() as f64 * HEADROOM;
+
+        if loss >= self.config.step_down_loss_percent || !fits(self.rung) {
+            self.up_since = None;
+            let since = *self.down_since.get_or_insert(now);
+            if now - since < Duration::from_millis(self.config.step_down_after_ms) {
+                return None;
+            }
+            let to = self.rung.down()?;
+            let reason = if loss >= self.config.step_down_loss_percent {
+                format!("{:.1}% packet loss", loss)
+            } else {
+                format!("estimated bandwidth {:.0} kbps", bandwidth)
+            };
+            return Some(self.step(to, reason));
+        }
+
+        self.down_since = None;
+        match self.rung.up() {
+            Some(up) if loss < self.config.step_up_loss_percent && fits(up) => {
+                let since = *self.up_since.get_or_insert(now);
+                if now - since < Duration::from_millis(self.config.step_up_after_ms) {
+                    return None;
+                }
+                Some(self.step(up, format!("{:.1}% packet loss", loss)))
+            }
+            _ => {
+                self.up_since = None;
+                None
+            }
+        }
+    }
+
+    // The next step needs a sustained stretch of its own
+    fn step(&mut self, to: QualityRung, reason: String) -> LadderStep {
+        let from = std::mem::replace(&mut self.rung, to);
+        self.down_since = None;
+        self.up_since = None;
+        LadderStep { from, to, reason }
+    }
+}
+
+// Turns redundant audio on and off with the call's loss. Fed the same readings as the
+// ladder; runs even with the ladder off, since it doesn't touch the bitrate asked for.
+#[derive(Debug, Clone)]
+pub struct RedundancySwitch {
+    enabled: bool,
+    config: LadderConfig,
+    on: bool,
+    clean_since: Option<Instant>,
+}
+
+impl RedundancySwitch {
+    // `enabled` is whether RED was offered at all
+    pub fn new(enabled: bool, config: LadderConfig) -> Self {
+        Self {
+            enabled,
+            config,
+            on: false,
+            clean_since: None,
+        }
+    }
+
+    pub fn is_on(&self) -> bool {
+        self.on
+    }
+
+    // Some when redundancy should switch, with the new setting
+    pub fn observe(&mut self, quality: &ConnectionQuality, now: Instant) -> Option<bool> {
+        if !self.enabled {
+            return None;
+        }
+        let loss = quality.packet_loss_rate;
+        if loss >= self.config.redundancy_loss_percent {
+            self.clean_since = None;
+            return (!self.on).then(|| {
+                self.on = true;
+                true
+            });
+        }
+        if !self.on || loss >= self.config.redundancy_loss_percent / 2.0 {
+            self.clean_since = None;
+            return None;
+        }
+        let since = *self.clean_since.get_or_insert(now);
+        if now - since < Duration::from_millis(self.config.redundancy_off_after_ms) {
+            return None;
+        }
+        self.on = false;
+        self.clean_since = None;
+        Some(false)
+    }
+}
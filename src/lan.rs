@@ -0,0 +1,390 @@
+use anyhow::{anyhow, Result};
+use futures::channel::mpsc as client_channel;
+use futures_util::StreamExt;
+use mdns_sd::{ServiceDaemon, ServiceEvent, ServiceInfo};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio_util::sync::{CancellationToken, DropGuard};
+
+use crate::protocol::{SignalingMessage, PROTOCOL_VERSION};
+use crate::tasks::TaskGroup;
+
+// Serverless calls on one network: with signaling_url set to lan://, peers find each
+// other over mDNS/DNS-SD and send each other what would have gone through a signaling
+// server over direct TCP connections, one JSON frame per line. Everyone on the network
+// who joined the same room is in it.
+pub const SERVICE_TYPE: &str = "_webrtc-client._tcp.local.";
+
+// Whoever opened a connection has this long to say who they are
+const HELLO_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub fn is_lan(url: &str) -> bool {
+    url.trim_start().to_ascii_lowercase().starts_with("lan:")
+}
+
+// The first line on every connection, from the side that opened it, so replies can go
+// back the same way
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Hello {
+    pub peer_id: String,
+    pub protocol_version: u32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FoundPeer {
+    pub room_id: String,
+    pub addrs: Vec<SocketAddr>,
+}
+
+// Who is where, as far as this machine can tell; routes frames the way a signaling
+// server would
+#[derive(Debug, Default)]
+pub struct LanRoom {
+    // Our peer and room ID, from Join
+    own: Option<(String, String)>,
+    found: HashMap<String, FoundPeer>,
+}
+
+impl LanRoom {
+    pub fn join(&mut self, peer_id: &str, room_id: &str) {
+        self.own = Some((peer_id.to_string(), room_id.to_string()));
+    }
+
+    pub fn leave(&mut self) {
+        self.own = None;
+    }
+
+    pub fn own_peer(&self) -> Option<&str> {
+        self.own.as_ref().map(|(peer_id, _)| peer_id.as_str())
+    }
+
+    // True if our room's peer list changed
+    pub fn found(&mut self, peer_id: &str, peer: FoundPeer) -> bool {
+        if self.own_peer() == Some(peer_id) {
+            return false;
+        }
+        let in_room = |peer: &FoundPeer| self.is_our_room(&peer.room_id);
+        let changed = in_room(&peer) || self.found.get(peer_id).is_some_and(in_room);
+        self.found.insert(peer_id.to_string(), peer);
+        changed
+    }
+
+    pub fn lost(&mut self, peer_id: &str) -> bool {
+        self.found.remove(peer_id).is_some_and(|peer| self.is_our_room(&peer.room_id))
+    }
+
+    pub fn addrs(&self, peer_id: &str) -> Vec<SocketAddr> {
+        self.found.get(peer_id).map(|peer| peer.addrs.clone()).unwrap_or_default()
+    }
+
+    // The PeerList frame for our room, ourselves included as a server would; None
+    // before Join
+    pub fn peer_list(&self) -> Option<String> {
+        let (own_id, room_id) = self.own.as_ref()?;
+        let mut peers: Vec<String> = self
+            .found
+            .iter()
+            .filter(|(_, peer)| peer.room_id == *room_id)
+            .map(|(peer_id, _)| peer_id.clone())
+            .chain(std::iter::once(own_id.clone()))
+            .collect();
+        peers.sort();
+        serde_json::to_string(&SignalingMessage::PeerList { peers }).ok()
+    }
+
+    // to_peer, to_peers, or else everyone else in the room
+    pub fn targets(&self, frame: &Value) -> Vec<String> {
+        if let Some(to) = frame["to_peer"].as_str() {
+            return vec![to.to_string()];
+        }
+        if let Some(to) = frame["to_peers"].as_array() {
+            return to.iter().filter_map(|peer| peer.as_str().map(str::to_string)).collect();
+        }
+        self.found
+            .iter()
+            .filter(|(_, peer)| self.is_our_room(&peer.room_id))
+            .map(|(peer_id, _)| peer_id.clone())
+            .collect()
+    }
+
+    fn is_our_room(&self, room_id: &str) -> bool {
+        self.own.as_ref().is_some_and(|(_, own_room)| own_room == room_id)
+    }
+}
+
+// Keeps the node running; it stops, and leaves the network, once this is dropped
+pub struct LanSession {
+    _node: DropGuard,
+}
+
+// The client's end: frames it writes go to the node, frames for it come back
+pub type ClientEnds = (client_channel::UnboundedSender<String>, client_channel::UnboundedReceiver<String>, LanSession);
+
+pub async fn connect() -> Result<ClientEnds> {
+    let listener = TcpListener::bind(("0.0.0.0", 0)).await?;
+    let mdns = ServiceDaemon::new().map_err(|e| anyhow!("mDNS unavailable: {}", e))?;
+    let (to_node, from_client) = client_channel::unbounded();
+    let (to_client, from_node) = client_channel::unbounded();
+    let tasks = TaskGroup::new();
+    let node = tasks.cancel_on_drop();
+    let links = tasks.clone();
+    tasks.spawn_with_cleanup(|cancel| run(mdns, listener, from_client, to_client, links, cancel));
+    Ok((to_node, from_node, LanSession { _node: node }))
+}
+
+enum LinkEvent {
+    Opened(String, mpsc::UnboundedSender<String>),
+    Frame(String),
+    Closed(String, mpsc::UnboundedSender<String>),
+}
+
+struct Node {
+    mdns: ServiceDaemon,
+    port: u16,
+    room: LanRoom,
+    // Our registration while joined
+    registered: Option<String>,
+    // mDNS full names of the peers found, to tell who went away
+    names: HashMap<String, String>,
+    // Open connections, whichever side opened them
+    links: HashMap<String, mpsc::UnboundedSender<String>>,
+    events: mpsc::UnboundedSender<LinkEvent>,
+    to_client: client_channel::UnboundedSender<String>,
+    tasks: TaskGroup,
+}
+
+async fn run(
+    mdns: ServiceDaemon,
+    listener: TcpListener,
+    mut from_client: client_channel::UnboundedReceiver<String>,
+    to_client: client_channel::UnboundedSender<String>,
+    tasks: TaskGroup,
+    cancel: CancellationToken,
+) {
+    let browse = match mdns.browse(SERVICE_TYPE) {
+        Ok(browse) => browse,
+        Err(e) => {
+            eprintln!("mDNS browsing failed: {}", e);
+            return;
+        }
+    };
+    let (events, mut link_events) = mpsc::unbounded_channel();
+    let mut node = Node {
+        mdns,
+        port: listener.local_addr().map_or(0, |addr| addr.port()),
+        room: LanRoom::default(),
+        registered: None,
+        names: HashMap::new(),
+        links: HashMap::new(),
+        events,
+        to_client,
+        tasks,
+    };
+    loop {
+        tokio::select! {
+            _ = cancel.cancelled() => break,
+            frame = from_client.next() => match frame {
+                Some(frame) => node.outgoing(frame),
+                // The client closed
+                None => break,
+            },
+            Ok(event) = browse.recv_async() => node.mdns_event(event),
+            Ok((stream, _)) = listener.accept() => {
+                let events = node.events.clone();
+                node.tasks.spawn(accept(stream, events));
+            }
+            Some(event) = link_events.recv() => node.link_event(event),
+        }
+    }
+    node.unregister();
+    let _ = node.mdns.shutdown();
+}
+
+impl Node {
+    fn outgoing(&mut self, frame: String) {
+        let Ok(value) = serde_json::from_str::<Value>(&frame) else { return };
+        match value["message_type"].as_str() {
+            Some("Join") => {
+                let peer_id = value["peer_id"].as_str().unwrap_or_default();
+                let room_id = value["room_id"].as_str().unwrap_or_default();
+                self.room.join(peer_id, room_id);
+                self.register(peer_id, room_id);
+                self.send_peer_list();
+            }
+            Some("Disconnect") => {
+                self.unregister();
+                self.room.leave();
+            }
+            Some("RequestPeerList") => self.send_peer_list(),
+            _ => {
+                for peer_id in self.room.targets(&value) {
+                    self.send_to(&peer_id, frame.clone());
+                }
+            }
+        }
+    }
+
+    fn register(&mut self, peer_id: &str, room_id: &str) {
+        self.unregister();
+        // Instance names are DNS labels; the exact ID goes in the TXT record
+        let instance: String = peer_id.replace('.', "-").chars().take(63).collect();
+        let host = format!("{}.local.", instance);
+        let properties = [("peer", peer_id), ("room", room_id)];
+        match ServiceInfo::new(SERVICE_TYPE, &instance, &host, "", self.port, &properties[..]) {
+            Ok(info) => {
+                let info = info.enable_addr_auto();
+                let name = info.get_fullname().to_string();
+                match self.mdns.register(info) {
+                    Ok(()) => self.registered = Some(name),
+                    Err(e) => eprintln!("mDNS registration failed: {}", e),
+                }
+            }
+            Err(e) => eprintln!("mDNS registration failed: {}", e),
+        }
+    }
+
+    fn unregister(&mut self) {
+        if let Some(name) = self.registered.take() {
+            let _ = self.mdns.unregister(&name);
+        }
+    }
+
+    fn mdns_event(&mut self, event: ServiceEvent) {
+        match event {
+            ServiceEvent::ServiceResolved(info) => {
+                let (Some(peer_id), Some(room_id)) = (info.get_property_val_str("peer"), info.get_property_val_str("room"))
+                else {
+                    return;
+                };
+                let port = info.get_port();
+                let addrs = info.get_addresses().iter().map(|ip| SocketAddr::new((*ip).into(), port)).collect();
+                self.names.insert(info.get_fullname().to_string(), peer_id.to_string());
+                if self.room.found(peer_id, FoundPeer { room_id: room_id.to_string(), addrs }) {
+                    self.send_peer_list();
+                }
+            }
+            ServiceEvent::ServiceRemoved(_, name) => {
+                if let Some(peer_id) = self.names.remove(&name) {
+                    self.links.remove(&peer_id);
+                    if self.room.lost(&peer_id) {
+                        self.send_peer_list();
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn link_event(&mut self, event: LinkEvent) {
+        match event {
+            // Theirs replaces ours if both sides dialed at once; either way works
+            LinkEvent::Opened(peer_id, link) => {
+                self.links.insert(peer_id, link);
+            }
+            LinkEvent::Frame(frame) => {
+                let _ = self.to_client.unbounded_send(frame);
+            }
+            LinkEvent::Closed(peer_id, link) => {
+                if self.links.get(&peer_id).is_some_and(|open| open.same_channel(&link)) {
+                    self.links.remove(&peer_id);
+                }
+            }
+        }
+    }
+
+    fn send_peer_list(&self) {
+        if let Some(frame) = self.room.peer_list() {
+            let _ = self.to_client.unbounded_send(frame);
+        }
+    }
+
+    // Over the open connection, or a new one; frames queue while it's being opened
+    fn send_to(&mut self, peer_id: &str, frame: String) {
+        if let Some(link) = self.links.get(peer_id) {
+            if link.send(frame.clone()).is_ok() {
+                return;
+            }
+        }
+        let addrs = self.room.addrs(peer_id);
+        let Some(own_id) = self.room.own_peer().map(str::to_string) else { return };
+        if addrs.is_empty() {
+            return;
+        }
+        let (link, frames) = mpsc::unbounded_channel();
+        let _ = link.send(frame);
+        self.links.insert(peer_id.to_string(), link.clone());
+        self.tasks.spawn(dial(peer_id.to_string(), own_id, addrs, link, frames, self.events.clone()));
+    }
+}
+
+async fn dial(
+    peer_id: String,
+    own_id: String,
+    addrs: Vec<SocketAddr>,
+    link: mpsc::UnboundedSender<String>,
+    frames: mpsc::UnboundedReceiver<String>,
+    events: mpsc::UnboundedSender<LinkEvent>,
+) {
+    match TcpStream::connect(&addrs[..]).await {
+        Ok(stream) => {
+            let hello = serde_json::to_string(&Hello { peer_id: own_id, protocol_version: PROTOCOL_VERSION })
+                .unwrap_or_default();
+            let (read, write) = stream.into_split();
+            pump(BufReader::new(read), write, Some(hello), frames, &events).await;
+        }
+        Err(e) => eprintln!("Could not reach {} on the local network: {}", peer_id, e),
+    }
+    let _ = events.send(LinkEvent::Closed(peer_id, link));
+}
+
+async fn accept(stream: TcpStream, events: mpsc::UnboundedSender<LinkEvent>) {
+    let (read, write) = stream.into_split();
+    let mut read = BufReader::new(read);
+    let mut line = String::new();
+    let Ok(Ok(_)) = tokio::time::timeout(HELLO_TIMEOUT, read.read_line(&mut line)).await else { return };
+    let Ok(hello) = serde_json::from_str::<Hello>(line.trim_end()) else { return };
+    let (link, frames) = mpsc::unbounded_channel();
+    if events.send(LinkEvent::Opened(hello.peer_id.clone(), link.clone())).is_err() {
+        return;
+    }
+    pump(read, write, None, frames, &events).await;
+    let _ = events.send(LinkEvent::Closed(hello.peer_id, link));
+}
+
+// Until either side closes
+async fn pump(
+    read: BufReader<OwnedReadHalf>,
+    mut write: OwnedWriteHalf,
+    hello: Option<String>,
+    mut frames: mpsc::UnboundedReceiver<String>,
+    events: &mpsc::UnboundedSender<LinkEvent>,
+) {
+    let writer = async move {
+        if let Some(hello) = hello {
+            write.write_all(format!("{}\n", hello).as_bytes()).await?;
+        }
+        while let Some(frame) = frames.recv().await {
+            write.write_all(format!("{}\n", frame).as_bytes()).await?;
+        }
+        std::io::Result::Ok(())
+    };
+    let reader = async move {
+        let mut lines = read.lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            if events.send(LinkEvent::Frame(line)).is_err() {
+                break;
+            }
+        }
+    };
+    tokio::select! {
+        _ = writer => {}
+        _ = reader => {}
+    }
+}
@@ -0,0 +1,112 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::audio::AudioLatency;
+
+// Per-stage timings from the media path: how long encoding and decoding a frame takes
+// and how much audio goes into one packet. Written from the audio hot paths, so
+// lock-free and smoothed like RFC 3550 jitter (1/16 per sample).
+#[derive(Clone, Default)]
+pub struct LatencyProbe {
+    // Microseconds, 0 = not measured yet
+    encode_us: Arc<AtomicU64>,
+    decode_us: Arc<AtomicU64>,
+    packetization_us: Arc<AtomicU64>,
+}
+
+impl LatencyProbe {
+    // Marks the start of a stage; hand the result to the matching record_* call
+    pub fn mark() -> Instant {
+        Instant::now()
+    }
+
+    pub fn record_encode(&self, started: Instant, frame: Duration) {
+        smooth(&self.encode_us, started.elapsed());
+        smooth(&self.packetization_us, frame);
+    }
+
+    pub fn record_decode(&self, started: Instant) {
+        smooth(&self.decode_us, started.elapsed());
+    }
+
+    pub fn encode(&self) -> Option<Duration> {
+        read(&self.encode_us)
+    }
+
+    pub fn decode(&self) -> Option<Duration> {
+        read(&self.decode_us)
+    }
+
+    pub fn packetization(&self) -> Option<Duration> {
+        read(&self.packetization_us)
+    }
+}
+
+fn smooth(value: &AtomicU64, sample: Duration) {
+    let sample = (sample.as_micros() as u64).max(1);
+    let current = value.load(Ordering::Relaxed);
+    let next = if current == 0 {
+        sample
+    } else {
+        (current as i64 + (sample as i64 - current as i64) / 16).max(1) as u64
+    };
+    value.store(next, Ordering::Relaxed);
+}
+
+fn read(value: &AtomicU64) -> Option<Duration> {
+    match value.load(Ordering::Relaxed) {
+        0 => None,
+        us => Some(Duration::from_micros(us)),
+    }
+}
+
+// Where the time goes between someone speaking and the other side hearing it. Only our
+// half of the pipeline can be measured; the far end runs the same client, so our capture
+// side stands in for theirs.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct LatencyBreakdown {
+    pub capture: Option<Duration>,
+    pub packetization: Option<Duration>,
+    pub encode: Option<Duration>,
+    // One way, half the RTCP round trip
+    pub network: Option<Duration>,
+    pub decode: Option<Duration>,
+    // Decoded audio queued ahead of the device
+    pub buffered: Option<Duration>,
+    pub playback: Option<Duration>,
+}
+
+impl LatencyBreakdown {
+    pub fn estimate(probe: &LatencyProbe, audio: AudioLatency, round_trip_ms: f64) -> Self {
+        Self {
+            capture: audio.capture,
+            packetization: probe.packetization(),
+            encode: probe.encode(),
+            network: (round_trip_ms > 0.0).then(|| Duration::from_secs_f64(round_trip_ms / 2000.0)),
+            decode: probe.decode(),
+            buffered: audio.buffered,
+            playback: audio.playback,
+        }
+    }
+
+    // Sum of the stages measured so far; None without the network leg, which
+    // usually dominates and would make the total misleadingly low
+    pub fn mouth_to_ear(&self) -> Option<Duration> {
+        self.network?;
+        Some(
+            [
+                self.capture,
+                self.packetization,
+                self.encode,
+                self.network,
+                self.decode,
+                self.buffered,
+                self.playback,
+            ]
+            .into_iter()
+            .flatten()
+            .sum(),
+        )
+    }
+}
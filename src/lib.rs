@@ -0,0 +1,106 @@
+// Shared with the browser build: the signaling protocol, call state and metrics
+pub mod audio;
+pub mod call;
+pub mod chat;
+pub mod i18n;
+pub mod identity;
+pub mod invite;
+pub mod metrics;
+pub mod presence;
+pub mod protocol;
+pub mod room;
+pub mod server_config;
+
+// Native only: webrtc-rs, tokio, audio devices and the filesystem
+#[cfg(not(target_arch = "wasm32"))]
+pub mod api;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod config;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod connection;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod contacts;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod control;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod discovery;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod dtmf;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod echo;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod engine;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod error;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod factory;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod failover;
+// C ABI for the mobile shells
+#[cfg(all(not(target_arch = "wasm32"), feature = "ffi"))]
+pub mod ffi;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod impairment;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod ladder;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod lan;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod latency;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod manual;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod negotiation;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod nettest;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod netwatch;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod one_way;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod plugins;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod publisher;
+// Python bindings for test automation
+#[cfg(all(not(target_arch = "wasm32"), feature = "python"))]
+pub mod python;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod recording;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod reconnect;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod red;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod scripting;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod sdp_hooks;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod security;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod setup_time;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod shutdown;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod signaling;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod soak;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod storage;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod tasks;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod telemetry;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod transcription;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod turn;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod warmstart;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod watchdog;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod webrtc;
+
+// The browser's own WebRTC and WebSocket, through web-sys
+#[cfg(target_arch = "wasm32")]
+pub mod web;
@@ -1,249 +1,1745 @@
-mod audio;
-mod connection;
-mod error;
-mod metrics;
-mod signaling;
-mod webrtc;
-
-use crate::audio::{AudioCapture, AudioPlayback};
-use crate::connection::{ConnectionMonitor, ConnectionState, ConnectionStatus};
-use crate::error::{Error, Result};
-use crate::metrics::{ConnectionQuality, QualityMonitor};
-use crate::signaling::{SignalingClient, SignalingMessage};
-use crate::webrtc::WebRTCClient;
+mod ui;
+
+use webrtc_client::audio::bluetooth::{self, BluetoothProfile};
+use webrtc_client::audio::tones::{self, play_alert, RingbackStyle};
+use webrtc_client::api::{ApiCommand, ApiServer, ApiStatus};
+use webrtc_client::audio::tap::TapSource;
+use webrtc_client::audio::{AudioBackendKind, AudioStreamHandle, DeviceEvent, InputWarning, MutedSpeechBanner, StreamDirection, MAX_PLAYBACK_DELAY_MS};
+use webrtc_client::call::{format_duration, CallCue, CallSession, CallSetup, IncomingCall, PeerSetup};
+use webrtc_client::chat::ChatEntry;
+use webrtc_client::config::{RoomBookmark, Settings};
+use webrtc_client::contacts::{Contact, ContactBook};
+use webrtc_client::echo::EchoTest;
+use webrtc_client::engine::{CallDiagnostics, CallEngine, EngineCommand, EngineConfig, EngineEvent, EngineHandle};
+use webrtc_client::i18n::{self, tr, tr_args};
+use webrtc_client::identity::Identity;
+use webrtc_client::impairment::ImpairmentConfig;
+use webrtc_client::invite::Invite;
+use webrtc_client::manual::ManualCall;
+use webrtc_client::reconnect::{Backoff, ReconnectPolicy};
+use webrtc_client::recording::RecordingConfig;
+use webrtc_client::scripting::ScriptHooks;
+use webrtc_client::room::{ConsentPolicy, ConsentRequest, ConsentState, RecordingConsent};
+use webrtc_client::metrics::{ConnectionQuality, PeerQualities};
+use webrtc_client::nettest::{self, NetworkTestReport};
+use webrtc_client::one_way::OneWayAudio;
+use webrtc_client::security::CallSecurity;
+use webrtc_client::plugins::{self, PluginPanel};
+use webrtc_client::presence::{PeerPresence, PresenceStatus};
+use webrtc_client::publisher::{CallEvent, EventPublisher, QualityAlarm};
+use webrtc_client::shutdown::{self, SHUTDOWN_TIMEOUT};
+use webrtc_client::storage::{CallDirection, CallRecord, Storage};
+use webrtc_client::soak::{self, SoakConfig};
+use webrtc_client::telemetry;
+use webrtc_client::transcription::Caption;
+use webrtc_client::watchdog::FailureKind;
+use crate::ui::{CaptionLine, Captions, ChatPanel, LiveDiagnostics, NegotiationInspector, NetworkTestResult, PanelFeeds, PluginPanels, PeerSignal, PresenceDot, SecurityIndicator, Toast, Toasts, TOAST_DURATION};
+use webrtc_client::webrtc::WebRTCConfig;
 
 use dioxus::prelude::*;
-use std::collections::HashSet;
+use dioxus_desktop::tao::event::{Event, WindowEvent};
+use std::collections::{BTreeMap, HashSet};
 use std::sync::Arc;
-use tokio::sync::Mutex;
-use rand::random;
-use std::time::Duration;
+use std::time::{Duration, Instant, SystemTime};
+use tokio::sync::{broadcast, watch};
 use tokio::time::sleep;
-use webrtc::peer_connection::signaling_state::RTCSignalingState;
-use webrtc::ice_transport::ice_connection_state::RTCIceConnectionState;
-use webrtc::peer_connection::peer_connection_state::RTCPeerConnectionState;
-use webrtc::ice_transport::ice_candidate::RTCIceCandidateInit;
-use webrtc::api::media_engine::MediaEngine;
-use anyhow::Error as AnyhowError;
-
-const MAX_RECONNECT_ATTEMPTS: u32 = 5;
-const RECONNECT_DELAY_MS: u64 = 1000;
 
 struct AppState {
-    signaling: Option<Arc<Mutex<SignalingClient>>>,
-    webrtc: Option<Arc<WebRTCClient>>,
-    audio_capture: Option<AudioCapture>,
+    // Calls, signaling and presence all run in the engine, on its own thread. The
+    // window sends it commands and shows what it reports.
+    engine: EngineHandle,
     peer_id: String,
+    // Signs what we send; peer_id is derived from it
+    identity: Identity,
     room_id: String,
-    reconnect_attempts: u32,
+    // The server room_id is on: the configured one, or that of the invite or bookmark
+    // we joined from. Never saved, so following a link doesn't change the settings.
+    signaling_url: String,
+    // The current call as the engine last reported it
+    call_session: Option<CallSession>,
+    call_direction: CallDirection,
+    // Set while a call we placed is on its way, so its CallStarted counts as outgoing
+    dialing: bool,
+    // How far each callee of an outgoing mesh call got
+    call_setup: CallSetup,
+    incoming_call: Option<IncomingCall>,
+    // Our request to record the current call, and a request from someone else awaiting
+    // our answer
+    recording_consent: Option<RecordingConsent>,
+    recording_request: Option<ConsentRequest>,
+    // The ringtone or notification playing on the alert output
+    alert: Option<AudioStreamHandle>,
+    // The call tone last started for the call session's state
+    call_cue: Option<CallCue>,
+    settings: Settings,
+    // The call automation hooks of settings.scripts, as loaded at startup
+    scripts: Option<ScriptHooks>,
+    chat_log: Vec<ChatEntry>,
+    // None if the history database couldn't be opened; the app works without it
+    storage: Option<Storage>,
+    recent_calls: Vec<CallRecord>,
+    peer_presence: PeerPresence,
+    // Started from the UI once the runtime is up; None when no webhook or broker is
+    // configured
+    event_publisher: Option<EventPublisher>,
+    // Minimised or hidden, for pausing the call panels
+    window_hidden: bool,
 }
 
+// How much of a room's chat is shown again after a restart
+const CHAT_HISTORY_LIMIT: usize = 200;
+const RECENT_CALLS_LIMIT: usize = 10;
+// Caption lines kept on screen
+const CAPTION_LIMIT: usize = 50;
+
 impl AppState {
-    async fn reconnect(&mut self) -> Result<()> {
-        if self.reconnect_attempts >= MAX_RECONNECT_ATTEMPTS {
-            return Err(Error::Connection(
-                "Max reconnection attempts reached".to_string(),
-            ));
-        }
-
-        self.reconnect_attempts += 1;
-        sleep(Duration::from_millis(RECONNECT_DELAY_MS)).await;
-
-        // Try to reconnect WebSocket
-        match SignalingClient::connect("ws://127.0.0.1:8080").await {
-            Ok(client) => {
-                let client = Arc::new(Mutex::new(client));
-                
-                // Re-join the room
-                let join_msg = SignalingMessage::Join {
-                    room_id: self.room_id.clone(),
-                    peer_id: self.peer_id.clone(),
-                };
-                
-                client.lock().await.send(join_msg).await?;
-                self.signaling = Some(client);
-                self.reconnect_attempts = 0;
-                Ok(())
+    fn new() -> Self {
+        let storage = Storage::open_default()
+            .map_err(|e| eprintln!("Chat and call history unavailable: {}", e))
+            .ok();
+        let mut settings = Settings::load();
+        let args: Vec<String> = std::env::args().collect();
+        if let Some(profile) = arg_value(&args, "--profile") {
+            if let Err(e) = settings.switch_profile(&profile) {
+                eprintln!("{}; using the last settings", e);
             }
-            Err(e) => {
-                Err(Error::Connection(format!("Reconnection failed: {}", e)))
+        }
+        // For bots driving the app: stdin, a FIFO path, or udp:<address>
+        if let Some(source) = arg_value(&args, "--capture-from") {
+            match source.parse() {
+                Ok(source) => settings.capture_source = Some(source),
+                Err(e) => eprintln!("{}; using the microphone", e),
             }
         }
+        let identity = Identity::load_or_create().unwrap_or_else(|e| {
+            eprintln!("Could not load the saved identity, using a temporary one: {}", e);
+            Identity::generate()
+        });
+        let scripts = settings.scripts.as_deref().and_then(|path| {
+            ScriptHooks::load(path).map_err(|e| eprintln!("Scripts not loaded: {}", e)).ok()
+        });
+        let room_id = "test-room".to_string();
+        let engine = CallEngine::spawn_on_thread(engine_config(&settings, &identity, &room_id, &scripts)).unwrap_or_else(|e| {
+            eprintln!("Failed to start the call engine: {}", e);
+            std::process::exit(1);
+        });
+        let mut state = Self {
+            engine,
+            peer_id: identity.peer_id(),
+            identity,
+            room_id,
+            signaling_url: settings.signaling_url.clone(),
+            call_session: None,
+            call_direction: CallDirection::Outgoing,
+            dialing: false,
+            call_setup: CallSetup::default(),
+            incoming_call: None,
+            recording_consent: None,
+            recording_request: None,
+            alert: None,
+            call_cue: None,
+            event_publisher: None,
+            settings,
+            scripts,
+            chat_log: Vec::new(),
+            storage,
+            recent_calls: Vec::new(),
+            peer_presence: PeerPresence::default(),
+            window_hidden: false,
+        };
+        state.load_history();
+        state
+    }
+
+    // Only fails once the engine has shut down, which it does when the app quits
+    fn send(&self, command: EngineCommand) {
+        if let Err(e) = self.engine.send(command) {
+            eprintln!("{}", e);
+        }
+    }
+
+    fn engine_config(&self) -> EngineConfig {
+        EngineConfig {
+            signaling_url: self.signaling_url.clone(),
+            ..engine_config(&self.settings, &self.identity, &self.room_id, &self.scripts)
+        }
+    }
+
+    // Saves the settings and hands them to the engine, which applies what it can to a
+    // running call
+    fn save_settings(&self) {
+        if let Err(e) = self.settings.save() {
+            eprintln!("Failed to save settings: {}", e);
+        }
+        self.send(EngineCommand::Reconfigure(Box::new(self.engine_config())));
+    }
+
+    fn stats_paused(&self) -> bool {
+        self.window_hidden && self.settings.webrtc.stats.pause_when_hidden
+    }
+
+    // Chat of the current room and the latest calls, from the database
+    fn load_history(&mut self) {
+        let Some(ref storage) = self.storage else { return };
+        match storage.chat_history(&self.room_id, CHAT_HISTORY_LIMIT) {
+            Ok(entries) => self.chat_log = entries,
+            Err(e) => eprintln!("Failed to load chat history: {}", e),
+        }
+        match storage.recent_calls(RECENT_CALLS_LIMIT) {
+            Ok(calls) => self.recent_calls = calls,
+            Err(e) => eprintln!("Failed to load call history: {}", e),
+        }
     }
 
-    async fn handle_connection_error(&mut self, error: Error) -> Result<()> {
-        match error {
-            Error::WebSocket(_) | Error::Connection(_) => {
-                println!("Connection error, attempting to reconnect...");
-                self.reconnect().await
+    fn add_chat(&mut self, entry: ChatEntry) {
+        if let (Some(ref storage), true) = (&self.storage, self.settings.keep_history) {
+            if let Err(e) = storage.add_chat(&self.room_id, &entry) {
+                eprintln!("Failed to save chat message: {}", e);
             }
-            Error::WebRTC(e) => {
-                // If it's a fatal WebRTC error, clean up and restart the call
-                println!("WebRTC error: {}, cleaning up...", e);
-                self.cleanup_call().await;
-                Err(Error::WebRTC(e))
+        }
+        self.chat_log.push(entry);
+    }
+
+    fn record_call(&mut self, record: CallRecord) {
+        if let (Some(ref storage), true) = (&self.storage, self.settings.keep_history) {
+            if let Err(e) = storage.add_call(&record) {
+                eprintln!("Failed to save call history: {}", e);
             }
-            Error::Audio(e) => {
-                // Log audio error but try to continue
-                println!("Audio error: {}, continuing...", e);
-                Ok(())
+        }
+        self.recent_calls.insert(0, record);
+        self.recent_calls.truncate(RECENT_CALLS_LIMIT);
+    }
+
+    fn purge_history(&mut self) -> anyhow::Result<()> {
+        if let Some(ref storage) = self.storage {
+            storage.purge_history()?;
+        }
+        self.chat_log.clear();
+        self.recent_calls.clear();
+        Ok(())
+    }
+
+    // Never over the ringtone; other alerts are short and may cut each other off
+    fn play_roster_cue(&mut self, joined: bool) {
+        if self.incoming_call.is_some() {
+            return;
+        }
+        let cues = &self.settings.roster_cues;
+        let (custom, built_in): (_, fn() -> Vec<f32>) = if joined {
+            (cues.join_sound.clone(), tones::joined)
+        } else {
+            (cues.leave_sound.clone(), tones::left)
+        };
+        let sound = custom
+            .and_then(|path| tones::load(&path).map_err(|e| eprintln!("Failed to load {}: {}", path.display(), e)).ok())
+            .unwrap_or_else(built_in);
+        self.play_alert(sound, false);
+    }
+
+    // Ringback while an outgoing call dials, busy or failed if it doesn't go through.
+    // Called after anything that moves the call session along.
+    fn follow_call_cue(&mut self) {
+        let cue = self
+            .call_session
+            .as_ref()
+            .filter(|_| self.call_direction == CallDirection::Outgoing)
+            .and_then(|session| session.state.cue());
+        if cue == self.call_cue {
+            return;
+        }
+        match cue {
+            Some(cue) if self.settings.call_tones.enabled => {
+                let sound = self.settings.call_tones.sound(cue);
+                self.play_alert(sound, cue.repeats());
             }
-            _ => Err(error),
+            // Busy and failed play out even once the call is cleaned up
+            None if self.call_cue == Some(CallCue::Ringback) => self.alert = None,
+            _ => {}
         }
+        self.call_cue = cue;
     }
 
-    async fn cleanup_call(&mut self) {
-        self.webrtc = None;
-        self.audio_capture = None;
-        
-        if let Some(ref signaling) = self.signaling {
-            let _ = signaling.lock().await.send(SignalingMessage::EndCall {
-                room_id: self.room_id.clone(),
-                peer_id: self.peer_id.clone(),
-            }).await;
+    // The engine has hung up and closed the connections; what's left is the call's record
+    fn end_call(&mut self) {
+        self.call_setup = CallSetup::default();
+        self.recording_consent = None;
+        self.recording_request = None;
+        if let Some(session) = self.call_session.take() {
+            let answered = session.answered();
+            self.record_call(CallRecord::from_session(&session, self.call_direction, answered));
+            self.publish_event(CallEvent::Ended {
+                room: session.room_id.clone(),
+                participants: session.participants.clone(),
+                answered,
+                duration_secs: session.elapsed().as_secs(),
+            });
+        }
+        self.follow_call_cue();
+    }
+
+    fn publish_event(&self, event: CallEvent) {
+        if let Some(ref publisher) = self.event_publisher {
+            publisher.publish(event);
         }
     }
+
+    // Replaces whatever alert is playing
+    fn play_alert(&mut self, sound: Vec<f32>, repeat: bool) {
+        let backend = self.settings.create_audio_backend();
+        self.alert = match play_alert(&backend, sound, repeat) {
+            Ok(alert) => Some(alert),
+            Err(e) => {
+                eprintln!("Failed to play alert: {}", e);
+                None
+            }
+        };
+    }
+}
+
+// What the engine runs with: the settings, plus who we are, which room we're in and the
+// scripts loaded from the settings
+fn engine_config(settings: &Settings, identity: &Identity, room_id: &str, scripts: &Option<ScriptHooks>) -> EngineConfig {
+    EngineConfig {
+        signaling_url: settings.signaling_url.clone(),
+        signaling_token: settings.signaling_token.clone(),
+        room_id: room_id.to_string(),
+        peer_id: identity.peer_id(),
+        identity: Some(identity.clone()),
+        auto_answer: settings.auto_answer,
+        auto_answer_allowlist: settings.auto_answer_allowlist.clone(),
+        audio_backend: settings.audio_backend,
+        audio_buffer_frames: settings.audio_buffer_frames,
+        sidetone_level: settings.sidetone_level,
+        playback_delay_ms: settings.playback_delay_ms,
+        output_routing: settings.output_routing.clone(),
+        capture_source: settings.capture_source.clone(),
+        recording: Some(settings.recording.clone()),
+        transcription: settings.transcription.clone(),
+        idle: settings.idle,
+        scripts: scripts.clone(),
+        webrtc: settings.webrtc.clone(),
+        reconnect: settings.reconnect,
+        signaling_channels: settings.signaling_channels,
+        signaling_failover: settings.signaling_failover.clone(),
+        watch_network: true,
+    }
+}
+
+// Everything the remote side and the disk should see before the process goes away.
+// Bounded so an unreachable signaling server can't keep the app from closing.
+async fn shutdown(state: &UseRef<AppState>, contacts: &UseRef<ContactBook>) {
+    let engine = state.read().engine.clone();
+    let _ = tokio::time::timeout(SHUTDOWN_TIMEOUT, engine.shutdown()).await;
+    save_on_exit(state, contacts);
+}
+
+// From the window's event loop, which has no runtime of its own to wait on
+fn shutdown_blocking(state: &UseRef<AppState>, contacts: &UseRef<ContactBook>) {
+    let engine = state.read().engine.clone();
+    engine.shutdown_blocking(SHUTDOWN_TIMEOUT);
+    save_on_exit(state, contacts);
+}
+
+fn save_on_exit(state: &UseRef<AppState>, contacts: &UseRef<ContactBook>) {
+    if let Err(e) = state.read().settings.save() {
+        eprintln!("Failed to save settings: {}", e);
+    }
+    if let Err(e) = contacts.read().save() {
+        eprintln!("Failed to save contacts: {}", e);
+    }
+    telemetry::shutdown();
 }
 
 #[derive(Props)]
 struct PeerItemProps<'a> {
     peer_id: String,
     selected: bool,
+    // No entry while we have no media connection to this peer
+    qualities: &'a watch::Receiver<PeerQualities>,
+    presence: Option<PresenceStatus>,
     on_select: EventHandler<'a, String>,
 }
 
 fn PeerItem<'a>(cx: Scope<'a, PeerItemProps<'a>>) -> Element {
+    let checkbox_id = format!("peer-{}", cx.props.peer_id);
+
     cx.render(rsx! {
         div { class: "peer-item",
             input {
+                id: "{checkbox_id}",
                 r#type: "checkbox",
                 checked: "{cx.props.selected}",
+                aria_label: tr_args("a11y.select_peer", &[("peer", &cx.props.peer_id)]),
                 onclick: move |_| cx.props.on_select.call(cx.props.peer_id.clone())
             }
-            span { "{cx.props.peer_id}" }
+            PresenceDot { status: cx.props.presence }
+            label { r#for: "{checkbox_id}", "{cx.props.peer_id}" }
+            PeerSignal { peer_id: &cx.props.peer_id, qualities: cx.props.qualities }
+        }
+    })
+}
+
+#[derive(Props)]
+struct ContactItemProps<'a> {
+    contact: Contact,
+    presence: Option<PresenceStatus>,
+    can_call: bool,
+    on_call: EventHandler<'a, String>,
+    on_toggle_favorite: EventHandler<'a, String>,
+    on_remove: EventHandler<'a, String>,
+}
+
+fn ContactItem<'a>(cx: Scope<'a, ContactItemProps<'a>>) -> Element {
+    let contact = &cx.props.contact;
+    let star = if contact.favorite { "\u{2605}" } else { "\u{2606}" };
+
+    cx.render(rsx! {
+        div { class: "contact-item",
+            button {
+                class: "favorite-toggle",
+                title: tr("contacts.toggle_favorite"),
+                aria_label: tr("contacts.toggle_favorite"),
+                aria_pressed: "{contact.favorite}",
+                onclick: move |_| cx.props.on_toggle_favorite.call(cx.props.contact.peer_id.clone()),
+                "{star}"
+            }
+            PresenceDot { status: cx.props.presence }
+            span { class: "contact-name", "{contact.name}" }
+            span { class: "contact-peer-id", "{contact.peer_id}" }
+            button {
+                disabled: "{!cx.props.can_call}",
+                aria_label: tr_args("a11y.call_contact", &[("name", &contact.name)]),
+                onclick: move |_| cx.props.on_call.call(cx.props.contact.peer_id.clone()),
+                {tr("contacts.call")}
+            }
+            button {
+                class: "end-call",
+                aria_label: tr_args("a11y.remove_contact", &[("name", &contact.name)]),
+                onclick: move |_| cx.props.on_remove.call(cx.props.contact.peer_id.clone()),
+                {tr("contacts.remove")}
+            }
+        }
+    })
+}
+
+#[derive(Props)]
+struct CallHeaderProps<'a> {
+    session: CallSession,
+    // None until the connection has been checked
+    security: Option<CallSecurity>,
+    is_muted: bool,
+    on_toggle_mute: EventHandler<'a, MouseEvent>,
+    on_end_call: EventHandler<'a, MouseEvent>,
+}
+
+fn CallHeader<'a>(cx: Scope<'a, CallHeaderProps<'a>>) -> Element {
+    let ticks = use_state(cx, || 0u64);
+
+    // Re-render once a second so the elapsed time stays current
+    use_future(cx, (), |_| {
+        let ticks = ticks.clone();
+        async move {
+            loop {
+                sleep(Duration::from_secs(1)).await;
+                ticks.modify(|t| t + 1);
+            }
+        }
+    });
+
+    let session = &cx.props.session;
+    let elapsed = format_duration(session.elapsed());
+
+    cx.render(rsx! {
+        div { class: "call-header",
+            role: "region",
+            aria_label: tr("a11y.call_header"),
+            div { class: "call-header-info",
+                cx.props.security.clone().map(|security| rsx!(SecurityIndicator { security: security })),
+                span { class: "call-header-room", "{session.room_id}" }
+                span { class: "call-header-participants", {tr_args("call.participants", &[("count", &session.participant_count().to_string())])} }
+                span { class: "call-header-state", "{session.state}" }
+                span { class: "call-header-timer", role: "timer", "{elapsed}" }
+            }
+            div { class: "call-header-actions",
+                button {
+                    aria_pressed: "{cx.props.is_muted}",
+                    aria_keyshortcuts: "Control+M",
+                    onclick: move |evt| cx.props.on_toggle_mute.call(evt),
+                    {if cx.props.is_muted { tr("call.unmute") } else { tr("call.mute") }}
+                }
+                button {
+                    class: "end-call",
+                    aria_keyshortcuts: "Control+H",
+                    onclick: move |evt| cx.props.on_end_call.call(evt),
+                    {tr("call.end")}
+                }
+            }
+        }
+    })
+}
+
+const DIAL_PAD: [char; 12] = ['1', '2', '3', '4', '5', '6', '7', '8', '9', '*', '0', '#'];
+
+#[derive(Props)]
+struct DialPadProps<'a> {
+    on_tone: EventHandler<'a, char>,
+}
+
+fn DialPad<'a>(cx: Scope<'a, DialPadProps<'a>>) -> Element {
+    let dialed = use_state(cx, String::new);
+
+    let press = move |tone: char| {
+        dialed.modify(|dialed| format!("{}{}", dialed, tone));
+        cx.props.on_tone.call(tone);
+    };
+
+    // Typing digits while the pad has focus works like clicking them
+    let handle_key = move |evt: KeyboardEvent| {
+        if let Key::Character(c) = evt.key() {
+            if let Some(tone) = c.chars().next().filter(|tone| webrtc_client::dtmf::event_code(*tone).is_some()) {
+                press(tone);
+            }
+        }
+    };
+
+    cx.render(rsx! {
+        div { class: "dial-pad",
+            role: "group",
+            aria_label: tr("dtmf.title"),
+            onkeydown: handle_key,
+            output { class: "dial-pad-display", aria_live: "polite", "{dialed}" }
+            div { class: "dial-pad-keys",
+                DIAL_PAD.iter().map(|&tone| rsx! {
+                    button {
+                        key: "{tone}",
+                        onclick: move |_| press(tone),
+                        "{tone}"
+                    }
+                })
+            }
         }
     })
 }
 
 fn main() {
+    let args: Vec<String> = std::env::args().collect();
+
+    // Translator tooling: dump the source strings or list untranslated keys, then exit
+    if args.iter().any(|arg| arg == "--export-strings") {
+        println!("{}", i18n::export_template());
+        return;
+    }
+    if let Some(locale) = arg_value(&args, "--check-locale") {
+        for key in i18n::missing_keys(&locale) {
+            println!("{}", key);
+        }
+        return;
+    }
+
+    let settings = Settings::load();
+    let locale = arg_value(&args, "--locale").or(settings.locale);
+    i18n::init(locale.as_deref());
+
+    // Before anything can start a call; the audio and signaling paths use them from here on
+    let (host, errors) = plugins::load_all(&settings.plugins);
+    for e in errors {
+        eprintln!("Plugin not loaded: {}", e);
+    }
+    let _ = plugins::install(host);
+
+    // Hidden: hours of calls against a loopback peer, to catch leaks
+    if args.iter().any(|arg| arg == "--soak") {
+        std::process::exit(run_soak(&args, &settings.webrtc));
+    }
+
     dioxus_desktop::launch(App);
 }
 
+// Reports every minute and exits non-zero if tasks are left behind by finished calls.
+// --soak-hours sets the length (4 hours by default).
+fn run_soak(args: &[String], webrtc: &WebRTCConfig) -> i32 {
+    let mut config = SoakConfig::default();
+    if let Some(hours) = arg_value(args, "--soak-hours")
+        .and_then(|hours| hours.parse::<f64>().ok())
+        .filter(|hours| *hours > 0.0)
+    {
+        config.duration = Duration::from_secs_f64(hours * 3600.0);
+    }
+    let runtime = match tokio::runtime::Runtime::new() {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            eprintln!("Failed to start the soak runtime: {}", e);
+            return 1;
+        }
+    };
+    match runtime.block_on(soak::run(&config, webrtc, |report| println!("soak: {}", report.summary()))) {
+        Ok(report) => {
+            println!("soak finished: {}", report.summary());
+            if report.is_leaking() {
+                eprintln!("{} tasks outlived their calls", report.leaked_tasks());
+                1
+            } else {
+                0
+            }
+        }
+        Err(e) => {
+            eprintln!("Soak test failed: {}", e);
+            1
+        }
+    }
+}
+
+fn update_impairment(state: &UseRef<AppState>, update: impl FnOnce(&mut ImpairmentConfig)) {
+    let mut state = state.write();
+    update(&mut state.settings.webrtc.impairment);
+    state.save_settings();
+}
+
+fn audio_backend_label(kind: AudioBackendKind) -> &'static str {
+    match kind {
+        AudioBackendKind::Cpal => tr("audio.backend_system"),
+        AudioBackendKind::PipeWire => tr("audio.backend_pipewire"),
+        AudioBackendKind::Asio => tr("audio.backend_asio"),
+        AudioBackendKind::WasapiExclusive => tr("audio.backend_wasapi_exclusive"),
+        AudioBackendKind::Oboe => tr("audio.backend_oboe"),
+        AudioBackendKind::Mock => tr("audio.backend_test_tone"),
+    }
+}
+
+fn device_event_message(event: &DeviceEvent) -> String {
+    match event {
+        DeviceEvent::Switched { direction: StreamDirection::Capture, from, to } => {
+            tr_args("device.input_switched", &[("from", from), ("to", to)])
+        }
+        DeviceEvent::Switched { direction: StreamDirection::Playback, from, to } => {
+            tr_args("device.output_switched", &[("from", from), ("to", to)])
+        }
+        DeviceEvent::Lost { direction: StreamDirection::Capture, device } => {
+            tr_args("device.input_lost", &[("device", device)])
+        }
+        DeviceEvent::Lost { direction: StreamDirection::Playback, device } => {
+            tr_args("device.output_lost", &[("device", device)])
+        }
+    }
+}
+
+fn recording_consent_status(consent: &RecordingConsent) -> String {
+    let answered: Vec<(&str, Option<bool>)> = consent.answers().collect();
+    match consent.state() {
+        ConsentState::Pending => {
+            let agreed = answered.iter().filter(|(_, answer)| *answer == Some(true)).count();
+            tr_args("recording.pending", &[("agreed", &agreed.to_string()), ("total", &answered.len().to_string())])
+        }
+        ConsentState::Granted => tr("recording.granted").to_string(),
+        ConsentState::Declined => {
+            let declined: Vec<&str> = answered.iter().filter(|(_, answer)| *answer == Some(false)).map(|(peer, _)| *peer).collect();
+            tr_args("recording.declined", &[("peers", &declined.join(", "))])
+        }
+    }
+}
+
+fn recording_status(config: &RecordingConfig) -> String {
+    let directory = config.directory().display().to_string();
+    if config.voice_activated {
+        tr_args("recording.active_voice", &[("directory", &directory)])
+    } else {
+        tr_args("recording.active", &[("directory", &directory)])
+    }
+}
+
+fn update_settings(state: &UseRef<AppState>, update: impl FnOnce(&mut Settings)) {
+    let mut state = state.write();
+    update(&mut state.settings);
+    state.save_settings();
+}
+
+fn update_reconnect(state: &UseRef<AppState>, update: impl FnOnce(&mut ReconnectPolicy)) {
+    let mut state = state.write();
+    update(&mut state.settings.reconnect);
+    state.save_settings();
+}
+
+fn arg_value(args: &[String], flag: &str) -> Option<String> {
+    args.iter()
+        .position(|arg| arg == flag)
+        .and_then(|i| args.get(i + 1))
+        .cloned()
+}
+
+// The sound and toast for someone joining or leaving the room, as the settings ask
+fn follow_roster(state: &UseRef<AppState>, toasts: &UseRef<Vec<Toast>>, peer: &str, joined: bool) {
+    let cues = state.read().settings.roster_cues.clone();
+    if cues.sounds {
+        state.write().play_roster_cue(joined);
+    }
+    if cues.toasts {
+        let key = if joined { "roster.joined" } else { "roster.left" };
+        toasts.write().push(Toast::new(tr_args(key, &[("peer", peer)])));
+    }
+}
+
 fn App(cx: Scope) -> Element {
-    let state = use_ref(cx, || AppState {
-        signaling: None,
-        webrtc: None,
-        audio_capture: None,
-        peer_id: format!("user-{}", rand::random::<u32>()),
-        room_id: "test-room".to_string(),
-        reconnect_attempts: 0,
-    });
+    let startup_invite = use_state(cx, Invite::from_args);
+    // QA-only controls stay hidden unless launched with --dev
+    let developer_mode = use_state(cx, || std::env::args().any(|arg| arg == "--dev"));
+    let state = use_ref(cx, AppState::new);
+    let invite_link = use_state(cx, String::new);
+    let invite_input = use_state(cx, String::new);
+    let profile_name = use_state(cx, String::new);
+    // Enumerating devices is slow, so only once per run
+    let output_devices = use_state(cx, || state.read().settings.create_audio_backend().output_devices());
 
-    let connection_status = use_state(cx, || ConnectionStatus {
-        state: ConnectionState::Disconnected,
-        signaling_state: RTCSignalingState::Stable,
-        ice_state: RTCIceConnectionState::New,
-        peer_state: RTCPeerConnectionState::New,
-        last_error: None,
-    });
+    // The engine refreshes these with the call's stats; the window re-renders only when
+    // something it shows outside the metric panels changed
+    let diagnostics = use_ref(cx, CallDiagnostics::default);
     let available_peers = use_state(cx, || Vec::<String>::new());
     let selected_peers = use_state(cx, || HashSet::<String>::new());
+    // Who the current call would be handed to
+    let transfer_target = use_state(cx, String::new);
     let is_connected = use_state(cx, || false);
     let is_in_call = use_state(cx, || false);
     let is_muted = use_state(cx, || false);
     let error_message = use_state(cx, String::new);
-    let quality_status = use_state(cx, || ConnectionQuality::default());
+    let input_warning = use_state(cx, || None::<InputWarning>);
+    // Peers whose audio only gets through one way
+    let one_way_audio = use_state(cx, BTreeMap::<String, OneWayAudio>::new);
+    let speaking_muted = use_state(cx, || false);
+    let connection_failure = use_state(cx, || None::<FailureKind>);
+    let ice_restarting = use_state(cx, || false);
+    let toasts = use_ref(cx, Vec::<Toast>::new);
+    let captions = use_ref(cx, Vec::<Caption>::new);
+    let plugin_panels = use_state(cx, Vec::<PluginPanel>::new);
+    let contacts = use_ref(cx, ContactBook::load);
+    let window = dioxus_desktop::use_window(cx);
+    let panel_feeds = use_ref(cx, PanelFeeds::new);
+    // Call metrics don't go through the window's state; the panels showing them follow these
+    let metric_feeds = &*cx.use_hook(|| panel_feeds.read().metrics());
+    let new_contact_name = use_state(cx, String::new);
+    let new_contact_peer_id = use_state(cx, String::new);
+    let network_report = use_state(cx, || None::<NetworkTestReport>);
+    let network_testing = use_state(cx, || false);
+    let echo_test = use_ref(cx, || None::<EchoTest>);
+    let echo_starting = use_state(cx, || false);
+    let show_sdp = use_state(cx, || false);
+    let show_negotiation = use_state(cx, || false);
+    let manual_call = use_ref(cx, || None::<Arc<ManualCall>>);
+    let manual_local = use_state(cx, String::new);
+    let manual_remote = use_state(cx, String::new);
+    let manual_status = use_state(cx, String::new);
+    let manual_busy = use_state(cx, || false);
 
-    let connect = move |_| {
+    // Closing the main window or Ctrl-C/SIGTERM hangs up properly instead of leaving the
+    // remote side listening to silence until its ICE times out
+    {
+        let state = state.clone();
+        let contacts = contacts.clone();
+        let window = window.clone();
+        let main_window = window.id();
+        dioxus_desktop::use_wry_event_handler(cx, move |event, _| {
+            if let Event::WindowEvent { event: WindowEvent::CloseRequested, window_id, .. } = event {
+                if *window_id == main_window {
+                    // Gone from the screen while the hang-up goes out
+                    window.set_visible(false);
+                    shutdown_blocking(&state, &contacts);
+                }
+            }
+        });
+    }
+    // Nobody is looking at the call panels, so the engine stops updating them. Readings go
+    // on for the quality ladder, RED and the watchdogs.
+    {
+        let state = state.clone();
+        let window = window.clone();
+        let main_window = window.id();
+        dioxus_desktop::use_wry_event_handler(cx, move |event, _| {
+            let Event::WindowEvent { event, window_id, .. } = event else { return };
+            if *window_id != main_window || !matches!(event, WindowEvent::Resized(_) | WindowEvent::Focused(_)) {
+                return;
+            }
+            let hidden = window.is_minimized() || !window.is_visible();
+            if hidden != state.read().window_hidden {
+                state.write().window_hidden = hidden;
+                let state = state.read();
+                state.send(EngineCommand::PauseStats(state.stats_paused()));
+            }
+        });
+    }
+    use_future(cx, (), |_| {
         let state = state.clone();
-        let connection_status = connection_status.clone();
+        let contacts = contacts.clone();
+        async move {
+            shutdown::signal().await;
+            println!("Shutting down...");
+            shutdown(&state, &contacts).await;
+            std::process::exit(0);
+        }
+    });
+
+    // Inside the runtime: the OTLP exporter sends batches from a background task
+    use_future(cx, (), |_| {
+        let telemetry_config = state.read().settings.telemetry.clone();
+        let error_message = error_message.clone();
+        async move {
+            if let Err(e) = telemetry::init(&telemetry_config) {
+                error_message.set(e.to_string());
+            }
+        }
+    });
+
+    // Needs the runtime too, for the webhook client and MQTT event loop
+    use_future(cx, (), |_| {
+        let state = state.clone();
+        let error_message = error_message.clone();
+        async move {
+            let (config, client) = {
+                let state = state.read();
+                (state.settings.events.clone(), state.peer_id.clone())
+            };
+            match EventPublisher::start(&config, &client) {
+                Ok(publisher) => state.write().event_publisher = publisher,
+                Err(e) => error_message.set(e.to_string()),
+            }
+        }
+    });
+
+    // Plugins update their panels on their own; show the latest about once a second
+    use_future(cx, (), |_| {
+        let plugin_panels = plugin_panels.clone();
+        async move {
+            if plugins::host().is_empty() {
+                return;
+            }
+            let mut interval = tokio::time::interval(Duration::from_secs(1));
+            loop {
+                interval.tick().await;
+                let panels = plugins::host().panels();
+                if *plugin_panels.get() != panels {
+                    plugin_panels.set(panels);
+                }
+            }
+        }
+    });
+
+    // What the engine reports, onto the window. Toasts are swept here too.
+    use_future(cx, (), |_| {
+        let state = state.clone();
+        let available_peers = available_peers.clone();
         let is_connected = is_connected.clone();
-        
-        cx.spawn(async move {
-            connection_status.set("Connecting...".to_string());
-            
-            if let Ok(client) = SignalingClient::connect("ws://127.0.0.1:8080").await {
-                let client = Arc::new(Mutex::new(client));
-                
-                let join_msg = SignalingMessage::Join {
-                    room_id: state.read().room_id.clone(),
-                    peer_id: state.read().peer_id.clone(),
+        let is_in_call = is_in_call.clone();
+        let is_muted = is_muted.clone();
+        let error_message = error_message.clone();
+        let input_warning = input_warning.clone();
+        let one_way_audio = one_way_audio.clone();
+        let connection_failure = connection_failure.clone();
+        let ice_restarting = ice_restarting.clone();
+        let toasts = toasts.clone();
+        let captions = captions.clone();
+        let panel_feeds = panel_feeds.clone();
+        let mut events = state.read().engine.subscribe();
+        async move {
+            let mut sweep = tokio::time::interval(Duration::from_secs(1));
+            loop {
+                let event = tokio::select! {
+                    event = events.recv() => match event {
+                        Ok(event) => event,
+                        Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    },
+                    _ = sweep.tick() => {
+                        if toasts.read().iter().any(|toast| toast.shown_at.elapsed() >= TOAST_DURATION) {
+                            toasts.write().retain(|toast| toast.shown_at.elapsed() < TOAST_DURATION);
+                        }
+                        continue;
+                    }
                 };
-                
-                if let Ok(mut guard) = client.lock().await {
-                    if guard.send(join_msg).await.is_ok() {
-                        state.write().signaling = Some(client.clone());
-                        connection_status.set("Connected to server".to_string());
+                match event {
+                    EngineEvent::Connected => {
+                        // An invite may have switched rooms since startup
+                        state.write().load_history();
                         is_connected.set(true);
                     }
+                    EngineEvent::Disconnected => {
+                        is_connected.set(false);
+                        available_peers.set(Vec::new());
+                    }
+                    EngineEvent::PeerList(peers) => {
+                        state.write().peer_presence.retain_peers(&peers);
+                        available_peers.set(peers);
+                    }
+                    EngineEvent::PeerJoined(peer) => follow_roster(&state, &toasts, &peer, true),
+                    EngineEvent::PeerLeft(peer) => {
+                        {
+                            let mut state = state.write();
+                            state.peer_presence.remove(&peer);
+                            // Nobody left to answer
+                            if state.incoming_call.as_ref().map_or(false, |call| call.from_peer == peer) {
+                                state.incoming_call = None;
+                                state.alert = None;
+                            }
+                        }
+                        follow_roster(&state, &toasts, &peer, false);
+                    }
+                    EngineEvent::IncomingCall(call) => {
+                        let mut state = state.write();
+                        state.publish_event(CallEvent::IncomingCall {
+                            room: call.room_id.clone(),
+                            from_peer: call.from_peer.clone(),
+                        });
+                        // Left for the user to accept or decline
+                        state.incoming_call = Some(call);
+                        state.play_alert(tones::ringtone(), true);
+                    }
+                    EngineEvent::CallStarted(session) => {
+                        let mut state = state.write();
+                        let dialed = std::mem::take(&mut state.dialing);
+                        state.call_direction = if dialed {
+                            CallDirection::Outgoing
+                        } else {
+                            CallDirection::Incoming
+                        };
+                        if state.incoming_call.take().is_some() {
+                            state.alert = None;
+                        }
+                        state.call_session = Some(session);
+                        state.follow_call_cue();
+                        // Starts the call muted if the user wants that
+                        let join_muted = state.settings.join_muted;
+                        state.send(EngineCommand::SetMuted(join_muted));
+                        is_muted.set(join_muted);
+                        captions.write().clear();
+                        is_in_call.set(true);
+                    }
+                    EngineEvent::CallUpdated(session) | EngineEvent::CallRetrieved(session) => {
+                        let mut state = state.write();
+                        state.call_session = Some(session);
+                        state.follow_call_cue();
+                        is_in_call.set(true);
+                    }
+                    EngineEvent::CallActive => {
+                        let mut state = state.write();
+                        if let Some(ref mut session) = state.call_session {
+                            session.mark_active();
+                        }
+                        state.follow_call_cue();
+                    }
+                    EngineEvent::CallSetup(setup) => state.write().call_setup = setup,
+                    EngineEvent::RemoteAudioStarted => {
+                        let state = state.read();
+                        if let Some(ref session) = state.call_session {
+                            state.publish_event(CallEvent::Answered {
+                                room: session.room_id.clone(),
+                                participants: session.participants.clone(),
+                            });
+                        }
+                    }
+                    EngineEvent::SetupTime(setup) => print!("{}", setup.report()),
+                    // Their quality badge goes right away, not at the next reading
+                    EngineEvent::RemoteTrackEnded(peer_id) => {
+                        panel_feeds.read().update_peer_qualities(|qualities| qualities.remove(&peer_id));
+                        if one_way_audio.contains_key(&peer_id) {
+                            one_way_audio.with_mut(|directions| directions.remove(&peer_id));
+                        }
+                    }
+                    // The engine calls the target; that call is ours
+                    EngineEvent::Transferred { .. } => state.write().dialing = true,
+                    EngineEvent::CallEnded => {
+                        state.write().end_call();
+                        panel_feeds.read().clear_metrics();
+                        input_warning.set(None);
+                        one_way_audio.set(BTreeMap::new());
+                        ice_restarting.set(false);
+                        is_in_call.set(false);
+                    }
+                    EngineEvent::Failure(kind) => connection_failure.set(Some(kind)),
+                    EngineEvent::Recovered | EngineEvent::CallResumed => {
+                        connection_failure.set(None);
+                        ice_restarting.set(false);
+                    }
+                    EngineEvent::OneWayAudio { peer_id, direction: Some(direction) } => {
+                        one_way_audio.with_mut(|directions| directions.insert(peer_id, direction));
+                    }
+                    EngineEvent::OneWayAudio { peer_id, direction: None } => {
+                        one_way_audio.with_mut(|directions| directions.remove(&peer_id));
+                        ice_restarting.set(false);
+                    }
+                    EngineEvent::Caption(caption) => {
+                        let mut captions = captions.write();
+                        captions.push(caption);
+                        if captions.len() > CAPTION_LIMIT {
+                            captions.remove(0);
+                        }
+                    }
+                    EngineEvent::Chat(entry) => {
+                        let mut state = state.write();
+                        if entry.from_peer != state.peer_id {
+                            state.play_alert(tones::notification(), false);
+                        }
+                        state.add_chat(entry);
+                    }
+                    EngineEvent::Presence { peer_id, status } => state.write().peer_presence.update(peer_id, status),
+                    EngineEvent::LeftRoom(room_id) => {
+                        toasts.write().push(Toast::new(tr_args("idle.left_room", &[("room", &room_id)])));
+                    }
+                    EngineEvent::RecordingConsentRequested(request) => state.write().recording_request = Some(request),
+                    EngineEvent::RecordingConsent(consent) => state.write().recording_consent = Some(consent),
+                    EngineEvent::Error(e) => error_message.set(e),
+                    _ => {}
+                }
+            }
+        }
+    });
+
+    // Security, audio device and connection state of the call, and the metrics the
+    // panels follow
+    use_future(cx, (), |_| {
+        let diagnostics = diagnostics.clone();
+        let panel_feeds = panel_feeds.clone();
+        let mut updates = state.read().engine.diagnostics();
+        async move {
+            while updates.changed().await.is_ok() {
+                let current = updates.borrow().clone();
+                panel_feeds.read().publish_metrics(None, current.latency.as_ref(), Some(&current.history), Some(&current.setup));
+                let shown = {
+                    let previous = diagnostics.read();
+                    previous.status != current.status
+                        || previous.security != current.security
+                        || previous.bluetooth != current.bluetooth
+                        || previous.sdp != current.sdp
+                        || previous.pans != current.pans
+                };
+                *diagnostics.write_silent() = current;
+                if shown {
+                    diagnostics.needs_update();
                 }
-            } else {
-                connection_status.set("Connection failed".to_string());
             }
+        }
+    });
+
+    // Every peer of the call shows the readings of their own connection until their track
+    // ends; the global panel shows the worst peer
+    use_future(cx, (), |_| {
+        let state = state.clone();
+        let panel_feeds = panel_feeds.clone();
+        let mut readings = state.read().engine.peer_qualities();
+        let mut events = state.read().engine.subscribe();
+        async move {
+            let mut alarm = QualityAlarm::default();
+            let mut ended = HashSet::new();
+            loop {
+                tokio::select! {
+                    changed = readings.changed() => if changed.is_err() { break },
+                    event = events.recv() => {
+                        match event {
+                            Ok(EngineEvent::RemoteTrackEnded(peer_id)) => {
+                                ended.insert(peer_id);
+                            }
+                            Ok(EngineEvent::CallStarted(_)) => ended.clear(),
+                            Err(broadcast::error::RecvError::Closed) => break,
+                            _ => {}
+                        }
+                        continue;
+                    }
+                }
+                let current = readings.borrow().clone();
+                if current.is_empty() {
+                    continue;
+                }
+                let room_id = state.read().room_id.clone();
+                let mut overall = ConnectionQuality::default();
+                panel_feeds.read().update_peer_qualities(|qualities| {
+                    qualities.clear();
+                    for (peer_id, reading) in current.into_iter().filter(|(peer, _)| !ended.contains(peer)) {
+                        qualities.update(&peer_id, reading);
+                    }
+                    overall = qualities.overall();
+                });
+                if let Some(event) = alarm.check(&room_id, &overall) {
+                    state.read().publish_event(event);
+                }
+                panel_feeds.read().publish_metrics(Some(&overall), None, None, None);
+            }
+        }
+    });
+
+    // Shows the banner while the user talks into the muted mic, and warns of a silent or
+    // clipping one. Follows each call's microphone track.
+    use_future(cx, (), |_| {
+        let state = state.clone();
+        let speaking_muted = speaking_muted.clone();
+        let input_warning = input_warning.clone();
+        let mut local_track = state.read().engine.local_track();
+        async move {
+            let mut banner = MutedSpeechBanner::default();
+            let mut ticker = tokio::time::interval(Duration::from_millis(200));
+            loop {
+                let track = local_track.borrow_and_update().clone();
+                let Some(track) = track else {
+                    speaking_muted.set(false);
+                    if local_track.changed().await.is_err() {
+                        break;
+                    }
+                    continue;
+                };
+                let input_meter = track.input_meter();
+                loop {
+                    tokio::select! {
+                        changed = local_track.changed() => if changed.is_err() { return } else { break },
+                        _ = ticker.tick() => {}
+                    }
+                    let now = Instant::now();
+                    // Turning the reminder off applies to a running call too
+                    let muted = track.is_muted() && state.read().settings.muted_speech_reminder;
+                    let show = banner.check(muted, input_meter.speaking_for(now), now);
+                    if *speaking_muted.get() != show {
+                        speaking_muted.set(show);
+                    }
+                    let warning = input_meter.warning(now);
+                    if *input_warning.get() != warning {
+                        input_warning.set(warning);
+                    }
+                }
+            }
+        }
+    });
+
+    // Joins state.room_id, on the server in the settings
+    let do_connect = move || {
+        let state = state.read();
+        state.send(EngineCommand::Reconfigure(Box::new(state.engine_config())));
+        state.send(EngineCommand::Connect);
+    };
+
+    let connect = move |_| do_connect();
+
+    let test_network = move |_| {
+        let webrtc_config = state.read().settings.webrtc.clone();
+        let network_report = network_report.clone();
+        let network_testing = network_testing.clone();
+        let error_message = error_message.clone();
+
+        network_testing.set(true);
+        network_report.set(None);
+        cx.spawn(async move {
+            match nettest::run(&webrtc_config).await {
+                Ok(report) => network_report.set(Some(report)),
+                Err(e) => error_message.set(tr_args("nettest.failed", &[("error", &e.to_string())])),
+            }
+            network_testing.set(false);
         });
     };
 
-    let start_call = move |_| {
+    // Launched from an invite link: point at its server and join its room right away.
+    // Otherwise a bookmarked standing room may be joined at launch.
+    use_future(cx, (), |_| {
+        let bookmark = state.read().settings.auto_join_bookmark().cloned();
+        if let Some(invite) = startup_invite.get().clone() {
+            let mut state = state.write();
+            state.signaling_url = invite.server;
+            state.room_id = invite.room;
+            drop(state);
+            do_connect();
+        } else if let Some(bookmark) = bookmark {
+            let mut state = state.write();
+            state.signaling_url = bookmark.server;
+            state.room_id = bookmark.room_id;
+            drop(state);
+            do_connect();
+        }
+        async {}
+    });
+
+    let create_invite = move |_| {
+        let state = state.read();
+        let invite = Invite::new(state.signaling_url.clone(), state.room_id.clone());
+        invite_link.set(invite.to_link());
+    };
+
+    let bookmark_room = move |_| {
+        let mut state = state.write();
+        let (server, room_id) = (state.signaling_url.clone(), state.room_id.clone());
+        if let Err(e) = state.settings.bookmark_room(&server, &room_id) {
+            error_message.set(e.to_string());
+            return;
+        }
+        state.save_settings();
+    };
+
+    // Like an invite, a bookmark takes us back to the server it was made on
+    let join_bookmark = move |bookmark: RoomBookmark| {
+        let mut state = state.write();
+        state.signaling_url = bookmark.server;
+        state.room_id = bookmark.room_id;
+        drop(state);
+        do_connect();
+    };
+
+    let join_from_invite = move |_| {
+        match Invite::parse(invite_input.get()) {
+            Ok(invite) => {
+                let mut state = state.write();
+                state.signaling_url = invite.server;
+                state.room_id = invite.room;
+                drop(state);
+                invite_input.set(String::new());
+                do_connect();
+            }
+            Err(e) => error_message.set(e.to_string()),
+        }
+    };
+
+    // The engine reports the call once it's placed; callees it couldn't reach show as
+    // failed with the option to try them again
+    let do_start_call = move |peers: Vec<String>| {
+        if peers.is_empty() {
+            return;
+        }
+        let mut state = state.write();
+        state.dialing = true;
+        state.send(EngineCommand::Call(peers));
+    };
+
+    let retry_failed_peers = move |_| state.read().send(EngineCommand::RetryFailed);
+
+    // Ends the call for everyone; the engine's CallEnded clears the rest
+    let do_end_call = move || state.read().send(EngineCommand::HangUp);
+
+    let start_call = move |_| do_start_call(selected_peers.get().iter().cloned().collect());
+
+    let end_call = move |_| do_end_call();
+
+    // Blind: the engine hangs up as soon as they've been told
+    let transfer_call = move |_| {
+        let target = transfer_target.get().clone();
+        if target.is_empty() {
+            return;
+        }
+        state.read().send(EngineCommand::Transfer(target));
+        transfer_target.set(String::new());
+    };
+
+    // On our own track rather than the sender's, so joining muted holds before the
+    // sender is negotiated and the input meter still hears the mic
+    let do_toggle_mute = move || {
+        let muted = !is_muted.get();
+        state.read().send(EngineCommand::SetMuted(muted));
+        is_muted.set(muted);
+    };
+
+    let toggle_mute = move |_| do_toggle_mute();
+
+    // Offered when the call connected but no audio arrived; cleared once the engine
+    // reports the call recovered
+    let restart_ice = move |_| {
+        ice_restarting.set(true);
+        state.read().send(EngineCommand::RestartIce);
+    };
+
+    // The local control API drives the same actions as the buttons. This future outlives
+    // the render it started in, so it reads the live values, not that render's snapshot.
+    use_future(cx, (), |_| {
         let state = state.clone();
-        let selected = selected_peers.clone();
+        let is_connected = is_connected.clone();
         let is_in_call = is_in_call.clone();
-        
+        let is_muted = is_muted.clone();
+        let available_peers = available_peers.clone();
+        let error_message = error_message.clone();
+        async move {
+            let config = state.read().settings.api.clone();
+            if !config.enabled {
+                return;
+            }
+            let (server, mut requests) = match ApiServer::bind(&config) {
+                Ok(bound) => bound,
+                Err(e) => {
+                    error_message.set(e.to_string());
+                    return;
+                }
+            };
+            println!("Control API listening on http://{}", server.local_addr());
+            while let Some(request) = requests.recv().await {
+                let result = match request.command {
+                    ApiCommand::Status => Ok(()),
+                    ApiCommand::Join { room } => {
+                        if let Some(room) = room {
+                            state.write().room_id = room;
+                        }
+                        do_connect();
+                        Ok(())
+                    }
+                    ApiCommand::Call { .. } if *is_in_call.current() => Err("Already in a call".to_string()),
+                    ApiCommand::Call { .. } if !*is_connected.current() => Err("Not connected; POST /join first".to_string()),
+                    ApiCommand::Call { peers } => {
+                        do_start_call(peers);
+                        Ok(())
+                    }
+                    ApiCommand::HangUp => {
+                        do_end_call();
+                        Ok(())
+                    }
+                    ApiCommand::SetMuted(_) if !*is_in_call.current() => Err("Not in a call".to_string()),
+                    ApiCommand::SetMuted(muted) => {
+                        if muted != *is_muted.current() {
+                            do_toggle_mute();
+                        }
+                        Ok(())
+                    }
+                };
+                let status = {
+                    let state = state.read();
+                    ApiStatus {
+                        connected: *is_connected.current(),
+                        room: state.room_id.clone(),
+                        peer_id: state.peer_id.clone(),
+                        peers: available_peers.current().to_vec(),
+                        in_call: *is_in_call.current(),
+                        participants: state.call_session.as_ref().map(|session| session.participants.clone()).unwrap_or_default(),
+                        muted: *is_muted.current(),
+                    }
+                };
+                let _ = request.reply.send(result.map(|()| status));
+            }
+        }
+    });
+
+    let send_dtmf = move |tone: char| state.read().send(EngineCommand::SendDtmf(tone.to_string()));
+
+    let toggle_echo_test = move |_| {
+        if let Some(test) = echo_test.write().take() {
+            cx.spawn(test.stop());
+            return;
+        }
+
+        let settings = state.read().settings.clone();
+        let echo_test = echo_test.clone();
+        let echo_starting = echo_starting.clone();
+        let error_message = error_message.clone();
+        echo_starting.set(true);
+        cx.spawn(async move {
+            match EchoTest::start(&settings.webrtc, settings.create_audio_backend()).await {
+                Ok(test) => *echo_test.write() = Some(test),
+                Err(e) => error_message.set(tr_args("echo.failed", &[("error", &e.to_string())])),
+            }
+            echo_starting.set(false);
+        });
+    };
+    let echo_running = echo_test.read().is_some();
+
+    // Manual signaling: the offer and answer are copied between the machines by hand
+    let manual_offer = move |_| {
+        let settings = state.read().settings.clone();
+        let manual_call = manual_call.clone();
+        let manual_local = manual_local.clone();
+        let manual_status = manual_status.clone();
+        let manual_busy = manual_busy.clone();
+        let error_message = error_message.clone();
+        manual_busy.set(true);
         cx.spawn(async move {
-            let peers: Vec<String> = selected.get().iter().cloned().collect();
-            if !peers.is_empty() {
-                if let Ok(()) = start_call(state, peers).await {
-                    is_in_call.set(true);
+            match ManualCall::offer(&settings.webrtc, settings.create_audio_backend()).await {
+                Ok((call, offer)) => {
+                    *manual_call.write() = Some(Arc::new(call));
+                    manual_local.set(offer);
+                    manual_status.set(tr("manual.offer_ready"));
                 }
+                Err(e) => error_message.set(tr_args("manual.failed", &[("error", &e.to_string())])),
             }
+            manual_busy.set(false);
         });
     };
 
-    let end_call = move |_| {
-        let state = state.clone();
-        let is_in_call = is_in_call.clone();
-        
+    let manual_answer = move |_| {
+        let settings = state.read().settings.clone();
+        let offer = manual_remote.get().clone();
+        let manual_call = manual_call.clone();
+        let manual_local = manual_local.clone();
+        let manual_remote = manual_remote.clone();
+        let manual_status = manual_status.clone();
+        let manual_busy = manual_busy.clone();
+        let error_message = error_message.clone();
+        manual_busy.set(true);
         cx.spawn(async move {
-            if let Ok(mut state) = state.write() {
-                // Clean up WebRTC and audio
-                state.webrtc = None;
-                state.audio_capture = None;
-                
-                // Send end call signal if needed
-                if let Some(ref signaling) = state.signaling {
-                    let mut sig = signaling.lock().await;
-                    let _ = sig.send(SignalingMessage::EndCall {
-                        room_id: state.room_id.clone(),
-                        peer_id: state.peer_id.clone(),
-                    }).await;
+            match ManualCall::answer(&settings.webrtc, settings.create_audio_backend(), &offer).await {
+                Ok((call, answer)) => {
+                    *manual_call.write() = Some(Arc::new(call));
+                    manual_local.set(answer);
+                    manual_remote.set(String::new());
+                    manual_status.set(tr("manual.answer_ready"));
                 }
-                
-                is_in_call.set(false);
+                Err(e) => error_message.set(tr_args("manual.failed", &[("error", &e.to_string())])),
             }
+            manual_busy.set(false);
         });
     };
 
-    let toggle_mute = move |_| {
-        let state = state.clone();
-        let is_muted = is_muted.clone();
-        
+    let manual_apply_answer = move |_| {
+        let Some(call) = manual_call.read().clone() else { return };
+        let answer = manual_remote.get().clone();
+        let manual_remote = manual_remote.clone();
+        let manual_status = manual_status.clone();
+        let error_message = error_message.clone();
         cx.spawn(async move {
-            if let Ok(state) = state.read() {
-                if let Some(ref webrtc_client) = state.webrtc {
-                    let muted = !is_muted.get();
-                    // Toggle audio track enabled state
-                    if let Ok(senders) = webrtc_client.peer_connection.get_senders().await {
-                        if let Some(sender) = senders.first() {
-                            if let Some(track) = sender.track().await {
-                                track.set_enabled(!muted);
-                                is_muted.set(muted);
-                            }
-                        }
-                    }
+            match call.accept_answer(&answer).await {
+                Ok(()) => {
+                    manual_remote.set(String::new());
+                    manual_status.set(tr("manual.answer_applied"));
+                }
+                Err(e) => error_message.set(tr_args("manual.failed", &[("error", &e.to_string())])),
+            }
+        });
+    };
+
+    let manual_add_candidates = move |_| {
+        let Some(call) = manual_call.read().clone() else { return };
+        let candidates = manual_remote.get().clone();
+        let manual_remote = manual_remote.clone();
+        let manual_status = manual_status.clone();
+        let error_message = error_message.clone();
+        cx.spawn(async move {
+            match call.add_candidates(&candidates).await {
+                Ok(added) => {
+                    manual_remote.set(String::new());
+                    manual_status.set(tr_args("manual.candidates_added", &[("count", &added.to_string())]));
                 }
+                Err(e) => error_message.set(tr_args("manual.failed", &[("error", &e.to_string())])),
             }
         });
     };
 
+    let manual_hang_up = move |_| {
+        if let Some(call) = manual_call.write().take() {
+            cx.spawn(async move { call.hang_up().await });
+        }
+        manual_local.set(String::new());
+        manual_remote.set(String::new());
+        manual_status.set(String::new());
+    };
+    let manual_active = manual_call.read().is_some();
+    let manual_pasted = !manual_remote.get().trim().is_empty();
+
+    let respond_to_call = move |accepted: bool| {
+        let mut state = state.write();
+        state.alert = None;
+        let Some(call) = state.incoming_call.take() else { return };
+        if !accepted {
+            state.record_call(CallRecord {
+                room_id: call.room_id.clone(),
+                participants: vec![call.from_peer.clone()],
+                direction: CallDirection::Incoming,
+                answered: false,
+                started_at: SystemTime::now(),
+                duration: Duration::ZERO,
+            });
+        }
+        state.send(EngineCommand::Answer { call, accepted });
+    };
+
+    // Takes effect from the next call or echo test; a running stream keeps its backend
+    let select_audio_backend = move |evt: FormEvent| {
+        let Some(kind) = AudioBackendKind::available().into_iter().find(|kind| format!("{:?}", kind) == evt.value) else {
+            return;
+        };
+        let mut state = state.write();
+        state.settings.audio_backend = kind;
+        state.save_settings();
+    };
+
+    // Applies to streams started from now on
+    let select_output = move |evt: FormEvent, alerts: bool| {
+        let device = Some(evt.value.clone()).filter(|name| !name.is_empty());
+        let mut state = state.write();
+        let routing = &mut state.settings.output_routing;
+        if alerts {
+            routing.alerts = device;
+        } else {
+            routing.call = device;
+        }
+        state.save_settings();
+    };
+
+    let set_audio_buffer_frames = move |evt: FormEvent| {
+        let value = evt.value.trim();
+        let frames = match value.parse::<u32>() {
+            Ok(0) => return,
+            Ok(frames) => Some(frames),
+            Err(_) if value.is_empty() => None,
+            Err(_) => return,
+        };
+        let mut state = state.write();
+        state.settings.audio_buffer_frames = frames;
+        state.save_settings();
+    };
+
+    // Only offered while disconnected, so nothing running still uses the old server
+    let select_profile = move |evt: FormEvent| {
+        let mut state = state.write();
+        if let Err(e) = state.settings.switch_profile(&evt.value) {
+            error_message.set(e.to_string());
+            return;
+        }
+        state.signaling_url = state.settings.signaling_url.clone();
+        state.save_settings();
+    };
+
+    let save_profile = move |_| {
+        let mut state = state.write();
+        if let Err(e) = state.settings.save_profile(profile_name.get()) {
+            error_message.set(e.to_string());
+            return;
+        }
+        state.save_settings();
+        profile_name.set(String::new());
+    };
+
+    let delete_profile = move |_| {
+        let mut state = state.write();
+        let Some(active) = state.settings.active_profile.clone() else { return };
+        state.settings.delete_profile(&active);
+        state.save_settings();
+    };
+
+    // From the next call
+    let toggle_join_muted = move |_| {
+        let mut state = state.write();
+        state.settings.join_muted = !state.settings.join_muted;
+        state.save_settings();
+    };
+
+    let toggle_muted_speech_reminder = move |_| {
+        let mut state = state.write();
+        state.settings.muted_speech_reminder = !state.settings.muted_speech_reminder;
+        state.save_settings();
+    };
+
+    // Also applies to a running call right away
+    let set_playback_delay = move |evt: FormEvent| {
+        let Ok(delay_ms) = evt.value.parse::<u32>() else { return };
+        let mut state = state.write();
+        state.settings.playback_delay_ms = delay_ms.min(MAX_PLAYBACK_DELAY_MS);
+        state.save_settings();
+    };
+
+    // Also applies to a running call right away
+    let set_sidetone_level = move |evt: FormEvent| {
+        let Ok(percent) = evt.value.parse::<f32>() else { return };
+        let mut state = state.write();
+        state.settings.sidetone_level = (percent / 100.0).clamp(0.0, 1.0);
+        state.save_settings();
+    };
+
+    // Negotiated with the peer, so it takes effect from the next call
+    let toggle_music_mode = move |_| {
+        let mut state = state.write();
+        state.settings.webrtc.music_mode = !state.settings.webrtc.music_mode;
+        state.save_settings();
+    };
+
+    // Offered from the next call; sent only while the link is lossy
+    let toggle_redundant_audio = move |_| {
+        let mut state = state.write();
+        state.settings.webrtc.redundant_audio = !state.settings.webrtc.redundant_audio;
+        state.save_settings();
+    };
+
+    let toggle_normalize_loudness = move |_| {
+        let mut state = state.write();
+        state.settings.webrtc.normalize_loudness = !state.settings.webrtc.normalize_loudness;
+        state.save_settings();
+    };
+
+    let toggle_spatial_audio = move |_| {
+        let mut state = state.write();
+        state.settings.webrtc.spatial_audio = !state.settings.webrtc.spatial_audio;
+        state.save_settings();
+    };
+
+    // Takes effect from the next call
+    let toggle_captions = move |_| {
+        let mut state = state.write();
+        state.settings.transcription.enabled = !state.settings.transcription.enabled;
+        state.save_settings();
+    };
+
+    // These apply from the next recording
+    let toggle_voice_activated_recording = move |_| {
+        let mut state = state.write();
+        state.settings.recording.voice_activated = !state.settings.recording.voice_activated;
+        state.save_settings();
+    };
+
+    let toggle_separate_tracks = move |_| {
+        let mut state = state.write();
+        state.settings.recording.separate_tracks = !state.settings.recording.separate_tracks;
+        state.save_settings();
+    };
+
+    let toggle_roster_sounds = move |_| {
+        let mut state = state.write();
+        state.settings.roster_cues.sounds = !state.settings.roster_cues.sounds;
+        state.save_settings();
+    };
+
+    let toggle_roster_toasts = move |_| {
+        let mut state = state.write();
+        state.settings.roster_cues.toasts = !state.settings.roster_cues.toasts;
+        state.save_settings();
+    };
+
+    let toggle_call_tones = move |_| {
+        let mut state = state.write();
+        state.settings.call_tones.enabled = !state.settings.call_tones.enabled;
+        state.save_settings();
+    };
+
+    let select_ringback = move |evt: FormEvent| {
+        let style = match evt.value.as_str() {
+            "europe" => RingbackStyle::Europe,
+            "uk" => RingbackStyle::Uk,
+            _ => RingbackStyle::NorthAmerica,
+        };
+        let mut state = state.write();
+        state.settings.call_tones.ringback = style;
+        state.save_settings();
+    };
+
+    let toggle_keep_history = move |_| {
+        let mut state = state.write();
+        state.settings.keep_history = !state.settings.keep_history;
+        state.save_settings();
+    };
+
+    let purge_history = move |_| {
+        if let Err(e) = state.write().purge_history() {
+            error_message.set(tr_args("history.purge_failed", &[("error", &e.to_string())]));
+        }
+    };
+
+    let toggle_auto_answer = move |_| {
+        let mut state = state.write();
+        state.settings.auto_answer = !state.settings.auto_answer;
+        state.save_settings();
+    };
+
+    let select_recording_consent = move |evt: FormEvent| {
+        let policy = match evt.value.as_str() {
+            "majority" => ConsentPolicy::Majority,
+            _ => ConsentPolicy::Unanimous,
+        };
+        let mut state = state.write();
+        state.settings.recording_consent = policy;
+        state.save_settings();
+    };
+
+    let request_recording = move |_| {
+        let state = state.read();
+        state.send(EngineCommand::RequestRecording(state.settings.recording_consent));
+    };
+
+    let respond_to_recording = move |granted: bool| {
+        let mut state = state.write();
+        if let Some(request) = state.recording_request.take() {
+            state.send(EngineCommand::RespondRecording { request, granted });
+        }
+    };
+
+    let update_allowlist = move |evt: FormEvent| {
+        let mut state = state.write();
+        state.settings.auto_answer_allowlist = evt.value
+            .split(',')
+            .map(|id| id.trim().to_string())
+            .filter(|id| !id.is_empty())
+            .collect();
+        state.save_settings();
+    };
+
+    let toggle_impairment = move |_| {
+        update_impairment(state, |impairment| impairment.enabled = !impairment.enabled);
+    };
+    let update_latency = move |evt: FormEvent| {
+        let value = evt.value.trim().parse().unwrap_or(0);
+        update_impairment(state, |impairment| impairment.latency_ms = value);
+    };
+    let update_jitter = move |evt: FormEvent| {
+        let value = evt.value.trim().parse().unwrap_or(0);
+        update_impairment(state, |impairment| impairment.jitter_ms = value);
+    };
+    let update_loss = move |evt: FormEvent| {
+        let value: f64 = evt.value.trim().parse().unwrap_or(0.0);
+        update_impairment(state, |impairment| impairment.loss_percent = value.clamp(0.0, 100.0));
+    };
+
+    let toggle_resume_calls = move |_| {
+        update_reconnect(state, |policy| policy.resume_calls = !policy.resume_calls);
+    };
+    let update_away_after = move |evt: FormEvent| {
+        let mins = evt.value.trim().parse().unwrap_or(0);
+        update_settings(state, |settings| settings.idle.away_after_mins = mins);
+    };
+    let update_leave_empty_room = move |evt: FormEvent| {
+        let mins = evt.value.trim().parse().unwrap_or(0);
+        update_settings(state, |settings| settings.idle.leave_empty_room_after_mins = mins);
+    };
+
+    // From the next call; a running one keeps its interval
+    let update_stats_interval = move |evt: FormEvent| {
+        let Ok(interval_ms) = evt.value.trim().parse() else { return };
+        update_settings(state, |settings| settings.webrtc.stats.interval_ms = interval_ms);
+    };
+    let toggle_pause_stats = move |_| {
+        update_settings(state, |settings| settings.webrtc.stats.pause_when_hidden = !settings.webrtc.stats.pause_when_hidden);
+        let state = state.read();
+        state.send(EngineCommand::PauseStats(state.stats_paused()));
+    };
+
+    let update_max_attempts = move |evt: FormEvent| {
+        let value = evt.value.trim().parse().unwrap_or(0);
+        update_reconnect(state, |policy| policy.max_attempts = value);
+    };
+    let update_backoff = move |evt: FormEvent| {
+        let backoff = match evt.value.as_str() {
+            "Constant" => Backoff::Constant,
+            "Linear" => Backoff::Linear,
+            _ => Backoff::Exponential,
+        };
+        update_reconnect(state, |policy| policy.backoff = backoff);
+    };
+    let update_reconnect_deadline = move |evt: FormEvent| {
+        let seconds: u64 = evt.value.trim().parse().unwrap_or(0);
+        update_reconnect(state, |policy| policy.deadline_ms = seconds * 1000);
+    };
+
+    let call_contact = move |peer_id: String| do_start_call(vec![peer_id]);
+
+    let add_contact = move |_| {
+        let name = new_contact_name.get().trim().to_string();
+        let peer_id = new_contact_peer_id.get().trim().to_string();
+        if name.is_empty() || peer_id.is_empty() {
+            return;
+        }
+
+        let mut book = contacts.write();
+        book.add(name, peer_id);
+        if let Err(e) = book.save() {
+            eprintln!("Failed to save contacts: {}", e);
+        }
+        new_contact_name.set(String::new());
+        new_contact_peer_id.set(String::new());
+    };
+
+    let toggle_favorite = move |peer_id: String| {
+        let mut book = contacts.write();
+        book.toggle_favorite(&peer_id);
+        if let Err(e) = book.save() {
+            eprintln!("Failed to save contacts: {}", e);
+        }
+    };
+
+    let remove_contact = move |peer_id: String| {
+        let mut book = contacts.write();
+        book.remove(&peer_id);
+        if let Err(e) = book.save() {
+            eprintln!("Failed to save contacts: {}", e);
+        }
+    };
+
     let toggle_peer_selection = move |peer_id: String| {
         let selected = selected_peers.clone();
         let mut current = selected.get().clone();
@@ -257,85 +1753,413 @@ fn App(cx: Scope) -> Element {
         selected_peers.set(current);
     };
 
-    let handle_error = move |error: Error| {
-        let state = state.clone();
-        let error_message = error_message.clone();
-        
-        cx.spawn(async move {
-            let mut state = state.write();
-            match state.handle_connection_error(error).await {
-                Ok(_) => {
-                    error_message.set("".to_string());
+    // The engine sends it and reports it back as chat, like anyone else's
+    let send_chat = move |text: String| state.read().send(EngineCommand::SendChat(text));
+
+    // Forward chat typed into a popped-out window
+    use_future(cx, (), |_| {
+        let outgoing = panel_feeds.write().take_outgoing_chat();
+        async move {
+            if let Some(mut outgoing) = outgoing {
+                while let Some(text) = outgoing.recv().await {
+                    send_chat(text);
                 }
-                Err(e) => {
-                    error_message.set(e.to_string());
+            }
+        }
+    });
+
+    // Keep any popped-out windows in sync with this one
+    let (status, bluetooth_device, call_security, pans, sdp_log, negotiation, setup_time) = {
+        let diagnostics = diagnostics.read();
+        (
+            diagnostics.status.clone(),
+            diagnostics.bluetooth.clone(),
+            diagnostics.security.clone(),
+            diagnostics.pans.clone(),
+            diagnostics.sdp.clone(),
+            diagnostics.negotiation.clone(),
+            diagnostics.setup,
+        )
+    };
+    panel_feeds.read().publish(&status, &bluetooth_device, &state.read().chat_log);
+    let diagnostics_popped = panel_feeds.read().is_diagnostics_popped();
+    let chat_popped = panel_feeds.read().is_chat_popped();
+
+    // Ctrl+M toggles mute, Ctrl+H hangs up
+    let handle_shortcut = move |evt: KeyboardEvent| {
+        state.read().send(EngineCommand::Activity);
+        // What the muted-speech banner promises; only while it's up, so typing an M
+        // elsewhere doesn't unmute
+        if *speaking_muted.get() && evt.modifiers().is_empty() {
+            if let Key::Character(c) = evt.key() {
+                if c.eq_ignore_ascii_case("m") {
+                    do_toggle_mute();
+                    return;
                 }
             }
-        });
+        }
+        if !evt.modifiers().contains(Modifiers::CONTROL) || !*is_in_call.get() {
+            return;
+        }
+        match evt.key() {
+            Key::Character(c) if c.eq_ignore_ascii_case("m") => do_toggle_mute(),
+            Key::Character(c) if c.eq_ignore_ascii_case("h") => do_end_call(),
+            _ => {}
+        }
     };
 
-    // Set up connection status monitoring when WebRTC client is created
-    let monitor_connection = move |webrtc: Arc<WebRTCClient>| {
-        let status = connection_status.clone();
-        let mut receiver = webrtc.connection_monitor.subscribe();
-        
+    // The headset gives up its mic for full-quality playback; the stream moves on its own
+    let use_high_fidelity_bluetooth = move |_| {
+        let error_message = error_message.clone();
         cx.spawn(async move {
-            while receiver.changed().await.is_ok() {
-                let new_status = receiver.borrow().clone();
-                status.set(new_status);
+            let error = match tokio::task::spawn_blocking(|| bluetooth::switch_profile(BluetoothProfile::HighFidelity)).await {
+                Ok(result) => result.err().map(|e| e.to_string()),
+                Err(e) => Some(e.to_string()),
+            };
+            if let Some(error) = error {
+                error_message.set(tr_args("bluetooth.switch_failed", &[("error", &error)]));
             }
         });
     };
 
-    // Set up quality monitoring when WebRTC client is created
-    let monitor_quality = move |webrtc: Arc<WebRTCClient>| {
-        let quality = quality_status.clone();
-        let mut receiver = webrtc.quality_monitor.subscribe();
-        
-        cx.spawn(async move {
-            while receiver.changed().await.is_ok() {
-                let new_quality = receiver.borrow().clone();
-                quality.set(new_quality);
+    let toggle_high_contrast = move |_| {
+        let mut state = state.write();
+        state.settings.high_contrast = !state.settings.high_contrast;
+        state.save_settings();
+    };
+
+    let app_class = if state.read().settings.high_contrast { "app high-contrast" } else { "app" };
+    let allowlist = state.read().settings.auto_answer_allowlist.join(", ");
+    let recent_calls = state.read().recent_calls.clone();
+    let profiles = state.read().settings.profiles.keys().cloned().collect::<Vec<_>>();
+    let bookmarks = state.read().settings.bookmarks.clone();
+    let room_bookmarked = {
+        let state = state.read();
+        state.settings.is_bookmarked(&state.signaling_url, &state.room_id)
+    };
+    let active_profile = state.read().settings.active_profile.clone().unwrap_or_default();
+    let impairment = state.read().settings.webrtc.impairment;
+    let reconnect = state.read().settings.reconnect;
+    let reconnect_deadline_secs = reconnect.deadline_ms / 1000;
+    let no_sdp = tr("developer.sdp_none");
+    let local_sdp = sdp_log.local.as_deref().unwrap_or(no_sdp);
+    let remote_sdp = sdp_log.remote.as_deref().unwrap_or(no_sdp);
+
+    // Everything below the theme and the window-wide handlers
+    let content = rsx! {
+        h1 { {tr("app.title")} }
+
+        // Muting in the app doesn't touch capture, but silence is expected then
+        {input_warning.get().filter(|warning| *warning != InputWarning::Silent || !*is_muted.get()).map(|warning| rsx!(
+            div { class: "status status-error input-warning",
+                role: "alert",
+                {match warning {
+                    InputWarning::Silent => tr("input.silent"),
+                    InputWarning::Clipping => tr("input.clipping"),
+                }}
+            }
+        ))}
+
+        {bluetooth_device.as_ref().filter(|device| device.profile == BluetoothProfile::HandsFree).map(|device| rsx!(
+            div { class: "status status-warning bluetooth-warning",
+                role: "alert",
+                span { {tr_args("bluetooth.hands_free", &[("device", &device.name), ("rate", &(device.sample_rate / 1000).to_string())])} }
+                {bluetooth::can_switch_profile().then(|| rsx!(
+                    button {
+                        onclick: use_high_fidelity_bluetooth,
+                        {tr("bluetooth.switch")}
+                    }
+                ))}
+            }
+        ))}
+
+        {speaking_muted.get().then(|| rsx!(
+            div { class: "status muted-speech",
+                role: "status",
+                aria_live: "polite",
+                span { {tr("mute.speaking_while_muted")} }
+                button {
+                    aria_keyshortcuts: "M",
+                    onclick: toggle_mute,
+                    {tr("call.unmute")}
+                }
+            }
+        ))}
+
+        Toasts { toasts: toasts.read().clone() }
+
+        {connection_failure.get().map(|kind| rsx!(
+            div { class: "status status-warning connection-failure",
+                role: "alert",
+                {tr(kind.message_key())}
+                {(kind == FailureKind::NoAudio).then(|| rsx!(
+                    ul { class: "no-audio-hints",
+                        li { {tr("watchdog.no_audio_codec")} }
+                        li { {tr("watchdog.no_audio_firewall")} }
+                    }
+                    button {
+                        onclick: restart_ice,
+                        disabled: "{ice_restarting}",
+                        {tr("watchdog.restart_ice")}
+                    }
+                ))}
+            }
+        ))}
+
+        {one_way_audio.get().iter().map(|(peer, direction)| {
+            let name = contacts.read().name_for(peer).unwrap_or(peer).to_string();
+            rsx!(div { key: "{peer}", class: "status status-warning one-way-audio",
+                role: "alert",
+                {tr_args(direction.message_key(), &[("peer", &name)])}
+                ul { class: "no-audio-hints",
+                    {direction.hint_keys().iter().map(|key| rsx!(li { key: "{key}", {tr(key)} }))}
+                }
+                button {
+                    onclick: restart_ice,
+                    disabled: "{ice_restarting}",
+                    {tr("watchdog.restart_ice")}
+                }
+            })
+        })}
+
+        {status.device_event.as_ref().map(|event| rsx!(
+            div { class: "status status-warning device-notice",
+                role: "alert",
+                {device_event_message(event)}
+            }
+        ))}
+
+        {state.read().call_session.clone().map(|session| rsx!(
+            CallHeader {
+                session: session.clone(),
+                security: call_security.clone(),
+                is_muted: *is_muted.get(),
+                on_toggle_mute: toggle_mute,
+                on_end_call: end_call,
+            }
+            {(!state.read().call_setup.is_empty()).then(|| rsx!(
+                ul { class: "call-setup",
+                    aria_label: tr("call_setup.label"),
+                    aria_live: "polite",
+                    {state.read().call_setup.peers().iter().map(|(peer, setup)| {
+                        let name = contacts.read().name_for(peer).unwrap_or(peer).to_string();
+                        let (class, status) = peer_setup_status(setup);
+                        rsx!(li { key: "{peer}", class: "{class}",
+                            span { class: "call-setup-peer", "{name}" }
+                            span { class: "call-setup-status", "{status}" }
+                        })
+                    })}
+                }
+                {(!state.read().call_setup.failed().is_empty()).then(|| rsx!(
+                    button { class: "call-setup-retry",
+                        onclick: retry_failed_peers,
+                        {tr_args("call_setup.retry", &[("count", &state.read().call_setup.failed().len().to_string())])}
+                    }
+                ))}
+            ))}
+            {(session.participants.len() == 1).then(|| rsx!(
+                div { class: "transfer",
+                    label { r#for: "transferTarget", {tr("transfer.label")} }
+                    select {
+                        id: "transferTarget",
+                        value: "{transfer_target}",
+                        onchange: move |evt: FormEvent| transfer_target.set(evt.value.clone()),
+                        option { value: "", {tr("transfer.pick")} }
+                        available_peers.get().iter().filter(|peer| !session.participants.contains(peer)).map(|peer| {
+                            let name = contacts.read().name_for(peer).unwrap_or(peer).to_string();
+                            rsx!(option { key: "{peer}", value: "{peer}", "{name}" })
+                        })
+                    }
+                    button {
+                        disabled: "{transfer_target.get().is_empty()}",
+                        onclick: transfer_call,
+                        {tr("transfer.button")}
+                    }
+                }
+            ))}
+            div { class: "recording-consent",
+                aria_live: "polite",
+                {match state.read().recording_consent.as_ref() {
+                    Some(consent) => rsx!(
+                        span { {recording_consent_status(consent)} }
+                        {consent.is_granted().then(|| rsx!(
+                            span { class: "recording-active", {recording_status(&state.read().settings.recording)} }
+                        ))}
+                    ),
+                    None => rsx!(button {
+                        disabled: "{session.participants.is_empty()}",
+                        onclick: request_recording,
+                        {tr("recording.request")}
+                    }),
+                }}
             }
-        });
-    };
+        ))}
 
-    cx.render(rsx! {
-        style { include_str!("./style.css") }
-        h1 { "WebRTC Voice Chat" }
-        
+        {(*is_in_call.get() && !captions.read().is_empty()).then(|| {
+            let lines = captions.read().iter().map(|caption| caption_line(caption, &contacts.read())).collect();
+            rsx!(Captions { lines: lines })
+        })}
+
+        PluginPanels { panels: plugin_panels.get().clone() }
+
+        {state.read().recording_request.clone().map(|request| rsx!(
+            div { class: "incoming-call",
+                role: "alertdialog",
+                aria_live: "assertive",
+                span { {tr_args("recording.requested", &[("peer", &request.from_peer)])} }
+                button {
+                    onclick: move |_| respond_to_recording(true),
+                    {tr("recording.allow")}
+                }
+                button {
+                    class: "end-call",
+                    onclick: move |_| respond_to_recording(false),
+                    {tr("recording.deny")}
+                }
+            }
+        ))}
+    
         div { class: "control-panel",
-            h3 { "Connection Settings" }
+            h3 { {tr("connection.settings")} }
+            div { class: "profiles",
+                label { r#for: "profile", {tr("profile.label")} }
+                select {
+                    id: "profile",
+                    value: "{active_profile}",
+                    disabled: "{*is_connected.get() || profiles.is_empty()}",
+                    onchange: select_profile,
+                    {active_profile.is_empty().then(|| rsx!(
+                        option { value: "", {tr("profile.none")} }
+                    ))}
+                    profiles.iter().map(|name| rsx! {
+                        option { key: "{name}", value: "{name}", "{name}" }
+                    })
+                }
+                button {
+                    onclick: delete_profile,
+                    disabled: "{*is_connected.get() || active_profile.is_empty()}",
+                    {tr("profile.delete")}
+                }
+                input {
+                    placeholder: tr("profile.new_name"),
+                    aria_label: tr("profile.new_name"),
+                    value: "{profile_name}",
+                    oninput: move |evt| profile_name.set(evt.value.clone())
+                }
+                button {
+                    onclick: save_profile,
+                    disabled: "{profile_name.get().trim().is_empty()}",
+                    {tr("profile.save")}
+                }
+            }
             div {
-                label { r#for: "roomId", "Room ID:" }
+                label { r#for: "roomId", {tr("connection.room_id")} }
                 input {
                     id: "roomId",
                     value: "{state.read().room_id}",
                     disabled: "{*is_connected.get()}"
                 }
-                label { r#for: "peerId", "Peer ID:" }
+                button {
+                    onclick: bookmark_room,
+                    disabled: "{room_bookmarked}",
+                    {tr("bookmarks.add")}
+                }
+                label { r#for: "peerId", {tr("connection.peer_id")} }
                 input {
                     id: "peerId",
                     value: "{state.read().peer_id}",
                     disabled: "{*is_connected.get()}"
                 }
             }
+            {(!bookmarks.is_empty()).then(|| rsx!(
+                ul { class: "bookmarks", aria_label: tr("bookmarks.label"),
+                    bookmarks.iter().map(|bookmark| {
+                        let joined = bookmark.clone();
+                        let (server, room_id) = (bookmark.server.clone(), bookmark.room_id.clone());
+                        let (removed_server, removed_room) = (server.clone(), room_id.clone());
+                        let auto_join = bookmark.auto_join;
+                        rsx! {
+                            li { key: "{server} {room_id}",
+                                span { class: "bookmark-room", title: "{server}", "{room_id}" }
+                                button {
+                                    onclick: move |_| join_bookmark(joined.clone()),
+                                    disabled: "{*is_connected.get()}",
+                                    {tr("bookmarks.join")}
+                                }
+                                label {
+                                    input {
+                                        r#type: "checkbox",
+                                        checked: "{auto_join}",
+                                        onchange: move |_| update_settings(state, |settings| settings.set_auto_join(&server, &room_id, !auto_join))
+                                    }
+                                    {tr("bookmarks.auto_join")}
+                                }
+                                button {
+                                    onclick: move |_| update_settings(state, |settings| settings.remove_bookmark(&removed_server, &removed_room)),
+                                    {tr("bookmarks.remove")}
+                                }
+                            }
+                        }
+                    })
+                }
+            ))}
             button {
                 onclick: connect,
                 disabled: "{*is_connected.get()}",
-                "Connect to Server"
+                {tr("connection.connect")}
+            }
+            button {
+                onclick: test_network,
+                disabled: "{*network_testing.get()}",
+                {if *network_testing.get() { tr("nettest.running") } else { tr("nettest.run") }}
+            }
+            {network_report.get().clone().map(|report| rsx!(
+                NetworkTestResult { report: report }
+            ))}
+            div { class: "invite",
+                button {
+                    onclick: create_invite,
+                    {tr("invite.create")}
+                }
+                {(!invite_link.get().is_empty()).then(|| rsx!(
+                    input {
+                        class: "invite-link",
+                        readonly: "true",
+                        aria_label: tr("invite.link"),
+                        value: "{invite_link}"
+                    }
+                ))}
+            }
+            div { class: "invite",
+                input {
+                    class: "invite-link",
+                    placeholder: tr("invite.paste"),
+                    aria_label: tr("invite.paste"),
+                    value: "{invite_input}",
+                    disabled: "{*is_connected.get()}",
+                    oninput: move |evt| invite_input.set(evt.value.clone())
+                }
+                button {
+                    onclick: join_from_invite,
+                    disabled: "{*is_connected.get() || invite_input.get().is_empty()}",
+                    {tr("invite.join")}
+                }
             }
         }
 
         div { class: "control-panel",
-            h3 { "Available Peers" }
+            h3 { {tr("peers.title")} }
             div { class: "peer-list",
+                role: "group",
+                aria_label: tr("peers.title"),
                 available_peers.get().iter().map(|peer_id| {
                     rsx! {
                         PeerItem {
                             key: "{peer_id}",
                             peer_id: peer_id.clone(),
                             selected: selected_peers.get().contains(peer_id),
+                            qualities: &metric_feeds.peer_qualities,
+                            presence: state.read().peer_presence.get(peer_id),
                             on_select: toggle_peer_selection
                         }
                     }
@@ -344,193 +2168,704 @@ fn App(cx: Scope) -> Element {
             button {
                 onclick: start_call,
                 disabled: "{!*is_connected.get() || *is_in_call.get() || selected_peers.get().is_empty()}",
-                "Call Selected Peers"
+                {tr("peers.call_selected")}
             }
             button {
                 onclick: end_call,
                 disabled: "{!*is_in_call.get()}",
-                "End Call"
+                {tr("call.end")}
+            }
+        }
+
+        {state.read().incoming_call.clone().map(|call| rsx!(
+            div { class: "incoming-call",
+                role: "alertdialog",
+                aria_live: "assertive",
+                span { {tr_args("call.incoming", &[("peer", &call.from_peer), ("room", &call.room_id)])} }
+                button {
+                    onclick: move |_| respond_to_call(true),
+                    {tr("call.accept")}
+                }
+                button {
+                    class: "end-call",
+                    onclick: move |_| respond_to_call(false),
+                    {tr("call.decline")}
+                }
+            }
+        ))}
+
+        div { class: "control-panel",
+            h3 { {tr("contacts.title")} }
+            div { class: "contact-list",
+                contacts.read().sorted().into_iter().map(|contact| {
+                    rsx! {
+                        ContactItem {
+                            key: "{contact.peer_id}",
+                            presence: state.read().peer_presence.get(&contact.peer_id),
+                            contact: contact,
+                            can_call: *is_connected.get() && !*is_in_call.get(),
+                            on_call: call_contact,
+                            on_toggle_favorite: toggle_favorite,
+                            on_remove: remove_contact
+                        }
+                    }
+                })
+            }
+            div {
+                input {
+                    placeholder: tr("contacts.name"),
+                    aria_label: tr("contacts.name"),
+                    value: "{new_contact_name}",
+                    oninput: move |evt| new_contact_name.set(evt.value.clone())
+                }
+                input {
+                    placeholder: tr("contacts.peer_id"),
+                    aria_label: tr("contacts.peer_id"),
+                    value: "{new_contact_peer_id}",
+                    oninput: move |evt| new_contact_peer_id.set(evt.value.clone())
+                }
+                button {
+                    onclick: add_contact,
+                    {tr("contacts.add")}
+                }
             }
         }
 
         div { class: "control-panel",
-            h3 { "Audio Controls" }
+            h3 { {tr("history.title")} }
+            ul { class: "call-history",
+                aria_label: tr("history.recent_calls"),
+                recent_calls.iter().enumerate().map(|(index, call)| {
+                    let direction = match (call.direction, call.answered) {
+                        (CallDirection::Incoming, false) => tr("history.missed"),
+                        (CallDirection::Incoming, true) => tr("history.incoming"),
+                        (CallDirection::Outgoing, _) => tr("history.outgoing"),
+                    };
+                    let peers = call
+                        .participants
+                        .iter()
+                        .map(|peer_id| contacts.read().name_for(peer_id).unwrap_or(peer_id).to_string())
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    let missed = if call.answered { "" } else { "missed" };
+                    rsx! {
+                        li { key: "{index}", class: "call-history-item {missed}",
+                            span { class: "call-history-direction", "{direction}" }
+                            span { class: "call-history-peers", "{peers}" }
+                            span { class: "call-history-duration", {format_duration(call.duration)} }
+                        }
+                    }
+                })
+            }
+            div {
+                input {
+                    id: "keepHistory",
+                    r#type: "checkbox",
+                    checked: "{state.read().settings.keep_history}",
+                    onclick: toggle_keep_history
+                }
+                label { r#for: "keepHistory", {tr("history.keep")} }
+            }
             button {
-                onclick: toggle_mute,
-                disabled: "{!*is_in_call.get()}",
-                "{if *is_muted.get() { "Unmute" } else { "Mute" }}"
+                onclick: purge_history,
+                {tr("history.purge")}
             }
         }
 
-        div { class: "connection-status",
-            div { class: "status-item",
-                "Connection: ",
-                span { 
-                    class: "status-value {connection_status.get().state}",
-                    "{connection_status.get().state}"
+        div { class: "control-panel",
+            h3 { {tr("call_handling.title")} }
+            div {
+                input {
+                    id: "autoAnswer",
+                    r#type: "checkbox",
+                    checked: "{state.read().settings.auto_answer}",
+                    onclick: toggle_auto_answer
                 }
+                label { r#for: "autoAnswer", {tr("call_handling.auto_answer")} }
             }
-            div { class: "status-item",
-                "ICE: ",
-                span { 
-                    class: "status-value",
-                    "{connection_status.get().ice_state}"
+            div {
+                input {
+                    id: "highContrast",
+                    r#type: "checkbox",
+                    checked: "{state.read().settings.high_contrast}",
+                    onclick: toggle_high_contrast
                 }
+                label { r#for: "highContrast", {tr("a11y.high_contrast")} }
             }
-            div { class: "status-item",
-                "Signaling: ",
-                span { 
-                    class: "status-value",
-                    "{connection_status.get().signaling_state}"
+            div {
+                label { r#for: "recordingConsent", {tr("recording.policy")} }
+                select {
+                    id: "recordingConsent",
+                    value: "{state.read().settings.recording_consent.as_str()}",
+                    onchange: select_recording_consent,
+                    option { value: "unanimous", {tr("recording.policy_unanimous")} }
+                    option { value: "majority", {tr("recording.policy_majority")} }
                 }
             }
-            {connection_status.get().last_error.as_ref().map(|error| rsx!(
-                div { class: "status-error",
-                    "Error: {error}"
+            div {
+                input {
+                    id: "voiceActivatedRecording",
+                    r#type: "checkbox",
+                    checked: "{state.read().settings.recording.voice_activated}",
+                    onclick: toggle_voice_activated_recording
                 }
-            ))}
-        }
-
-        {!error_message.get().is_empty().then(|| rsx!(
+                label { r#for: "voiceActivatedRecording", {tr("recording.voice_activated")} }
+                span { class: "hint", {tr("recording.voice_activated_hint")} }
+            }
             div {
-                class: "error-message",
-                "{error_message.get()}"
+                input {
+                    id: "separateTracks",
+                    r#type: "checkbox",
+                    checked: "{state.read().settings.recording.separate_tracks}",
+                    onclick: toggle_separate_tracks
+                }
+                label { r#for: "separateTracks", {tr("recording.separate_tracks")} }
+                span { class: "hint", {tr("recording.separate_tracks_hint")} }
             }
-        ))}
-
-        div { class: "quality-metrics",
-            h3 { "Connection Quality" }
-            div { class: "quality-item",
-                "Quality Score: ",
-                span { 
-                    class: "quality-value {get_quality_class(quality_status.get().quality_score)}",
-                    "{quality_status.get().quality_score}%"
+            div {
+                input {
+                    id: "rosterSounds",
+                    r#type: "checkbox",
+                    checked: "{state.read().settings.roster_cues.sounds}",
+                    onclick: toggle_roster_sounds
+                }
+                label { r#for: "rosterSounds", {tr("roster.sounds")} }
+            }
+            div {
+                input {
+                    id: "rosterToasts",
+                    r#type: "checkbox",
+                    checked: "{state.read().settings.roster_cues.toasts}",
+                    onclick: toggle_roster_toasts
+                }
+                label { r#for: "rosterToasts", {tr("roster.toasts")} }
+            }
+            div {
+                label { r#for: "awayAfter", {tr("idle.away_after")} }
+                input {
+                    id: "awayAfter",
+                    r#type: "number",
+                    min: "0",
+                    value: "{state.read().settings.idle.away_after_mins}",
+                    onchange: update_away_after
                 }
             }
-            div { class: "quality-item",
-                "Round Trip Time: ",
-                span { class: "quality-value",
-                    "{quality_status.get().round_trip_time:.1} ms"
+            div {
+                label { r#for: "leaveEmptyRoom", {tr("idle.leave_empty_room")} }
+                input {
+                    id: "leaveEmptyRoom",
+                    r#type: "number",
+                    min: "0",
+                    value: "{state.read().settings.idle.leave_empty_room_after_mins}",
+                    onchange: update_leave_empty_room
                 }
+                span { class: "hint", {tr("idle.leave_empty_room_hint")} }
             }
-            div { class: "quality-item",
-                "Packet Loss: ",
-                span { class: "quality-value",
-                    "{quality_status.get().packet_loss_rate:.1}%"
+            div {
+                input {
+                    id: "callTones",
+                    r#type: "checkbox",
+                    checked: "{state.read().settings.call_tones.enabled}",
+                    onclick: toggle_call_tones
                 }
+                label { r#for: "callTones", {tr("call_tones.enabled")} }
             }
-            div { class: "quality-item",
-                "Bitrate: ",
-                span { class: "quality-value",
-                    "{quality_status.get().bitrate:.1} kbps"
+            div {
+                label { r#for: "ringback", {tr("call_tones.ringback")} }
+                select {
+                    id: "ringback",
+                    value: "{state.read().settings.call_tones.ringback.as_str()}",
+                    disabled: "{!state.read().settings.call_tones.enabled}",
+                    onchange: select_ringback,
+                    option { value: "north_america", {tr("call_tones.north_america")} }
+                    option { value: "europe", {tr("call_tones.europe")} }
+                    option { value: "uk", {tr("call_tones.uk")} }
                 }
             }
-            div { class: "quality-item",
-                "Audio Level: ",
-                span { class: "quality-value",
-                    "{quality_status.get().audio_level} dB"
+            div {
+                label { r#for: "autoAnswerAllowlist", {tr("call_handling.allowlist")} }
+                input {
+                    id: "autoAnswerAllowlist",
+                    value: "{allowlist}",
+                    disabled: "{!state.read().settings.auto_answer}",
+                    onchange: update_allowlist
                 }
             }
         }
-    })
-}
-
-fn get_quality_class(score: u8) -> &'static str {
-    match score {
-        90..=100 => "quality-excellent",
-        70..=89 => "quality-good",
-        50..=69 => "quality-fair",
-        _ => "quality-poor"
-    }
-}
 
-async fn handle_signaling_message(
-    msg: SignalingMessage,
-    state: Arc<Mutex<AppState>>,
-) -> Result<()> {
-    let mut state = state.lock().await;
-    
-    match msg {
-        SignalingMessage::Error { message } => {
-            Err(Error::Signaling(message))
-        }
-        SignalingMessage::ConnectionLost { peer_id } => {
-            println!("Peer {} disconnected", peer_id);
-            if state.webrtc.is_some() {
-                state.cleanup_call().await;
+        div { class: "control-panel",
+            h3 { {tr("reconnect.title")} }
+            div {
+                input {
+                    id: "resumeCalls",
+                    r#type: "checkbox",
+                    checked: "{reconnect.resume_calls}",
+                    onclick: toggle_resume_calls
+                }
+                label { r#for: "resumeCalls", {tr("reconnect.resume_calls")} }
+            }
+            div {
+                label { r#for: "reconnectAttempts", {tr("reconnect.max_attempts")} }
+                input {
+                    id: "reconnectAttempts",
+                    r#type: "number",
+                    min: "0",
+                    value: "{reconnect.max_attempts}",
+                    onchange: update_max_attempts
+                }
+            }
+            div {
+                label { r#for: "reconnectBackoff", {tr("reconnect.backoff")} }
+                select {
+                    id: "reconnectBackoff",
+                    value: "{reconnect.backoff:?}",
+                    onchange: update_backoff,
+                    option { value: "Constant", {tr("reconnect.backoff_constant")} }
+                    option { value: "Linear", {tr("reconnect.backoff_linear")} }
+                    option { value: "Exponential", {tr("reconnect.backoff_exponential")} }
+                }
+            }
+            div {
+                label { r#for: "reconnectDeadline", {tr("reconnect.deadline")} }
+                input {
+                    id: "reconnectDeadline",
+                    r#type: "number",
+                    min: "0",
+                    value: "{reconnect_deadline_secs}",
+                    onchange: update_reconnect_deadline
+                }
             }
-            Ok(())
         }
-        SignalingMessage::CallRequest { from_peer, room_id, .. } => {
-            // Create WebRTC client if it doesn't exist
-            if state.webrtc.is_none() {
-                state.webrtc = Some(Arc::new(WebRTCClient::new().await?));
+
+        if *developer_mode.get() {
+            rsx! {
+                div { class: "control-panel developer-panel",
+                    h3 { {tr("developer.title")} }
+                    div {
+                        input {
+                            id: "impairment",
+                            r#type: "checkbox",
+                            checked: "{impairment.enabled}",
+                            onclick: toggle_impairment
+                        }
+                        label { r#for: "impairment", {tr("developer.impairment")} }
+                    }
+                    div {
+                        label { r#for: "impairmentLatency", {tr("developer.latency")} }
+                        input {
+                            id: "impairmentLatency",
+                            r#type: "number",
+                            min: "0",
+                            value: "{impairment.latency_ms}",
+                            disabled: "{!impairment.enabled}",
+                            onchange: update_latency
+                        }
+                    }
+                    div {
+                        label { r#for: "impairmentJitter", {tr("developer.jitter")} }
+                        input {
+                            id: "impairmentJitter",
+                            r#type: "number",
+                            min: "0",
+                            value: "{impairment.jitter_ms}",
+                            disabled: "{!impairment.enabled}",
+                            onchange: update_jitter
+                        }
+                    }
+                    div {
+                        label { r#for: "impairmentLoss", {tr("developer.loss")} }
+                        input {
+                            id: "impairmentLoss",
+                            r#type: "number",
+                            min: "0",
+                            max: "100",
+                            step: "0.5",
+                            value: "{impairment.loss_percent}",
+                            disabled: "{!impairment.enabled}",
+                            onchange: update_loss
+                        }
+                    }
+                    p { class: "hint", {tr("developer.applies_next_call")} }
+                    div {
+                        input {
+                            id: "showSdp",
+                            r#type: "checkbox",
+                            checked: "{show_sdp}",
+                            onclick: move |_| show_sdp.set(!*show_sdp.get())
+                        }
+                        label { r#for: "showSdp", {tr("developer.show_sdp")} }
+                    }
+                    {show_sdp.get().then(|| rsx!(
+                        h4 { {tr("developer.local_sdp")} }
+                        pre { class: "sdp-dump", "{local_sdp}" }
+                        h4 { {tr("developer.remote_sdp")} }
+                        pre { class: "sdp-dump", "{remote_sdp}" }
+                    ))}
+                }
             }
+        }
 
-            // Send call response
-            if let Some(ref signaling) = state.signaling {
-                signaling.lock().await.send(SignalingMessage::CallResponse {
-                    room_id,
-                    from_peer: state.peer_id.clone(),
-                    to_peer: from_peer,
-                    accepted: true,
-                }).await?;
+        div { class: "control-panel",
+            h3 { {tr("manual.title")} }
+            p { class: "hint", {tr("manual.hint")} }
+            div { class: "manual-actions",
+                button {
+                    onclick: manual_offer,
+                    disabled: "{manual_active || *manual_busy.get() || *is_in_call.get()}",
+                    {tr("manual.create_offer")}
+                }
+                button {
+                    onclick: manual_answer,
+                    disabled: "{manual_active || *manual_busy.get() || *is_in_call.get() || !manual_pasted}",
+                    {tr("manual.answer_offer")}
+                }
+                button {
+                    onclick: manual_apply_answer,
+                    disabled: "{!manual_active || !manual_pasted}",
+                    {tr("manual.apply_answer")}
+                }
+                button {
+                    onclick: manual_add_candidates,
+                    disabled: "{!manual_active || !manual_pasted}",
+                    {tr("manual.add_candidates")}
+                }
+                button {
+                    onclick: manual_hang_up,
+                    disabled: "{!manual_active}",
+                    {tr("manual.hang_up")}
+                }
+            }
+            {(!manual_local.get().is_empty()).then(|| rsx!(
+                label { r#for: "manualLocal", {tr("manual.local")} }
+                textarea {
+                    id: "manualLocal",
+                    class: "manual-sdp",
+                    readonly: "true",
+                    value: "{manual_local}"
+                }
+            ))}
+            label { r#for: "manualRemote", {tr("manual.remote")} }
+            textarea {
+                id: "manualRemote",
+                class: "manual-sdp",
+                placeholder: tr("manual.remote_placeholder"),
+                value: "{manual_remote}",
+                oninput: move |evt| manual_remote.set(evt.value.clone())
             }
+            {(!manual_status.get().is_empty()).then(|| rsx!(
+                p { class: "hint", role: "status", "{manual_status}" }
+            ))}
         }
-        SignalingMessage::Offer { sdp, from_peer, room_id, .. } => {
-            if let Some(ref webrtc) = state.webrtc {
-                let answer = webrtc.handle_offer(sdp).await?;
-                
-                if let Some(ref signaling) = state.signaling {
-                    signaling.lock().await.send(SignalingMessage::Answer {
-                        room_id,
-                        sdp: answer,
-                        from_peer: state.peer_id.clone(),
-                        to_peer: from_peer,
-                    }).await?;
+
+        div { class: "control-panel",
+            h3 { {tr("audio.title")} }
+            div {
+                label { r#for: "audioBackend", {tr("audio.backend")} }
+                select {
+                    id: "audioBackend",
+                    value: "{state.read().settings.audio_backend:?}",
+                    onchange: select_audio_backend,
+                    AudioBackendKind::available().into_iter().map(|kind| rsx! {
+                        option { key: "{kind:?}", value: "{kind:?}", {audio_backend_label(kind)} }
+                    })
+                }
+            }
+            {[("callOutput", false), ("alertOutput", true)].into_iter().map(|(id, alerts)| {
+                let routing = state.read().settings.output_routing.clone();
+                let selected = if alerts { routing.alerts.clone() } else { routing.call.clone() }.unwrap_or_default();
+                let label = if alerts { tr("audio.alert_output") } else { tr("audio.call_output") };
+                rsx! {
+                    div { key: "{id}",
+                        label { r#for: "{id}", "{label}" }
+                        select {
+                            id: "{id}",
+                            value: "{selected}",
+                            onchange: move |evt| select_output(evt, alerts),
+                            option { value: "", {tr("audio.default_output")} }
+                            output_devices.get().iter().map(|name| rsx! {
+                                option { key: "{name}", value: "{name}", "{name}" }
+                            })
+                        }
+                    }
+                }
+            })}
+            div {
+                label { r#for: "audioBufferFrames", {tr("audio.buffer_frames")} }
+                input {
+                    id: "audioBufferFrames",
+                    r#type: "number",
+                    min: "16",
+                    step: "16",
+                    value: "{state.read().settings.audio_buffer_frames.map(|frames| frames.to_string()).unwrap_or_default()}",
+                    onchange: set_audio_buffer_frames,
+                }
+            }
+            div {
+                label { r#for: "sidetoneLevel", {tr("audio.sidetone")} }
+                input {
+                    id: "sidetoneLevel",
+                    r#type: "range",
+                    min: "0",
+                    max: "100",
+                    value: "{(state.read().settings.sidetone_level * 100.0).round()}",
+                    oninput: set_sidetone_level,
+                }
+                span { class: "hint", {tr("audio.sidetone_hint")} }
+            }
+            div {
+                label { r#for: "playbackDelay", {tr("audio.playback_delay")} }
+                input {
+                    id: "playbackDelay",
+                    r#type: "range",
+                    min: "0",
+                    max: "{MAX_PLAYBACK_DELAY_MS}",
+                    step: "10",
+                    value: "{state.read().settings.playback_delay_ms}",
+                    oninput: set_playback_delay,
+                }
+                span { {tr_args("audio.playback_delay_value", &[("ms", &state.read().settings.playback_delay_ms.to_string())])} }
+                span { class: "hint", {tr("audio.playback_delay_hint")} }
+            }
+            div {
+                input {
+                    id: "joinMuted",
+                    r#type: "checkbox",
+                    checked: "{state.read().settings.join_muted}",
+                    onclick: toggle_join_muted
+                }
+                label { r#for: "joinMuted", {tr("mute.join_muted")} }
+            }
+            div {
+                input {
+                    id: "mutedSpeechReminder",
+                    r#type: "checkbox",
+                    checked: "{state.read().settings.muted_speech_reminder}",
+                    onclick: toggle_muted_speech_reminder
+                }
+                label { r#for: "mutedSpeechReminder", {tr("mute.speech_reminder")} }
+            }
+            div {
+                input {
+                    id: "musicMode",
+                    r#type: "checkbox",
+                    checked: "{state.read().settings.webrtc.music_mode}",
+                    onclick: toggle_music_mode
+                }
+                label { r#for: "musicMode", {tr("audio.music_mode")} }
+                span { class: "hint", {tr("audio.music_mode_hint")} }
+            }
+            div {
+                input {
+                    id: "redundantAudio",
+                    r#type: "checkbox",
+                    checked: "{state.read().settings.webrtc.redundant_audio}",
+                    onclick: toggle_redundant_audio
+                }
+                label { r#for: "redundantAudio", {tr("audio.redundant_audio")} }
+                span { class: "hint", {tr("audio.redundant_audio_hint")} }
+            }
+            div {
+                input {
+                    id: "normalizeLoudness",
+                    r#type: "checkbox",
+                    checked: "{state.read().settings.webrtc.normalize_loudness}",
+                    onclick: toggle_normalize_loudness
+                }
+                label { r#for: "normalizeLoudness", {tr("audio.normalize_loudness")} }
+                span { class: "hint", {tr("audio.normalize_loudness_hint")} }
+            }
+            div {
+                label { r#for: "statsInterval", {tr("stats.interval")} }
+                input {
+                    id: "statsInterval",
+                    r#type: "number",
+                    min: "250",
+                    step: "250",
+                    value: "{state.read().settings.webrtc.stats.interval_ms}",
+                    onchange: update_stats_interval
+                }
+                span { class: "hint", {tr("stats.interval_hint")} }
+            }
+            div {
+                input {
+                    id: "pauseStats",
+                    r#type: "checkbox",
+                    checked: "{state.read().settings.webrtc.stats.pause_when_hidden}",
+                    onclick: toggle_pause_stats
+                }
+                label { r#for: "pauseStats", {tr("stats.pause_when_hidden")} }
+            }
+            div {
+                input {
+                    id: "spatialAudio",
+                    r#type: "checkbox",
+                    checked: "{state.read().settings.webrtc.spatial_audio}",
+                    onclick: toggle_spatial_audio
+                }
+                label { r#for: "spatialAudio", {tr("audio.spatial")} }
+                span { class: "hint", {tr("audio.spatial_hint")} }
+            }
+            div {
+                input {
+                    id: "liveCaptions",
+                    r#type: "checkbox",
+                    checked: "{state.read().settings.transcription.enabled}",
+                    onclick: toggle_captions
+                }
+                label { r#for: "liveCaptions", {tr("captions.enabled")} }
+                span { class: "hint", {tr("captions.hint")} }
+            }
+            // Only while in a call with spatial placement on, and only peers we're hearing
+            {(!pans.is_empty()).then(|| rsx!(
+                ul { class: "stereo-layout",
+                    aria_label: tr("audio.spatial"),
+                    pans.iter().map(|(peer_id, pan)| {
+                        let name = contacts.read().name_for(peer_id).unwrap_or(peer_id).to_string();
+                        let pan = (pan * 100.0).round();
+                        let peer = peer_id.clone();
+                        rsx! {
+                            li { key: "{peer_id}",
+                                label { r#for: "pan-{peer_id}", "{name}" }
+                                span { aria_hidden: "true", {tr("audio.pan_left")} }
+                                input {
+                                    id: "pan-{peer_id}",
+                                    r#type: "range",
+                                    min: "-100",
+                                    max: "100",
+                                    value: "{pan}",
+                                    oninput: move |evt: FormEvent| {
+                                        if let Ok(pan) = evt.value.parse::<f32>() {
+                                            state.read().send(EngineCommand::SetPan { peer_id: peer.clone(), pan: pan / 100.0 });
+                                        }
+                                    },
+                                }
+                                span { aria_hidden: "true", {tr("audio.pan_right")} }
+                            }
+                        }
+                    })
                 }
+            ))}
+            button {
+                onclick: toggle_mute,
+                disabled: "{!*is_in_call.get()}",
+                aria_pressed: "{*is_muted.get()}",
+                aria_keyshortcuts: "Control+M",
+                {if *is_muted.get() { tr("call.unmute") } else { tr("call.mute") }}
+            }
+            button {
+                onclick: toggle_echo_test,
+                disabled: "{*is_in_call.get() || *echo_starting.get()}",
+                aria_pressed: "{echo_running}",
+                {if echo_running { tr("echo.stop") } else { tr("echo.start") }}
             }
+            {echo_running.then(|| rsx!(
+                p { class: "hint", {tr("echo.hint")} }
+            ))}
+            {is_in_call.get().then(|| rsx!(
+                h4 { {tr("dtmf.title")} }
+                DialPad { on_tone: send_dtmf }
+            ))}
         }
-        SignalingMessage::Answer { sdp, .. } => {
-            if let Some(ref webrtc) = state.webrtc {
-                webrtc.handle_answer(sdp).await?;
+
+        {!error_message.get().is_empty().then(|| rsx!(
+            div {
+                class: "error-message",
+                role: "alert",
+                "{error_message.get()}"
+            }
+        ))}
+
+        div { class: "control-panel",
+            h3 { {tr("chat.title")} }
+            if chat_popped {
+                rsx! {
+                    button {
+                        onclick: move |_| panel_feeds.write().dock_chat(),
+                        {tr("chat.dock")}
+                    }
+                }
+            } else {
+                rsx! {
+                    button {
+                        onclick: move |_| panel_feeds.write().pop_out_chat(window, state.read().peer_id.clone()),
+                        {tr("panel.pop_out")}
+                    }
+                    ChatPanel {
+                        entries: state.read().chat_log.clone(),
+                        local_peer_id: state.read().peer_id.clone(),
+                        enabled: *is_connected.get(),
+                        on_send: send_chat
+                    }
+                }
             }
         }
-        SignalingMessage::IceCandidate { candidate, .. } => {
-            let candidate_init = RTCIceCandidateInit {
-                candidate: candidate,
-                ..Default::default()
-            };
-            if let Some(ref webrtc) = state.webrtc {
-                webrtc.peer_connection.add_ice_candidate(candidate_init).await?;
+
+        div { class: "control-panel",
+            h3 { {tr("diagnostics.title")} }
+            if diagnostics_popped {
+                rsx! {
+                    button {
+                        onclick: move |_| panel_feeds.write().dock_diagnostics(),
+                        {tr("diagnostics.dock")}
+                    }
+                }
+            } else {
+                rsx! {
+                    button {
+                        onclick: move |_| panel_feeds.write().pop_out_diagnostics(window),
+                        {tr("panel.pop_out")}
+                    }
+                    LiveDiagnostics {
+                        status: status.clone(),
+                        bluetooth: bluetooth_device.clone(),
+                        metrics: metric_feeds,
+                    }
+                }
+            }
+            h4 { {tr("negotiation.title")} }
+            button {
+                onclick: move |_| show_negotiation.set(!*show_negotiation.get()),
+                aria_expanded: "{show_negotiation}",
+                {if *show_negotiation.get() { tr("negotiation.hide") } else { tr("negotiation.show") }}
             }
+            {show_negotiation.get().then(|| rsx!(
+                NegotiationInspector {
+                    log: negotiation.clone(),
+                    setup: setup_time,
+                }
+            ))}
         }
-        _ => {}
-    }
-    Ok(())
-}
+    };
 
-async fn start_call(state: Arc<Mutex<AppState>>, selected_peers: Vec<String>) -> Result<()> {
-    let mut state = state.lock().await;
-    
-    // Create WebRTC client if it doesn't exist
-    if state.webrtc.is_none() {
-        state.webrtc = Some(Arc::new(WebRTCClient::new().await?));
-        
-        // Set up audio capture
-        if let Some(ref webrtc) = state.webrtc {
-            state.audio_capture = Some(AudioCapture::new(webrtc.audio_track.clone())?);
+    cx.render(rsx! {
+        style { include_str!("./style.css") }
+        div {
+            class: "{app_class}",
+            onkeydown: handle_shortcut,
+            onmousedown: move |_| state.read().send(EngineCommand::Activity),
+            content
         }
-    }
+    })
+}
 
-    // Send call request
-    if let Some(ref signaling) = state.signaling {
-        signaling.lock().await.send(SignalingMessage::CallRequest {
-            room_id: state.room_id.clone(),
-            from_peer: state.peer_id.clone(),
-            to_peers: selected_peers,
-        }).await?;
+fn peer_setup_status(setup: &PeerSetup) -> (&'static str, String) {
+    match setup {
+        PeerSetup::Preparing => ("preparing", tr("call_setup.preparing").to_string()),
+        PeerSetup::Ringing => ("ringing", tr("call_setup.ringing").to_string()),
+        PeerSetup::Connected => ("connected", tr("call_setup.connected").to_string()),
+        PeerSetup::Declined => ("failed", tr("call_setup.declined").to_string()),
+        PeerSetup::Failed(error) => ("failed", tr_args("call_setup.failed", &[("error", error.as_str())])),
     }
+}
 
-    Ok(())
+fn caption_line(caption: &Caption, contacts: &ContactBook) -> CaptionLine {
+    let (speaker, own) = match caption.source {
+        TapSource::Local => (tr("captions.you").to_string(), true),
+        TapSource::Remote(ref peer_id) => (contacts.name_for(peer_id).unwrap_or(peer_id).to_string(), false),
+    };
+    CaptionLine {
+        speaker,
+        text: caption.text.clone(),
+        own,
+    }
 }
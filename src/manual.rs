@@ -0,0 +1,105 @@
+use anyhow::{anyhow, Result};
+use std::sync::Arc;
+use std::time::Duration;
+use webrtc::peer_connection::sdp::sdp_type::RTCSdpType;
+use webrtc::peer_connection::sdp::session_description::RTCSessionDescription;
+
+use crate::audio::{AudioBackend, AudioStreamHandle};
+use crate::webrtc::{WebRTCClient, WebRTCConfig};
+
+// Signaling by hand, for debugging and for demos where no server is reachable: the users
+// copy the offer and the answer between the two machines themselves. Nothing can trickle
+// that way, so each side waits for gathering and hands over the complete description;
+// candidates that turn up elsewhere can still be pasted on their own.
+const GATHER_TIMEOUT: Duration = Duration::from_secs(10);
+// What pasted candidates are queued under until the remote description is set
+const REMOTE_PEER: &str = "manual";
+
+pub struct ManualCall {
+    client: Arc<WebRTCClient>,
+    _capture: AudioStreamHandle,
+}
+
+impl ManualCall {
+    async fn start(config: &WebRTCConfig, backend: Arc<dyn AudioBackend>) -> Result<Self> {
+        let client = Arc::new(WebRTCClient::builder().config(config).audio_backend(backend.clone()).build().await?);
+        let capture = backend.start_capture(client.audio_track.clone())?;
+        Ok(Self {
+            client,
+            _capture: capture,
+        })
+    }
+
+    // The calling side; the text is the offer to hand to the other machine
+    pub async fn offer(config: &WebRTCConfig, backend: Arc<dyn AudioBackend>) -> Result<(Self, String)> {
+        let call = Self::start(config, backend).await?;
+        call.client.create_offer().await?;
+        let offer = call.client.gathered_local_description(GATHER_TIMEOUT).await?;
+        Ok((call, offer))
+    }
+
+    // The called side, from the pasted offer; the text is the answer to hand back
+    pub async fn answer(config: &WebRTCConfig, backend: Arc<dyn AudioBackend>, offer: &str) -> Result<(Self, String)> {
+        let offer = parse_description(offer, RTCSdpType::Offer)?;
+        let call = Self::start(config, backend).await?;
+        call.client.handle_offer(offer).await?;
+        let answer = call.client.gathered_local_description(GATHER_TIMEOUT).await?;
+        Ok((call, answer))
+    }
+
+    pub async fn accept_answer(&self, answer: &str) -> Result<()> {
+        self.client.handle_answer(parse_description(answer, RTCSdpType::Answer)?).await
+    }
+
+    // Returns how many were added
+    pub async fn add_candidates(&self, text: &str) -> Result<usize> {
+        let candidates = parse_candidates(text);
+        if candidates.is_empty() {
+            return Err(anyhow!("No ICE candidates in the pasted text"));
+        }
+        for candidate in &candidates {
+            self.client.add_ice_candidate(REMOTE_PEER, candidate.clone()).await?;
+        }
+        Ok(candidates.len())
+    }
+
+    pub fn client(&self) -> &Arc<WebRTCClient> {
+        &self.client
+    }
+
+    pub async fn hang_up(&self) {
+        self.client.close().await;
+    }
+}
+
+// Either what this app hands out ({"type": "offer", "sdp": "..."}) or bare SDP as other
+// tools print it; comes back as the JSON handle_offer and handle_answer take
+pub fn parse_description(text: &str, expected: RTCSdpType) -> Result<String> {
+    let text = text.trim();
+    let description: RTCSessionDescription = if text.starts_with('{') {
+        serde_json::from_str(text).map_err(|e| anyhow!("Not a session description: {}", e))?
+    } else if text.starts_with("v=0") {
+        // Copying through a text box tends to lose the \r
+        let sdp = text.lines().map(str::trim_end).collect::<Vec<_>>().join("\r\n") + "\r\n";
+        match expected {
+            RTCSdpType::Offer => RTCSessionDescription::offer(sdp)?,
+            _ => RTCSessionDescription::answer(sdp)?,
+        }
+    } else {
+        return Err(anyhow!("Paste the whole {}, starting with {{ or v=0", expected));
+    };
+    if description.sdp_type != expected {
+        return Err(anyhow!("Pasted an {} where an {} was expected", description.sdp_type, expected));
+    }
+    Ok(serde_json::to_string(&description)?)
+}
+
+// One per line, as "candidate:..." or the SDP's "a=candidate:..."; anything else is skipped
+pub fn parse_candidates(text: &str) -> Vec<String> {
+    text.lines()
+        .map(|line| line.trim())
+        .map(|line| line.strip_prefix("a=").unwrap_or(line))
+        .filter(|line| line.starts_with("candidate:"))
+        .map(str::to_string)
+        .collect()
+}
@@ -1,21 +1,40 @@
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(not(target_arch = "wasm32"))]
 use std::sync::Arc;
+#[cfg(not(target_arch = "wasm32"))]
 use std::time::{Duration, Instant};
+#[cfg(not(target_arch = "wasm32"))]
 use tokio::sync::watch;
+#[cfg(not(target_arch = "wasm32"))]
 use tokio::sync::Mutex;
+#[cfg(not(target_arch = "wasm32"))]
 use tokio::time::interval;
+#[cfg(not(target_arch = "wasm32"))]
 use webrtc::peer_connection::RTCPeerConnection;
+#[cfg(not(target_arch = "wasm32"))]
 use webrtc::rtp_transceiver::rtp_transceiver_direction::RTCRtpTransceiverDirection;
-use webrtc::stats::stats_report::StatsReport;
+#[cfg(not(target_arch = "wasm32"))]
+use webrtc::peer_connection::peer_connection_state::RTCPeerConnectionState;
+#[cfg(not(target_arch = "wasm32"))]
+use webrtc::stats::{StatsReport, StatsReportType};
+#[cfg(not(target_arch = "wasm32"))]
 use anyhow::Result;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::tasks::TaskGroup;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ConnectionQuality {
     pub round_trip_time: f64,        // milliseconds
     pub jitter: f64,                 // milliseconds
     pub packet_loss_rate: f64,       // percentage (0-100)
     pub audio_level: f64,            // dB (-127 to 0)
     pub bitrate: f64,                // kbps
+    // What the transport estimates we can send, kbps; 0 until it has an estimate
+    #[serde(default)]
+    pub available_bitrate: f64,
     pub quality_score: u8,           // 0-100
 }
 
@@ -27,13 +46,15 @@ impl Default for ConnectionQuality {
             packet_loss_rate: 0.0,
             audio_level: -127.0,
             bitrate: 0.0,
+            available_bitrate: 0.0,
             quality_score: 100,
         }
     }
 }
 
 impl ConnectionQuality {
-    fn calculate_quality_score(&mut self) {
+    // Also used by the browser build, which reads the same numbers from getStats()
+    pub fn calculate_quality_score(&mut self) {
         let rtt_score = if self.round_trip_time < 150.0 { 40 } 
                        else if self.round_trip_time < 300.0 { 30 }
                        else { 20 };
@@ -51,32 +72,195 @@ impl ConnectionQuality {
     }
 }
 
+impl ConnectionQuality {
+    // The worst of several connections, for a single summary of a group call
+    pub fn worst<'a>(qualities: impl IntoIterator<Item = &'a ConnectionQuality>) -> Option<ConnectionQuality> {
+        qualities
+            .into_iter()
+            .min_by_key(|quality| quality.quality_score)
+            .cloned()
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn from_stats(report: &StatsReport, previous_bytes: &mut Option<(u64, Instant)>) -> Self {
+        let mut quality = Self::default();
+
+        if let Some(rtt) = extract_rtt(report) {
+            quality.round_trip_time = rtt;
+        }
+        if let Some(loss) = extract_packet_loss(report) {
+            quality.packet_loss_rate = loss;
+        }
+        if let Some(bitrate) = extract_available_bitrate(report) {
+            quality.available_bitrate = bitrate;
+        }
+
+        let bytes_received = extract_bytes_received(report);
+        let now = Instant::now();
+        if let Some((bytes, at)) = *previous_bytes {
+            let seconds = now.duration_since(at).as_secs_f64();
+            if seconds > 0.0 {
+                quality.bitrate = bytes_received.saturating_sub(bytes) as f64 * 8.0 / seconds / 1000.0;
+            }
+        }
+        *previous_bytes = Some((bytes_received, now));
+
+        // webrtc-rs does not report receive jitter yet, so it stays at zero
+        quality.calculate_quality_score();
+        quality
+    }
+}
+
+// The last minute at the default of one reading a second
+pub const HISTORY_LEN: usize = 60;
+
+// Recent readings, oldest first, for the quality panel's charts
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct QualityHistory {
+    samples: VecDeque<ConnectionQuality>,
+}
+
+impl QualityHistory {
+    pub fn push(&mut self, quality: ConnectionQuality) {
+        if self.samples.len() == HISTORY_LEN {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(quality);
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &ConnectionQuality> {
+        self.samples.iter()
+    }
+
+    // One metric over time, e.g. `history.series(|quality| quality.bitrate)`
+    pub fn series(&self, metric: impl Fn(&ConnectionQuality) -> f64) -> Vec<f64> {
+        self.samples.iter().map(metric).collect()
+    }
+}
+
+// get_stats walks every transport and stream of the connection, so faster than this
+// costs more than it tells
+#[cfg(not(target_arch = "wasm32"))]
+const MIN_STATS_INTERVAL_MS: u64 = 250;
+
+// How often a call's stats are read and shown
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StatsConfig {
+    // Between readings. The quality ladder and alarms work from them, so a longer
+    // interval also makes those slower to react.
+    pub interval_ms: u64,
+    // The desktop app stops updating its panels while its window is minimised or hidden;
+    // readings go on for the ladder, RED and the watchdogs
+    pub pause_when_hidden: bool,
+    // The desktop app redraws the quality panel at most this often; readings in between
+    // still drive alarms and adaptation
+    pub ui_refresh_ms: u64,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Default for StatsConfig {
+    fn default() -> Self {
+        Self {
+            interval_ms: 1_000,
+            pause_when_hidden: true,
+            ui_refresh_ms: 2_000,
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl StatsConfig {
+    pub fn interval(&self) -> Duration {
+        Duration::from_millis(self.interval_ms.max(MIN_STATS_INTERVAL_MS))
+    }
+
+    pub fn ui_refresh(&self) -> Duration {
+        Duration::from_millis(self.ui_refresh_ms)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
 pub struct QualityMonitor {
     peer_connection: Arc<RTCPeerConnection>,
+    interval: Duration,
+    paused: Arc<AtomicBool>,
     stats: Arc<Mutex<Option<StatsReport>>>,
+    quality: Arc<watch::Sender<ConnectionQuality>>,
+    history: Arc<watch::Sender<QualityHistory>>,
+    tasks: TaskGroup,
 }
 
+#[cfg(not(target_arch = "wasm32"))]
 impl QualityMonitor {
     pub fn new(peer_connection: Arc<RTCPeerConnection>) -> Self {
+        Self::with_interval(peer_connection, StatsConfig::default().interval())
+    }
+
+    pub fn with_interval(peer_connection: Arc<RTCPeerConnection>, interval: Duration) -> Self {
+        Self::with_tasks(peer_connection, interval, TaskGroup::new())
+    }
+
+    // The stats loop runs in `tasks`, so it stops with the rest of its owner's tasks
+    pub fn with_tasks(peer_connection: Arc<RTCPeerConnection>, interval: Duration, tasks: TaskGroup) -> Self {
+        let (quality, _) = watch::channel(ConnectionQuality::default());
+        let (history, _) = watch::channel(QualityHistory::default());
         Self {
             peer_connection,
+            interval,
+            paused: Arc::new(AtomicBool::new(false)),
             stats: Arc::new(Mutex::new(None)),
+            quality: Arc::new(quality),
+            history: Arc::new(history),
+            tasks,
         }
     }
 
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    // Nobody is looking: readings go on, since the quality ladder, RED and the watchdogs
+    // depend on them, but panels following them can skip their updates until resumed
+    pub fn set_paused(&self, paused: bool) {
+        self.paused.store(paused, Ordering::Relaxed);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
     pub async fn start_monitoring(&self) {
         let pc = self.peer_connection.clone();
         let stats = self.stats.clone();
+        let quality = self.quality.clone();
+        let history = self.history.clone();
+        let period = self.interval;
         
-        tokio::spawn(async move {
-            let mut interval = interval(Duration::from_secs(1));
+        self.tasks.spawn(async move {
+            let mut interval = interval(period);
+            let mut previous_bytes = None;
             
             loop {
                 interval.tick().await;
-                if let Ok(report) = pc.get_stats().await {
-                    let mut stats_guard = stats.lock().await;
-                    *stats_guard = Some(report);
+                if pc.connection_state() == RTCPeerConnectionState::Closed {
+                    break;
                 }
+                let report = pc.get_stats().await;
+                let reading = ConnectionQuality::from_stats(&report, &mut previous_bytes);
+                history.send_modify(|history| history.push(reading.clone()));
+                quality.send_replace(reading);
+                let mut stats_guard = stats.lock().await;
+                *stats_guard = Some(report);
             }
         });
     }
@@ -85,11 +269,117 @@ impl QualityMonitor {
         let stats = self.stats.lock().await;
         stats.clone()
     }
+
+    pub fn subscribe(&self) -> watch::Receiver<ConnectionQuality> {
+        self.quality.subscribe()
+    }
+
+    pub fn current(&self) -> ConnectionQuality {
+        self.quality.borrow().clone()
+    }
+
+    pub fn history(&self) -> QualityHistory {
+        self.history.borrow().clone()
+    }
 }
 
+// Milliseconds. RTCP receiver reports give the media path RTT; the nominated candidate
+// pair's STUN RTT is the fallback before the first report arrives.
+#[cfg(not(target_arch = "wasm32"))]
 fn extract_rtt(stats: &StatsReport) -> Option<f64> {
-    // Implementation for extracting RTT from stats
-    None // Placeholder
+    let from_reports = stats.reports.values().find_map(|report| match report {
+        StatsReportType::RemoteInboundRTP(remote) if remote.kind == "audio" => remote.round_trip_time,
+        _ => None,
+    });
+    from_reports
+        .or_else(|| {
+            stats.reports.values().find_map(|report| match report {
+                StatsReportType::CandidatePair(pair) if pair.nominated && pair.current_round_trip_time > 0.0 => {
+                    Some(pair.current_round_trip_time)
+                }
+                _ => None,
+            })
+        })
+        .map(|seconds| seconds * 1000.0)
+}
+
+// Percentage the remote side reported losing of what we sent
+#[cfg(not(target_arch = "wasm32"))]
+fn extract_packet_loss(stats: &StatsReport) -> Option<f64> {
+    stats.reports.values().find_map(|report| match report {
+        StatsReportType::RemoteInboundRTP(remote) if remote.kind == "audio" => Some(remote.fraction_lost * 100.0),
+        _ => None,
+    })
+}
+
+// Kbps, from the congestion controller's estimate on the nominated pair
+#[cfg(not(target_arch = "wasm32"))]
+fn extract_available_bitrate(stats: &StatsReport) -> Option<f64> {
+    stats.reports.values().find_map(|report| match report {
+        StatsReportType::CandidatePair(pair) if pair.nominated && pair.available_outgoing_bitrate > 0.0 => {
+            Some(pair.available_outgoing_bitrate / 1000.0)
+        }
+        _ => None,
+    })
 }
 
-// Similar helper functions for other metrics... 
\ No newline at end of file
+#[cfg(not(target_arch = "wasm32"))]
+fn extract_bytes_received(stats: &StatsReport) -> u64 {
+    stats
+        .reports
+        .values()
+        .map(|report| match report {
+            StatsReportType::InboundRTP(inbound) if inbound.kind == "audio" => inbound.bytes_received,
+            _ => 0,
+        })
+        .sum()
+}
+
+// Latest quality per remote peer, keyed by peer ID. Each peer's entry is fed by the
+// QualityMonitor of the connection that carries its media.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PeerQualities {
+    peers: BTreeMap<String, ConnectionQuality>,
+}
+
+impl PeerQualities {
+    pub fn update(&mut self, peer_id: &str, quality: ConnectionQuality) {
+        self.peers.insert(peer_id.to_string(), quality);
+    }
+
+    pub fn remove(&mut self, peer_id: &str) {
+        self.peers.remove(peer_id);
+    }
+
+    pub fn clear(&mut self) {
+        self.peers.clear();
+    }
+
+    pub fn get(&self, peer_id: &str) -> Option<&ConnectionQuality> {
+        self.peers.get(peer_id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &ConnectionQuality)> {
+        self.peers.iter().map(|(peer_id, quality)| (peer_id.as_str(), quality))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.peers.is_empty()
+    }
+
+    // Summary for the diagnostics panel: a call is only as good as its worst leg
+    pub fn overall(&self) -> ConnectionQuality {
+        ConnectionQuality::worst(self.peers.values()).unwrap_or_default()
+    }
+}
+
+// Bars (1-4) for the signal-strength icon next to a participant, on the same bands
+// as the diagnostics panel's quality classes
+pub fn signal_bars(score: u8) -> u8 {
+    match score {
+        90..=100 => 4,
+        70..=89 => 3,
+        50..=69 => 2,
+        _ => 1,
+    }
+}
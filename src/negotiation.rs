@@ -0,0 +1,208 @@
+use anyhow::Result;
+use std::fmt::{self, Write as _};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use webrtc::peer_connection::sdp::sdp_type::RTCSdpType;
+
+// Everything a call's connections exchanged to get connected, in order: each description
+// as applied and each ICE candidate, for the diagnostics panel and for bug reports.
+// Renegotiations keep adding to it, so a long call drops its oldest entries.
+const MAX_ENTRIES: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Sent,
+    Received,
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Direction::Sent => write!(f, "sent"),
+            Direction::Received => write!(f, "received"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NegotiationKind {
+    Offer,
+    Answer,
+    Pranswer,
+    Rollback,
+    Candidate,
+}
+
+impl From<RTCSdpType> for NegotiationKind {
+    fn from(sdp_type: RTCSdpType) -> Self {
+        match sdp_type {
+            RTCSdpType::Answer => NegotiationKind::Answer,
+            RTCSdpType::Pranswer => NegotiationKind::Pranswer,
+            RTCSdpType::Rollback => NegotiationKind::Rollback,
+            _ => NegotiationKind::Offer,
+        }
+    }
+}
+
+impl fmt::Display for NegotiationKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NegotiationKind::Offer => write!(f, "offer"),
+            NegotiationKind::Answer => write!(f, "answer"),
+            NegotiationKind::Pranswer => write!(f, "pranswer"),
+            NegotiationKind::Rollback => write!(f, "rollback"),
+            NegotiationKind::Candidate => write!(f, "candidate"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NegotiationEntry {
+    // Since the log was started
+    pub elapsed: Duration,
+    // Wall clock, to line up with the signaling server's logs
+    pub at: SystemTime,
+    pub direction: Direction,
+    pub kind: NegotiationKind,
+    // The other side, where known
+    pub peer: Option<String>,
+    // The SDP, or the candidate line
+    pub content: String,
+}
+
+struct Entries {
+    entries: Vec<NegotiationEntry>,
+    dropped: usize,
+}
+
+// One per call, shared by its connections; each connection records through a clone
+// labelled with its peer
+#[derive(Clone)]
+pub struct NegotiationLog {
+    started: Instant,
+    started_at: SystemTime,
+    entries: Arc<Mutex<Entries>>,
+    peer: Option<String>,
+}
+
+impl Default for NegotiationLog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// The same log, not just the same contents
+impl PartialEq for NegotiationLog {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.entries, &other.entries) && self.peer == other.peer
+    }
+}
+
+impl NegotiationLog {
+    pub fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            started_at: SystemTime::now(),
+            entries: Arc::new(Mutex::new(Entries {
+                entries: Vec::new(),
+                dropped: 0,
+            })),
+            peer: None,
+        }
+    }
+
+    // Records into the same log, with entries attributed to peer
+    pub fn for_peer(&self, peer: &str) -> Self {
+        Self {
+            peer: Some(peer.to_string()),
+            ..self.clone()
+        }
+    }
+
+    pub fn peer(&self) -> Option<&str> {
+        self.peer.as_deref()
+    }
+
+    pub fn record(&self, direction: Direction, kind: NegotiationKind, content: &str) {
+        self.record_from(direction, kind, None, content);
+    }
+
+    // For candidates that say where they came from; the log's own peer wins
+    pub fn record_from(&self, direction: Direction, kind: NegotiationKind, peer: Option<&str>, content: &str) {
+        let entry = NegotiationEntry {
+            elapsed: self.started.elapsed(),
+            at: SystemTime::now(),
+            direction,
+            kind,
+            peer: self.peer.clone().or_else(|| peer.map(str::to_string)),
+            content: content.to_string(),
+        };
+        let mut entries = self.entries.lock().unwrap();
+        if entries.entries.len() == MAX_ENTRIES {
+            entries.entries.remove(0);
+            entries.dropped += 1;
+        }
+        entries.entries.push(entry);
+    }
+
+    pub fn entries(&self) -> Vec<NegotiationEntry> {
+        self.entries.lock().unwrap().entries.clone()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // Plain text to attach to a bug report
+    pub fn export(&self) -> String {
+        let entries = self.entries.lock().unwrap();
+        let mut text = format!("Negotiation log started {}\n", unix_time(self.started_at));
+        if entries.dropped > 0 {
+            let _ = writeln!(text, "({} older entries dropped)", entries.dropped);
+        }
+        for entry in &entries.entries {
+            let peer = match (&entry.peer, entry.direction) {
+                (Some(peer), Direction::Sent) => format!(" to {}", peer),
+                (Some(peer), Direction::Received) => format!(" from {}", peer),
+                (None, _) => String::new(),
+            };
+            let _ = writeln!(
+                text,
+                "\n[{} +{:.3}s] {} {}{}",
+                unix_time(entry.at),
+                entry.elapsed.as_secs_f64(),
+                entry.direction,
+                entry.kind,
+                peer
+            );
+            let _ = writeln!(text, "{}", entry.content.trim_end());
+        }
+        text
+    }
+
+    // Writes export() to a new file in dir and returns its path
+    pub fn save(&self, dir: &Path) -> Result<PathBuf> {
+        self.save_with_header(dir, "")
+    }
+
+    // With more about the call ahead of the log, e.g. its setup times
+    pub fn save_with_header(&self, dir: &Path, header: &str) -> Result<PathBuf> {
+        std::fs::create_dir_all(dir)?;
+        let since_epoch = self.started_at.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let path = dir.join(format!("negotiation-{}.txt", since_epoch.as_millis()));
+        let text = if header.is_empty() { self.export() } else { format!("{}\n{}", header.trim_end(), self.export()) };
+        std::fs::write(&path, text)?;
+        Ok(path)
+    }
+}
+
+// Seconds since the Unix epoch, to the millisecond
+fn unix_time(at: SystemTime) -> String {
+    let since_epoch = at.duration_since(UNIX_EPOCH).unwrap_or_default();
+    format!("{}.{:03}", since_epoch.as_secs(), since_epoch.subsec_millis())
+}
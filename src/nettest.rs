@@ -0,0 +1,362 @@
+use anyhow::{anyhow, Result};
+use bytes::Bytes;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::net::UdpSocket;
+use tokio::sync::Notify;
+use tokio::time::timeout;
+use webrtc::api::media_engine::MediaEngine;
+use webrtc::api::{APIBuilder, API};
+use webrtc::data_channel::data_channel_message::DataChannelMessage;
+use webrtc::ice_transport::ice_server::RTCIceServer;
+use webrtc::peer_connection::configuration::RTCConfiguration;
+use webrtc::peer_connection::policy::ice_transport_policy::RTCIceTransportPolicy;
+use webrtc::peer_connection::sdp::session_description::RTCSessionDescription;
+use webrtc::peer_connection::RTCPeerConnection;
+
+use crate::webrtc::WebRTCConfig;
+
+const STUN_MAGIC_COOKIE: u32 = 0x2112_A442;
+const STUN_BINDING_REQUEST: u16 = 0x0001;
+const STUN_BINDING_SUCCESS: u16 = 0x0101;
+const ATTR_MAPPED_ADDRESS: u16 = 0x0001;
+const ATTR_XOR_MAPPED_ADDRESS: u16 = 0x0020;
+
+const STUN_ATTEMPTS: usize = 5;
+const STUN_TIMEOUT: Duration = Duration::from_secs(2);
+const GATHER_TIMEOUT: Duration = Duration::from_secs(10);
+const PROBE_CHUNK: usize = 16 * 1024;
+const PROBE_BYTES: usize = 1024 * 1024;
+const PROBE_TIMEOUT: Duration = Duration::from_secs(15);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServerProbe {
+    pub url: String,
+    pub reachable: bool,
+    // Median of the binding round trips that got an answer
+    pub rtt: Option<Duration>,
+    pub mapped_address: Option<SocketAddr>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CandidateSummary {
+    pub host: usize,
+    pub srflx: usize,
+    pub relay: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NetworkTestReport {
+    pub servers: Vec<ServerProbe>,
+    pub candidates: CandidateSummary,
+    // Only measured when a TURN server is configured; the probe loops through the relay
+    pub bandwidth_kbps: Option<f64>,
+    pub udp_blocked: bool,
+    // Different servers saw different mapped ports from the same socket
+    pub symmetric_nat: bool,
+    pub relay_needed: bool,
+}
+
+pub async fn run(config: &WebRTCConfig) -> Result<NetworkTestReport> {
+    let socket = UdpSocket::bind("0.0.0.0:0").await?;
+    let mut servers = Vec::new();
+    for server in &config.ice_servers {
+        for url in &server.urls {
+            servers.push(probe_server(&socket, url).await);
+        }
+    }
+
+    let api = APIBuilder::new().with_media_engine(MediaEngine::default()).build();
+    let candidates = gather_candidates(&api, config.rtc_ice_servers()).await?;
+
+    let has_turn = config
+        .ice_servers
+        .iter()
+        .flat_map(|server| server.urls.iter())
+        .any(|url| url.starts_with("turn:") || url.starts_with("turns:"));
+    let bandwidth_kbps = if has_turn && candidates.relay > 0 {
+        match bandwidth_probe(&api, config.rtc_ice_servers()).await {
+            Ok(kbps) => Some(kbps),
+            Err(e) => {
+                eprintln!("Bandwidth probe failed: {}", e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    let udp_blocked = candidates.srflx == 0 && !servers.iter().any(|s| s.reachable);
+    let symmetric_nat = is_symmetric_nat(&servers);
+
+    Ok(NetworkTestReport {
+        servers,
+        candidates,
+        bandwidth_kbps,
+        udp_blocked,
+        symmetric_nat,
+        relay_needed: udp_blocked || symmetric_nat,
+    })
+}
+
+fn is_symmetric_nat(servers: &[ServerProbe]) -> bool {
+    let mut ports = servers.iter().filter_map(|s| s.mapped_address).map(|addr| addr.port());
+    match ports.next() {
+        Some(first) => ports.any(|port| port != first),
+        None => false,
+    }
+}
+
+async fn probe_server(socket: &UdpSocket, url: &str) -> ServerProbe {
+    let mut probe = ServerProbe {
+        url: url.to_string(),
+        reachable: false,
+        rtt: None,
+        mapped_address: None,
+        error: None,
+    };
+
+    let target = match resolve_ice_url(url).await {
+        Ok(target) => target,
+        Err(e) => {
+            probe.error = Some(e.to_string());
+            return probe;
+        }
+    };
+
+    let mut rtts = Vec::new();
+    for _ in 0..STUN_ATTEMPTS {
+        match stun_binding(socket, target).await {
+            Ok((rtt, mapped)) => {
+                rtts.push(rtt);
+                probe.mapped_address = Some(mapped);
+            }
+            Err(e) => probe.error = Some(e.to_string()),
+        }
+    }
+
+    if !rtts.is_empty() {
+        rtts.sort();
+        probe.reachable = true;
+        probe.rtt = Some(rtts[rtts.len() / 2]);
+        probe.error = None;
+    }
+    probe
+}
+
+// stun:host[:port] and turn:host[:port][?transport=udp]; TLS and TCP servers are skipped
+async fn resolve_ice_url(url: &str) -> Result<SocketAddr> {
+    let (scheme, rest) = url.split_once(':').ok_or_else(|| anyhow!("Invalid ICE server URL"))?;
+    if scheme != "stun" && scheme != "turn" {
+        return Err(anyhow!("Only UDP stun:/turn: servers can be probed"));
+    }
+    let (host_port, query) = match rest.split_once('?') {
+        Some((host_port, query)) => (host_port, query),
+        None => (rest, ""),
+    };
+    if query.contains("transport=tcp") {
+        return Err(anyhow!("Only UDP stun:/turn: servers can be probed"));
+    }
+
+    let host_port = if host_port.rsplit_once(':').map_or(false, |(_, port)| port.parse::<u16>().is_ok()) {
+        host_port.to_string()
+    } else {
+        format!("{}:3478", host_port)
+    };
+    tokio::net::lookup_host(&host_port)
+        .await?
+        .find(|addr| addr.is_ipv4())
+        .ok_or_else(|| anyhow!("Could not resolve {}", host_port))
+}
+
+async fn stun_binding(socket: &UdpSocket, target: SocketAddr) -> Result<(Duration, SocketAddr)> {
+    let transaction_id: [u8; 12] = rand::random();
+    let mut request = Vec::with_capacity(20);
+    request.extend_from_slice(&STUN_BINDING_REQUEST.to_be_bytes());
+    request.extend_from_slice(&0u16.to_be_bytes());
+    request.extend_from_slice(&STUN_MAGIC_COOKIE.to_be_bytes());
+    request.extend_from_slice(&transaction_id);
+
+    let started = Instant::now();
+    socket.send_to(&request, target).await?;
+
+    let mut buf = [0u8; 1024];
+    timeout(STUN_TIMEOUT, async {
+        loop {
+            let (len, from) = socket.recv_from(&mut buf).await?;
+            // Late answers to earlier attempts carry a different transaction ID
+            if from != target || len < 20 || buf[8..20] != transaction_id {
+                continue;
+            }
+            let mapped = parse_binding_response(&buf[..len], &transaction_id)?;
+            return Ok::<_, anyhow::Error>((started.elapsed(), mapped));
+        }
+    })
+    .await
+    .map_err(|_| anyhow!("No response within {:?}", STUN_TIMEOUT))?
+}
+
+fn parse_binding_response(msg: &[u8], transaction_id: &[u8; 12]) -> Result<SocketAddr> {
+    if u16::from_be_bytes([msg[0], msg[1]]) != STUN_BINDING_SUCCESS {
+        return Err(anyhow!("Server rejected the binding request"));
+    }
+
+    let mut mapped = None;
+    let mut offset = 20;
+    while offset + 4 <= msg.len() {
+        let attr_type = u16::from_be_bytes([msg[offset], msg[offset + 1]]);
+        let attr_len = u16::from_be_bytes([msg[offset + 2], msg[offset + 3]]) as usize;
+        let value = msg
+            .get(offset + 4..offset + 4 + attr_len)
+            .ok_or_else(|| anyhow!("Truncated STUN attribute"))?;
+        match attr_type {
+            ATTR_XOR_MAPPED_ADDRESS => return parse_address(value, Some(transaction_id)),
+            ATTR_MAPPED_ADDRESS => mapped = Some(parse_address(value, None)?),
+            _ => {}
+        }
+        // Attributes are padded to four bytes
+        offset += 4 + (attr_len + 3) / 4 * 4;
+    }
+    mapped.ok_or_else(|| anyhow!("Response has no mapped address"))
+}
+
+fn parse_address(value: &[u8], xor_with: Option<&[u8; 12]>) -> Result<SocketAddr> {
+    if value.len() < 8 {
+        return Err(anyhow!("Truncated address attribute"));
+    }
+    let cookie = STUN_MAGIC_COOKIE.to_be_bytes();
+    let mut port = u16::from_be_bytes([value[2], value[3]]);
+    if xor_with.is_some() {
+        port ^= (STUN_MAGIC_COOKIE >> 16) as u16;
+    }
+
+    let ip = match value[1] {
+        0x01 => {
+            let mut octets = [value[4], value[5], value[6], value[7]];
+            if xor_with.is_some() {
+                for (octet, key) in octets.iter_mut().zip(cookie) {
+                    *octet ^= key;
+                }
+            }
+            IpAddr::V4(Ipv4Addr::from(octets))
+        }
+        0x02 if value.len() >= 20 => {
+            let mut octets = [0u8; 16];
+            octets.copy_from_slice(&value[4..20]);
+            if let Some(transaction_id) = xor_with {
+                let key: Vec<u8> = cookie.iter().chain(transaction_id.iter()).copied().collect();
+                for (octet, key) in octets.iter_mut().zip(key) {
+                    *octet ^= key;
+                }
+            }
+            IpAddr::V6(Ipv6Addr::from(octets))
+        }
+        family => return Err(anyhow!("Unknown address family {}", family)),
+    };
+    Ok(SocketAddr::new(ip, port))
+}
+
+async fn gather_candidates(api: &API, ice_servers: Vec<RTCIceServer>) -> Result<CandidateSummary> {
+    let pc = api
+        .new_peer_connection(RTCConfiguration {
+            ice_servers,
+            ..Default::default()
+        })
+        .await?;
+    // Nothing is gathered for an offer without at least one m-line
+    pc.create_data_channel("nettest", None).await?;
+    let local = gather_local_description(&pc).await;
+    pc.close().await?;
+
+    let mut summary = CandidateSummary::default();
+    for line in local?.lines().filter(|line| line.starts_with("a=candidate:")) {
+        match line.split_whitespace().skip_while(|part| *part != "typ").nth(1) {
+            Some("host") => summary.host += 1,
+            Some("srflx") | Some("prflx") => summary.srflx += 1,
+            Some("relay") => summary.relay += 1,
+            _ => {}
+        }
+    }
+    Ok(summary)
+}
+
+// Creates the offer or answer and waits for gathering so the SDP carries every candidate
+async fn gather_local_description(pc: &RTCPeerConnection) -> Result<String> {
+    let description = match pc.remote_description().await {
+        Some(_) => pc.create_answer(None).await?,
+        None => pc.create_offer(None).await?,
+    };
+    let mut gathered = pc.gathering_complete_promise().await;
+    pc.set_local_description(description).await?;
+    let _ = timeout(GATHER_TIMEOUT, gathered.recv()).await;
+    pc.local_description()
+        .await
+        .map(|description| description.sdp)
+        .ok_or_else(|| anyhow!("No local description"))
+}
+
+// Two in-process peers forced through the relay: data goes up to the TURN server and back
+async fn bandwidth_probe(api: &API, ice_servers: Vec<RTCIceServer>) -> Result<f64> {
+    let config = RTCConfiguration {
+        ice_servers,
+        ice_transport_policy: RTCIceTransportPolicy::Relay,
+        ..Default::default()
+    };
+    let sender = api.new_peer_connection(config.clone()).await?;
+    let receiver = api.new_peer_connection(config).await?;
+
+    let result = run_bandwidth_probe(&sender, &receiver).await;
+    let _ = sender.close().await;
+    let _ = receiver.close().await;
+    result
+}
+
+async fn run_bandwidth_probe(sender: &RTCPeerConnection, receiver: &RTCPeerConnection) -> Result<f64> {
+    let received = Arc::new(AtomicUsize::new(0));
+    let done = Arc::new(Notify::new());
+    let opened = Arc::new(Notify::new());
+
+    let (received_count, done_signal) = (received.clone(), done.clone());
+    receiver.on_data_channel(Box::new(move |channel| {
+        let (received, done) = (received_count.clone(), done_signal.clone());
+        Box::pin(async move {
+            channel.on_message(Box::new(move |msg: DataChannelMessage| {
+                let total = received.fetch_add(msg.data.len(), Ordering::SeqCst) + msg.data.len();
+                if total >= PROBE_BYTES {
+                    done.notify_one();
+                }
+                Box::pin(async {})
+            }));
+        })
+    }));
+
+    let channel = sender.create_data_channel("probe", None).await?;
+    let open_signal = opened.clone();
+    channel.on_open(Box::new(move || {
+        open_signal.notify_one();
+        Box::pin(async {})
+    }));
+
+    let offer = gather_local_description(sender).await?;
+    receiver.set_remote_description(RTCSessionDescription::offer(offer)?).await?;
+    let answer = gather_local_description(receiver).await?;
+    sender.set_remote_description(RTCSessionDescription::answer(answer)?).await?;
+
+    timeout(PROBE_TIMEOUT, opened.notified())
+        .await
+        .map_err(|_| anyhow!("Relay path did not open"))?;
+
+    let started = Instant::now();
+    let chunk = Bytes::from(vec![0u8; PROBE_CHUNK]);
+    for _ in 0..PROBE_BYTES / PROBE_CHUNK {
+        channel.send(&chunk).await?;
+    }
+    timeout(PROBE_TIMEOUT, done.notified())
+        .await
+        .map_err(|_| anyhow!("Probe data did not arrive in time"))?;
+
+    Ok((PROBE_BYTES * 8) as f64 / started.elapsed().as_secs_f64() / 1000.0)
+}
@@ -0,0 +1,96 @@
+use std::net::{IpAddr, SocketAddr, UdpSocket};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+// Wall-clock time running this far ahead of the monotonic clock between two polls means
+// the machine was asleep (the monotonic clock stops during suspend)
+const SUSPEND_THRESHOLD: Duration = Duration::from_secs(5);
+
+// Documentation prefixes: never answered, but still resolved through the default route.
+// connect() on a UDP socket only picks a source address; nothing is sent.
+const PROBE_V4: &str = "192.0.2.1:9";
+const PROBE_V6: &str = "[2001:db8::1]:9";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NetworkChange {
+    // The address we would reach the internet from changed (Wi-Fi to Ethernet, new DHCP
+    // lease, VPN up/down) or the default route appeared/disappeared
+    AddressChanged,
+    // Woke up from sleep; existing sockets are probably dead even if the address is the same
+    Resumed,
+}
+
+// Polls the OS for the source addresses of the default routes. Portable and dependency
+// free, at the cost of noticing a change up to one poll interval late.
+pub struct NetworkWatcher {
+    task: JoinHandle<()>,
+}
+
+impl NetworkWatcher {
+    pub fn spawn(poll_interval: Duration) -> (Self, mpsc::UnboundedReceiver<NetworkChange>) {
+        let (changes_tx, changes_rx) = mpsc::unbounded_channel();
+
+        let task = tokio::spawn(async move {
+            let mut addresses = default_route_addresses();
+            let mut last_poll = (Instant::now(), SystemTime::now());
+
+            loop {
+                tokio::time::sleep(poll_interval).await;
+                let now = (Instant::now(), SystemTime::now());
+                let slept = slept_between(last_poll, now);
+                last_poll = now;
+
+                let current = default_route_addresses();
+                let change = if slept {
+                    Some(NetworkChange::Resumed)
+                } else if current != addresses {
+                    Some(NetworkChange::AddressChanged)
+                } else {
+                    None
+                };
+                addresses = current;
+
+                if let Some(change) = change {
+                    println!("Network change detected: {:?}", change);
+                    if changes_tx.send(change).is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+
+        (Self { task }, changes_rx)
+    }
+}
+
+impl Drop for NetworkWatcher {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+// Source addresses for IPv4 and IPv6 traffic; None where there is no route at all
+pub fn default_route_addresses() -> (Option<IpAddr>, Option<IpAddr>) {
+    (
+        route_source("0.0.0.0:0", PROBE_V4),
+        route_source("[::]:0", PROBE_V6),
+    )
+}
+
+fn route_source(bind: &str, probe: &str) -> Option<IpAddr> {
+    let socket = UdpSocket::bind(bind).ok()?;
+    socket.connect(probe).ok()?;
+    socket.local_addr().ok().map(|addr: SocketAddr| addr.ip())
+}
+
+fn slept_between(earlier: (Instant, SystemTime), later: (Instant, SystemTime)) -> bool {
+    let monotonic = later.0.duration_since(earlier.0);
+    match later.1.duration_since(earlier.1) {
+        Ok(wall) => wall > monotonic + SUSPEND_THRESHOLD,
+        // Clock set backwards; not a suspend
+        Err(_) => false,
+    }
+}
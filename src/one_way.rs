@@ -0,0 +1,160 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+use webrtc::stats::{StatsReport, StatsReportType};
+
+// Audio that only gets through one way, told from our RTP counters against what the
+// peer's RTCP reports say. Receiver and sender reports come about once a second, so a
+// counter that hasn't moved for this long has stopped.
+pub const ONE_WAY_AFTER: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OneWayAudio {
+    // We send and hear them, but their receiver reports show nothing arriving
+    PeerCannotHearUs,
+    // Their sender reports say they are sending, but nothing reaches us
+    WeCannotHearPeer,
+}
+
+impl OneWayAudio {
+    pub fn message_key(self) -> &'static str {
+        match self {
+            OneWayAudio::PeerCannotHearUs => "one_way.peer_cannot_hear_you",
+            OneWayAudio::WeCannotHearPeer => "one_way.cannot_hear_peer",
+        }
+    }
+
+    // Suggested fixes, most likely first
+    pub fn hint_keys(self) -> &'static [&'static str] {
+        match self {
+            OneWayAudio::PeerCannotHearUs => &["one_way.hint_outgoing_firewall", "one_way.hint_turn", "one_way.hint_restart"],
+            OneWayAudio::WeCannotHearPeer => &["one_way.hint_incoming_firewall", "one_way.hint_turn", "one_way.hint_restart"],
+        }
+    }
+}
+
+// Audio packet totals of one connection
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PacketCounters {
+    pub sent: u64,
+    pub received: u64,
+    // From the peer's receiver reports; None before the first one
+    pub peer_received: Option<u64>,
+    // From the peer's sender reports
+    pub peer_sent: Option<u64>,
+}
+
+impl PacketCounters {
+    pub fn from_stats(report: &StatsReport) -> Self {
+        Self::count(report, |_| true)
+    }
+
+    // One peer's share of a connection that carries several, as through a relay: only
+    // the streams with these SSRCs count as theirs, so one live stream can't hide a dead
+    // one. What we send goes to all of them.
+    pub fn for_streams(report: &StatsReport, ssrcs: &[u32]) -> Self {
+        Self::count(report, |ssrc| ssrcs.contains(&ssrc))
+    }
+
+    fn count(report: &StatsReport, theirs: impl Fn(u32) -> bool) -> Self {
+        let mut counters = Self::default();
+        for report in report.reports.values() {
+            match report {
+                StatsReportType::OutboundRTP(outbound) if outbound.kind == "audio" => counters.sent += outbound.packets_sent,
+                StatsReportType::InboundRTP(inbound) if inbound.kind == "audio" && theirs(inbound.ssrc) => {
+                    counters.received += inbound.packets_received;
+                }
+                StatsReportType::RemoteInboundRTP(remote) if remote.kind == "audio" => {
+                    *counters.peer_received.get_or_insert(0) += remote.packets_received;
+                }
+                StatsReportType::RemoteOutboundRTP(remote) if remote.kind == "audio" && theirs(remote.ssrc) => {
+                    *counters.peer_sent.get_or_insert(0) += remote.packets_sent;
+                }
+                _ => {}
+            }
+        }
+        counters
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OneWayVerdict {
+    Detected(OneWayAudio),
+    Cleared,
+}
+
+// When each counter last grew
+#[derive(Debug)]
+struct PeerCounters {
+    first_seen: Instant,
+    counters: PacketCounters,
+    sent_at: Option<Instant>,
+    received_at: Option<Instant>,
+    peer_received_at: Option<Instant>,
+    peer_sent_at: Option<Instant>,
+    reported: Option<OneWayAudio>,
+}
+
+// Fed each peer's counters every stats reading; reports when a peer's diagnosis changes
+#[derive(Debug, Default)]
+pub struct OneWayDetector {
+    peers: BTreeMap<String, PeerCounters>,
+}
+
+impl OneWayDetector {
+    pub fn observe(&mut self, peer_id: &str, counters: PacketCounters, now: Instant) -> Option<OneWayVerdict> {
+        let peer = self.peers.entry(peer_id.to_string()).or_insert_with(|| PeerCounters {
+            first_seen: now,
+            counters: PacketCounters::default(),
+            sent_at: None,
+            received_at: None,
+            peer_received_at: None,
+            peer_sent_at: None,
+            reported: None,
+        });
+        let previous = std::mem::replace(&mut peer.counters, counters);
+        if counters.sent > previous.sent {
+            peer.sent_at = Some(now);
+        }
+        if counters.received > previous.received {
+            peer.received_at = Some(now);
+        }
+        if counters.peer_received.unwrap_or(0) > previous.peer_received.unwrap_or(0) {
+            peer.peer_received_at = Some(now);
+        }
+        if counters.peer_sent.unwrap_or(0) > previous.peer_sent.unwrap_or(0) {
+            peer.peer_sent_at = Some(now);
+        }
+
+        let recent = |at: Option<Instant>| at.map_or(false, |at| now - at < ONE_WAY_AFTER);
+        // Every connection starts out looking one-way until the first reports arrive
+        let settled = now - peer.first_seen >= ONE_WAY_AFTER;
+        let diagnosis = if !settled {
+            None
+        } else if recent(peer.sent_at) && recent(peer.received_at) && !recent(peer.peer_received_at) {
+            Some(OneWayAudio::PeerCannotHearUs)
+        } else if recent(peer.peer_sent_at) && !recent(peer.received_at) {
+            Some(OneWayAudio::WeCannotHearPeer)
+        } else {
+            None
+        };
+        if diagnosis == peer.reported {
+            return None;
+        }
+        let previous = std::mem::replace(&mut peer.reported, diagnosis);
+        match diagnosis {
+            Some(direction) => Some(OneWayVerdict::Detected(direction)),
+            None => previous.map(|_| OneWayVerdict::Cleared),
+        }
+    }
+
+    // The peer left the call, or its connection was replaced
+    pub fn remove(&mut self, peer_id: &str) {
+        self.peers.remove(peer_id);
+    }
+
+    pub fn clear(&mut self) {
+        self.peers.clear();
+    }
+}
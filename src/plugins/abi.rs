@@ -0,0 +1,303 @@
+// The C interface native plugins are loaded through. Nothing Rust-specific crosses the
+// library boundary, so a plugin needn't be built with the client's compiler or version,
+// or in Rust at all: a library exports
+//
+//     const uint32_t WEBRTC_CLIENT_PLUGIN_ABI_VERSION;
+//     PluginVtable webrtc_client_plugin_create(void);
+//
+// and the client calls through the returned table from then on. Messages and panels are
+// UTF-8 JSON, as in the WebAssembly interface. Strings a plugin returns stay its own
+// until the client hands them back to free_string. Any function may be called from any
+// thread, but never two at once on the same instance.
+//
+// Rust plugins get all of this from declare_plugin!. Below are both sides: export wraps
+// a Rust plugin in a table, import wraps a table back into a Plugin.
+use std::ffi::c_void;
+use std::panic::AssertUnwindSafe;
+use std::sync::{Arc, Mutex};
+
+use super::{AudioProcessor, Plugin, PluginPanel, SignalingFilter};
+use crate::audio::tap::TapSource;
+use crate::signaling::SignalingMessage;
+
+/// Bumped on any incompatible change to the structs below.
+pub const PLUGIN_ABI_VERSION: u32 = 1;
+
+/// A string owned by the plugin. A null `data` means none.
+#[repr(C)]
+pub struct PluginString {
+    pub data: *const u8,
+    pub len: usize,
+}
+
+/// Returned by `filter_signaling`.
+pub const FILTER_KEEP: i32 = -1;
+pub const FILTER_DROP: i32 = 0;
+pub const FILTER_REPLACE: i32 = 1;
+
+/// What `webrtc_client_plugin_create` returns. Optional hooks are null when unused.
+#[repr(C)]
+pub struct PluginVtable {
+    pub instance: *mut c_void,
+    pub name: extern "C" fn(instance: *mut c_void) -> PluginString,
+    /// Changes `count` f32 samples in place; `incoming` is true for a peer's audio.
+    pub process_audio:
+        Option<extern "C" fn(instance: *mut c_void, samples: *mut f32, count: usize, sample_rate: u32, incoming: bool)>,
+    /// Returns FILTER_KEEP, FILTER_DROP, or FILTER_REPLACE with the new message in
+    /// `replacement`.
+    pub filter_signaling: Option<
+        extern "C" fn(
+            instance: *mut c_void,
+            message: *const u8,
+            len: usize,
+            outgoing: bool,
+            replacement: *mut PluginString,
+        ) -> i32,
+    >,
+    /// `{"title": ..., "lines": [...]}`, or a null string for no panel.
+    pub panel: Option<extern "C" fn(instance: *mut c_void) -> PluginString>,
+    pub free_string: extern "C" fn(instance: *mut c_void, string: PluginString),
+    /// Called once, last.
+    pub destroy: extern "C" fn(instance: *mut c_void),
+}
+
+// A Rust plugin behind the table, with its hooks asked for once up front
+struct Exported {
+    plugin: Box<dyn Plugin>,
+    processor: Option<Arc<dyn AudioProcessor>>,
+    filter: Option<Arc<dyn SignalingFilter>>,
+}
+
+// The table for a Rust plugin; what declare_plugin! returns from the library
+pub fn export(plugin: Box<dyn Plugin>) -> PluginVtable {
+    let processor = plugin.audio_processor();
+    let filter = plugin.signaling_filter();
+    PluginVtable {
+        process_audio: processor.is_some().then_some(exported_process_audio as _),
+        filter_signaling: filter.is_some().then_some(exported_filter_signaling as _),
+        instance: Box::into_raw(Box::new(Exported { plugin, processor, filter })) as *mut c_void,
+        name: exported_name,
+        panel: Some(exported_panel),
+        free_string: exported_free_string,
+        destroy: exported_destroy,
+    }
+}
+
+// Safety, for everything below: the client only passes the instance pointer export made,
+// buffers of the lengths given, and strings these functions returned
+fn exported<'a>(instance: *mut c_void) -> &'a Exported {
+    unsafe { &*(instance as *const Exported) }
+}
+
+// A panic must not unwind into the client; the audio or message stays as it was
+fn guarded<T>(fallback: T, hook: impl FnOnce() -> T) -> T {
+    std::panic::catch_unwind(AssertUnwindSafe(hook)).unwrap_or(fallback)
+}
+
+fn to_plugin_string(text: String) -> PluginString {
+    let bytes = text.into_bytes().into_boxed_slice();
+    let len = bytes.len();
+    PluginString {
+        data: Box::into_raw(bytes) as *const u8,
+        len,
+    }
+}
+
+extern "C" fn exported_name(instance: *mut c_void) -> PluginString {
+    to_plugin_string(guarded(String::new(), || exported(instance).plugin.name()))
+}
+
+extern "C" fn exported_process_audio(instance: *mut c_void, samples: *mut f32, count: usize, sample_rate: u32, incoming: bool) {
+    let Some(processor) = &exported(instance).processor else { return };
+    let samples = unsafe { std::slice::from_raw_parts_mut(samples, count) };
+    // The peer isn't part of the interface; plugins only learn the direction
+    let source = if incoming { TapSource::Remote(String::new()) } else { TapSource::Local };
+    guarded((), || processor.process(&source, samples, sample_rate));
+}
+
+extern "C" fn exported_filter_signaling(
+    instance: *mut c_void,
+    message: *const u8,
+    len: usize,
+    outgoing: bool,
+    replacement: *mut PluginString,
+) -> i32 {
+    let Some(filter) = &exported(instance).filter else { return FILTER_KEEP };
+    let json = unsafe { std::slice::from_raw_parts(message, len) };
+    let Ok(message) = serde_json::from_slice::<SignalingMessage>(json) else { return FILTER_KEEP };
+    let original = message.clone();
+    let filtered = guarded(Some(original.clone()), || if outgoing { filter.outgoing(message) } else { filter.incoming(message) });
+    match filtered {
+        None => FILTER_DROP,
+        Some(filtered) if filtered == original => FILTER_KEEP,
+        Some(filtered) => match serde_json::to_string(&filtered) {
+            Ok(json) => {
+                unsafe { replacement.write(to_plugin_string(json)) };
+                FILTER_REPLACE
+            }
+            Err(_) => FILTER_KEEP,
+        },
+    }
+}
+
+extern "C" fn exported_panel(instance: *mut c_void) -> PluginString {
+    match guarded(None, || exported(instance).plugin.panel()).and_then(|panel| serde_json::to_string(&panel).ok()) {
+        Some(json) => to_plugin_string(json),
+        None => PluginString { data: std::ptr::null(), len: 0 },
+    }
+}
+
+extern "C" fn exported_free_string(_instance: *mut c_void, string: PluginString) {
+    if !string.data.is_null() {
+        drop(unsafe { Box::from_raw(std::ptr::slice_from_raw_parts_mut(string.data as *mut u8, string.len)) });
+    }
+}
+
+extern "C" fn exported_destroy(instance: *mut c_void) {
+    drop(unsafe { Box::from_raw(instance as *mut Exported) });
+}
+
+/// The client side: a plugin behind a table, whether a library returned it or export
+/// made it.
+///
+/// # Safety
+/// The table's functions must behave as described above for as long as the plugin lives.
+pub unsafe fn import(vtable: PluginVtable) -> Box<dyn Plugin> {
+    let instance = Arc::new(Instance(Mutex::new(vtable)));
+    let name = instance
+        .call(|vtable| take_string(vtable, (vtable.name)(vtable.instance)))
+        .and_then(|name| String::from_utf8(name).ok())
+        .unwrap_or_default();
+    Box::new(NativePlugin { name, instance })
+}
+
+// The table a library returned, destroyed with the last hook holding it. Calls are one
+// at a time, as the interface promises plugins.
+struct Instance(Mutex<PluginVtable>);
+
+// Safety: the interface requires instances to work from any thread, and the mutex keeps
+// calls from overlapping
+unsafe impl Send for Instance {}
+unsafe impl Sync for Instance {}
+
+impl Instance {
+    fn call<T>(&self, hook: impl FnOnce(&PluginVtable) -> Option<T>) -> Option<T> {
+        hook(&*self.0.lock().ok()?)
+    }
+}
+
+impl Drop for Instance {
+    fn drop(&mut self) {
+        let vtable = self.0.get_mut().unwrap_or_else(|poisoned| poisoned.into_inner());
+        (vtable.destroy)(vtable.instance);
+    }
+}
+
+// Copies a string the plugin returned and gives it back
+fn take_string(vtable: &PluginVtable, string: PluginString) -> Option<Vec<u8>> {
+    if string.data.is_null() {
+        return None;
+    }
+    // Safety: the plugin returned data and len together, and keeps them until freed
+    let bytes = unsafe { std::slice::from_raw_parts(string.data, string.len) }.to_vec();
+    (vtable.free_string)(vtable.instance, string);
+    Some(bytes)
+}
+
+struct NativePlugin {
+    name: String,
+    instance: Arc<Instance>,
+}
+
+impl NativePlugin {
+    fn hooks(&self) -> NativeHooks {
+        NativeHooks {
+            name: self.name.clone(),
+            instance: self.instance.clone(),
+        }
+    }
+}
+
+struct NativeHooks {
+    name: String,
+    instance: Arc<Instance>,
+}
+
+impl AudioProcessor for NativeHooks {
+    fn process(&self, source: &TapSource, samples: &mut [f32], sample_rate: u32) {
+        let incoming = matches!(source, TapSource::Remote(_));
+        self.instance.call(|vtable| {
+            let process = vtable.process_audio?;
+            process(vtable.instance, samples.as_mut_ptr(), samples.len(), sample_rate, incoming);
+            Some(())
+        });
+    }
+}
+
+impl SignalingFilter for NativeHooks {
+    fn outgoing(&self, message: SignalingMessage) -> Option<SignalingMessage> {
+        self.filter(message, true)
+    }
+
+    fn incoming(&self, message: SignalingMessage) -> Option<SignalingMessage> {
+        self.filter(message, false)
+    }
+}
+
+impl NativeHooks {
+    // A plugin that returns garbage leaves the message as it was
+    fn filter(&self, message: SignalingMessage, outgoing: bool) -> Option<SignalingMessage> {
+        let Ok(json) = serde_json::to_vec(&message) else { return Some(message) };
+        let verdict = self.instance.call(|vtable| {
+            let filter = vtable.filter_signaling?;
+            let mut replacement = PluginString { data: std::ptr::null(), len: 0 };
+            match filter(vtable.instance, json.as_ptr(), json.len(), outgoing, &mut replacement) {
+                FILTER_DROP => Some(None),
+                FILTER_REPLACE => take_string(vtable, replacement).map(Some),
+                // FILTER_KEEP, or anything this version doesn't know
+                _ => None,
+            }
+        });
+        match verdict {
+            None => Some(message),
+            Some(None) => None,
+            Some(Some(replacement)) => match serde_json::from_slice(&replacement) {
+                Ok(replacement) => Some(replacement),
+                Err(e) => {
+                    eprintln!("Plugin {} returned an invalid signaling message: {}", self.name, e);
+                    Some(message)
+                }
+            },
+        }
+    }
+}
+
+impl Plugin for NativePlugin {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn audio_processor(&self) -> Option<Arc<dyn AudioProcessor>> {
+        let exported = self.instance.call(|vtable| Some(vtable.process_audio.is_some()))?;
+        exported.then(|| Arc::new(self.hooks()) as Arc<dyn AudioProcessor>)
+    }
+
+    fn signaling_filter(&self) -> Option<Arc<dyn SignalingFilter>> {
+        let exported = self.instance.call(|vtable| Some(vtable.filter_signaling.is_some()))?;
+        exported.then(|| Arc::new(self.hooks()) as Arc<dyn SignalingFilter>)
+    }
+
+    fn panel(&self) -> Option<PluginPanel> {
+        let json = self.instance.call(|vtable| {
+            let panel = vtable.panel?;
+            take_string(vtable, panel(vtable.instance))
+        })?;
+        match serde_json::from_slice(&json) {
+            Ok(panel) => Some(panel),
+            Err(e) => {
+                eprintln!("Plugin {} returned an invalid panel: {}", self.name, e);
+                None
+            }
+        }
+    }
+}
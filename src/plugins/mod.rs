@@ -0,0 +1,177 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+
+use crate::audio::tap::TapSource;
+use crate::signaling::SignalingMessage;
+
+pub mod abi;
+#[cfg(feature = "native-plugins")]
+mod native;
+#[cfg(feature = "wasm-plugins")]
+mod wasm;
+
+// Bumped whenever the traits below change in a way old plugins would get wrong. Loading
+// refuses plugins built against another version.
+pub const PLUGIN_API_VERSION: u32 = 1;
+
+// Sees every 20 ms frame: Local before it is encoded and sent, Remote(peer) right after
+// decoding, before playback and loudness normalization. Runs on the audio path, so it
+// must not block.
+pub trait AudioProcessor: Send + Sync {
+    fn process(&self, source: &TapSource, samples: &mut [f32], sample_rate: u32);
+}
+
+// Can rewrite or drop signaling messages; returning None drops the message
+pub trait SignalingFilter: Send + Sync {
+    fn outgoing(&self, message: SignalingMessage) -> Option<SignalingMessage> {
+        Some(message)
+    }
+
+    fn incoming(&self, message: SignalingMessage) -> Option<SignalingMessage> {
+        Some(message)
+    }
+}
+
+// A section of the main window, asked for again about once a second
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PluginPanel {
+    pub title: String,
+    pub lines: Vec<String>,
+}
+
+pub trait Plugin: Send + Sync {
+    fn name(&self) -> String;
+
+    fn audio_processor(&self) -> Option<Arc<dyn AudioProcessor>> {
+        None
+    }
+
+    fn signaling_filter(&self) -> Option<Arc<dyn SignalingFilter>> {
+        None
+    }
+
+    fn panel(&self) -> Option<PluginPanel> {
+        None
+    }
+}
+
+// Exports a plugin from a native library (crate-type = ["cdylib"]) through the C
+// interface in abi.rs, so the client loading it may be built with another compiler or
+// webrtc-client version.
+//
+//     webrtc_client::declare_plugin!(MyPlugin::new);
+#[macro_export]
+macro_rules! declare_plugin {
+    ($constructor:path) => {
+        #[no_mangle]
+        pub static WEBRTC_CLIENT_PLUGIN_ABI_VERSION: u32 = $crate::plugins::abi::PLUGIN_ABI_VERSION;
+
+        #[no_mangle]
+        pub extern "C" fn webrtc_client_plugin_create() -> $crate::plugins::abi::PluginVtable {
+            $crate::plugins::abi::export(Box::new($constructor()))
+        }
+    };
+}
+
+// The plugins in use, in load order; each sees what the ones before it produced
+#[derive(Default)]
+pub struct PluginHost {
+    plugins: Vec<Box<dyn Plugin>>,
+    audio_processors: Vec<Arc<dyn AudioProcessor>>,
+    signaling_filters: Vec<Arc<dyn SignalingFilter>>,
+    // Declared last so the plugins are dropped before their code is unloaded
+    #[cfg(feature = "native-plugins")]
+    libraries: Vec<libloading::Library>,
+}
+
+impl PluginHost {
+    pub fn add(&mut self, plugin: Box<dyn Plugin>) {
+        self.audio_processors.extend(plugin.audio_processor());
+        self.signaling_filters.extend(plugin.signaling_filter());
+        self.plugins.push(plugin);
+    }
+
+    // Native libraries (.so, .dylib, .dll) or WebAssembly modules (.wasm)
+    pub fn load(&mut self, path: &Path) -> Result<()> {
+        let plugin = match path.extension().and_then(|extension| extension.to_str()) {
+            Some("wasm") => load_wasm(path)?,
+            Some("so" | "dylib" | "dll") => self.load_native(path)?,
+            _ => return Err(anyhow!("{} is not a plugin library or .wasm module", path.display())),
+        };
+        println!("Loaded plugin {} from {}", plugin.name(), path.display());
+        self.add(plugin);
+        Ok(())
+    }
+
+    #[cfg(feature = "native-plugins")]
+    fn load_native(&mut self, path: &Path) -> Result<Box<dyn Plugin>> {
+        let (library, plugin) = native::load(path)?;
+        self.libraries.push(library);
+        Ok(plugin)
+    }
+
+    #[cfg(not(feature = "native-plugins"))]
+    fn load_native(&mut self, path: &Path) -> Result<Box<dyn Plugin>> {
+        Err(anyhow!("Cannot load {}: this build has no native plugin support (native-plugins feature)", path.display()))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.plugins.is_empty()
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.plugins.iter().map(|plugin| plugin.name()).collect()
+    }
+
+    // False when no plugin processes audio and the frame is untouched
+    pub fn process_audio(&self, source: &TapSource, samples: &mut [f32], sample_rate: u32) -> bool {
+        for processor in &self.audio_processors {
+            processor.process(source, samples, sample_rate);
+        }
+        !self.audio_processors.is_empty()
+    }
+
+    pub fn filter_outgoing(&self, message: SignalingMessage) -> Option<SignalingMessage> {
+        self.signaling_filters.iter().try_fold(message, |message, filter| filter.outgoing(message))
+    }
+
+    pub fn filter_incoming(&self, message: SignalingMessage) -> Option<SignalingMessage> {
+        self.signaling_filters.iter().try_fold(message, |message, filter| filter.incoming(message))
+    }
+
+    pub fn panels(&self) -> Vec<PluginPanel> {
+        self.plugins.iter().filter_map(|plugin| plugin.panel()).collect()
+    }
+}
+
+#[cfg(feature = "wasm-plugins")]
+fn load_wasm(path: &Path) -> Result<Box<dyn Plugin>> {
+    Ok(Box::new(wasm::WasmPlugin::load(path)?))
+}
+
+#[cfg(not(feature = "wasm-plugins"))]
+fn load_wasm(path: &Path) -> Result<Box<dyn Plugin>> {
+    Err(anyhow!("Cannot load {}: this build has no WebAssembly plugin support (wasm-plugins feature)", path.display()))
+}
+
+static HOST: OnceLock<PluginHost> = OnceLock::new();
+
+// Loads every plugin it can; one that fails is reported and left out rather than
+// keeping the client from starting
+pub fn load_all(paths: &[PathBuf]) -> (PluginHost, Vec<anyhow::Error>) {
+    let mut host = PluginHost::default();
+    let errors = paths.iter().filter_map(|path| host.load(path).err()).collect();
+    (host, errors)
+}
+
+// Once at startup, before any call; the audio and signaling paths read it from then on
+pub fn install(host: PluginHost) -> Result<()> {
+    HOST.set(host).map_err(|_| anyhow!("Plugins are already installed"))
+}
+
+// Empty until install
+pub fn host() -> &'static PluginHost {
+    HOST.get_or_init(PluginHost::default)
+}
@@ -0,0 +1,39 @@
+use anyhow::{anyhow, Result};
+use libloading::{Library, Symbol};
+use std::path::Path;
+
+use super::abi::{import, PluginVtable, PLUGIN_ABI_VERSION};
+use super::Plugin;
+
+type CreatePlugin = extern "C" fn() -> PluginVtable;
+
+// A library exporting the C interface in abi.rs. The library has to outlive the plugin,
+// so both are returned.
+pub fn load(path: &Path) -> Result<(Library, Box<dyn Plugin>)> {
+    // Safety: loading runs the library's initializers, and the symbols are trusted to
+    // have the types abi.rs gives them. Plugins are code the user chose to run.
+    unsafe {
+        let library = Library::new(path).map_err(|e| anyhow!("Failed to load plugin {}: {}", path.display(), e))?;
+        let version: Symbol<*const u32> = match library.get(b"WEBRTC_CLIENT_PLUGIN_ABI_VERSION\0") {
+            Ok(version) => version,
+            // Before the C interface plugins were handed over as Rust trait objects
+            Err(_) if library.get::<*const u32>(b"WEBRTC_CLIENT_PLUGIN_API_VERSION\0").is_ok() => {
+                return Err(anyhow!("{} was built for the old Rust-only plugin interface; rebuild it", path.display()));
+            }
+            Err(_) => return Err(anyhow!("{} is not a webrtc-client plugin", path.display())),
+        };
+        let version = **version;
+        if version != PLUGIN_ABI_VERSION {
+            return Err(anyhow!(
+                "{} was built for plugin ABI v{}, but this client supports v{}",
+                path.display(),
+                version,
+                PLUGIN_ABI_VERSION
+            ));
+        }
+        let create: Symbol<CreatePlugin> = library
+            .get(b"webrtc_client_plugin_create\0")
+            .map_err(|_| anyhow!("{} is not a webrtc-client plugin", path.display()))?;
+        Ok((library, import(create())))
+    }
+}
@@ -0,0 +1,195 @@
+use anyhow::{anyhow, Result};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use wasmtime::{Engine, Instance, Memory, Module, Store, TypedFunc};
+
+use super::{AudioProcessor, Plugin, PluginPanel, SignalingFilter, PLUGIN_API_VERSION};
+use crate::audio::tap::TapSource;
+use crate::signaling::SignalingMessage;
+
+// What a module exports; everything but memory, alloc and api_version is optional.
+// Strings are UTF-8 JSON, passed as (pointer, length) into the module's memory and
+// returned packed as pointer << 32 | length. The client never frees what it allocs;
+// the module can hand out the same scratch buffer every time.
+//
+//   api_version() -> i32
+//   alloc(len: i32) -> i32
+//   name() -> i64
+//   process_audio(samples: i32, count: i32, sample_rate: i32, incoming: i32)
+//       f32 samples, changed in place
+//   filter_signaling(message: i32, len: i32, outgoing: i32) -> i64
+//       -1 keeps the message, 0 drops it, anything else is the replacement
+//   panel() -> i64
+//       {"title": ..., "lines": [...]}, or 0 for none
+struct Exports {
+    store: Store<()>,
+    memory: Memory,
+    alloc: TypedFunc<i32, i32>,
+    process_audio: Option<TypedFunc<(i32, i32, i32, i32), ()>>,
+    filter_signaling: Option<TypedFunc<(i32, i32, i32), i64>>,
+    panel: Option<TypedFunc<(), i64>>,
+}
+
+// Modules get no imports, so they can't touch anything but what they are handed
+pub struct WasmPlugin {
+    name: String,
+    module: Arc<Mutex<Exports>>,
+}
+
+impl WasmPlugin {
+    pub fn load(path: &Path) -> Result<Self> {
+        let engine = Engine::default();
+        let module = Module::from_file(&engine, path).map_err(|e| anyhow!("Failed to load plugin {}: {}", path.display(), e))?;
+        let mut store = Store::new(&engine, ());
+        let instance = Instance::new(&mut store, &module, &[])
+            .map_err(|e| anyhow!("Failed to start plugin {}: {}", path.display(), e))?;
+        let version = instance.get_typed_func::<(), i32>(&mut store, "api_version")?.call(&mut store, ())?;
+        if version as u32 != PLUGIN_API_VERSION {
+            return Err(anyhow!(
+                "{} was built for plugin API v{}, but this client supports v{}",
+                path.display(),
+                version,
+                PLUGIN_API_VERSION
+            ));
+        }
+        let mut exports = Exports {
+            memory: instance
+                .get_memory(&mut store, "memory")
+                .ok_or_else(|| anyhow!("{} exports no memory", path.display()))?,
+            alloc: instance.get_typed_func(&mut store, "alloc")?,
+            process_audio: instance.get_typed_func(&mut store, "process_audio").ok(),
+            filter_signaling: instance.get_typed_func(&mut store, "filter_signaling").ok(),
+            panel: instance.get_typed_func(&mut store, "panel").ok(),
+            store,
+        };
+        let name = match instance.get_typed_func::<(), i64>(&mut exports.store, "name") {
+            Ok(name) => {
+                let packed = name.call(&mut exports.store, ())?;
+                String::from_utf8(exports.read(packed)?)?
+            }
+            Err(_) => path.file_stem().unwrap_or_default().to_string_lossy().to_string(),
+        };
+        Ok(Self {
+            name,
+            module: Arc::new(Mutex::new(exports)),
+        })
+    }
+
+    fn hooks(&self) -> WasmHooks {
+        WasmHooks {
+            name: self.name.clone(),
+            module: self.module.clone(),
+        }
+    }
+}
+
+impl Exports {
+    fn write(&mut self, bytes: &[u8]) -> Result<i32> {
+        let pointer = self.alloc.call(&mut self.store, bytes.len() as i32)?;
+        self.memory.write(&mut self.store, pointer as usize, bytes)?;
+        Ok(pointer)
+    }
+
+    fn read(&self, packed: i64) -> Result<Vec<u8>> {
+        let (pointer, len) = ((packed >> 32) as u32 as usize, packed as u32 as usize);
+        let mut bytes = vec![0; len];
+        self.memory.read(&self.store, pointer, &mut bytes)?;
+        Ok(bytes)
+    }
+
+    fn process_audio(&mut self, source: &TapSource, samples: &mut [f32], sample_rate: u32) -> Result<()> {
+        let Some(process) = self.process_audio.clone() else { return Ok(()) };
+        let bytes: Vec<u8> = samples.iter().flat_map(|sample| sample.to_le_bytes()).collect();
+        let pointer = self.write(&bytes)?;
+        let incoming = matches!(source, TapSource::Remote(_)) as i32;
+        process.call(&mut self.store, (pointer, samples.len() as i32, sample_rate as i32, incoming))?;
+        let mut processed = vec![0; bytes.len()];
+        self.memory.read(&self.store, pointer as usize, &mut processed)?;
+        // Only once everything is read, so a failure leaves the frame untouched
+        for (sample, bytes) in samples.iter_mut().zip(processed.chunks_exact(4)) {
+            *sample = f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        }
+        Ok(())
+    }
+
+    fn filter(&mut self, message: SignalingMessage, outgoing: bool) -> Result<Option<SignalingMessage>> {
+        let Some(filter) = self.filter_signaling.clone() else { return Ok(Some(message)) };
+        let json = serde_json::to_vec(&message)?;
+        let pointer = self.write(&json)?;
+        match filter.call(&mut self.store, (pointer, json.len() as i32, outgoing as i32))? {
+            -1 => Ok(Some(message)),
+            0 => Ok(None),
+            packed => Ok(Some(serde_json::from_slice(&self.read(packed)?)?)),
+        }
+    }
+
+    fn panel(&mut self) -> Result<Option<PluginPanel>> {
+        let Some(panel) = self.panel.clone() else { return Ok(None) };
+        match panel.call(&mut self.store, ())? {
+            0 => Ok(None),
+            packed => Ok(Some(serde_json::from_slice(&self.read(packed)?)?)),
+        }
+    }
+}
+
+struct WasmHooks {
+    name: String,
+    module: Arc<Mutex<Exports>>,
+}
+
+// A module that traps or returns garbage leaves the audio or message as it was
+impl AudioProcessor for WasmHooks {
+    fn process(&self, source: &TapSource, samples: &mut [f32], sample_rate: u32) {
+        let Ok(mut module) = self.module.lock() else { return };
+        if let Err(e) = module.process_audio(source, samples, sample_rate) {
+            eprintln!("Plugin {} failed to process audio: {}", self.name, e);
+        }
+    }
+}
+
+impl SignalingFilter for WasmHooks {
+    fn outgoing(&self, message: SignalingMessage) -> Option<SignalingMessage> {
+        self.filter(message, true)
+    }
+
+    fn incoming(&self, message: SignalingMessage) -> Option<SignalingMessage> {
+        self.filter(message, false)
+    }
+}
+
+impl WasmHooks {
+    fn filter(&self, message: SignalingMessage, outgoing: bool) -> Option<SignalingMessage> {
+        let Ok(mut module) = self.module.lock() else { return Some(message) };
+        match module.filter(message.clone(), outgoing) {
+            Ok(filtered) => filtered,
+            Err(e) => {
+                eprintln!("Plugin {} failed to filter a signaling message: {}", self.name, e);
+                Some(message)
+            }
+        }
+    }
+}
+
+impl Plugin for WasmPlugin {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn audio_processor(&self) -> Option<Arc<dyn AudioProcessor>> {
+        let exported = self.module.lock().ok()?.process_audio.is_some();
+        exported.then(|| Arc::new(self.hooks()) as Arc<dyn AudioProcessor>)
+    }
+
+    fn signaling_filter(&self) -> Option<Arc<dyn SignalingFilter>> {
+        let exported = self.module.lock().ok()?.filter_signaling.is_some();
+        exported.then(|| Arc::new(self.hooks()) as Arc<dyn SignalingFilter>)
+    }
+
+    fn panel(&self) -> Option<PluginPanel> {
+        let mut module = self.module.lock().ok()?;
+        module.panel().unwrap_or_else(|e| {
+            eprintln!("Plugin {} failed to draw its panel: {}", self.name, e);
+            None
+        })
+    }
+}
@@ -0,0 +1,167 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+// std's clocks panic in the browser
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+#[cfg(target_arch = "wasm32")]
+use web_time::Instant;
+
+// No input for this long and the user shows as away
+pub const IDLE_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+// When to show as away, and when to give up on a room nobody else is in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IdleConfig {
+    // Minutes without input; 0 never shows as away
+    pub away_after_mins: u32,
+    // Minutes alone in the room while away before leaving it; 0 stays
+    pub leave_empty_room_after_mins: u32,
+}
+
+impl Default for IdleConfig {
+    fn default() -> Self {
+        Self {
+            away_after_mins: (IDLE_TIMEOUT.as_secs() / 60) as u32,
+            leave_empty_room_after_mins: 0,
+        }
+    }
+}
+
+impl IdleConfig {
+    pub fn away_after(&self) -> Option<Duration> {
+        minutes(self.away_after_mins)
+    }
+
+    pub fn leave_empty_room_after(&self) -> Option<Duration> {
+        minutes(self.leave_empty_room_after_mins)
+    }
+}
+
+fn minutes(mins: u32) -> Option<Duration> {
+    (mins > 0).then(|| Duration::from_secs(mins as u64 * 60))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PresenceStatus {
+    Online,
+    Away,
+    InCall,
+}
+
+impl PresenceStatus {
+    // CSS class and i18n key suffix
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PresenceStatus::Online => "online",
+            PresenceStatus::Away => "away",
+            PresenceStatus::InCall => "in_call",
+        }
+    }
+}
+
+// Works out our own status from calls and input, and which changes still need to be
+// broadcast. Being in a call wins over being idle.
+#[derive(Debug, Clone)]
+pub struct PresenceTracker {
+    // None never goes away, e.g. headless use
+    idle_timeout: Option<Duration>,
+    last_activity: Instant,
+    in_call: bool,
+    // Since when nobody else has been in the room
+    alone_since: Option<Instant>,
+    published: Option<PresenceStatus>,
+}
+
+impl PresenceTracker {
+    pub fn new(idle_timeout: Option<Duration>) -> Self {
+        Self {
+            idle_timeout,
+            last_activity: Instant::now(),
+            in_call: false,
+            alone_since: None,
+            published: None,
+        }
+    }
+
+    pub fn activity(&mut self, now: Instant) {
+        self.last_activity = now;
+    }
+
+    pub fn set_in_call(&mut self, in_call: bool) {
+        self.in_call = in_call;
+    }
+
+    // Settings changed; takes effect from the next status check
+    pub fn set_idle_timeout(&mut self, idle_timeout: Option<Duration>) {
+        self.idle_timeout = idle_timeout;
+    }
+
+    // From each peer list, and false once we've left the room
+    pub fn set_alone(&mut self, alone: bool, now: Instant) {
+        if !alone {
+            self.alone_since = None;
+        } else if self.alone_since.is_none() {
+            self.alone_since = Some(now);
+        }
+    }
+
+    // Away and alone in the room for `after`: nobody is around to talk to or to notice
+    // we're gone
+    pub fn should_leave_room(&self, after: Duration, now: Instant) -> bool {
+        self.status(now) == PresenceStatus::Away
+            && self.alone_since.map_or(false, |since| now.duration_since(since) >= after)
+    }
+
+    pub fn status(&self, now: Instant) -> PresenceStatus {
+        if self.in_call {
+            return PresenceStatus::InCall;
+        }
+        match self.idle_timeout {
+            Some(timeout) if now.duration_since(self.last_activity) >= timeout => PresenceStatus::Away,
+            _ => PresenceStatus::Online,
+        }
+    }
+
+    // The status to broadcast, if it differs from what the room last heard
+    pub fn take_change(&mut self, now: Instant) -> Option<PresenceStatus> {
+        let status = self.status(now);
+        if self.published == Some(status) {
+            return None;
+        }
+        self.published = Some(status);
+        Some(status)
+    }
+
+    // After (re)joining the room nobody there knows our status
+    pub fn reset(&mut self) {
+        self.published = None;
+    }
+}
+
+// Last status heard from each peer
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PeerPresence {
+    statuses: HashMap<String, PresenceStatus>,
+}
+
+impl PeerPresence {
+    pub fn update(&mut self, peer_id: String, status: PresenceStatus) {
+        self.statuses.insert(peer_id, status);
+    }
+
+    pub fn get(&self, peer_id: &str) -> Option<PresenceStatus> {
+        self.statuses.get(peer_id).copied()
+    }
+
+    // Peers no longer in the room; their old status would be misleading
+    pub fn retain_peers(&mut self, peers: &[String]) {
+        self.statuses.retain(|peer_id, _| peers.contains(peer_id));
+    }
+
+    pub fn remove(&mut self, peer_id: &str) {
+        self.statuses.remove(peer_id);
+    }
+}
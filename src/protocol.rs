@@ -0,0 +1,346 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::identity::{self, Identity};
+use crate::presence::PresenceStatus;
+use crate::room::ConsentPolicy;
+use crate::server_config::ServerConfig;
+
+// Bumped whenever a message changes shape. Messages from before versioning carry no
+// protocol_version field and deserialize as version 0.
+pub const PROTOCOL_VERSION: u32 = 1;
+pub const MIN_PROTOCOL_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "message_type")]
+pub enum SignalingMessage {
+    Join {
+        room_id: String,
+        peer_id: String,
+        #[serde(default)]
+        protocol_version: u32,
+    },
+    Disconnect {
+        room_id: String,
+        peer_id: String,
+    },
+    PeerList {
+        peers: Vec<String>,
+    },
+    Offer {
+        room_id: String,
+        sdp: String,
+        from_peer: String,
+        to_peer: String,
+    },
+    Answer {
+        room_id: String,
+        sdp: String,
+        from_peer: String,
+        to_peer: String,
+    },
+    IceCandidate {
+        room_id: String,
+        candidate: String,
+        from_peer: String,
+        to_peer: String,
+    },
+    RequestPeerList,
+    InitiateCall {
+        peer_id: String,
+        room_id: String,
+    },
+    MediaError {
+        error_type: String,
+        description: String,
+        peer_id: String,
+    },
+    // Goes to the whole room unless to_peers narrows it down, as it must while another
+    // call with someone in the room is on hold
+    EndCall {
+        room_id: String,
+        peer_id: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        to_peers: Option<Vec<String>>,
+    },
+    CallRequest {
+        room_id: String,
+        from_peer: String,
+        to_peers: Vec<String>,
+        #[serde(default)]
+        protocol_version: u32,
+    },
+    CallResponse {
+        room_id: String,
+        from_peer: String,
+        to_peer: String,
+        accepted: bool,
+    },
+    Error {
+        message: String,
+    },
+    ConnectionLost {
+        peer_id: String,
+    },
+    ChatMessage {
+        room_id: String,
+        from_peer: String,
+        text: String,
+    },
+    // Sent back to a peer whose protocol version we can't talk to
+    ProtocolMismatch {
+        from_peer: String,
+        to_peer: String,
+        protocol_version: u32,
+        min_protocol_version: u32,
+    },
+    // From the server after Join; see ServerConfig
+    ServerConfig {
+        config: ServerConfig,
+    },
+    // Broadcast to the room whenever our status changes
+    Presence {
+        room_id: String,
+        peer_id: String,
+        status: PresenceStatus,
+    },
+    // From the server at Join, and again whenever it changes: the peer all media in the
+    // room goes through. None means peers connect directly.
+    MediaRelay {
+        room_id: String,
+        relay_peer: Option<String>,
+    },
+    // From a relay, before the offer that adds a forwarded stream: the peer whose audio
+    // stream_id carries. A peer may also name its own stream if it doesn't go by its
+    // peer ID.
+    TrackInfo {
+        room_id: String,
+        from_peer: String,
+        to_peer: String,
+        stream_id: String,
+        peer_id: String,
+    },
+    // Asks everyone else in the call before from_peer starts recording
+    RecordingConsentRequest {
+        room_id: String,
+        from_peer: String,
+        to_peers: Vec<String>,
+        request_id: String,
+        #[serde(default)]
+        policy: ConsentPolicy,
+    },
+    RecordingConsentResponse {
+        room_id: String,
+        from_peer: String,
+        to_peer: String,
+        request_id: String,
+        granted: bool,
+    },
+    // Blind transfer: to_peer hangs up on from_peer and calls target instead
+    Transfer {
+        room_id: String,
+        from_peer: String,
+        to_peer: String,
+        target: String,
+    },
+    // Conference merge: from_peer is in separate calls with the to_peers and pulls them
+    // together. Each of them hangs up on from_peer, joins target_room and takes from_peer's
+    // call there.
+    MergeCalls {
+        room_id: String,
+        from_peer: String,
+        to_peers: Vec<String>,
+        target_room: String,
+    },
+}
+
+impl SignalingMessage {
+    // The peer the message claims to come from. ConnectionLost is sent by the server
+    // about another peer, so it has none.
+    pub fn sender(&self) -> Option<&str> {
+        match self {
+            SignalingMessage::Join { peer_id, .. }
+            | SignalingMessage::Disconnect { peer_id, .. }
+            | SignalingMessage::InitiateCall { peer_id, .. }
+            | SignalingMessage::MediaError { peer_id, .. }
+            | SignalingMessage::EndCall { peer_id, .. }
+            | SignalingMessage::Presence { peer_id, .. } => Some(peer_id),
+            SignalingMessage::Offer { from_peer, .. }
+            | SignalingMessage::Answer { from_peer, .. }
+            | SignalingMessage::IceCandidate { from_peer, .. }
+            | SignalingMessage::CallRequest { from_peer, .. }
+            | SignalingMessage::CallResponse { from_peer, .. }
+            | SignalingMessage::ChatMessage { from_peer, .. }
+            | SignalingMessage::ProtocolMismatch { from_peer, .. }
+            | SignalingMessage::TrackInfo { from_peer, .. }
+            | SignalingMessage::RecordingConsentRequest { from_peer, .. }
+            | SignalingMessage::RecordingConsentResponse { from_peer, .. }
+            | SignalingMessage::Transfer { from_peer, .. }
+            | SignalingMessage::MergeCalls { from_peer, .. } => Some(from_peer),
+            SignalingMessage::PeerList { .. }
+            | SignalingMessage::RequestPeerList
+            | SignalingMessage::Error { .. }
+            | SignalingMessage::ServerConfig { .. }
+            | SignalingMessage::MediaRelay { .. }
+            | SignalingMessage::ConnectionLost { .. } => None,
+        }
+    }
+}
+
+// Signed frames carry the message's own fields, which the server routes by, plus these.
+// The payload is the exact bytes the signature covers: the message with a send time and
+// a nonce. Receivers act on the payload, never on the routing copy next to it.
+const PAYLOAD_FIELD: &str = "payload";
+const PUBLIC_KEY_FIELD: &str = "public_key";
+const SIGNATURE_FIELD: &str = "signature";
+const SENT_AT_FIELD: &str = "sent_at";
+const NONCE_FIELD: &str = "nonce";
+
+// How far a signed frame's send time may be from ours, either way, before it is taken
+// for a replay. Generous, since it is also the clock skew peers can have.
+pub const REPLAY_WINDOW: Duration = Duration::from_secs(300);
+
+// The JSON frame for a message, signed when there is an identity
+pub fn encode_frame(msg: &SignalingMessage, identity: Option<&Identity>) -> Result<String> {
+    let Some(identity) = identity else {
+        return Ok(serde_json::to_string(msg)?);
+    };
+    let mut signed = serde_json::to_value(msg)?;
+    let object = signed.as_object_mut().ok_or_else(|| anyhow!("Signaling message is not a JSON object"))?;
+    object.insert(SENT_AT_FIELD.to_string(), unix_millis().into());
+    object.insert(NONCE_FIELD.to_string(), format!("{:032x}", rand::random::<u128>()).into());
+    let payload = signed.to_string();
+
+    let mut value = serde_json::to_value(msg)?;
+    let object = value.as_object_mut().ok_or_else(|| anyhow!("Signaling message is not a JSON object"))?;
+    object.insert(PUBLIC_KEY_FIELD.to_string(), identity.public_key_hex().into());
+    object.insert(SIGNATURE_FIELD.to_string(), identity.sign(payload.as_bytes()).into());
+    object.insert(PAYLOAD_FIELD.to_string(), payload.into());
+    Ok(value.to_string())
+}
+
+// Parses frames and checks their signatures, remembering the nonces of recent signed
+// frames to drop repeats. Frames that fail come back as Error, like unparseable ones,
+// so a forged or replayed message never reaches the call logic. One per connection.
+#[derive(Debug, Default)]
+pub struct FrameDecoder {
+    // Nonce to send time, for frames still inside the replay window
+    seen: HashMap<String, u64>,
+}
+
+impl FrameDecoder {
+    pub fn decode(&mut self, text: &str) -> SignalingMessage {
+        self.decode_at(text, unix_millis())
+    }
+
+    // With the current time in milliseconds since the epoch given
+    pub fn decode_at(&mut self, text: &str, now: u64) -> SignalingMessage {
+        match self.decode_signed(text, now) {
+            Ok(msg) => msg,
+            Err(message) => SignalingMessage::Error { message },
+        }
+    }
+
+    fn decode_signed(&mut self, text: &str, now: u64) -> std::result::Result<SignalingMessage, String> {
+        let mut value: serde_json::Value = serde_json::from_str(text)
+            .map_err(|e| format!("Received a non-JSON signaling frame: {}", e))?;
+        let (payload, public_key, signature) = match value.as_object_mut() {
+            Some(object) => (
+                object.remove(PAYLOAD_FIELD),
+                object.remove(PUBLIC_KEY_FIELD),
+                object.remove(SIGNATURE_FIELD),
+            ),
+            None => (None, None, None),
+        };
+        let msg = serde_json::from_value::<SignalingMessage>(value)
+            .map_err(|e| describe_parse_failure(text, &e))?;
+        let Some(sender) = msg.sender() else { return Ok(msg) };
+        let envelope = (
+            payload.as_ref().and_then(|v| v.as_str()),
+            public_key.as_ref().and_then(|v| v.as_str()),
+            signature.as_ref().and_then(|v| v.as_str()),
+        );
+        match envelope {
+            (Some(payload), Some(public_key), Some(signature)) => {
+                identity::verify(sender, public_key, signature, payload.as_bytes())
+                    .map_err(|e| format!("Dropped a message claiming to be from {}: {}", sender, e))?;
+                let signed = self.check_fresh(sender, payload, now)?;
+                // The server routed by the plain copy, so it has to be the message that was signed
+                if signed != msg {
+                    return Err(format!("Dropped a message from {} that differs from what they signed", sender));
+                }
+                Ok(signed)
+            }
+            // Random IDs from clients without an identity are still accepted
+            _ if identity::is_identity_peer_id(sender) => {
+                Err(format!("Dropped an unsigned message claiming to be from {}", sender))
+            }
+            _ => Ok(msg),
+        }
+    }
+
+    // The signed message, once its send time and nonce show it is not a replay
+    fn check_fresh(&mut self, sender: &str, payload: &str, now: u64) -> std::result::Result<SignalingMessage, String> {
+        let mut signed: serde_json::Value =
+            serde_json::from_str(payload).map_err(|e| format!("Signed payload from {} is not JSON: {}", sender, e))?;
+        let object = signed
+            .as_object_mut()
+            .ok_or_else(|| format!("Signed payload from {} is not a JSON object", sender))?;
+        let sent_at = object.remove(SENT_AT_FIELD).and_then(|v| v.as_u64());
+        let nonce = object.remove(NONCE_FIELD).and_then(|v| v.as_str().map(str::to_string));
+        let (Some(sent_at), Some(nonce)) = (sent_at, nonce) else {
+            return Err(format!("Dropped a signed message from {} without a send time or nonce", sender));
+        };
+        let window = REPLAY_WINDOW.as_millis() as u64;
+        if sent_at.abs_diff(now) > window {
+            return Err(format!("Dropped a stale or replayed message from {}", sender));
+        }
+        self.seen.retain(|_, seen_at| seen_at.abs_diff(now) <= window);
+        if self.seen.insert(nonce, sent_at).is_some() {
+            return Err(format!("Dropped a replayed message from {}", sender));
+        }
+        serde_json::from_value(signed).map_err(|e| format!("Signed payload from {} does not parse: {}", sender, e))
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn unix_millis() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |since| since.as_millis() as u64)
+}
+
+// The browser has no system clock through std
+#[cfg(target_arch = "wasm32")]
+fn unix_millis() -> u64 {
+    js_sys::Date::now() as u64
+}
+
+pub fn check_protocol_version(peer: &str, version: u32) -> Result<()> {
+    if version < MIN_PROTOCOL_VERSION {
+        return Err(anyhow!(
+            "{} uses signaling protocol v{}, but this client requires v{} or newer; they need to update",
+            peer, version, MIN_PROTOCOL_VERSION
+        ));
+    }
+    Ok(())
+}
+
+// Explains why a frame could not be parsed instead of dropping it silently
+fn describe_parse_failure(text: &str, error: &serde_json::Error) -> String {
+    match serde_json::from_str::<serde_json::Value>(text) {
+        Ok(value) => {
+            let message_type = value["message_type"].as_str().unwrap_or("<missing>");
+            match value["protocol_version"].as_u64() {
+                Some(version) if version > PROTOCOL_VERSION as u64 => format!(
+                    "Received {} using signaling protocol v{}, newer than this client's v{}; please update",
+                    message_type, version, PROTOCOL_VERSION
+                ),
+                _ => format!("Unsupported or malformed {} message: {}", message_type, error),
+            }
+        }
+        Err(_) => format!("Received a non-JSON signaling frame: {}", error),
+    }
+}
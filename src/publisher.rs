@@ -0,0 +1,253 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+use crate::metrics::ConnectionQuality;
+
+// A call counts as poor below this score, and recovered once back at RECOVERED_SCORE,
+// so a call hovering at the edge doesn't alert on every stats tick
+const POOR_SCORE: u8 = 50;
+const RECOVERED_SCORE: u8 = 70;
+
+// Where call events go, for home automation and monitoring. Nothing is sent unless a
+// webhook or broker is set.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PublisherConfig {
+    // Gets a JSON POST per event
+    pub webhook_url: Option<String>,
+    // Sent as a bearer token, for webhooks that check one
+    pub webhook_token: Option<String>,
+    // Needs a build with the mqtt feature
+    pub mqtt: Option<MqttConfig>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MqttConfig {
+    pub host: String,
+    pub port: u16,
+    // Events go to <topic>/<event>, e.g. webrtc-client/incoming_call
+    pub topic: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+}
+
+impl Default for MqttConfig {
+    fn default() -> Self {
+        Self {
+            host: "localhost".to_string(),
+            port: 1883,
+            topic: "webrtc-client".to_string(),
+            username: None,
+            password: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum CallEvent {
+    IncomingCall { room: String, from_peer: String },
+    // Media is flowing
+    Answered { room: String, participants: Vec<String> },
+    Ended {
+        room: String,
+        participants: Vec<String>,
+        answered: bool,
+        duration_secs: u64,
+    },
+    QualityAlert {
+        room: String,
+        quality_score: u8,
+        packet_loss_rate: f64,
+        round_trip_time: f64,
+        jitter: f64,
+    },
+    QualityRecovered { room: String, quality_score: u8 },
+}
+
+impl CallEvent {
+    // Matches the serialized "event" field
+    pub fn name(&self) -> &'static str {
+        match self {
+            CallEvent::IncomingCall { .. } => "incoming_call",
+            CallEvent::Answered { .. } => "answered",
+            CallEvent::Ended { .. } => "ended",
+            CallEvent::QualityAlert { .. } => "quality_alert",
+            CallEvent::QualityRecovered { .. } => "quality_recovered",
+        }
+    }
+
+    // The published JSON: the event's fields plus who sent it and when
+    pub fn payload(&self, client: &str, at: SystemTime) -> Value {
+        let mut payload = serde_json::to_value(self).unwrap_or_default();
+        payload["client"] = Value::from(client);
+        payload["timestamp_ms"] = Value::from(at.duration_since(UNIX_EPOCH).map_or(0, |since| since.as_millis() as u64));
+        payload
+    }
+}
+
+// Turns the stream of quality readings into one alert when a call goes bad and one
+// notice when it recovers
+#[derive(Debug, Default)]
+pub struct QualityAlarm {
+    raised: bool,
+}
+
+impl QualityAlarm {
+    pub fn check(&mut self, room: &str, quality: &ConnectionQuality) -> Option<CallEvent> {
+        if !self.raised && quality.quality_score < POOR_SCORE {
+            self.raised = true;
+            return Some(CallEvent::QualityAlert {
+                room: room.to_string(),
+                quality_score: quality.quality_score,
+                packet_loss_rate: quality.packet_loss_rate,
+                round_trip_time: quality.round_trip_time,
+                jitter: quality.jitter,
+            });
+        }
+        if self.raised && quality.quality_score >= RECOVERED_SCORE {
+            self.raised = false;
+            return Some(CallEvent::QualityRecovered {
+                room: room.to_string(),
+                quality_score: quality.quality_score,
+            });
+        }
+        None
+    }
+}
+
+// Sends events in the background, in order. A webhook or broker that's down costs us
+// the events, never the call.
+pub struct EventPublisher {
+    client: String,
+    events: mpsc::UnboundedSender<(&'static str, Value)>,
+    task: JoinHandle<()>,
+}
+
+impl EventPublisher {
+    // None when nothing is configured. Must run inside the tokio runtime.
+    pub fn start(config: &PublisherConfig, client: &str) -> Result<Option<Self>> {
+        if config.webhook_url.is_none() && config.mqtt.is_none() {
+            return Ok(None);
+        }
+        let mqtt = match config.mqtt {
+            Some(ref mqtt) => Some(mqtt::Connection::connect(mqtt, client)?),
+            None => None,
+        };
+        let webhook = config.webhook_url.clone().map(|url| (url, config.webhook_token.clone()));
+        let (events, mut events_rx) = mpsc::unbounded_channel::<(&'static str, Value)>();
+        let task = tokio::spawn(async move {
+            let http = reqwest::Client::new();
+            while let Some((name, payload)) = events_rx.recv().await {
+                if let Some((ref url, ref token)) = webhook {
+                    let mut request = http.post(url).json(&payload).timeout(Duration::from_secs(5));
+                    if let Some(token) = token {
+                        request = request.bearer_auth(token);
+                    }
+                    if let Err(e) = request.send().await.and_then(|response| response.error_for_status()) {
+                        eprintln!("Failed to post {} event: {}", name, e);
+                    }
+                }
+                if let Some(ref mqtt) = mqtt {
+                    mqtt.publish(name, &payload).await;
+                }
+            }
+        });
+        Ok(Some(Self {
+            client: client.to_string(),
+            events,
+            task,
+        }))
+    }
+
+    pub fn publish(&self, event: CallEvent) {
+        let payload = event.payload(&self.client, SystemTime::now());
+        let _ = self.events.send((event.name(), payload));
+    }
+}
+
+impl Drop for EventPublisher {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+#[cfg(feature = "mqtt")]
+mod mqtt {
+    use anyhow::Result;
+    use rumqttc::{AsyncClient, MqttOptions, QoS};
+    use serde_json::Value;
+    use std::time::Duration;
+    use tokio::task::JoinHandle;
+
+    use super::MqttConfig;
+
+    pub struct Connection {
+        client: AsyncClient,
+        topic: String,
+        event_loop: JoinHandle<()>,
+    }
+
+    impl Connection {
+        pub fn connect(config: &MqttConfig, client_id: &str) -> Result<Self> {
+            let mut options = MqttOptions::new(format!("webrtc-client-{}", client_id), &config.host, config.port);
+            options.set_keep_alive(Duration::from_secs(30));
+            if let (Some(username), Some(password)) = (&config.username, &config.password) {
+                options.set_credentials(username, password);
+            }
+            let (client, mut event_loop) = AsyncClient::new(options, 16);
+            // The event loop does the actual sending and reconnects on its own while polled
+            let event_loop = tokio::spawn(async move {
+                loop {
+                    if let Err(e) = event_loop.poll().await {
+                        eprintln!("MQTT connection error: {}", e);
+                        tokio::time::sleep(Duration::from_secs(5)).await;
+                    }
+                }
+            });
+            Ok(Self {
+                client,
+                topic: config.topic.trim_end_matches('/').to_string(),
+                event_loop,
+            })
+        }
+
+        pub async fn publish(&self, event: &str, payload: &Value) {
+            let topic = format!("{}/{}", self.topic, event);
+            if let Err(e) = self.client.publish(topic, QoS::AtLeastOnce, false, payload.to_string()).await {
+                eprintln!("Failed to publish {} event over MQTT: {}", event, e);
+            }
+        }
+    }
+
+    impl Drop for Connection {
+        fn drop(&mut self) {
+            self.event_loop.abort();
+        }
+    }
+}
+
+#[cfg(not(feature = "mqtt"))]
+mod mqtt {
+    use anyhow::{anyhow, Result};
+    use serde_json::Value;
+
+    use super::MqttConfig;
+
+    pub enum Connection {}
+
+    impl Connection {
+        pub fn connect(_config: &MqttConfig, _client_id: &str) -> Result<Self> {
+            Err(anyhow!("MQTT publishing is configured, but this build has no MQTT support (mqtt feature)"))
+        }
+
+        pub async fn publish(&self, _event: &str, _payload: &Value) {
+            match *self {}
+        }
+    }
+}
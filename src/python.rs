@@ -0,0 +1,207 @@
+// Python module over the engine, for scripting call scenarios in test suites. Build and
+// install it into the current virtualenv with `maturin develop` (see pyproject.toml):
+//
+//     from webrtc_client import CallEngine
+//
+//     alice = CallEngine("ws://localhost:8080", "qa", "alice", audio_backend="Mock")
+//     bob = CallEngine("ws://localhost:8080", "qa", "bob", auto_answer=True, audio_backend="Mock")
+//     alice.connect(); bob.connect()
+//     alice.wait_for("peer_list", lambda e: "bob" in e["peers"])
+//     alice.call(["bob"])
+//     alice.wait_for("remote_audio_started")
+//     assert alice.wait_for_quality(lambda q: q["quality_score"] >= 80)
+//
+// Events and stats come back as dicts shaped like the JSON of the C ABI (ffi.rs).
+use pyo3::exceptions::{PyRuntimeError, PyTimeoutError, PyValueError};
+use pyo3::prelude::*;
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+use crate::audio::AudioBackendKind;
+use crate::ffi::{FfiCommand, FfiConfig, FfiEngine};
+
+// Stats are refreshed once a second; no point asking more often
+const QUALITY_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+#[pyclass(name = "CallEngine", module = "webrtc_client")]
+pub struct PyCallEngine {
+    // None after shutdown()
+    engine: Option<FfiEngine>,
+}
+
+impl PyCallEngine {
+    fn engine(&self) -> PyResult<&FfiEngine> {
+        self.engine.as_ref().ok_or_else(|| PyRuntimeError::new_err("The engine has been shut down"))
+    }
+
+    fn send(&self, command: FfiCommand) -> PyResult<()> {
+        self.engine()?.send(command).map_err(|e| PyRuntimeError::new_err(e.to_string()))
+    }
+}
+
+// Python passes seconds as a float; Duration panics on negative, NaN or huge ones
+fn deadline(timeout: f64) -> PyResult<(Duration, Instant)> {
+    let invalid = || PyValueError::new_err(format!("timeout must be a non-negative number of seconds, not {}", timeout));
+    let timeout = Duration::try_from_secs_f64(timeout).map_err(|_| invalid())?;
+    let deadline = Instant::now().checked_add(timeout).ok_or_else(invalid)?;
+    Ok((timeout, deadline))
+}
+
+fn to_python(py: Python<'_>, value: &impl Serialize) -> PyResult<PyObject> {
+    let json = serde_json::to_string(value).map_err(|e| PyValueError::new_err(e.to_string()))?;
+    Ok(py.import_bound("json")?.call_method1("loads", (json,))?.unbind())
+}
+
+#[pymethods]
+impl PyCallEngine {
+    // audio_backend is an AudioBackendKind name; "Mock" needs no sound hardware
+    #[new]
+    #[pyo3(signature = (signaling_url, room_id, peer_id, auto_answer = false, audio_backend = None))]
+    fn new(
+        signaling_url: String,
+        room_id: String,
+        peer_id: String,
+        auto_answer: bool,
+        audio_backend: Option<String>,
+    ) -> PyResult<Self> {
+        let audio_backend = match audio_backend {
+            Some(name) => serde_json::from_value::<AudioBackendKind>(serde_json::Value::String(name))
+                .map_err(|e| PyValueError::new_err(e.to_string()))?,
+            None => AudioBackendKind::default(),
+        };
+        let config = FfiConfig {
+            signaling_url,
+            room_id,
+            peer_id,
+            auto_answer,
+            audio_backend,
+            webrtc: Default::default(),
+            reconnect: Default::default(),
+            signaling_failover: Default::default(),
+            watch_network: false,
+        };
+        let engine = FfiEngine::start(config).map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
+        Ok(Self { engine: Some(engine) })
+    }
+
+    fn connect(&self) -> PyResult<()> {
+        self.send(FfiCommand::Connect)
+    }
+
+    fn call(&self, peers: Vec<String>) -> PyResult<()> {
+        self.send(FfiCommand::Call { peers })
+    }
+
+    #[pyo3(signature = (room_id, from_peer, accepted = true))]
+    fn answer(&self, room_id: String, from_peer: String, accepted: bool) -> PyResult<()> {
+        self.send(FfiCommand::Answer { room_id, from_peer, accepted })
+    }
+
+    fn hang_up(&self) -> PyResult<()> {
+        self.send(FfiCommand::HangUp)
+    }
+
+    fn transfer(&self, target: String) -> PyResult<()> {
+        self.send(FfiCommand::Transfer { target })
+    }
+
+    fn merge(&self) -> PyResult<()> {
+        self.send(FfiCommand::Merge)
+    }
+
+    fn set_muted(&self, muted: bool) -> PyResult<()> {
+        self.send(FfiCommand::SetMuted { muted })
+    }
+
+    fn send_chat(&self, text: String) -> PyResult<()> {
+        self.send(FfiCommand::SendChat { text })
+    }
+
+    fn send_dtmf(&self, digits: String) -> PyResult<()> {
+        self.send(FfiCommand::SendDtmf { digits })
+    }
+
+    fn network_changed(&self) -> PyResult<()> {
+        self.send(FfiCommand::NetworkChanged)
+    }
+
+    fn restart_ice(&self) -> PyResult<()> {
+        self.send(FfiCommand::RestartIce)
+    }
+
+    // The next event as a dict, or None if nothing happened within `timeout` seconds
+    #[pyo3(signature = (timeout = 1.0))]
+    fn next_event(&self, py: Python<'_>, timeout: f64) -> PyResult<Option<PyObject>> {
+        let (timeout, _) = deadline(timeout)?;
+        let engine = self.engine()?;
+        let event = py.allow_threads(|| engine.next_event(timeout));
+        event.map(|event| to_python(py, &event)).transpose()
+    }
+
+    // Skips events until one named `event` (e.g. "call_active") that `predicate` accepts,
+    // if given, and returns it. Raises TimeoutError after `timeout` seconds.
+    #[pyo3(signature = (event, predicate = None, timeout = 15.0))]
+    fn wait_for(&self, py: Python<'_>, event: &str, predicate: Option<PyObject>, timeout: f64) -> PyResult<PyObject> {
+        let (_, deadline) = deadline(timeout)?;
+        let engine = self.engine()?;
+        while let Some(left) = deadline.checked_duration_since(Instant::now()) {
+            let Some(next) = py.allow_threads(|| engine.next_event(left)) else { continue };
+            let next = to_python(py, &next)?;
+            if next.bind(py).get_item("event")?.extract::<String>()? != event {
+                continue;
+            }
+            match &predicate {
+                Some(predicate) if !predicate.call1(py, (next.clone_ref(py),))?.is_truthy(py)? => continue,
+                _ => return Ok(next),
+            }
+        }
+        Err(PyTimeoutError::new_err(format!("No {} event within {}s", event, timeout)))
+    }
+
+    // The current call's latest stats as a dict (round_trip_time, jitter,
+    // packet_loss_rate, audio_level, bitrate, available_bitrate, quality_score), or None
+    // between calls
+    fn quality(&self, py: Python<'_>) -> PyResult<Option<PyObject>> {
+        self.engine()?.quality().map(|quality| to_python(py, &quality)).transpose()
+    }
+
+    // Polls the stats until `predicate` accepts them and returns them, or None if it
+    // never did within `timeout` seconds; for asserting that a call settles
+    #[pyo3(signature = (predicate, timeout = 15.0))]
+    fn wait_for_quality(&self, py: Python<'_>, predicate: PyObject, timeout: f64) -> PyResult<Option<PyObject>> {
+        let (_, deadline) = deadline(timeout)?;
+        loop {
+            if let Some(quality) = self.quality(py)? {
+                if predicate.call1(py, (quality.clone_ref(py),))?.is_truthy(py)? {
+                    return Ok(Some(quality));
+                }
+            }
+            if Instant::now() >= deadline {
+                return Ok(None);
+            }
+            py.allow_threads(|| std::thread::sleep(QUALITY_POLL_INTERVAL));
+        }
+    }
+
+    // Hangs up and disconnects; the engine can't be used afterwards
+    fn shutdown(&mut self, py: Python<'_>) {
+        if let Some(engine) = self.engine.take() {
+            py.allow_threads(|| drop(engine));
+        }
+    }
+
+    fn __enter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __exit__(&mut self, py: Python<'_>, _exc_type: PyObject, _exc: PyObject, _traceback: PyObject) {
+        self.shutdown(py);
+    }
+}
+
+#[pymodule]
+fn webrtc_client(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyCallEngine>()?;
+    m.add("ABI_VERSION", crate::ffi::WEBRTC_CLIENT_ABI_VERSION)?;
+    Ok(())
+}
@@ -0,0 +1,65 @@
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Backoff {
+    // Every retry waits initial_delay
+    Constant,
+    // initial_delay, 2x, 3x, ...
+    Linear,
+    // initial_delay, 2x, 4x, ...
+    Exponential,
+}
+
+// How hard to try getting back after signaling or a call's media path drops. Shared by
+// the signaling reconnect loop and ICE-restart call resumption.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReconnectPolicy {
+    pub max_attempts: u32,
+    pub initial_delay_ms: u64,
+    // Upper bound for a single wait, whatever the backoff curve says
+    pub max_delay_ms: u64,
+    pub backoff: Backoff,
+    // Give up once the connection has been down this long, even with attempts left
+    pub deadline_ms: u64,
+    // Keep calls alive across drops; off, a dropped call ends and only signaling comes back
+    pub resume_calls: bool,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_delay_ms: 1000,
+            max_delay_ms: 8000,
+            backoff: Backoff::Exponential,
+            deadline_ms: 30_000,
+            resume_calls: true,
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    // Wait before the given retry (1 = first retry)
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let step = attempt.max(1) as u64;
+        let delay = match self.backoff {
+            Backoff::Constant => self.initial_delay_ms,
+            Backoff::Linear => self.initial_delay_ms.saturating_mul(step),
+            Backoff::Exponential => self
+                .initial_delay_ms
+                .saturating_mul(1u64.checked_shl(step as u32 - 1).unwrap_or(u64::MAX)),
+        };
+        Duration::from_millis(delay.min(self.max_delay_ms))
+    }
+
+    pub fn deadline(&self) -> Duration {
+        Duration::from_millis(self.deadline_ms)
+    }
+
+    // Whether the given retry may still be made after being down for `elapsed`
+    pub fn allows(&self, attempt: u32, elapsed: Duration) -> bool {
+        attempt <= self.max_attempts && elapsed + self.delay(attempt) <= self.deadline()
+    }
+}
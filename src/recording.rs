@@ -0,0 +1,366 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::{broadcast, mpsc};
+
+use crate::audio::tap::{TapConfig, TapFrame, TapSink, TapSource};
+use crate::audio::wav::WavWriter;
+use crate::audio::codec::{resample, PLAYBACK_SAMPLE_RATE};
+use crate::audio::{level_dbfs, AudioStreamHandle, FRAME_DURATION};
+use crate::config::config_dir;
+use crate::webrtc::WebRTCClient;
+
+// How long the mixdown waits for every participant's frames before writing a stretch
+const MIX_DELAY: Duration = Duration::from_millis(200);
+// A participant's frame arriving this close to where its previous one ended follows on
+// directly
+const MIX_JITTER: Duration = Duration::from_millis(60);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RecordingConfig {
+    // None is "recordings" next to the settings file
+    pub directory: Option<PathBuf>,
+    // Record only while someone talks, one file per stretch of speech. For rooms used
+    // like a radio channel, where hours of silence aren't worth keeping.
+    pub voice_activated: bool,
+    // A file per participant next to the mixdown, for editing each voice on its own
+    pub separate_tracks: bool,
+    // Speech needed before a segment opens, so clicks and breaths don't
+    pub start_after_ms: u64,
+    // Silence that closes the segment
+    pub pause_after_ms: u64,
+    // Frames louder than this count as speech
+    pub threshold_dbfs: f32,
+}
+
+impl Default for RecordingConfig {
+    fn default() -> Self {
+        Self {
+            directory: None,
+            voice_activated: false,
+            separate_tracks: false,
+            start_after_ms: 300,
+            pause_after_ms: 3000,
+            threshold_dbfs: -45.0,
+        }
+    }
+}
+
+impl RecordingConfig {
+    pub fn directory(&self) -> PathBuf {
+        self.directory.clone().unwrap_or_else(|| config_dir().join("recordings"))
+    }
+}
+
+// A finished file
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordingSegment {
+    // None for the mixdown
+    pub source: Option<TapSource>,
+    pub path: PathBuf,
+    pub started_at: SystemTime,
+    pub duration: Duration,
+}
+
+struct OpenSegment {
+    writer: WavWriter,
+    info: RecordingSegment,
+}
+
+// Writes one track, a participant or the mixdown, to WAV files named after the time
+// they start. Continuous recordings are a single file; voice-activated ones open a file
+// once speech has lasted start_after and close it after pause_after of silence.
+pub struct SegmentRecorder {
+    config: RecordingConfig,
+    directory: PathBuf,
+    source: Option<TapSource>,
+    segment: Option<OpenSegment>,
+    // The speech so far while waiting for start_after, written once the segment opens
+    onset: Vec<f32>,
+    silence: Duration,
+}
+
+impl SegmentRecorder {
+    pub fn new(config: RecordingConfig, directory: PathBuf, source: Option<TapSource>) -> Self {
+        Self {
+            config,
+            directory,
+            source,
+            segment: None,
+            onset: Vec::new(),
+            silence: Duration::ZERO,
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.segment.is_some()
+    }
+
+    // `at` is when the frame starts. Returns the segment this frame closed, if any.
+    pub fn push(&mut self, samples: &[f32], sample_rate: u32, at: SystemTime) -> Result<Option<RecordingSegment>> {
+        if samples.is_empty() || sample_rate == 0 {
+            return Ok(None);
+        }
+        let mut finished = None;
+        if self.segment.as_ref().is_some_and(|segment| segment.writer.sample_rate() != sample_rate) {
+            finished = self.finish()?;
+        }
+        let length = seconds(samples.len(), sample_rate);
+        let speaking = level_dbfs(samples) > self.config.threshold_dbfs;
+
+        if self.segment.is_none() {
+            if self.config.voice_activated {
+                if !speaking {
+                    self.onset.clear();
+                    return Ok(finished);
+                }
+                self.onset.extend_from_slice(samples);
+                if seconds(self.onset.len(), sample_rate) < Duration::from_millis(self.config.start_after_ms) {
+                    return Ok(finished);
+                }
+            } else {
+                self.onset.extend_from_slice(samples);
+            }
+            let started_at = at + length - seconds(self.onset.len(), sample_rate);
+            self.open(started_at, sample_rate)?;
+            let onset = std::mem::take(&mut self.onset);
+            self.write(&onset, sample_rate)?;
+            return Ok(finished);
+        }
+
+        self.write(samples, sample_rate)?;
+        self.silence = if speaking { Duration::ZERO } else { self.silence + length };
+        if self.config.voice_activated && self.silence >= Duration::from_millis(self.config.pause_after_ms) {
+            return Ok(self.finish()?.or(finished));
+        }
+        Ok(finished)
+    }
+
+    // Closes the open segment, e.g. when the call ends
+    pub fn finish(&mut self) -> Result<Option<RecordingSegment>> {
+        self.onset.clear();
+        self.silence = Duration::ZERO;
+        let Some(segment) = self.segment.take() else { return Ok(None) };
+        segment.writer.finish()?;
+        Ok(Some(segment.info))
+    }
+
+    fn open(&mut self, started_at: SystemTime, sample_rate: u32) -> Result<()> {
+        let label = match self.source {
+            None => "mix".to_string(),
+            Some(TapSource::Local) => "local".to_string(),
+            Some(TapSource::Remote(ref peer)) => file_safe(peer),
+        };
+        let path = self.directory.join(format!("{}-{}.wav", file_timestamp(started_at), label));
+        self.segment = Some(OpenSegment {
+            writer: WavWriter::create(&path, sample_rate)?,
+            info: RecordingSegment {
+                source: self.source.clone(),
+                path,
+                started_at,
+                duration: Duration::ZERO,
+            },
+        });
+        Ok(())
+    }
+
+    fn write(&mut self, samples: &[f32], sample_rate: u32) -> Result<()> {
+        if let Some(ref mut segment) = self.segment {
+            segment.writer.write(samples)?;
+            segment.info.duration += seconds(samples.len(), sample_rate);
+        }
+        Ok(())
+    }
+}
+
+fn seconds(samples: usize, sample_rate: u32) -> Duration {
+    Duration::from_secs_f64(samples as f64 / sample_rate as f64)
+}
+
+fn file_safe(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect()
+}
+
+// UTC, sortable and without characters file systems object to: 2024-05-01T13-45-07Z
+pub fn file_timestamp(time: SystemTime) -> String {
+    let secs = time.duration_since(UNIX_EPOCH).map_or(0, |since| since.as_secs());
+    let (days, rest) = (secs / 86_400, secs % 86_400);
+    // Days since the epoch to a civil date (Howard Hinnant's algorithm)
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}-{:02}-{:02}Z",
+        year,
+        month,
+        day,
+        rest / 3600,
+        rest % 3600 / 60,
+        rest % 60
+    )
+}
+
+// Mixes every participant onto one timeline. Frames are placed by when they arrived,
+// and a participant's consecutive frames are kept back to back so arrival jitter
+// doesn't click.
+pub struct Mixdown {
+    sample_rate: u32,
+    started: Option<SystemTime>,
+    // Samples already taken
+    taken: u64,
+    // Mixed samples from `taken` on
+    pending: Vec<f32>,
+    // Where each participant's next frame goes
+    cursors: HashMap<TapSource, u64>,
+}
+
+impl Mixdown {
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            sample_rate,
+            started: None,
+            taken: 0,
+            pending: Vec::new(),
+            cursors: HashMap::new(),
+        }
+    }
+
+    pub fn add(&mut self, source: &TapSource, samples: &[f32], sample_rate: u32, at: SystemTime) {
+        let samples = resample(samples, sample_rate, self.sample_rate);
+        self.started.get_or_insert(at);
+        let arrived = self.position(at);
+        let tolerance = self.sample_rate as u64 * MIX_JITTER.as_millis() as u64 / 1000;
+        let start = match self.cursors.get(source) {
+            Some(&cursor) if cursor.abs_diff(arrived) <= tolerance => cursor,
+            // First frame, or back after a gap
+            _ => arrived,
+        }
+        // Too late for what was already taken; play it as soon as possible instead
+        .max(self.taken);
+        self.cursors.insert(source.clone(), start + samples.len() as u64);
+
+        let offset = (start - self.taken) as usize;
+        if self.pending.len() < offset + samples.len() {
+            self.pending.resize(offset + samples.len(), 0.0);
+        }
+        for (mixed, sample) in self.pending[offset..].iter_mut().zip(samples) {
+            *mixed += sample;
+        }
+    }
+
+    // The mix up to `until`, with when it starts. Frames arriving later than that for
+    // the same stretch of time are mixed in after it.
+    pub fn take_until(&mut self, until: SystemTime) -> Option<(SystemTime, Vec<f32>)> {
+        let end = self.position(until).saturating_sub(self.taken).min(self.pending.len() as u64);
+        self.take(end as usize)
+    }
+
+    pub fn take_all(&mut self) -> Option<(SystemTime, Vec<f32>)> {
+        self.take(self.pending.len())
+    }
+
+    fn take(&mut self, count: usize) -> Option<(SystemTime, Vec<f32>)> {
+        let started = self.started?;
+        if count == 0 {
+            return None;
+        }
+        let at = started + seconds(self.taken as usize, self.sample_rate);
+        self.taken += count as u64;
+        Some((at, self.pending.drain(..count).collect()))
+    }
+
+    fn position(&self, at: SystemTime) -> u64 {
+        let since = self.started.and_then(|started| at.duration_since(started).ok()).unwrap_or_default();
+        (since.as_secs_f64() * self.sample_rate as f64).round() as u64
+    }
+}
+
+// Records a call as a mixdown of everyone and, with separate_tracks, a file per
+// participant as well. Open segments are closed when this is dropped.
+pub struct CallRecorder {
+    segments: broadcast::Sender<RecordingSegment>,
+    _tap: AudioStreamHandle,
+}
+
+impl CallRecorder {
+    pub fn start(webrtc: &WebRTCClient, config: &RecordingConfig) -> Result<Self> {
+        let directory = config.directory();
+        fs::create_dir_all(&directory)?;
+        let (frames_tx, mut frames_rx) = mpsc::unbounded_channel::<(TapFrame, SystemTime)>();
+        let tap = webrtc.start_tap(&TapConfig {
+            sink: TapSink::Callback(Arc::new(move |frame: &TapFrame| {
+                let _ = frames_tx.send((frame.clone(), SystemTime::now()));
+            })),
+            include_local: true,
+        });
+
+        let (segments, _) = broadcast::channel(16);
+        let segments_tx = segments.clone();
+        let config = config.clone();
+        // File writes block, so they get their own thread. Dropping the tap drops the
+        // sender, which ends it.
+        std::thread::spawn(move || {
+            let report = |result: Result<Option<RecordingSegment>>| match result {
+                Ok(Some(segment)) => {
+                    let _ = segments_tx.send(segment);
+                }
+                Ok(None) => {}
+                Err(e) => eprintln!("Recording failed: {}", e),
+            };
+            let mut mixdown = Mixdown::new(PLAYBACK_SAMPLE_RATE);
+            let mut mix_recorder = SegmentRecorder::new(config.clone(), directory.clone(), None);
+            let mut tracks: HashMap<TapSource, SegmentRecorder> = HashMap::new();
+
+            while let Some((frame, at)) = frames_rx.blocking_recv() {
+                if config.separate_tracks {
+                    let track = tracks.entry(frame.source.clone()).or_insert_with(|| {
+                        SegmentRecorder::new(config.clone(), directory.clone(), Some(frame.source.clone()))
+                    });
+                    report(track.push(&frame.samples, frame.sample_rate, at));
+                }
+                mixdown.add(&frame.source, &frame.samples, frame.sample_rate, at);
+                if let Some((start, mixed)) = at.checked_sub(MIX_DELAY).and_then(|until| mixdown.take_until(until)) {
+                    push_frames(&mut mix_recorder, &mixed, start).into_iter().for_each(&report);
+                }
+            }
+
+            if let Some((start, mixed)) = mixdown.take_all() {
+                push_frames(&mut mix_recorder, &mixed, start).into_iter().for_each(&report);
+            }
+            report(mix_recorder.finish());
+            for track in tracks.values_mut() {
+                report(track.finish());
+            }
+        });
+
+        Ok(Self { segments, _tap: tap })
+    }
+
+    // Each file as it's closed
+    pub fn subscribe(&self) -> broadcast::Receiver<RecordingSegment> {
+        self.segments.subscribe()
+    }
+}
+
+// In frame-sized pieces, so voice activation sees the mix the way it sees a track
+fn push_frames(recorder: &mut SegmentRecorder, samples: &[f32], start: SystemTime) -> Vec<Result<Option<RecordingSegment>>> {
+    let frame = (PLAYBACK_SAMPLE_RATE as u128 * FRAME_DURATION.as_millis() / 1000) as usize;
+    samples
+        .chunks(frame)
+        .enumerate()
+        .map(|(index, chunk)| recorder.push(chunk, PLAYBACK_SAMPLE_RATE, start + FRAME_DURATION * index as u32))
+        .collect()
+}
@@ -0,0 +1,247 @@
+use anyhow::{anyhow, Result};
+use bytes::{BufMut, Bytes, BytesMut};
+use std::collections::VecDeque;
+use webrtc::api::media_engine::MediaEngine;
+use webrtc::rtp_transceiver::rtp_codec::{RTCRtpCodecCapability, RTCRtpCodecParameters, RTPCodecType};
+
+use crate::audio::AudioCodec;
+
+// RFC 2198 redundant audio: every packet also carries the previous frames, so a lost
+// packet's audio arrives with the next one. Costs a frame or two of extra bitrate, which
+// is why it's only switched on while the link is losing packets.
+pub const RED_MIME: &str = "audio/red";
+// One RED entry per audio codec, with that codec's clock. The payload types are only what
+// we offer; negotiated ones are looked up per call. Not for Opus: its frames travel as raw
+// PCM here, several kilobytes each, and a redundant block can't be longer than 1023 bytes.
+const RED_VARIANTS: [(AudioCodec, u8); 2] = [(AudioCodec::Pcmu, 117), (AudioCodec::Pcma, 118)];
+
+// Earlier frames repeated in each packet; two covers a burst of two lost packets
+pub const REDUNDANT_FRAMES: usize = 2;
+// The redundant block header has 14 bits of timestamp offset and 10 of length
+const MAX_TIMESTAMP_OFFSET: u32 = (1 << 14) - 1;
+const MAX_BLOCK_LENGTH: usize = (1 << 10) - 1;
+
+// RED for `codecs`, carrying each one as both the primary and the redundant encoding
+pub fn red_parameters(codecs: &[AudioCodec]) -> Vec<RTCRtpCodecParameters> {
+    RED_VARIANTS
+        .into_iter()
+        .filter(|(codec, _)| codecs.contains(codec))
+        .map(|(codec, payload_type)| {
+            let primary = codec.parameters();
+            RTCRtpCodecParameters {
+                capability: RTCRtpCodecCapability {
+                    mime_type: RED_MIME.to_owned(),
+                    clock_rate: primary.capability.clock_rate,
+                    channels: primary.capability.channels,
+                    sdp_fmtp_line: format!("{0}/{0}", primary.payload_type),
+                    rtcp_feedback: vec![],
+                },
+                payload_type,
+                ..Default::default()
+            }
+        })
+        .collect()
+}
+
+pub fn carries_red(codec: AudioCodec) -> bool {
+    RED_VARIANTS.iter().any(|(variant, _)| *variant == codec)
+}
+
+pub fn register_red(media_engine: &mut MediaEngine) -> Result<()> {
+    for parameters in red_parameters(&AudioCodec::ALL) {
+        media_engine.register_codec(parameters, RTPCodecType::Audio)?;
+    }
+    Ok(())
+}
+
+// A negotiated RED payload type and the codec it wraps
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RedFormat {
+    pub payload_type: u8,
+    pub primary_payload_type: u8,
+    pub primary: AudioCodec,
+}
+
+impl RedFormat {
+    // The RED entries among `codecs` whose blocks are all one codec we carry in RED;
+    // mixed-codec redundancy ("111/0") is not supported
+    pub fn negotiated(codecs: &[RTCRtpCodecParameters]) -> Vec<RedFormat> {
+        codecs
+            .iter()
+            .filter(|parameters| parameters.capability.mime_type.eq_ignore_ascii_case(RED_MIME))
+            .filter_map(|red| {
+                let mut blocks = red.capability.sdp_fmtp_line.split('/').map(|block| block.trim().parse::<u8>());
+                let primary_payload_type = blocks.next()?.ok()?;
+                if !blocks.all(|block| block == Ok(primary_payload_type)) {
+                    return None;
+                }
+                let primary = codecs
+                    .iter()
+                    .find(|parameters| parameters.payload_type == primary_payload_type)
+                    .and_then(|parameters| AudioCodec::from_mime(&parameters.capability.mime_type))
+                    .filter(|codec| carries_red(*codec))?;
+                Some(RedFormat {
+                    payload_type: red.payload_type,
+                    primary_payload_type,
+                    primary,
+                })
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RedBlock {
+    pub payload_type: u8,
+    pub timestamp: u32,
+    pub data: Bytes,
+}
+
+// Oldest block first, the primary last. Blocks the headers can't describe (too old or too
+// long) are left out; the primary always goes in.
+pub fn encode(timestamp: u32, blocks: &[RedBlock]) -> Bytes {
+    let Some((primary, redundant)) = blocks.split_last() else {
+        return Bytes::new();
+    };
+    let redundant: Vec<&RedBlock> = redundant
+        .iter()
+        .filter(|block| {
+            timestamp.wrapping_sub(block.timestamp) <= MAX_TIMESTAMP_OFFSET && block.data.len() <= MAX_BLOCK_LENGTH
+        })
+        .collect();
+    let length = redundant.iter().map(|block| 4 + block.data.len()).sum::<usize>() + 1 + primary.data.len();
+    let mut payload = BytesMut::with_capacity(length);
+    for block in &redundant {
+        let offset = timestamp.wrapping_sub(block.timestamp);
+        payload.put_u8(0x80 | (block.payload_type & 0x7f));
+        payload.put_u8((offset >> 6) as u8);
+        payload.put_u8(((offset & 0x3f) << 2) as u8 | (block.data.len() >> 8) as u8);
+        payload.put_u8(block.data.len() as u8);
+    }
+    payload.put_u8(primary.payload_type & 0x7f);
+    for block in &redundant {
+        payload.put_slice(&block.data);
+    }
+    payload.put_slice(&primary.data);
+    payload.freeze()
+}
+
+// A RED packet's blocks, oldest first, with the timestamps they had in their own packets
+pub fn decode(timestamp: u32, payload: &Bytes) -> Result<Vec<RedBlock>> {
+    let mut blocks = Vec::new();
+    for_each_block(timestamp, payload, |block| blocks.push(block))?;
+    Ok(blocks)
+}
+
+// Like decode, without collecting the blocks, for the receive loop. The whole packet is
+// checked before the first block, so a malformed one visits none.
+fn for_each_block(timestamp: u32, payload: &Bytes, mut visit: impl FnMut(RedBlock)) -> Result<()> {
+    let mut headers_end = 0;
+    let mut redundant_length = 0;
+    loop {
+        let first = *payload.get(headers_end).ok_or_else(|| anyhow!("RED packet ends in its headers"))?;
+        if first & 0x80 == 0 {
+            headers_end += 1;
+            break;
+        }
+        let header = payload
+            .get(headers_end..headers_end + 4)
+            .ok_or_else(|| anyhow!("RED packet ends in its headers"))?;
+        redundant_length += block_header(header).1;
+        headers_end += 4;
+    }
+    if headers_end + redundant_length > payload.len() {
+        return Err(anyhow!("RED block runs past the end of the packet"));
+    }
+
+    let (mut header, mut position) = (0, headers_end);
+    while payload[header] & 0x80 != 0 {
+        let (offset, length) = block_header(&payload[header..header + 4]);
+        visit(RedBlock {
+            payload_type: payload[header] & 0x7f,
+            timestamp: timestamp.wrapping_sub(offset),
+            data: payload.slice(position..position + length),
+        });
+        header += 4;
+        position += length;
+    }
+    visit(RedBlock {
+        payload_type: payload[header] & 0x7f,
+        timestamp,
+        data: payload.slice(position..),
+    });
+    Ok(())
+}
+
+// The timestamp offset and length of a redundant block
+fn block_header(header: &[u8]) -> (u32, usize) {
+    let offset = (header[1] as u32) << 6 | (header[2] as u32) >> 2;
+    let length = ((header[2] as usize) & 0x03) << 8 | header[3] as usize;
+    (offset, length)
+}
+
+// Wraps each outgoing frame together with the ones sent just before it
+#[derive(Debug, Default)]
+pub struct RedEncoder {
+    history: VecDeque<RedBlock>,
+}
+
+impl RedEncoder {
+    pub fn encode(&mut self, payload_type: u8, timestamp: u32, data: Bytes) -> Bytes {
+        self.history.push_back(RedBlock {
+            payload_type,
+            timestamp,
+            data,
+        });
+        while self.history.len() > REDUNDANT_FRAMES + 1 {
+            self.history.pop_front();
+        }
+        encode(timestamp, self.history.make_contiguous())
+    }
+
+    // Redundancy was switched off; frames from before must not show up once it's back on
+    pub fn reset(&mut self) {
+        self.history.clear();
+    }
+}
+
+// Unpacks incoming RED packets into the frames to play: the primary, plus any earlier
+// frame whose own packet never arrived
+#[derive(Debug, Default)]
+pub struct RedDecoder {
+    last_timestamp: Option<u32>,
+}
+
+impl RedDecoder {
+    // Oldest first. Malformed packets are dropped whole.
+    pub fn unpack(&mut self, format: &RedFormat, timestamp: u32, payload: &Bytes) -> Vec<Bytes> {
+        let mut frames = Vec::new();
+        self.unpack_into(format, timestamp, payload, &mut frames);
+        frames
+    }
+
+    // Appends to frames, so the receive loop can keep one buffer for every packet
+    pub fn unpack_into(&mut self, format: &RedFormat, timestamp: u32, payload: &Bytes, frames: &mut Vec<Bytes>) {
+        let unpacked = for_each_block(timestamp, payload, |block| {
+            if block.payload_type == format.primary_payload_type && self.is_new(block.timestamp) {
+                frames.push(block.data);
+            }
+        });
+        if unpacked.is_ok() {
+            self.seen(timestamp);
+        }
+    }
+
+    // A plain packet of the primary codec, sent while redundancy was off
+    pub fn seen(&mut self, timestamp: u32) {
+        if self.is_new(timestamp) {
+            self.last_timestamp = Some(timestamp);
+        }
+    }
+
+    // Later than anything played so far, allowing for wraparound
+    fn is_new(&self, timestamp: u32) -> bool {
+        self.last_timestamp
+            .map_or(true, |last| (timestamp.wrapping_sub(last) as i32) > 0)
+    }
+}
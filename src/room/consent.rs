@@ -0,0 +1,110 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+// How many of the other participants must agree before a call may be recorded
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConsentPolicy {
+    #[default]
+    Unanimous,
+    Majority,
+}
+
+impl ConsentPolicy {
+    // Matches the serialized form
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ConsentPolicy::Unanimous => "unanimous",
+            ConsentPolicy::Majority => "majority",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConsentState {
+    Pending,
+    Granted,
+    Declined,
+}
+
+// A request to record that arrived from another participant, waiting for our answer
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConsentRequest {
+    pub room_id: String,
+    pub from_peer: String,
+    pub request_id: String,
+    pub policy: ConsentPolicy,
+}
+
+// Our own request to record: every other participant is asked, and recording may only
+// start once the policy is met. Someone joining later has to agree too.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordingConsent {
+    request_id: String,
+    policy: ConsentPolicy,
+    // None until the peer answers
+    answers: BTreeMap<String, Option<bool>>,
+}
+
+impl RecordingConsent {
+    pub fn new(peers: impl IntoIterator<Item = String>, policy: ConsentPolicy) -> Self {
+        Self {
+            request_id: format!("rec-{:016x}", rand::random::<u64>()),
+            policy,
+            answers: peers.into_iter().map(|peer| (peer, None)).collect(),
+        }
+    }
+
+    pub fn request_id(&self) -> &str {
+        &self.request_id
+    }
+
+    pub fn policy(&self) -> ConsentPolicy {
+        self.policy
+    }
+
+    // False for peers that were never asked
+    pub fn respond(&mut self, peer_id: &str, granted: bool) -> bool {
+        match self.answers.get_mut(peer_id) {
+            Some(answer) => {
+                *answer = Some(granted);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn peer_joined(&mut self, peer_id: String) {
+        self.answers.entry(peer_id).or_insert(None);
+    }
+
+    pub fn peer_left(&mut self, peer_id: &str) {
+        self.answers.remove(peer_id);
+    }
+
+    pub fn answers(&self) -> impl Iterator<Item = (&str, Option<bool>)> {
+        self.answers.iter().map(|(peer, answer)| (peer.as_str(), *answer))
+    }
+
+    pub fn state(&self) -> ConsentState {
+        let total = self.answers.len();
+        let granted = self.answers.values().filter(|answer| **answer == Some(true)).count();
+        let declined = self.answers.values().filter(|answer| **answer == Some(false)).count();
+        // Nobody else left in the call to ask
+        if total == 0 {
+            return ConsentState::Granted;
+        }
+        match self.policy {
+            ConsentPolicy::Unanimous if declined > 0 => ConsentState::Declined,
+            ConsentPolicy::Unanimous if granted == total => ConsentState::Granted,
+            ConsentPolicy::Majority if granted * 2 > total => ConsentState::Granted,
+            // Even the outstanding answers can't make a majority any more
+            ConsentPolicy::Majority if (total - declined) * 2 <= total => ConsentState::Declined,
+            _ => ConsentState::Pending,
+        }
+    }
+
+    pub fn is_granted(&self) -> bool {
+        self.state() == ConsentState::Granted
+    }
+}
@@ -0,0 +1,10 @@
+// Room-wide state: who is in the room and what everyone in a call agreed on
+mod consent;
+mod relay;
+mod roster;
+mod tracks;
+
+pub use consent::{ConsentPolicy, ConsentRequest, ConsentState, RecordingConsent};
+pub use relay::MediaRelays;
+pub use roster::{RoomRoster, RosterCues, RosterDiff};
+pub use tracks::TrackOwners;
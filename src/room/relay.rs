@@ -0,0 +1,40 @@
+use std::collections::HashMap;
+
+// Relay peers (an SFU or a well-connected member) the server designated per room. While
+// a room has one, every participant sends its media to the relay instead of to each
+// other, and the relay forwards it; call setup still goes peer to peer.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MediaRelays {
+    relays: HashMap<String, String>,
+}
+
+impl MediaRelays {
+    // None takes the room back to direct connections
+    pub fn assign(&mut self, room_id: &str, relay_peer: Option<String>) {
+        match relay_peer {
+            Some(relay) => {
+                self.relays.insert(room_id.to_string(), relay);
+            }
+            None => {
+                self.relays.remove(room_id);
+            }
+        }
+    }
+
+    pub fn relay_for(&self, room_id: &str) -> Option<&str> {
+        self.relays.get(room_id).map(String::as_str)
+    }
+
+    pub fn is_relay(&self, room_id: &str, peer_id: &str) -> bool {
+        self.relay_for(room_id) == Some(peer_id)
+    }
+
+    // Where offers and candidates meant for `peer_id` have to go
+    pub fn media_peer(&self, room_id: &str, peer_id: &str) -> String {
+        self.relay_for(room_id).unwrap_or(peer_id).to_string()
+    }
+
+    pub fn clear(&mut self) {
+        self.relays.clear();
+    }
+}
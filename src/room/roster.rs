@@ -0,0 +1,68 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+// Who is in each room we've been in, to tell arrivals and departures apart in the
+// server's peer lists, which always carry the whole room. Rooms are remembered across
+// reconnects, so whoever came or went during an outage still shows up in the next diff.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RoomRoster {
+    rooms: HashMap<String, Vec<String>>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RosterDiff {
+    pub joined: Vec<String>,
+    pub left: Vec<String>,
+}
+
+impl RosterDiff {
+    pub fn is_empty(&self) -> bool {
+        self.joined.is_empty() && self.left.is_empty()
+    }
+}
+
+impl RoomRoster {
+    // The first list for a room is who was already there, not arrivals. Both sides of the
+    // diff keep the order of the lists they came from.
+    pub fn update(&mut self, room_id: &str, peers: &[String]) -> RosterDiff {
+        let Some(known) = self.rooms.insert(room_id.to_string(), peers.to_vec()) else {
+            return RosterDiff::default();
+        };
+        RosterDiff {
+            joined: peers.iter().filter(|peer| !known.contains(peer)).cloned().collect(),
+            left: known.into_iter().filter(|peer| !peers.contains(peer)).collect(),
+        }
+    }
+
+    pub fn peers(&self, room_id: &str) -> &[String] {
+        self.rooms.get(room_id).map_or(&[], Vec::as_slice)
+    }
+
+    // Coming back to the room starts from a fresh list again
+    pub fn forget(&mut self, room_id: &str) {
+        self.rooms.remove(room_id);
+    }
+}
+
+// What the app does when someone joins or leaves the room
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RosterCues {
+    pub sounds: bool,
+    pub toasts: bool,
+    // WAV files to play instead of the built-in tones
+    pub join_sound: Option<PathBuf>,
+    pub leave_sound: Option<PathBuf>,
+}
+
+impl Default for RosterCues {
+    fn default() -> Self {
+        Self {
+            sounds: true,
+            toasts: true,
+            join_sound: None,
+            leave_sound: None,
+        }
+    }
+}
@@ -0,0 +1,29 @@
+use std::collections::HashMap;
+
+// Which peer's audio each incoming stream carries, so per-peer levels, placement and
+// taps land on the right person. By convention a client's stream ID is its peer ID,
+// which covers direct calls and meshes; a relay forwarding streams under IDs of its own
+// announces each one's owner with TrackInfo.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TrackOwners {
+    owners: HashMap<String, String>,
+}
+
+impl TrackOwners {
+    pub fn assign(&mut self, stream_id: &str, peer_id: &str) {
+        self.owners.insert(stream_id.to_string(), peer_id.to_string());
+    }
+
+    // Streams nobody announced belong to the peer they're named after
+    pub fn peer_for(&self, stream_id: &str) -> String {
+        self.owners.get(stream_id).cloned().unwrap_or_else(|| stream_id.to_string())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.owners.iter().map(|(stream_id, peer_id)| (stream_id.as_str(), peer_id.as_str()))
+    }
+
+    pub fn clear(&mut self) {
+        self.owners.clear();
+    }
+}
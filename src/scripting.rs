@@ -0,0 +1,142 @@
+use anyhow::{anyhow, Result};
+use rhai::{Array, Dynamic, Engine, Map, Scope, AST};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::call::IncomingCall;
+use crate::metrics::ConnectionQuality;
+
+// Keeps a runaway script from stalling the call engine; plenty for rules and routing
+const MAX_OPERATIONS: u64 = 100_000;
+
+// Rhai scripts for call automation. A script defines any of these hooks:
+//
+//     fn on_incoming_call(call) {}      // call.room, call.from_peer
+//     fn on_peer_joined(peer) {}        // peer.room, peer.peer_id
+//     fn on_quality_degraded(quality) {} // quality.room, quality.quality_score, ...
+//
+// and acts through answer(), decline(), call(peer or [peers]), hang_up(),
+// send_chat(text), send_dtmf(tones) and play(wav), which mixes a file into the call.
+// Relative paths are from the script's directory.
+#[derive(Clone)]
+pub struct ScriptHooks {
+    engine: Arc<Engine>,
+    ast: Arc<AST>,
+    dir: PathBuf,
+    // Filled by the action functions while a hook runs. Clones share the script, so
+    // hooks run one at a time.
+    actions: Arc<Mutex<Vec<ScriptAction>>>,
+    running: Arc<Mutex<()>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScriptAction {
+    Answer,
+    Decline,
+    Call(Vec<String>),
+    HangUp,
+    SendChat(String),
+    SendDtmf(String),
+    Play(PathBuf),
+}
+
+impl ScriptHooks {
+    pub fn load(path: &Path) -> Result<Self> {
+        let source = std::fs::read_to_string(path).map_err(|e| anyhow!("Could not read script {}: {}", path.display(), e))?;
+        let dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        Self::compile(&source, dir).map_err(|e| anyhow!("{}: {}", path.display(), e))
+    }
+
+    // Paths in the script are relative to the working directory
+    pub fn from_source(source: &str) -> Result<Self> {
+        Self::compile(source, PathBuf::new())
+    }
+
+    fn compile(source: &str, dir: PathBuf) -> Result<Self> {
+        let actions = Arc::new(Mutex::new(Vec::new()));
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+        engine.on_print(|text| println!("[script] {}", text));
+
+        let push = {
+            let actions = actions.clone();
+            move |action: ScriptAction| actions.lock().unwrap().push(action)
+        };
+        let queue = push.clone();
+        engine.register_fn("answer", move || queue(ScriptAction::Answer));
+        let queue = push.clone();
+        engine.register_fn("decline", move || queue(ScriptAction::Decline));
+        let queue = push.clone();
+        engine.register_fn("call", move |peer: &str| queue(ScriptAction::Call(vec![peer.to_string()])));
+        let queue = push.clone();
+        engine.register_fn("call", move |peers: Array| {
+            queue(ScriptAction::Call(peers.into_iter().filter_map(|peer| peer.into_string().ok()).collect()))
+        });
+        let queue = push.clone();
+        engine.register_fn("hang_up", move || queue(ScriptAction::HangUp));
+        let queue = push.clone();
+        engine.register_fn("send_chat", move |text: &str| queue(ScriptAction::SendChat(text.to_string())));
+        let queue = push.clone();
+        engine.register_fn("send_dtmf", move |tones: &str| queue(ScriptAction::SendDtmf(tones.to_string())));
+        let queue = push;
+        let base = dir.clone();
+        engine.register_fn("play", move |path: &str| queue(ScriptAction::Play(base.join(path))));
+
+        let ast = engine.compile(source).map_err(|e| anyhow!("Script error: {}", e))?;
+        Ok(Self {
+            engine: Arc::new(engine),
+            ast: Arc::new(ast),
+            dir,
+            actions,
+            running: Arc::default(),
+        })
+    }
+
+    pub fn has_hook(&self, name: &str) -> bool {
+        self.ast.iter_functions().any(|function| function.name == name)
+    }
+
+    pub fn incoming_call(&self, call: &IncomingCall) -> Result<Vec<ScriptAction>> {
+        let mut details = Map::new();
+        details.insert("room".into(), call.room_id.clone().into());
+        details.insert("from_peer".into(), call.from_peer.clone().into());
+        self.run("on_incoming_call", details)
+    }
+
+    pub fn peer_joined(&self, room: &str, peer_id: &str) -> Result<Vec<ScriptAction>> {
+        let mut details = Map::new();
+        details.insert("room".into(), room.into());
+        details.insert("peer_id".into(), peer_id.into());
+        self.run("on_peer_joined", details)
+    }
+
+    pub fn quality_degraded(&self, room: &str, quality: &ConnectionQuality) -> Result<Vec<ScriptAction>> {
+        let mut details = Map::new();
+        details.insert("room".into(), room.into());
+        details.insert("quality_score".into(), (quality.quality_score as i64).into());
+        details.insert("packet_loss_rate".into(), quality.packet_loss_rate.into());
+        details.insert("round_trip_time".into(), quality.round_trip_time.into());
+        details.insert("jitter".into(), quality.jitter.into());
+        self.run("on_quality_degraded", details)
+    }
+
+    // The actions the hook asked for, in order; none if the script doesn't define it
+    fn run(&self, hook: &str, details: Map) -> Result<Vec<ScriptAction>> {
+        if !self.has_hook(hook) {
+            return Ok(Vec::new());
+        }
+        let _running = self.running.lock().unwrap();
+        self.actions.lock().unwrap().clear();
+        let result = self.engine.call_fn::<Dynamic>(&mut Scope::new(), &self.ast, hook, (details,));
+        let actions = std::mem::take(&mut *self.actions.lock().unwrap());
+        result.map_err(|e| anyhow!("Script hook {} failed: {}", hook, e))?;
+        Ok(actions)
+    }
+}
+
+impl fmt::Debug for ScriptHooks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ScriptHooks").field("dir", &self.dir).finish_non_exhaustive()
+    }
+}
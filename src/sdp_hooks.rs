@@ -0,0 +1,207 @@
+use anyhow::{anyhow, Result};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use webrtc::peer_connection::sdp::sdp_type::RTCSdpType;
+use webrtc::peer_connection::sdp::session_description::RTCSessionDescription;
+use webrtc::sdp::description::common::{Attribute, Bandwidth};
+use webrtc::sdp::SessionDescription;
+
+use crate::ladder::QualityRung;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SdpStage {
+    BeforeSetLocal,
+    BeforeSetRemote,
+}
+
+// Receives the parsed SDP just before it is applied and may rewrite it, e.g. to strip
+// candidates or tweak an fmtp line. Returning an error aborts the negotiation step.
+pub type SdpHook = Arc<dyn Fn(SdpStage, RTCSdpType, &mut SessionDescription) -> Result<()> + Send + Sync>;
+
+#[derive(Clone, Default)]
+pub struct SdpHooks(Vec<SdpHook>);
+
+impl SdpHooks {
+    pub fn add(&mut self, hook: impl Fn(SdpStage, RTCSdpType, &mut SessionDescription) -> Result<()> + Send + Sync + 'static) {
+        self.0.push(Arc::new(hook));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    // Descriptions pass through untouched (not even re-serialized) when there are no hooks
+    pub fn apply(&self, stage: SdpStage, description: RTCSessionDescription) -> Result<RTCSessionDescription> {
+        if self.0.is_empty() {
+            return Ok(description);
+        }
+
+        let sdp_type = description.sdp_type;
+        let mut parsed = description.unmarshal()?;
+        for hook in &self.0 {
+            hook(stage, sdp_type, &mut parsed)?;
+        }
+
+        let sdp = parsed.marshal();
+        Ok(match sdp_type {
+            RTCSdpType::Offer => RTCSessionDescription::offer(sdp)?,
+            RTCSdpType::Answer => RTCSessionDescription::answer(sdp)?,
+            RTCSdpType::Pranswer => RTCSessionDescription::pranswer(sdp)?,
+            other => return Err(anyhow!("Cannot rewrite a {} description", other)),
+        })
+    }
+}
+
+impl fmt::Debug for SdpHooks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SdpHooks({})", self.0.len())
+    }
+}
+
+impl PartialEq for SdpHooks {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.len() == other.0.len() && self.0.iter().zip(&other.0).all(|(a, b)| Arc::ptr_eq(a, b))
+    }
+}
+
+// Asks the other side for fullband stereo Opus. Only what we can receive: we have no
+// stereo Opus encoder, so nothing is said about what we send (sprop-stereo, bitrate, DTX).
+pub fn music_mode() -> impl Fn(SdpStage, RTCSdpType, &mut SessionDescription) -> Result<()> + Send + Sync + 'static {
+    move |stage, _, description| {
+        if stage != SdpStage::BeforeSetLocal {
+            return Ok(());
+        }
+        for media in description.media_descriptions.iter_mut().filter(|media| media.media_name.media == "audio") {
+            for payload in opus_payloads(&media.attributes) {
+                for (name, value) in [("stereo", "1"), ("maxplaybackrate", "48000")] {
+                    set_fmtp_parameter(&mut media.attributes, &payload, name, value);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// Asks the remote side to send us at most `kbps`: b=AS on each audio section, plus
+// maxaveragebitrate on Opus, which ignores b=AS in most stacks
+pub fn limit_bitrate(kbps: u32) -> impl Fn(SdpStage, RTCSdpType, &mut SessionDescription) -> Result<()> + Send + Sync + 'static {
+    move |stage, _, description| {
+        if stage != SdpStage::BeforeSetLocal {
+            return Ok(());
+        }
+        for media in description.media_descriptions.iter_mut().filter(|media| media.media_name.media == "audio") {
+            media.bandwidth.retain(|bandwidth| bandwidth.bandwidth_type != "AS");
+            media.bandwidth.push(Bandwidth {
+                experimental: false,
+                bandwidth_type: "AS".to_string(),
+                bandwidth: kbps as u64,
+            });
+            for payload in opus_payloads(&media.attributes) {
+                set_fmtp_parameter(&mut media.attributes, &payload, "maxaveragebitrate", &(kbps * 1000).to_string());
+            }
+        }
+        Ok(())
+    }
+}
+
+// Follows the quality ladder: below the top rung, every later offer and answer asks the
+// other side for the rung's channels, audio bandwidth and bitrate. Added after
+// limit_bitrate, and a lower configured limit still wins.
+pub fn quality_rung(rung: Arc<Mutex<QualityRung>>) -> impl Fn(SdpStage, RTCSdpType, &mut SessionDescription) -> Result<()> + Send + Sync + 'static {
+    move |stage, _, description| {
+        let rung = *rung.lock().map_err(|_| anyhow!("Quality rung lock poisoned"))?;
+        if stage != SdpStage::BeforeSetLocal || rung.up().is_none() {
+            return Ok(());
+        }
+        let stereo = if rung.stereo() { "1" } else { "0" };
+        let playback_rate = rung.max_playback_rate().to_string();
+        for media in description.media_descriptions.iter_mut().filter(|media| media.media_name.media == "audio") {
+            for payload in opus_payloads(&media.attributes) {
+                let limit = fmtp_parameter(&media.attributes, &payload, "maxaveragebitrate").and_then(|bps| bps.parse::<u32>().ok());
+                let bitrate = limit.map_or(rung.kbps() * 1000, |limit| limit.min(rung.kbps() * 1000)).to_string();
+                for (name, value) in [
+                    ("stereo", stereo),
+                    ("sprop-stereo", stereo),
+                    ("maxplaybackrate", playback_rate.as_str()),
+                    ("maxaveragebitrate", bitrate.as_str()),
+                ] {
+                    set_fmtp_parameter(&mut media.attributes, &payload, name, value);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn opus_payloads(attributes: &[Attribute]) -> Vec<String> {
+    attributes
+        .iter()
+        .filter(|attribute| attribute.key == "rtpmap")
+        .filter_map(|attribute| attribute.value.as_deref())
+        .filter(|value| value.to_ascii_lowercase().contains(" opus/"))
+        .filter_map(|value| value.split_whitespace().next().map(str::to_string))
+        .collect()
+}
+
+fn fmtp_parameter(attributes: &[Attribute], payload: &str, name: &str) -> Option<String> {
+    let prefix = format!("{} ", payload);
+    let fmtp = attributes
+        .iter()
+        .filter(|attribute| attribute.key == "fmtp")
+        .filter_map(|attribute| attribute.value.as_deref())
+        .find(|value| value.starts_with(&prefix))?;
+    fmtp[prefix.len()..]
+        .split(';')
+        .filter_map(|parameter| parameter.trim().split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value.to_string())
+}
+
+fn set_fmtp_parameter(attributes: &mut Vec<Attribute>, payload: &str, name: &str, value: &str) {
+    let prefix = format!("{} ", payload);
+    let fmtp = attributes
+        .iter_mut()
+        .find(|attribute| attribute.key == "fmtp" && attribute.value.as_deref().map_or(false, |v| v.starts_with(&prefix)));
+    let Some(fmtp) = fmtp else {
+        attributes.push(Attribute {
+            key: "fmtp".to_string(),
+            value: Some(format!("{}{}={}", prefix, name, value)),
+        });
+        return;
+    };
+    let current = fmtp.value.take().unwrap_or_default();
+    let parameters: Vec<&str> = current[prefix.len()..]
+        .split(';')
+        .map(str::trim)
+        .filter(|parameter| !parameter.is_empty() && !parameter.starts_with(&format!("{}=", name)))
+        .collect();
+    let mut parameters = parameters.join(";");
+    if !parameters.is_empty() {
+        parameters.push(';');
+    }
+    fmtp.value = Some(format!("{}{}{}={}", prefix, parameters, name, value));
+}
+
+// Raw SDP of the most recent negotiation as applied, after any hooks ran
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SdpLog {
+    pub local: Option<String>,
+    pub remote: Option<String>,
+}
+
+#[derive(Clone, Default)]
+pub struct SdpRecorder(Arc<Mutex<SdpLog>>);
+
+impl SdpRecorder {
+    pub fn record(&self, stage: SdpStage, description: &RTCSessionDescription) {
+        let mut log = self.0.lock().unwrap();
+        match stage {
+            SdpStage::BeforeSetLocal => log.local = Some(description.sdp.clone()),
+            SdpStage::BeforeSetRemote => log.remote = Some(description.sdp.clone()),
+        }
+    }
+
+    pub fn snapshot(&self) -> SdpLog {
+        self.0.lock().unwrap().clone()
+    }
+}
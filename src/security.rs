@@ -0,0 +1,51 @@
+use serde::Serialize;
+use webrtc::ice::candidate::CandidateType;
+use webrtc::stats::{StatsReport, StatsReportType};
+
+// What protects a call's media, for the lock icon and its details
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct CallSecurity {
+    // The DTLS handshake is done; media never flows unencrypted before that
+    pub encrypted: bool,
+    // The SRTP protection profile DTLS settled on, e.g. SRTP_AEAD_AES_128_GCM
+    pub srtp_profile: Option<String>,
+    // Certificate fingerprints from the SDP. Reading them out to each other rules out
+    // anyone in the middle, the signaling server included.
+    pub local_fingerprint: Option<String>,
+    pub remote_fingerprint: Option<String>,
+    // Media goes through a TURN server; it only ever sees SRTP
+    pub turn_relayed: bool,
+    // A media relay (SFU) ends DTLS and hears the audio
+    pub media_relay: Option<String>,
+}
+
+impl CallSecurity {
+    // Nobody but the people in the call can decrypt the audio
+    pub fn end_to_end(&self) -> bool {
+        self.encrypted && self.media_relay.is_none()
+    }
+}
+
+// The first a=fingerprint in an SDP, as "sha-256 AB:CD:..."
+pub fn sdp_fingerprint(sdp: &str) -> Option<String> {
+    sdp.lines()
+        .find_map(|line| line.trim().strip_prefix("a=fingerprint:"))
+        .map(|fingerprint| fingerprint.trim().to_string())
+}
+
+// Whether either end of the nominated candidate pair is a TURN relay
+pub fn relayed_by_turn(stats: &StatsReport) -> bool {
+    let Some(pair) = stats.reports.values().find_map(|report| match report {
+        StatsReportType::CandidatePair(pair) if pair.nominated => Some(pair),
+        _ => None,
+    }) else {
+        return false;
+    };
+    stats.reports.values().any(|report| match report {
+        StatsReportType::LocalCandidate(candidate) | StatsReportType::RemoteCandidate(candidate) => {
+            (candidate.id == pair.local_candidate_id || candidate.id == pair.remote_candidate_id)
+                && candidate.candidate_type == CandidateType::Relay
+        }
+        _ => false,
+    })
+}
@@ -0,0 +1,50 @@
+use serde::{Deserialize, Serialize};
+
+use crate::audio::AudioCodec;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::webrtc::WebRTCConfig;
+
+// Credentials are only needed for turn:/turns: URLs
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IceServerConfig {
+    pub urls: Vec<String>,
+    pub username: String,
+    pub credential: String,
+}
+
+// Settings a deployment manages centrally, sent by the signaling server right after
+// Join. They take effect from the next peer connection and are never saved locally, so
+// short-lived TURN credentials don't end up in config.json.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ServerConfig {
+    // Replace the configured ICE servers when not empty
+    pub ice_servers: Vec<IceServerConfig>,
+    // Codecs the deployment allows, in its preferred order. Empty allows all.
+    pub codec_policy: Vec<AudioCodec>,
+    pub max_bitrate_kbps: Option<u32>,
+}
+
+impl ServerConfig {
+    // The user's codec order is kept among the allowed codecs; if none of their
+    // preferences is allowed, the server's order is used
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn apply(&self, config: &mut WebRTCConfig) {
+        if !self.ice_servers.is_empty() {
+            config.ice_servers = self.ice_servers.clone();
+        }
+        if !self.codec_policy.is_empty() {
+            let allowed: Vec<AudioCodec> = config
+                .codec_preferences
+                .iter()
+                .copied()
+                .filter(|codec| self.codec_policy.contains(codec))
+                .collect();
+            config.codec_preferences = if allowed.is_empty() { self.codec_policy.clone() } else { allowed };
+        }
+        if let Some(kbps) = self.max_bitrate_kbps {
+            config.max_bitrate_kbps = Some(config.max_bitrate_kbps.map_or(kbps, |own| own.min(kbps)));
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use std::fmt::Write as _;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+// How long a call took from the CallRequest to the first decoded audio, and where the
+// time went. The connection stamps each milestone as it gets there; only the first time
+// counts, so renegotiations and ICE restarts don't move them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Milestone {
+    OfferSent,
+    AnswerReceived,
+    GatheringStarted,
+    GatheringDone,
+    IceConnected,
+    DtlsConnected,
+    FirstRtp,
+    FirstAudio,
+}
+
+const MILESTONES: usize = 8;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SetupMarks([Option<Instant>; MILESTONES]);
+
+impl SetupMarks {
+    pub fn get(&self, milestone: Milestone) -> Option<Instant> {
+        self.0[milestone as usize]
+    }
+
+    // Ignored once the milestone has a time
+    pub fn set(&mut self, milestone: Milestone, at: Instant) {
+        self.0[milestone as usize].get_or_insert(at);
+    }
+
+    fn between(&self, from: Milestone, to: Milestone) -> Option<Duration> {
+        Some(self.get(to)?.saturating_duration_since(self.get(from)?))
+    }
+}
+
+// Started with the call; the connections of a call each keep one
+#[derive(Debug, Clone)]
+pub struct SetupClock {
+    requested: Instant,
+    marks: Arc<Mutex<SetupMarks>>,
+}
+
+impl Default for SetupClock {
+    fn default() -> Self {
+        Self::start()
+    }
+}
+
+impl SetupClock {
+    pub fn start() -> Self {
+        Self {
+            requested: Instant::now(),
+            marks: Arc::new(Mutex::new(SetupMarks::default())),
+        }
+    }
+
+    pub fn mark(&self, milestone: Milestone) {
+        if let Ok(mut marks) = self.marks.lock() {
+            marks.set(milestone, Instant::now());
+        }
+    }
+
+    pub fn setup_time(&self) -> CallSetupTime {
+        let marks = self.marks.lock().map(|marks| *marks).unwrap_or_default();
+        CallSetupTime::measure(self.requested, &marks)
+    }
+}
+
+// Each part is None until both of its ends have happened
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CallSetupTime {
+    // CallRequest to the first decoded audio frame
+    pub total: Option<Duration>,
+    // Our offer to its answer, through the signaling server; the callee never sees this
+    pub signaling_rtt: Option<Duration>,
+    // Candidate gathering, from the local description to the last candidate
+    pub gathering: Option<Duration>,
+    // ICE connected to DTLS connected
+    pub dtls: Option<Duration>,
+    // DTLS connected to the first inbound RTP packet
+    pub first_rtp: Option<Duration>,
+}
+
+impl CallSetupTime {
+    pub fn measure(requested: Instant, marks: &SetupMarks) -> Self {
+        Self {
+            total: marks.get(Milestone::FirstAudio).map(|at| at.saturating_duration_since(requested)),
+            signaling_rtt: marks.between(Milestone::OfferSent, Milestone::AnswerReceived),
+            gathering: marks.between(Milestone::GatheringStarted, Milestone::GatheringDone),
+            dtls: marks.between(Milestone::IceConnected, Milestone::DtlsConnected),
+            first_rtp: marks.between(Milestone::DtlsConnected, Milestone::FirstRtp),
+        }
+    }
+
+    // For the call report
+    pub fn report(&self) -> String {
+        let mut text = format!("Call setup: {} to first audio\n", format_ms(self.total));
+        for (phase, duration) in [
+            ("signaling round trip", self.signaling_rtt),
+            ("ICE gathering", self.gathering),
+            ("DTLS handshake", self.dtls),
+            ("first RTP", self.first_rtp),
+        ] {
+            let _ = writeln!(text, "  {}: {}", phase, format_ms(duration));
+        }
+        text
+    }
+}
+
+// "-" for what wasn't measured
+pub fn format_ms(duration: Option<Duration>) -> String {
+    match duration {
+        Some(duration) => format!("{} ms", duration.as_millis()),
+        None => "-".to_string(),
+    }
+}
@@ -0,0 +1,29 @@
+use std::time::Duration;
+
+// Upper bound for saying goodbye on the way out; a dead signaling server must not keep
+// the process (or a closing window) hanging
+pub const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(3);
+
+// Resolves on Ctrl-C, and on Unix also on SIGTERM (service managers, `kill`) and SIGHUP
+// (the controlling terminal went away)
+pub async fn signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let (Ok(mut terminate), Ok(mut hangup)) = (signal(SignalKind::terminate()), signal(SignalKind::hangup())) else {
+            let _ = tokio::signal::ctrl_c().await;
+            return;
+        };
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {}
+            _ = terminate.recv() => {}
+            _ = hangup.recv() => {}
+        }
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+    }
+}
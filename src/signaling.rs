@@ -1,130 +1,476 @@
+use futures_util::{Sink, Stream};
 use serde::{Deserialize, Serialize};
-use tokio::sync::mpsc;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+use tokio_util::sync::DropGuard;
 use tokio_tungstenite::connect_async;
 use futures_util::{SinkExt, StreamExt};
-use anyhow::Result;
-use crate::utils::Error;
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(tag = "message_type")]
-pub enum SignalingMessage {
-    Join {
-        room_id: String,
-        peer_id: String,
-    },
-    Disconnect {
-        room_id: String,
-        peer_id: String,
-    },
-    PeerList {
-        peers: Vec<String>,
-    },
-    Offer {
-        room_id: String,
-        sdp: String,
-        from_peer: String,
-        to_peer: String,
-    },
-    Answer {
-        room_id: String,
-        sdp: String,
-        from_peer: String,
-        to_peer: String,
-    },
-    IceCandidate {
-        room_id: String,
-        candidate: String,
-        from_peer: String,
-        to_peer: String,
-    },
-    RequestPeerList,
-    InitiateCall {
-        peer_id: String,
-        room_id: String,
-    },
-    MediaError {
-        error_type: String,
-        description: String,
-        peer_id: String,
-    },
-    EndCall {
-        room_id: String,
-        peer_id: String,
-    },
-    CallRequest {
-        room_id: String,
-        from_peer: String,
-        to_peers: Vec<String>,
-    },
-    CallResponse {
-        room_id: String,
-        from_peer: String,
-        to_peer: String,
-        accepted: bool,
-    },
-    Error {
-        message: String,
-    },
-    ConnectionLost {
-        peer_id: String,
-    },
+use anyhow::{anyhow, Result};
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::http::HeaderValue;
+use tokio_tungstenite::tungstenite::{Error as WsError, Message};
+
+use crate::identity::Identity;
+use crate::lan;
+use crate::plugins;
+use crate::tasks::TaskGroup;
+
+// The messages themselves are shared with the browser build
+pub use crate::protocol::*;
+
+// How many messages wait in each direction. When a queue is full the oldest ICE
+// candidate in it makes room; offers, answers and the rest are never dropped and may
+// go over the limit instead.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChannelLimits {
+    // Waiting to be written to the socket
+    pub outgoing: usize,
+    // Waiting per subscriber, including the client's own receive()
+    pub incoming: usize,
+}
+
+impl Default for ChannelLimits {
+    fn default() -> Self {
+        Self { outgoing: 100, incoming: 256 }
+    }
+}
+
+// Messages thrown away by the overflow policy since connecting
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DroppedMessages {
+    pub outgoing: u64,
+    pub incoming: u64,
+}
+
+// A later candidate is as good as an earlier one; everything else changes call state
+fn droppable(msg: &SignalingMessage) -> bool {
+    matches!(msg, SignalingMessage::IceCandidate { .. })
+}
+
+struct QueueState<T> {
+    // With whether each may be dropped
+    items: VecDeque<(T, bool)>,
+    closed: bool,
+}
+
+// Single-consumer queue with the overflow policy above
+struct OverflowQueue<T> {
+    state: Mutex<QueueState<T>>,
+    ready: Notify,
+    capacity: usize,
+    dropped: Arc<AtomicU64>,
+    direction: &'static str,
+}
+
+impl<T> OverflowQueue<T> {
+    fn new(capacity: usize, dropped: Arc<AtomicU64>, direction: &'static str) -> Self {
+        Self {
+            state: Mutex::new(QueueState { items: VecDeque::new(), closed: false }),
+            ready: Notify::new(),
+            capacity: capacity.max(1),
+            dropped,
+            direction,
+        }
+    }
+
+    // False once closed
+    fn push(&self, item: T, droppable: bool) -> bool {
+        let Ok(mut state) = self.state.lock() else { return false };
+        if state.closed {
+            return false;
+        }
+        if state.items.len() >= self.capacity {
+            if let Some(oldest) = state.items.iter().position(|(_, droppable)| *droppable) {
+                state.items.remove(oldest);
+                self.count_drop();
+            } else if droppable {
+                self.count_drop();
+                return true;
+            }
+        }
+        state.items.push_back((item, droppable));
+        drop(state);
+        self.ready.notify_one();
+        true
+    }
+
+    // None once closed and drained
+    async fn pop(&self) -> Option<T> {
+        loop {
+            let ready = self.ready.notified();
+            {
+                let mut state = self.state.lock().ok()?;
+                if let Some((item, _)) = state.items.pop_front() {
+                    return Some(item);
+                }
+                if state.closed {
+                    return None;
+                }
+            }
+            ready.await;
+        }
+    }
+
+    fn is_closed(&self) -> bool {
+        self.state.lock().map_or(true, |state| state.closed)
+    }
+
+    fn close(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            state.closed = true;
+        }
+        self.ready.notify_one();
+    }
+
+    fn count_drop(&self) {
+        let dropped = self.dropped.fetch_add(1, Ordering::Relaxed) + 1;
+        // Once per doubling, so a flood doesn't flood the log too
+        if dropped.is_power_of_two() {
+            eprintln!("Signaling {} queue full; {} ICE candidates dropped so far", self.direction, dropped);
+        }
+    }
+}
+
+// Fans incoming messages out to every live subscription
+struct Subscribers {
+    // Closed once the socket is; later subscriptions start out closed
+    queues: Mutex<(Vec<Weak<OverflowQueue<SignalingMessage>>>, bool)>,
+    capacity: usize,
+    dropped: Arc<AtomicU64>,
+}
+
+impl Subscribers {
+    fn subscribe(&self) -> SignalingSubscription {
+        let queue = Arc::new(OverflowQueue::new(self.capacity, self.dropped.clone(), "incoming"));
+        if let Ok(mut queues) = self.queues.lock() {
+            if queues.1 {
+                queue.close();
+            } else {
+                queues.0.push(Arc::downgrade(&queue));
+            }
+        }
+        SignalingSubscription { queue }
+    }
+
+    fn publish(&self, msg: SignalingMessage) {
+        let Ok(mut queues) = self.queues.lock() else { return };
+        queues.0.retain(|queue| queue.strong_count() > 0);
+        let may_drop = droppable(&msg);
+        for queue in queues.0.iter().filter_map(Weak::upgrade) {
+            queue.push(msg.clone(), may_drop);
+        }
+    }
+
+    fn close(&self) {
+        let Ok(mut queues) = self.queues.lock() else { return };
+        queues.1 = true;
+        for queue in queues.0.drain(..).filter_map(|queue| queue.upgrade()) {
+            queue.close();
+        }
+    }
+}
+
+// One consumer's view of the incoming messages
+pub struct SignalingSubscription {
+    queue: Arc<OverflowQueue<SignalingMessage>>,
+}
+
+impl SignalingSubscription {
+    // None once the connection is gone and everything before that was read
+    pub async fn recv(&mut self) -> Option<SignalingMessage> {
+        self.queue.pop().await
+    }
+}
+
+// The sending half. Clones share the connection; it closes once the last one is
+// dropped, or on close().
+#[derive(Clone)]
+pub struct SignalingSender {
+    outgoing: Arc<Outgoing>,
+    // Signs everything sent when set
+    identity: Option<Identity>,
+}
+
+struct Outgoing {
+    queue: Arc<OverflowQueue<String>>,
+    writer: Mutex<Option<JoinHandle<()>>>,
+    dropped: Arc<AtomicU64>,
+    _reader: Arc<DropGuard>,
+}
+
+impl Drop for Outgoing {
+    // Lets the writer finish what was queued and close the socket
+    fn drop(&mut self) {
+        self.queue.close();
+    }
 }
 
+impl SignalingSender {
+    pub fn set_identity(&mut self, identity: Identity) {
+        self.identity = Some(identity);
+    }
+
+    // Queues the message for the socket. Never waits: under a flood old ICE candidates
+    // are dropped instead.
+    pub fn send(&self, msg: SignalingMessage) -> Result<()> {
+        // Dropped by a plugin
+        let Some(msg) = plugins::host().filter_outgoing(msg) else { return Ok(()) };
+        let frame = encode_frame(&msg, self.identity.as_ref())?;
+        if !self.outgoing.queue.push(frame, droppable(&msg)) {
+            return Err(anyhow!("Signaling connection is closed"));
+        }
+        Ok(())
+    }
+
+    // Writes out everything already sent, then closes the WebSocket for every clone.
+    // Dropping the sender instead can lose the last messages, e.g. an EndCall sent right
+    // before exiting.
+    pub async fn close(&self) {
+        self.outgoing.queue.close();
+        let writer = self.outgoing.writer.lock().ok().and_then(|mut writer| writer.take());
+        if let Some(writer) = writer {
+            let _ = writer.await;
+        }
+    }
+
+    pub fn dropped(&self) -> u64 {
+        self.outgoing.dropped.load(Ordering::Relaxed)
+    }
+
+    // False once the socket is gone, from either side, or close() was called
+    pub fn is_open(&self) -> bool {
+        !self.outgoing.queue.is_closed()
+    }
+}
+
+// The receiving half
+pub struct SignalingReceiver {
+    subscribers: Arc<Subscribers>,
+    // The owner's own subscription, taken at connect so receive() sees the first message
+    rx: SignalingSubscription,
+    // Shared with the sender: the reader stops once both halves are gone, even if the
+    // server never closes its end
+    _reader: Arc<DropGuard>,
+}
+
+impl SignalingReceiver {
+    // Every incoming message from now on, independent of receive() and other
+    // subscribers, each with its own queue of ChannelLimits::incoming
+    pub fn subscribe(&self) -> SignalingSubscription {
+        self.subscribers.subscribe()
+    }
+
+    // subscribe() as a stream; ends with the connection
+    pub fn messages(&self) -> impl Stream<Item = SignalingMessage> + Send + 'static {
+        futures_util::stream::unfold(self.subscribe(), |mut rx| async move { rx.recv().await.map(|msg| (msg, rx)) })
+    }
+
+    // The next message on the owner's subscription; None once the connection is gone
+    pub async fn receive(&mut self) -> Result<Option<SignalingMessage>> {
+        Ok(self.rx.recv().await)
+    }
+
+    pub fn dropped(&self) -> u64 {
+        self.subscribers.dropped.load(Ordering::Relaxed)
+    }
+}
+
+// Both halves together, for owners that send and receive from one place
 pub struct SignalingClient {
-    tx: mpsc::Sender<SignalingMessage>,
-    rx: mpsc::Receiver<SignalingMessage>,
+    sender: SignalingSender,
+    receiver: SignalingReceiver,
 }
 
 impl SignalingClient {
     pub async fn connect(url: &str) -> Result<Self> {
-        let (ws_stream, _) = connect_async(url).await?;
+        Self::connect_with_token(url, None).await
+    }
+
+    // The token goes in an Authorization: Bearer header on the WebSocket handshake
+    pub async fn connect_with_token(url: &str, token: Option<&str>) -> Result<Self> {
+        Self::connect_with_options(url, token, ChannelLimits::default()).await
+    }
+
+    // https:// URLs are WebTransport sessions (see is_webtransport) and lan:// the
+    // serverless local network mode (see lan.rs); the rest are WebSockets
+    pub async fn connect_with_options(url: &str, token: Option<&str>, limits: ChannelLimits) -> Result<Self> {
+        if lan::is_lan(url) {
+            let (write, read, session) = lan::connect().await?;
+            return Ok(Self::start(write, read, session, limits));
+        }
+        if is_webtransport(url) {
+            let (write, read, session) = webtransport::connect(url, token).await?;
+            return Ok(Self::start(write, read, session, limits));
+        }
+        let mut request = url.into_client_request()?;
+        if let Some(token) = token.filter(|token| !token.is_empty()) {
+            request
+                .headers_mut()
+                .insert("Authorization", HeaderValue::from_str(&format!("Bearer {}", token))?);
+        }
+        let (ws_stream, _) = connect_async(request).await?;
         let (write, read) = ws_stream.split();
-        
-        let (tx, rx) = mpsc::channel(100);
-        let (outgoing_tx, mut outgoing_rx) = mpsc::channel(100);
+        let write = write.with(|json: String| futures_util::future::ready(Ok::<_, WsError>(Message::Text(json))));
+        let read = read.filter_map(|msg| futures_util::future::ready(msg.ok().map(|msg| msg.to_string())));
+        Ok(Self::start(write, read, (), limits))
+    }
+
+    // Runs the queues over either transport. `session` is whatever has to stay alive
+    // for the frames to flow; it goes once the writer is done.
+    fn start<W, R>(mut write: W, read: R, session: impl Send + 'static, limits: ChannelLimits) -> Self
+    where
+        W: Sink<String> + Send + Unpin + 'static,
+        R: Stream<Item = String> + Send + Unpin + 'static,
+    {
+        let dropped_outgoing = Arc::new(AtomicU64::new(0));
+        let queue = Arc::new(OverflowQueue::new(limits.outgoing, dropped_outgoing.clone(), "outgoing"));
+        let subscribers = Arc::new(Subscribers {
+            queues: Mutex::new((Vec::new(), false)),
+            capacity: limits.incoming,
+            dropped: Arc::new(AtomicU64::new(0)),
+        });
+        let rx = subscribers.subscribe();
 
         // Handle outgoing messages
-        tokio::spawn(async move {
-            while let Some(msg) = outgoing_rx.recv().await {
-                if let Ok(json) = serde_json::to_string(&msg) {
-                    if write.send(json.into()).await.is_err() {
-                        break;
-                    }
+        let outgoing = queue.clone();
+        let writer = tokio::spawn(async move {
+            while let Some(json) = outgoing.pop().await {
+                if write.send(json).await.is_err() {
+                    // Later sends fail instead of queueing for nobody
+                    outgoing.close();
+                    return;
                 }
             }
+            let _ = write.close().await;
+            drop(session);
         });
 
-        // Handle incoming messages
-        tokio::spawn(async move {
+        // Handle incoming messages; plugins filter once for every subscriber
+        let incoming = subscribers.clone();
+        let outgoing = queue.clone();
+        let tasks = TaskGroup::new();
+        let reader = Arc::new(tasks.cancel_on_drop());
+        tasks.spawn(async move {
             let mut read = read;
-            while let Some(msg) = read.next().await {
-                if let Ok(msg) = msg {
-                    if let Ok(signal) = serde_json::from_str::<SignalingMessage>(msg.to_string().as_str()) {
-                        if tx.send(signal).await.is_err() {
-                            break;
-                        }
-                    }
+            let mut frames = FrameDecoder::default();
+            while let Some(frame) = read.next().await {
+                if let Some(msg) = plugins::host().filter_incoming(frames.decode(&frame)) {
+                    incoming.publish(msg);
                 }
             }
+            incoming.close();
+            // The server hung up; sends fail from here on, even for owners that never read
+            outgoing.close();
         });
 
-        Ok(Self {
-            tx: outgoing_tx,
-            rx,
-        })
+        Self {
+            sender: SignalingSender {
+                outgoing: Arc::new(Outgoing {
+                    queue,
+                    writer: Mutex::new(Some(writer)),
+                    dropped: dropped_outgoing,
+                    _reader: reader.clone(),
+                }),
+                identity: None,
+            },
+            receiver: SignalingReceiver { subscribers, rx, _reader: reader },
+        }
+    }
+
+    // Send from anywhere without sharing the client; the receiver can go to a task of
+    // its own
+    pub fn split(self) -> (SignalingSender, SignalingReceiver) {
+        (self.sender, self.receiver)
+    }
+
+    pub fn set_identity(&mut self, identity: Identity) {
+        self.sender.set_identity(identity);
     }
 
     pub async fn send(&mut self, msg: SignalingMessage) -> Result<()> {
-        let json = serde_json::to_string(&msg)?;
-        self.tx.send(msg).await.map_err(|e| Error::Signaling(format!("Failed to send message: {}", e)))?;
-        Ok(())
+        self.sender.send(msg)
+    }
+
+    pub async fn close(&mut self) {
+        self.sender.close().await;
+    }
+
+    pub fn subscribe(&self) -> SignalingSubscription {
+        self.receiver.subscribe()
+    }
+
+    pub fn messages(&self) -> impl Stream<Item = SignalingMessage> + Send + 'static {
+        self.receiver.messages()
     }
 
     pub async fn receive(&mut self) -> Result<Option<SignalingMessage>> {
-        if let Some(msg) = self.rx.recv().await {
-            Ok(Some(msg))
-        } else {
-            Ok(None)
+        self.receiver.receive().await
+    }
+
+    pub fn dropped(&self) -> DroppedMessages {
+        DroppedMessages {
+            outgoing: self.sender.dropped(),
+            incoming: self.receiver.dropped(),
         }
     }
-} 
\ No newline at end of file
+}
+
+// WebTransport is always over HTTP/3, so an https:// server URL selects it: one QUIC
+// stream instead of TCP, so a lost packet doesn't hold up every frame behind it and the
+// session can survive an address change
+pub fn is_webtransport(url: &str) -> bool {
+    url.trim_start().to_ascii_lowercase().starts_with("https://")
+}
+
+// Longest frame accepted on a WebTransport stream; a signed offer is a few KB
+#[cfg(feature = "webtransport")]
+const MAX_FRAME_LEN: usize = 256 * 1024;
+
+// Frames go one JSON document per line over a single bidirectional stream of the session
+#[cfg(feature = "webtransport")]
+mod webtransport {
+    use anyhow::Result;
+    use futures_util::{Sink, Stream, StreamExt};
+    use tokio_util::codec::{FramedRead, FramedWrite, LinesCodec};
+    use wtransport::endpoint::ConnectOptions;
+    use wtransport::{ClientConfig, Connection, Endpoint};
+
+    use super::MAX_FRAME_LEN;
+
+    pub async fn connect(
+        url: &str,
+        token: Option<&str>,
+    ) -> Result<(impl Sink<String> + Send + Unpin, impl Stream<Item = String> + Send + Unpin, Connection)> {
+        let config = ClientConfig::builder().with_bind_default().with_native_certs().build();
+        let mut options = ConnectOptions::builder(url);
+        if let Some(token) = token.filter(|token| !token.is_empty()) {
+            options = options.add_header("Authorization", format!("Bearer {}", token));
+        }
+        let session = Endpoint::client(config)?.connect(options.build()).await?;
+        let (send, recv) = session.open_bi().await?.await?;
+        let write = FramedWrite::new(send, LinesCodec::new_with_max_length(MAX_FRAME_LEN));
+        let read = FramedRead::new(recv, LinesCodec::new_with_max_length(MAX_FRAME_LEN))
+            .filter_map(|line| futures_util::future::ready(line.ok()));
+        Ok((write, read, session))
+    }
+}
+
+#[cfg(not(feature = "webtransport"))]
+mod webtransport {
+    use anyhow::{anyhow, Result};
+    use futures_util::sink::Drain;
+    use futures_util::stream::Empty;
+
+    pub enum Session {}
+
+    pub async fn connect(url: &str, _token: Option<&str>) -> Result<(Drain<String>, Empty<String>, Session)> {
+        Err(anyhow!(
+            "{} is a WebTransport server, but this build has no WebTransport support (webtransport feature)",
+            url
+        ))
+    }
+}
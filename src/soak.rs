@@ -0,0 +1,147 @@
+use anyhow::Result;
+use std::time::{Duration, Instant};
+
+use crate::audio::AudioBackendKind;
+use crate::echo::EchoTest;
+use crate::webrtc::WebRTCConfig;
+
+// Long-running stability check behind the hidden --soak flag: calls against the echo bot,
+// an in-process loopback peer on the mock backend, made and torn down over and over while
+// memory, open files and live tasks are watched. Whatever a call leaves behind (a peer
+// connection nobody closed, a task nobody stops) adds up over hours.
+#[derive(Debug, Clone)]
+pub struct SoakConfig {
+    pub duration: Duration,
+    pub call_length: Duration,
+    // Between calls; also lets the last call's tasks wind down before a reading
+    pub pause: Duration,
+    pub report_every: Duration,
+}
+
+impl Default for SoakConfig {
+    fn default() -> Self {
+        Self {
+            duration: Duration::from_secs(4 * 3600),
+            call_length: Duration::from_secs(10),
+            pause: Duration::from_secs(1),
+            report_every: Duration::from_secs(60),
+        }
+    }
+}
+
+// More live tasks than this over the baseline, after a call is gone, is a leak
+const TASK_SLACK: usize = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResourceSample {
+    // Linux only; None elsewhere
+    pub resident_bytes: Option<u64>,
+    pub open_files: Option<usize>,
+    // On the runtime the soak runs on
+    pub tasks: usize,
+}
+
+impl ResourceSample {
+    // Must be called from within a tokio runtime
+    pub fn take() -> Self {
+        Self {
+            resident_bytes: resident_bytes(),
+            open_files: std::fs::read_dir("/proc/self/fd").ok().map(|entries| entries.count()),
+            tasks: tokio::runtime::Handle::current().metrics().num_alive_tasks(),
+        }
+    }
+}
+
+fn resident_bytes() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    let kilobytes: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kilobytes * 1024)
+}
+
+#[derive(Debug, Clone)]
+pub struct SoakReport {
+    pub calls: u64,
+    pub failures: u64,
+    pub elapsed: Duration,
+    // Taken after the first call, once lazily built state exists
+    pub baseline: ResourceSample,
+    pub last: ResourceSample,
+}
+
+impl SoakReport {
+    pub fn leaked_tasks(&self) -> usize {
+        self.last.tasks.saturating_sub(self.baseline.tasks)
+    }
+
+    pub fn is_leaking(&self) -> bool {
+        self.leaked_tasks() > TASK_SLACK
+    }
+
+    pub fn summary(&self) -> String {
+        let megabytes = |bytes: u64| bytes as f64 / (1024.0 * 1024.0);
+        let memory = match (self.baseline.resident_bytes, self.last.resident_bytes) {
+            (Some(baseline), Some(last)) => {
+                format!("rss {:.1} MB ({:+.1})", megabytes(last), megabytes(last) - megabytes(baseline))
+            }
+            _ => "rss n/a".to_string(),
+        };
+        let files = match (self.baseline.open_files, self.last.open_files) {
+            (Some(baseline), Some(last)) => format!("fds {} ({:+})", last, last as i64 - baseline as i64),
+            _ => "fds n/a".to_string(),
+        };
+        let seconds = self.elapsed.as_secs();
+        format!(
+            "{} calls ({} failed) in {:02}:{:02}:{:02}, {}, {}, tasks {} ({:+})",
+            self.calls,
+            self.failures,
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60,
+            memory,
+            files,
+            self.last.tasks,
+            self.last.tasks as i64 - self.baseline.tasks as i64
+        )
+    }
+}
+
+// Calls until `config.duration` is up; `progress` gets a report every
+// `config.report_every`. A call that fails to set up counts as a failure, not an error.
+pub async fn run(config: &SoakConfig, webrtc: &WebRTCConfig, mut progress: impl FnMut(&SoakReport)) -> Result<SoakReport> {
+    let started = Instant::now();
+    let mut report = SoakReport {
+        calls: 0,
+        failures: 0,
+        elapsed: Duration::ZERO,
+        baseline: ResourceSample::take(),
+        last: ResourceSample::take(),
+    };
+    let mut last_progress = Instant::now();
+
+    while started.elapsed() < config.duration {
+        match EchoTest::start(webrtc, AudioBackendKind::Mock.create()).await {
+            Ok(call) => {
+                tokio::time::sleep(config.call_length).await;
+                call.stop().await;
+            }
+            Err(e) => {
+                eprintln!("Soak call failed: {}", e);
+                report.failures += 1;
+            }
+        }
+        report.calls += 1;
+        tokio::time::sleep(config.pause).await;
+
+        report.last = ResourceSample::take();
+        if report.calls == 1 {
+            report.baseline = report.last;
+        }
+        report.elapsed = started.elapsed();
+        if last_progress.elapsed() >= config.report_every {
+            progress(&report);
+            last_progress = Instant::now();
+        }
+    }
+    Ok(report)
+}
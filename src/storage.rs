@@ -0,0 +1,269 @@
+use anyhow::{anyhow, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::call::CallSession;
+use crate::chat::ChatEntry;
+use crate::config::config_dir;
+use crate::contacts::{Contact, ContactBook};
+
+const DATABASE_FILE: &str = "history.db";
+
+// One entry per schema version, applied in order. PRAGMA user_version records how many
+// have run, so existing databases only get the ones they are missing. Never edit an
+// entry once released; add a new one instead.
+const MIGRATIONS: &[&str] = &[
+    "CREATE TABLE chat_messages (
+        id INTEGER PRIMARY KEY,
+        room_id TEXT NOT NULL,
+        from_peer TEXT NOT NULL,
+        text TEXT NOT NULL,
+        sent_at_ms INTEGER NOT NULL
+    );
+    CREATE INDEX chat_messages_by_room ON chat_messages (room_id, sent_at_ms);",
+    "CREATE TABLE calls (
+        id INTEGER PRIMARY KEY,
+        room_id TEXT NOT NULL,
+        participants TEXT NOT NULL,
+        incoming INTEGER NOT NULL,
+        answered INTEGER NOT NULL,
+        started_at_ms INTEGER NOT NULL,
+        duration_ms INTEGER NOT NULL
+    );",
+    "CREATE TABLE contacts (
+        peer_id TEXT PRIMARY KEY,
+        name TEXT NOT NULL,
+        favorite INTEGER NOT NULL DEFAULT 0
+    );",
+];
+
+// Contacts moved here from contacts.json in this schema version
+const CONTACTS_VERSION: u32 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CallDirection {
+    Outgoing,
+    Incoming,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CallRecord {
+    pub room_id: String,
+    pub participants: Vec<String>,
+    pub direction: CallDirection,
+    // Declined or never picked up
+    pub answered: bool,
+    pub started_at: SystemTime,
+    pub duration: Duration,
+}
+
+impl CallRecord {
+    pub fn from_session(session: &CallSession, direction: CallDirection, answered: bool) -> Self {
+        let duration = session.elapsed();
+        Self {
+            room_id: session.room_id.clone(),
+            participants: session.participants.clone(),
+            direction,
+            answered,
+            started_at: SystemTime::now() - duration,
+            duration,
+        }
+    }
+}
+
+// Local history that survives restarts: chat per room, calls, and contacts
+pub struct Storage {
+    conn: Connection,
+}
+
+impl Storage {
+    // The database next to config.json; contacts from an older contacts.json are
+    // imported the first time
+    pub fn open_default() -> Result<Self> {
+        let dir = config_dir();
+        fs::create_dir_all(&dir)?;
+        let mut storage = Self::open(&dir.join(DATABASE_FILE))?;
+        if storage.contacts_need_import()? {
+            if let Some(book) = ContactBook::load_legacy() {
+                storage.save_contacts(&book)?;
+                println!("Imported {} contacts into {}", book.contacts().len(), DATABASE_FILE);
+            }
+            storage.conn.execute("INSERT INTO storage_meta (key) VALUES ('contacts_imported')", [])?;
+        }
+        Ok(storage)
+    }
+
+    pub fn open(path: &Path) -> Result<Self> {
+        let conn = Connection::open(path).map_err(|e| anyhow!("Failed to open {}: {}", path.display(), e))?;
+        Self::with_connection(conn)
+    }
+
+    pub fn open_in_memory() -> Result<Self> {
+        Self::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(conn: Connection) -> Result<Self> {
+        let mut storage = Self { conn };
+        storage.migrate()?;
+        Ok(storage)
+    }
+
+    pub fn schema_version(&self) -> Result<u32> {
+        Ok(self.conn.query_row("PRAGMA user_version", [], |row| row.get(0))?)
+    }
+
+    fn migrate(&mut self) -> Result<()> {
+        let current = self.schema_version()? as usize;
+        if current > MIGRATIONS.len() {
+            return Err(anyhow!(
+                "History database is from a newer version (schema v{}, this build knows v{}); not touching it",
+                current,
+                MIGRATIONS.len()
+            ));
+        }
+        for (index, migration) in MIGRATIONS.iter().enumerate().skip(current) {
+            let tx = self.conn.transaction()?;
+            tx.execute_batch(migration)?;
+            // PRAGMA doesn't take parameters
+            tx.execute_batch(&format!("PRAGMA user_version = {}", index + 1))?;
+            tx.commit()?;
+        }
+        self.conn
+            .execute_batch("CREATE TABLE IF NOT EXISTS storage_meta (key TEXT PRIMARY KEY)")?;
+        Ok(())
+    }
+
+    fn contacts_need_import(&self) -> Result<bool> {
+        if self.schema_version()? < CONTACTS_VERSION {
+            return Ok(false);
+        }
+        let imported: Option<String> = self
+            .conn
+            .query_row("SELECT key FROM storage_meta WHERE key = 'contacts_imported'", [], |row| row.get(0))
+            .optional()?;
+        Ok(imported.is_none())
+    }
+
+    pub fn add_chat(&self, room_id: &str, entry: &ChatEntry) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO chat_messages (room_id, from_peer, text, sent_at_ms) VALUES (?1, ?2, ?3, ?4)",
+            params![room_id, entry.from_peer, entry.text, to_millis(entry.sent_at)],
+        )?;
+        Ok(())
+    }
+
+    // The newest `limit` messages of a room, oldest first like the chat log shows them
+    pub fn chat_history(&self, room_id: &str, limit: usize) -> Result<Vec<ChatEntry>> {
+        let mut statement = self.conn.prepare(
+            "SELECT from_peer, text, sent_at_ms FROM chat_messages
+             WHERE room_id = ?1 ORDER BY sent_at_ms DESC, id DESC LIMIT ?2",
+        )?;
+        let mut entries = statement
+            .query_map(params![room_id, limit as i64], |row| {
+                Ok(ChatEntry {
+                    from_peer: row.get(0)?,
+                    text: row.get(1)?,
+                    sent_at: from_millis(row.get(2)?),
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        entries.reverse();
+        Ok(entries)
+    }
+
+    pub fn add_call(&self, record: &CallRecord) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO calls (room_id, participants, incoming, answered, started_at_ms, duration_ms)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                record.room_id,
+                serde_json::to_string(&record.participants)?,
+                record.direction == CallDirection::Incoming,
+                record.answered,
+                to_millis(record.started_at),
+                record.duration.as_millis() as i64,
+            ],
+        )?;
+        Ok(())
+    }
+
+    // Newest first
+    pub fn recent_calls(&self, limit: usize) -> Result<Vec<CallRecord>> {
+        let mut statement = self.conn.prepare(
+            "SELECT room_id, participants, incoming, answered, started_at_ms, duration_ms FROM calls
+             ORDER BY started_at_ms DESC, id DESC LIMIT ?1",
+        )?;
+        let rows = statement
+            .query_map(params![limit as i64], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, bool>(2)?,
+                    row.get::<_, bool>(3)?,
+                    row.get::<_, i64>(4)?,
+                    row.get::<_, i64>(5)?,
+                ))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        rows.into_iter()
+            .map(|(room_id, participants, incoming, answered, started_at_ms, duration_ms)| {
+                Ok(CallRecord {
+                    room_id,
+                    participants: serde_json::from_str(&participants)?,
+                    direction: if incoming { CallDirection::Incoming } else { CallDirection::Outgoing },
+                    answered,
+                    started_at: from_millis(started_at_ms),
+                    duration: Duration::from_millis(duration_ms.max(0) as u64),
+                })
+            })
+            .collect()
+    }
+
+    pub fn load_contacts(&self) -> Result<ContactBook> {
+        let mut statement = self.conn.prepare("SELECT name, peer_id, favorite FROM contacts ORDER BY rowid")?;
+        let contacts = statement
+            .query_map([], |row| {
+                Ok(Contact {
+                    name: row.get(0)?,
+                    peer_id: row.get(1)?,
+                    favorite: row.get(2)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(ContactBook::from_contacts(contacts))
+    }
+
+    // Replaces the stored contacts with the book's
+    pub fn save_contacts(&mut self, book: &ContactBook) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        tx.execute("DELETE FROM contacts", [])?;
+        for contact in book.contacts() {
+            tx.execute(
+                "INSERT INTO contacts (peer_id, name, favorite) VALUES (?1, ?2, ?3)",
+                params![contact.peer_id, contact.name, contact.favorite],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    // Forgets chat and call history; contacts are kept
+    pub fn purge_history(&self) -> Result<()> {
+        self.conn.execute_batch(
+            "DELETE FROM chat_messages;
+             DELETE FROM calls;
+             VACUUM;",
+        )?;
+        Ok(())
+    }
+}
+
+fn to_millis(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH).map_or(0, |since| since.as_millis() as i64)
+}
+
+fn from_millis(millis: i64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_millis(millis.max(0) as u64)
+}
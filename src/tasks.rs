@@ -0,0 +1,72 @@
+use std::future::Future;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio_util::sync::{CancellationToken, DropGuard};
+use tokio_util::task::TaskTracker;
+
+// Background tasks that belong to one owner (a client, a connection) and have to stop
+// with it instead of waiting for a channel that may never close. Clones share the tasks.
+#[derive(Debug, Clone, Default)]
+pub struct TaskGroup {
+    tracker: TaskTracker,
+    cancel: CancellationToken,
+}
+
+impl TaskGroup {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Dropped at its next await once the group is cancelled
+    pub fn spawn<F>(&self, task: F) -> JoinHandle<()>
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let cancel = self.cancel.clone();
+        self.tracker.spawn(async move {
+            tokio::select! {
+                _ = cancel.cancelled() => {}
+                _ = task => {}
+            }
+        })
+    }
+
+    // For tasks with cleanup of their own: they get the token and watch it themselves
+    pub fn spawn_with_cleanup<F, Fut>(&self, task: F) -> JoinHandle<()>
+    where
+        F: FnOnce(CancellationToken) -> Fut,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.tracker.spawn(task(self.cancel.clone()))
+    }
+
+    pub fn cancel(&self) {
+        self.cancel.cancel();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.is_cancelled()
+    }
+
+    // Cancels the group once the guard, and every clone of it the caller makes, is gone
+    pub fn cancel_on_drop(&self) -> DropGuard {
+        self.cancel.clone().drop_guard()
+    }
+
+    // Tasks still running
+    pub fn len(&self) -> usize {
+        self.tracker.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tracker.is_empty()
+    }
+
+    // Cancels and waits up to `within` for every task to finish; false if some didn't.
+    // Tasks spawned afterwards still run, but are cancelled from the start.
+    pub async fn shutdown(&self, within: Duration) -> bool {
+        self.cancel.cancel();
+        self.tracker.close();
+        tokio::time::timeout(within, self.tracker.wait()).await.is_ok()
+    }
+}
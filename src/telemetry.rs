@@ -0,0 +1,220 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "otel")]
+use std::time::SystemTime;
+#[cfg(feature = "otel")]
+use opentelemetry::global::{self, BoxedSpan};
+#[cfg(feature = "otel")]
+use opentelemetry::trace::{Span, Status, TraceContextExt, Tracer};
+#[cfg(feature = "otel")]
+use opentelemetry::{Context, KeyValue};
+
+const TRACER_NAME: &str = "webrtc-client";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TelemetryConfig {
+    // Export spans over OTLP; needs a build with the otel feature
+    pub enabled: bool,
+    // OTLP/gRPC collector endpoint
+    pub otlp_endpoint: String,
+    // service.name on every span, to tell client fleets apart
+    pub service_name: String,
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            otlp_endpoint: "http://localhost:4317".to_string(),
+            service_name: TRACER_NAME.to_string(),
+        }
+    }
+}
+
+// Installs the OTLP exporter as the global tracer provider. Must run inside the tokio
+// runtime; spans are batched and sent from a background task. Without this, spans go
+// to the no-op provider and only the local timings are kept.
+pub fn init(config: &TelemetryConfig) -> Result<()> {
+    if !config.enabled {
+        return Ok(());
+    }
+    #[cfg(feature = "otel")]
+    {
+        use opentelemetry_otlp::WithExportConfig;
+        use opentelemetry_sdk::{trace as sdktrace, Resource};
+
+        opentelemetry_otlp::new_pipeline()
+            .tracing()
+            .with_exporter(opentelemetry_otlp::new_exporter().tonic().with_endpoint(&config.otlp_endpoint))
+            .with_trace_config(sdktrace::config().with_resource(Resource::new(vec![KeyValue::new(
+                "service.name",
+                config.service_name.clone(),
+            )])))
+            .install_batch(opentelemetry_sdk::runtime::Tokio)
+            .map_err(|e| anyhow!("Failed to start OTLP exporter: {}", e))?;
+        println!("Exporting call traces to {}", config.otlp_endpoint);
+        Ok(())
+    }
+    #[cfg(not(feature = "otel"))]
+    Err(anyhow!("Trace export is enabled, but this build has no OpenTelemetry support (otel feature)"))
+}
+
+// Flushes spans still queued in the batch exporter
+pub fn shutdown() {
+    #[cfg(feature = "otel")]
+    global::shutdown_tracer_provider();
+}
+
+// A finished operation that was timed elsewhere, e.g. joining the signaling room
+pub fn record_span(name: &'static str, started: Instant, attributes: &[(&'static str, String)], error: Option<&str>) {
+    let elapsed = started.elapsed();
+    match error {
+        Some(error) => println!("{} failed after {} ms: {}", name, elapsed.as_millis(), error),
+        None => println!("{} took {} ms", name, elapsed.as_millis()),
+    }
+    #[cfg(feature = "otel")]
+    {
+        let tracer = global::tracer(TRACER_NAME);
+        let mut span = tracer
+            .span_builder(name)
+            .with_start_time(SystemTime::now() - elapsed)
+            .with_attributes(attributes.iter().map(|(key, value)| KeyValue::new(*key, value.clone())).collect::<Vec<_>>())
+            .start(&tracer);
+        if let Some(error) = error {
+            span.set_status(Status::error(error.to_string()));
+        }
+        span.end();
+    }
+    #[cfg(not(feature = "otel"))]
+    let _ = attributes;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CallRole {
+    Caller,
+    Callee,
+}
+
+impl CallRole {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CallRole::Caller => "caller",
+            CallRole::Callee => "callee",
+        }
+    }
+}
+
+// Call setup as a root span with one child per phase (request, offer, answer, ICE).
+// Each phase() ends the previous phase; the trace ends on finish(), fail(), or being
+// dropped half way, which counts as abandoned.
+pub struct CallTrace {
+    started: Instant,
+    current: Option<(&'static str, Instant)>,
+    phases: Vec<(&'static str, Duration)>,
+    done: bool,
+    #[cfg(feature = "otel")]
+    context: Context,
+    #[cfg(feature = "otel")]
+    phase_span: Option<BoxedSpan>,
+}
+
+impl CallTrace {
+    pub fn start(role: CallRole, room_id: &str, peers: &[String]) -> Self {
+        #[cfg(not(feature = "otel"))]
+        let _ = (role, room_id, peers);
+        Self {
+            started: Instant::now(),
+            current: None,
+            phases: Vec::new(),
+            done: false,
+            #[cfg(feature = "otel")]
+            context: {
+                let tracer = global::tracer(TRACER_NAME);
+                let mut root = tracer.start("call.setup");
+                root.set_attribute(KeyValue::new("call.role", role.as_str()));
+                root.set_attribute(KeyValue::new("room.id", room_id.to_string()));
+                root.set_attribute(KeyValue::new("call.peers", peers.join(",")));
+                Context::current_with_span(root)
+            },
+            #[cfg(feature = "otel")]
+            phase_span: None,
+        }
+    }
+
+    pub fn phase(&mut self, name: &'static str) {
+        self.end_phase();
+        self.current = Some((name, Instant::now()));
+        #[cfg(feature = "otel")]
+        {
+            self.phase_span = Some(global::tracer(TRACER_NAME).start_with_context(name, &self.context));
+        }
+    }
+
+    pub fn current_phase(&self) -> Option<&'static str> {
+        self.current.map(|(name, _)| name)
+    }
+
+    // Completed phases so far, in order
+    pub fn phases(&self) -> &[(&'static str, Duration)] {
+        &self.phases
+    }
+
+    pub fn finish(mut self) {
+        self.end_phase();
+        println!("Call set up in {} ms ({})", self.started.elapsed().as_millis(), self.summary());
+        #[cfg(feature = "otel")]
+        {
+            let span = self.context.span();
+            span.set_status(Status::Ok);
+            span.end();
+        }
+        self.done = true;
+    }
+
+    pub fn fail(mut self, reason: &str) {
+        self.close_failed(reason);
+    }
+
+    fn close_failed(&mut self, reason: &str) {
+        let phase = self.current_phase().unwrap_or("setup");
+        self.end_phase();
+        println!("Call setup failed during {} after {} ms: {}", phase, self.started.elapsed().as_millis(), reason);
+        #[cfg(feature = "otel")]
+        {
+            let span = self.context.span();
+            span.set_attribute(KeyValue::new("call.failed_phase", phase));
+            span.set_status(Status::error(reason.to_string()));
+            span.end();
+        }
+        self.done = true;
+    }
+
+    fn end_phase(&mut self) {
+        if let Some((name, started)) = self.current.take() {
+            self.phases.push((name, started.elapsed()));
+        }
+        #[cfg(feature = "otel")]
+        if let Some(mut span) = self.phase_span.take() {
+            span.end();
+        }
+    }
+
+    fn summary(&self) -> String {
+        self.phases
+            .iter()
+            .map(|(name, duration)| format!("{} {} ms", name, duration.as_millis()))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+impl Drop for CallTrace {
+    fn drop(&mut self) {
+        if !self.done {
+            self.close_failed("call ended before it connected");
+        }
+    }
+}
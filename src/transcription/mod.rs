@@ -0,0 +1,261 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::sync::{broadcast, mpsc};
+use tokio::task::JoinHandle;
+
+use crate::audio::tap::{TapConfig, TapFrame, TapSink, TapSource};
+use crate::audio::{level_dbfs, AudioStreamHandle};
+use crate::webrtc::WebRTCClient;
+
+mod remote;
+#[cfg(feature = "whisper")]
+mod whisper;
+
+pub use remote::{wav_bytes, RemoteStt};
+#[cfg(feature = "whisper")]
+pub use whisper::WhisperStt;
+
+// Frames quieter than this count as a pause between words
+const SPEECH_THRESHOLD_DBFS: f32 = -45.0;
+// Pause that ends an utterance and sends it off to be recognized
+const END_OF_UTTERANCE: Duration = Duration::from_millis(600);
+// Long monologues are cut so their captions don't lag far behind
+const MAX_UTTERANCE: Duration = Duration::from_secs(15);
+// Coughs and clicks aren't worth a request
+const MIN_SPEECH: Duration = Duration::from_millis(300);
+// Utterances waiting for a slow recognizer; older speech is dropped beyond this
+const PENDING_UTTERANCES: usize = 4;
+
+// Speech-to-text engine. Gets one utterance of mono audio at a time.
+#[async_trait]
+pub trait SttBackend: Send + Sync {
+    fn name(&self) -> &'static str;
+    async fn transcribe(&self, samples: &[f32], sample_rate: u32) -> Result<String>;
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TranscriptionConfig {
+    // Show live captions during calls
+    pub enabled: bool,
+    // Caption what we say too, not only the other participants
+    pub include_local: bool,
+    // ISO 639-1 code handed to the recognizer; None lets it detect the language
+    pub language: Option<String>,
+    pub backend: SttBackendKind,
+}
+
+impl Default for TranscriptionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            include_local: true,
+            language: None,
+            backend: SttBackendKind::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SttBackendKind {
+    // whisper.cpp in-process with a ggml model file; needs a build with the whisper feature
+    Whisper { model_path: PathBuf },
+    // An OpenAI-compatible /audio/transcriptions endpoint, which whisper.cpp's server
+    // also provides
+    Remote {
+        url: String,
+        api_key: Option<String>,
+        model: String,
+    },
+}
+
+impl Default for SttBackendKind {
+    fn default() -> Self {
+        SttBackendKind::Remote {
+            url: "https://api.openai.com/v1/audio/transcriptions".to_string(),
+            api_key: None,
+            model: "whisper-1".to_string(),
+        }
+    }
+}
+
+impl TranscriptionConfig {
+    pub fn create_backend(&self) -> Result<Arc<dyn SttBackend>> {
+        match &self.backend {
+            SttBackendKind::Remote { url, api_key, model } => Ok(Arc::new(RemoteStt::new(
+                url.clone(),
+                api_key.clone(),
+                model.clone(),
+                self.language.clone(),
+            ))),
+            #[cfg(feature = "whisper")]
+            SttBackendKind::Whisper { model_path } => Ok(Arc::new(WhisperStt::load(model_path, self.language.clone())?)),
+            #[cfg(not(feature = "whisper"))]
+            SttBackendKind::Whisper { .. } => Err(anyhow::anyhow!(
+                "Local transcription is configured, but this build has no whisper.cpp support (whisper feature)"
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Caption {
+    // Who spoke. Remote audio relayed through a mixing server carries the relay's id.
+    pub source: TapSource,
+    pub text: String,
+    // When the utterance ended
+    pub at: SystemTime,
+}
+
+// One speaker's audio between pauses
+#[derive(Debug, Clone)]
+pub struct Utterance {
+    pub source: TapSource,
+    pub sample_rate: u32,
+    pub samples: Vec<f32>,
+    speech: Duration,
+    silence: Duration,
+}
+
+impl Utterance {
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs_f64(self.samples.len() as f64 / self.sample_rate as f64)
+    }
+}
+
+// Splits each speaker's frames into utterances at pauses, so captions are attributed
+// per peer and arrive a sentence at a time
+#[derive(Debug, Default)]
+pub struct Segmenter {
+    open: HashMap<TapSource, Utterance>,
+}
+
+impl Segmenter {
+    pub fn push(&mut self, frame: &TapFrame) -> Option<Utterance> {
+        if frame.samples.is_empty() || frame.sample_rate == 0 {
+            return None;
+        }
+        let length = Duration::from_secs_f64(frame.samples.len() as f64 / frame.sample_rate as f64);
+        let speaking = level_dbfs(&frame.samples) > SPEECH_THRESHOLD_DBFS;
+
+        // A rate change mid-utterance (codec switch) closes what was collected so far
+        let mut finished = None;
+        if self.open.get(&frame.source).is_some_and(|open| open.sample_rate != frame.sample_rate) {
+            finished = self.close(&frame.source);
+        }
+        if !speaking && !self.open.contains_key(&frame.source) {
+            return finished;
+        }
+
+        let utterance = self.open.entry(frame.source.clone()).or_insert_with(|| Utterance {
+            source: frame.source.clone(),
+            sample_rate: frame.sample_rate,
+            samples: Vec::new(),
+            speech: Duration::ZERO,
+            silence: Duration::ZERO,
+        });
+        utterance.samples.extend_from_slice(&frame.samples);
+        if speaking {
+            utterance.speech += length;
+            utterance.silence = Duration::ZERO;
+        } else {
+            utterance.silence += length;
+        }
+        if utterance.silence >= END_OF_UTTERANCE || utterance.duration() >= MAX_UTTERANCE {
+            return self.close(&frame.source).or(finished);
+        }
+        finished
+    }
+
+    fn close(&mut self, source: &TapSource) -> Option<Utterance> {
+        self.open.remove(source).filter(|utterance| utterance.speech >= MIN_SPEECH)
+    }
+}
+
+// Captions for one call. Stops when dropped, with the call's WebRTCClient.
+pub struct Transcriber {
+    captions: broadcast::Sender<Caption>,
+    _tap: Option<AudioStreamHandle>,
+    segmenting: JoinHandle<()>,
+    recognizing: JoinHandle<()>,
+}
+
+impl Transcriber {
+    pub fn start(webrtc: &WebRTCClient, backend: Arc<dyn SttBackend>, include_local: bool) -> Self {
+        let (frames_tx, frames_rx) = mpsc::unbounded_channel();
+        let tap = webrtc.start_tap(&TapConfig {
+            sink: TapSink::Callback(Arc::new(move |frame: &TapFrame| {
+                let _ = frames_tx.send(frame.clone());
+            })),
+            include_local,
+        });
+        Self::spawn(frames_rx, backend, Some(tap))
+    }
+
+    // Captions for frames from anywhere, e.g. a recording
+    pub fn from_frames(frames: mpsc::UnboundedReceiver<TapFrame>, backend: Arc<dyn SttBackend>) -> Self {
+        Self::spawn(frames, backend, None)
+    }
+
+    fn spawn(
+        mut frames: mpsc::UnboundedReceiver<TapFrame>,
+        backend: Arc<dyn SttBackend>,
+        tap: Option<AudioStreamHandle>,
+    ) -> Self {
+        let (captions, _) = broadcast::channel(32);
+        let (utterances_tx, mut utterances_rx) = mpsc::channel::<Utterance>(PENDING_UTTERANCES);
+
+        let segmenting = tokio::spawn(async move {
+            let mut segmenter = Segmenter::default();
+            while let Some(frame) = frames.recv().await {
+                if let Some(utterance) = segmenter.push(&frame) {
+                    // Falling behind: captions for the latest speech matter more
+                    if utterances_tx.try_send(utterance).is_err() {
+                        eprintln!("Transcription is falling behind, skipping an utterance");
+                    }
+                }
+            }
+        });
+
+        let captions_tx = captions.clone();
+        let recognizing = tokio::spawn(async move {
+            while let Some(utterance) = utterances_rx.recv().await {
+                match backend.transcribe(&utterance.samples, utterance.sample_rate).await {
+                    Ok(text) if !text.trim().is_empty() => {
+                        let _ = captions_tx.send(Caption {
+                            source: utterance.source,
+                            text: text.trim().to_string(),
+                            at: SystemTime::now(),
+                        });
+                    }
+                    Ok(_) => {}
+                    Err(e) => eprintln!("{} transcription failed: {}", backend.name(), e),
+                }
+            }
+        });
+
+        Self {
+            captions,
+            _tap: tap,
+            segmenting,
+            recognizing,
+        }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<Caption> {
+        self.captions.subscribe()
+    }
+}
+
+impl Drop for Transcriber {
+    fn drop(&mut self) {
+        self.segmenting.abort();
+        self.recognizing.abort();
+    }
+}
@@ -0,0 +1,73 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use reqwest::multipart::{Form, Part};
+use serde::Deserialize;
+use std::time::Duration;
+
+use super::SttBackend;
+use crate::audio::wav::{to_i16, wav_header};
+
+#[derive(Deserialize)]
+struct TranscriptionResponse {
+    text: String,
+}
+
+// POSTs each utterance as a WAV upload, the way OpenAI's API and whisper.cpp's server
+// take them
+pub struct RemoteStt {
+    client: reqwest::Client,
+    url: String,
+    api_key: Option<String>,
+    model: String,
+    language: Option<String>,
+}
+
+impl RemoteStt {
+    pub fn new(url: String, api_key: Option<String>, model: String, language: Option<String>) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url,
+            api_key,
+            model,
+            language,
+        }
+    }
+}
+
+#[async_trait]
+impl SttBackend for RemoteStt {
+    fn name(&self) -> &'static str {
+        "Remote"
+    }
+
+    async fn transcribe(&self, samples: &[f32], sample_rate: u32) -> Result<String> {
+        let file = Part::bytes(wav_bytes(samples, sample_rate))
+            .file_name("utterance.wav")
+            .mime_str("audio/wav")?;
+        let mut form = Form::new().part("file", file).text("model", self.model.clone());
+        if let Some(ref language) = self.language {
+            form = form.text("language", language.clone());
+        }
+        let mut request = self.client.post(&self.url).multipart(form).timeout(Duration::from_secs(30));
+        if let Some(ref key) = self.api_key {
+            request = request.bearer_auth(key);
+        }
+        let response: TranscriptionResponse = request
+            .send()
+            .await
+            .map_err(|e| anyhow!("Transcription request failed: {}", e))?
+            .error_for_status()
+            .map_err(|e| anyhow!("Transcription request failed: {}", e))?
+            .json()
+            .await
+            .map_err(|e| anyhow!("Unexpected transcription response: {}", e))?;
+        Ok(response.text)
+    }
+}
+
+// 16-bit mono PCM in a WAV container
+pub fn wav_bytes(samples: &[f32], sample_rate: u32) -> Vec<u8> {
+    let mut bytes = wav_header(sample_rate, samples.len() as u32);
+    bytes.extend(samples.iter().flat_map(|sample| to_i16(*sample).to_le_bytes()));
+    bytes
+}
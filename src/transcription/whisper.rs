@@ -0,0 +1,60 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use std::path::Path;
+use std::sync::Arc;
+use whisper_rs::{FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters};
+
+use super::SttBackend;
+use crate::audio::codec::resample;
+
+// What whisper models are trained on
+const WHISPER_SAMPLE_RATE: u32 = 16_000;
+
+// whisper.cpp on the CPU. Recognition blocks, so it runs on tokio's blocking pool.
+pub struct WhisperStt {
+    context: Arc<WhisperContext>,
+    language: Option<String>,
+}
+
+impl WhisperStt {
+    pub fn load(model_path: &Path, language: Option<String>) -> Result<Self> {
+        let path = model_path
+            .to_str()
+            .ok_or_else(|| anyhow!("Whisper model path is not valid UTF-8: {}", model_path.display()))?;
+        let context = WhisperContext::new_with_params(path, WhisperContextParameters::default())
+            .map_err(|e| anyhow!("Failed to load whisper model {}: {:?}", model_path.display(), e))?;
+        Ok(Self {
+            context: Arc::new(context),
+            language,
+        })
+    }
+}
+
+#[async_trait]
+impl SttBackend for WhisperStt {
+    fn name(&self) -> &'static str {
+        "Whisper"
+    }
+
+    async fn transcribe(&self, samples: &[f32], sample_rate: u32) -> Result<String> {
+        let samples = resample(samples, sample_rate, WHISPER_SAMPLE_RATE);
+        let context = self.context.clone();
+        let language = self.language.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut state = context.create_state().map_err(|e| anyhow!("{:?}", e))?;
+            let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
+            params.set_language(Some(language.as_deref().unwrap_or("auto")));
+            params.set_print_progress(false);
+            params.set_print_realtime(false);
+            params.set_print_timestamps(false);
+            state.full(params, &samples).map_err(|e| anyhow!("{:?}", e))?;
+            let segments = state.full_n_segments().map_err(|e| anyhow!("{:?}", e))?;
+            let mut text = String::new();
+            for segment in 0..segments {
+                text.push_str(&state.full_get_segment_text(segment).map_err(|e| anyhow!("{:?}", e))?);
+            }
+            Ok(text)
+        })
+        .await?
+    }
+}
@@ -0,0 +1,123 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+use crate::webrtc::{IceServerConfig, WebRTCConfig};
+
+// Retry interval after a failed fetch; the previous credentials stay in use meanwhile
+const RETRY_DELAY: Duration = Duration::from_secs(30);
+const MIN_REFRESH: Duration = Duration::from_secs(10);
+
+// A TURN REST endpoint handing out time-limited credentials, in the style of
+// draft-uberti-behave-turn-rest: GET <url>?service=turn&username=<user>&key=<api_key>
+// returns {"username", "password", "ttl", "uris"}.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TurnRestConfig {
+    pub url: String,
+    // Sent as the key parameter; some deployments authenticate the request this way
+    pub api_key: Option<String>,
+    // Becomes part of the issued TURN username, for the server's logs
+    pub username: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct TurnCredentials {
+    pub username: String,
+    pub password: String,
+    // Seconds the credentials are valid for
+    pub ttl: u64,
+    pub uris: Vec<String>,
+}
+
+impl TurnCredentials {
+    pub fn ice_server(&self) -> IceServerConfig {
+        IceServerConfig {
+            urls: self.uris.clone(),
+            username: self.username.clone(),
+            credential: self.password.clone(),
+        }
+    }
+
+    // Refreshed at 80% of the lifetime, so a slow or failed fetch still has slack
+    pub fn refresh_after(&self) -> Duration {
+        Duration::from_secs(self.ttl * 4 / 5).max(MIN_REFRESH)
+    }
+}
+
+pub async fn fetch(config: &TurnRestConfig) -> Result<TurnCredentials> {
+    let mut query = vec![("service", "turn"), ("username", config.username.as_str())];
+    if let Some(ref key) = config.api_key {
+        query.push(("key", key.as_str()));
+    }
+    let response = reqwest::Client::new()
+        .get(&config.url)
+        .query(&query)
+        .timeout(Duration::from_secs(10))
+        .send()
+        .await
+        .map_err(|e| anyhow!("TURN credential request failed: {}", e))?
+        .error_for_status()
+        .map_err(|e| anyhow!("TURN credential request failed: {}", e))?;
+    let credentials: TurnCredentials = response
+        .json()
+        .await
+        .map_err(|e| anyhow!("Unexpected TURN credential response: {}", e))?;
+    if credentials.uris.is_empty() {
+        return Err(anyhow!("TURN credential response lists no servers"));
+    }
+    Ok(credentials)
+}
+
+// Keeps a current set of credentials in the background. Running peer connections don't
+// pick them up by themselves: callers apply them when building a connection and again
+// before an ICE restart.
+pub struct TurnCredentialProvider {
+    current: watch::Receiver<Option<(TurnCredentials, Instant)>>,
+    task: JoinHandle<()>,
+}
+
+impl TurnCredentialProvider {
+    pub fn spawn(config: TurnRestConfig) -> Self {
+        let (tx, current) = watch::channel(None);
+        let task = tokio::spawn(async move {
+            loop {
+                let delay = match fetch(&config).await {
+                    Ok(credentials) => {
+                        let delay = credentials.refresh_after();
+                        let _ = tx.send(Some((credentials, Instant::now())));
+                        delay
+                    }
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        RETRY_DELAY
+                    }
+                };
+                tokio::time::sleep(delay).await;
+            }
+        });
+        Self { current, task }
+    }
+
+    // None until the first fetch succeeds, and again once the last credentials expired
+    pub fn current(&self) -> Option<TurnCredentials> {
+        let current = self.current.borrow();
+        let (credentials, fetched_at) = current.as_ref()?;
+        (fetched_at.elapsed() < Duration::from_secs(credentials.ttl)).then(|| credentials.clone())
+    }
+
+    // Adds the current relay to the configured ICE servers
+    pub fn apply(&self, config: &mut WebRTCConfig) {
+        if let Some(credentials) = self.current() {
+            config.ice_servers.push(credentials.ice_server());
+        }
+    }
+}
+
+impl Drop for TurnCredentialProvider {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
@@ -0,0 +1,35 @@
+use dioxus::prelude::*;
+
+use webrtc_client::i18n::tr;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CaptionLine {
+    pub speaker: String,
+    pub text: String,
+    // Said by us
+    pub own: bool,
+}
+
+#[derive(Props, PartialEq)]
+pub struct CaptionsProps {
+    lines: Vec<CaptionLine>,
+}
+
+pub fn Captions(cx: Scope<CaptionsProps>) -> Element {
+    cx.render(rsx! {
+        div { class: "captions",
+            role: "log",
+            aria_live: "polite",
+            aria_label: tr("captions.title"),
+            cx.props.lines.iter().map(|line| {
+                let class = if line.own { "caption own" } else { "caption" };
+                rsx! {
+                    p { class: "{class}",
+                        span { class: "caption-speaker", "{line.speaker}: " }
+                        span { class: "caption-text", "{line.text}" }
+                    }
+                }
+            })
+        }
+    })
+}
@@ -0,0 +1,102 @@
+use dioxus::prelude::*;
+
+use webrtc_client::i18n::{tr, tr_args};
+use webrtc_client::metrics::{QualityHistory, HISTORY_LEN};
+
+// Chart area in SVG units; the element itself stretches to the panel width
+const WIDTH: f64 = 120.0;
+const HEIGHT: f64 = 30.0;
+
+#[derive(Props, PartialEq)]
+pub struct QualityChartsProps {
+    history: QualityHistory,
+}
+
+// Bitrate, loss and RTT over the last minute, so a trend shows before the score drops
+pub fn QualityCharts(cx: Scope<QualityChartsProps>) -> Element {
+    let history = &cx.props.history;
+
+    cx.render(rsx! {
+        div { class: "quality-charts",
+            h4 { {tr("chart.title")} }
+            Sparkline {
+                label: tr("chart.bitrate"),
+                unit: "kbps",
+                values: history.series(|quality| quality.bitrate),
+                // Opus voice sits around 30-50 kbps
+                floor: 64.0,
+            }
+            Sparkline {
+                label: tr("chart.packet_loss"),
+                unit: "%",
+                values: history.series(|quality| quality.packet_loss_rate),
+                floor: 5.0,
+            }
+            Sparkline {
+                label: tr("chart.rtt"),
+                unit: "ms",
+                values: history.series(|quality| quality.round_trip_time),
+                floor: 300.0,
+            }
+        }
+    })
+}
+
+#[derive(Props, PartialEq)]
+pub struct SparklineProps {
+    label: &'static str,
+    unit: &'static str,
+    values: Vec<f64>,
+    // Smallest top of the scale, so a quiet line stays flat instead of filling the chart
+    floor: f64,
+}
+
+pub fn Sparkline(cx: Scope<SparklineProps>) -> Element {
+    let values = &cx.props.values;
+    let unit = cx.props.unit;
+    let top = values.iter().copied().fold(cx.props.floor, f64::max);
+    let latest = match values.last() {
+        Some(value) => format!("{:.1} {}", value, unit),
+        None => "-".to_string(),
+    };
+    let peak = format!("{:.1} {}", values.iter().copied().fold(0.0, f64::max), unit);
+
+    // Newest reading at the right edge; a short history starts partway in
+    let step = WIDTH / (HISTORY_LEN - 1) as f64;
+    let offset = HISTORY_LEN.saturating_sub(values.len()) as f64 * step;
+    let points = values
+        .iter()
+        .enumerate()
+        .map(|(index, value)| {
+            let x = offset + index as f64 * step;
+            let y = HEIGHT - (value / top).clamp(0.0, 1.0) * HEIGHT;
+            format!("{:.1},{:.1}", x, y)
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+    let summary = tr_args(
+        "chart.summary",
+        &[
+            ("label", cx.props.label),
+            ("latest", &latest),
+            ("peak", &peak),
+        ],
+    );
+
+    cx.render(rsx! {
+        div { class: "sparkline",
+            div { class: "quality-item",
+                {cx.props.label},
+                span { class: "quality-value", "{latest}" }
+            }
+            svg {
+                class: "sparkline-chart",
+                view_box: "0 0 {WIDTH} {HEIGHT}",
+                preserve_aspect_ratio: "none",
+                role: "img",
+                aria_label: "{summary}",
+                polyline { points: "{points}" }
+            }
+        }
+    })
+}
@@ -0,0 +1,92 @@
+use dioxus::prelude::*;
+use tokio::sync::{mpsc, watch};
+
+use webrtc_client::chat::ChatEntry;
+use webrtc_client::i18n::tr;
+
+#[derive(Props)]
+pub struct ChatPanelProps<'a> {
+    entries: Vec<ChatEntry>,
+    local_peer_id: String,
+    enabled: bool,
+    on_send: EventHandler<'a, String>,
+}
+
+pub fn ChatPanel<'a>(cx: Scope<'a, ChatPanelProps<'a>>) -> Element {
+    let draft = use_state(cx, String::new);
+
+    let send = move || {
+        let text = draft.get().trim().to_string();
+        if !text.is_empty() {
+            cx.props.on_send.call(text);
+            draft.set(String::new());
+        }
+    };
+
+    cx.render(rsx! {
+        div { class: "chat-panel",
+            div { class: "chat-log",
+                role: "log",
+                aria_live: "polite",
+                cx.props.entries.iter().map(|entry| {
+                    let class = if entry.from_peer == cx.props.local_peer_id { "chat-entry own" } else { "chat-entry" };
+                    rsx! {
+                        div { class: "{class}",
+                            span { class: "chat-from", "{entry.from_peer}: " }
+                            span { class: "chat-text", "{entry.text}" }
+                        }
+                    }
+                })
+            }
+            div { class: "chat-input",
+                input {
+                    placeholder: tr("chat.message"),
+                    aria_label: tr("chat.message"),
+                    value: "{draft}",
+                    disabled: "{!cx.props.enabled}",
+                    oninput: move |evt| draft.set(evt.value.clone()),
+                    onkeydown: move |evt| if evt.key() == Key::Enter { send() }
+                }
+                button {
+                    disabled: "{!cx.props.enabled}",
+                    onclick: move |_| send(),
+                    {tr("chat.send")}
+                }
+            }
+        }
+    })
+}
+
+pub struct ChatWindowProps {
+    pub local_peer_id: String,
+    pub entries_rx: watch::Receiver<Vec<ChatEntry>>,
+    pub outgoing_tx: mpsc::UnboundedSender<String>,
+}
+
+// Root of the popped-out chat window; messages typed here go back to the main window to be sent
+pub fn ChatWindow(cx: Scope<ChatWindowProps>) -> Element {
+    let entries = use_state(cx, || cx.props.entries_rx.borrow().clone());
+
+    use_future(cx, (), |_| {
+        let entries = entries.clone();
+        let mut receiver = cx.props.entries_rx.clone();
+        async move {
+            while receiver.changed().await.is_ok() {
+                let new_entries = receiver.borrow().clone();
+                entries.set(new_entries);
+            }
+        }
+    });
+
+    cx.render(rsx! {
+        style { include_str!("../style.css") }
+        ChatPanel {
+            entries: entries.get().clone(),
+            local_peer_id: cx.props.local_peer_id.clone(),
+            enabled: true,
+            on_send: move |text: String| {
+                let _ = cx.props.outgoing_tx.send(text);
+            }
+        }
+    })
+}
@@ -0,0 +1,226 @@
+use dioxus::prelude::*;
+use tokio::sync::watch;
+
+use webrtc_client::audio::bluetooth::BluetoothDevice;
+use webrtc_client::latency::LatencyBreakdown;
+use webrtc_client::connection::ConnectionStatus;
+use webrtc_client::i18n::{tr, tr_args};
+use webrtc_client::metrics::{ConnectionQuality, QualityHistory};
+use webrtc_client::setup_time::{format_ms, CallSetupTime};
+
+use super::chart::QualityCharts;
+use super::popout::{use_feed, MetricFeeds};
+
+#[derive(Props, PartialEq)]
+pub struct DiagnosticsPanelProps {
+    status: ConnectionStatus,
+    quality: ConnectionQuality,
+    latency: LatencyBreakdown,
+    history: QualityHistory,
+    setup: CallSetupTime,
+    bluetooth: Option<BluetoothDevice>,
+}
+
+pub fn DiagnosticsPanel(cx: Scope<DiagnosticsPanelProps>) -> Element {
+    let status = &cx.props.status;
+    let quality = &cx.props.quality;
+    let latency = &cx.props.latency;
+    let breakdown = [
+        ("capture", format_latency(latency.capture)),
+        ("packetization", format_latency(latency.packetization)),
+        ("encode", format_latency(latency.encode)),
+        ("network", format_latency(latency.network)),
+        ("decode", format_latency(latency.decode)),
+        ("buffered", format_latency(latency.buffered)),
+        ("playback", format_latency(latency.playback)),
+    ];
+    let breakdown: Vec<(&str, &str)> = breakdown.iter().map(|(stage, value)| (*stage, value.as_str())).collect();
+    let latency_details = tr_args("quality.latency_breakdown", &breakdown);
+    let setup = &cx.props.setup;
+    let setup_details = tr_args(
+        "quality.setup_breakdown",
+        &[
+            ("signaling", &format_ms(setup.signaling_rtt)),
+            ("gathering", &format_ms(setup.gathering)),
+            ("dtls", &format_ms(setup.dtls)),
+            ("rtp", &format_ms(setup.first_rtp)),
+        ],
+    );
+
+    cx.render(rsx! {
+        div { class: "connection-status",
+            role: "status",
+            aria_live: "polite",
+            div { class: "status-item",
+                {tr("status.connection")},
+                span { 
+                    class: "status-value {status.state}",
+                    "{status.state}"
+                }
+            }
+            div { class: "status-item",
+                {tr("status.ice")},
+                span { 
+                    class: "status-value",
+                    "{status.ice_state}"
+                }
+            }
+            div { class: "status-item",
+                {tr("status.signaling")},
+                span { 
+                    class: "status-value",
+                    "{status.signaling_state}"
+                }
+            }
+            {cx.props.bluetooth.as_ref().map(|device| rsx!(
+                div { class: "status-item",
+                    title: "{device.name}",
+                    {tr("status.bluetooth")},
+                    span {
+                        class: "status-value",
+                        "{device.profile}, {device.sample_rate / 1000} kHz"
+                    }
+                }
+            ))}
+            {status.last_error.as_ref().map(|error| rsx!(
+                div { class: "status-error",
+                    role: "alert",
+                    {tr_args("status.error", &[("error", error)])}
+                }
+            ))}
+        }
+
+        div { class: "quality-metrics",
+            role: "region",
+            aria_label: tr("quality.title"),
+            h3 { {tr("quality.title")} }
+            div { class: "quality-item",
+                {tr("quality.score")},
+                span { 
+                    class: "quality-value {get_quality_class(quality.quality_score)}",
+                    "{quality.quality_score}%"
+                }
+            }
+            div { class: "quality-item",
+                {tr("quality.rtt")},
+                span { class: "quality-value",
+                    "{quality.round_trip_time:.1} ms"
+                }
+            }
+            div { class: "quality-item",
+                {tr("quality.packet_loss")},
+                span { class: "quality-value",
+                    "{quality.packet_loss_rate:.1}%"
+                }
+            }
+            div { class: "quality-item",
+                {tr("quality.bitrate")},
+                span { class: "quality-value",
+                    "{quality.bitrate:.1} kbps"
+                }
+            }
+            div { class: "quality-item",
+                {tr("quality.audio_level")},
+                span { class: "quality-value",
+                    "{quality.audio_level} dB"
+                }
+            }
+            div { class: "quality-item",
+                title: "{latency_details}",
+                {tr("quality.mouth_to_ear")},
+                span { class: "quality-value", {format_latency(latency.mouth_to_ear())} }
+            }
+            div { class: "quality-item",
+                title: "{setup_details}",
+                {tr("quality.setup_time")},
+                span { class: "quality-value", {format_ms(setup.total)} }
+            }
+            div { class: "quality-item",
+                {tr("quality.capture_latency")},
+                span { class: "quality-value", {format_latency(latency.capture)} }
+            }
+            div { class: "quality-item",
+                {tr("quality.playback_latency")},
+                span { class: "quality-value", {format_latency(latency.playback)} }
+            }
+            {(!cx.props.history.is_empty()).then(|| rsx!(
+                QualityCharts { history: cx.props.history.clone() }
+            ))}
+        }
+    })
+}
+
+pub struct DiagnosticsWindowProps {
+    pub status_rx: watch::Receiver<ConnectionStatus>,
+    pub quality_rx: watch::Receiver<ConnectionQuality>,
+    pub latency_rx: watch::Receiver<LatencyBreakdown>,
+    pub history_rx: watch::Receiver<QualityHistory>,
+    pub setup_rx: watch::Receiver<CallSetupTime>,
+    pub bluetooth_rx: watch::Receiver<Option<BluetoothDevice>>,
+}
+
+// Root of the popped-out diagnostics window, fed from the main window's watch channels
+pub fn DiagnosticsWindow(cx: Scope<DiagnosticsWindowProps>) -> Element {
+    let status = use_feed(cx, &cx.props.status_rx);
+    let quality = use_feed(cx, &cx.props.quality_rx);
+    let latency = use_feed(cx, &cx.props.latency_rx);
+    let history = use_feed(cx, &cx.props.history_rx);
+    let setup = use_feed(cx, &cx.props.setup_rx);
+    let bluetooth = use_feed(cx, &cx.props.bluetooth_rx);
+
+    cx.render(rsx! {
+        style { include_str!("../style.css") }
+        DiagnosticsPanel {
+            status: status.get().clone(),
+            quality: quality.get().clone(),
+            latency: *latency.get(),
+            history: history.get().clone(),
+            setup: *setup.get(),
+            bluetooth: bluetooth.get().clone(),
+        }
+    })
+}
+
+#[derive(Props)]
+pub struct LiveDiagnosticsProps<'a> {
+    status: ConnectionStatus,
+    bluetooth: Option<BluetoothDevice>,
+    metrics: &'a MetricFeeds,
+}
+
+// The docked panel: status comes from the main window, which rarely changes it; the
+// stats readings re-render just this panel
+pub fn LiveDiagnostics<'a>(cx: Scope<'a, LiveDiagnosticsProps<'a>>) -> Element<'a> {
+    let quality = use_feed(cx, &cx.props.metrics.quality);
+    let latency = use_feed(cx, &cx.props.metrics.latency);
+    let history = use_feed(cx, &cx.props.metrics.history);
+    let setup = use_feed(cx, &cx.props.metrics.setup);
+
+    cx.render(rsx! {
+        DiagnosticsPanel {
+            status: cx.props.status.clone(),
+            quality: quality.get().clone(),
+            latency: *latency.get(),
+            history: history.get().clone(),
+            setup: *setup.get(),
+            bluetooth: cx.props.bluetooth.clone(),
+        }
+    })
+}
+
+// "-" until the stage has been measured
+fn format_latency(latency: Option<std::time::Duration>) -> String {
+    match latency {
+        Some(latency) => format!("{:.1} ms", latency.as_secs_f64() * 1000.0),
+        None => "-".to_string(),
+    }
+}
+
+pub fn get_quality_class(score: u8) -> &'static str {
+    match score {
+        90..=100 => "quality-excellent",
+        70..=89 => "quality-good",
+        50..=69 => "quality-fair",
+        _ => "quality-poor"
+    }
+}
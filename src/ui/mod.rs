@@ -0,0 +1,24 @@
+pub mod captions;
+pub mod chart;
+pub mod chat;
+pub mod diagnostics;
+pub mod negotiation;
+pub mod nettest;
+pub mod plugins;
+pub mod popout;
+pub mod presence;
+pub mod security;
+pub mod signal;
+pub mod toasts;
+
+pub use captions::{CaptionLine, Captions};
+pub use chat::ChatPanel;
+pub use diagnostics::{DiagnosticsPanel, LiveDiagnostics};
+pub use negotiation::NegotiationInspector;
+pub use nettest::NetworkTestResult;
+pub use plugins::PluginPanels;
+pub use popout::PanelFeeds;
+pub use presence::PresenceDot;
+pub use security::SecurityIndicator;
+pub use signal::{PeerSignal, SignalStrength};
+pub use toasts::{Toast, Toasts, TOAST_DURATION};
@@ -0,0 +1,97 @@
+use dioxus::prelude::*;
+use std::time::Duration;
+
+use webrtc_client::config::config_dir;
+use webrtc_client::i18n::{tr, tr_args};
+use webrtc_client::negotiation::{Direction, NegotiationKind, NegotiationLog};
+use webrtc_client::setup_time::CallSetupTime;
+
+// The log has no change notification; reading it this often is plenty for a person watching
+const REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Props, PartialEq)]
+pub struct NegotiationInspectorProps {
+    log: NegotiationLog,
+    setup: CallSetupTime,
+}
+
+// What the call's connections exchanged, oldest first; descriptions fold open
+pub fn NegotiationInspector(cx: Scope<NegotiationInspectorProps>) -> Element {
+    let entries = use_state(cx, || cx.props.log.entries());
+    let export_status = use_state(cx, String::new);
+
+    use_future(cx, (&cx.props.log,), |(log,)| {
+        let entries = entries.clone();
+        async move {
+            loop {
+                let current = log.entries();
+                if *entries.current() != current {
+                    entries.set(current);
+                }
+                tokio::time::sleep(REFRESH_INTERVAL).await;
+            }
+        }
+    });
+
+    let export = move |_| {
+        // The call report: where the setup time went, then how it was negotiated
+        let report = cx.props.log.save_with_header(&config_dir().join("negotiation"), &cx.props.setup.report());
+        let status = match report {
+            Ok(path) => tr_args("negotiation.exported", &[("path", &path.display().to_string())]),
+            Err(e) => tr_args("negotiation.export_failed", &[("error", &e.to_string())]),
+        };
+        export_status.set(status);
+    };
+
+    cx.render(rsx! {
+        div { class: "negotiation",
+            button {
+                onclick: export,
+                disabled: "{entries.is_empty()}",
+                {tr("negotiation.export")}
+            }
+            {(!export_status.get().is_empty()).then(|| rsx!(
+                p { class: "hint", role: "status", "{export_status}" }
+            ))}
+            {entries.is_empty().then(|| rsx!(
+                p { class: "hint", {tr("negotiation.empty")} }
+            ))}
+            ol { class: "negotiation-log",
+                entries.iter().enumerate().map(|(index, entry)| {
+                    let direction = match entry.direction {
+                        Direction::Sent => tr("negotiation.sent"),
+                        Direction::Received => tr("negotiation.received"),
+                    };
+                    let kind = entry.kind.to_string();
+                    let label = match &entry.peer {
+                        Some(peer) => tr_args("negotiation.entry_peer", &[("direction", direction), ("kind", &kind), ("peer", peer)]),
+                        None => tr_args("negotiation.entry", &[("direction", direction), ("kind", &kind)]),
+                    };
+                    let elapsed = format!("+{:.3}s", entry.elapsed.as_secs_f64());
+                    let direction_class = entry.direction.to_string();
+                    if entry.kind == NegotiationKind::Candidate {
+                        rsx! {
+                            li { key: "{index}", class: "negotiation-entry {direction_class}",
+                                span { class: "negotiation-time", "{elapsed}" }
+                                " {label}: "
+                                code { "{entry.content}" }
+                            }
+                        }
+                    } else {
+                        rsx! {
+                            li { key: "{index}", class: "negotiation-entry {direction_class}",
+                                details {
+                                    summary {
+                                        span { class: "negotiation-time", "{elapsed}" }
+                                        " {label}"
+                                    }
+                                    pre { class: "sdp-dump", "{entry.content}" }
+                                }
+                            }
+                        }
+                    }
+                })
+            }
+        }
+    })
+}
@@ -0,0 +1,64 @@
+use dioxus::prelude::*;
+
+use webrtc_client::i18n::{tr, tr_args};
+use webrtc_client::nettest::NetworkTestReport;
+
+#[derive(Props, PartialEq)]
+pub struct NetworkTestResultProps {
+    report: NetworkTestReport,
+}
+
+pub fn NetworkTestResult(cx: Scope<NetworkTestResultProps>) -> Element {
+    let report = &cx.props.report;
+    let (verdict, verdict_class) = if report.relay_needed {
+        (tr("nettest.relay_needed"), "nettest-verdict warning")
+    } else {
+        (tr("nettest.direct_ok"), "nettest-verdict")
+    };
+    let reason = if report.udp_blocked {
+        Some(tr("nettest.udp_blocked"))
+    } else if report.symmetric_nat {
+        Some(tr("nettest.symmetric_nat"))
+    } else {
+        None
+    };
+    let candidates = tr_args(
+        "nettest.candidates",
+        &[
+            ("host", &report.candidates.host.to_string()),
+            ("srflx", &report.candidates.srflx.to_string()),
+            ("relay", &report.candidates.relay.to_string()),
+        ],
+    );
+    let bandwidth = match report.bandwidth_kbps {
+        Some(kbps) => tr_args("nettest.bandwidth", &[("kbps", &format!("{:.0}", kbps))]),
+        None => tr("nettest.bandwidth_skipped").to_string(),
+    };
+
+    cx.render(rsx! {
+        div { class: "network-test",
+            role: "status",
+            aria_live: "polite",
+            div { class: "{verdict_class}",
+                {verdict}
+            }
+            {reason.map(|reason| rsx!( div { {reason} } ))}
+            ul {
+                report.servers.iter().map(|server| {
+                    let result = match (server.rtt, &server.error) {
+                        (Some(rtt), _) => tr_args("nettest.rtt", &[("ms", &rtt.as_millis().to_string())]),
+                        (None, Some(error)) => tr_args("nettest.unreachable", &[("error", error)]),
+                        (None, None) => tr("nettest.unreachable_unknown").to_string(),
+                    };
+                    rsx! {
+                        li { key: "{server.url}",
+                            "{server.url}: {result}"
+                        }
+                    }
+                })
+            }
+            div { {candidates} }
+            div { {bandwidth} }
+        }
+    })
+}
@@ -0,0 +1,23 @@
+use dioxus::prelude::*;
+
+use webrtc_client::plugins::PluginPanel;
+
+#[derive(Props, PartialEq)]
+pub struct PluginPanelsProps {
+    panels: Vec<PluginPanel>,
+}
+
+// What loaded plugins contribute to the window, one section each
+pub fn PluginPanels(cx: Scope<PluginPanelsProps>) -> Element {
+    cx.render(rsx! {
+        cx.props.panels.iter().map(|panel| rsx! {
+            section { class: "plugin-panel",
+                aria_label: "{panel.title}",
+                h2 { "{panel.title}" }
+                panel.lines.iter().map(|line| rsx! {
+                    p { class: "plugin-line", "{line}" }
+                })
+            }
+        })
+    })
+}
@@ -0,0 +1,235 @@
+use std::rc::Weak;
+use dioxus::prelude::*;
+use dioxus_desktop::{Config, DesktopContext, DesktopService, LogicalSize, WindowBuilder};
+use tokio::sync::{mpsc, watch};
+
+use webrtc_client::audio::bluetooth::BluetoothDevice;
+use webrtc_client::latency::LatencyBreakdown;
+use webrtc_client::chat::ChatEntry;
+use webrtc_client::connection::ConnectionStatus;
+use webrtc_client::i18n::tr;
+use webrtc_client::metrics::{ConnectionQuality, PeerQualities, QualityHistory};
+use webrtc_client::setup_time::CallSetupTime;
+use crate::ui::chat::{ChatWindow, ChatWindowProps};
+use crate::ui::diagnostics::{DiagnosticsWindow, DiagnosticsWindowProps};
+
+// Watch channels that mirror the main window's state into popped-out windows.
+// Each window runs its own VirtualDom, so they can't share hooks directly.
+// Call metrics are published here first: the panels showing them follow the feeds
+// with use_feed, so a stats reading re-renders those panels and not the whole window.
+pub struct PanelFeeds {
+    status: watch::Sender<ConnectionStatus>,
+    quality: watch::Sender<ConnectionQuality>,
+    latency: watch::Sender<LatencyBreakdown>,
+    history: watch::Sender<QualityHistory>,
+    setup: watch::Sender<CallSetupTime>,
+    peer_qualities: watch::Sender<PeerQualities>,
+    bluetooth: watch::Sender<Option<BluetoothDevice>>,
+    chat: watch::Sender<Vec<ChatEntry>>,
+    outgoing_chat_tx: mpsc::UnboundedSender<String>,
+    outgoing_chat_rx: Option<mpsc::UnboundedReceiver<String>>,
+    diagnostics_window: Option<Weak<DesktopService>>,
+    chat_window: Option<Weak<DesktopService>>,
+}
+
+impl PanelFeeds {
+    pub fn new() -> Self {
+        let (status, _) = watch::channel(ConnectionStatus::default());
+        let (quality, _) = watch::channel(ConnectionQuality::default());
+        let (latency, _) = watch::channel(LatencyBreakdown::default());
+        let (history, _) = watch::channel(QualityHistory::default());
+        let (setup, _) = watch::channel(CallSetupTime::default());
+        let (peer_qualities, _) = watch::channel(PeerQualities::default());
+        let (bluetooth, _) = watch::channel(None);
+        let (chat, _) = watch::channel(Vec::new());
+        let (outgoing_chat_tx, outgoing_chat_rx) = mpsc::unbounded_channel();
+        Self {
+            status,
+            quality,
+            latency,
+            history,
+            setup,
+            peer_qualities,
+            bluetooth,
+            chat,
+            outgoing_chat_tx,
+            outgoing_chat_rx: Some(outgoing_chat_rx),
+            diagnostics_window: None,
+            chat_window: None,
+        }
+    }
+
+    // The main window's own state, on each of its renders
+    pub fn publish(&self, status: &ConnectionStatus, bluetooth: &Option<BluetoothDevice>, chat: &[ChatEntry]) {
+        self.status.send_if_modified(|current| replace_if_changed(current, status));
+        self.bluetooth.send_if_modified(|current| replace_if_changed(current, bluetooth));
+        self.chat.send_if_modified(|current| {
+            if current.as_slice() == chat {
+                false
+            } else {
+                *current = chat.to_vec();
+                true
+            }
+        });
+    }
+
+    // From the stats loop; None values are left as they are
+    pub fn publish_metrics(
+        &self,
+        quality: Option<&ConnectionQuality>,
+        latency: Option<&LatencyBreakdown>,
+        history: Option<&QualityHistory>,
+        setup: Option<&CallSetupTime>,
+    ) {
+        if let Some(quality) = quality {
+            self.quality.send_if_modified(|current| replace_if_changed(current, quality));
+        }
+        if let Some(latency) = latency {
+            self.latency.send_if_modified(|current| replace_if_changed(current, latency));
+        }
+        if let Some(history) = history {
+            self.history.send_if_modified(|current| replace_if_changed(current, history));
+        }
+        if let Some(setup) = setup {
+            self.setup.send_if_modified(|current| replace_if_changed(current, setup));
+        }
+    }
+
+    // Between calls every metric goes back to nothing measured
+    pub fn clear_metrics(&self) {
+        self.publish_metrics(
+            Some(&ConnectionQuality::default()),
+            Some(&LatencyBreakdown::default()),
+            Some(&QualityHistory::default()),
+            Some(&CallSetupTime::default()),
+        );
+        self.update_peer_qualities(|qualities| qualities.clear());
+    }
+
+    // Subscribers only hear of it when something changed
+    pub fn update_peer_qualities(&self, update: impl FnOnce(&mut PeerQualities)) {
+        self.peer_qualities.send_if_modified(|current| {
+            let before = current.clone();
+            update(current);
+            *current != before
+        });
+    }
+
+    pub fn metrics(&self) -> MetricFeeds {
+        MetricFeeds {
+            quality: self.quality.subscribe(),
+            latency: self.latency.subscribe(),
+            history: self.history.subscribe(),
+            setup: self.setup.subscribe(),
+            peer_qualities: self.peer_qualities.subscribe(),
+        }
+    }
+
+    // Chat lines typed into a popped-out window; only the main window can take these
+    pub fn take_outgoing_chat(&mut self) -> Option<mpsc::UnboundedReceiver<String>> {
+        self.outgoing_chat_rx.take()
+    }
+
+    pub fn is_diagnostics_popped(&self) -> bool {
+        is_open(&self.diagnostics_window)
+    }
+
+    pub fn is_chat_popped(&self) -> bool {
+        is_open(&self.chat_window)
+    }
+
+    pub fn pop_out_diagnostics(&mut self, window: &DesktopContext) {
+        if self.is_diagnostics_popped() {
+            return;
+        }
+        let dom = VirtualDom::new_with_props(
+            DiagnosticsWindow,
+            DiagnosticsWindowProps {
+                status_rx: self.status.subscribe(),
+                quality_rx: self.quality.subscribe(),
+                latency_rx: self.latency.subscribe(),
+                history_rx: self.history.subscribe(),
+                setup_rx: self.setup.subscribe(),
+                bluetooth_rx: self.bluetooth.subscribe(),
+            },
+        );
+        self.diagnostics_window = Some(window.new_window(dom, window_config(tr("diagnostics.title"), 420.0, 520.0)));
+    }
+
+    pub fn pop_out_chat(&mut self, window: &DesktopContext, local_peer_id: String) {
+        if self.is_chat_popped() {
+            return;
+        }
+        let dom = VirtualDom::new_with_props(
+            ChatWindow,
+            ChatWindowProps {
+                local_peer_id,
+                entries_rx: self.chat.subscribe(),
+                outgoing_tx: self.outgoing_chat_tx.clone(),
+            },
+        );
+        self.chat_window = Some(window.new_window(dom, window_config(tr("chat.title"), 380.0, 480.0)));
+    }
+
+    pub fn dock_diagnostics(&mut self) {
+        close(self.diagnostics_window.take());
+    }
+
+    pub fn dock_chat(&mut self) {
+        close(self.chat_window.take());
+    }
+}
+
+// What the main window's metric panels follow
+#[derive(Clone)]
+pub struct MetricFeeds {
+    pub quality: watch::Receiver<ConnectionQuality>,
+    pub latency: watch::Receiver<LatencyBreakdown>,
+    pub history: watch::Receiver<QualityHistory>,
+    pub setup: watch::Receiver<CallSetupTime>,
+    pub peer_qualities: watch::Receiver<PeerQualities>,
+}
+
+// Component state that follows a watch channel. Only the component calling this
+// re-renders when the channel changes.
+pub fn use_feed<'a, T: Clone + 'static>(cx: &'a ScopeState, receiver: &watch::Receiver<T>) -> &'a UseState<T> {
+    let value = use_state(cx, || receiver.borrow().clone());
+    use_future(cx, (), |_| {
+        let value = value.clone();
+        let mut receiver = receiver.clone();
+        async move {
+            while receiver.changed().await.is_ok() {
+                let new_value = receiver.borrow().clone();
+                value.set(new_value);
+            }
+        }
+    });
+    value
+}
+
+fn replace_if_changed<T: Clone + PartialEq>(current: &mut T, new: &T) -> bool {
+    if current == new {
+        false
+    } else {
+        *current = new.clone();
+        true
+    }
+}
+
+fn is_open(window: &Option<Weak<DesktopService>>) -> bool {
+    window.as_ref().map_or(false, |w| w.upgrade().is_some())
+}
+
+fn close(window: Option<Weak<DesktopService>>) {
+    if let Some(window) = window.and_then(|w| w.upgrade()) {
+        window.close();
+    }
+}
+
+fn window_config(title: &str, width: f64, height: f64) -> Config {
+    Config::new().with_window(
+        WindowBuilder::new()
+            .with_title(title)
+            .with_inner_size(LogicalSize::new(width, height)),
+    )
+}
@@ -0,0 +1,33 @@
+use dioxus::prelude::*;
+
+use webrtc_client::i18n::tr;
+use webrtc_client::presence::PresenceStatus;
+
+#[derive(Props, PartialEq)]
+pub struct PresenceDotProps {
+    // None until the peer has told us
+    status: Option<PresenceStatus>,
+}
+
+pub fn PresenceDot(cx: Scope<PresenceDotProps>) -> Element {
+    let (class, label) = match cx.props.status {
+        Some(status) => (status.as_str(), presence_label(status)),
+        None => ("unknown", tr("presence.unknown")),
+    };
+
+    cx.render(rsx! {
+        span { class: "presence-dot {class}",
+            role: "img",
+            title: label,
+            aria_label: label,
+        }
+    })
+}
+
+fn presence_label(status: PresenceStatus) -> &'static str {
+    match status {
+        PresenceStatus::Online => tr("presence.online"),
+        PresenceStatus::Away => tr("presence.away"),
+        PresenceStatus::InCall => tr("presence.in_call"),
+    }
+}
@@ -0,0 +1,55 @@
+use dioxus::prelude::*;
+
+use webrtc_client::i18n::{tr, tr_args};
+use webrtc_client::security::CallSecurity;
+
+#[derive(Props, PartialEq)]
+pub struct SecurityIndicatorProps {
+    security: CallSecurity,
+}
+
+// Lock icon in the call header; opening it lists what protects the call
+pub fn SecurityIndicator(cx: Scope<SecurityIndicatorProps>) -> Element {
+    let security = &cx.props.security;
+    let (icon, summary, class) = match (security.encrypted, security.end_to_end()) {
+        (false, _) => ("🔓", tr("security.not_encrypted"), "insecure"),
+        (true, true) => ("🔒", tr("security.end_to_end"), "end-to-end"),
+        (true, false) => ("🔒", tr("security.encrypted"), "encrypted"),
+    };
+
+    cx.render(rsx! {
+        details { class: "security-indicator {class}",
+            summary {
+                title: "{summary}",
+                aria_label: "{summary}",
+                span { aria_hidden: "true", "{icon}" }
+            }
+            div { class: "security-details",
+                role: "dialog",
+                aria_label: tr("security.title"),
+                p { "{summary}" }
+                ul {
+                    security.srtp_profile.as_ref().map(|profile| rsx!(
+                        li { {tr_args("security.cipher", &[("profile", profile.as_str())])} }
+                    )),
+                    li {
+                        {match &security.media_relay {
+                            Some(relay) => tr_args("security.media_relay", &[("relay", relay.as_str())]),
+                            None if security.turn_relayed => tr("security.turn").to_string(),
+                            None => tr("security.direct").to_string(),
+                        }}
+                    }
+                }
+                security.local_fingerprint.as_ref().zip(security.remote_fingerprint.as_ref()).map(|(local, remote)| rsx!(
+                    dl { class: "security-fingerprints",
+                        dt { {tr("security.local_fingerprint")} }
+                        dd { "{local}" }
+                        dt { {tr("security.remote_fingerprint")} }
+                        dd { "{remote}" }
+                    }
+                    p { class: "hint", {tr("security.compare")} }
+                ))
+            }
+        }
+    })
+}
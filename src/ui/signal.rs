@@ -0,0 +1,64 @@
+use dioxus::prelude::*;
+use tokio::sync::watch;
+
+use webrtc_client::i18n::{tr, tr_args};
+use webrtc_client::metrics::{signal_bars, ConnectionQuality, PeerQualities};
+
+use super::diagnostics::get_quality_class;
+use super::popout::use_feed;
+
+#[derive(Props, PartialEq)]
+pub struct SignalStrengthProps {
+    quality: Option<ConnectionQuality>,
+}
+
+// Four-bar icon next to a participant; hovering shows the numbers behind it
+pub fn SignalStrength(cx: Scope<SignalStrengthProps>) -> Element {
+    let Some(quality) = &cx.props.quality else {
+        return cx.render(rsx! {
+            span { class: "signal-strength idle",
+                title: tr("signal.no_media"),
+                aria_label: tr("signal.no_media"),
+            }
+        });
+    };
+
+    let bars = signal_bars(quality.quality_score);
+    let details = tr_args(
+        "signal.details",
+        &[
+            ("score", &quality.quality_score.to_string()),
+            ("rtt", &format!("{:.0}", quality.round_trip_time)),
+            ("loss", &format!("{:.1}", quality.packet_loss_rate)),
+            ("bitrate", &format!("{:.0}", quality.bitrate)),
+        ],
+    );
+
+    cx.render(rsx! {
+        span { class: "signal-strength {get_quality_class(quality.quality_score)}",
+            role: "img",
+            title: "{details}",
+            aria_label: "{details}",
+            (1..=4u8).map(|bar| {
+                let lit = if bar <= bars { "lit" } else { "" };
+                rsx! {
+                    span { key: "{bar}", class: "signal-bar bar-{bar} {lit}" }
+                }
+            })
+        }
+    })
+}
+
+#[derive(Props)]
+pub struct PeerSignalProps<'a> {
+    peer_id: &'a str,
+    qualities: &'a watch::Receiver<PeerQualities>,
+}
+
+// A participant's bars, following the stats feed without the peer list re-rendering
+pub fn PeerSignal<'a>(cx: Scope<'a, PeerSignalProps<'a>>) -> Element<'a> {
+    let qualities = use_feed(cx, cx.props.qualities);
+    cx.render(rsx! {
+        SignalStrength { quality: qualities.get().get(cx.props.peer_id).cloned() }
+    })
+}
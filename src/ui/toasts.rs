@@ -0,0 +1,43 @@
+use dioxus::prelude::*;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+// How long each toast stays up
+pub const TOAST_DURATION: Duration = Duration::from_secs(4);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Toast {
+    pub id: u64,
+    pub text: String,
+    pub shown_at: Instant,
+}
+
+impl Toast {
+    // Ids are unique across everything that shows toasts
+    pub fn new(text: String) -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+        Self {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            text,
+            shown_at: Instant::now(),
+        }
+    }
+}
+
+#[derive(Props, PartialEq)]
+pub struct ToastsProps {
+    toasts: Vec<Toast>,
+}
+
+// Passing notices in a corner; screen readers announce them without moving focus
+pub fn Toasts(cx: Scope<ToastsProps>) -> Element {
+    cx.render(rsx! {
+        div { class: "toasts",
+            role: "status",
+            aria_live: "polite",
+            cx.props.toasts.iter().map(|toast| rsx! {
+                div { key: "{toast.id}", class: "toast", "{toast.text}" }
+            })
+        }
+    })
+}
@@ -0,0 +1,99 @@
+use std::collections::HashMap;
+use webrtc::ice::candidate::CandidateType;
+use webrtc::stats::{StatsReport, StatsReportType};
+
+use crate::server_config::IceServerConfig;
+
+// How the last call to a peer got connected. The next call to them tries the same way
+// first instead of waiting for every candidate pair to be checked.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WarmRoute {
+    // Only a TURN relay got through; the next call skips host and server-reflexive
+    // candidates, which would just time out again
+    pub relayed: bool,
+    // The remote candidate of the pair that connected, as the peer trickled it. Added
+    // as soon as the next call starts, so that pair is checked before the peer's new
+    // candidates even arrive.
+    pub remote_candidate: Option<String>,
+}
+
+impl WarmRoute {
+    // Relay-only needs a TURN server to relay through; the config may have lost it
+    pub fn relay_only(&self, ice_servers: &[IceServerConfig]) -> bool {
+        self.relayed
+            && ice_servers
+                .iter()
+                .flat_map(|server| &server.urls)
+                .any(|url| url.starts_with("turn:") || url.starts_with("turns:"))
+    }
+}
+
+// Routes by the peer at the other end of the connection (a media relay counts as a
+// peer). Candidates belong to the network they were gathered on, so a network change
+// clears the lot.
+#[derive(Debug, Clone, Default)]
+pub struct RouteCache {
+    routes: HashMap<String, WarmRoute>,
+}
+
+impl RouteCache {
+    pub fn remember(&mut self, peer_id: &str, route: WarmRoute) {
+        self.routes.insert(peer_id.to_string(), route);
+    }
+
+    pub fn get(&self, peer_id: &str) -> Option<&WarmRoute> {
+        self.routes.get(peer_id)
+    }
+
+    // The route didn't connect this time; the next call starts cold
+    pub fn forget(&mut self, peer_id: &str) {
+        self.routes.remove(peer_id);
+    }
+
+    pub fn clear(&mut self) {
+        self.routes.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.routes.is_empty()
+    }
+}
+
+// The route of the nominated candidate pair; None until ICE has connected
+pub fn nominated_route(stats: &StatsReport, trickled: &[String]) -> Option<WarmRoute> {
+    let pair = stats.reports.values().find_map(|report| match report {
+        StatsReportType::CandidatePair(pair) if pair.nominated => Some(pair),
+        _ => None,
+    })?;
+    let mut relayed = false;
+    let mut remote_candidate = None;
+    for report in stats.reports.values() {
+        match report {
+            StatsReportType::LocalCandidate(candidate) if candidate.id == pair.local_candidate_id => {
+                relayed |= candidate.candidate_type == CandidateType::Relay;
+            }
+            StatsReportType::RemoteCandidate(candidate) if candidate.id == pair.remote_candidate_id => {
+                relayed |= candidate.candidate_type == CandidateType::Relay;
+                remote_candidate = matching_candidate(trickled, &candidate.ip, candidate.port);
+            }
+            _ => {}
+        }
+    }
+    Some(WarmRoute { relayed, remote_candidate })
+}
+
+// The trickled candidate line for an address, e.g. from
+// "candidate:1 1 udp 2130706431 192.168.1.20 50000 typ host"
+pub fn matching_candidate(trickled: &[String], ip: &str, port: u16) -> Option<String> {
+    trickled
+        .iter()
+        .find(|candidate| candidate_address(candidate) == Some((ip, port)))
+        .cloned()
+}
+
+pub fn candidate_address(candidate: &str) -> Option<(&str, u16)> {
+    let mut fields = candidate.split_whitespace().skip(4);
+    let ip = fields.next()?;
+    let port = fields.next()?.parse().ok()?;
+    Some((ip, port))
+}
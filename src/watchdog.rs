@@ -0,0 +1,141 @@
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+pub const WATCHDOG_INTERVAL: Duration = Duration::from_secs(1);
+
+// How long a path has to stay down before it counts. Audio arrives every 20ms, so three
+// seconds without a packet is not a gap in speech; shorter blips heal by themselves.
+pub const STALL_AFTER: Duration = Duration::from_secs(3);
+
+// How long a connected call may go without its first packet. The first RTP normally
+// follows DTLS within a second; this leaves room for a slow handshake.
+pub const NO_AUDIO_AFTER: Duration = Duration::from_secs(10);
+
+// What broke, judged from which of the two paths still works
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureKind {
+    // The WebSocket is gone but the peer's audio still arrives: only the server is down
+    ServerDown,
+    // Signaling is fine but the peer's media stopped: the peer left or lost its network
+    PeerGone,
+    // Both stopped at once: our own network
+    LocalNetwork,
+    // ICE connected but not a single packet arrived: no common codec, or a firewall
+    // that lets the connectivity checks through but drops the media
+    NoAudio,
+}
+
+impl FailureKind {
+    pub fn message_key(self) -> &'static str {
+        match self {
+            FailureKind::ServerDown => "watchdog.server_down",
+            FailureKind::PeerGone => "watchdog.peer_gone",
+            FailureKind::LocalNetwork => "watchdog.local_network",
+            FailureKind::NoAudio => "watchdog.no_audio",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MediaHealth {
+    pub ice_connected: bool,
+    // Inbound RTP packets since the call started; only its growth matters
+    pub inbound_packets: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HealthSample {
+    pub signaling_up: bool,
+    // None outside an established call
+    pub media: Option<MediaHealth>,
+}
+
+// The media path on its own, before it is set against signaling
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MediaPath {
+    Fine,
+    Stalled,
+    // Connected for NO_AUDIO_AFTER without the first packet
+    Silent,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    Failed(FailureKind),
+    Recovered,
+}
+
+// Correlates the signaling connection with the call's media path, sampled every
+// WATCHDOG_INTERVAL, and reports when the diagnosis changes
+#[derive(Debug, Default)]
+pub struct Watchdog {
+    signaling_down_since: Option<Instant>,
+    // Media is only judged as stalled once it has flowed in this call; before that only
+    // its absence after ICE connected counts
+    media_seen: bool,
+    connected_since: Option<Instant>,
+    inbound_packets: u64,
+    media_down_since: Option<Instant>,
+    failure: Option<FailureKind>,
+}
+
+impl Watchdog {
+    pub fn observe(&mut self, sample: HealthSample, now: Instant) -> Option<Verdict> {
+        self.signaling_down_since = match (sample.signaling_up, self.signaling_down_since) {
+            (true, _) => None,
+            (false, since) => Some(since.unwrap_or(now)),
+        };
+        let signaling_down = self.signaling_down_since.map_or(false, |since| now - since >= STALL_AFTER);
+        let media = self.media_path(sample.media, now);
+
+        let failure = match (signaling_down, media) {
+            (true, MediaPath::Fine) => Some(FailureKind::ServerDown),
+            (false, MediaPath::Stalled) => Some(FailureKind::PeerGone),
+            (true, MediaPath::Stalled | MediaPath::Silent) => Some(FailureKind::LocalNetwork),
+            (false, MediaPath::Silent) => Some(FailureKind::NoAudio),
+            (false, MediaPath::Fine) => None,
+        };
+        if failure == self.failure {
+            return None;
+        }
+        let previous = std::mem::replace(&mut self.failure, failure);
+        match failure {
+            Some(kind) => Some(Verdict::Failed(kind)),
+            None => previous.map(|_| Verdict::Recovered),
+        }
+    }
+
+    fn media_path(&mut self, media: Option<MediaHealth>, now: Instant) -> MediaPath {
+        let Some(media) = media else {
+            // Between calls; the next one starts from scratch
+            self.media_seen = false;
+            self.connected_since = None;
+            self.inbound_packets = 0;
+            self.media_down_since = None;
+            return MediaPath::Fine;
+        };
+        let flowing = media.ice_connected && media.inbound_packets > self.inbound_packets;
+        self.inbound_packets = media.inbound_packets;
+        if flowing {
+            self.media_seen = true;
+            self.media_down_since = None;
+            return MediaPath::Fine;
+        }
+        if !self.media_seen {
+            // Still setting up ICE, or restarting it: the wait starts over once it connects
+            if !media.ice_connected {
+                self.connected_since = None;
+                return MediaPath::Fine;
+            }
+            let since = *self.connected_since.get_or_insert(now);
+            return if now - since >= NO_AUDIO_AFTER { MediaPath::Silent } else { MediaPath::Fine };
+        }
+        let since = *self.media_down_since.get_or_insert(now);
+        if now - since >= STALL_AFTER {
+            MediaPath::Stalled
+        } else {
+            MediaPath::Fine
+        }
+    }
+}
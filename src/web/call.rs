@@ -0,0 +1,249 @@
+use anyhow::{anyhow, Result};
+use js_sys::{Array, Map, Reflect};
+use serde::{Deserialize, Serialize};
+use std::cell::{Cell, RefCell};
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{
+    HtmlAudioElement, MediaStream, MediaStreamConstraints, MediaStreamTrack, RtcConfiguration, RtcIceCandidateInit,
+    RtcIceServer, RtcPeerConnection, RtcPeerConnectionIceEvent, RtcSdpType, RtcSessionDescriptionInit, RtcTrackEvent,
+};
+
+use super::js_error;
+use crate::metrics::ConnectionQuality;
+use crate::server_config::IceServerConfig;
+
+// Offers and answers travel as the JSON webrtc-rs makes of an RTCSessionDescription,
+// so desktop and browser clients can negotiate with each other
+#[derive(Serialize, Deserialize)]
+struct Description {
+    #[serde(rename = "type")]
+    kind: String,
+    sdp: String,
+}
+
+// One call's RTCPeerConnection, with the microphone sent and the remote audio played
+// through an <audio> element the browser keeps off-page
+pub struct WebCall {
+    connection: RtcPeerConnection,
+    microphone: MediaStream,
+    speaker: HtmlAudioElement,
+    // Trickled candidates that beat the offer/answer here
+    pending_candidates: RefCell<Vec<String>>,
+    // (bytes received, stats timestamp in ms) from the last quality reading
+    previous_bytes: Cell<Option<(f64, f64)>>,
+    _on_ice_candidate: Closure<dyn FnMut(RtcPeerConnectionIceEvent)>,
+    _on_track: Closure<dyn FnMut(RtcTrackEvent)>,
+}
+
+impl WebCall {
+    // Asks for the microphone. `on_candidate` gets each local ICE candidate to trickle
+    // to the other side.
+    pub async fn start(ice_servers: &[IceServerConfig], on_candidate: impl Fn(String) + 'static) -> Result<Self> {
+        let config = RtcConfiguration::new();
+        let servers: Array = ice_servers.iter().map(|server| JsValue::from(to_rtc(server))).collect();
+        config.set_ice_servers(&servers);
+        let connection = RtcPeerConnection::new_with_configuration(&config).map_err(js_error)?;
+
+        let microphone = microphone().await?;
+        for track in microphone.get_audio_tracks().iter() {
+            connection.add_track_0(track.unchecked_ref::<MediaStreamTrack>(), &microphone);
+        }
+
+        let speaker = HtmlAudioElement::new().map_err(js_error)?;
+        speaker.set_autoplay(true);
+        let on_track = {
+            let speaker = speaker.clone();
+            Closure::<dyn FnMut(RtcTrackEvent)>::new(move |event: RtcTrackEvent| {
+                if let Ok(stream) = event.streams().get(0).dyn_into::<MediaStream>() {
+                    speaker.set_src_object(Some(&stream));
+                    let _ = speaker.play();
+                }
+            })
+        };
+        let on_ice_candidate = Closure::<dyn FnMut(RtcPeerConnectionIceEvent)>::new(move |event: RtcPeerConnectionIceEvent| {
+            // None marks the end of gathering
+            if let Some(candidate) = event.candidate() {
+                on_candidate(candidate.candidate());
+            }
+        });
+        connection.set_ontrack(Some(on_track.as_ref().unchecked_ref()));
+        connection.set_onicecandidate(Some(on_ice_candidate.as_ref().unchecked_ref()));
+
+        Ok(Self {
+            connection,
+            microphone,
+            speaker,
+            pending_candidates: RefCell::new(Vec::new()),
+            previous_bytes: Cell::new(None),
+            _on_ice_candidate: on_ice_candidate,
+            _on_track: on_track,
+        })
+    }
+
+    pub async fn create_offer(&self) -> Result<String> {
+        let offer = JsFuture::from(self.connection.create_offer()).await.map_err(js_error)?;
+        self.set_local(RtcSdpType::Offer, "offer", sdp_of(&offer)?).await
+    }
+
+    // Returns the answer to send back
+    pub async fn handle_offer(&self, sdp: String) -> Result<String> {
+        self.set_remote(RtcSdpType::Offer, &sdp).await?;
+        let answer = JsFuture::from(self.connection.create_answer()).await.map_err(js_error)?;
+        self.set_local(RtcSdpType::Answer, "answer", sdp_of(&answer)?).await
+    }
+
+    pub async fn handle_answer(&self, sdp: String) -> Result<()> {
+        self.set_remote(RtcSdpType::Answer, &sdp).await
+    }
+
+    // Candidates can arrive before the description they belong to; those wait until
+    // it is set
+    pub async fn add_ice_candidate(&self, candidate: String) -> Result<()> {
+        if self.connection.remote_description().is_none() {
+            self.pending_candidates.borrow_mut().push(candidate);
+            return Ok(());
+        }
+        add_candidate(&self.connection, &candidate).await
+    }
+
+    // Stops sending without renegotiating; the track keeps running
+    pub fn set_muted(&self, muted: bool) {
+        for track in self.microphone.get_audio_tracks().iter() {
+            track.unchecked_ref::<MediaStreamTrack>().set_enabled(!muted);
+        }
+    }
+
+    // The same score the desktop computes, from the browser's getStats()
+    pub async fn quality(&self) -> Result<ConnectionQuality> {
+        let report: Map = JsFuture::from(self.connection.get_stats()).await.map_err(js_error)?.unchecked_into();
+        let mut quality = ConnectionQuality::default();
+        let mut pair_rtt = None;
+        let mut bytes_received = 0.0;
+        let mut timestamp = None;
+        report.for_each(&mut |stats, _| {
+            let kind = string_field(&stats, "kind");
+            match string_field(&stats, "type").as_deref() {
+                Some("remote-inbound-rtp") if kind.as_deref() == Some("audio") => {
+                    if let Some(rtt) = number_field(&stats, "roundTripTime") {
+                        quality.round_trip_time = rtt * 1000.0;
+                    }
+                    if let Some(lost) = number_field(&stats, "fractionLost") {
+                        quality.packet_loss_rate = lost * 100.0;
+                    }
+                }
+                Some("inbound-rtp") if kind.as_deref() == Some("audio") => {
+                    quality.jitter = number_field(&stats, "jitter").unwrap_or_default() * 1000.0;
+                    quality.audio_level = number_field(&stats, "audioLevel").map_or(-127.0, |level| 20.0 * level.max(1e-7).log10());
+                    bytes_received += number_field(&stats, "bytesReceived").unwrap_or_default();
+                    timestamp = number_field(&stats, "timestamp");
+                }
+                Some("candidate-pair") if Reflect::get(&stats, &"nominated".into()).ok() == Some(JsValue::TRUE) => {
+                    pair_rtt = number_field(&stats, "currentRoundTripTime").map(|rtt| rtt * 1000.0);
+                }
+                _ => {}
+            }
+        });
+        // RTCP receiver reports give the media path RTT; the candidate pair's is the
+        // fallback before the first report arrives
+        if quality.round_trip_time == 0.0 {
+            quality.round_trip_time = pair_rtt.unwrap_or_default();
+        }
+        if let Some(now) = timestamp {
+            if let Some((bytes, at)) = self.previous_bytes.get() {
+                let seconds = (now - at) / 1000.0;
+                if seconds > 0.0 {
+                    quality.bitrate = (bytes_received - bytes).max(0.0) * 8.0 / seconds / 1000.0;
+                }
+            }
+            self.previous_bytes.set(Some((bytes_received, now)));
+        }
+        quality.calculate_quality_score();
+        Ok(quality)
+    }
+
+    // Returns the description as it is sent
+    async fn set_local(&self, kind: RtcSdpType, name: &str, sdp: String) -> Result<String> {
+        let description = RtcSessionDescriptionInit::new(kind);
+        description.set_sdp(&sdp);
+        JsFuture::from(self.connection.set_local_description(&description)).await.map_err(js_error)?;
+        Ok(serde_json::to_string(&Description {
+            kind: name.to_string(),
+            sdp,
+        })?)
+    }
+
+    async fn set_remote(&self, kind: RtcSdpType, json: &str) -> Result<()> {
+        let received: Description = serde_json::from_str(json)?;
+        let description = RtcSessionDescriptionInit::new(kind);
+        description.set_sdp(&received.sdp);
+        JsFuture::from(self.connection.set_remote_description(&description)).await.map_err(js_error)?;
+        let pending = self.pending_candidates.take();
+        for candidate in pending {
+            if let Err(e) = add_candidate(&self.connection, &candidate).await {
+                web_sys::console::warn_1(&format!("Dropping queued ICE candidate: {}", e).into());
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for WebCall {
+    fn drop(&mut self) {
+        self.connection.set_ontrack(None);
+        self.connection.set_onicecandidate(None);
+        self.connection.close();
+        for track in self.microphone.get_tracks().iter() {
+            track.unchecked_ref::<MediaStreamTrack>().stop();
+        }
+        self.speaker.set_src_object(None);
+    }
+}
+
+fn to_rtc(server: &IceServerConfig) -> RtcIceServer {
+    let rtc = RtcIceServer::new();
+    let urls: Array = server.urls.iter().map(|url| JsValue::from_str(url)).collect();
+    rtc.set_urls(&urls);
+    // Credentials are only needed for turn:/turns: URLs
+    if !server.username.is_empty() {
+        rtc.set_username(&server.username);
+        rtc.set_credential(&server.credential);
+    }
+    rtc
+}
+
+async fn microphone() -> Result<MediaStream> {
+    let window = web_sys::window().ok_or_else(|| anyhow!("No window"))?;
+    let devices = window.navigator().media_devices().map_err(js_error)?;
+    let constraints = MediaStreamConstraints::new();
+    constraints.set_audio(&JsValue::TRUE);
+    let promise = devices.get_user_media_with_constraints(&constraints).map_err(js_error)?;
+    let stream = JsFuture::from(promise)
+        .await
+        .map_err(|e| anyhow!("Microphone not available: {}", js_error(e)))?;
+    Ok(stream.unchecked_into())
+}
+
+// Candidates are sent as bare candidate lines, like the desktop does; there is a
+// single audio m-line to attach them to
+async fn add_candidate(connection: &RtcPeerConnection, candidate: &str) -> Result<()> {
+    let init = RtcIceCandidateInit::new(candidate);
+    init.set_sdp_m_line_index(Some(0));
+    JsFuture::from(connection.add_ice_candidate_with_opt_rtc_ice_candidate_init(Some(&init)))
+        .await
+        .map_err(js_error)?;
+    Ok(())
+}
+
+fn sdp_of(description: &JsValue) -> Result<String> {
+    string_field(description, "sdp").ok_or_else(|| anyhow!("Session description has no SDP"))
+}
+
+fn string_field(object: &JsValue, field: &str) -> Option<String> {
+    Reflect::get(object, &field.into()).ok()?.as_string()
+}
+
+fn number_field(object: &JsValue, field: &str) -> Option<f64> {
+    Reflect::get(object, &field.into()).ok()?.as_f64()
+}
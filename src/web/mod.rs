@@ -0,0 +1,20 @@
+// The browser build's transport and media: a WebSocket for signaling and an
+// RTCPeerConnection for the call, both through web-sys. Messages, call state and quality
+// scores are the same types the desktop uses.
+mod call;
+mod signaling;
+
+pub use call::WebCall;
+pub use signaling::WebSignalingClient;
+
+use anyhow::{anyhow, Error};
+use wasm_bindgen::JsValue;
+
+// JS exceptions carry their message in different places depending on what threw
+fn js_error(value: JsValue) -> Error {
+    let message = value
+        .as_string()
+        .or_else(|| js_sys::Reflect::get(&value, &"message".into()).ok().and_then(|message| message.as_string()))
+        .unwrap_or_else(|| format!("{:?}", value));
+    anyhow!(message)
+}
@@ -0,0 +1,105 @@
+use anyhow::{anyhow, Result};
+use futures::channel::{mpsc, oneshot};
+use std::cell::RefCell;
+use std::rc::Rc;
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::JsCast;
+use web_sys::{Event, MessageEvent, WebSocket};
+
+use super::js_error;
+use crate::identity::Identity;
+use crate::protocol::{encode_frame, FrameDecoder, SignalingMessage};
+
+// The socket and the callbacks it calls, which must live as long as it does
+struct Connection {
+    socket: WebSocket,
+    _on_message: Closure<dyn FnMut(MessageEvent)>,
+    _on_close: Closure<dyn FnMut(Event)>,
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        let _ = self.socket.close();
+    }
+}
+
+// Same frames and signing as the desktop SignalingClient. Clones share the socket, so
+// button handlers can send while another task receives; it closes with the last clone.
+#[derive(Clone)]
+pub struct WebSignalingClient {
+    connection: Rc<Connection>,
+    // Signs everything sent when set
+    identity: Option<Identity>,
+}
+
+impl WebSignalingClient {
+    // Incoming messages arrive on the receiver, which ends when the socket closes
+    pub async fn connect(url: &str) -> Result<(Self, mpsc::UnboundedReceiver<SignalingMessage>)> {
+        let socket = WebSocket::new(url).map_err(js_error)?;
+        let (tx, rx) = mpsc::unbounded();
+        let on_message = {
+            let tx = tx.clone();
+            let mut frames = FrameDecoder::default();
+            Closure::<dyn FnMut(MessageEvent)>::new(move |event: MessageEvent| {
+                if let Some(text) = event.data().as_string() {
+                    let _ = tx.unbounded_send(frames.decode(&text));
+                }
+            })
+        };
+        let on_close = Closure::<dyn FnMut(Event)>::new(move |_: Event| tx.close_channel());
+        socket.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+        socket.set_onclose(Some(on_close.as_ref().unchecked_ref()));
+        opened(&socket).await?;
+
+        let connection = Connection {
+            socket,
+            _on_message: on_message,
+            _on_close: on_close,
+        };
+        Ok((
+            Self {
+                connection: Rc::new(connection),
+                identity: None,
+            },
+            rx,
+        ))
+    }
+
+    pub fn set_identity(&mut self, identity: Identity) {
+        self.identity = Some(identity);
+    }
+
+    pub fn send(&self, msg: SignalingMessage) -> Result<()> {
+        let frame = encode_frame(&msg, self.identity.as_ref())?;
+        self.connection
+            .socket
+            .send_with_str(&frame)
+            .map_err(|e| anyhow!("Failed to send message: {}", js_error(e)))
+    }
+}
+
+// Until the socket opens, or fails if it errors or closes first
+async fn opened(socket: &WebSocket) -> Result<()> {
+    let (tx, rx) = oneshot::channel();
+    let tx = Rc::new(RefCell::new(Some(tx)));
+    let settle = |opened: bool| {
+        let tx = tx.clone();
+        Closure::<dyn FnMut(Event)>::new(move |_: Event| {
+            if let Some(tx) = tx.borrow_mut().take() {
+                let _ = tx.send(opened);
+            }
+        })
+    };
+    let on_open = settle(true);
+    let on_error = settle(false);
+    socket.set_onopen(Some(on_open.as_ref().unchecked_ref()));
+    socket.set_onerror(Some(on_error.as_ref().unchecked_ref()));
+    let opened = rx.await.unwrap_or(false);
+    socket.set_onopen(None);
+    socket.set_onerror(None);
+    if opened {
+        Ok(())
+    } else {
+        Err(anyhow!("Failed to connect to {}", socket.url()))
+    }
+}
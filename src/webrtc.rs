@@ -1,88 +1,706 @@
 use anyhow::Result;
+use bytes::Bytes;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
-use tokio::sync::Mutex;
-use webrtc::api::APIBuilder;
+use std::time::Duration;
+use tokio::sync::{broadcast, Mutex, Notify};
 use webrtc::api::media_engine::MediaEngine;
+use webrtc::ice_transport::ice_candidate::RTCIceCandidateInit;
 use webrtc::ice_transport::ice_server::RTCIceServer;
 use webrtc::peer_connection::configuration::RTCConfiguration;
+use webrtc::peer_connection::policy::ice_transport_policy::RTCIceTransportPolicy;
 use webrtc::peer_connection::peer_connection_state::RTCPeerConnectionState;
 use webrtc::peer_connection::RTCPeerConnection;
+use webrtc::peer_connection::offer_answer_options::RTCOfferOptions;
 use webrtc::peer_connection::signaling_state::RTCSignalingState;
+use webrtc::peer_connection::sdp::sdp_type::RTCSdpType;
+use webrtc::peer_connection::sdp::session_description::RTCSessionDescription;
+use webrtc::sdp::SessionDescription;
 use webrtc::ice_transport::ice_connection_state::RTCIceConnectionState;
-use webrtc::track::track_local::track_local_static_sample::TrackLocalStaticSample;
-use webrtc::track::track_local::{TrackLocal, TrackLocalWriter};
+use webrtc::ice_transport::ice_gatherer_state::RTCIceGathererState;
+use webrtc::dtls_transport::dtls_transport_state::RTCDtlsTransportState;
+use webrtc::track::track_local::TrackLocal;
 use webrtc::track::track_remote::TrackRemote;
-use webrtc::rtp_transceiver::rtp_codec::{RTCRtpCodecCapability, RTPCodecType};
+use webrtc::rtp_transceiver::rtp_codec::{RTCRtpCodecParameters, RTPCodecType};
 use webrtc::rtp_transceiver::rtp_receiver::RTCRtpReceiver;
 use webrtc::media::media_stream::MediaStream;
-use crate::audio::AudioPlayback;
+use webrtc::rtcp::goodbye::Goodbye;
+use webrtc::rtcp::packet::Packet;
+use webrtc::interceptor::registry::Registry;
+use webrtc::interceptor::InterceptorBuilder;
+use std::fmt;
+use serde::{Deserialize, Serialize};
+use crate::audio::codec::{StreamDecoder, PLAYBACK_SAMPLE_RATE};
+use crate::audio::{decode_frame_into, AudioBackend, AudioBackendKind, AudioCodec, AudioStreamHandle, AudioTrack, FramePool, LoudnessNormalizer};
+use crate::audio::tap::{start_tap, TapConfig, TapFrame, TapSource};
+use crate::audio::{pan_to_stereo_into, StereoLayout};
 use crate::connection::{ConnectionMonitor, ConnectionState};
-use crate::metrics::QualityMonitor;
+use crate::control::ControlChannel;
+use crate::dtmf::{telephone_event_parameters, DtmfSender, TELEPHONE_EVENT_MIME};
+use crate::factory::{MediaEngineCustomizer, RegistryBuilder, WebRTCFactory};
+use crate::impairment::ImpairmentConfig;
+use crate::latency::{LatencyBreakdown, LatencyProbe};
+use crate::ladder::{LadderConfig, QualityRung};
+use crate::sdp_hooks::{limit_bitrate, music_mode, quality_rung, SdpHooks, SdpLog, SdpRecorder, SdpStage};
+use crate::metrics::{QualityMonitor, StatsConfig};
+use crate::negotiation::{Direction, NegotiationKind, NegotiationLog};
+use crate::plugins;
+use crate::red::{red_parameters, RedDecoder, RedFormat};
+use crate::room::TrackOwners;
+use crate::security::{relayed_by_turn, sdp_fingerprint, CallSecurity};
+use crate::setup_time::{CallSetupTime, Milestone, SetupClock};
+use crate::tasks::TaskGroup;
+use crate::turn::TurnRestConfig;
+use crate::warmstart::{nominated_route, WarmRoute};
+
+// Lives with ServerConfig so the browser build can share it
+pub use crate::server_config::IceServerConfig;
+
+// How long close() waits for a call's tasks to notice they were cancelled
+pub const TASK_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(2);
+
+// Per-client media pipeline options, read when the peer connection is built
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WebRTCConfig {
+    pub ice_servers: Vec<IceServerConfig>,
+    // Audio codecs in the order to offer them; codecs left out are not offered at all.
+    // Empty keeps the default order (Opus, PCMU, PCMA).
+    pub codec_preferences: Vec<AudioCodec>,
+    // Advertised to the remote side as b=AS and Opus maxaveragebitrate; None is unlimited
+    pub max_bitrate_kbps: Option<u32>,
+    // For instruments and music: asks the other side for fullband stereo Opus, and no
+    // platform voice processing (noise suppression, AGC) on the mic
+    pub music_mode: bool,
+    // Step calls down to mono and narrowband on lossy or thin links, and back up
+    pub quality_ladder: LadderConfig,
+    // Offer RFC 2198 redundant audio for PCMU and PCMA. It's only sent while loss is at
+    // or above quality_ladder.redundancy_loss_percent.
+    pub redundant_audio: bool,
+    // Even out the playback levels of remote peers
+    pub normalize_loudness: bool,
+    // Place remote peers across the stereo field; playback turns stereo
+    pub spatial_audio: bool,
+    // An Offer with no Answer after answer_timeout_ms is sent again, up to offer_attempts
+    // times in all, before the call fails
+    pub answer_timeout_ms: u64,
+    pub offer_attempts: u32,
+    // Candidate gathering and connectivity checks must get the call connected within
+    // this long of the offer/answer completing
+    pub ice_timeout_ms: u64,
+    // Fetch short-lived TURN credentials from here and keep them fresh
+    pub turn_rest: Option<TurnRestConfig>,
+    pub interceptors: InterceptorConfig,
+    pub ice_transport: IceTransportConfig,
+    pub impairment: ImpairmentConfig,
+    pub stats: StatsConfig,
+    // Embedder-supplied interceptors; code-only, never persisted
+    #[serde(skip)]
+    pub custom_interceptors: CustomInterceptors,
+    #[serde(skip)]
+    pub sdp_hooks: SdpHooks,
+    // Remote sides see our audio under this stream id; set it to the peer id so they
+    // can tell participants apart
+    #[serde(skip)]
+    pub stream_id: String,
+}
+
+impl Default for WebRTCConfig {
+    fn default() -> Self {
+        Self {
+            ice_servers: vec![IceServerConfig {
+                urls: vec!["stun:stun.l.google.com:19302".to_string()],
+                ..Default::default()
+            }],
+            codec_preferences: Vec::new(),
+            max_bitrate_kbps: None,
+            music_mode: false,
+            quality_ladder: LadderConfig::default(),
+            redundant_audio: false,
+            normalize_loudness: true,
+            spatial_audio: false,
+            answer_timeout_ms: 10_000,
+            offer_attempts: 3,
+            ice_timeout_ms: 20_000,
+            turn_rest: None,
+            interceptors: InterceptorConfig::default(),
+            ice_transport: IceTransportConfig::default(),
+            impairment: ImpairmentConfig::default(),
+            stats: StatsConfig::default(),
+            custom_interceptors: CustomInterceptors::default(),
+            sdp_hooks: SdpHooks::default(),
+            stream_id: "webrtc-rs".to_string(),
+        }
+    }
+}
+
+impl WebRTCConfig {
+    pub fn answer_timeout(&self) -> Duration {
+        Duration::from_millis(self.answer_timeout_ms)
+    }
+
+    pub fn ice_timeout(&self) -> Duration {
+        Duration::from_millis(self.ice_timeout_ms)
+    }
+
+    pub fn rtc_ice_servers(&self) -> Vec<RTCIceServer> {
+        self.ice_servers.iter().map(IceServerConfig::to_rtc).collect()
+    }
+
+    // Telephone events always stay available so DTMF works with any audio codec. RED goes
+    // after the plain codecs so neither side picks it to start sending with.
+    pub fn audio_codec_parameters(&self) -> Vec<RTCRtpCodecParameters> {
+        let codecs: &[AudioCodec] = if self.codec_preferences.is_empty() {
+            &AudioCodec::ALL
+        } else {
+            &self.codec_preferences
+        };
+        let mut parameters: Vec<RTCRtpCodecParameters> = Vec::new();
+        for codec in codecs {
+            if !parameters.iter().any(|p| p.capability.mime_type == codec.mime_type()) {
+                parameters.push(codec.parameters());
+            }
+        }
+        if self.redundant_audio {
+            parameters.extend(red_parameters(codecs));
+        }
+        parameters.extend(telephone_event_parameters());
+        parameters
+    }
+
+    // Custom interceptors are registered after the stock ones, so they see outgoing
+    // packets as the track writes them and incoming packets after NACK/reports
+    pub fn with_interceptor(mut self, builder: impl InterceptorBuilder + Send + Sync + 'static) -> Self {
+        self.custom_interceptors.0.push(Arc::new(builder));
+        self
+    }
+
+    pub fn with_sdp_hook(
+        mut self,
+        hook: impl Fn(SdpStage, RTCSdpType, &mut SessionDescription) -> anyhow::Result<()> + Send + Sync + 'static,
+    ) -> Self {
+        self.sdp_hooks.add(hook);
+        self
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct CustomInterceptors(Vec<Arc<dyn InterceptorBuilder + Send + Sync>>);
+
+impl CustomInterceptors {
+    pub fn iter(&self) -> impl Iterator<Item = &Arc<dyn InterceptorBuilder + Send + Sync>> {
+        self.0.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl fmt::Debug for CustomInterceptors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "CustomInterceptors({})", self.0.len())
+    }
+}
+
+// Builders have no identity beyond the allocation they live in
+impl PartialEq for CustomInterceptors {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.len() == other.0.len()
+            && self.0.iter().zip(&other.0).all(|(a, b)| Arc::ptr_eq(a, b))
+    }
+}
+
+impl IceServerConfig {
+    pub fn to_rtc(&self) -> RTCIceServer {
+        RTCIceServer {
+            urls: self.urls.clone(),
+            username: self.username.clone(),
+            credential: self.credential.clone(),
+            ..Default::default()
+        }
+    }
+}
+
+// webrtc-rs SettingEngine knobs, for strict NATs and firewalls that only let a fixed set
+// of ports through
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IceTransportConfig {
+    // Without a response to connectivity checks for this long the connection counts as
+    // disconnected, and then as failed
+    pub disconnected_timeout_ms: u64,
+    pub failed_timeout_ms: u64,
+    // STUN binding requests on an idle pair; also what keeps NAT mappings open
+    pub keepalive_interval_ms: u64,
+    // Local UDP ports for host candidates, inclusive; 0 and 0 lets the OS pick
+    pub port_min: u16,
+    pub port_max: u16,
+    // Every connection shares this one UDP port instead; overrides the range
+    pub udp_mux_port: Option<u16>,
+}
+
+impl Default for IceTransportConfig {
+    // The webrtc-rs defaults
+    fn default() -> Self {
+        Self {
+            disconnected_timeout_ms: 5_000,
+            failed_timeout_ms: 25_000,
+            keepalive_interval_ms: 2_000,
+            port_min: 0,
+            port_max: 0,
+            udp_mux_port: None,
+        }
+    }
+}
+
+// Which of the stock webrtc-rs interceptors are registered
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InterceptorConfig {
+    pub nack: bool,
+    // Sender and receiver reports; needed for RTT and remote loss stats
+    pub rtcp_reports: bool,
+    pub twcc: bool,
+}
+
+impl Default for InterceptorConfig {
+    fn default() -> Self {
+        Self {
+            nack: true,
+            rtcp_reports: true,
+            twcc: true,
+        }
+    }
+}
+
+// A remote stream starting or stopping, by the peer it carries
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RemoteTrackEvent {
+    Started(String),
+    // RTCP BYE, the transceiver stopped, or the connection closed
+    Ended(String),
+}
+
+// Whether an RTCP compound packet says goodbye for the stream `ssrc`
+pub fn is_goodbye(packets: &[Box<dyn Packet + Send + Sync>], ssrc: u32) -> bool {
+    packets.iter().any(|packet| {
+        packet
+            .as_any()
+            .downcast_ref::<Goodbye>()
+            .map_or(false, |bye| bye.sources.contains(&ssrc))
+    })
+}
 
 pub struct WebRTCClient {
     pub peer_connection: Arc<RTCPeerConnection>,
-    pub audio_track: Arc<TrackLocalStaticSample>,
-    pub audio_playback: Arc<Mutex<Option<AudioPlayback>>>,
+    pub audio_track: Arc<AudioTrack>,
+    pub audio_playback: Arc<Mutex<Option<AudioStreamHandle>>>,
     pub connection_monitor: ConnectionMonitor,
     pub quality_monitor: QualityMonitor,
+    pub audio_backend: Arc<dyn AudioBackend>,
+    pub latency_probe: LatencyProbe,
+    // Lets the two ends renegotiate without the signaling server
+    pub control: ControlChannel,
+    remote_audio: broadcast::Sender<Bytes>,
+    // Decoded per-peer frames for audio taps
+    remote_taps: broadcast::Sender<TapFrame>,
+    normalize_loudness: Arc<AtomicBool>,
+    // Every inbound RTP packet so far, telephone events included; the watchdog's sign of life
+    inbound_packets: Arc<AtomicU64>,
+    // Measured speech level of each remote stream, dBFS
+    remote_loudness: Arc<std::sync::Mutex<HashMap<String, f32>>>,
+    stereo_layout: Arc<std::sync::Mutex<StereoLayout>>,
+    // Live remote audio tracks per peer
+    remote_peers: Arc<std::sync::Mutex<HashMap<String, usize>>>,
+    // The peer of each live remote audio stream, by SSRC
+    remote_streams: Arc<std::sync::Mutex<HashMap<u32, String>>>,
+    remote_tracks: broadcast::Sender<RemoteTrackEvent>,
+    track_owners: Arc<std::sync::Mutex<TrackOwners>>,
+    // Trickled candidates that beat the offer/answer here, by the peer that sent them
+    pending_candidates: Mutex<HashMap<String, Vec<RTCIceCandidateInit>>>,
+    // Every remote candidate this call, so the one that connected can be kept for next time
+    trickled_candidates: std::sync::Mutex<Vec<String>>,
+    dtmf: DtmfSender,
+    // Where the quality ladder has this call; read by its SDP hook
+    quality_rung: Arc<std::sync::Mutex<QualityRung>>,
+    sdp_hooks: SdpHooks,
+    sdp_log: SdpRecorder,
+    negotiation_log: NegotiationLog,
+    // Started when the client is built, which its owner does for the CallRequest
+    setup_clock: SetupClock,
+    // RTP and RTCP readers, the stats loop and whatever owners spawn for this call
+    tasks: TaskGroup,
+}
+
+// Everything a WebRTCClient is made of. Whatever is left unset is built from the
+// WebRTCConfig, the way with_config() does.
+#[derive(Default)]
+pub struct WebRTCClientBuilder {
+    config: WebRTCConfig,
+    audio_backend: Option<Arc<dyn AudioBackend>>,
+    audio_track: Option<Arc<AudioTrack>>,
+    rtc_configuration: Option<RTCConfiguration>,
+    media_engine: Vec<MediaEngineCustomizer>,
+    registry: Option<RegistryBuilder>,
+    factory: Option<Arc<WebRTCFactory>>,
+    connection_monitor: Option<ConnectionMonitor>,
+    warm_route: Option<WarmRoute>,
+    negotiation_log: Option<NegotiationLog>,
+}
+
+impl WebRTCClientBuilder {
+    pub fn config(mut self, config: &WebRTCConfig) -> Self {
+        self.config = config.clone();
+        self
+    }
+
+    // Defaults to the platform's default backend
+    pub fn audio_backend(mut self, audio_backend: Arc<dyn AudioBackend>) -> Self {
+        self.audio_backend = Some(audio_backend);
+        self
+    }
+
+    // What we send. Its latency probe becomes the client's.
+    pub fn audio_track(mut self, audio_track: Arc<AudioTrack>) -> Self {
+        self.audio_track = Some(audio_track);
+        self
+    }
+
+    pub fn ice_servers(mut self, ice_servers: Vec<IceServerConfig>) -> Self {
+        self.config.ice_servers = ice_servers;
+        self
+    }
+
+    // For ICE settings beyond the servers, like a relay-only transport policy. Its
+    // servers are kept if it has any, otherwise the configured ones are filled in.
+    pub fn rtc_configuration(mut self, rtc_configuration: RTCConfiguration) -> Self {
+        self.rtc_configuration = Some(rtc_configuration);
+        self
+    }
+
+    // Runs after the built-in codecs are registered; for extra codecs or header
+    // extensions. May be called more than once.
+    pub fn customize_media_engine(mut self, customize: impl FnOnce(&mut MediaEngine) -> Result<()> + Send + 'static) -> Self {
+        self.media_engine.push(Box::new(customize));
+        self
+    }
+
+    // Replaces the stock interceptors the config would register (impairment, NACK,
+    // reports, TWCC). DTMF and the config's custom interceptors still go on top.
+    pub fn interceptor_registry(
+        mut self,
+        build: impl FnOnce(Registry, &mut MediaEngine) -> Result<Registry> + Send + 'static,
+    ) -> Self {
+        self.registry = Some(Box::new(build));
+        self
+    }
+
+    // Makes the peer connection from a shared API instead of building one; its media
+    // engine and interceptors are used, not this builder's or the config's
+    pub fn factory(mut self, factory: Arc<WebRTCFactory>) -> Self {
+        self.factory = Some(factory);
+        self
+    }
+
+    // Keep a clone to watch the connection from outside, e.g. in a test
+    pub fn connection_monitor(mut self, connection_monitor: ConnectionMonitor) -> Self {
+        self.connection_monitor = Some(connection_monitor);
+        self
+    }
+
+    // How the last call to the same peer connected, to try that first
+    pub fn warm_route(mut self, warm_route: WarmRoute) -> Self {
+        self.warm_route = Some(warm_route);
+        self
+    }
+
+    // To record into a call's shared log; each client keeps its own otherwise
+    pub fn negotiation_log(mut self, negotiation_log: NegotiationLog) -> Self {
+        self.negotiation_log = Some(negotiation_log);
+        self
+    }
+
+    pub async fn build(self) -> Result<WebRTCClient> {
+        WebRTCClient::build(self).await
+    }
 }
 
 impl WebRTCClient {
-    pub async fn new() -> Result<Self> {
-        let connection_monitor = ConnectionMonitor::new();
-        let monitor = connection_monitor.clone();
+    pub fn builder() -> WebRTCClientBuilder {
+        WebRTCClientBuilder::default()
+    }
 
-        // Create a MediaEngine object to configure the supported codec
-        let mut media_engine = webrtc::media_engine::MediaEngine::default();
-        
-        // Register default codecs
-        media_engine.register_default_codecs()?;
+    pub async fn with_config(
+        webrtc_config: &WebRTCConfig,
+        audio_backend: Arc<dyn AudioBackend>,
+    ) -> Result<Self> {
+        Self::builder().config(webrtc_config).audio_backend(audio_backend).build().await
+    }
 
-        // Create an API object
-        let api = APIBuilder::new()
-            .with_media_engine(media_engine)
-            .build();
+    async fn build(parts: WebRTCClientBuilder) -> Result<Self> {
+        let webrtc_config = &parts.config;
+        let audio_backend = parts.audio_backend.unwrap_or_else(|| AudioBackendKind::default().create());
+        let connection_monitor = parts.connection_monitor.unwrap_or_else(ConnectionMonitor::new);
+        let monitor = connection_monitor.clone();
+        // Before any stream starts; backends read it when opening the device
+        audio_backend.set_voice_processing(!webrtc_config.music_mode);
 
-        // Create configuration
-        let config = RTCConfiguration {
-            ice_servers: vec![RTCIceServer {
-                urls: vec!["stun:stun.l.google.com:19302".to_owned()],
-                ..Default::default()
-            }],
-            ..Default::default()
+        // A one-off API unless the connection comes from a shared one
+        let factory = match parts.factory {
+            Some(factory) if parts.media_engine.is_empty() && parts.registry.is_none() => factory,
+            Some(_) => return Err(anyhow::anyhow!("Customize the media engine and interceptors on the WebRTCFactory")),
+            None => Arc::new(WebRTCFactory::build(webrtc_config, parts.media_engine, parts.registry)?),
         };
 
+        // Create configuration
+        let mut config = parts.rtc_configuration.unwrap_or_default();
+        if config.ice_servers.is_empty() {
+            config.ice_servers = webrtc_config.rtc_ice_servers();
+        }
+        if parts.warm_route.as_ref().map_or(false, |route| route.relay_only(&webrtc_config.ice_servers)) {
+            config.ice_transport_policy = RTCIceTransportPolicy::Relay;
+        }
+
         // Create a new RTCPeerConnection
-        let peer_connection = Arc::new(api.new_peer_connection(config).await?);
+        let dtmf = DtmfSender::new();
+        let peer_connection = factory.new_peer_connection(config, &dtmf).await?;
 
         // Create an audio track
-        let audio_track = Arc::new(TrackLocalStaticSample::new(
-            RTCRtpCodecCapability {
-                mime_type: "audio/opus".to_owned(),
-                ..Default::default()
-            },
-            "audio".to_owned(),
-            "webrtc-rs".to_owned(),
-        ));
+        let audio_track = parts.audio_track.unwrap_or_else(|| {
+            Arc::new(AudioTrack::with_latency_probe(
+                "audio".to_owned(),
+                webrtc_config.stream_id.clone(),
+                LatencyProbe::default(),
+            ))
+        });
+        let latency_probe = audio_track.latency_probe();
 
         // Add the audio track to the peer connection
         peer_connection
             .add_track(Arc::clone(&audio_track) as Arc<dyn TrackLocal + Send + Sync>)
             .await?;
 
+        // Must happen before the first offer or answer is created
+        let codec_parameters = webrtc_config.audio_codec_parameters();
+        for transceiver in peer_connection.get_transceivers().await {
+            if transceiver.kind() == RTPCodecType::Audio {
+                transceiver.set_codec_preferences(codec_parameters.clone()).await?;
+            }
+        }
+
         let audio_playback = Arc::new(Mutex::new(None));
         let audio_playback_clone = audio_playback.clone();
+        let (remote_audio, _) = broadcast::channel(256);
+        let remote_audio_tx = remote_audio.clone();
+        let (remote_taps, _) = broadcast::channel(256);
+        let remote_taps_tx = remote_taps.clone();
+        let playback_backend = audio_backend.clone();
+        let decode_probe = latency_probe.clone();
+        let normalize_loudness = Arc::new(AtomicBool::new(webrtc_config.normalize_loudness));
+        let rung = Arc::new(std::sync::Mutex::new(QualityRung::FullbandStereo));
+        let remote_loudness = Arc::new(std::sync::Mutex::new(HashMap::new()));
+        let stereo_layout = Arc::new(std::sync::Mutex::new(StereoLayout::new(webrtc_config.spatial_audio)));
+        let normalize = normalize_loudness.clone();
+        let inbound_packets = Arc::new(AtomicU64::new(0));
+        let received = inbound_packets.clone();
+        let loudness = remote_loudness.clone();
+        let layout = stereo_layout.clone();
+        let remote_peers = Arc::new(std::sync::Mutex::new(HashMap::new()));
+        let live_peers = remote_peers.clone();
+        let remote_streams = Arc::new(std::sync::Mutex::new(HashMap::new()));
+        let live_streams = remote_streams.clone();
+        let (remote_tracks, _) = broadcast::channel(32);
+        let remote_tracks_tx = remote_tracks.clone();
+        let track_owners = Arc::new(std::sync::Mutex::new(TrackOwners::default()));
+        let owners = track_owners.clone();
+        let tasks = TaskGroup::new();
+        let track_tasks = tasks.clone();
+        let setup_clock = SetupClock::start();
+        let track_clock = setup_clock.clone();
 
         // Set up track handling
-        peer_connection.on_track(Box::new(move |track: Option<Arc<TrackRemote>>, _: Option<Arc<MediaStream>>, _: Option<Arc<RTCRtpReceiver>>| {
+        peer_connection.on_track(Box::new(move |track: Option<Arc<TrackRemote>>, _: Option<Arc<MediaStream>>, receiver: Option<Arc<RTCRtpReceiver>>| {
             if let Some(track) = track {
                 if track.kind() == RTPCodecType::Audio {
                     let audio_playback = audio_playback_clone.clone();
+                    let audio_backend = playback_backend.clone();
+                    let remote_audio_tx = remote_audio_tx.clone();
+                    let remote_taps_tx = remote_taps_tx.clone();
+                    let remote_tracks_tx = remote_tracks_tx.clone();
+                    let decode_probe = decode_probe.clone();
+                    let mut normalizer = LoudnessNormalizer::new(normalize.clone());
+                    let loudness = loudness.clone();
+                    let layout = layout.clone();
+                    let live_peers = live_peers.clone();
+                    let live_streams = live_streams.clone();
+                    let owners = owners.clone();
+                    let received = received.clone();
+                    let tasks = track_tasks.clone();
+                    let clock = track_clock.clone();
                     Box::pin(async move {
-                        if let Ok(playback) = AudioPlayback::new(track) {
+                        // One playback mixes every remote track. It is optional (no output
+                        // device in CI), so reading RTP must not depend on it.
+                        {
                             let mut guard = audio_playback.lock().await;
-                            *guard = Some(playback);
+                            if guard.is_none() {
+                                match audio_backend.start_playback(remote_audio_tx.subscribe()) {
+                                    Ok(playback) => *guard = Some(playback),
+                                    Err(e) => eprintln!("Failed to start {} playback: {}", audio_backend.name(), e),
+                                }
+                            }
+                        }
+                        let codec = AudioCodec::from_mime(&track.codec().capability.mime_type);
+                        // The track's codec is whichever came first; RED and plain packets can
+                        // alternate as the sender switches redundancy on and off
+                        let red_formats = match receiver {
+                            Some(ref receiver) => RedFormat::negotiated(&receiver.get_parameters().await.codecs),
+                            None => Vec::new(),
+                        };
+                        let mut red_decoder = RedDecoder::default();
+                        let mut decoder = StreamDecoder::default();
+                        // Each remote stream is one peer, even when a relay forwards several
+                        let stream_id = track.stream_id();
+                        let peer = owners.lock().map_or(stream_id.clone(), |owners| owners.peer_for(&stream_id));
+                        if let Ok(mut layout) = layout.lock() {
+                            layout.add_peer(&peer);
                         }
+                        if let Ok(mut live) = live_peers.lock() {
+                            *live.entry(peer.clone()).or_insert(0) += 1;
+                        }
+                        let stream_ssrc = track.ssrc();
+                        if let Ok(mut streams) = live_streams.lock() {
+                            streams.insert(stream_ssrc, peer.clone());
+                        }
+                        let _ = remote_tracks_tx.send(RemoteTrackEvent::Started(peer.clone()));
+
+                        // webrtc-rs keeps a track open after the sender's BYE, so watch for it
+                        let bye = Arc::new(Notify::new());
+                        if let Some(receiver) = receiver {
+                            let bye = bye.clone();
+                            let ssrc = track.ssrc();
+                            tasks.spawn(async move {
+                                while let Ok((packets, _)) = receiver.read_rtcp().await {
+                                    if is_goodbye(&packets, ssrc) {
+                                        bye.notify_one();
+                                        break;
+                                    }
+                                }
+                            });
+                        }
+
+                        // Cleans up after itself, so it watches for cancellation instead of
+                        // being dropped
+                        tasks.spawn_with_cleanup(|cancel| async move {
+                            // Reused for every frame of the track
+                            let (mut samples, mut stereo, mut pool) = (Vec::new(), Vec::new(), FramePool::default());
+                            let mut frames: Vec<Bytes> = Vec::new();
+                            let mut decoded_any = false;
+                            loop {
+                                // Errors once the transceiver stops or the connection closes
+                                let rtp = tokio::select! {
+                                    read = track.read_rtp() => match read {
+                                        Ok((rtp, _)) => rtp,
+                                        Err(_) => break,
+                                    },
+                                    _ = bye.notified() => break,
+                                    _ = cancel.cancelled() => break,
+                                };
+                                if received.fetch_add(1, Ordering::Relaxed) == 0 {
+                                    clock.mark(Milestone::FirstRtp);
+                                }
+                                // Telephone events share the stream; only audio goes to playback
+                                let payload_type = rtp.header.payload_type;
+                                let codec = match red_formats.iter().find(|red| red.payload_type == payload_type) {
+                                    Some(red) => {
+                                        red_decoder.unpack_into(red, rtp.header.timestamp, &rtp.payload, &mut frames);
+                                        red.primary
+                                    }
+                                    None => {
+                                        let plain = if payload_type == track.payload_type() {
+                                            codec
+                                        } else {
+                                            red_formats
+                                                .iter()
+                                                .find(|red| red.primary_payload_type == payload_type)
+                                                .map(|red| red.primary)
+                                        };
+                                        let Some(codec) = plain else { continue };
+                                        red_decoder.seen(rtp.header.timestamp);
+                                        frames.push(rtp.payload);
+                                        codec
+                                    }
+                                };
+                                for payload in frames.drain(..) {
+                                    let decode_started = LatencyProbe::mark();
+                                    decode_frame_into(&decoder.decode(codec, &payload), &mut samples);
+                                    decode_probe.record_decode(decode_started);
+                                    if !decoded_any {
+                                        decoded_any = true;
+                                        clock.mark(Milestone::FirstAudio);
+                                    }
+                                    plugins::host().process_audio(&TapSource::Remote(peer.clone()), &mut samples, PLAYBACK_SAMPLE_RATE);
+                                    if remote_taps_tx.receiver_count() > 0 {
+                                        let _ = remote_taps_tx.send(TapFrame {
+                                            source: TapSource::Remote(peer.clone()),
+                                            sample_rate: PLAYBACK_SAMPLE_RATE,
+                                            samples: samples.as_slice().into(),
+                                        });
+                                    }
+                                    normalizer.process(&mut samples);
+                                    if let (Some(level), Ok(mut levels)) = (normalizer.loudness_dbfs(), loudness.lock()) {
+                                        levels.insert(peer.clone(), level);
+                                    }
+                                    // Read per frame so moving a peer is heard right away
+                                    let pan = layout.lock().ok().and_then(|layout| layout.pan(&peer));
+                                    let frame = match pan {
+                                        Some(pan) => {
+                                            pan_to_stereo_into(&samples, pan, &mut stereo);
+                                            pool.encode(&stereo)
+                                        }
+                                        None => pool.encode(&samples),
+                                    };
+                                    let _ = remote_audio_tx.send(frame);
+                                }
+                            }
+
+                            if let Ok(mut streams) = live_streams.lock() {
+                                streams.remove(&stream_ssrc);
+                            }
+                            // Tear down what this track had in the mix, unless the peer
+                            // still has another one
+                            let last = match live_peers.lock() {
+                                Ok(mut live) => match live.get_mut(&peer) {
+                                    Some(count) if *count > 1 => {
+                                        *count -= 1;
+                                        false
+                                    }
+                                    _ => {
+                                        live.remove(&peer);
+                                        true
+                                    }
+                                },
+                                Err(_) => true,
+                            };
+                            if last {
+                                if let Ok(mut layout) = layout.lock() {
+                                    layout.remove_peer(&peer);
+                                }
+                                if let Ok(mut levels) = loudness.lock() {
+                                    levels.remove(&peer);
+                                }
+                                // Nobody left to hear: release the output device
+                                if live_peers.lock().map_or(false, |live| live.is_empty()) {
+                                    audio_playback.lock().await.take();
+                                }
+                                let _ = remote_tracks_tx.send(RemoteTrackEvent::Ended(peer));
+                            }
+                        });
                     })
                 } else {
                     Box::pin(async {})
@@ -93,11 +711,16 @@ impl WebRTCClient {
         }));
 
         // Set up connection state monitoring
+        let clock = setup_clock.clone();
         peer_connection.on_peer_connection_state_change(Box::new(move |s: RTCPeerConnectionState| {
             let monitor = monitor.clone();
+            // Connected once DTLS is up on top of ICE
+            if s == RTCPeerConnectionState::Connected {
+                clock.mark(Milestone::DtlsConnected);
+            }
             Box::pin(async move {
                 monitor.update_peer_state(s);
-                println!("Peer Connection State has changed: {}", s);
+                monitor.log_event(format!("Peer Connection State has changed: {}", s));
             })
         }));
 
@@ -111,15 +734,61 @@ impl WebRTCClient {
         }));
 
         let monitor = connection_monitor.clone();
+        let clock = setup_clock.clone();
         peer_connection.on_ice_connection_state_change(Box::new(move |s: RTCIceConnectionState| {
             let monitor = monitor.clone();
+            if s == RTCIceConnectionState::Connected {
+                clock.mark(Milestone::IceConnected);
+            }
             Box::pin(async move {
                 monitor.update_ice_state(s);
-                println!("ICE Connection State has changed: {}", s);
+                monitor.log_event(format!("ICE Connection State has changed: {}", s));
             })
         }));
 
-        let quality_monitor = QualityMonitor::new(peer_connection.clone());
+        let clock = setup_clock.clone();
+        peer_connection.on_ice_gathering_state_change(Box::new(move |s: RTCIceGathererState| {
+            match s {
+                RTCIceGathererState::Gathering => clock.mark(Milestone::GatheringStarted),
+                RTCIceGathererState::Complete => clock.mark(Milestone::GatheringDone),
+                _ => {}
+            }
+            Box::pin(async {})
+        }));
+
+        // Device fallbacks happen inside the backend; surface them with the connection state
+        if let Some(mut device_events) = audio_backend.device_events() {
+            let monitor = connection_monitor.clone();
+            // The backend outlives calls, so stop listening once this connection is gone
+            let connection = Arc::downgrade(&peer_connection);
+            tasks.spawn(async move {
+                loop {
+                    match device_events.recv().await {
+                        Ok(_) if connection.strong_count() == 0 => break,
+                        Ok(event) => monitor.report_device_event(event),
+                        Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    }
+                }
+            });
+        }
+
+        let control = ControlChannel::open(&peer_connection).await?;
+        let quality_monitor = QualityMonitor::with_tasks(peer_connection.clone(), webrtc_config.stats.interval(), tasks.clone());
+
+        // Queued like a trickled candidate, so it's checked as soon as there's a remote
+        // description. Peers on a fixed port (udp_mux_port) keep their host candidate
+        // between calls; otherwise this check simply fails and the new ones win.
+        let mut pending_candidates = HashMap::new();
+        if let Some(candidate) = parts.warm_route.and_then(|route| route.remote_candidate) {
+            pending_candidates.insert(
+                "warm start".to_string(),
+                vec![RTCIceCandidateInit {
+                    candidate,
+                    ..Default::default()
+                }],
+            );
+        }
         
         Ok(Self {
             peer_connection,
@@ -127,37 +796,321 @@ impl WebRTCClient {
             audio_playback,
             connection_monitor,
             quality_monitor,
+            audio_backend,
+            latency_probe,
+            control,
+            remote_audio,
+            remote_taps,
+            normalize_loudness,
+            inbound_packets,
+            remote_loudness,
+            stereo_layout,
+            remote_peers,
+            remote_streams,
+            remote_tracks,
+            track_owners,
+            pending_candidates: Mutex::new(pending_candidates),
+            trickled_candidates: std::sync::Mutex::new(Vec::new()),
+            dtmf,
+            quality_rung: rung.clone(),
+            sdp_hooks: {
+                let mut hooks = webrtc_config.sdp_hooks.clone();
+                if webrtc_config.music_mode {
+                    hooks.add(music_mode());
+                }
+                if let Some(kbps) = webrtc_config.max_bitrate_kbps {
+                    hooks.add(limit_bitrate(kbps));
+                }
+                if webrtc_config.quality_ladder.enabled {
+                    hooks.add(quality_rung(rung));
+                }
+                hooks
+            },
+            sdp_log: SdpRecorder::default(),
+            negotiation_log: parts.negotiation_log.unwrap_or_default(),
+            setup_clock,
+            tasks,
         })
     }
 
-    pub async fn create_offer(&self) -> Result<String> {
-        let offer = self.peer_connection.create_offer(None).await?;
+    // Background work tied to this call. Owners spawn their per-call tasks here too, so
+    // close() stops them along with the client's own.
+    pub fn tasks(&self) -> &TaskGroup {
+        &self.tasks
+    }
+
+    // Ends the call: stops every task in tasks(), then closes the peer connection. Waits
+    // up to TASK_SHUTDOWN_TIMEOUT for the tasks; one that's stuck is reported, not waited on.
+    pub async fn close(&self) {
+        let stopped = self.tasks.shutdown(TASK_SHUTDOWN_TIMEOUT).await;
+        if !stopped {
+            eprintln!("{} call tasks still running after close", self.tasks.len());
+        }
+        if let Err(e) = self.peer_connection.close().await {
+            eprintln!("Failed to close peer connection: {}", e);
+        }
+    }
+
+    // Mouth-to-ear estimate from our own pipeline plus the RTCP round trip
+    pub fn latency_estimate(&self, round_trip_ms: f64) -> LatencyBreakdown {
+        LatencyBreakdown::estimate(&self.latency_probe, self.audio_backend.latency(), round_trip_ms)
+    }
+
+    // Sends RFC 4733 telephone events on the audio stream; resolves once all tones are out
+    pub async fn send_dtmf(&self, tones: &str) -> Result<()> {
+        let mut payload_types = Vec::new();
+        for sender in self.peer_connection.get_senders().await {
+            let parameters = sender.get_parameters().await;
+            payload_types.extend(
+                parameters
+                    .rtp_parameters
+                    .codecs
+                    .iter()
+                    .filter(|codec| codec.capability.mime_type.eq_ignore_ascii_case(TELEPHONE_EVENT_MIME))
+                    .map(|codec| (codec.capability.clock_rate, codec.payload_type)),
+            );
+        }
+        if payload_types.is_empty() {
+            return Err(anyhow::anyhow!("The remote peer does not accept DTMF"));
+        }
+        self.dtmf.insert_dtmf(tones, &payload_types).await
+    }
+
+    // Grows while the remote side's media arrives
+    pub fn inbound_packets(&self) -> u64 {
+        self.inbound_packets.load(Ordering::Relaxed)
+    }
+
+    // Payloads of every inbound audio RTP packet, for playback and anything else listening
+    pub fn subscribe_remote_audio(&self) -> broadcast::Receiver<Bytes> {
+        self.remote_audio.subscribe()
+    }
+
+    // Streams this call's audio to `config.sink` until the handle is dropped
+    pub fn start_tap(&self, config: &TapConfig) -> AudioStreamHandle {
+        let local = config.include_local.then(|| self.audio_track.subscribe_frames());
+        start_tap(config.sink.clone(), self.remote_taps.subscribe(), local)
+    }
+
+    // Takes effect on the next frame; measuring goes on either way
+    pub fn set_loudness_normalization(&self, enabled: bool) {
+        self.normalize_loudness.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn remote_loudness(&self) -> HashMap<String, f32> {
+        self.remote_loudness.lock().map(|levels| levels.clone()).unwrap_or_default()
+    }
+
+    // Peers whose audio tracks are live right now
+    pub fn remote_peers(&self) -> Vec<String> {
+        self.remote_peers.lock().map(|live| live.keys().cloned().collect()).unwrap_or_default()
+    }
+
+    // Which peer each live remote audio stream belongs to, by SSRC
+    pub fn remote_streams(&self) -> HashMap<u32, String> {
+        self.remote_streams.lock().map(|streams| streams.clone()).unwrap_or_default()
+    }
+
+    // Attributes a remote stream to a peer other than its ID says, from TrackInfo. Only
+    // streams that start afterwards pick it up.
+    pub fn assign_track(&self, stream_id: &str, peer_id: &str) {
+        if let Ok(mut owners) = self.track_owners.lock() {
+            owners.assign(stream_id, peer_id);
+        }
+    }
+
+    // Remote audio tracks starting and ending, so per-peer UI can follow them
+    pub fn subscribe_remote_tracks(&self) -> broadcast::Receiver<RemoteTrackEvent> {
+        self.remote_tracks.subscribe()
+    }
+
+    pub fn stereo_layout(&self) -> StereoLayout {
+        self.stereo_layout.lock().map(|layout| layout.clone()).unwrap_or_default()
+    }
+
+    pub fn set_spatial_audio(&self, enabled: bool) {
+        if let Ok(mut layout) = self.stereo_layout.lock() {
+            layout.set_enabled(enabled);
+        }
+    }
+
+    // Overrides the automatic placement for one peer
+    pub fn set_pan(&self, peer: &str, pan: f32) {
+        if let Ok(mut layout) = self.stereo_layout.lock() {
+            layout.set_pan(peer, pan);
+        }
+    }
+
+    // Applies from the next offer or answer on
+    pub fn set_quality_rung(&self, rung: QualityRung) {
+        if let Ok(mut current) = self.quality_rung.lock() {
+            *current = rung;
+        }
+    }
+
+    pub fn quality_rung(&self) -> QualityRung {
+        self.quality_rung.lock().map(|rung| *rung).unwrap_or(QualityRung::FullbandStereo)
+    }
+
+    // The media relay, if any, is the caller's to fill in; the connection can't tell
+    pub async fn security(&self) -> CallSecurity {
+        let dtls = self.peer_connection.sctp().transport();
+        let encrypted = dtls.state() == RTCDtlsTransportState::Connected;
+        let srtp_profile = match dtls.conn().await {
+            Some(conn) if encrypted => Some(format!("{:?}", conn.selected_srtpprotection_profile()).to_uppercase()),
+            _ => None,
+        };
+        let local = self.peer_connection.local_description().await;
+        let remote = self.peer_connection.remote_description().await;
+        CallSecurity {
+            encrypted,
+            srtp_profile,
+            local_fingerprint: local.and_then(|description| sdp_fingerprint(&description.sdp)),
+            remote_fingerprint: remote.and_then(|description| sdp_fingerprint(&description.sdp)),
+            turn_relayed: relayed_by_turn(&self.peer_connection.get_stats().await),
+            media_relay: None,
+        }
+    }
+
+    // The route ICE settled on, for the next call to the same peer; None while not connected
+    pub async fn warm_route(&self) -> Option<WarmRoute> {
+        let stats = self.peer_connection.get_stats().await;
+        let trickled = self.trickled_candidates.lock().map(|trickled| trickled.clone()).unwrap_or_default();
+        nominated_route(&stats, &trickled)
+    }
+
+    // Raw SDP of the last offer/answer exchange, for debugging
+    pub fn last_sdp(&self) -> SdpLog {
+        self.sdp_log.snapshot()
+    }
+
+    // Every description and candidate exchanged so far, with the other calls sharing it
+    pub fn negotiation_log(&self) -> &NegotiationLog {
+        &self.negotiation_log
+    }
+
+    // So far: parts not reached yet are None
+    pub fn setup_time(&self) -> CallSetupTime {
+        self.setup_clock.setup_time()
+    }
+
+    async fn set_local_description(&self, description: RTCSessionDescription) -> Result<RTCSessionDescription> {
+        let description = self.sdp_hooks.apply(SdpStage::BeforeSetLocal, description)?;
+        self.sdp_log.record(SdpStage::BeforeSetLocal, &description);
+        self.negotiation_log.record(Direction::Sent, description.sdp_type.into(), &description.sdp);
         self.peer_connection
-            .set_local_description(offer.clone())
+            .set_local_description(description.clone())
             .await?;
+        Ok(description)
+    }
+
+    async fn set_remote_description(&self, description: RTCSessionDescription) -> Result<()> {
+        let description = self.sdp_hooks.apply(SdpStage::BeforeSetRemote, description)?;
+        self.sdp_log.record(SdpStage::BeforeSetRemote, &description);
+        self.negotiation_log.record(Direction::Received, description.sdp_type.into(), &description.sdp);
+        // Held throughout so no candidate is queued after the flush
+        let mut pending = self.pending_candidates.lock().await;
+        self.peer_connection.set_remote_description(description).await?;
+        for (peer, candidates) in pending.drain() {
+            for candidate in candidates {
+                if let Err(e) = self.peer_connection.add_ice_candidate(candidate).await {
+                    eprintln!("Dropping queued ICE candidate from {}: {}", peer, e);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Candidates can arrive before the description they belong to; those wait until
+    // it is set
+    pub async fn add_ice_candidate(&self, from_peer: &str, candidate: String) -> Result<()> {
+        self.negotiation_log.record_from(Direction::Received, NegotiationKind::Candidate, Some(from_peer), &candidate);
+        if let Ok(mut trickled) = self.trickled_candidates.lock() {
+            trickled.push(candidate.clone());
+        }
+        let candidate = RTCIceCandidateInit {
+            candidate,
+            ..Default::default()
+        };
+        let mut pending = self.pending_candidates.lock().await;
+        if self.peer_connection.remote_description().await.is_none() {
+            pending.entry(from_peer.to_string()).or_default().push(candidate);
+            return Ok(());
+        }
+        self.peer_connection.add_ice_candidate(candidate).await?;
+        Ok(())
+    }
+
+    pub async fn pending_candidates(&self, from_peer: &str) -> usize {
+        self.pending_candidates.lock().await.get(from_peer).map_or(0, Vec::len)
+    }
+
+    pub async fn create_offer(&self) -> Result<String> {
+        let offer = self.peer_connection.create_offer(None).await?;
+        let offer = self.set_local_description(offer).await?;
+        self.setup_clock.mark(Milestone::OfferSent);
+        Ok(serde_json::to_string(&offer)?)
+    }
+
+    // For credentials that changed since the connection was built; used by the next ICE
+    // restart, existing allocations keep their old credentials until then
+    pub async fn set_ice_servers(&self, servers: &[IceServerConfig]) -> Result<()> {
+        let mut configuration = self.peer_connection.get_configuration().await;
+        configuration.ice_servers = servers.iter().map(IceServerConfig::to_rtc).collect();
+        self.peer_connection.set_configuration(configuration).await?;
+        Ok(())
+    }
+
+    // New ICE credentials on the existing connection, so candidates are gathered again on
+    // whatever network we are on now; DTLS and the media streams carry on unchanged
+    pub async fn restart_ice(&self) -> Result<String> {
+        let options = RTCOfferOptions {
+            ice_restart: true,
+            ..Default::default()
+        };
+        let offer = self.peer_connection.create_offer(Some(options)).await?;
+        let offer = self.set_local_description(offer).await?;
         Ok(serde_json::to_string(&offer)?)
     }
 
     pub async fn handle_answer(&self, sdp: String) -> Result<()> {
         let answer = serde_json::from_str(&sdp)?;
-        self.peer_connection.set_remote_description(answer).await?;
-        Ok(())
+        self.setup_clock.mark(Milestone::AnswerReceived);
+        self.set_remote_description(answer).await
     }
 
     pub async fn handle_offer(&self, sdp: String) -> Result<String> {
         let offer = serde_json::from_str(&sdp)?;
-        self.peer_connection.set_remote_description(offer).await?;
+        self.set_remote_description(offer).await?;
         
         let answer = self.peer_connection.create_answer(None).await?;
-        self.peer_connection
-            .set_local_description(answer.clone())
-            .await?;
+        let answer = self.set_local_description(answer).await?;
         
         Ok(serde_json::to_string(&answer)?)
     }
 
+    // The local description with every candidate in it, for signaling that can't trickle;
+    // whatever was found by the time `within` runs out if gathering never finishes
+    pub async fn gathered_local_description(&self, within: Duration) -> Result<String> {
+        let mut gathered = self.peer_connection.gathering_complete_promise().await;
+        let _ = tokio::time::timeout(within, gathered.recv()).await;
+        let description = self
+            .peer_connection
+            .local_description()
+            .await
+            .ok_or_else(|| anyhow::anyhow!("No local description to hand over"))?;
+        Ok(serde_json::to_string(&description)?)
+    }
+
     pub async fn start_monitoring(&mut self) -> Result<()> {
         self.quality_monitor.start_monitoring().await;
         Ok(())
     }
-} 
\ No newline at end of file
+} 
+
+// A client dropped without close() still stops its tasks; they just aren't waited for
+impl Drop for WebRTCClient {
+    fn drop(&mut self) {
+        self.tasks.cancel();
+    }
+}
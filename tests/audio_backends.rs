@@ -0,0 +1,77 @@
+use std::time::Duration;
+use webrtc_client::audio::{AudioBackendKind, AudioLatency, LatencyTracker};
+use webrtc_client::config::Settings;
+
+#[test]
+fn pipewire_selection_survives_a_settings_round_trip() {
+    let settings = Settings {
+        audio_backend: AudioBackendKind::PipeWire,
+        ..Default::default()
+    };
+    let json = serde_json::to_string(&settings).unwrap();
+    let loaded: Settings = serde_json::from_str(&json).unwrap();
+    assert_eq!(loaded.audio_backend, AudioBackendKind::PipeWire);
+}
+
+#[test]
+fn pipewire_is_only_offered_where_it_was_built() {
+    let available = AudioBackendKind::available();
+    assert!(available.contains(&AudioBackendKind::Cpal));
+    assert!(available.contains(&AudioBackendKind::Mock));
+    assert_eq!(
+        available.contains(&AudioBackendKind::PipeWire),
+        cfg!(all(target_os = "linux", feature = "pipewire"))
+    );
+}
+
+#[cfg(not(all(target_os = "linux", feature = "pipewire")))]
+#[test]
+fn unavailable_pipewire_falls_back_to_cpal() {
+    assert_eq!(AudioBackendKind::PipeWire.create().name(), "cpal");
+}
+
+#[test]
+fn low_latency_backends_are_windows_only() {
+    let available = AudioBackendKind::available();
+    assert_eq!(available.contains(&AudioBackendKind::Asio), cfg!(all(windows, feature = "asio")));
+    assert_eq!(
+        available.contains(&AudioBackendKind::WasapiExclusive),
+        cfg!(all(windows, feature = "wasapi-exclusive"))
+    );
+}
+
+#[cfg(not(windows))]
+#[test]
+fn unavailable_low_latency_backends_fall_back_to_cpal() {
+    assert_eq!(AudioBackendKind::Asio.create_with_buffer(Some(128)).name(), "cpal");
+    assert_eq!(AudioBackendKind::WasapiExclusive.create().name(), "cpal");
+}
+
+#[test]
+fn latency_is_unknown_until_a_stream_reports_it() {
+    let backend = AudioBackendKind::Mock.create();
+    assert_eq!(backend.latency(), AudioLatency::default());
+
+    let tracker = LatencyTracker::default();
+    tracker.set_playback(Duration::from_millis(5));
+    assert_eq!(tracker.get().capture, None);
+    assert_eq!(tracker.get().playback, Some(Duration::from_millis(5)));
+}
+
+#[test]
+fn settings_without_buffer_size_leave_it_to_the_driver() {
+    let loaded: Settings = serde_json::from_str(r#"{"audio_backend":"Cpal"}"#).unwrap();
+    assert_eq!(loaded.audio_buffer_frames, None);
+}
+
+#[test]
+fn oboe_is_android_only() {
+    let available = AudioBackendKind::available();
+    assert_eq!(available.contains(&AudioBackendKind::Oboe), cfg!(all(target_os = "android", feature = "oboe")));
+}
+
+#[cfg(not(target_os = "android"))]
+#[test]
+fn unavailable_oboe_falls_back_to_cpal() {
+    assert_eq!(AudioBackendKind::Oboe.create().name(), "cpal");
+}
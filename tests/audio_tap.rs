@@ -0,0 +1,94 @@
+use bytes::Bytes;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::net::UdpSocket;
+use tokio::sync::broadcast;
+use tokio::time::timeout;
+use webrtc::media::Sample;
+use webrtc_client::audio::encode_frame;
+use webrtc_client::audio::tap::{start_tap, TapFrame, TapSink, TapSource};
+use webrtc_client::audio::AudioTrack;
+
+fn frame(peer: &str) -> TapFrame {
+    TapFrame {
+        source: TapSource::Remote(peer.to_string()),
+        sample_rate: 48_000,
+        samples: vec![0.5, -1.0, 2.0].into(),
+    }
+}
+
+#[test]
+fn frames_encode_source_rate_and_s16le_samples() {
+    let bytes = frame("bob").encode();
+    assert_eq!(&bytes[..5], &[1, 3, b'b', b'o', b'b']);
+    assert_eq!(u32::from_le_bytes(bytes[5..9].try_into().unwrap()), 48_000);
+    let samples: Vec<i16> = bytes[9..].chunks_exact(2).map(|pair| i16::from_le_bytes([pair[0], pair[1]])).collect();
+    // Out-of-range samples are clamped rather than wrapped
+    assert_eq!(samples, vec![16383, -32767, 32767]);
+}
+
+#[tokio::test]
+async fn the_microphone_reaches_a_callback_tap() {
+    let track = Arc::new(AudioTrack::new("audio".to_string(), "alice".to_string()));
+    let (_remote_tx, remote) = broadcast::channel(8);
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let sink_received = received.clone();
+    let sink = TapSink::Callback(Arc::new(move |frame: &TapFrame| sink_received.lock().unwrap().push(frame.clone())));
+    let _tap = start_tap(sink, remote, Some(track.subscribe_frames()));
+
+    let sample = Sample {
+        data: encode_frame(&[0.25; 960]),
+        duration: Duration::from_millis(20),
+        ..Default::default()
+    };
+    timeout(Duration::from_secs(5), async {
+        while received.lock().unwrap().is_empty() {
+            track.write_sample(&sample).await.unwrap();
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    })
+    .await
+    .expect("no frames reached the tap");
+
+    let frame = received.lock().unwrap()[0].clone();
+    assert_eq!(frame.source, TapSource::Local);
+    assert_eq!(frame.sample_rate, 48_000);
+    assert_eq!(frame.samples.len(), 960);
+}
+
+#[tokio::test]
+async fn remote_frames_go_out_as_datagrams() {
+    let consumer = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let address: SocketAddr = consumer.local_addr().unwrap();
+    let (remote_tx, remote) = broadcast::channel(8);
+    let _tap = start_tap(TapSink::Udp(address), remote, None);
+
+    let mut buffer = [0u8; 2048];
+    let received = timeout(Duration::from_secs(5), async {
+        loop {
+            let _ = remote_tx.send(frame("bob"));
+            if let Ok(Ok(len)) = timeout(Duration::from_millis(50), consumer.recv(&mut buffer)).await {
+                return Bytes::copy_from_slice(&buffer[..len]);
+            }
+        }
+    })
+    .await
+    .expect("no datagram from the tap");
+    assert_eq!(received.as_ref(), frame("bob").encode().as_slice());
+}
+
+#[tokio::test]
+async fn a_muted_microphone_taps_as_silence() {
+    let track = AudioTrack::new("audio".to_string(), "alice".to_string());
+    let mut frames = track.subscribe_frames();
+    track.set_muted(true);
+    let sample = Sample {
+        data: encode_frame(&[0.25; 960]),
+        duration: Duration::from_millis(20),
+        ..Default::default()
+    };
+    track.write_sample(&sample).await.unwrap();
+    let frame = frames.recv().await.unwrap();
+    assert!(frame.samples.iter().all(|sample| *sample == 0.0));
+}
@@ -0,0 +1,40 @@
+use webrtc_client::audio::bluetooth::{detect, BluetoothProfile, BluetoothTracker};
+use webrtc_client::audio::StreamDirection;
+
+#[test]
+fn hands_free_shows_in_the_name_or_the_rate() {
+    let pipewire = detect("bluez_output.AC_80_0A.headset-head-unit", 16_000).unwrap();
+    assert_eq!(pipewire.profile, BluetoothProfile::HandsFree);
+
+    let windows = detect("Headset (WH-1000XM4 Hands-Free AG Audio)", 16_000).unwrap();
+    assert_eq!(windows.profile, BluetoothProfile::HandsFree);
+
+    // macOS only gives the product name
+    let mac = detect("Jabra Evolve2 65", 8_000).unwrap();
+    assert_eq!(mac.profile, BluetoothProfile::HandsFree);
+}
+
+#[test]
+fn a2dp_is_recognized_and_wired_devices_are_left_alone() {
+    let a2dp = detect("bluez_output.AC_80_0A.a2dp-sink", 48_000).unwrap();
+    assert_eq!(a2dp.profile, BluetoothProfile::HighFidelity);
+
+    assert_eq!(detect("USB Headset", 48_000), None);
+    assert_eq!(detect("Built-in Output", 44_100), None);
+}
+
+#[test]
+fn hands_free_on_either_side_is_what_gets_reported() {
+    let tracker = BluetoothTracker::default();
+    assert_eq!(tracker.current(), None);
+
+    tracker.opened(StreamDirection::Playback, "bluez_output.AC_80_0A.a2dp-sink", 48_000);
+    assert_eq!(tracker.current().unwrap().profile, BluetoothProfile::HighFidelity);
+
+    tracker.opened(StreamDirection::Capture, "bluez_input.AC_80_0A.headset-head-unit", 16_000);
+    assert_eq!(tracker.current().unwrap().profile, BluetoothProfile::HandsFree);
+
+    // Moving the mic to a wired device
+    tracker.opened(StreamDirection::Capture, "USB Microphone", 48_000);
+    assert_eq!(tracker.current().unwrap().profile, BluetoothProfile::HighFidelity);
+}
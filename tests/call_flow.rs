@@ -0,0 +1,172 @@
+mod support;
+
+use support::{engine, engine_config, wait_for, LoopbackServer};
+use webrtc_client::call::CallState;
+use webrtc_client::engine::{CallEngine, EngineCommand, EngineConfig, EngineEvent};
+
+#[tokio::test]
+async fn two_clients_negotiate_and_exchange_audio() {
+    let server = LoopbackServer::start().await;
+    let alice = engine(&server, "alice", false);
+    let bob = engine(&server, "bob", true);
+    let mut alice_events = alice.subscribe();
+    let mut bob_events = bob.subscribe();
+
+    alice.send(EngineCommand::Connect).unwrap();
+    wait_for(&mut alice_events, "alice connected", |e| matches!(e, EngineEvent::Connected)).await;
+    bob.send(EngineCommand::Connect).unwrap();
+    wait_for(&mut bob_events, "bob connected", |e| matches!(e, EngineEvent::Connected)).await;
+    wait_for(&mut alice_events, "bob in alice's peer list", |e| {
+        matches!(e, EngineEvent::PeerList(peers) if peers.contains(&"bob".to_string()))
+    })
+    .await;
+
+    alice.send(EngineCommand::Call(vec!["bob".to_string()])).unwrap();
+
+    // Offer/answer completes on both sides
+    wait_for(&mut alice_events, "alice call active", |e| matches!(e, EngineEvent::CallActive)).await;
+    wait_for(&mut bob_events, "bob call active", |e| matches!(e, EngineEvent::CallActive)).await;
+
+    // ICE and DTLS complete and RTP flows both ways
+    wait_for(&mut alice_events, "audio at alice", |e| matches!(e, EngineEvent::RemoteAudioStarted)).await;
+    wait_for(&mut bob_events, "audio at bob", |e| matches!(e, EngineEvent::RemoteAudioStarted)).await;
+
+    alice.send(EngineCommand::HangUp).unwrap();
+    wait_for(&mut alice_events, "alice call ended", |e| matches!(e, EngineEvent::CallEnded)).await;
+    wait_for(&mut bob_events, "bob call ended", |e| matches!(e, EngineEvent::CallEnded)).await;
+}
+
+#[tokio::test]
+async fn declined_call_is_reported_to_caller() {
+    let server = LoopbackServer::start().await;
+    let alice = engine(&server, "alice", false);
+    let bob = engine(&server, "bob", false);
+    let mut alice_events = alice.subscribe();
+    let mut bob_events = bob.subscribe();
+
+    alice.send(EngineCommand::Connect).unwrap();
+    bob.send(EngineCommand::Connect).unwrap();
+    wait_for(&mut alice_events, "bob in alice's peer list", |e| {
+        matches!(e, EngineEvent::PeerList(peers) if peers.contains(&"bob".to_string()))
+    })
+    .await;
+
+    alice.send(EngineCommand::Call(vec!["bob".to_string()])).unwrap();
+    let call = match wait_for(&mut bob_events, "incoming call", |e| matches!(e, EngineEvent::IncomingCall(_))).await {
+        EngineEvent::IncomingCall(call) => call,
+        _ => unreachable!(),
+    };
+    assert_eq!(call.from_peer, "alice");
+
+    bob.send(EngineCommand::Answer { call, accepted: false }).unwrap();
+    wait_for(&mut alice_events, "decline", |e| {
+        matches!(e, EngineEvent::CallDeclined { peer_id } if peer_id == "bob")
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn shutting_down_mid_call_ends_the_call_for_the_remote_peer() {
+    let server = LoopbackServer::start().await;
+    let alice = engine(&server, "alice", false);
+    let bob = engine(&server, "bob", true);
+    let mut alice_events = alice.subscribe();
+    let mut bob_events = bob.subscribe();
+
+    alice.send(EngineCommand::Connect).unwrap();
+    bob.send(EngineCommand::Connect).unwrap();
+    wait_for(&mut alice_events, "bob in alice's peer list", |e| {
+        matches!(e, EngineEvent::PeerList(peers) if peers.contains(&"bob".to_string()))
+    })
+    .await;
+
+    alice.send(EngineCommand::Call(vec!["bob".to_string()])).unwrap();
+    wait_for(&mut bob_events, "bob call active", |e| matches!(e, EngineEvent::CallActive)).await;
+
+    alice.shutdown().await;
+    assert!(alice.send(EngineCommand::HangUp).is_err(), "engine still running after shutdown");
+    wait_for(&mut bob_events, "bob call ended", |e| matches!(e, EngineEvent::CallEnded)).await;
+}
+
+#[tokio::test]
+async fn a_call_declined_by_everyone_ends_busy() {
+    let server = LoopbackServer::start().await;
+    let alice = engine(&server, "alice", false);
+    let bob = engine(&server, "bob", false);
+    let mut alice_events = alice.subscribe();
+    let mut bob_events = bob.subscribe();
+
+    alice.send(EngineCommand::Connect).unwrap();
+    bob.send(EngineCommand::Connect).unwrap();
+    wait_for(&mut alice_events, "bob in alice's peer list", |e| {
+        matches!(e, EngineEvent::PeerList(peers) if peers.contains(&"bob".to_string()))
+    })
+    .await;
+
+    alice.send(EngineCommand::Call(vec!["bob".to_string()])).unwrap();
+    let call = match wait_for(&mut bob_events, "incoming call", |e| matches!(e, EngineEvent::IncomingCall(_))).await {
+        EngineEvent::IncomingCall(call) => call,
+        _ => unreachable!(),
+    };
+    bob.send(EngineCommand::Answer { call, accepted: false }).unwrap();
+
+    let session = match wait_for(&mut alice_events, "call updated", |e| matches!(e, EngineEvent::CallUpdated(_))).await {
+        EngineEvent::CallUpdated(session) => session,
+        _ => unreachable!(),
+    };
+    assert_eq!(session.state, CallState::Busy);
+    assert!(!session.answered());
+    wait_for(&mut alice_events, "alice call ended", |e| matches!(e, EngineEvent::CallEnded)).await;
+}
+
+#[tokio::test]
+async fn only_peers_on_the_allowlist_are_answered_automatically() {
+    let server = LoopbackServer::start().await;
+    let alice = engine(&server, "alice", false);
+    let bob = CallEngine::spawn(EngineConfig {
+        auto_answer_allowlist: vec!["carol".to_string()],
+        ..engine_config(&server, "bob", true)
+    });
+    let mut alice_events = alice.subscribe();
+    let mut bob_events = bob.subscribe();
+
+    alice.send(EngineCommand::Connect).unwrap();
+    bob.send(EngineCommand::Connect).unwrap();
+    wait_for(&mut alice_events, "bob in alice's peer list", |e| {
+        matches!(e, EngineEvent::PeerList(peers) if peers.contains(&"bob".to_string()))
+    })
+    .await;
+
+    alice.send(EngineCommand::Call(vec!["bob".to_string()])).unwrap();
+    let call = match wait_for(&mut bob_events, "incoming call", |e| matches!(e, EngineEvent::IncomingCall(_))).await {
+        EngineEvent::IncomingCall(call) => call,
+        _ => unreachable!(),
+    };
+    assert_eq!(call.from_peer, "alice");
+}
+
+#[tokio::test]
+async fn reconfiguring_applies_to_the_next_call() {
+    let server = LoopbackServer::start().await;
+    let alice = engine(&server, "alice", false);
+    let bob = engine(&server, "bob", false);
+    let mut alice_events = alice.subscribe();
+    let mut bob_events = bob.subscribe();
+
+    alice.send(EngineCommand::Connect).unwrap();
+    bob.send(EngineCommand::Connect).unwrap();
+    wait_for(&mut alice_events, "bob in alice's peer list", |e| {
+        matches!(e, EngineEvent::PeerList(peers) if peers.contains(&"bob".to_string()))
+    })
+    .await;
+
+    // Turning auto-answer on while connected; the room and who we are in it stay
+    bob.send(EngineCommand::Reconfigure(Box::new(EngineConfig {
+        room_id: "another-room".to_string(),
+        ..engine_config(&server, "bob", true)
+    })))
+    .unwrap();
+    alice.send(EngineCommand::Call(vec!["bob".to_string()])).unwrap();
+    wait_for(&mut bob_events, "bob call active", |e| matches!(e, EngineEvent::CallActive)).await;
+    wait_for(&mut alice_events, "alice call active", |e| matches!(e, EngineEvent::CallActive)).await;
+}
@@ -0,0 +1,37 @@
+mod support;
+
+use support::{engine, wait_for, LoopbackServer};
+use webrtc_client::engine::{EngineCommand, EngineEvent};
+
+#[tokio::test]
+async fn call_resumes_after_network_change() {
+    let server = LoopbackServer::start().await;
+    let alice = engine(&server, "alice", false);
+    let bob = engine(&server, "bob", true);
+    let mut alice_events = alice.subscribe();
+    let mut bob_events = bob.subscribe();
+
+    alice.send(EngineCommand::Connect).unwrap();
+    bob.send(EngineCommand::Connect).unwrap();
+    wait_for(&mut alice_events, "bob in alice's peer list", |e| {
+        matches!(e, EngineEvent::PeerList(peers) if peers.contains(&"bob".to_string()))
+    })
+    .await;
+
+    alice.send(EngineCommand::Call(vec!["bob".to_string()])).unwrap();
+    wait_for(&mut alice_events, "audio at alice", |e| matches!(e, EngineEvent::RemoteAudioStarted)).await;
+    wait_for(&mut bob_events, "audio at bob", |e| matches!(e, EngineEvent::RemoteAudioStarted)).await;
+
+    // Signaling is re-established and ICE restarts without anyone hanging up
+    alice.send(EngineCommand::NetworkChanged).unwrap();
+    wait_for(&mut alice_events, "alice reconnecting", |e| matches!(e, EngineEvent::Reconnecting)).await;
+    wait_for(&mut alice_events, "alice back on signaling", |e| matches!(e, EngineEvent::Connected)).await;
+    wait_for(&mut alice_events, "call resumed", |e| {
+        assert!(!matches!(e, EngineEvent::CallEnded), "call ended instead of resuming");
+        matches!(e, EngineEvent::CallResumed)
+    })
+    .await;
+
+    alice.send(EngineCommand::HangUp).unwrap();
+    wait_for(&mut bob_events, "bob call ended", |e| matches!(e, EngineEvent::CallEnded)).await;
+}
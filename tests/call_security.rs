@@ -0,0 +1,22 @@
+use webrtc_client::security::{sdp_fingerprint, CallSecurity};
+
+#[test]
+fn the_fingerprint_comes_from_the_sdp() {
+    let sdp = "v=0\r\no=- 1 2 IN IP4 0.0.0.0\r\na=fingerprint:sha-256 AB:CD:EF\r\na=setup:actpass\r\n";
+    assert_eq!(sdp_fingerprint(sdp), Some("sha-256 AB:CD:EF".to_string()));
+    assert_eq!(sdp_fingerprint("v=0\r\n"), None);
+}
+
+#[test]
+fn a_media_relay_is_not_end_to_end() {
+    let direct = CallSecurity { encrypted: true, ..Default::default() };
+    assert!(direct.end_to_end());
+
+    let turn = CallSecurity { turn_relayed: true, ..direct.clone() };
+    assert!(turn.end_to_end());
+
+    let relayed = CallSecurity { media_relay: Some("relay-1".to_string()), ..direct };
+    assert!(!relayed.end_to_end());
+
+    assert!(!CallSecurity::default().end_to_end());
+}
@@ -0,0 +1,55 @@
+use webrtc_client::audio::tones::{self, CallTones, RingbackStyle};
+use webrtc_client::call::{CallCue, CallSession, CallState};
+
+#[test]
+fn the_last_decline_makes_the_call_busy() {
+    let mut session = CallSession::new("room".to_string(), vec!["alice".to_string(), "bob".to_string()]);
+    assert_eq!(session.state.cue(), Some(CallCue::Ringback));
+
+    session.decline("alice");
+    assert_eq!(session.state, CallState::Dialing);
+    session.decline("bob");
+    assert_eq!(session.state, CallState::Busy);
+    assert_eq!(session.state.cue(), Some(CallCue::Busy));
+    assert!(!session.answered());
+}
+
+#[test]
+fn only_a_call_still_dialing_fails() {
+    let mut dialing = CallSession::new("room".to_string(), vec!["alice".to_string()]);
+    dialing.fail();
+    assert_eq!(dialing.state, CallState::Failed);
+    assert_eq!(dialing.state.cue(), Some(CallCue::Failed));
+
+    let mut active = CallSession::new("room".to_string(), vec!["alice".to_string()]);
+    active.mark_active();
+    active.fail();
+    assert_eq!(active.state, CallState::Active);
+    assert_eq!(active.state.cue(), None);
+    assert!(active.answered());
+}
+
+#[test]
+fn only_ringback_repeats() {
+    assert!(CallCue::Ringback.repeats());
+    assert!(!CallCue::Busy.repeats());
+    assert!(!CallCue::Failed.repeats());
+}
+
+#[test]
+fn the_ringback_style_is_configurable() {
+    let tones: CallTones = serde_json::from_str(r#"{"ringback":"europe"}"#).unwrap();
+    assert!(tones.enabled);
+    assert_eq!(tones.ringback, RingbackStyle::Europe);
+    assert_eq!(tones.sound(CallCue::Ringback), tones::ringback(RingbackStyle::Europe));
+    assert_ne!(tones::ringback(RingbackStyle::Europe), tones::ringback(RingbackStyle::NorthAmerica));
+}
+
+#[test]
+fn an_unreadable_custom_sound_falls_back_to_the_built_in_one() {
+    let tones = CallTones {
+        busy_sound: Some("/nonexistent/busy.wav".into()),
+        ..Default::default()
+    };
+    assert_eq!(tones.sound(CallCue::Busy), tones::busy());
+}
@@ -0,0 +1,33 @@
+use webrtc_client::config::Settings;
+use webrtc_client::telemetry::{self, CallRole, CallTrace, TelemetryConfig};
+
+#[test]
+fn phases_are_recorded_in_order() {
+    let mut trace = CallTrace::start(CallRole::Caller, "room", &["bob".to_string()]);
+    trace.phase("call.request");
+    trace.phase("call.offer");
+    trace.phase("call.answer");
+    assert_eq!(trace.current_phase(), Some("call.answer"));
+
+    let names: Vec<&str> = trace.phases().iter().map(|(name, _)| *name).collect();
+    assert_eq!(names, vec!["call.request", "call.offer"]);
+    trace.finish();
+}
+
+#[test]
+fn export_is_off_unless_configured() {
+    let settings: Settings = serde_json::from_str("{}").unwrap();
+    assert_eq!(settings.telemetry, TelemetryConfig::default());
+    assert!(!settings.telemetry.enabled);
+    assert!(telemetry::init(&settings.telemetry).is_ok());
+}
+
+#[cfg(not(feature = "otel"))]
+#[test]
+fn enabling_export_without_otel_support_is_an_error() {
+    let config = TelemetryConfig {
+        enabled: true,
+        ..Default::default()
+    };
+    assert!(telemetry::init(&config).is_err());
+}
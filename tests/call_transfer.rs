@@ -0,0 +1,52 @@
+mod support;
+
+use support::{engine, wait_for, LoopbackServer};
+use webrtc_client::engine::{EngineCommand, EngineEvent};
+
+#[tokio::test]
+async fn a_blind_transfer_hands_the_call_to_a_third_peer() {
+    let server = LoopbackServer::start().await;
+    let alice = engine(&server, "alice", false);
+    let bob = engine(&server, "bob", true);
+    let carol = engine(&server, "carol", true);
+    let mut alice_events = alice.subscribe();
+    let mut bob_events = bob.subscribe();
+    let mut carol_events = carol.subscribe();
+
+    for client in [&alice, &bob, &carol] {
+        client.send(EngineCommand::Connect).unwrap();
+    }
+    wait_for(&mut alice_events, "everyone in alice's peer list", |e| {
+        matches!(e, EngineEvent::PeerList(peers) if peers.len() == 2)
+    })
+    .await;
+
+    alice.send(EngineCommand::Call(vec!["bob".to_string()])).unwrap();
+    wait_for(&mut bob_events, "bob call active", |e| matches!(e, EngineEvent::CallActive)).await;
+
+    alice.send(EngineCommand::Transfer("carol".to_string())).unwrap();
+    wait_for(&mut alice_events, "alice call ended", |e| matches!(e, EngineEvent::CallEnded)).await;
+    let transferred = wait_for(&mut bob_events, "bob transferred", |e| matches!(e, EngineEvent::Transferred { .. })).await;
+    match transferred {
+        EngineEvent::Transferred { by, target } => {
+            assert_eq!(by, "alice");
+            assert_eq!(target, "carol");
+        }
+        _ => unreachable!(),
+    }
+
+    wait_for(&mut carol_events, "carol call active", |e| matches!(e, EngineEvent::CallActive)).await;
+    wait_for(&mut bob_events, "bob connected to carol", |e| matches!(e, EngineEvent::CallActive)).await;
+}
+
+#[tokio::test]
+async fn only_a_call_in_progress_can_be_transferred() {
+    let server = LoopbackServer::start().await;
+    let alice = engine(&server, "alice", false);
+    let mut alice_events = alice.subscribe();
+
+    alice.send(EngineCommand::Connect).unwrap();
+    wait_for(&mut alice_events, "alice connected", |e| matches!(e, EngineEvent::Connected)).await;
+    alice.send(EngineCommand::Transfer("carol".to_string())).unwrap();
+    wait_for(&mut alice_events, "transfer refused", |e| matches!(e, EngineEvent::Error(message) if message.contains("Not in a call"))).await;
+}
@@ -0,0 +1,37 @@
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::time::timeout;
+use webrtc::ice_transport::ice_candidate::RTCIceCandidate;
+use webrtc_client::audio::MockBackend;
+use webrtc_client::webrtc::WebRTCClient;
+
+async fn client() -> WebRTCClient {
+    WebRTCClient::builder().audio_backend(Arc::new(MockBackend::default())).build().await.unwrap()
+}
+
+#[tokio::test]
+async fn candidates_ahead_of_the_offer_wait_for_it() {
+    let alice = client().await;
+    let bob = client().await;
+
+    let (candidates_tx, mut candidates) = mpsc::unbounded_channel();
+    alice.peer_connection.on_ice_candidate(Box::new(move |candidate: Option<RTCIceCandidate>| {
+        if let Some(init) = candidate.and_then(|c| c.to_json().ok()) {
+            let _ = candidates_tx.send(init.candidate);
+        }
+        Box::pin(async {})
+    }));
+    let offer = alice.create_offer().await.unwrap();
+    let candidate = timeout(Duration::from_secs(10), candidates.recv())
+        .await
+        .expect("no candidate gathered")
+        .unwrap();
+
+    // Delivered before the offer, as a reordering server might
+    bob.add_ice_candidate("alice", candidate).await.unwrap();
+    assert_eq!(bob.pending_candidates("alice").await, 1);
+
+    bob.handle_offer(offer).await.unwrap();
+    assert_eq!(bob.pending_candidates("alice").await, 0);
+}
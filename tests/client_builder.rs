@@ -0,0 +1,66 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use webrtc::api::interceptor_registry::configure_rtcp_reports;
+use webrtc::peer_connection::configuration::RTCConfiguration;
+use webrtc::peer_connection::policy::ice_transport_policy::RTCIceTransportPolicy;
+use webrtc_client::audio::{AudioTrack, MockBackend};
+use webrtc_client::connection::ConnectionMonitor;
+use webrtc_client::webrtc::{IceServerConfig, WebRTCClient};
+
+#[tokio::test]
+async fn injected_parts_are_the_ones_used() {
+    let track = Arc::new(AudioTrack::new("audio".into(), "alice".into()));
+    let monitor = ConnectionMonitor::new();
+    let client = WebRTCClient::builder()
+        .audio_backend(Arc::new(MockBackend::default()))
+        .audio_track(track.clone())
+        .connection_monitor(monitor.clone())
+        .build()
+        .await
+        .unwrap();
+
+    assert!(Arc::ptr_eq(&client.audio_track, &track));
+    client.connection_monitor.log_event("from the client");
+    assert_eq!(monitor.events().last().map(|event| event.message.as_str()), Some("from the client"));
+}
+
+#[tokio::test]
+async fn media_engine_and_registry_hooks_run() {
+    let customized = Arc::new(AtomicBool::new(false));
+    let registry_built = Arc::new(AtomicBool::new(false));
+    let (customized_flag, registry_flag) = (customized.clone(), registry_built.clone());
+    WebRTCClient::builder()
+        .audio_backend(Arc::new(MockBackend::default()))
+        .customize_media_engine(move |_| {
+            customized_flag.store(true, Ordering::Relaxed);
+            Ok(())
+        })
+        .interceptor_registry(move |registry, _| {
+            registry_flag.store(true, Ordering::Relaxed);
+            Ok(configure_rtcp_reports(registry))
+        })
+        .build()
+        .await
+        .unwrap();
+
+    assert!(customized.load(Ordering::Relaxed));
+    assert!(registry_built.load(Ordering::Relaxed));
+}
+
+#[tokio::test]
+async fn ice_configuration_keeps_its_policy_and_gets_the_servers() {
+    let client = WebRTCClient::builder()
+        .audio_backend(Arc::new(MockBackend::default()))
+        .ice_servers(vec![IceServerConfig { urls: vec!["stun:stun.example.org:3478".into()], ..Default::default() }])
+        .rtc_configuration(RTCConfiguration {
+            ice_transport_policy: RTCIceTransportPolicy::Relay,
+            ..Default::default()
+        })
+        .build()
+        .await
+        .unwrap();
+
+    let configuration = client.peer_connection.get_configuration().await;
+    assert_eq!(configuration.ice_transport_policy, RTCIceTransportPolicy::Relay);
+    assert_eq!(configuration.ice_servers[0].urls, vec!["stun:stun.example.org:3478".to_string()]);
+}
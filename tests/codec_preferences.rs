@@ -0,0 +1,79 @@
+mod support;
+
+use support::{engine, engine_with_config, wait_for, LoopbackServer};
+use webrtc_client::audio::AudioCodec;
+use webrtc_client::engine::{EngineCommand, EngineEvent};
+use webrtc_client::webrtc::WebRTCConfig;
+
+#[tokio::test]
+async fn g711_only_caller_negotiates_pcmu_and_audio_flows() {
+    let config = WebRTCConfig {
+        codec_preferences: vec![AudioCodec::Pcmu],
+        ..Default::default()
+    };
+
+    let server = LoopbackServer::start().await;
+    let alice = engine_with_config(&server, "alice", false, config);
+    let bob = engine(&server, "bob", true);
+    let mut alice_events = alice.subscribe();
+    let mut bob_events = bob.subscribe();
+
+    alice.send(EngineCommand::Connect).unwrap();
+    bob.send(EngineCommand::Connect).unwrap();
+    wait_for(&mut alice_events, "bob in alice's peer list", |e| {
+        matches!(e, EngineEvent::PeerList(peers) if peers.contains(&"bob".to_string()))
+    })
+    .await;
+
+    alice.send(EngineCommand::Call(vec!["bob".to_string()])).unwrap();
+    wait_for(&mut bob_events, "audio at bob", |e| matches!(e, EngineEvent::RemoteAudioStarted)).await;
+    wait_for(&mut alice_events, "audio at alice", |e| matches!(e, EngineEvent::RemoteAudioStarted)).await;
+
+    let track = alice.local_track().borrow().clone().expect("alice has a local track");
+    assert_eq!(track.codec().await, Some(AudioCodec::Pcmu));
+}
+
+#[tokio::test]
+async fn g722_only_caller_negotiates_g722_and_audio_flows() {
+    let config = WebRTCConfig {
+        codec_preferences: vec![AudioCodec::G722],
+        ..Default::default()
+    };
+
+    let server = LoopbackServer::start().await;
+    let alice = engine_with_config(&server, "alice", false, config);
+    let bob = engine(&server, "bob", true);
+    let mut alice_events = alice.subscribe();
+    let mut bob_events = bob.subscribe();
+
+    alice.send(EngineCommand::Connect).unwrap();
+    bob.send(EngineCommand::Connect).unwrap();
+    wait_for(&mut alice_events, "bob in alice's peer list", |e| {
+        matches!(e, EngineEvent::PeerList(peers) if peers.contains(&"bob".to_string()))
+    })
+    .await;
+
+    alice.send(EngineCommand::Call(vec!["bob".to_string()])).unwrap();
+    wait_for(&mut bob_events, "audio at bob", |e| matches!(e, EngineEvent::RemoteAudioStarted)).await;
+    wait_for(&mut alice_events, "audio at alice", |e| matches!(e, EngineEvent::RemoteAudioStarted)).await;
+
+    let track = alice.local_track().borrow().clone().expect("alice has a local track");
+    assert_eq!(track.codec().await, Some(AudioCodec::G722));
+}
+
+#[test]
+fn preferences_reorder_and_keep_telephone_events() {
+    let config = WebRTCConfig {
+        codec_preferences: vec![AudioCodec::Pcma, AudioCodec::Opus, AudioCodec::Pcma],
+        ..Default::default()
+    };
+    let mimes: Vec<String> = config
+        .audio_codec_parameters()
+        .into_iter()
+        .map(|p| p.capability.mime_type)
+        .collect();
+    assert_eq!(
+        mimes,
+        vec!["audio/PCMA", "audio/opus", "audio/telephone-event", "audio/telephone-event"]
+    );
+}
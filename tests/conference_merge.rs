@@ -0,0 +1,94 @@
+mod support;
+
+use support::{engine, wait_for, LoopbackServer};
+use webrtc_client::call::{CallSession, CallState, ConferenceMerge};
+use webrtc_client::engine::{EngineCommand, EngineEvent};
+
+#[test]
+fn only_a_connected_call_goes_on_hold() {
+    let mut session = CallSession::new("room".to_string(), vec!["bob".to_string()]);
+    session.hold();
+    assert_eq!(session.state, CallState::Dialing);
+
+    session.mark_active();
+    session.hold();
+    assert_eq!(session.state, CallState::Held);
+    assert!(session.answered());
+    assert_eq!(session.state.cue(), None);
+    session.resume();
+    assert_eq!(session.state, CallState::Active);
+}
+
+#[test]
+fn the_conference_goes_ahead_with_whoever_arrived() {
+    let merge = ConferenceMerge::new("lobby", "alice".to_string(), vec!["bob".to_string(), "carol".to_string()]);
+    assert!(merge.room_id.starts_with("lobby-conference-"));
+
+    let in_room = vec!["carol".to_string(), "dave".to_string()];
+    assert_eq!(merge.arrived(&in_room), vec!["carol"]);
+    assert!(!merge.everyone_arrived(&in_room));
+    assert!(merge.everyone_arrived(&["bob".to_string(), "carol".to_string()]));
+}
+
+#[tokio::test]
+async fn two_calls_are_merged_into_a_conference_room() {
+    let server = LoopbackServer::start().await;
+    let alice = engine(&server, "alice", false);
+    let bob = engine(&server, "bob", true);
+    let carol = engine(&server, "carol", false);
+    let mut alice_events = alice.subscribe();
+    let mut bob_events = bob.subscribe();
+    let mut carol_events = carol.subscribe();
+
+    for client in [&alice, &bob, &carol] {
+        client.send(EngineCommand::Connect).unwrap();
+    }
+    wait_for(&mut alice_events, "everyone in alice's peer list", |e| {
+        matches!(e, EngineEvent::PeerList(peers) if peers.len() == 2)
+    })
+    .await;
+
+    alice.send(EngineCommand::Call(vec!["bob".to_string()])).unwrap();
+    wait_for(&mut alice_events, "alice's call with bob active", |e| matches!(e, EngineEvent::CallActive)).await;
+
+    // Carol calls in; answering puts bob on hold
+    carol.send(EngineCommand::Call(vec!["alice".to_string()])).unwrap();
+    let incoming = wait_for(&mut alice_events, "carol calling alice", |e| matches!(e, EngineEvent::IncomingCall(_))).await;
+    let EngineEvent::IncomingCall(call) = incoming else { unreachable!() };
+    alice.send(EngineCommand::Answer { call, accepted: true }).unwrap();
+    wait_for(&mut alice_events, "bob on hold", |e| {
+        matches!(e, EngineEvent::CallHeld(session) if session.participants == ["bob"])
+    })
+    .await;
+    wait_for(&mut carol_events, "carol's call active", |e| matches!(e, EngineEvent::CallActive)).await;
+
+    alice.send(EngineCommand::Merge).unwrap();
+    let merging = wait_for(&mut alice_events, "alice merging", |e| matches!(e, EngineEvent::Merging { .. })).await;
+    let EngineEvent::Merging { room_id, .. } = merging else { unreachable!() };
+
+    for events in [&mut bob_events, &mut carol_events] {
+        wait_for(events, "pulled into the conference", |e| {
+            matches!(e, EngineEvent::Merging { host, room_id: room } if host == "alice" && *room == room_id)
+        })
+        .await;
+        wait_for(events, "called from the conference room", |e| {
+            matches!(e, EngineEvent::CallStarted(session) if session.room_id == room_id)
+        })
+        .await;
+    }
+}
+
+#[tokio::test]
+async fn merging_needs_a_call_on_hold() {
+    let server = LoopbackServer::start().await;
+    let alice = engine(&server, "alice", false);
+    let mut alice_events = alice.subscribe();
+
+    alice.send(EngineCommand::Connect).unwrap();
+    wait_for(&mut alice_events, "alice connected", |e| matches!(e, EngineEvent::Connected)).await;
+    alice.send(EngineCommand::Merge).unwrap();
+    wait_for(&mut alice_events, "merge refused", |e| {
+        matches!(e, EngineEvent::Error(message) if message.contains("No call on hold"))
+    })
+    .await;
+}
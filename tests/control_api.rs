@@ -0,0 +1,168 @@
+use serde_json::{json, Value};
+use std::net::SocketAddr;
+use tokio::sync::mpsc;
+use webrtc_client::api::{ApiCommand, ApiConfig, ApiRequest, ApiServer, ApiStatus};
+
+fn start(token: Option<&str>) -> (ApiServer, mpsc::Receiver<ApiRequest>, String) {
+    let config = ApiConfig {
+        enabled: true,
+        listen: SocketAddr::from(([127, 0, 0, 1], 0)),
+        token: token.map(str::to_string),
+    };
+    let (server, requests) = ApiServer::bind(&config).unwrap();
+    let url = format!("http://{}", server.local_addr());
+    (server, requests, url)
+}
+
+// Stands in for the UI: records each command and reports a call with bob
+fn answer(mut requests: mpsc::Receiver<ApiRequest>) -> mpsc::UnboundedReceiver<ApiCommand> {
+    let (seen_tx, seen) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        while let Some(request) = requests.recv().await {
+            let reply = match request.command {
+                ApiCommand::SetMuted(_) => Err("Not in a call".to_string()),
+                _ => Ok(ApiStatus {
+                    connected: true,
+                    room: "lobby".to_string(),
+                    in_call: true,
+                    participants: vec!["bob".to_string()],
+                    ..Default::default()
+                }),
+            };
+            let _ = seen_tx.send(request.command);
+            let _ = request.reply.send(reply);
+        }
+    });
+    seen
+}
+
+#[tokio::test]
+async fn commands_reach_the_client_and_return_its_status() {
+    let (_server, requests, url) = start(None);
+    let mut seen = answer(requests);
+    let client = reqwest::Client::new();
+
+    let response = client.post(format!("{}/call", url)).json(&json!({ "peers": ["bob"] })).send().await.unwrap();
+    assert_eq!(response.status(), 202);
+    let status: Value = response.json().await.unwrap();
+    assert_eq!(status["participants"], json!(["bob"]));
+    assert_eq!(seen.recv().await, Some(ApiCommand::Call { peers: vec!["bob".to_string()] }));
+
+    let response = client.post(format!("{}/join", url)).send().await.unwrap();
+    assert_eq!(response.status(), 202);
+    assert_eq!(seen.recv().await, Some(ApiCommand::Join { room: None }));
+
+    let response = client.get(format!("{}/status", url)).send().await.unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(seen.recv().await, Some(ApiCommand::Status));
+}
+
+#[tokio::test]
+async fn bad_requests_are_rejected_before_the_client_sees_them() {
+    let (_server, requests, url) = start(None);
+    let mut seen = answer(requests);
+    let client = reqwest::Client::new();
+
+    let response = client
+        .post(format!("{}/call", url))
+        .header("Content-Type", "application/json")
+        .body("{\"peers\": \"bob\"}")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 400);
+    let response = client.get(format!("{}/dial", url)).send().await.unwrap();
+    assert_eq!(response.status(), 404);
+
+    // Refused by the client
+    let response = client.post(format!("{}/mute", url)).json(&json!({ "muted": true })).send().await.unwrap();
+    assert_eq!(response.status(), 409);
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["error"], "Not in a call");
+    assert_eq!(seen.recv().await, Some(ApiCommand::SetMuted(true)));
+}
+
+#[tokio::test]
+async fn a_configured_token_is_required() {
+    let (_server, requests, url) = start(Some("kiosk-secret"));
+    let _seen = answer(requests);
+    let client = reqwest::Client::new();
+
+    let response = client.get(format!("{}/status", url)).send().await.unwrap();
+    assert_eq!(response.status(), 401);
+    let response = client.get(format!("{}/status", url)).bearer_auth("wrong").send().await.unwrap();
+    assert_eq!(response.status(), 401);
+    let response = client.get(format!("{}/status", url)).bearer_auth("kiosk-secret").send().await.unwrap();
+    assert_eq!(response.status(), 200);
+}
+
+#[tokio::test]
+async fn requests_from_web_pages_are_refused() {
+    let (_server, requests, url) = start(None);
+    let _seen = answer(requests);
+    let client = reqwest::Client::new();
+
+    let response = client
+        .post(format!("{}/call", url))
+        .header("Origin", "https://evil.example")
+        .json(&json!({ "peers": ["bob"] }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 403);
+}
+
+#[tokio::test]
+async fn bodies_must_be_json() {
+    let (_server, requests, url) = start(None);
+    let _seen = answer(requests);
+    let client = reqwest::Client::new();
+
+    // What a page can post without a preflight
+    let response = client
+        .post(format!("{}/call", url))
+        .header("Content-Type", "text/plain")
+        .body("{\"peers\": [\"bob\"]}")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 415);
+    let response = client.post(format!("{}/mute", url)).body("{\"muted\": true}").send().await.unwrap();
+    assert_eq!(response.status(), 415);
+    let response = client
+        .post(format!("{}/call", url))
+        .header("Content-Type", "application/json; charset=utf-8")
+        .body("{\"peers\": [\"bob\"]}")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 202);
+}
+
+#[tokio::test]
+async fn a_rebound_host_name_is_refused() {
+    let (_server, requests, url) = start(None);
+    let _seen = answer(requests);
+    let client = reqwest::Client::new();
+
+    let response = client.get(format!("{}/status", url)).header("Host", "evil.example").send().await.unwrap();
+    assert_eq!(response.status(), 403);
+    for host in ["localhost:7878", "127.0.0.1", "[::1]:7878"] {
+        let response = client.get(format!("{}/status", url)).header("Host", host).send().await.unwrap();
+        assert_eq!(response.status(), 200, "{}", host);
+    }
+}
+
+#[tokio::test]
+async fn other_interfaces_need_a_token() {
+    let mut config = ApiConfig {
+        enabled: true,
+        listen: SocketAddr::from(([0, 0, 0, 0], 0)),
+        token: None,
+    };
+    assert!(ApiServer::bind(&config).is_err());
+    config.token = Some(String::new());
+    assert!(ApiServer::bind(&config).is_err());
+    config.token = Some("kiosk-secret".to_string());
+    assert!(ApiServer::bind(&config).is_ok());
+}
@@ -0,0 +1,34 @@
+use webrtc_client::audio::{fallback_device, DeviceEvent, StreamDirection};
+use webrtc_client::connection::ConnectionMonitor;
+
+#[test]
+fn default_device_is_kept_until_it_fails() {
+    let available = ["Speakers", "USB Headset", "HDMI"];
+    assert_eq!(fallback_device(Some("USB Headset"), &available, &[]), Some("USB Headset"));
+
+    let failed = vec!["USB Headset".to_string()];
+    assert_eq!(fallback_device(Some("USB Headset"), &available, &failed), Some("Speakers"));
+}
+
+#[test]
+fn every_device_failing_leaves_nothing_to_fall_back_to() {
+    let available = ["Speakers", "HDMI"];
+    let failed = vec!["Speakers".to_string(), "HDMI".to_string()];
+    assert_eq!(fallback_device(None, &available, &failed), None);
+    assert_eq!(fallback_device(None, &available, &failed[..1]), Some("HDMI"));
+}
+
+#[tokio::test]
+async fn device_switches_reach_connection_subscribers() {
+    let monitor = ConnectionMonitor::new();
+    let mut status = monitor.subscribe();
+    let event = DeviceEvent::Switched {
+        direction: StreamDirection::Capture,
+        from: "USB Headset".to_string(),
+        to: "Built-in Microphone".to_string(),
+    };
+
+    monitor.report_device_event(event.clone());
+    status.changed().await.unwrap();
+    assert_eq!(status.borrow().device_event, Some(event));
+}
@@ -0,0 +1,21 @@
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::{sleep, Instant};
+use webrtc_client::audio::MockBackend;
+use webrtc_client::echo::{EchoTest, ECHO_DELAY};
+use webrtc_client::webrtc::WebRTCConfig;
+
+#[tokio::test]
+async fn captured_audio_comes_back_through_the_bot() {
+    let backend = Arc::new(MockBackend::default());
+    let test = EchoTest::start(&WebRTCConfig::default(), backend.clone()).await.unwrap();
+
+    let deadline = Instant::now() + ECHO_DELAY + Duration::from_secs(15);
+    while backend.frames_played() == 0 {
+        assert!(Instant::now() < deadline, "no echoed audio was played back");
+        sleep(Duration::from_millis(100)).await;
+    }
+    assert!(backend.frames_captured() > 0);
+
+    test.stop().await;
+}
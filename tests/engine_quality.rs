@@ -0,0 +1,69 @@
+mod support;
+
+use std::time::Duration;
+use support::{engine, wait_for, LoopbackServer};
+use tokio::time::timeout;
+use webrtc_client::engine::{EngineCommand, EngineEvent};
+
+#[tokio::test]
+async fn call_stats_are_published_until_hang_up() {
+    let server = LoopbackServer::start().await;
+    let alice = engine(&server, "alice", false);
+    let bob = engine(&server, "bob", true);
+    let mut alice_events = alice.subscribe();
+    let mut quality = alice.quality();
+    assert_eq!(*quality.borrow(), None);
+
+    alice.send(EngineCommand::Connect).unwrap();
+    bob.send(EngineCommand::Connect).unwrap();
+    wait_for(&mut alice_events, "bob in alice's peer list", |e| {
+        matches!(e, EngineEvent::PeerList(peers) if peers.contains(&"bob".to_string()))
+    })
+    .await;
+
+    alice.send(EngineCommand::Call(vec!["bob".to_string()])).unwrap();
+    timeout(Duration::from_secs(15), quality.wait_for(|quality| quality.is_some()))
+        .await
+        .expect("timed out waiting for call stats")
+        .unwrap();
+
+    alice.send(EngineCommand::HangUp).unwrap();
+    wait_for(&mut alice_events, "alice call ended", |e| matches!(e, EngineEvent::CallEnded)).await;
+    assert_eq!(*alice.quality().borrow(), None);
+}
+
+#[tokio::test]
+async fn each_peer_of_a_mesh_call_has_readings_of_their_own_connection() {
+    let server = LoopbackServer::start().await;
+    let alice = engine(&server, "alice", false);
+    let bob = engine(&server, "bob", true);
+    let carol = engine(&server, "carol", true);
+    let mut alice_events = alice.subscribe();
+    let mut readings = alice.peer_qualities();
+
+    for peer in [&alice, &bob, &carol] {
+        peer.send(EngineCommand::Connect).unwrap();
+    }
+    wait_for(&mut alice_events, "bob and carol in alice's peer list", |e| {
+        matches!(e, EngineEvent::PeerList(peers) if peers.len() == 3)
+    })
+    .await;
+
+    alice.send(EngineCommand::Call(vec!["bob".to_string(), "carol".to_string()])).unwrap();
+    timeout(Duration::from_secs(15), readings.wait_for(|readings| readings.contains_key("bob") && readings.contains_key("carol")))
+        .await
+        .expect("timed out waiting for both peers' readings")
+        .unwrap();
+
+    // Carol leaving takes her reading with her; bob's connection goes on
+    carol.send(EngineCommand::HangUp).unwrap();
+    timeout(Duration::from_secs(15), readings.wait_for(|readings| !readings.contains_key("carol")))
+        .await
+        .expect("timed out waiting for carol's reading to go")
+        .unwrap();
+    assert!(readings.borrow().contains_key("bob"));
+
+    alice.send(EngineCommand::HangUp).unwrap();
+    wait_for(&mut alice_events, "alice call ended", |e| matches!(e, EngineEvent::CallEnded)).await;
+    assert!(alice.peer_qualities().borrow().is_empty());
+}
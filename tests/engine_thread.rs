@@ -0,0 +1,62 @@
+mod support;
+
+use std::time::Duration;
+use support::{engine_config, wait_for, LoopbackServer};
+use tokio::runtime::Runtime;
+use webrtc_client::engine::{CallEngine, EngineCommand, EngineEvent};
+
+// The host here is a plain thread: only the test's loopback server has a runtime, and the
+// engine never runs on it
+#[test]
+fn the_engine_runs_on_a_thread_of_its_own() {
+    let host = Runtime::new().unwrap();
+    let server = host.block_on(LoopbackServer::start());
+    let alice = CallEngine::spawn_on_thread(engine_config(&server, "alice", false)).unwrap();
+    let bob = CallEngine::spawn_on_thread(engine_config(&server, "bob", true)).unwrap();
+    let mut alice_events = alice.subscribe();
+    let mut bob_events = bob.subscribe();
+
+    alice.send(EngineCommand::Connect).unwrap();
+    bob.send(EngineCommand::Connect).unwrap();
+    host.block_on(async {
+        wait_for(&mut alice_events, "bob in alice's peer list", |e| {
+            matches!(e, EngineEvent::PeerList(peers) if peers.contains(&"bob".to_string()))
+        })
+        .await;
+        alice.send(EngineCommand::Call(vec!["bob".to_string()])).unwrap();
+        wait_for(&mut bob_events, "bob call active", |e| matches!(e, EngineEvent::CallActive)).await;
+
+        alice.shutdown().await;
+        wait_for(&mut bob_events, "bob call ended", |e| matches!(e, EngineEvent::CallEnded)).await;
+        bob.shutdown().await;
+    });
+}
+
+// Like a window's close handler: no runtime on the calling thread to wait with
+#[test]
+fn a_thread_without_a_runtime_can_shut_the_engine_down() {
+    let host = Runtime::new().unwrap();
+    let server = host.block_on(LoopbackServer::start());
+    let alice = CallEngine::spawn_on_thread(engine_config(&server, "alice", false)).unwrap();
+    let bob = CallEngine::spawn_on_thread(engine_config(&server, "bob", true)).unwrap();
+    let mut alice_events = alice.subscribe();
+    let mut bob_events = bob.subscribe();
+
+    alice.send(EngineCommand::Connect).unwrap();
+    bob.send(EngineCommand::Connect).unwrap();
+    host.block_on(async {
+        wait_for(&mut alice_events, "bob in alice's peer list", |e| {
+            matches!(e, EngineEvent::PeerList(peers) if peers.contains(&"bob".to_string()))
+        })
+        .await;
+        alice.send(EngineCommand::Call(vec!["bob".to_string()])).unwrap();
+        wait_for(&mut bob_events, "bob call active", |e| matches!(e, EngineEvent::CallActive)).await;
+    });
+
+    alice.shutdown_blocking(Duration::from_secs(5));
+    assert!(alice.send(EngineCommand::HangUp).is_err(), "engine still running after shutdown");
+    host.block_on(async {
+        wait_for(&mut bob_events, "bob call ended", |e| matches!(e, EngineEvent::CallEnded)).await;
+        bob.shutdown().await;
+    });
+}
@@ -0,0 +1,108 @@
+use hyper::header::AUTHORIZATION;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Request, Response, Server};
+use serde_json::{json, Value};
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::time::{Duration, UNIX_EPOCH};
+use tokio::sync::mpsc;
+use webrtc_client::metrics::ConnectionQuality;
+use webrtc_client::publisher::{CallEvent, EventPublisher, PublisherConfig, QualityAlarm};
+
+fn quality(score: u8) -> ConnectionQuality {
+    ConnectionQuality {
+        quality_score: score,
+        ..Default::default()
+    }
+}
+
+// Collects the authorization header and JSON body of each POST
+fn webhook() -> (String, mpsc::UnboundedReceiver<(Option<String>, Value)>) {
+    let (received_tx, received) = mpsc::unbounded_channel();
+    let make_service = make_service_fn(move |_| {
+        let received_tx = received_tx.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request: Request<Body>| {
+                let received_tx = received_tx.clone();
+                async move {
+                    let auth = request.headers().get(AUTHORIZATION).and_then(|value| value.to_str().ok()).map(str::to_string);
+                    let body = hyper::body::to_bytes(request.into_body()).await.unwrap();
+                    let _ = received_tx.send((auth, serde_json::from_slice(&body).unwrap()));
+                    Ok::<_, Infallible>(Response::new(Body::empty()))
+                }
+            }))
+        }
+    });
+    let server = Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0))).serve(make_service);
+    let url = format!("http://{}/hooks/call", server.local_addr());
+    tokio::spawn(server);
+    (url, received)
+}
+
+#[test]
+fn payloads_carry_the_event_name_sender_and_time() {
+    let event = CallEvent::Ended {
+        room: "lobby".to_string(),
+        participants: vec!["bob".to_string()],
+        answered: true,
+        duration_secs: 95,
+    };
+    assert_eq!(event.name(), "ended");
+    let payload = event.payload("alice", UNIX_EPOCH + Duration::from_millis(1_714_571_107_250));
+    assert_eq!(
+        payload,
+        json!({
+            "event": "ended",
+            "room": "lobby",
+            "participants": ["bob"],
+            "answered": true,
+            "duration_secs": 95,
+            "client": "alice",
+            "timestamp_ms": 1_714_571_107_250u64,
+        })
+    );
+}
+
+#[test]
+fn a_poor_call_alerts_once_until_it_recovers() {
+    let mut alarm = QualityAlarm::default();
+    assert!(alarm.check("lobby", &quality(80)).is_none());
+    assert!(matches!(alarm.check("lobby", &quality(40)), Some(CallEvent::QualityAlert { quality_score: 40, .. })));
+    assert!(alarm.check("lobby", &quality(30)).is_none());
+    // Not far enough above the alert line yet
+    assert!(alarm.check("lobby", &quality(60)).is_none());
+    assert!(matches!(alarm.check("lobby", &quality(75)), Some(CallEvent::QualityRecovered { quality_score: 75, .. })));
+    assert!(alarm.check("lobby", &quality(90)).is_none());
+}
+
+#[tokio::test]
+async fn nothing_configured_means_no_publisher() {
+    assert!(EventPublisher::start(&PublisherConfig::default(), "alice").unwrap().is_none());
+}
+
+#[tokio::test]
+async fn events_are_posted_to_the_webhook_in_order() {
+    let (url, mut received) = webhook();
+    let config = PublisherConfig {
+        webhook_url: Some(url),
+        webhook_token: Some("hook-secret".to_string()),
+        mqtt: None,
+    };
+    let publisher = EventPublisher::start(&config, "alice").unwrap().unwrap();
+    publisher.publish(CallEvent::IncomingCall {
+        room: "lobby".to_string(),
+        from_peer: "bob".to_string(),
+    });
+    publisher.publish(CallEvent::Answered {
+        room: "lobby".to_string(),
+        participants: vec!["bob".to_string()],
+    });
+
+    let (auth, first) = received.recv().await.unwrap();
+    assert_eq!(auth.as_deref(), Some("Bearer hook-secret"));
+    assert_eq!(first["event"], "incoming_call");
+    assert_eq!(first["from_peer"], "bob");
+    assert_eq!(first["client"], "alice");
+    let (_, second) = received.recv().await.unwrap();
+    assert_eq!(second["event"], "answered");
+}
@@ -0,0 +1,65 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use webrtc_client::audio::{AudioBackend, AudioTrack, CaptureSource, ExternalCaptureBackend, MockBackend, EXTERNAL_SAMPLE_RATE};
+
+// Half scale, so the meter reads about -6 dBFS
+fn pcm_frames(frames: usize) -> Vec<u8> {
+    let samples = EXTERNAL_SAMPLE_RATE as usize / 50 * frames;
+    (0..samples).flat_map(|_| 16384i16.to_le_bytes()).collect()
+}
+
+fn backend(source: CaptureSource) -> ExternalCaptureBackend {
+    ExternalCaptureBackend::new(source, Arc::new(MockBackend::default()))
+}
+
+async fn wait_for_level(track: &AudioTrack) -> f32 {
+    for _ in 0..100 {
+        if let Some(level) = track.input_meter().level_db() {
+            return level;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("no audio reached the track");
+}
+
+#[test]
+fn sources_parse_from_the_command_line() {
+    assert_eq!("-".parse::<CaptureSource>().unwrap(), CaptureSource::Stdin);
+    assert_eq!(
+        "udp:127.0.0.1:5004".parse::<CaptureSource>().unwrap(),
+        CaptureSource::Udp("127.0.0.1:5004".parse().unwrap())
+    );
+    assert_eq!("/tmp/tts.fifo".parse::<CaptureSource>().unwrap(), CaptureSource::Pipe(PathBuf::from("/tmp/tts.fifo")));
+    assert!("udp:nowhere".parse::<CaptureSource>().is_err());
+}
+
+#[tokio::test]
+async fn pcm_from_a_pipe_reaches_the_call() {
+    let path = std::env::temp_dir().join(format!("webrtc-client-capture-{}.pcm", std::process::id()));
+    std::fs::write(&path, pcm_frames(5)).unwrap();
+
+    let track = Arc::new(AudioTrack::new("audio".to_string(), "bot".to_string()));
+    let _capture = backend(CaptureSource::Pipe(path.clone())).start_capture(track.clone()).unwrap();
+    let level = wait_for_level(&track).await;
+    assert!((level + 6.02).abs() < 0.1, "level {}", level);
+    let _ = std::fs::remove_file(path);
+}
+
+#[tokio::test]
+async fn pcm_datagrams_reach_the_call() {
+    let address = std::net::UdpSocket::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+    let track = Arc::new(AudioTrack::new("audio".to_string(), "bot".to_string()));
+    let _capture = backend(CaptureSource::Udp(address)).start_capture(track.clone()).unwrap();
+
+    let sender = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    for _ in 0..20 {
+        let _ = sender.send_to(&pcm_frames(1), address).await;
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        if track.input_meter().level_db().is_some() {
+            break;
+        }
+    }
+    let level = wait_for_level(&track).await;
+    assert!((level + 6.02).abs() < 0.1, "level {}", level);
+}
@@ -0,0 +1,30 @@
+#![cfg(feature = "ffi")]
+
+use webrtc_client::engine::{EngineCommand, EngineEvent};
+use webrtc_client::ffi::{FfiCommand, FfiConfig, FfiEvent};
+
+#[test]
+fn config_needs_only_the_server_and_names() {
+    let config: FfiConfig =
+        serde_json::from_str(r#"{"signaling_url":"ws://localhost:8080","room_id":"lobby","peer_id":"alice"}"#).unwrap();
+    assert!(!config.auto_answer);
+}
+
+#[test]
+fn commands_arrive_as_tagged_json() {
+    let command: FfiCommand = serde_json::from_str(r#"{"command":"call","peers":["bob"]}"#).unwrap();
+    assert!(matches!(EngineCommand::from(command), EngineCommand::Call(peers) if peers == ["bob"]));
+    assert!(serde_json::from_str::<FfiCommand>(r#"{"command":"set_audio_tap"}"#).is_err());
+}
+
+#[test]
+fn events_leave_as_tagged_json() {
+    let event = FfiEvent::from_engine(&EngineEvent::CallDeclined { peer_id: "bob".into() }).unwrap();
+    assert_eq!(serde_json::to_string(&event).unwrap(), r#"{"event":"call_declined","peer_id":"bob"}"#);
+}
+
+#[test]
+fn mute_maps_onto_the_engine() {
+    let command: FfiCommand = serde_json::from_str(r#"{"command":"set_muted","muted":true}"#).unwrap();
+    assert!(matches!(EngineCommand::from(command), EngineCommand::SetMuted(true)));
+}
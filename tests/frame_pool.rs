@@ -0,0 +1,78 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::sync::atomic::AtomicU32;
+use std::sync::Arc;
+use webrtc_client::audio::{decode_frame, encode_frame, FramePool, PlaybackBuffer};
+
+// Counts this thread's allocations only, so tests running alongside don't show up
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        // try_with: threads still allocate while their locals are torn down
+        let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn allocations() -> usize {
+    ALLOCATIONS.with(Cell::get)
+}
+
+fn frame(n: usize) -> Vec<f32> {
+    (0..1920).map(|i| ((i + n) % 100) as f32 / 100.0).collect()
+}
+
+#[test]
+fn pooled_frames_are_the_wire_format() {
+    let mut pool = FramePool::new(4);
+    // Bigger than a whole block, and frames outliving the block they came from
+    let frames: Vec<_> = (0..40).map(|n| (frame(n), pool.encode(&frame(n)))).collect();
+    for (samples, encoded) in &frames {
+        assert_eq!(encoded, &encode_frame(samples));
+        assert_eq!(&decode_frame(encoded), samples);
+    }
+    assert_eq!(pool.copy(&encode_frame(&[0.25, -1.0])), encode_frame(&[0.25, -1.0]));
+}
+
+#[test]
+fn steady_state_frames_allocate_nothing() {
+    let frames: Vec<Vec<f32>> = (0..8).map(frame).collect();
+    let mut pool = FramePool::default();
+    let mut buffer = PlaybackBuffer::new(96_000, Arc::new(AtomicU32::new(0)));
+    let mut output = Vec::with_capacity(1920);
+    // Lets the pool and the playback queue reach their working size
+    for samples in frames.iter().cycle().take(64) {
+        buffer.push_encoded(&pool.encode(samples));
+        output.clear();
+        buffer.pop_into(&mut output, samples.len());
+    }
+
+    let before = allocations();
+    for samples in frames.iter().cycle().take(1000) {
+        buffer.push_encoded(&pool.encode(samples));
+        output.clear();
+        buffer.pop_into(&mut output, samples.len());
+    }
+    assert_eq!(allocations() - before, 0);
+
+    // What every frame cost before
+    let before = allocations();
+    for samples in frames.iter().cycle().take(1000) {
+        buffer.push(&decode_frame(&encode_frame(samples)));
+        output.clear();
+        buffer.pop_into(&mut output, samples.len());
+    }
+    assert!(allocations() - before >= 2000);
+}
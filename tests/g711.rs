@@ -0,0 +1,51 @@
+use webrtc_client::audio::codec::{
+    alaw_to_linear, linear_to_alaw, linear_to_ulaw, resample, ulaw_to_linear, AudioCodec,
+};
+use webrtc_client::audio::{decode_frame, encode_frame};
+
+#[test]
+fn silence_encodes_to_the_reference_code_words() {
+    assert_eq!(linear_to_ulaw(0), 0xFF);
+    assert_eq!(linear_to_alaw(0), 0xD5);
+}
+
+#[test]
+fn companding_round_trips_within_quantization_error() {
+    for sample in (i16::MIN..=i16::MAX).step_by(97) {
+        let tolerance = (sample as i32).abs() / 16 + 16;
+        let ulaw = ulaw_to_linear(linear_to_ulaw(sample)) as i32;
+        assert!((ulaw - sample as i32).abs() <= tolerance, "u-law {} -> {}", sample, ulaw);
+        let alaw = alaw_to_linear(linear_to_alaw(sample)) as i32;
+        assert!((alaw - sample as i32).abs() <= tolerance, "A-law {} -> {}", sample, alaw);
+    }
+}
+
+#[test]
+fn every_code_word_decodes_and_re_encodes_to_itself() {
+    for byte in 0..=255u8 {
+        // 0x7F and 0xFF are both u-law zero
+        if byte != 0x7F {
+            assert_eq!(linear_to_ulaw(ulaw_to_linear(byte)), byte, "u-law {:#04x}", byte);
+        }
+        assert_eq!(linear_to_alaw(alaw_to_linear(byte)), byte, "A-law {:#04x}", byte);
+    }
+}
+
+#[test]
+fn g711_frames_are_one_byte_per_8khz_sample() {
+    // 20ms at 48kHz
+    let frame = encode_frame(&vec![0.25; 960]);
+    for codec in [AudioCodec::Pcmu, AudioCodec::Pcma] {
+        let payload = codec.encode(&frame, 48_000);
+        assert_eq!(payload.len(), 160);
+        let decoded = decode_frame(&codec.decode(&payload));
+        assert_eq!(decoded.len(), 960);
+        assert!(decoded.iter().all(|s| (s - 0.25).abs() < 0.02));
+    }
+}
+
+#[test]
+fn resampling_keeps_duration() {
+    assert_eq!(resample(&[0.0; 320], 16_000, 8_000).len(), 160);
+    assert_eq!(resample(&[0.0; 160], 8_000, 48_000).len(), 960);
+}
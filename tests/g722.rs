@@ -0,0 +1,87 @@
+use bytes::Bytes;
+use webrtc_client::audio::codec::{resample, AudioCodec, StreamDecoder, StreamEncoder};
+use webrtc_client::audio::{decode_frame, encode_frame};
+
+const RATE: u32 = 16_000;
+
+fn tone(frequency: f32, samples: usize) -> Vec<f32> {
+    (0..samples)
+        .map(|i| (i as f32 * frequency * std::f32::consts::TAU / RATE as f32).sin() * 0.3)
+        .collect()
+}
+
+// Encodes and decodes in 20ms frames, back at 16kHz
+fn round_trip(input: &[f32]) -> Vec<f32> {
+    let mut encoder = StreamEncoder::default();
+    let mut decoder = StreamDecoder::default();
+    input
+        .chunks(320)
+        .flat_map(|frame| {
+            let payload = encoder.encode(AudioCodec::G722, &encode_frame(frame), RATE);
+            resample(&decode_frame(&decoder.decode(AudioCodec::G722, &payload)), 48_000, RATE)
+        })
+        .collect()
+}
+
+// The filter banks delay the output by a few samples
+fn best_snr_db(input: &[f32], output: &[f32]) -> f32 {
+    (0..48)
+        .map(|delay| {
+            let (mut signal, mut noise) = (0.0, 0.0);
+            for i in 1000..input.len() - 48 {
+                signal += input[i] * input[i];
+                noise += (input[i] - output[i + delay]).powi(2);
+            }
+            10.0 * (signal / noise).log10()
+        })
+        .fold(f32::MIN, f32::max)
+}
+
+#[test]
+fn rtp_clock_is_8khz_though_audio_is_16khz() {
+    let parameters = AudioCodec::G722.parameters();
+    assert_eq!(parameters.payload_type, 9);
+    assert_eq!(parameters.capability.mime_type, "audio/G722");
+    assert_eq!(parameters.capability.clock_rate, 8_000);
+    assert_eq!(AudioCodec::G722.clock_rate(), 8_000);
+    assert_eq!(AudioCodec::G722.sample_rate(), 16_000);
+    assert_eq!(AudioCodec::from_mime("audio/g722"), Some(AudioCodec::G722));
+}
+
+#[test]
+fn frames_are_one_byte_per_pair_of_16khz_samples() {
+    // 20ms at 48kHz is 320 samples at 16kHz, or 160 bytes: the same as 160 ticks of the RTP clock
+    let frame = encode_frame(&vec![0.0; 960]);
+    let payload = AudioCodec::G722.encode(&frame, 48_000);
+    assert_eq!(payload.len(), 160);
+    assert_eq!(decode_frame(&AudioCodec::G722.decode(&payload)).len(), 960);
+}
+
+#[test]
+fn speech_band_tones_survive_a_round_trip() {
+    for frequency in [300.0, 1_000.0, 3_000.0] {
+        let input = tone(frequency, RATE as usize);
+        let snr = best_snr_db(&input, &round_trip(&input));
+        assert!(snr > 30.0, "{} Hz came back at {:.1} dB", frequency, snr);
+    }
+}
+
+#[test]
+fn wideband_tones_above_g711_get_through() {
+    // 6kHz is beyond anything 8kHz G.711 can carry
+    let input = tone(6_000.0, RATE as usize);
+    let snr = best_snr_db(&input, &round_trip(&input));
+    assert!(snr > 20.0, "6 kHz came back at {:.1} dB", snr);
+}
+
+#[test]
+fn a_stream_carries_its_state_from_frame_to_frame() {
+    let input = tone(1_000.0, 3_200);
+    let frames: Vec<Bytes> = input.chunks(320).map(encode_frame).collect();
+
+    let mut stream = StreamEncoder::default();
+    let streamed: Vec<Bytes> = frames.iter().map(|frame| stream.encode(AudioCodec::G722, frame, RATE)).collect();
+    let one_off: Vec<Bytes> = frames.iter().map(|frame| AudioCodec::G722.encode(frame, RATE)).collect();
+    assert_eq!(streamed[0], one_off[0]);
+    assert_ne!(streamed[5], one_off[5]);
+}
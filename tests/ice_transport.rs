@@ -0,0 +1,38 @@
+use webrtc_client::factory::WebRTCFactory;
+use webrtc_client::webrtc::{IceTransportConfig, WebRTCConfig};
+
+fn with_ice(ice_transport: IceTransportConfig) -> WebRTCConfig {
+    WebRTCConfig { ice_transport, ..Default::default() }
+}
+
+#[test]
+fn settings_files_only_need_what_they_change() {
+    let config: WebRTCConfig = serde_json::from_str(r#"{"ice_transport":{"port_min":40000,"port_max":40100}}"#).unwrap();
+    assert_eq!(config.ice_transport.port_min, 40000);
+    assert_eq!(config.ice_transport.keepalive_interval_ms, IceTransportConfig::default().keepalive_interval_ms);
+    assert_eq!(config.ice_transport.udp_mux_port, None);
+}
+
+#[test]
+fn a_port_range_is_checked_up_front() {
+    let range = IceTransportConfig { port_min: 40000, port_max: 40100, ..Default::default() };
+    assert!(WebRTCFactory::new(&with_ice(range)).is_ok());
+
+    let inverted = IceTransportConfig { port_min: 40100, port_max: 40000, ..Default::default() };
+    assert!(WebRTCFactory::new(&with_ice(inverted)).is_err());
+}
+
+#[tokio::test]
+async fn a_taken_mux_port_fails_the_factory() {
+    let taken = std::net::UdpSocket::bind("0.0.0.0:0").unwrap();
+    let port = taken.local_addr().unwrap().port();
+    let mux = IceTransportConfig { udp_mux_port: Some(port), ..Default::default() };
+    assert!(WebRTCFactory::new(&with_ice(mux)).is_err());
+}
+
+#[test]
+fn changing_the_transport_needs_a_new_factory() {
+    let factory = WebRTCFactory::new(&WebRTCConfig::default()).unwrap();
+    let keepalive = IceTransportConfig { keepalive_interval_ms: 500, ..Default::default() };
+    assert!(!factory.serves(&with_ice(keepalive)));
+}
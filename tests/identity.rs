@@ -0,0 +1,150 @@
+mod support;
+
+use webrtc_client::engine::{CallEngine, EngineCommand, EngineConfig, EngineEvent};
+use webrtc_client::identity::{is_identity_peer_id, Identity};
+use serde_json::Value;
+use std::time::{SystemTime, UNIX_EPOCH};
+use support::{engine, engine_config, wait_for, LoopbackServer};
+use webrtc_client::signaling::{encode_frame, FrameDecoder, SignalingMessage, REPLAY_WINDOW};
+
+fn chat_from(peer: &str) -> SignalingMessage {
+    SignalingMessage::ChatMessage {
+        room_id: "room".to_string(),
+        from_peer: peer.to_string(),
+        text: "hello".to_string(),
+    }
+}
+
+fn decode_frame(text: &str) -> SignalingMessage {
+    FrameDecoder::default().decode(text)
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as u64
+}
+
+#[test]
+fn identity_is_the_same_across_sessions() {
+    let dir = std::env::temp_dir().join(format!("identity-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("identity.key");
+    let _ = std::fs::remove_file(&path);
+
+    let first = Identity::load_or_create_at(&path).unwrap();
+    let second = Identity::load_or_create_at(&path).unwrap();
+    assert_eq!(first.peer_id(), second.peer_id());
+    assert!(is_identity_peer_id(&first.peer_id()));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn signed_messages_verify() {
+    let identity = Identity::generate();
+    let msg = chat_from(&identity.peer_id());
+    let frame = encode_frame(&msg, Some(&identity)).unwrap();
+    assert_eq!(decode_frame(&frame), msg);
+}
+
+#[test]
+fn tampered_or_impersonated_messages_are_dropped() {
+    let identity = Identity::generate();
+    let frame = encode_frame(&chat_from(&identity.peer_id()), Some(&identity)).unwrap();
+    let tampered = frame.replace("hello", "send money");
+    assert!(matches!(decode_frame(&tampered), SignalingMessage::Error { .. }));
+
+    // Signed with a different key than the one the ID belongs to
+    let impostor = Identity::generate();
+    let frame = encode_frame(&chat_from(&identity.peer_id()), Some(&impostor)).unwrap();
+    assert!(matches!(decode_frame(&frame), SignalingMessage::Error { .. }));
+
+    // Unsigned, but claiming a key-derived ID
+    let frame = encode_frame(&chat_from(&identity.peer_id()), None).unwrap();
+    assert!(matches!(decode_frame(&frame), SignalingMessage::Error { .. }));
+}
+
+#[test]
+fn unsigned_messages_from_plain_ids_still_arrive() {
+    let msg = chat_from("user-1234");
+    assert_eq!(decode_frame(&encode_frame(&msg, None).unwrap()), msg);
+}
+
+#[test]
+fn the_signature_covers_the_payload_bytes_as_sent() {
+    let identity = Identity::generate();
+    let frame: Value = serde_json::from_str(&encode_frame(&chat_from(&identity.peer_id()), Some(&identity)).unwrap()).unwrap();
+    let payload: Value = serde_json::from_str(frame["payload"].as_str().unwrap()).unwrap();
+    assert_eq!(payload["text"], "hello");
+    assert!(payload["sent_at"].is_u64());
+    assert!(payload["nonce"].is_string());
+    assert!(frame["public_key"].is_string() && frame["signature"].is_string());
+}
+
+#[test]
+fn a_routing_copy_that_differs_from_the_payload_is_dropped() {
+    let identity = Identity::generate();
+    let frame = encode_frame(&chat_from(&identity.peer_id()), Some(&identity)).unwrap();
+    let mut value: Value = serde_json::from_str(&frame).unwrap();
+    value["text"] = "send money".into();
+    assert!(matches!(decode_frame(&value.to_string()), SignalingMessage::Error { .. }));
+}
+
+#[test]
+fn a_replayed_frame_is_dropped() {
+    let identity = Identity::generate();
+    let msg = chat_from(&identity.peer_id());
+    let frame = encode_frame(&msg, Some(&identity)).unwrap();
+    let mut decoder = FrameDecoder::default();
+    assert_eq!(decoder.decode(&frame), msg);
+    assert!(matches!(decoder.decode(&frame), SignalingMessage::Error { .. }));
+    // The same message sent again is a new frame
+    assert_eq!(decoder.decode(&encode_frame(&msg, Some(&identity)).unwrap()), msg);
+}
+
+#[test]
+fn frames_from_outside_the_replay_window_are_dropped() {
+    let identity = Identity::generate();
+    let frame = encode_frame(&chat_from(&identity.peer_id()), Some(&identity)).unwrap();
+    let later = now_millis() + REPLAY_WINDOW.as_millis() as u64 + 60_000;
+    assert!(matches!(FrameDecoder::default().decode_at(&frame, later), SignalingMessage::Error { .. }));
+}
+
+#[test]
+fn signed_frames_without_a_send_time_are_dropped() {
+    // A frame signed the old way: the signature over the message alone
+    let identity = Identity::generate();
+    let msg = chat_from(&identity.peer_id());
+    let payload = serde_json::to_string(&msg).unwrap();
+    let mut value = serde_json::to_value(&msg).unwrap();
+    value["public_key"] = identity.public_key_hex().into();
+    value["signature"] = identity.sign(payload.as_bytes()).into();
+    value["payload"] = payload.into();
+    assert!(matches!(decode_frame(&value.to_string()), SignalingMessage::Error { .. }));
+}
+
+#[tokio::test]
+async fn an_engine_with_an_identity_signs_what_it_sends() {
+    let server = LoopbackServer::start().await;
+    let identity = Identity::generate();
+    let peer_id = identity.peer_id();
+    let alice = CallEngine::spawn(EngineConfig {
+        identity: Some(identity),
+        ..engine_config(&server, &peer_id, false)
+    });
+    let bob = engine(&server, "bob", false);
+    let mut alice_events = alice.subscribe();
+    let mut bob_events = bob.subscribe();
+
+    alice.send(EngineCommand::Connect).unwrap();
+    bob.send(EngineCommand::Connect).unwrap();
+    wait_for(&mut alice_events, "bob in alice's peer list", |e| {
+        matches!(e, EngineEvent::PeerList(peers) if peers.contains(&"bob".to_string()))
+    })
+    .await;
+
+    // A message claiming an identity peer id only arrives if it's signed by that key
+    alice.send(EngineCommand::SendChat("hello".to_string())).unwrap();
+    wait_for(&mut bob_events, "signed chat", |e| {
+        matches!(e, EngineEvent::Chat(entry) if entry.from_peer == peer_id && entry.text == "hello")
+    })
+    .await;
+}
@@ -0,0 +1,41 @@
+use std::time::{Duration, Instant};
+use webrtc_client::audio::{InputMeter, InputWarning};
+
+#[test]
+fn sustained_silence_warns_after_a_few_seconds() {
+    let meter = InputMeter::default();
+    let start = Instant::now();
+    meter.measure(&[0.0; 960], start);
+    assert_eq!(meter.warning(start + Duration::from_secs(1)), None);
+    meter.measure(&[0.0; 960], start + Duration::from_secs(6));
+    assert_eq!(meter.warning(start + Duration::from_secs(6)), Some(InputWarning::Silent));
+
+    // Speaking clears it
+    meter.measure(&[0.1; 960], start + Duration::from_secs(7));
+    assert_eq!(meter.warning(start + Duration::from_secs(7)), None);
+    assert!(meter.level_db().unwrap() > -30.0);
+}
+
+#[test]
+fn repeated_clipping_warns_but_a_single_over_does_not() {
+    let meter = InputMeter::default();
+    let now = Instant::now();
+    let mut loud = vec![0.2; 960];
+    loud[0] = 1.0;
+    for _ in 0..10 {
+        meter.measure(&loud, now);
+    }
+    assert_eq!(meter.warning(now), None);
+
+    let clipped = vec![1.0; 960];
+    for _ in 0..5 {
+        meter.measure(&clipped, now);
+    }
+    assert_eq!(meter.warning(now), Some(InputWarning::Clipping));
+
+    // Ages out of the window once the gain is lowered
+    for _ in 0..50 {
+        meter.measure(&loud, now);
+    }
+    assert_eq!(meter.warning(now), None);
+}
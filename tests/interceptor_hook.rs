@@ -0,0 +1,96 @@
+mod support;
+
+use async_trait::async_trait;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use support::{engine, engine_with_config, wait_for, LoopbackServer};
+use webrtc::interceptor::stream_info::StreamInfo;
+use webrtc::interceptor::{
+    Attributes, Interceptor, InterceptorBuilder, RTCPReader, RTCPWriter, RTPReader, RTPWriter,
+};
+use webrtc::rtp::packet::Packet;
+use webrtc_client::engine::{EngineCommand, EngineEvent};
+use webrtc_client::webrtc::WebRTCConfig;
+
+// Counts outgoing RTP packets, standing in for an embedder's packet logger
+struct CountingBuilder(Arc<AtomicUsize>);
+
+impl InterceptorBuilder for CountingBuilder {
+    fn build(&self, _id: &str) -> webrtc::interceptor::Result<Arc<dyn Interceptor + Send + Sync>> {
+        Ok(Arc::new(CountingInterceptor(self.0.clone())))
+    }
+}
+
+struct CountingInterceptor(Arc<AtomicUsize>);
+
+#[async_trait]
+impl Interceptor for CountingInterceptor {
+    async fn bind_rtcp_reader(&self, reader: Arc<dyn RTCPReader + Send + Sync>) -> Arc<dyn RTCPReader + Send + Sync> {
+        reader
+    }
+
+    async fn bind_rtcp_writer(&self, writer: Arc<dyn RTCPWriter + Send + Sync>) -> Arc<dyn RTCPWriter + Send + Sync> {
+        writer
+    }
+
+    async fn bind_local_stream(
+        &self,
+        _info: &StreamInfo,
+        writer: Arc<dyn RTPWriter + Send + Sync>,
+    ) -> Arc<dyn RTPWriter + Send + Sync> {
+        Arc::new(CountingWriter { count: self.0.clone(), next: writer })
+    }
+
+    async fn unbind_local_stream(&self, _info: &StreamInfo) {}
+
+    async fn bind_remote_stream(
+        &self,
+        _info: &StreamInfo,
+        reader: Arc<dyn RTPReader + Send + Sync>,
+    ) -> Arc<dyn RTPReader + Send + Sync> {
+        reader
+    }
+
+    async fn unbind_remote_stream(&self, _info: &StreamInfo) {}
+
+    async fn close(&self) -> webrtc::interceptor::Result<()> {
+        Ok(())
+    }
+}
+
+struct CountingWriter {
+    count: Arc<AtomicUsize>,
+    next: Arc<dyn RTPWriter + Send + Sync>,
+}
+
+#[async_trait]
+impl RTPWriter for CountingWriter {
+    async fn write(&self, pkt: &Packet, attributes: &Attributes) -> webrtc::interceptor::Result<usize> {
+        self.count.fetch_add(1, Ordering::SeqCst);
+        self.next.write(pkt, attributes).await
+    }
+}
+
+#[tokio::test]
+async fn custom_interceptor_sees_outgoing_rtp() {
+    let sent = Arc::new(AtomicUsize::new(0));
+    let config = WebRTCConfig::default().with_interceptor(CountingBuilder(sent.clone()));
+
+    let server = LoopbackServer::start().await;
+    let alice = engine_with_config(&server, "alice", false, config);
+    let bob = engine(&server, "bob", true);
+    let mut alice_events = alice.subscribe();
+    let mut bob_events = bob.subscribe();
+
+    alice.send(EngineCommand::Connect).unwrap();
+    bob.send(EngineCommand::Connect).unwrap();
+    wait_for(&mut alice_events, "bob in alice's peer list", |e| {
+        matches!(e, EngineEvent::PeerList(peers) if peers.contains(&"bob".to_string()))
+    })
+    .await;
+
+    alice.send(EngineCommand::Call(vec!["bob".to_string()])).unwrap();
+    wait_for(&mut bob_events, "audio at bob", |e| matches!(e, EngineEvent::RemoteAudioStarted)).await;
+
+    assert!(sent.load(Ordering::SeqCst) > 0, "custom interceptor never saw a packet");
+}
@@ -0,0 +1,63 @@
+use serde_json::json;
+use std::net::SocketAddr;
+use webrtc_client::lan::{self, FoundPeer, LanRoom};
+use webrtc_client::signaling::SignalingMessage;
+
+fn peer(room_id: &str, port: u16) -> FoundPeer {
+    FoundPeer {
+        room_id: room_id.to_string(),
+        addrs: vec![SocketAddr::from(([192, 168, 1, 20], port))],
+    }
+}
+
+fn peer_list(room: &LanRoom) -> Vec<String> {
+    match serde_json::from_str(&room.peer_list().unwrap()).unwrap() {
+        SignalingMessage::PeerList { peers } => peers,
+        other => panic!("not a peer list: {:?}", other),
+    }
+}
+
+#[test]
+fn lan_urls_select_lan_mode() {
+    assert!(lan::is_lan("lan://"));
+    assert!(lan::is_lan("LAN://office"));
+    assert!(!lan::is_lan("wss://lan.example.com"));
+}
+
+#[test]
+fn the_peer_list_has_everyone_found_in_our_room() {
+    let mut room = LanRoom::default();
+    assert!(room.peer_list().is_none());
+    room.join("alice", "standup");
+
+    assert!(room.found("bob", peer("standup", 4000)));
+    assert!(!room.found("carol", peer("lunch", 4001)));
+    // Our own announcement comes back to us too
+    assert!(!room.found("alice", peer("standup", 4002)));
+    assert_eq!(peer_list(&room), ["alice", "bob"]);
+
+    // Moving out of our room changes our list, moving between others' doesn't
+    assert!(room.found("bob", peer("lunch", 4000)));
+    assert_eq!(peer_list(&room), ["alice"]);
+    assert!(room.found("carol", peer("standup", 4001)));
+    assert!(room.lost("carol"));
+    assert!(!room.lost("bob"));
+    assert_eq!(peer_list(&room), ["alice"]);
+}
+
+#[test]
+fn frames_go_where_a_server_would_send_them() {
+    let mut room = LanRoom::default();
+    room.join("alice", "standup");
+    room.found("bob", peer("standup", 4000));
+    room.found("carol", peer("standup", 4001));
+    room.found("dave", peer("lunch", 4002));
+
+    assert_eq!(room.targets(&json!({"message_type": "Offer", "to_peer": "bob"})), ["bob"]);
+    assert_eq!(room.targets(&json!({"message_type": "CallRequest", "to_peers": ["carol"]})), ["carol"]);
+    let mut everyone = room.targets(&json!({"message_type": "ChatMessage"}));
+    everyone.sort();
+    assert_eq!(everyone, ["bob", "carol"]);
+    assert_eq!(room.addrs("bob"), [SocketAddr::from(([192, 168, 1, 20], 4000))]);
+    assert!(room.addrs("erin").is_empty());
+}
@@ -0,0 +1,49 @@
+use std::time::{Duration, Instant};
+use webrtc_client::audio::AudioLatency;
+use webrtc_client::latency::{LatencyBreakdown, LatencyProbe};
+
+fn ms(value: u64) -> Duration {
+    Duration::from_millis(value)
+}
+
+#[test]
+fn mouth_to_ear_sums_every_measured_stage() {
+    let probe = LatencyProbe::default();
+    probe.record_encode(Instant::now(), ms(20));
+    let audio = AudioLatency {
+        capture: Some(ms(10)),
+        playback: Some(ms(10)),
+        buffered: Some(ms(40)),
+    };
+
+    let estimate = LatencyBreakdown::estimate(&probe, audio, 100.0);
+    assert_eq!(estimate.network, Some(ms(50)));
+    assert_eq!(estimate.packetization, Some(ms(20)));
+    assert_eq!(estimate.decode, None);
+
+    let total = estimate.mouth_to_ear().unwrap();
+    assert!(total >= ms(130), "{:?}", total);
+    assert!(total < ms(140), "{:?}", total);
+}
+
+#[test]
+fn no_estimate_before_the_round_trip_is_known() {
+    let audio = AudioLatency {
+        capture: Some(ms(10)),
+        ..Default::default()
+    };
+    let estimate = LatencyBreakdown::estimate(&LatencyProbe::default(), audio, 0.0);
+    assert_eq!(estimate.network, None);
+    assert_eq!(estimate.mouth_to_ear(), None);
+}
+
+#[test]
+fn probe_smooths_out_single_spikes() {
+    let probe = LatencyProbe::default();
+    for _ in 0..20 {
+        probe.record_encode(Instant::now(), ms(20));
+    }
+    probe.record_encode(Instant::now(), ms(180));
+    let packetization = probe.packetization().unwrap();
+    assert!(packetization >= ms(29) && packetization <= ms(31), "{:?}", packetization);
+}
@@ -0,0 +1,50 @@
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use webrtc_client::audio::{LoudnessNormalizer, TARGET_LOUDNESS_DBFS};
+
+// 20 ms at 48 kHz
+fn sine_frame(amplitude: f32) -> Vec<f32> {
+    (0..960).map(|i| amplitude * (i as f32 * 440.0 * std::f32::consts::TAU / 48_000.0).sin()).collect()
+}
+
+fn rms_dbfs(samples: &[f32]) -> f32 {
+    let power = samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32;
+    10.0 * power.log10()
+}
+
+// Output level after a few seconds of steady speech
+fn settled_level(normalizer: &mut LoudnessNormalizer, amplitude: f32) -> f32 {
+    let mut frame = Vec::new();
+    for _ in 0..250 {
+        frame = sine_frame(amplitude);
+        normalizer.process(&mut frame);
+    }
+    rms_dbfs(&frame)
+}
+
+#[test]
+fn loud_and_quiet_peers_end_up_alike() {
+    let enabled = Arc::new(AtomicBool::new(true));
+    let loud = settled_level(&mut LoudnessNormalizer::new(enabled.clone()), 0.3);
+    let quiet = settled_level(&mut LoudnessNormalizer::new(enabled), 0.03);
+    assert!((loud - TARGET_LOUDNESS_DBFS).abs() < 1.0, "loud peer at {} dBFS", loud);
+    assert!((quiet - TARGET_LOUDNESS_DBFS).abs() < 1.0, "quiet peer at {} dBFS", quiet);
+}
+
+#[test]
+fn disabled_normalizer_still_measures_but_leaves_audio_alone() {
+    let mut normalizer = LoudnessNormalizer::new(Arc::new(AtomicBool::new(false)));
+    let level = settled_level(&mut normalizer, 0.03);
+    assert!((level - rms_dbfs(&sine_frame(0.03))).abs() < 1e-3);
+    assert!(normalizer.loudness_dbfs().is_some());
+    assert_eq!(normalizer.gain_db(), 0.0);
+}
+
+#[test]
+fn silence_is_not_boosted() {
+    let mut normalizer = LoudnessNormalizer::new(Arc::new(AtomicBool::new(true)));
+    let mut frame = sine_frame(0.0005);
+    normalizer.process(&mut frame);
+    assert_eq!(normalizer.loudness_dbfs(), None);
+    assert_eq!(frame, sine_frame(0.0005));
+}
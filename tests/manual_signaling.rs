@@ -0,0 +1,53 @@
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::{sleep, Instant};
+use webrtc::peer_connection::sdp::sdp_type::RTCSdpType;
+use webrtc_client::audio::MockBackend;
+use webrtc_client::manual::{parse_candidates, parse_description, ManualCall};
+use webrtc_client::webrtc::WebRTCConfig;
+
+#[tokio::test]
+async fn pasting_the_offer_and_answer_connects_the_call() {
+    let mut config = WebRTCConfig::default();
+    config.ice_servers.clear();
+    let alice_audio = Arc::new(MockBackend::default());
+    let bob_audio = Arc::new(MockBackend::default());
+
+    let (alice, offer) = ManualCall::offer(&config, alice_audio.clone()).await.unwrap();
+    assert!(offer.contains("a=candidate:"), "the offer should carry its candidates");
+    let (bob, answer) = ManualCall::answer(&config, bob_audio.clone(), &offer).await.unwrap();
+    alice.accept_answer(&answer).await.unwrap();
+
+    let deadline = Instant::now() + Duration::from_secs(15);
+    while alice_audio.frames_played() == 0 || bob_audio.frames_played() == 0 {
+        assert!(Instant::now() < deadline, "no audio made it across");
+        sleep(Duration::from_millis(100)).await;
+    }
+
+    alice.hang_up().await;
+    bob.hang_up().await;
+}
+
+#[test]
+fn bare_sdp_is_accepted_and_the_wrong_kind_is_not() {
+    let offer = "v=0\no=- 1 1 IN IP4 0.0.0.0\ns=-\nt=0 0\n";
+    let json = parse_description(offer, RTCSdpType::Offer).unwrap();
+    assert!(json.contains(r#""type":"offer""#));
+    assert!(json.contains("s=-\\r\\nt=0 0\\r\\n"));
+
+    assert!(parse_description(&json, RTCSdpType::Answer).is_err());
+    assert!(parse_description("hello", RTCSdpType::Offer).is_err());
+}
+
+#[test]
+fn candidates_are_picked_out_of_pasted_lines() {
+    let text = "a=candidate:1 1 udp 2130706431 192.168.1.2 50000 typ host\n\
+                m=audio 9 UDP/TLS/RTP/SAVPF 111\n  candidate:2 1 udp 1694498815 203.0.113.5 50001 typ srflx\n";
+    assert_eq!(
+        parse_candidates(text),
+        [
+            "candidate:1 1 udp 2130706431 192.168.1.2 50000 typ host",
+            "candidate:2 1 udp 1694498815 203.0.113.5 50001 typ srflx",
+        ]
+    );
+}
@@ -0,0 +1,62 @@
+mod support;
+
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use std::time::Duration;
+use support::{engine, wait_for, LoopbackServer};
+use tokio::time::timeout;
+use tokio_tungstenite::connect_async;
+use tokio_tungstenite::tungstenite::Message;
+use webrtc_client::engine::{EngineCommand, EngineEvent};
+use webrtc_client::room::MediaRelays;
+
+#[test]
+fn media_goes_to_the_relay_only_in_its_room() {
+    let mut relays = MediaRelays::default();
+    relays.assign("music", Some("sfu-1".to_string()));
+    assert_eq!(relays.media_peer("music", "bob"), "sfu-1");
+    assert_eq!(relays.media_peer("lobby", "bob"), "bob");
+    assert!(relays.is_relay("music", "sfu-1"));
+
+    relays.assign("music", None);
+    assert_eq!(relays.media_peer("music", "bob"), "bob");
+}
+
+#[tokio::test]
+async fn both_sides_of_a_call_offer_to_the_designated_relay() {
+    let server = LoopbackServer::start_with_relay("sfu").await;
+    // Stands in for the SFU: only joins and notes who offers to it
+    let (mut sfu, _) = connect_async(&server.url).await.unwrap();
+    let join = json!({ "message_type": "Join", "room_id": "test-room", "peer_id": "sfu", "protocol_version": 1 });
+    sfu.send(Message::Text(join.to_string())).await.unwrap();
+
+    let alice = engine(&server, "alice", false);
+    let bob = engine(&server, "bob", true);
+    let mut alice_events = alice.subscribe();
+    let mut bob_events = bob.subscribe();
+    alice.send(EngineCommand::Connect).unwrap();
+    bob.send(EngineCommand::Connect).unwrap();
+    wait_for(&mut alice_events, "bob in alice's peer list", |e| {
+        matches!(e, EngineEvent::PeerList(peers) if peers.contains(&"bob".to_string()))
+    })
+    .await;
+
+    alice.send(EngineCommand::Call(vec!["bob".to_string()])).unwrap();
+    wait_for(&mut bob_events, "bob in the call", |e| matches!(e, EngineEvent::CallStarted(_))).await;
+
+    let mut offers_from = Vec::new();
+    timeout(Duration::from_secs(15), async {
+        while offers_from.len() < 2 {
+            let Some(Ok(Message::Text(text))) = sfu.next().await else { continue };
+            let message: Value = serde_json::from_str(&text).unwrap();
+            if message["message_type"] == "Offer" {
+                assert_eq!(message["to_peer"], "sfu");
+                offers_from.push(message["from_peer"].as_str().unwrap().to_string());
+            }
+        }
+    })
+    .await
+    .expect("timed out waiting for offers at the relay");
+    offers_from.sort();
+    assert_eq!(offers_from, vec!["alice", "bob"]);
+}
@@ -0,0 +1,60 @@
+use std::time::{Duration, SystemTime};
+use webrtc_client::audio::tap::TapSource;
+use webrtc_client::recording::Mixdown;
+
+fn bob() -> TapSource {
+    TapSource::Remote("bob".to_string())
+}
+
+#[test]
+fn overlapping_speakers_are_summed() {
+    let start = SystemTime::now();
+    let mut mixdown = Mixdown::new(48_000);
+    mixdown.add(&bob(), &[0.25; 960], 48_000, start);
+    mixdown.add(&TapSource::Local, &[0.5; 960], 48_000, start);
+
+    let (at, mixed) = mixdown.take_all().unwrap();
+    assert_eq!(at, start);
+    assert_eq!(mixed, vec![0.75; 960]);
+}
+
+#[test]
+fn jittery_frames_stay_back_to_back() {
+    let start = SystemTime::now();
+    let mut mixdown = Mixdown::new(48_000);
+    mixdown.add(&bob(), &[0.1; 960], 48_000, start);
+    // 15 ms late, still the next 20 ms of bob
+    mixdown.add(&bob(), &[0.2; 960], 48_000, start + Duration::from_millis(35));
+
+    let (_, mixed) = mixdown.take_all().unwrap();
+    assert_eq!(mixed.len(), 1920);
+    assert_eq!(mixed[959], 0.1);
+    assert_eq!(mixed[960], 0.2);
+}
+
+#[test]
+fn gaps_are_filled_with_silence_and_taken_in_order() {
+    let start = SystemTime::now();
+    let mut mixdown = Mixdown::new(48_000);
+    mixdown.add(&bob(), &[0.1; 960], 48_000, start);
+    mixdown.add(&bob(), &[0.1; 960], 48_000, start + Duration::from_secs(1));
+
+    let (first_at, first) = mixdown.take_until(start + Duration::from_millis(500)).unwrap();
+    assert_eq!(first_at, start);
+    assert_eq!(first.len(), 24_000);
+    assert!(first[960..].iter().all(|sample| *sample == 0.0));
+
+    let (rest_at, rest) = mixdown.take_all().unwrap();
+    assert_eq!(rest_at, start + Duration::from_millis(500));
+    assert_eq!(rest.len(), 24_000 + 960);
+    assert!(mixdown.take_all().is_none());
+}
+
+#[test]
+fn other_rates_are_resampled_onto_the_mix() {
+    let start = SystemTime::now();
+    let mut mixdown = Mixdown::new(48_000);
+    mixdown.add(&TapSource::Local, &[0.1; 320], 16_000, start);
+    let (_, mixed) = mixdown.take_all().unwrap();
+    assert_eq!(mixed.len(), 960);
+}
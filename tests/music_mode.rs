@@ -0,0 +1,54 @@
+use std::sync::Arc;
+use webrtc_client::audio::MockBackend;
+use webrtc_client::webrtc::{WebRTCClient, WebRTCConfig};
+
+async fn offer_sdp(config: WebRTCConfig) -> String {
+    let client = WebRTCClient::with_config(&config, Arc::new(MockBackend::default())).await.unwrap();
+    let offer: serde_json::Value = serde_json::from_str(&client.create_offer().await.unwrap()).unwrap();
+    offer["sdp"].as_str().unwrap().to_string()
+}
+
+fn opus_fmtp(sdp: &str) -> String {
+    sdp.lines()
+        .find(|line| line.starts_with("a=fmtp:111 "))
+        .unwrap_or_else(|| panic!("no Opus fmtp in {}", sdp))
+        .to_string()
+}
+
+#[tokio::test]
+async fn music_mode_asks_for_fullband_stereo() {
+    let sdp = offer_sdp(WebRTCConfig { music_mode: true, ..Default::default() }).await;
+    let fmtp = opus_fmtp(&sdp);
+    for parameter in ["stereo=1", "maxplaybackrate=48000"] {
+        assert!(fmtp.contains(parameter), "{} missing from {}", parameter, fmtp);
+    }
+    // Voice settings are kept alongside
+    assert!(fmtp.contains("useinbandfec=1"), "{}", fmtp);
+}
+
+#[tokio::test]
+async fn music_mode_promises_nothing_about_what_we_send() {
+    let sdp = offer_sdp(WebRTCConfig { music_mode: true, ..Default::default() }).await;
+    let fmtp = opus_fmtp(&sdp);
+    for parameter in ["sprop-stereo", "maxaveragebitrate", "usedtx"] {
+        assert!(!fmtp.contains(parameter), "{} in {}", parameter, fmtp);
+    }
+}
+
+#[tokio::test]
+async fn a_configured_bitrate_limit_still_applies() {
+    let sdp = offer_sdp(WebRTCConfig {
+        music_mode: true,
+        max_bitrate_kbps: Some(64),
+        ..Default::default()
+    })
+    .await;
+    let fmtp = opus_fmtp(&sdp);
+    assert!(fmtp.contains("maxaveragebitrate=64000"), "{}", fmtp);
+}
+
+#[tokio::test]
+async fn voice_calls_stay_mono() {
+    let sdp = offer_sdp(WebRTCConfig::default()).await;
+    assert!(!opus_fmtp(&sdp).contains("stereo=1"), "{}", sdp);
+}
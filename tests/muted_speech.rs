@@ -0,0 +1,45 @@
+use std::time::{Duration, Instant};
+use webrtc_client::audio::{InputMeter, MutedSpeechBanner, MUTED_SPEECH_AFTER};
+use webrtc_client::config::Settings;
+
+#[test]
+fn short_pauses_between_words_keep_the_speech_going() {
+    let meter = InputMeter::default();
+    let start = Instant::now();
+    meter.measure(&[0.1; 960], start);
+    meter.measure(&[0.0; 960], start + Duration::from_millis(100));
+    meter.measure(&[0.1; 960], start + Duration::from_millis(200));
+    assert_eq!(meter.speaking_for(start + Duration::from_millis(200)), Duration::from_millis(200));
+
+    // A real pause ends it
+    meter.measure(&[0.0; 960], start + Duration::from_millis(800));
+    assert_eq!(meter.speaking_for(start + Duration::from_millis(800)), Duration::ZERO);
+}
+
+#[test]
+fn the_banner_comes_up_a_second_into_muted_speech() {
+    let mut banner = MutedSpeechBanner::default();
+    let now = Instant::now();
+    assert!(!banner.check(false, Duration::from_secs(5), now));
+    assert!(!banner.check(true, MUTED_SPEECH_AFTER / 2, now));
+    assert!(banner.check(true, MUTED_SPEECH_AFTER, now));
+
+    // Stays a little after they stop talking, then goes
+    assert!(banner.check(true, Duration::ZERO, now + Duration::from_secs(1)));
+    assert!(!banner.check(true, Duration::ZERO, now + Duration::from_secs(10)));
+}
+
+#[test]
+fn unmuting_takes_the_banner_down() {
+    let mut banner = MutedSpeechBanner::default();
+    let now = Instant::now();
+    assert!(banner.check(true, MUTED_SPEECH_AFTER * 2, now));
+    assert!(!banner.check(false, MUTED_SPEECH_AFTER * 2, now));
+}
+
+#[test]
+fn calls_start_unmuted_with_the_reminder_on_by_default() {
+    let settings = Settings::default();
+    assert!(!settings.join_muted);
+    assert!(settings.muted_speech_reminder);
+}
@@ -0,0 +1,70 @@
+use webrtc_client::audio::AudioBackendKind;
+use webrtc_client::negotiation::{Direction, NegotiationKind, NegotiationLog};
+use webrtc_client::webrtc::{WebRTCClient, WebRTCConfig};
+
+#[test]
+fn connections_of_one_call_share_the_log() {
+    let log = NegotiationLog::new();
+    log.for_peer("bob").record(Direction::Sent, NegotiationKind::Offer, "v=0\r\n");
+    log.for_peer("carol").record_from(Direction::Received, NegotiationKind::Candidate, Some("control"), "candidate:1");
+    log.record_from(Direction::Received, NegotiationKind::Candidate, Some("dave"), "candidate:2");
+
+    let entries = log.entries();
+    assert_eq!(entries.len(), 3);
+    assert_eq!(entries[0].peer.as_deref(), Some("bob"));
+    assert_eq!(entries[1].peer.as_deref(), Some("carol"));
+    assert_eq!(entries[2].peer.as_deref(), Some("dave"));
+    assert!(entries.windows(2).all(|pair| pair[0].elapsed <= pair[1].elapsed));
+
+    let export = log.export();
+    assert!(export.contains("sent offer to bob\nv=0\n"), "{}", export);
+    assert!(export.contains("received candidate from carol\ncandidate:1\n"), "{}", export);
+}
+
+#[test]
+fn the_export_is_saved_to_its_own_file() {
+    let dir = std::env::temp_dir().join(format!("negotiation-log-{}", std::process::id()));
+    let log = NegotiationLog::new();
+    log.record(Direction::Received, NegotiationKind::Answer, "v=0\r\n");
+
+    let path = log.save(&dir).unwrap();
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), log.export());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn offer_answer_and_candidates_are_recorded_by_the_client() {
+    let log = NegotiationLog::new();
+    let client = |log: NegotiationLog| {
+        WebRTCClient::builder()
+            .config(&WebRTCConfig::default())
+            .audio_backend(AudioBackendKind::Mock.create())
+            .negotiation_log(log)
+            .build()
+    };
+    let alice = client(log.for_peer("bob")).await.unwrap();
+    let bob = client(NegotiationLog::new()).await.unwrap();
+
+    // Before the offer: queued, but recorded as it arrives
+    bob.add_ice_candidate("alice", "candidate:1 1 udp 2130706431 192.0.2.1 50000 typ host".to_string()).await.unwrap();
+    let offer = alice.create_offer().await.unwrap();
+    let answer = bob.handle_offer(offer).await.unwrap();
+    alice.handle_answer(answer).await.unwrap();
+
+    let sent: Vec<(Direction, NegotiationKind)> = log.entries().iter().map(|entry| (entry.direction, entry.kind)).collect();
+    assert_eq!(sent, [(Direction::Sent, NegotiationKind::Offer), (Direction::Received, NegotiationKind::Answer)]);
+
+    let received: Vec<(Direction, NegotiationKind, Option<String>)> =
+        bob.negotiation_log().entries().into_iter().map(|entry| (entry.direction, entry.kind, entry.peer)).collect();
+    assert_eq!(
+        received,
+        [
+            (Direction::Received, NegotiationKind::Candidate, Some("alice".to_string())),
+            (Direction::Received, NegotiationKind::Offer, None),
+            (Direction::Sent, NegotiationKind::Answer, None),
+        ]
+    );
+
+    alice.close().await;
+    bob.close().await;
+}
@@ -0,0 +1,64 @@
+mod support;
+
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use std::time::Duration;
+use support::{engine_with_config, wait_for, LoopbackServer};
+use tokio::time::timeout;
+use tokio_tungstenite::connect_async;
+use tokio_tungstenite::tungstenite::Message;
+use webrtc_client::engine::{EngineCommand, EngineEvent};
+use webrtc_client::webrtc::WebRTCConfig;
+
+#[tokio::test]
+async fn unanswered_offer_is_retried_then_fails_the_call() {
+    let server = LoopbackServer::start().await;
+    // Accepts the call and then drops every offer on the floor
+    let (mut bob, _) = connect_async(&server.url).await.unwrap();
+    let join = json!({ "message_type": "Join", "room_id": "test-room", "peer_id": "bob", "protocol_version": 1 });
+    bob.send(Message::Text(join.to_string())).await.unwrap();
+
+    let config = WebRTCConfig {
+        answer_timeout_ms: 300,
+        offer_attempts: 2,
+        ..Default::default()
+    };
+    let alice = engine_with_config(&server, "alice", false, config);
+    let mut alice_events = alice.subscribe();
+    alice.send(EngineCommand::Connect).unwrap();
+    wait_for(&mut alice_events, "bob in alice's peer list", |e| {
+        matches!(e, EngineEvent::PeerList(peers) if peers.contains(&"bob".to_string()))
+    })
+    .await;
+    alice.send(EngineCommand::Call(vec!["bob".to_string()])).unwrap();
+
+    let mut offers = 0;
+    timeout(Duration::from_secs(10), async {
+        while offers < 2 {
+            let Some(Ok(Message::Text(text))) = bob.next().await else { continue };
+            let message: Value = serde_json::from_str(&text).unwrap();
+            match message["message_type"].as_str() {
+                Some("CallRequest") => {
+                    let response = json!({
+                        "message_type": "CallResponse",
+                        "room_id": "test-room",
+                        "from_peer": "bob",
+                        "to_peer": "alice",
+                        "accepted": true,
+                    });
+                    bob.send(Message::Text(response.to_string())).await.unwrap();
+                }
+                Some("Offer") => offers += 1,
+                _ => {}
+            }
+        }
+    })
+    .await
+    .expect("timed out waiting for the offer to be sent again");
+
+    wait_for(&mut alice_events, "no-answer error", |e| {
+        matches!(e, EngineEvent::Error(message) if message.contains("bob did not respond"))
+    })
+    .await;
+    wait_for(&mut alice_events, "call ended", |e| matches!(e, EngineEvent::CallEnded)).await;
+}
@@ -0,0 +1,217 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use webrtc::stats::{InboundRTPStats, OutboundRTPStats, RTCStatsType, RemoteInboundRTPStats, RemoteOutboundRTPStats, StatsReport, StatsReportType};
+use webrtc_client::one_way::{OneWayAudio, OneWayDetector, OneWayVerdict, PacketCounters, ONE_WAY_AFTER};
+
+// One stats reading a second for a single peer; each flag says whether that counter moves
+struct Feed {
+    detector: OneWayDetector,
+    start: Instant,
+    second: u64,
+    counters: PacketCounters,
+}
+
+impl Feed {
+    fn new() -> Self {
+        Self { detector: OneWayDetector::default(), start: Instant::now(), second: 0, counters: PacketCounters::default() }
+    }
+
+    fn tick(&mut self, we_send: bool, we_receive: bool, they_receive: bool, they_send: bool) -> Option<OneWayVerdict> {
+        let grow = |counter: &mut u64, on: bool| *counter += if on { 50 } else { 0 };
+        grow(&mut self.counters.sent, we_send);
+        grow(&mut self.counters.received, we_receive);
+        grow(self.counters.peer_received.get_or_insert(0), they_receive);
+        grow(self.counters.peer_sent.get_or_insert(0), they_send);
+        self.second += 1;
+        self.detector.observe("bob", self.counters, self.start + Duration::from_secs(self.second))
+    }
+
+    fn run(&mut self, seconds: u64, we_send: bool, we_receive: bool, they_receive: bool, they_send: bool) -> Vec<OneWayVerdict> {
+        (0..seconds).filter_map(|_| self.tick(we_send, we_receive, they_receive, they_send)).collect()
+    }
+}
+
+fn one_way_ticks() -> u64 {
+    ONE_WAY_AFTER.as_secs() + 2
+}
+
+#[test]
+fn audio_both_ways_raises_nothing() {
+    let mut feed = Feed::new();
+    assert!(feed.run(30, true, true, true, true).is_empty());
+}
+
+#[test]
+fn receiver_reports_showing_nothing_mean_the_peer_cannot_hear_us() {
+    let mut feed = Feed::new();
+    feed.run(10, true, true, true, true);
+    assert_eq!(feed.run(one_way_ticks(), true, true, false, true), vec![OneWayVerdict::Detected(OneWayAudio::PeerCannotHearUs)]);
+    assert_eq!(feed.run(3, true, true, true, true), vec![OneWayVerdict::Cleared]);
+}
+
+#[test]
+fn a_peer_sending_into_the_void_is_one_we_cannot_hear() {
+    let mut feed = Feed::new();
+    feed.run(10, true, true, true, true);
+    assert_eq!(feed.run(one_way_ticks(), true, false, true, true), vec![OneWayVerdict::Detected(OneWayAudio::WeCannotHearPeer)]);
+}
+
+#[test]
+fn a_dead_connection_is_not_one_way() {
+    let mut feed = Feed::new();
+    feed.run(10, true, true, true, true);
+    // Nothing either way is the watchdog's business
+    assert!(feed.run(one_way_ticks(), true, false, false, false).is_empty());
+}
+
+#[test]
+fn reports_every_few_seconds_are_not_a_stall() {
+    let mut feed = Feed::new();
+    let verdicts: Vec<OneWayVerdict> = (0..30).filter_map(|second| feed.tick(true, true, second % 3 == 0, second % 3 == 0)).collect();
+    assert!(verdicts.is_empty());
+}
+
+#[test]
+fn a_new_connection_is_not_judged_before_its_first_reports() {
+    let mut feed = Feed::new();
+    assert!(feed.run(ONE_WAY_AFTER.as_secs() - 1, true, true, false, false).is_empty());
+}
+
+#[test]
+fn directions_have_translations() {
+    assert_eq!(OneWayAudio::PeerCannotHearUs.message_key(), "one_way.peer_cannot_hear_you");
+    assert!(OneWayAudio::WeCannotHearPeer.hint_keys().contains(&"one_way.hint_incoming_firewall"));
+    assert_eq!(serde_json::to_value(OneWayAudio::WeCannotHearPeer).unwrap(), "we_cannot_hear_peer");
+}
+
+// What a connection's stats say after `second` seconds: we send and they hear us
+// throughout, and each remote stream's sender says it is sending; `arriving` tells which
+// of those streams actually reach us
+fn connection_stats(second: u64, streams: &[(u32, bool)]) -> StatsReport {
+    let timestamp = tokio::time::Instant::now();
+    let packets = second * 50;
+    let mut reports = HashMap::new();
+    reports.insert(
+        "outbound".to_string(),
+        StatsReportType::OutboundRTP(OutboundRTPStats {
+            timestamp,
+            stats_type: RTCStatsType::OutboundRTP,
+            id: "outbound".to_string(),
+            ssrc: 1,
+            kind: "audio".to_string(),
+            packets_sent: packets,
+            bytes_sent: packets * 160,
+            track_identifier: String::new(),
+            mid: Default::default(),
+            rid: None,
+            header_bytes_sent: 0,
+            nack_count: 0,
+            fir_count: None,
+            pli_count: None,
+        }),
+    );
+    reports.insert(
+        "remote-inbound".to_string(),
+        StatsReportType::RemoteInboundRTP(RemoteInboundRTPStats {
+            timestamp,
+            stats_type: RTCStatsType::RemoteInboundRTP,
+            id: "remote-inbound".to_string(),
+            ssrc: 1,
+            kind: "audio".to_string(),
+            packets_received: packets,
+            packets_lost: 0,
+            local_id: "outbound".to_string(),
+            round_trip_time: None,
+            total_round_trip_time: 0.0,
+            fraction_lost: 0.0,
+            round_trip_time_measurements: 0,
+        }),
+    );
+    for &(ssrc, arriving) in streams {
+        // Stops growing after the first ten seconds when the stream doesn't arrive
+        let received = if arriving { packets } else { packets.min(500) };
+        reports.insert(
+            format!("inbound-{}", ssrc),
+            StatsReportType::InboundRTP(InboundRTPStats {
+                timestamp,
+                stats_type: RTCStatsType::InboundRTP,
+                id: format!("inbound-{}", ssrc),
+                ssrc,
+                kind: "audio".to_string(),
+                packets_received: received,
+                track_identifier: String::new(),
+                mid: Default::default(),
+                last_packet_received_timestamp: None,
+                header_bytes_received: 0,
+                bytes_received: received * 160,
+                nack_count: 0,
+                fir_count: None,
+                pli_count: None,
+            }),
+        );
+        reports.insert(
+            format!("remote-outbound-{}", ssrc),
+            StatsReportType::RemoteOutboundRTP(RemoteOutboundRTPStats {
+                timestamp,
+                stats_type: RTCStatsType::RemoteOutboundRTP,
+                id: format!("remote-outbound-{}", ssrc),
+                ssrc,
+                kind: "audio".to_string(),
+                packets_sent: packets,
+                bytes_sent: packets * 160,
+                local_id: format!("inbound-{}", ssrc),
+                round_trip_time: None,
+                reports_sent: second,
+                total_round_trip_time: 0.0,
+                round_trip_time_measurements: 0,
+            }),
+        );
+    }
+    StatsReport { reports }
+}
+
+#[test]
+fn one_silent_stream_on_a_shared_connection_is_not_hidden_by_the_other() {
+    // Bob's and carol's audio both come through the relay; carol's stops after ten seconds
+    let mut detector = OneWayDetector::default();
+    let start = Instant::now();
+    let mut verdicts = Vec::new();
+    for second in 1..=10 + one_way_ticks() {
+        let report = connection_stats(second, &[(100, true), (200, second <= 10)]);
+        let now = start + Duration::from_secs(second);
+        for (peer, ssrc) in [("bob", 100), ("carol", 200)] {
+            if let Some(verdict) = detector.observe(peer, PacketCounters::for_streams(&report, &[ssrc]), now) {
+                verdicts.push((peer, verdict));
+            }
+        }
+    }
+    assert_eq!(verdicts, vec![("carol", OneWayVerdict::Detected(OneWayAudio::WeCannotHearPeer))]);
+    // Counted together, bob's packets would keep the connection looking alive
+    let mut together = OneWayDetector::default();
+    let verdicts: Vec<OneWayVerdict> = (1..=10 + one_way_ticks())
+        .filter_map(|second| {
+            let report = connection_stats(second, &[(100, true), (200, second <= 10)]);
+            together.observe("relay", PacketCounters::from_stats(&report), start + Duration::from_secs(second))
+        })
+        .collect();
+    assert!(verdicts.is_empty());
+}
+
+#[test]
+fn each_leg_of_a_mesh_call_is_judged_on_its_own() {
+    // A connection each; only carol's goes silent
+    let mut detector = OneWayDetector::default();
+    let start = Instant::now();
+    let mut verdicts = Vec::new();
+    for second in 1..=10 + one_way_ticks() {
+        let now = start + Duration::from_secs(second);
+        let bob = connection_stats(second, &[(100, true)]);
+        let carol = connection_stats(second, &[(200, second <= 10)]);
+        for (peer, report) in [("bob", &bob), ("carol", &carol)] {
+            if let Some(verdict) = detector.observe(peer, PacketCounters::from_stats(report), now) {
+                verdicts.push((peer, verdict));
+            }
+        }
+    }
+    assert_eq!(verdicts, vec![("carol", OneWayVerdict::Detected(OneWayAudio::WeCannotHearPeer))]);
+}
@@ -0,0 +1,45 @@
+use std::sync::Arc;
+use std::time::Duration;
+use webrtc_client::audio::tones::{self, play_alert};
+use webrtc_client::audio::{routed_device, AudioBackend, MockBackend, OutputRouting, SoundKind};
+use webrtc_client::config::Settings;
+
+#[test]
+fn alerts_and_calls_route_separately() {
+    let routing = OutputRouting {
+        call: Some("USB Headset".to_string()),
+        alerts: Some("Speakers".to_string()),
+    };
+    assert_eq!(routing.device_for(SoundKind::Call), Some("USB Headset"));
+    assert_eq!(routing.device_for(SoundKind::Alert), Some("Speakers"));
+
+    let settings = Settings {
+        output_routing: routing.clone(),
+        ..Default::default()
+    };
+    let loaded: Settings = serde_json::from_str(&serde_json::to_string(&settings).unwrap()).unwrap();
+    assert_eq!(loaded.output_routing, routing);
+}
+
+#[test]
+fn unplugged_routed_device_falls_back_to_the_default() {
+    let available = ["Speakers", "USB Headset"];
+    assert_eq!(routed_device(Some("USB Headset"), Some("Speakers"), &available), Some("USB Headset"));
+    assert_eq!(routed_device(Some("Bluetooth"), Some("Speakers"), &available), Some("Speakers"));
+    assert_eq!(routed_device(None, Some("Speakers"), &available), Some("Speakers"));
+}
+
+#[tokio::test]
+async fn ringtone_plays_until_dropped() {
+    let mock = Arc::new(MockBackend::default());
+    let backend: Arc<dyn AudioBackend> = mock.clone();
+    let ringing = play_alert(&backend, tones::ringtone(), true).unwrap();
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert!(mock.frames_played() > 0);
+
+    drop(ringing);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let stopped_at = mock.frames_played();
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(mock.frames_played(), stopped_at);
+}
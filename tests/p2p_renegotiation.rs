@@ -0,0 +1,44 @@
+mod support;
+
+use support::{engine, wait_for, LoopbackServer};
+use webrtc_client::control::ControlMessage;
+use webrtc_client::engine::{EngineCommand, EngineEvent};
+
+#[test]
+fn control_messages_are_tagged_json() {
+    let message = ControlMessage::IceCandidate { candidate: "candidate:1 1 udp 1 10.0.0.1 5000 typ host".to_string() };
+    let json = serde_json::to_value(&message).unwrap();
+    assert_eq!(json["type"], "ice_candidate");
+    assert_eq!(serde_json::from_value::<ControlMessage>(json).unwrap(), message);
+}
+
+#[tokio::test]
+async fn call_survives_the_signaling_server_going_away() {
+    let server = LoopbackServer::start().await;
+    let alice = engine(&server, "alice", false);
+    let bob = engine(&server, "bob", true);
+    let mut alice_events = alice.subscribe();
+    let mut bob_events = bob.subscribe();
+
+    alice.send(EngineCommand::Connect).unwrap();
+    bob.send(EngineCommand::Connect).unwrap();
+    wait_for(&mut alice_events, "bob in alice's peer list", |e| {
+        matches!(e, EngineEvent::PeerList(peers) if peers.contains(&"bob".to_string()))
+    })
+    .await;
+
+    alice.send(EngineCommand::Call(vec!["bob".to_string()])).unwrap();
+    wait_for(&mut alice_events, "audio at alice", |e| matches!(e, EngineEvent::RemoteAudioStarted)).await;
+    wait_for(&mut bob_events, "audio at bob", |e| matches!(e, EngineEvent::RemoteAudioStarted)).await;
+
+    server.stop().await;
+    wait_for(&mut alice_events, "alice losing signaling", |e| matches!(e, EngineEvent::Disconnected)).await;
+
+    // With the server gone the ICE restart is negotiated with bob directly
+    alice.send(EngineCommand::NetworkChanged).unwrap();
+    wait_for(&mut alice_events, "call resumed", |e| {
+        assert!(!matches!(e, EngineEvent::CallEnded), "call ended instead of resuming");
+        matches!(e, EngineEvent::CallResumed)
+    })
+    .await;
+}
@@ -0,0 +1,81 @@
+use anyhow::anyhow;
+use futures::StreamExt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use webrtc_client::call::{set_up_peers, CallSetup, PeerSetup};
+
+fn peers(count: usize) -> Vec<String> {
+    (0..count).map(|n| format!("peer-{}", n)).collect()
+}
+
+#[tokio::test]
+async fn no_more_than_the_limit_are_set_up_at_once() {
+    let running = Arc::new(AtomicUsize::new(0));
+    let most = Arc::new(AtomicUsize::new(0));
+    let results: Vec<_> = set_up_peers(peers(10), 3, |peer| {
+        let running = running.clone();
+        let most = most.clone();
+        async move {
+            let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+            most.fetch_max(now, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            running.fetch_sub(1, Ordering::SeqCst);
+            Ok(peer)
+        }
+    })
+    .collect()
+    .await;
+
+    assert_eq!(results.len(), 10);
+    assert_eq!(most.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn a_slow_peer_does_not_hold_up_the_rest() {
+    let order: Vec<String> = set_up_peers(peers(2), 2, |peer| async move {
+        if peer == "peer-0" {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        Ok(())
+    })
+    .map(|(peer, _)| peer)
+    .collect()
+    .await;
+    assert_eq!(order, vec!["peer-1", "peer-0"]);
+}
+
+#[tokio::test]
+async fn each_peer_gets_its_own_result() {
+    let mut setup = CallSetup::new(&peers(3));
+    let mut results = set_up_peers(peers(3), 2, |peer| async move {
+        if peer == "peer-1" {
+            Err(anyhow!("no route"))
+        } else {
+            Ok(())
+        }
+    });
+    while let Some((peer, result)) = results.next().await {
+        let state = match result {
+            Ok(()) => PeerSetup::Ringing,
+            Err(e) => PeerSetup::Failed(e.to_string()),
+        };
+        setup.update(&peer, state);
+    }
+
+    assert_eq!(setup.get("peer-0"), Some(&PeerSetup::Ringing));
+    assert_eq!(setup.get("peer-1"), Some(&PeerSetup::Failed("no route".to_string())));
+    assert_eq!(setup.failed(), vec!["peer-1"]);
+}
+
+#[test]
+fn the_setup_keeps_the_order_peers_were_called_in() {
+    let mut setup = CallSetup::new(&peers(2));
+    setup.update("peer-1", PeerSetup::Connected);
+    setup.update("stranger", PeerSetup::Connected);
+
+    let names: Vec<&str> = setup.peers().iter().map(|(peer, _)| peer.as_str()).collect();
+    assert_eq!(names, vec!["peer-0", "peer-1"]);
+    assert_eq!(setup.connected(), 1);
+    assert_eq!(setup.get("stranger"), None);
+}
@@ -0,0 +1,85 @@
+mod support;
+
+use support::{engine, wait_for, LoopbackServer};
+use webrtc_client::call::{CallSession, CallSetup, CallState, PeerSetup};
+use webrtc_client::engine::{EngineCommand, EngineEvent};
+
+fn callees() -> Vec<String> {
+    vec!["alice".to_string(), "bob".to_string(), "carol".to_string()]
+}
+
+#[test]
+fn declined_and_failed_peers_are_offered_a_retry() {
+    let mut setup = CallSetup::new(&callees());
+    setup.update("alice", PeerSetup::Connected);
+    setup.update("bob", PeerSetup::Declined);
+    setup.update("carol", PeerSetup::Failed("Could not connect".to_string()));
+    assert_eq!(setup.failed(), vec!["bob", "carol"]);
+    assert_eq!(setup.connected(), 1);
+
+    // Retrying puts them back to the start
+    setup.update("bob", PeerSetup::Preparing);
+    assert_eq!(setup.failed(), vec!["carol"]);
+}
+
+#[test]
+fn the_call_goes_on_with_whoever_connected() {
+    let mut session = CallSession::new("room".to_string(), callees());
+    session.mark_active();
+    session.decline("bob");
+    session.remove_participant("carol");
+    assert_eq!(session.state, CallState::Active);
+    assert_eq!(session.participants, vec!["alice"]);
+
+    // Back in after a retry
+    session.add_participant("bob".to_string());
+    assert_eq!(session.participant_count(), 3);
+}
+
+#[test]
+fn a_call_still_dialing_is_only_busy_once_everyone_declined() {
+    let mut session = CallSession::new("room".to_string(), callees());
+    session.decline("alice");
+    session.decline("bob");
+    assert_eq!(session.state, CallState::Dialing);
+    session.decline("carol");
+    assert_eq!(session.state, CallState::Busy);
+}
+
+#[tokio::test]
+async fn each_callee_of_a_mesh_call_reports_its_setup() {
+    let server = LoopbackServer::start().await;
+    let alice = engine(&server, "alice", false);
+    let bob = engine(&server, "bob", true);
+    let carol = engine(&server, "carol", false);
+    let mut alice_events = alice.subscribe();
+    let mut alice_setup = alice.subscribe();
+    let mut carol_events = carol.subscribe();
+
+    for peer in [&alice, &bob, &carol] {
+        peer.send(EngineCommand::Connect).unwrap();
+    }
+    wait_for(&mut alice_events, "bob and carol in alice's peer list", |e| {
+        matches!(e, EngineEvent::PeerList(peers) if peers.len() == 3)
+    })
+    .await;
+
+    alice.send(EngineCommand::Call(vec!["bob".to_string(), "carol".to_string()])).unwrap();
+    let call = match wait_for(&mut carol_events, "incoming call", |e| matches!(e, EngineEvent::IncomingCall(_))).await {
+        EngineEvent::IncomingCall(call) => call,
+        _ => unreachable!(),
+    };
+    carol.send(EngineCommand::Answer { call, accepted: false }).unwrap();
+
+    // Bob's connection comes up and carol's decline leaves the call going with him,
+    // in whichever order
+    wait_for(&mut alice_setup, "bob connected, carol declined", |e| {
+        matches!(e, EngineEvent::CallSetup(setup)
+            if setup.get("bob") == Some(&PeerSetup::Connected) && setup.get("carol") == Some(&PeerSetup::Declined))
+    })
+    .await;
+    wait_for(&mut alice_events, "call going on with bob", |e| {
+        matches!(e, EngineEvent::CallUpdated(session) if session.participants == ["bob"])
+    })
+    .await;
+}
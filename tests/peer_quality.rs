@@ -0,0 +1,31 @@
+use webrtc_client::metrics::{signal_bars, ConnectionQuality, PeerQualities};
+
+fn quality(score: u8) -> ConnectionQuality {
+    ConnectionQuality {
+        quality_score: score,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn overall_quality_is_the_worst_peer() {
+    let mut qualities = PeerQualities::default();
+    assert_eq!(qualities.overall(), ConnectionQuality::default());
+
+    qualities.update("alice", quality(100));
+    qualities.update("bob", quality(60));
+    assert_eq!(qualities.overall().quality_score, 60);
+    assert_eq!(qualities.get("alice").map(|q| q.quality_score), Some(100));
+
+    qualities.remove("bob");
+    assert_eq!(qualities.overall().quality_score, 100);
+    assert_eq!(qualities.get("bob"), None);
+}
+
+#[test]
+fn signal_bars_follow_quality_bands() {
+    assert_eq!(signal_bars(100), 4);
+    assert_eq!(signal_bars(80), 3);
+    assert_eq!(signal_bars(50), 2);
+    assert_eq!(signal_bars(40), 1);
+}
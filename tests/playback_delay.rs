@@ -0,0 +1,63 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use webrtc_client::audio::{PlaybackBuffer, MAX_PLAYBACK_DELAY_MS};
+use webrtc_client::config::Settings;
+
+// Mono at 48 kHz keeps the arithmetic readable: 48 samples per millisecond
+const RATE: usize = 48_000;
+
+fn pop(buffer: &mut PlaybackBuffer, count: usize) -> Vec<f32> {
+    let mut output = Vec::new();
+    buffer.pop_into(&mut output, count);
+    output
+}
+
+#[test]
+fn without_a_delay_audio_plays_as_soon_as_it_arrives() {
+    let mut buffer = PlaybackBuffer::new(RATE, Arc::new(AtomicU32::new(0)));
+    buffer.push(&[0.5; 960]);
+    assert_eq!(pop(&mut buffer, 480), vec![0.5; 480]);
+
+    // Running short pads with silence
+    let output = pop(&mut buffer, 960);
+    assert_eq!(&output[..480], &[0.5; 480][..]);
+    assert_eq!(&output[480..], &[0.0; 480][..]);
+}
+
+#[test]
+fn a_delay_holds_audio_back_until_the_cushion_is_full() {
+    let mut buffer = PlaybackBuffer::new(RATE, Arc::new(AtomicU32::new(40)));
+    buffer.push(&[0.5; 960]);
+    assert_eq!(pop(&mut buffer, 480), vec![0.0; 480]);
+
+    buffer.push(&[0.5; 960]);
+    assert_eq!(pop(&mut buffer, 480), vec![0.5; 480]);
+}
+
+#[test]
+fn running_dry_builds_the_cushion_up_again() {
+    let mut buffer = PlaybackBuffer::new(RATE, Arc::new(AtomicU32::new(20)));
+    buffer.push(&[0.5; 960]);
+    assert_eq!(pop(&mut buffer, 960), vec![0.5; 960]);
+    assert_eq!(pop(&mut buffer, 480), vec![0.0; 480]);
+
+    buffer.push(&[0.5; 480]);
+    assert_eq!(pop(&mut buffer, 480), vec![0.0; 480]);
+    buffer.push(&[0.5; 480]);
+    assert_eq!(pop(&mut buffer, 480), vec![0.5; 480]);
+}
+
+#[test]
+fn the_delay_caps_at_the_maximum_and_applies_while_running() {
+    let delay = Arc::new(AtomicU32::new(0));
+    let mut buffer = PlaybackBuffer::new(RATE, delay.clone());
+    delay.store(10_000, Ordering::Relaxed);
+    buffer.push(&vec![0.5; RATE]);
+    // 200 ms of slack plus the capped cushion; the rest was latency
+    assert_eq!(buffer.len(), RATE / 5 + RATE * MAX_PLAYBACK_DELAY_MS as usize / 1000);
+}
+
+#[test]
+fn settings_default_to_no_extra_delay() {
+    assert_eq!(Settings::default().playback_delay_ms, 0);
+}
@@ -0,0 +1,198 @@
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use webrtc::media::Sample;
+use webrtc_client::audio::tap::TapSource;
+use webrtc_client::audio::{encode_frame, AudioTrack};
+use std::ffi::c_void;
+use webrtc_client::plugins::abi::{self, PluginString, PluginVtable, FILTER_DROP, FILTER_KEEP};
+use webrtc_client::plugins::{self, AudioProcessor, Plugin, PluginHost, PluginPanel, SignalingFilter};
+use webrtc_client::signaling::SignalingMessage;
+
+// Halves outgoing audio, drops chat containing "spam" and counts what it let through
+#[derive(Default)]
+struct Moderator {
+    passed: Arc<AtomicU32>,
+}
+
+struct Halve;
+
+impl AudioProcessor for Halve {
+    fn process(&self, source: &TapSource, samples: &mut [f32], _sample_rate: u32) {
+        if *source == TapSource::Local {
+            samples.iter_mut().for_each(|sample| *sample *= 0.5);
+        }
+    }
+}
+
+struct SpamFilter {
+    passed: Arc<AtomicU32>,
+}
+
+impl SignalingFilter for SpamFilter {
+    fn incoming(&self, message: SignalingMessage) -> Option<SignalingMessage> {
+        if let SignalingMessage::ChatMessage { ref text, .. } = message {
+            if text.contains("spam") {
+                return None;
+            }
+        }
+        self.passed.fetch_add(1, Ordering::Relaxed);
+        Some(message)
+    }
+}
+
+impl Plugin for Moderator {
+    fn name(&self) -> String {
+        "moderator".to_string()
+    }
+
+    fn audio_processor(&self) -> Option<Arc<dyn AudioProcessor>> {
+        Some(Arc::new(Halve))
+    }
+
+    fn signaling_filter(&self) -> Option<Arc<dyn SignalingFilter>> {
+        Some(Arc::new(SpamFilter { passed: self.passed.clone() }))
+    }
+
+    fn panel(&self) -> Option<PluginPanel> {
+        Some(PluginPanel {
+            title: "Moderation".to_string(),
+            lines: vec![format!("{} messages passed", self.passed.load(Ordering::Relaxed))],
+        })
+    }
+}
+
+fn chat(text: &str) -> SignalingMessage {
+    SignalingMessage::ChatMessage {
+        room_id: "lobby".to_string(),
+        from_peer: "bob".to_string(),
+        text: text.to_string(),
+    }
+}
+
+#[test]
+fn filters_and_panels_come_from_every_plugin() {
+    let mut host = PluginHost::default();
+    host.add(Box::new(Moderator::default()));
+    assert_eq!(host.names(), vec!["moderator".to_string()]);
+
+    assert_eq!(host.filter_incoming(chat("hello")), Some(chat("hello")));
+    assert_eq!(host.filter_incoming(chat("buy spam")), None);
+    // Only incoming messages are filtered by this one
+    assert_eq!(host.filter_outgoing(chat("spam")), Some(chat("spam")));
+
+    assert_eq!(host.panels()[0].lines, vec!["1 messages passed".to_string()]);
+}
+
+#[test]
+fn rust_plugins_work_through_the_c_interface() {
+    // What a library built with declare_plugin! hands the client
+    let plugin = unsafe { abi::import(abi::export(Box::new(Moderator::default()))) };
+    let mut host = PluginHost::default();
+    host.add(plugin);
+    assert_eq!(host.names(), vec!["moderator".to_string()]);
+
+    assert_eq!(host.filter_incoming(chat("hello")), Some(chat("hello")));
+    assert_eq!(host.filter_incoming(chat("buy spam")), None);
+    let mut samples = vec![0.5; 960];
+    assert!(host.process_audio(&TapSource::Local, &mut samples, 48_000));
+    assert!(samples.iter().all(|sample| *sample == 0.25));
+    assert_eq!(host.panels()[0].lines, vec!["1 messages passed".to_string()]);
+}
+
+// A plugin as C would write one: static strings, no audio hook, drops every outgoing message
+extern "C" fn c_name(_instance: *mut c_void) -> PluginString {
+    PluginString { data: b"c-plugin".as_ptr(), len: 8 }
+}
+
+extern "C" fn c_filter(_instance: *mut c_void, _message: *const u8, _len: usize, outgoing: bool, _replacement: *mut PluginString) -> i32 {
+    if outgoing {
+        FILTER_DROP
+    } else {
+        FILTER_KEEP
+    }
+}
+
+extern "C" fn c_free_string(_instance: *mut c_void, _string: PluginString) {}
+
+extern "C" fn c_destroy(_instance: *mut c_void) {}
+
+#[test]
+fn tables_written_by_hand_load_too() {
+    let vtable = PluginVtable {
+        instance: std::ptr::null_mut(),
+        name: c_name,
+        process_audio: None,
+        filter_signaling: Some(c_filter),
+        panel: None,
+        free_string: c_free_string,
+        destroy: c_destroy,
+    };
+    let mut host = PluginHost::default();
+    host.add(unsafe { abi::import(vtable) });
+    assert_eq!(host.names(), vec!["c-plugin".to_string()]);
+    assert_eq!(host.filter_outgoing(chat("hello")), None);
+    assert_eq!(host.filter_incoming(chat("hello")), Some(chat("hello")));
+    let mut samples = vec![0.5; 960];
+    assert!(!host.process_audio(&TapSource::Local, &mut samples, 48_000));
+    assert!(host.panels().is_empty());
+}
+
+#[test]
+fn unloadable_plugins_are_reported_and_skipped() {
+    let (host, errors) = plugins::load_all(&[PathBuf::from("notes.txt"), PathBuf::from("missing.wasm")]);
+    assert!(host.is_empty());
+    assert_eq!(errors.len(), 2);
+}
+
+#[tokio::test]
+async fn installed_processors_change_what_is_sent() {
+    let mut host = PluginHost::default();
+    host.add(Box::new(Moderator::default()));
+    plugins::install(host).unwrap();
+    assert!(plugins::install(PluginHost::default()).is_err());
+
+    let track = AudioTrack::new("audio".to_string(), "alice".to_string());
+    let mut frames = track.subscribe_frames();
+    let sample = Sample {
+        data: encode_frame(&[0.5; 960]),
+        duration: Duration::from_millis(20),
+        ..Default::default()
+    };
+    track.write_sample(&sample).await.unwrap();
+    let frame = frames.recv().await.unwrap();
+    assert!(frame.samples.iter().all(|sample| *sample == 0.25));
+}
+
+#[cfg(feature = "wasm-plugins")]
+#[test]
+fn wasm_modules_process_audio_in_place() {
+    // Text format is accepted too; it keeps the test module readable
+    const MODULE: &str = r#"
+        (module
+          (memory (export "memory") 1)
+          (func (export "api_version") (result i32) i32.const 1)
+          (func (export "alloc") (param i32) (result i32) i32.const 1024)
+          (func (export "process_audio") (param $ptr i32) (param $count i32) (param i32) (param i32)
+            (local $end i32)
+            (local.set $end (i32.add (local.get $ptr) (i32.mul (local.get $count) (i32.const 4))))
+            (block $done
+              (loop $next
+                (br_if $done (i32.ge_u (local.get $ptr) (local.get $end)))
+                (f32.store (local.get $ptr) (f32.mul (f32.load (local.get $ptr)) (f32.const 0.5)))
+                (local.set $ptr (i32.add (local.get $ptr) (i32.const 4)))
+                (br $next)))))
+    "#;
+    let path = std::env::temp_dir().join(format!("webrtc-client-halve-{}.wasm", rand::random::<u32>()));
+    std::fs::write(&path, MODULE).unwrap();
+    let mut host = PluginHost::default();
+    host.load(&path).unwrap();
+    let _ = std::fs::remove_file(&path);
+
+    let mut samples = vec![0.5; 960];
+    assert!(host.process_audio(&TapSource::Remote("bob".to_string()), &mut samples, 48_000));
+    assert!(samples.iter().all(|sample| *sample == 0.25));
+    // No filter exported, so messages pass untouched
+    assert_eq!(host.filter_incoming(chat("hello")), Some(chat("hello")));
+}
@@ -0,0 +1,85 @@
+mod support;
+
+use std::time::{Duration, Instant};
+use support::{engine, wait_for, LoopbackServer};
+use webrtc_client::engine::{EngineCommand, EngineEvent};
+use webrtc_client::presence::{IdleConfig, PresenceStatus, PresenceTracker};
+
+#[test]
+fn idle_users_go_away_unless_in_a_call() {
+    let start = Instant::now();
+    let mut tracker = PresenceTracker::new(Some(Duration::from_secs(60)));
+    tracker.activity(start);
+    assert_eq!(tracker.take_change(start), Some(PresenceStatus::Online));
+    assert_eq!(tracker.take_change(start), None);
+
+    let later = start + Duration::from_secs(61);
+    assert_eq!(tracker.take_change(later), Some(PresenceStatus::Away));
+
+    tracker.set_in_call(true);
+    assert_eq!(tracker.take_change(later), Some(PresenceStatus::InCall));
+
+    tracker.set_in_call(false);
+    tracker.activity(later);
+    assert_eq!(tracker.take_change(later), Some(PresenceStatus::Online));
+}
+
+#[test]
+fn an_empty_room_is_left_only_while_away() {
+    let start = Instant::now();
+    let mut tracker = PresenceTracker::new(Some(Duration::from_secs(60)));
+    tracker.activity(start);
+    tracker.set_alone(true, start);
+    let after = Duration::from_secs(120);
+
+    // Still at the keyboard
+    tracker.activity(start + Duration::from_secs(100));
+    assert!(!tracker.should_leave_room(after, start + Duration::from_secs(130)));
+
+    let later = start + Duration::from_secs(200);
+    assert!(tracker.should_leave_room(after, later));
+
+    // Someone joined
+    tracker.set_alone(false, later);
+    assert!(!tracker.should_leave_room(after, later));
+    tracker.set_alone(true, later);
+    assert!(!tracker.should_leave_room(after, later + Duration::from_secs(60)));
+}
+
+#[test]
+fn zero_minutes_turns_idle_handling_off() {
+    let idle = IdleConfig::default();
+    assert_eq!(idle.away_after(), Some(Duration::from_secs(5 * 60)));
+    assert_eq!(idle.leave_empty_room_after(), None);
+
+    let idle = IdleConfig { away_after_mins: 0, leave_empty_room_after_mins: 30 };
+    assert_eq!(idle.away_after(), None);
+    assert_eq!(idle.leave_empty_room_after(), Some(Duration::from_secs(30 * 60)));
+}
+
+#[tokio::test]
+async fn peers_see_call_start_and_end() {
+    let server = LoopbackServer::start().await;
+    let alice = engine(&server, "alice", false);
+    let bob = engine(&server, "bob", true);
+    let mut bob_events = bob.subscribe();
+
+    alice.send(EngineCommand::Connect).unwrap();
+    bob.send(EngineCommand::Connect).unwrap();
+    wait_for(&mut bob_events, "alice online", |e| {
+        matches!(e, EngineEvent::Presence { peer_id, status: PresenceStatus::Online } if peer_id == "alice")
+    })
+    .await;
+
+    alice.send(EngineCommand::Call(vec!["bob".to_string()])).unwrap();
+    wait_for(&mut bob_events, "alice in call", |e| {
+        matches!(e, EngineEvent::Presence { peer_id, status: PresenceStatus::InCall } if peer_id == "alice")
+    })
+    .await;
+
+    alice.send(EngineCommand::HangUp).unwrap();
+    wait_for(&mut bob_events, "alice back online", |e| {
+        matches!(e, EngineEvent::Presence { peer_id, status: PresenceStatus::Online } if peer_id == "alice")
+    })
+    .await;
+}
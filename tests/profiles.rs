@@ -0,0 +1,68 @@
+use webrtc_client::config::Settings;
+use webrtc_client::webrtc::IceServerConfig;
+
+fn work_settings() -> Settings {
+    let mut settings = Settings::default();
+    settings.signaling_url = "wss://signal.example.com".to_string();
+    settings.signaling_token = Some("secret".to_string());
+    settings.webrtc.ice_servers = vec![IceServerConfig {
+        urls: vec!["turn:turn.example.com:3478".to_string()],
+        ..Default::default()
+    }];
+    settings.audio_buffer_frames = Some(256);
+    settings
+}
+
+#[test]
+fn switching_profiles_swaps_server_credentials_and_devices() {
+    let mut settings = work_settings();
+    settings.save_profile("work").unwrap();
+
+    settings.signaling_url = "ws://192.168.1.2:8080".to_string();
+    settings.signaling_token = None;
+    settings.audio_buffer_frames = None;
+    settings.save_profile("home").unwrap();
+
+    settings.switch_profile("work").unwrap();
+    assert_eq!(settings.active_profile.as_deref(), Some("work"));
+    assert_eq!(settings.current_profile(), work_settings().current_profile());
+
+    settings.switch_profile("home").unwrap();
+    assert_eq!(settings.signaling_url, "ws://192.168.1.2:8080");
+    assert_eq!(settings.signaling_token, None);
+}
+
+#[test]
+fn edits_are_kept_when_switching_away() {
+    let mut settings = work_settings();
+    settings.save_profile("work").unwrap();
+    settings.save_profile("home").unwrap();
+
+    settings.switch_profile("work").unwrap();
+    settings.signaling_url = "wss://new.example.com".to_string();
+    settings.switch_profile("home").unwrap();
+    settings.switch_profile("work").unwrap();
+    assert_eq!(settings.signaling_url, "wss://new.example.com");
+}
+
+#[test]
+fn unknown_profile_leaves_settings_alone() {
+    let mut settings = work_settings();
+    assert!(settings.switch_profile("nope").is_err());
+    assert_eq!(settings.signaling_url, "wss://signal.example.com");
+    assert_eq!(settings.active_profile, None);
+}
+
+#[test]
+fn profiles_survive_a_settings_round_trip() {
+    let mut settings = work_settings();
+    settings.save_profile("work").unwrap();
+    let json = serde_json::to_string(&settings).unwrap();
+    let loaded: Settings = serde_json::from_str(&json).unwrap();
+    assert_eq!(loaded.profiles, settings.profiles);
+    assert_eq!(loaded.active_profile.as_deref(), Some("work"));
+
+    // Settings from before profiles existed
+    let old: Settings = serde_json::from_str(r#"{"signaling_url": "ws://old:8080"}"#).unwrap();
+    assert!(old.profiles.is_empty());
+}
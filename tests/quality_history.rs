@@ -0,0 +1,34 @@
+use webrtc_client::metrics::{ConnectionQuality, QualityHistory, HISTORY_LEN};
+
+fn reading(bitrate: f64) -> ConnectionQuality {
+    ConnectionQuality {
+        bitrate,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn history_keeps_the_last_minute_oldest_first() {
+    let mut history = QualityHistory::default();
+    for second in 0..HISTORY_LEN + 5 {
+        history.push(reading(second as f64));
+    }
+
+    assert_eq!(history.len(), HISTORY_LEN);
+    let bitrates = history.series(|quality| quality.bitrate);
+    assert_eq!(bitrates.first(), Some(&5.0));
+    assert_eq!(bitrates.last(), Some(&(HISTORY_LEN as f64 + 4.0)));
+}
+
+#[test]
+fn series_picks_out_one_metric() {
+    let mut history = QualityHistory::default();
+    history.push(ConnectionQuality {
+        packet_loss_rate: 2.5,
+        round_trip_time: 80.0,
+        ..Default::default()
+    });
+
+    assert_eq!(history.series(|quality| quality.packet_loss_rate), vec![2.5]);
+    assert_eq!(history.series(|quality| quality.round_trip_time), vec![80.0]);
+}
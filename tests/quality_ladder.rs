@@ -0,0 +1,77 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use webrtc_client::audio::MockBackend;
+use webrtc_client::ladder::{LadderConfig, QualityLadder, QualityRung};
+use webrtc_client::metrics::ConnectionQuality;
+use webrtc_client::webrtc::{WebRTCClient, WebRTCConfig};
+
+fn reading(loss: f64, available_bitrate: f64) -> ConnectionQuality {
+    ConnectionQuality {
+        packet_loss_rate: loss,
+        available_bitrate,
+        ..Default::default()
+    }
+}
+
+// One reading a second for `seconds`, returning the rung after each step taken
+fn feed(ladder: &mut QualityLadder, start: &mut Instant, seconds: u64, quality: &ConnectionQuality) -> Vec<QualityRung> {
+    (0..seconds)
+        .filter_map(|_| {
+            *start += Duration::from_secs(1);
+            ladder.observe(quality, *start).map(|step| step.to)
+        })
+        .collect()
+}
+
+#[test]
+fn sustained_loss_steps_down_one_rung_at_a_time() {
+    let mut ladder = QualityLadder::new(LadderConfig::default());
+    let mut now = Instant::now();
+    // A blip shorter than step_down_after_ms changes nothing
+    assert!(feed(&mut ladder, &mut now, 3, &reading(12.0, 0.0)).is_empty());
+    assert!(feed(&mut ladder, &mut now, 1, &reading(0.0, 0.0)).is_empty());
+
+    let steps = feed(&mut ladder, &mut now, 12, &reading(12.0, 0.0));
+    assert_eq!(steps, vec![QualityRung::WidebandMono, QualityRung::Narrowband]);
+    // Nowhere lower to go
+    assert!(feed(&mut ladder, &mut now, 10, &reading(12.0, 0.0)).is_empty());
+}
+
+#[test]
+fn a_thin_bandwidth_estimate_steps_down_without_loss() {
+    let mut ladder = QualityLadder::new(LadderConfig::default());
+    let mut now = Instant::now();
+    assert_eq!(feed(&mut ladder, &mut now, 6, &reading(0.0, 45.0)), vec![QualityRung::WidebandMono]);
+    // 45 kbps is room for 32k mono, but not to go back to 48k stereo
+    assert!(feed(&mut ladder, &mut now, 60, &reading(0.0, 45.0)).is_empty());
+}
+
+#[test]
+fn a_clean_link_steps_back_up_slowly() {
+    let mut ladder = QualityLadder::new(LadderConfig::default());
+    let mut now = Instant::now();
+    feed(&mut ladder, &mut now, 6, &reading(10.0, 0.0));
+    assert_eq!(ladder.rung(), QualityRung::WidebandMono);
+
+    assert!(feed(&mut ladder, &mut now, 15, &reading(0.2, 200.0)).is_empty());
+    assert_eq!(feed(&mut ladder, &mut now, 10, &reading(0.2, 200.0)), vec![QualityRung::FullbandStereo]);
+}
+
+#[test]
+fn a_disabled_ladder_stays_put() {
+    let mut ladder = QualityLadder::new(LadderConfig { enabled: false, ..Default::default() });
+    let mut now = Instant::now();
+    assert!(feed(&mut ladder, &mut now, 30, &reading(30.0, 10.0)).is_empty());
+}
+
+#[tokio::test]
+async fn lower_rungs_renegotiate_as_mono_at_their_bitrate() {
+    let client = WebRTCClient::with_config(&WebRTCConfig::default(), Arc::new(MockBackend::default())).await.unwrap();
+    client.set_quality_rung(QualityRung::Narrowband);
+    let offer: serde_json::Value = serde_json::from_str(&client.create_offer().await.unwrap()).unwrap();
+    let sdp = offer["sdp"].as_str().unwrap();
+    let fmtp = sdp.lines().find(|line| line.starts_with("a=fmtp:111 ")).unwrap();
+    for parameter in ["stereo=0", "maxplaybackrate=8000", "maxaveragebitrate=16000"] {
+        assert!(fmtp.contains(parameter), "{} missing from {}", parameter, fmtp);
+    }
+}
@@ -0,0 +1,41 @@
+use std::time::Duration;
+use webrtc_client::config::Settings;
+use webrtc_client::reconnect::{Backoff, ReconnectPolicy};
+
+#[test]
+fn backoff_curves_are_capped_at_max_delay() {
+    let policy = ReconnectPolicy {
+        initial_delay_ms: 1000,
+        max_delay_ms: 5000,
+        backoff: Backoff::Exponential,
+        ..Default::default()
+    };
+    let delays: Vec<u64> = (1..=5).map(|attempt| policy.delay(attempt).as_millis() as u64).collect();
+    assert_eq!(delays, vec![1000, 2000, 4000, 5000, 5000]);
+
+    let linear = ReconnectPolicy { backoff: Backoff::Linear, ..policy };
+    assert_eq!(linear.delay(3), Duration::from_millis(3000));
+
+    let constant = ReconnectPolicy { backoff: Backoff::Constant, ..policy };
+    assert_eq!(constant.delay(4), Duration::from_millis(1000));
+}
+
+#[test]
+fn retries_stop_at_max_attempts_or_deadline() {
+    let policy = ReconnectPolicy {
+        max_attempts: 3,
+        initial_delay_ms: 1000,
+        backoff: Backoff::Constant,
+        deadline_ms: 10_000,
+        ..Default::default()
+    };
+    assert!(policy.allows(3, Duration::ZERO));
+    assert!(!policy.allows(4, Duration::ZERO));
+    assert!(!policy.allows(1, Duration::from_millis(9500)));
+}
+
+#[test]
+fn settings_without_a_policy_get_the_default() {
+    let settings: Settings = serde_json::from_str(r#"{"signaling_url": "ws://example.test"}"#).unwrap();
+    assert_eq!(settings.reconnect, ReconnectPolicy::default());
+}
@@ -0,0 +1,92 @@
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use webrtc_client::audio::tap::TapSource;
+use webrtc_client::recording::{file_timestamp, RecordingConfig, RecordingSegment, SegmentRecorder};
+
+const SPEECH: [f32; 960] = [0.1; 960];
+const SILENCE: [f32; 960] = [0.0; 960];
+
+fn temp_dir() -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("webrtc-client-recording-{}", rand::random::<u32>()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn voice_activated() -> RecordingConfig {
+    RecordingConfig {
+        voice_activated: true,
+        start_after_ms: 100,
+        pause_after_ms: 1000,
+        ..Default::default()
+    }
+}
+
+// Feeds 20 ms frames from `start`, returning the segments they closed
+fn feed(recorder: &mut SegmentRecorder, frames: &[&[f32]], start: SystemTime) -> Vec<RecordingSegment> {
+    frames
+        .iter()
+        .enumerate()
+        .filter_map(|(index, frame)| {
+            let at = start + Duration::from_millis(20 * index as u64);
+            recorder.push(frame, 48_000, at).unwrap()
+        })
+        .collect()
+}
+
+#[test]
+fn timestamps_are_utc_and_file_name_safe() {
+    let time = UNIX_EPOCH + Duration::from_secs(1_714_571_107);
+    assert_eq!(file_timestamp(time), "2024-05-01T13-45-07Z");
+    assert_eq!(file_timestamp(UNIX_EPOCH), "1970-01-01T00-00-00Z");
+}
+
+#[test]
+fn each_stretch_of_speech_gets_its_own_file() {
+    let dir = temp_dir();
+    let mut recorder = SegmentRecorder::new(voice_activated(), dir.clone(), Some(TapSource::Remote("base/1".to_string())));
+    let start = UNIX_EPOCH + Duration::from_secs(1_714_571_100);
+
+    // A second of speech, two of silence, then another second of speech
+    let mut frames: Vec<&[f32]> = Vec::new();
+    frames.extend([&SPEECH[..]; 50]);
+    frames.extend([&SILENCE[..]; 100]);
+    frames.extend([&SPEECH[..]; 50]);
+    let mut segments = feed(&mut recorder, &frames, start);
+    assert!(recorder.is_recording());
+    segments.extend(recorder.finish().unwrap());
+
+    assert_eq!(segments.len(), 2);
+    assert_eq!(segments[0].started_at, start);
+    // The speech plus the pause that closed it
+    assert_eq!(segments[0].duration, Duration::from_secs(2));
+    assert_eq!(segments[1].started_at, start + Duration::from_secs(3));
+    assert_eq!(segments[1].path, dir.join("2024-05-01T13-45-03Z-base_1.wav"));
+
+    let wav = std::fs::read(&segments[0].path).unwrap();
+    assert_eq!(&wav[..4], b"RIFF");
+    assert_eq!(u32::from_le_bytes(wav[40..44].try_into().unwrap()), 96_000 * 2);
+    assert_eq!(wav.len(), 44 + 96_000 * 2);
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn a_click_does_not_open_a_file() {
+    let dir = temp_dir();
+    let mut recorder = SegmentRecorder::new(voice_activated(), dir.clone(), Some(TapSource::Local));
+    let frames = [&SPEECH[..], &SILENCE[..], &SPEECH[..], &SILENCE[..]];
+    assert!(feed(&mut recorder, &frames, SystemTime::now()).is_empty());
+    assert!(!recorder.is_recording());
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn continuous_recording_keeps_the_silences() {
+    let dir = temp_dir();
+    let mut recorder = SegmentRecorder::new(RecordingConfig::default(), dir.clone(), Some(TapSource::Local));
+    let frames: Vec<&[f32]> = [&SILENCE[..]; 200].to_vec();
+    assert!(feed(&mut recorder, &frames, SystemTime::now()).is_empty());
+    let segment = recorder.finish().unwrap().unwrap();
+    assert_eq!(segment.duration, Duration::from_secs(4));
+    let _ = std::fs::remove_dir_all(dir);
+}
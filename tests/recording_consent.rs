@@ -0,0 +1,77 @@
+mod support;
+
+use support::{engine, wait_for, LoopbackServer};
+use webrtc_client::engine::{EngineCommand, EngineEvent};
+use webrtc_client::room::{ConsentPolicy, ConsentState, RecordingConsent};
+
+fn peers(names: &[&str]) -> Vec<String> {
+    names.iter().map(|name| name.to_string()).collect()
+}
+
+#[test]
+fn unanimous_needs_everyone_and_one_no_ends_it() {
+    let mut consent = RecordingConsent::new(peers(&["bob", "carol"]), ConsentPolicy::Unanimous);
+    assert_eq!(consent.state(), ConsentState::Pending);
+    assert!(consent.respond("bob", true));
+    assert_eq!(consent.state(), ConsentState::Pending);
+    assert!(consent.respond("carol", true));
+    assert!(consent.is_granted());
+
+    // Someone joining mid-call has to agree as well
+    consent.peer_joined("dave".to_string());
+    assert_eq!(consent.state(), ConsentState::Pending);
+    consent.respond("dave", false);
+    assert_eq!(consent.state(), ConsentState::Declined);
+}
+
+#[test]
+fn majority_settles_as_soon_as_the_outcome_is_certain() {
+    let mut consent = RecordingConsent::new(peers(&["bob", "carol", "dave"]), ConsentPolicy::Majority);
+    consent.respond("bob", true);
+    assert_eq!(consent.state(), ConsentState::Pending);
+    consent.respond("carol", true);
+    assert_eq!(consent.state(), ConsentState::Granted);
+
+    let mut consent = RecordingConsent::new(peers(&["bob", "carol", "dave"]), ConsentPolicy::Majority);
+    consent.respond("bob", false);
+    consent.respond("carol", false);
+    assert_eq!(consent.state(), ConsentState::Declined);
+}
+
+#[test]
+fn answers_from_peers_never_asked_are_ignored() {
+    let mut consent = RecordingConsent::new(peers(&["bob"]), ConsentPolicy::Unanimous);
+    assert!(!consent.respond("mallory", true));
+    assert_eq!(consent.state(), ConsentState::Pending);
+    consent.peer_left("bob");
+    assert!(consent.is_granted());
+}
+
+#[tokio::test]
+async fn consent_request_round_trips_through_the_room() {
+    let server = LoopbackServer::start().await;
+    let alice = engine(&server, "alice", false);
+    let bob = engine(&server, "bob", true);
+    let mut alice_events = alice.subscribe();
+    let mut bob_events = bob.subscribe();
+
+    alice.send(EngineCommand::Connect).unwrap();
+    bob.send(EngineCommand::Connect).unwrap();
+    wait_for(&mut alice_events, "bob in alice's peer list", |e| {
+        matches!(e, EngineEvent::PeerList(peers) if peers.contains(&"bob".to_string()))
+    })
+    .await;
+    alice.send(EngineCommand::Call(vec!["bob".to_string()])).unwrap();
+    wait_for(&mut alice_events, "call active", |e| matches!(e, EngineEvent::CallActive)).await;
+
+    alice.send(EngineCommand::RequestRecording(ConsentPolicy::Unanimous)).unwrap();
+    let event = wait_for(&mut bob_events, "consent request", |e| matches!(e, EngineEvent::RecordingConsentRequested(_))).await;
+    let EngineEvent::RecordingConsentRequested(request) = event else { unreachable!() };
+    assert_eq!(request.from_peer, "alice");
+
+    bob.send(EngineCommand::RespondRecording { request, granted: true }).unwrap();
+    wait_for(&mut alice_events, "consent granted", |e| {
+        matches!(e, EngineEvent::RecordingConsent(consent) if consent.is_granted())
+    })
+    .await;
+}
@@ -0,0 +1,196 @@
+use bytes::Bytes;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use webrtc_client::audio::{encode_frame, AudioCodec, MockBackend};
+use webrtc_client::ladder::{LadderConfig, RedundancySwitch};
+use webrtc_client::metrics::ConnectionQuality;
+use webrtc_client::red::{decode, encode, RedBlock, RedDecoder, RedEncoder, RedFormat};
+use webrtc_client::webrtc::{WebRTCClient, WebRTCConfig};
+
+const PCMU_RED: RedFormat = RedFormat {
+    payload_type: 117,
+    primary_payload_type: 0,
+    primary: AudioCodec::Pcmu,
+};
+
+fn frame(n: u8) -> Bytes {
+    Bytes::from(vec![n; 40])
+}
+
+fn loss(percent: f64) -> ConnectionQuality {
+    ConnectionQuality {
+        packet_loss_rate: percent,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn blocks_survive_a_round_trip() {
+    let blocks = vec![
+        RedBlock { payload_type: 111, timestamp: 1040, data: frame(1) },
+        RedBlock { payload_type: 111, timestamp: 2000, data: frame(2) },
+    ];
+    let payload = encode(2000, &blocks);
+    // One 4-byte header for the redundant block, one byte for the primary
+    assert_eq!(payload.len(), 4 + 1 + 80);
+    assert_eq!(decode(2000, &payload).unwrap(), blocks);
+
+    assert!(decode(2000, &payload.slice(..3)).is_err());
+}
+
+#[test]
+fn blocks_the_header_cant_describe_are_left_out() {
+    let blocks = vec![
+        RedBlock { payload_type: 111, timestamp: 0, data: frame(1) },
+        RedBlock { payload_type: 111, timestamp: 30_000, data: Bytes::from(vec![0; 2000]) },
+        RedBlock { payload_type: 111, timestamp: 30_960, data: frame(3) },
+    ];
+    let decoded = decode(30_960, &encode(30_960, &blocks)).unwrap();
+    assert_eq!(decoded, blocks[2..]);
+}
+
+#[test]
+fn a_lost_packet_is_recovered_from_the_next() {
+    let mut encoder = RedEncoder::default();
+    let packets: Vec<(u32, Bytes)> = (0..4u8)
+        .map(|n| {
+            let timestamp = n as u32 * 960;
+            (timestamp, encoder.encode(0, timestamp, frame(n)))
+        })
+        .collect();
+
+    let mut decoder = RedDecoder::default();
+    assert_eq!(decoder.unpack(&PCMU_RED, packets[0].0, &packets[0].1), vec![frame(0)]);
+    // Packets 1 and 2 never arrive; 3 still carries them
+    assert_eq!(decoder.unpack(&PCMU_RED, packets[3].0, &packets[3].1), vec![frame(1), frame(2), frame(3)]);
+    // Nothing is played twice
+    assert!(decoder.unpack(&PCMU_RED, packets[2].0, &packets[2].1).is_empty());
+}
+
+#[test]
+fn frames_are_unpacked_into_the_callers_buffer() {
+    let mut encoder = RedEncoder::default();
+    let first = encoder.encode(0, 0, frame(0));
+    let second = encoder.encode(0, 960, frame(1));
+
+    let mut decoder = RedDecoder::default();
+    let mut frames = Vec::with_capacity(4);
+    decoder.unpack_into(&PCMU_RED, 0, &first, &mut frames);
+    assert_eq!(frames, vec![frame(0)]);
+    frames.clear();
+    decoder.unpack_into(&PCMU_RED, 960, &second, &mut frames);
+    assert_eq!(frames, vec![frame(1)]);
+
+    // A malformed packet adds nothing, not even the blocks before the broken one
+    frames.clear();
+    decoder.unpack_into(&PCMU_RED, 1920, &second.slice(..second.len() - 41), &mut frames);
+    assert!(frames.is_empty());
+}
+
+// 20 ms of a 440 Hz tone as capture delivers it, through the codec the way the track sends it
+fn pipeline_frame(codec: AudioCodec, n: u32) -> Bytes {
+    let samples: Vec<f32> = (0..960).map(|i| ((n * 960 + i) as f32 * 440.0 / 48_000.0 * std::f32::consts::TAU).sin() * 0.5).collect();
+    codec.encode(&encode_frame(&samples), 48_000)
+}
+
+#[test]
+fn real_g711_frames_are_recovered() {
+    let mut encoder = RedEncoder::default();
+    let packets: Vec<(u32, Bytes, Bytes)> = (0..3)
+        .map(|n| {
+            let frame = pipeline_frame(AudioCodec::Pcmu, n);
+            let timestamp = n * 160;
+            (timestamp, frame.clone(), encoder.encode(0, timestamp, frame))
+        })
+        .collect();
+
+    let mut decoder = RedDecoder::default();
+    assert_eq!(decoder.unpack(&PCMU_RED, packets[0].0, &packets[0].2), vec![packets[0].1.clone()]);
+    // Packet 1 is lost
+    assert_eq!(decoder.unpack(&PCMU_RED, packets[2].0, &packets[2].2), vec![packets[1].1.clone(), packets[2].1.clone()]);
+}
+
+#[test]
+fn opus_frames_are_too_long_for_red() {
+    // Which is why RED is neither offered nor accepted for Opus
+    let frame = pipeline_frame(AudioCodec::Opus, 0);
+    assert!(frame.len() > 1023, "{}", frame.len());
+    let blocks = [
+        RedBlock { payload_type: 111, timestamp: 0, data: frame.clone() },
+        RedBlock { payload_type: 111, timestamp: 960, data: frame },
+    ];
+    assert_eq!(decode(960, &encode(960, &blocks)).unwrap(), blocks[1..]);
+}
+
+#[test]
+fn only_offered_for_g711_when_turned_on() {
+    let red_mimes = |config: WebRTCConfig| {
+        config
+            .audio_codec_parameters()
+            .into_iter()
+            .filter(|p| p.capability.mime_type == "audio/red")
+            .map(|p| (p.payload_type, p.capability.sdp_fmtp_line))
+            .collect::<Vec<_>>()
+    };
+    assert!(red_mimes(WebRTCConfig::default()).is_empty());
+    let opus_only = WebRTCConfig {
+        redundant_audio: true,
+        codec_preferences: vec![AudioCodec::Opus],
+        ..Default::default()
+    };
+    assert!(red_mimes(opus_only).is_empty());
+    let config = WebRTCConfig {
+        redundant_audio: true,
+        codec_preferences: vec![AudioCodec::Pcmu],
+        ..Default::default()
+    };
+    assert_eq!(red_mimes(config.clone()), vec![(117, "0/0".to_string())]);
+    assert_eq!(RedFormat::negotiated(&config.audio_codec_parameters()), vec![PCMU_RED]);
+}
+
+#[test]
+fn red_for_opus_from_the_other_side_is_not_used() {
+    let mut codecs = WebRTCConfig { codec_preferences: vec![AudioCodec::Opus], ..Default::default() }.audio_codec_parameters();
+    let mut red = codecs[0].clone();
+    red.capability.mime_type = "audio/red".to_string();
+    red.capability.sdp_fmtp_line = "111/111".to_string();
+    red.payload_type = 63;
+    codecs.push(red);
+    assert!(RedFormat::negotiated(&codecs).is_empty());
+}
+
+#[tokio::test]
+async fn the_offer_carries_red_for_g711_only() {
+    let config = WebRTCConfig { redundant_audio: true, ..Default::default() };
+    let client = WebRTCClient::with_config(&config, Arc::new(MockBackend::default())).await.unwrap();
+    let offer: serde_json::Value = serde_json::from_str(&client.create_offer().await.unwrap()).unwrap();
+    let sdp = offer["sdp"].as_str().unwrap();
+    assert!(sdp.contains("a=fmtp:117 0/0"), "{}", sdp);
+    assert!(sdp.contains("a=fmtp:118 8/8"), "{}", sdp);
+    assert!(!sdp.contains("111/111"), "{}", sdp);
+}
+
+#[test]
+fn redundancy_follows_loss_without_flapping() {
+    let mut switch = RedundancySwitch::new(true, LadderConfig::default());
+    let mut now = Instant::now();
+    assert_eq!(switch.observe(&loss(3.0), now), None);
+    assert_eq!(switch.observe(&loss(12.0), now), Some(true));
+    assert_eq!(switch.observe(&loss(15.0), now), None);
+
+    // Above half the threshold keeps it on however long it lasts
+    for _ in 0..30 {
+        now += Duration::from_secs(1);
+        assert_eq!(switch.observe(&loss(6.0), now), None);
+    }
+    let mut steps = Vec::new();
+    for _ in 0..25 {
+        now += Duration::from_secs(1);
+        steps.extend(switch.observe(&loss(1.0), now));
+    }
+    assert_eq!(steps, vec![false]);
+    assert!(!switch.is_on());
+
+    let mut disabled = RedundancySwitch::new(false, LadderConfig::default());
+    assert_eq!(disabled.observe(&loss(50.0), now), None);
+}
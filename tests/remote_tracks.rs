@@ -0,0 +1,22 @@
+use webrtc::rtcp::goodbye::Goodbye;
+use webrtc::rtcp::packet::Packet;
+use webrtc::rtcp::receiver_report::ReceiverReport;
+use webrtc_client::webrtc::is_goodbye;
+
+#[test]
+fn a_bye_for_the_stream_ends_it() {
+    let packets: Vec<Box<dyn Packet + Send + Sync>> = vec![
+        Box::new(ReceiverReport::default()),
+        Box::new(Goodbye { sources: vec![7, 42], ..Default::default() }),
+    ];
+    assert!(is_goodbye(&packets, 42));
+}
+
+#[test]
+fn other_streams_and_reports_are_not_a_bye() {
+    let packets: Vec<Box<dyn Packet + Send + Sync>> = vec![
+        Box::new(ReceiverReport::default()),
+        Box::new(Goodbye { sources: vec![7], ..Default::default() }),
+    ];
+    assert!(!is_goodbye(&packets, 42));
+}
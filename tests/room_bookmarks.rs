@@ -0,0 +1,57 @@
+use webrtc_client::config::{RoomBookmark, Settings};
+
+fn settings_on(server: &str) -> Settings {
+    Settings {
+        signaling_url: server.to_string(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn rooms_are_bookmarked_on_the_server_they_are_on() {
+    let mut settings = settings_on("wss://work.example.com");
+    settings.bookmark_room("wss://work.example.com", " standup ").unwrap();
+    settings.bookmark_room("wss://work.example.com", "standup").unwrap();
+    assert!(settings.bookmark_room("wss://work.example.com", "  ").is_err());
+
+    // A room joined from an invite is on the invite's server, not the configured one
+    settings.bookmark_room("ws://192.168.1.2:8080", "standup").unwrap();
+    assert_eq!(settings.bookmarks.len(), 2);
+    assert!(settings.is_bookmarked("wss://work.example.com", "standup"));
+    assert_eq!(settings.signaling_url, "wss://work.example.com");
+
+    settings.remove_bookmark("wss://work.example.com", "standup");
+    assert_eq!(
+        settings.bookmarks,
+        vec![RoomBookmark { server: "ws://192.168.1.2:8080".to_string(), room_id: "standup".to_string(), auto_join: false }]
+    );
+}
+
+#[test]
+fn only_one_room_is_joined_at_launch() {
+    let mut settings = settings_on("wss://work.example.com");
+    settings.bookmark_room("wss://work.example.com", "standup").unwrap();
+    settings.bookmark_room("wss://work.example.com", "support").unwrap();
+    assert_eq!(settings.auto_join_bookmark(), None);
+
+    settings.set_auto_join("wss://work.example.com", "standup", true);
+    settings.set_auto_join("wss://work.example.com", "support", true);
+    assert_eq!(settings.auto_join_bookmark().map(|bookmark| bookmark.room_id.as_str()), Some("support"));
+
+    settings.set_auto_join("wss://work.example.com", "support", false);
+    assert_eq!(settings.auto_join_bookmark(), None);
+}
+
+#[test]
+fn bookmarks_survive_a_settings_round_trip() {
+    let mut settings = settings_on("wss://work.example.com");
+    settings.bookmark_room("wss://work.example.com", "standup").unwrap();
+    settings.set_auto_join("wss://work.example.com", "standup", true);
+    let json = serde_json::to_string(&settings).unwrap();
+    let loaded: Settings = serde_json::from_str(&json).unwrap();
+    assert_eq!(loaded.bookmarks, settings.bookmarks);
+
+    // Config files from before bookmarks
+    let old: Settings = serde_json::from_str(r#"{"signaling_url": "wss://work.example.com"}"#).unwrap();
+    assert!(old.bookmarks.is_empty());
+}
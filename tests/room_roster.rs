@@ -0,0 +1,41 @@
+use webrtc_client::config::Settings;
+use webrtc_client::room::{RoomRoster, RosterCues, RosterDiff};
+
+fn peers(ids: &[&str]) -> Vec<String> {
+    ids.iter().map(|id| id.to_string()).collect()
+}
+
+#[test]
+fn the_first_list_is_who_was_already_there() {
+    let mut roster = RoomRoster::default();
+    assert!(roster.update("lobby", &peers(&["alice", "bob"])).is_empty());
+    assert_eq!(roster.peers("lobby"), peers(&["alice", "bob"]).as_slice());
+}
+
+#[test]
+fn later_lists_diff_against_the_last_one() {
+    let mut roster = RoomRoster::default();
+    roster.update("lobby", &peers(&["alice", "bob"]));
+    let diff = roster.update("lobby", &peers(&["bob", "carol", "dave"]));
+    assert_eq!(diff, RosterDiff { joined: peers(&["carol", "dave"]), left: peers(&["alice"]) });
+    assert!(roster.update("lobby", &peers(&["bob", "carol", "dave"])).is_empty());
+}
+
+#[test]
+fn rooms_are_tracked_separately() {
+    let mut roster = RoomRoster::default();
+    roster.update("lobby", &peers(&["alice"]));
+    // A new room starts from its own first list
+    assert!(roster.update("standup", &peers(&["bob"])).is_empty());
+    assert_eq!(roster.update("lobby", &peers(&["alice", "bob"])).joined, peers(&["bob"]));
+
+    roster.forget("lobby");
+    assert!(roster.update("lobby", &peers(&["carol"])).is_empty());
+}
+
+#[test]
+fn cues_are_on_by_default_and_kept_in_settings() {
+    let settings: Settings = serde_json::from_str(r#"{"signaling_url": "ws://example.test"}"#).unwrap();
+    assert_eq!(settings.roster_cues, RosterCues::default());
+    assert!(settings.roster_cues.sounds && settings.roster_cues.toasts);
+}
@@ -0,0 +1,162 @@
+mod support;
+
+use std::path::PathBuf;
+use std::time::Duration;
+use webrtc::media::Sample;
+use webrtc_client::audio::wav::{read_wav, WavWriter};
+use webrtc_client::audio::{encode_frame, AudioTrack};
+use webrtc_client::call::IncomingCall;
+use webrtc_client::config::Settings;
+use webrtc_client::engine::{CallEngine, EngineCommand, EngineConfig, EngineEvent};
+use webrtc_client::metrics::ConnectionQuality;
+use webrtc_client::scripting::{ScriptAction, ScriptHooks};
+
+use support::{engine, engine_config, wait_for, LoopbackServer};
+
+const RULES: &str = r#"
+    fn on_incoming_call(call) {
+        if call.from_peer == "front-door" {
+            answer();
+            play("greeting.wav");
+        } else {
+            decline();
+        }
+    }
+
+    fn on_quality_degraded(quality) {
+        if quality.quality_score < 20 {
+            send_chat("Dropping off, the connection is too poor");
+            hang_up();
+        }
+    }
+"#;
+
+fn incoming(from_peer: &str) -> IncomingCall {
+    IncomingCall {
+        room_id: "lobby".to_string(),
+        from_peer: from_peer.to_string(),
+    }
+}
+
+#[test]
+fn hooks_return_the_actions_the_script_asked_for() {
+    let scripts = ScriptHooks::from_source(RULES).unwrap();
+    assert_eq!(
+        scripts.incoming_call(&incoming("front-door")).unwrap(),
+        vec![ScriptAction::Answer, ScriptAction::Play(PathBuf::from("greeting.wav"))]
+    );
+    assert_eq!(scripts.incoming_call(&incoming("mallory")).unwrap(), vec![ScriptAction::Decline]);
+
+    let poor = ConnectionQuality {
+        quality_score: 10,
+        ..Default::default()
+    };
+    let actions = scripts.quality_degraded("lobby", &poor).unwrap();
+    assert_eq!(actions.last(), Some(&ScriptAction::HangUp));
+
+    // Not defined by this script
+    assert!(!scripts.has_hook("on_peer_joined"));
+    assert!(scripts.peer_joined("lobby", "bob").unwrap().is_empty());
+}
+
+#[test]
+fn broken_and_runaway_scripts_fail_instead_of_hanging() {
+    assert!(ScriptHooks::from_source("fn on_incoming_call(call) { answer( }").is_err());
+
+    let scripts = ScriptHooks::from_source("fn on_incoming_call(call) { answer(); loop {} }").unwrap();
+    assert!(scripts.incoming_call(&incoming("bob")).is_err());
+}
+
+#[tokio::test]
+async fn announcements_are_mixed_into_outgoing_frames() {
+    let path = std::env::temp_dir().join(format!("webrtc-client-greeting-{}.wav", rand::random::<u32>()));
+    let mut wav = WavWriter::create(&path, 16_000).unwrap();
+    wav.write(&[0.25; 320]).unwrap();
+    wav.finish().unwrap();
+    let (sample_rate, greeting) = read_wav(&path).unwrap();
+    assert_eq!(sample_rate, 16_000);
+    assert_eq!(greeting.len(), 320);
+    let _ = std::fs::remove_file(path);
+
+    let track = AudioTrack::new("audio".to_string(), "alice".to_string());
+    let mut frames = track.subscribe_frames();
+    track.announce(&greeting, sample_rate);
+    let sample = Sample {
+        data: encode_frame(&[0.5; 960]),
+        duration: Duration::from_millis(20),
+        ..Default::default()
+    };
+    track.write_sample(&sample).await.unwrap();
+    track.write_sample(&sample).await.unwrap();
+
+    let first = frames.recv().await.unwrap();
+    assert!(first.samples.iter().all(|sample| (sample - 0.75).abs() < 0.001));
+    // The 20 ms greeting is used up
+    let second = frames.recv().await.unwrap();
+    assert!(second.samples.iter().all(|sample| *sample == 0.5));
+}
+
+#[tokio::test]
+async fn a_script_answers_and_greets_newcomers() {
+    let server = LoopbackServer::start().await;
+    let scripts = ScriptHooks::from_source(
+        r#"
+        fn on_incoming_call(call) {
+            answer();
+            send_chat("Hi " + call.from_peer);
+        }
+
+        fn on_peer_joined(peer) {
+            send_chat("Welcome " + peer.peer_id);
+        }
+    "#,
+    )
+    .unwrap();
+    let alice = engine(&server, "alice", false);
+    // auto_answer off: answering is up to the script
+    let bob = CallEngine::spawn(EngineConfig {
+        scripts: Some(scripts),
+        ..engine_config(&server, "bob", false)
+    });
+    let mut alice_events = alice.subscribe();
+    let mut bob_events = bob.subscribe();
+
+    alice.send(EngineCommand::Connect).unwrap();
+    wait_for(&mut alice_events, "alice connected", |e| matches!(e, EngineEvent::Connected)).await;
+    bob.send(EngineCommand::Connect).unwrap();
+    wait_for(&mut alice_events, "bob in alice's peer list", |e| {
+        matches!(e, EngineEvent::PeerList(peers) if peers.contains(&"bob".to_string()))
+    })
+    .await;
+
+    alice.send(EngineCommand::Call(vec!["bob".to_string()])).unwrap();
+    wait_for(&mut bob_events, "bob call active", |e| matches!(e, EngineEvent::CallActive)).await;
+    wait_for(&mut alice_events, "bob's hello", |e| {
+        matches!(e, EngineEvent::Chat(chat) if chat.from_peer == "bob" && chat.text == "Hi alice")
+    })
+    .await;
+
+    // Alice was already there when bob joined; carol is new
+    let carol = engine(&server, "carol", false);
+    carol.send(EngineCommand::Connect).unwrap();
+    wait_for(&mut alice_events, "bob's welcome", |e| {
+        matches!(e, EngineEvent::Chat(chat) if chat.from_peer == "bob" && chat.text == "Welcome carol")
+    })
+    .await;
+}
+
+#[test]
+fn the_settings_name_the_script_loaded_at_startup() {
+    let path = std::env::temp_dir().join(format!("webrtc-client-rules-{}.rhai", rand::random::<u32>()));
+    std::fs::write(&path, RULES).unwrap();
+    let json = serde_json::json!({ "scripts": path }).to_string();
+    let settings: Settings = serde_json::from_str(&json).unwrap();
+    assert_eq!(settings.scripts.as_deref(), Some(path.as_path()));
+    let scripts = ScriptHooks::load(settings.scripts.as_deref().unwrap()).unwrap();
+    assert!(scripts.has_hook("on_incoming_call"));
+    std::fs::remove_file(&path).unwrap();
+
+    // Without one nothing is loaded
+    let settings: Settings = serde_json::from_str("{}").unwrap();
+    assert_eq!(settings.scripts, None);
+}
@@ -0,0 +1,36 @@
+use webrtc::peer_connection::sdp::sdp_type::RTCSdpType;
+use webrtc::peer_connection::sdp::session_description::RTCSessionDescription;
+use webrtc_client::sdp_hooks::{SdpHooks, SdpStage};
+
+const MINIMAL_SDP: &str = "v=0\r\no=- 0 0 IN IP4 127.0.0.1\r\ns=-\r\nt=0 0\r\n";
+
+#[test]
+fn hooks_see_stage_and_type_and_can_rewrite() {
+    let mut hooks = SdpHooks::default();
+    hooks.add(|stage, sdp_type, sdp| {
+        assert_eq!(stage, SdpStage::BeforeSetRemote);
+        assert_eq!(sdp_type, RTCSdpType::Offer);
+        sdp.session_name = "munged".to_string();
+        Ok(())
+    });
+
+    let offer = RTCSessionDescription::offer(MINIMAL_SDP.to_string()).unwrap();
+    let rewritten = hooks.apply(SdpStage::BeforeSetRemote, offer).unwrap();
+    assert_eq!(rewritten.sdp_type, RTCSdpType::Offer);
+    assert!(rewritten.sdp.contains("s=munged\r\n"), "{}", rewritten.sdp);
+}
+
+#[test]
+fn without_hooks_the_description_is_untouched() {
+    let offer = RTCSessionDescription::offer(MINIMAL_SDP.to_string()).unwrap();
+    let result = SdpHooks::default().apply(SdpStage::BeforeSetLocal, offer).unwrap();
+    assert_eq!(result.sdp, MINIMAL_SDP);
+}
+
+#[test]
+fn a_failing_hook_aborts() {
+    let mut hooks = SdpHooks::default();
+    hooks.add(|_, _, _| Err(anyhow::anyhow!("rejected")));
+    let offer = RTCSessionDescription::offer(MINIMAL_SDP.to_string()).unwrap();
+    assert!(hooks.apply(SdpStage::BeforeSetLocal, offer).is_err());
+}
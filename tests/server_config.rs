@@ -0,0 +1,56 @@
+mod support;
+
+use serde_json::json;
+use support::{engine, wait_for, LoopbackServer};
+use webrtc_client::audio::AudioCodec;
+use webrtc_client::engine::{EngineCommand, EngineEvent};
+use webrtc_client::server_config::ServerConfig;
+use webrtc_client::webrtc::{IceServerConfig, WebRTCConfig};
+
+#[test]
+fn pushed_settings_override_ice_servers_and_narrow_codecs() {
+    let server_config = ServerConfig {
+        ice_servers: vec![IceServerConfig {
+            urls: vec!["turn:turn.example.com:3478".to_string()],
+            username: "1700000000:alice".to_string(),
+            credential: "short-lived".to_string(),
+        }],
+        codec_policy: vec![AudioCodec::Opus, AudioCodec::Pcma],
+        max_bitrate_kbps: Some(32),
+    };
+    let mut config = WebRTCConfig {
+        codec_preferences: vec![AudioCodec::Pcmu, AudioCodec::Pcma, AudioCodec::Opus],
+        max_bitrate_kbps: Some(64),
+        ..Default::default()
+    };
+    server_config.apply(&mut config);
+
+    assert_eq!(config.ice_servers, server_config.ice_servers);
+    assert_eq!(config.codec_preferences, vec![AudioCodec::Pcma, AudioCodec::Opus]);
+    assert_eq!(config.max_bitrate_kbps, Some(32));
+}
+
+#[test]
+fn empty_push_changes_nothing() {
+    let mut config = WebRTCConfig::default();
+    ServerConfig::default().apply(&mut config);
+    assert_eq!(config, WebRTCConfig::default());
+}
+
+#[tokio::test]
+async fn server_config_arrives_at_join() {
+    let server = LoopbackServer::start_with_config(Some(json!({
+        "codec_policy": ["Pcmu"],
+        "max_bitrate_kbps": 24
+    })))
+    .await;
+    let alice = engine(&server, "alice", false);
+    let mut events = alice.subscribe();
+
+    alice.send(EngineCommand::Connect).unwrap();
+    let event = wait_for(&mut events, "server config", |e| matches!(e, EngineEvent::ServerConfig(_))).await;
+    let EngineEvent::ServerConfig(config) = event else { unreachable!() };
+    assert_eq!(config.codec_policy, vec![AudioCodec::Pcmu]);
+    assert_eq!(config.max_bitrate_kbps, Some(24));
+    assert!(config.ice_servers.is_empty());
+}
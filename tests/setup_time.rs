@@ -0,0 +1,71 @@
+mod support;
+
+use std::time::{Duration, Instant};
+use support::{engine, wait_for, LoopbackServer};
+use webrtc_client::engine::{EngineCommand, EngineEvent};
+use webrtc_client::setup_time::{CallSetupTime, Milestone, SetupMarks};
+
+#[test]
+fn phases_are_measured_between_their_milestones() {
+    let requested = Instant::now();
+    let at = |ms: u64| requested + Duration::from_millis(ms);
+    let mut marks = SetupMarks::default();
+    marks.set(Milestone::GatheringStarted, at(5));
+    marks.set(Milestone::OfferSent, at(10));
+    marks.set(Milestone::AnswerReceived, at(130));
+    marks.set(Milestone::GatheringDone, at(205));
+    marks.set(Milestone::IceConnected, at(300));
+    marks.set(Milestone::DtlsConnected, at(340));
+    marks.set(Milestone::FirstRtp, at(360));
+    // Only the first time counts
+    marks.set(Milestone::OfferSent, at(900));
+
+    let before_audio = CallSetupTime::measure(requested, &marks);
+    assert_eq!(before_audio.total, None);
+    assert_eq!(before_audio.signaling_rtt, Some(Duration::from_millis(120)));
+    assert_eq!(before_audio.gathering, Some(Duration::from_millis(200)));
+    assert_eq!(before_audio.dtls, Some(Duration::from_millis(40)));
+    assert_eq!(before_audio.first_rtp, Some(Duration::from_millis(20)));
+
+    marks.set(Milestone::FirstAudio, at(380));
+    let setup = CallSetupTime::measure(requested, &marks);
+    assert_eq!(setup.total, Some(Duration::from_millis(380)));
+    assert!(setup.report().starts_with("Call setup: 380 ms to first audio\n"), "{}", setup.report());
+}
+
+#[tokio::test]
+async fn both_sides_report_their_setup_time_once_audio_arrives() {
+    let server = LoopbackServer::start().await;
+    let alice = engine(&server, "alice", false);
+    let bob = engine(&server, "bob", true);
+    let mut alice_events = alice.subscribe();
+    let mut bob_events = bob.subscribe();
+
+    alice.send(EngineCommand::Connect).unwrap();
+    wait_for(&mut alice_events, "alice connected", |e| matches!(e, EngineEvent::Connected)).await;
+    bob.send(EngineCommand::Connect).unwrap();
+    wait_for(&mut bob_events, "bob connected", |e| matches!(e, EngineEvent::Connected)).await;
+    wait_for(&mut alice_events, "bob in alice's peer list", |e| {
+        matches!(e, EngineEvent::PeerList(peers) if peers.contains(&"bob".to_string()))
+    })
+    .await;
+
+    alice.send(EngineCommand::Call(vec!["bob".to_string()])).unwrap();
+
+    let EngineEvent::SetupTime(caller) = wait_for(&mut alice_events, "alice's setup time", |e| matches!(e, EngineEvent::SetupTime(_))).await else {
+        unreachable!()
+    };
+    assert!(caller.total.is_some());
+    assert!(caller.signaling_rtt.is_some(), "the caller sees its offer answered");
+    assert!(caller.dtls.is_some());
+    assert!(caller.first_rtp.is_some());
+
+    let EngineEvent::SetupTime(callee) = wait_for(&mut bob_events, "bob's setup time", |e| matches!(e, EngineEvent::SetupTime(_))).await else {
+        unreachable!()
+    };
+    assert!(callee.total.is_some());
+    assert_eq!(callee.signaling_rtt, None);
+
+    alice.send(EngineCommand::HangUp).unwrap();
+    wait_for(&mut alice_events, "alice call ended", |e| matches!(e, EngineEvent::CallEnded)).await;
+}
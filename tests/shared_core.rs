@@ -0,0 +1,11 @@
+use webrtc::api::media_engine::{MIME_TYPE_OPUS, MIME_TYPE_PCMA, MIME_TYPE_PCMU};
+use webrtc_client::audio::AudioCodec;
+
+// The browser build names codecs without webrtc-rs; both must agree on the strings
+#[test]
+fn codec_names_match_webrtc_rs() {
+    assert_eq!(AudioCodec::Opus.mime_type(), MIME_TYPE_OPUS);
+    assert_eq!(AudioCodec::Pcmu.mime_type(), MIME_TYPE_PCMU);
+    assert_eq!(AudioCodec::Pcma.mime_type(), MIME_TYPE_PCMA);
+    assert_eq!(AudioCodec::from_mime("audio/OPUS"), Some(AudioCodec::Opus));
+}
@@ -0,0 +1,38 @@
+use webrtc_client::audio::Sidetone;
+
+#[test]
+fn mic_is_mixed_into_every_output_channel_at_the_set_level() {
+    let sidetone = Sidetone::default();
+    sidetone.set_level(0.5);
+    // Stereo capture, downmixed to mono
+    sidetone.push_capture(&[0.2, 0.4, 0.6, 0.6], 2);
+
+    let mut output = vec![0.1; 6];
+    sidetone.mix_into(&mut output, 2);
+    let expected = [0.25, 0.25, 0.4, 0.4, 0.1, 0.1];
+    for (value, expected) in output.iter().zip(expected) {
+        assert!((value - expected).abs() < 1e-6, "{:?}", output);
+    }
+}
+
+#[test]
+fn nothing_is_buffered_or_mixed_while_off() {
+    let sidetone = Sidetone::default();
+    sidetone.push_capture(&[0.5; 8], 1);
+    sidetone.set_level(1.0);
+    let mut output = vec![0.0; 8];
+    sidetone.mix_into(&mut output, 1);
+    assert!(output.iter().all(|&value| value == 0.0));
+}
+
+#[test]
+fn old_samples_are_dropped_to_keep_latency_low() {
+    let sidetone = Sidetone::default();
+    sidetone.set_level(1.0);
+    // A second of audio at 48 kHz arriving while playback stalled
+    sidetone.push_capture(&vec![0.5; 48_000], 1);
+    let mut output = vec![0.0; 48_000];
+    sidetone.mix_into(&mut output, 1);
+    let mixed = output.iter().filter(|&&value| value != 0.0).count();
+    assert!(mixed <= 480, "{} samples of sidetone backlog", mixed);
+}
@@ -0,0 +1,58 @@
+use webrtc_client::discovery::{self, parse_well_known, srv_urls, SrvRecord};
+use webrtc_client::failover::FailoverConfig;
+
+fn record(priority: u16, weight: u16, port: u16, target: &str) -> SrvRecord {
+    SrvRecord {
+        priority,
+        weight,
+        port,
+        target: target.to_string(),
+    }
+}
+
+#[test]
+fn only_entries_without_a_scheme_are_looked_up() {
+    assert!(discovery::is_domain("example.com"));
+    assert!(discovery::is_domain(" example.com "));
+    assert!(!discovery::is_domain("wss://signal.example.com/ws"));
+    assert!(!discovery::is_domain(""));
+}
+
+#[test]
+fn srv_records_are_ordered_by_priority_then_weight() {
+    let records = [
+        record(20, 0, 443, "backup.example.com."),
+        record(10, 10, 8443, "b.example.com."),
+        record(10, 60, 443, "a.example.com."),
+        record(0, 0, 0, "."),
+    ];
+    assert_eq!(
+        srv_urls(&records),
+        ["wss://a.example.com:443", "wss://b.example.com:8443", "wss://backup.example.com:443"]
+    );
+}
+
+#[test]
+fn the_well_known_file_names_the_server_and_its_fallbacks() {
+    let urls = parse_well_known(
+        r#"{"signaling_url": "wss://signal.example.com/ws", "fallback_urls": ["wss://signal2.example.com/ws"]}"#,
+    )
+    .unwrap();
+    assert_eq!(urls, ["wss://signal.example.com/ws", "wss://signal2.example.com/ws"]);
+
+    assert_eq!(parse_well_known(r#"{"signaling_url": "ws://10.0.0.2:8080"}"#).unwrap(), ["ws://10.0.0.2:8080"]);
+    assert_eq!(parse_well_known(r#"{"signaling_url": "https://signal.example.com/wt"}"#).unwrap(), ["https://signal.example.com/wt"]);
+    assert!(parse_well_known(r#"{"signaling_url": "http://example.com"}"#).is_err());
+    assert!(parse_well_known("<html>").is_err());
+}
+
+#[tokio::test]
+async fn a_full_url_is_used_as_configured() {
+    let failover = FailoverConfig {
+        fallback_urls: vec!["wss://b.example.com".to_string()],
+        ..Default::default()
+    };
+    let (url, resolved) = discovery::resolve("wss://a.example.com", &failover).await.unwrap();
+    assert_eq!(url, "wss://a.example.com");
+    assert_eq!(resolved, failover);
+}
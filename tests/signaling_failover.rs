@@ -0,0 +1,94 @@
+mod support;
+
+use std::time::{Duration, Instant};
+use support::{engine_config, wait_for, LoopbackServer};
+use webrtc_client::engine::{CallEngine, EngineCommand, EngineConfig, EngineEvent};
+use webrtc_client::failover::{self, FailoverConfig, SignalingServers};
+use webrtc_client::signaling::ChannelLimits;
+
+const PRIMARY: &str = "wss://a.example.com";
+
+fn failover_to(fallbacks: &[&str]) -> FailoverConfig {
+    FailoverConfig {
+        fallback_urls: fallbacks.iter().map(|url| url.to_string()).collect(),
+        ..Default::default()
+    }
+}
+
+// Nothing listens there any more, so connecting is refused right away
+async fn dead_url() -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    format!("ws://{}", listener.local_addr().unwrap())
+}
+
+#[test]
+fn servers_that_failed_are_tried_last_until_their_hold_down_is_over() {
+    let config = failover_to(&["wss://b.example.com", PRIMARY, " ", "wss://c.example.com"]);
+    let mut servers = SignalingServers::new(PRIMARY, &config);
+    let now = Instant::now();
+    assert_eq!(servers.candidates(now), ["wss://a.example.com", "wss://b.example.com", "wss://c.example.com"]);
+
+    servers.mark_down(PRIMARY, now);
+    assert!(servers.is_down(PRIMARY, now));
+    assert_eq!(servers.candidates(now), ["wss://b.example.com", "wss://c.example.com", "wss://a.example.com"]);
+
+    let later = now + config.hold_down() + Duration::from_secs(1);
+    assert_eq!(servers.candidates(later)[0], PRIMARY);
+}
+
+#[test]
+fn the_server_that_worked_last_is_kept_when_sticky() {
+    let mut config = failover_to(&["wss://b.example.com"]);
+    let mut servers = SignalingServers::new(PRIMARY, &config);
+    let now = Instant::now();
+    servers.mark_down(PRIMARY, now);
+    servers.mark_up("wss://b.example.com");
+
+    // The primary is back, but we stay where we are
+    servers.apply_health(&[(PRIMARY.to_string(), true), ("wss://b.example.com".to_string(), true)], now);
+    assert_eq!(servers.current(), Some("wss://b.example.com"));
+    assert_eq!(servers.candidates(now)[0], "wss://b.example.com");
+
+    config.sticky = false;
+    servers.configure(PRIMARY, &config);
+    assert_eq!(servers.candidates(now)[0], PRIMARY);
+
+    // Switching to another deployment forgets the old one
+    servers.configure("wss://other.example.com", &FailoverConfig::default());
+    assert_eq!(servers.current(), None);
+    assert_eq!(servers.urls(), ["wss://other.example.com"]);
+}
+
+#[tokio::test]
+async fn an_unreachable_primary_falls_over_to_the_next_server() {
+    let server = LoopbackServer::start().await;
+    let dead = dead_url().await;
+    let mut servers = SignalingServers::new(&dead, &failover_to(&[&server.url]));
+
+    let (_client, url) = failover::connect_any(&mut servers, None, ChannelLimits::default()).await.unwrap();
+    assert_eq!(url, server.url);
+    assert!(servers.is_down(&dead, Instant::now()));
+    assert_eq!(servers.current(), Some(server.url.as_str()));
+
+    let health = failover::check_health(servers.urls(), Duration::from_secs(1)).await;
+    assert_eq!(health, [(dead, false), (server.url.clone(), true)]);
+}
+
+#[tokio::test]
+async fn the_engine_moves_to_a_fallback_when_its_server_goes_away() {
+    let primary = LoopbackServer::start().await;
+    let fallback = LoopbackServer::start().await;
+    let alice = CallEngine::spawn(EngineConfig {
+        signaling_failover: failover_to(&[&fallback.url]),
+        ..engine_config(&primary, "alice", false)
+    });
+    let mut events = alice.subscribe();
+
+    alice.send(EngineCommand::Connect).unwrap();
+    wait_for(&mut events, "connected to the primary", |e| matches!(e, EngineEvent::Connected)).await;
+
+    primary.stop().await;
+    wait_for(&mut events, "primary gone", |e| matches!(e, EngineEvent::Disconnected)).await;
+    wait_for(&mut events, "connected to the fallback", |e| matches!(e, EngineEvent::Connected)).await;
+    alice.shutdown().await;
+}
@@ -0,0 +1,167 @@
+use serde_json::Value;
+use std::fs;
+use std::path::PathBuf;
+use webrtc_client::signaling::{check_protocol_version, SignalingMessage, PROTOCOL_VERSION};
+
+// One representative of every variant. variant_name() has no wildcard arm, so adding a
+// variant without a sample and golden file fails to compile here.
+fn samples() -> Vec<SignalingMessage> {
+    use SignalingMessage::*;
+    vec![
+        Join { room_id: "room-1".into(), peer_id: "alice".into(), protocol_version: PROTOCOL_VERSION },
+        Disconnect { room_id: "room-1".into(), peer_id: "alice".into() },
+        PeerList { peers: vec!["alice".into(), "bob".into()] },
+        Offer {
+            room_id: "room-1".into(),
+            sdp: r#"{"type":"offer","sdp":"v=0"}"#.into(),
+            from_peer: "alice".into(),
+            to_peer: "bob".into(),
+        },
+        Answer {
+            room_id: "room-1".into(),
+            sdp: r#"{"type":"answer","sdp":"v=0"}"#.into(),
+            from_peer: "bob".into(),
+            to_peer: "alice".into(),
+        },
+        IceCandidate {
+            room_id: "room-1".into(),
+            candidate: "candidate:1 1 udp 2130706431 192.0.2.1 50000 typ host".into(),
+            from_peer: "alice".into(),
+            to_peer: "bob".into(),
+        },
+        RequestPeerList,
+        InitiateCall { peer_id: "bob".into(), room_id: "room-1".into() },
+        MediaError {
+            error_type: "NoInputDevice".into(),
+            description: "No input device available".into(),
+            peer_id: "alice".into(),
+        },
+        EndCall { room_id: "room-1".into(), peer_id: "alice".into(), to_peers: None },
+        CallRequest {
+            room_id: "room-1".into(),
+            from_peer: "alice".into(),
+            to_peers: vec!["bob".into(), "carol".into()],
+            protocol_version: PROTOCOL_VERSION,
+        },
+        CallResponse { room_id: "room-1".into(), from_peer: "bob".into(), to_peer: "alice".into(), accepted: true },
+        Error { message: "Room is full".into() },
+        ConnectionLost { peer_id: "bob".into() },
+        ChatMessage { room_id: "room-1".into(), from_peer: "alice".into(), text: "hello".into() },
+        ProtocolMismatch {
+            from_peer: "bob".into(),
+            to_peer: "alice".into(),
+            protocol_version: PROTOCOL_VERSION,
+            min_protocol_version: PROTOCOL_VERSION,
+        },
+        TrackInfo {
+            room_id: "room-1".into(),
+            from_peer: "relay".into(),
+            to_peer: "alice".into(),
+            stream_id: "relay-stream-2".into(),
+            peer_id: "bob".into(),
+        },
+        Transfer { room_id: "room-1".into(), from_peer: "alice".into(), to_peer: "bob".into(), target: "carol".into() },
+        MergeCalls {
+            room_id: "room-1".into(),
+            from_peer: "alice".into(),
+            to_peers: vec!["bob".into(), "carol".into()],
+            target_room: "room-1-conference-0000002a".into(),
+        },
+    ]
+}
+
+fn variant_name(msg: &SignalingMessage) -> &'static str {
+    use SignalingMessage::*;
+    match msg {
+        Join { .. } => "Join",
+        Disconnect { .. } => "Disconnect",
+        PeerList { .. } => "PeerList",
+        Offer { .. } => "Offer",
+        Answer { .. } => "Answer",
+        IceCandidate { .. } => "IceCandidate",
+        RequestPeerList => "RequestPeerList",
+        InitiateCall { .. } => "InitiateCall",
+        MediaError { .. } => "MediaError",
+        EndCall { .. } => "EndCall",
+        CallRequest { .. } => "CallRequest",
+        CallResponse { .. } => "CallResponse",
+        Error { .. } => "Error",
+        ConnectionLost { .. } => "ConnectionLost",
+        ChatMessage { .. } => "ChatMessage",
+        ProtocolMismatch { .. } => "ProtocolMismatch",
+        TrackInfo { .. } => "TrackInfo",
+        Transfer { .. } => "Transfer",
+        MergeCalls { .. } => "MergeCalls",
+    }
+}
+
+fn golden_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden/signaling")
+        .join(format!("{}.json", name))
+}
+
+#[test]
+fn every_variant_round_trips() {
+    for msg in samples() {
+        let json = serde_json::to_string(&msg).unwrap();
+        let parsed: SignalingMessage = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, msg, "round trip changed {}", variant_name(&msg));
+    }
+}
+
+// Set UPDATE_GOLDEN=1 to rewrite the files after an intentional protocol change
+#[test]
+fn every_variant_matches_golden_file() {
+    let update = std::env::var_os("UPDATE_GOLDEN").is_some();
+
+    for msg in samples() {
+        let name = variant_name(&msg);
+        let path = golden_path(name);
+        let actual = serde_json::to_value(&msg).unwrap();
+
+        if update {
+            fs::write(&path, serde_json::to_string_pretty(&actual).unwrap() + "\n").unwrap();
+            continue;
+        }
+
+        let contents = fs::read_to_string(&path)
+            .unwrap_or_else(|_| panic!("missing golden file {}", path.display()));
+        let expected: Value = serde_json::from_str(&contents).unwrap();
+        assert_eq!(actual, expected, "wire format of {} changed", name);
+
+        let parsed: SignalingMessage = serde_json::from_str(&contents).unwrap();
+        assert_eq!(parsed, msg, "golden {} no longer parses to the same message", name);
+    }
+}
+
+#[test]
+fn every_golden_file_has_a_variant() {
+    let names: Vec<&str> = samples().iter().map(variant_name).collect();
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden/signaling");
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        let stem = path.file_stem().unwrap().to_string_lossy().to_string();
+        assert!(names.contains(&stem.as_str()), "stale golden file {}", path.display());
+    }
+}
+
+#[test]
+fn pre_versioning_messages_parse_as_version_zero_and_are_rejected() {
+    let legacy = r#"{"message_type":"CallRequest","room_id":"room-1","from_peer":"old","to_peers":["bob"]}"#;
+    let msg: SignalingMessage = serde_json::from_str(legacy).unwrap();
+    let version = match msg {
+        SignalingMessage::CallRequest { protocol_version, .. } => protocol_version,
+        other => panic!("unexpected message {:?}", other),
+    };
+    assert_eq!(version, 0);
+
+    let err = check_protocol_version("old", version).unwrap_err().to_string();
+    assert!(err.contains("v0"), "error should name the peer's version: {}", err);
+    assert!(err.contains("update"), "error should tell the user what to do: {}", err);
+}
+
+#[test]
+fn current_version_is_accepted() {
+    assert!(check_protocol_version("bob", PROTOCOL_VERSION).is_ok());
+}
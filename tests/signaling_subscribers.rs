@@ -0,0 +1,143 @@
+mod support;
+
+use futures_util::StreamExt;
+use std::time::Duration;
+use support::LoopbackServer;
+use tokio::time::timeout;
+use webrtc_client::signaling::{ChannelLimits, SignalingClient, SignalingMessage, PROTOCOL_VERSION};
+
+fn is_peer_list(msg: &SignalingMessage) -> bool {
+    matches!(msg, SignalingMessage::PeerList { peers } if peers.contains(&"alice".to_string()))
+}
+
+#[tokio::test]
+async fn every_subscriber_sees_every_message_until_the_socket_closes() {
+    let server = LoopbackServer::start().await;
+    let mut client = SignalingClient::connect(&server.url).await.unwrap();
+    let mut logger = client.subscribe();
+    let mut ui = Box::pin(client.messages());
+
+    client
+        .send(SignalingMessage::Join {
+            room_id: "test-room".into(),
+            peer_id: "alice".into(),
+            protocol_version: PROTOCOL_VERSION,
+        })
+        .await
+        .unwrap();
+
+    let wait = Duration::from_secs(5);
+    let owner = timeout(wait, client.receive()).await.unwrap().unwrap().unwrap();
+    assert!(is_peer_list(&owner));
+    assert!(is_peer_list(&timeout(wait, logger.recv()).await.unwrap().unwrap()));
+    assert!(is_peer_list(&timeout(wait, ui.next()).await.unwrap().unwrap()));
+
+    server.stop().await;
+    assert_eq!(timeout(wait, client.receive()).await.unwrap().unwrap(), None);
+    assert_eq!(timeout(wait, ui.next()).await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn a_candidate_flood_drops_old_candidates_but_never_the_answer() {
+    let server = LoopbackServer::start().await;
+    let limits = ChannelLimits { outgoing: 4, incoming: 4 };
+    let mut alice = SignalingClient::connect_with_options(&server.url, None, limits).await.unwrap();
+    let mut bob = SignalingClient::connect_with_options(&server.url, None, limits).await.unwrap();
+    for (client, peer_id) in [(&mut alice, "alice"), (&mut bob, "bob")] {
+        client
+            .send(SignalingMessage::Join {
+                room_id: "test-room".into(),
+                peer_id: peer_id.into(),
+                protocol_version: PROTOCOL_VERSION,
+            })
+            .await
+            .unwrap();
+    }
+    while !matches!(alice.receive().await, Some(SignalingMessage::PeerList { peers }) if peers.len() == 2) {}
+
+    // Queued faster than the writer gets to run, so the outgoing queue overflows
+    for i in 0..50 {
+        alice
+            .send(SignalingMessage::IceCandidate {
+                room_id: "test-room".into(),
+                candidate: format!("candidate-{}", i),
+                from_peer: "alice".into(),
+                to_peer: "bob".into(),
+            })
+            .await
+            .unwrap();
+    }
+    alice
+        .send(SignalingMessage::Answer {
+            room_id: "test-room".into(),
+            sdp: "answer".into(),
+            from_peer: "alice".into(),
+            to_peer: "bob".into(),
+        })
+        .await
+        .unwrap();
+
+    let candidates = timeout(Duration::from_secs(5), async {
+        let mut candidates = Vec::new();
+        loop {
+            match bob.receive().await.unwrap() {
+                Some(SignalingMessage::IceCandidate { candidate, .. }) => candidates.push(candidate),
+                Some(SignalingMessage::Answer { .. }) => return candidates,
+                Some(_) => {}
+                None => panic!("connection closed before the answer"),
+            }
+        }
+    })
+    .await
+    .expect("the answer never arrived");
+    assert!(candidates.len() < 50);
+    // Drop-oldest: whatever got through includes the last one
+    assert_eq!(candidates.last().map(String::as_str), Some("candidate-49"));
+    assert_eq!(bob.dropped().incoming + alice.dropped().outgoing, 50 - candidates.len() as u64);
+}
+
+#[tokio::test]
+async fn split_senders_share_the_connection_until_the_last_is_dropped() {
+    let server = LoopbackServer::start().await;
+    let (alice, mut alice_rx) = SignalingClient::connect(&server.url).await.unwrap().split();
+    let mut bob = SignalingClient::connect(&server.url).await.unwrap();
+    let join = |peer_id: &str| SignalingMessage::Join {
+        room_id: "test-room".into(),
+        peer_id: peer_id.into(),
+        protocol_version: PROTOCOL_VERSION,
+    };
+    alice.send(join("alice")).unwrap();
+    bob.send(join("bob")).await.unwrap();
+    let wait = Duration::from_secs(5);
+    timeout(wait, async {
+        while !matches!(alice_rx.receive().await.unwrap(), Some(SignalingMessage::PeerList { peers }) if peers.len() == 2) {}
+    })
+    .await
+    .expect("bob never joined");
+
+    // From another task, without &mut or a lock
+    let sender = alice.clone();
+    tokio::spawn(async move {
+        sender
+            .send(SignalingMessage::ChatMessage {
+                room_id: "test-room".into(),
+                from_peer: "alice".into(),
+                text: "hello".into(),
+            })
+            .unwrap();
+    })
+    .await
+    .unwrap();
+    timeout(wait, async {
+        while !matches!(bob.receive().await.unwrap(), Some(SignalingMessage::ChatMessage { text, .. }) if text == "hello") {}
+    })
+    .await
+    .expect("the chat never arrived");
+
+    drop(alice);
+    timeout(wait, async {
+        while !matches!(bob.receive().await.unwrap(), Some(SignalingMessage::PeerList { peers }) if peers == ["bob"]) {}
+    })
+    .await
+    .expect("alice's connection stayed open");
+}
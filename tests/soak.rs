@@ -0,0 +1,49 @@
+use std::time::Duration;
+use webrtc_client::soak::{self, ResourceSample, SoakConfig, SoakReport};
+use webrtc_client::webrtc::WebRTCConfig;
+
+fn sample(tasks: usize) -> ResourceSample {
+    ResourceSample {
+        resident_bytes: Some(80 << 20),
+        open_files: Some(40),
+        tasks,
+    }
+}
+
+#[test]
+fn only_tasks_beyond_the_slack_count_as_a_leak() {
+    let mut report = SoakReport {
+        calls: 360,
+        failures: 1,
+        elapsed: Duration::from_secs(3723),
+        baseline: sample(10),
+        last: sample(12),
+    };
+    assert!(!report.is_leaking());
+    assert_eq!(
+        report.summary(),
+        "360 calls (1 failed) in 01:02:03, rss 80.0 MB (+0.0), fds 40 (+0), tasks 12 (+2)"
+    );
+
+    report.last = sample(30);
+    assert_eq!(report.leaked_tasks(), 20);
+    assert!(report.is_leaking());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn calls_are_made_and_torn_down_until_time_is_up() {
+    let config = SoakConfig {
+        duration: Duration::from_secs(3),
+        call_length: Duration::from_millis(500),
+        pause: Duration::from_millis(200),
+        report_every: Duration::ZERO,
+    };
+    let mut progress = Vec::new();
+    let report = soak::run(&config, &WebRTCConfig::default(), |report| progress.push(report.calls))
+        .await
+        .unwrap();
+
+    assert!(report.calls >= 2, "{}", report.summary());
+    assert_eq!(report.failures, 0);
+    assert_eq!(progress, (1..=report.calls).collect::<Vec<_>>());
+}
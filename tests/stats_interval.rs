@@ -0,0 +1,34 @@
+use std::sync::Arc;
+use std::time::Duration;
+use webrtc_client::audio::MockBackend;
+use webrtc_client::metrics::StatsConfig;
+use webrtc_client::webrtc::{WebRTCClient, WebRTCConfig};
+
+#[test]
+fn the_interval_has_a_floor() {
+    assert_eq!(StatsConfig::default().interval(), Duration::from_secs(1));
+    let config = StatsConfig { interval_ms: 10, ..Default::default() };
+    assert_eq!(config.interval(), Duration::from_millis(250));
+
+    // Config files from before the setting
+    let old: WebRTCConfig = serde_json::from_str(r#"{"music_mode": false}"#).unwrap();
+    assert_eq!(old.stats, StatsConfig::default());
+}
+
+#[tokio::test]
+async fn a_paused_monitor_keeps_taking_readings() {
+    let config = WebRTCConfig {
+        stats: StatsConfig { interval_ms: 250, ..Default::default() },
+        ..Default::default()
+    };
+    let client = WebRTCClient::with_config(&config, Arc::new(MockBackend::default())).await.unwrap();
+    assert_eq!(client.quality_monitor.interval(), Duration::from_millis(250));
+
+    // Only the panels stop; the ladder and the watchdogs still need the readings
+    client.quality_monitor.set_paused(true);
+    client.quality_monitor.start_monitoring().await;
+    tokio::time::sleep(Duration::from_millis(800)).await;
+    assert!(client.quality_monitor.is_paused());
+    assert!(!client.quality_monitor.history().is_empty());
+    assert!(client.quality_monitor.get_current_stats().await.is_some());
+}
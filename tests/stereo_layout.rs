@@ -0,0 +1,53 @@
+use webrtc_client::audio::{pan_to_stereo, StereoLayout};
+
+#[test]
+fn peers_are_spread_evenly_in_the_order_they_joined() {
+    let mut layout = StereoLayout::new(true);
+    layout.add_peer("alice");
+    assert_eq!(layout.pan("alice"), Some(0.0));
+
+    layout.add_peer("bob");
+    layout.add_peer("carol");
+    assert_eq!(layout.pan("alice"), Some(-0.8));
+    assert_eq!(layout.pan("bob"), Some(0.0));
+    assert_eq!(layout.pan("carol"), Some(0.8));
+    assert_eq!(layout.pan("dave"), None);
+}
+
+#[test]
+fn placing_a_peer_overrides_the_automatic_layout() {
+    let mut layout = StereoLayout::new(true);
+    layout.add_peer("alice");
+    layout.add_peer("bob");
+    layout.set_pan("bob", -3.0);
+    assert_eq!(layout.pan("bob"), Some(-1.0));
+
+    layout.set_enabled(false);
+    assert_eq!(layout.pan("bob"), None);
+}
+
+#[test]
+fn panning_keeps_constant_power() {
+    for pan in [-1.0, -0.4, 0.0, 0.7, 1.0] {
+        let stereo = pan_to_stereo(&[0.5], pan);
+        let power = stereo[0] * stereo[0] + stereo[1] * stereo[1];
+        assert!((power - 0.25).abs() < 1e-6, "pan {} gave {:?}", pan, stereo);
+    }
+    let hard_left = pan_to_stereo(&[0.5, -0.5], -1.0);
+    assert!((hard_left[0] - 0.5).abs() < 1e-6 && hard_left[1].abs() < 1e-6);
+    assert!((hard_left[2] + 0.5).abs() < 1e-6 && hard_left[3].abs() < 1e-6);
+}
+
+#[test]
+fn a_departed_peer_frees_their_place() {
+    let mut layout = StereoLayout::new(true);
+    layout.add_peer("alice");
+    layout.add_peer("bob");
+    layout.add_peer("carol");
+    layout.set_pan("carol", 0.5);
+
+    layout.remove_peer("bob");
+    assert_eq!(layout.pan("bob"), None);
+    assert_eq!(layout.pan("alice"), Some(-0.8));
+    assert_eq!(layout.pan("carol"), Some(0.5));
+}
@@ -0,0 +1,75 @@
+use std::time::{Duration, SystemTime};
+use webrtc_client::chat::ChatEntry;
+use webrtc_client::contacts::ContactBook;
+use webrtc_client::storage::{CallDirection, CallRecord, Storage};
+
+fn call(room_id: &str, answered: bool) -> CallRecord {
+    CallRecord {
+        room_id: room_id.to_string(),
+        participants: vec!["bob".to_string()],
+        direction: CallDirection::Incoming,
+        answered,
+        started_at: SystemTime::now(),
+        duration: Duration::from_secs(42),
+    }
+}
+
+#[test]
+fn chat_history_is_kept_per_room_oldest_first() {
+    let storage = Storage::open_in_memory().unwrap();
+    for text in ["one", "two", "three"] {
+        storage.add_chat("room-a", &ChatEntry::new("alice".into(), text.into())).unwrap();
+    }
+    storage.add_chat("room-b", &ChatEntry::new("bob".into(), "elsewhere".into())).unwrap();
+
+    let texts: Vec<String> = storage.chat_history("room-a", 2).unwrap().into_iter().map(|e| e.text).collect();
+    assert_eq!(texts, vec!["two", "three"]);
+}
+
+#[test]
+fn history_survives_reopening_the_database() {
+    let dir = std::env::temp_dir().join(format!("webrtc-client-storage-{}", rand::random::<u32>()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("history.db");
+
+    {
+        let mut storage = Storage::open(&path).unwrap();
+        storage.add_call(&call("room", false)).unwrap();
+        let mut book = ContactBook::default();
+        book.add("Bob".into(), "bob".into());
+        book.toggle_favorite("bob");
+        storage.save_contacts(&book).unwrap();
+    }
+
+    let storage = Storage::open(&path).unwrap();
+    let calls = storage.recent_calls(10).unwrap();
+    assert_eq!(calls.len(), 1);
+    assert!(!calls[0].answered);
+    assert_eq!(calls[0].duration, Duration::from_secs(42));
+    let contacts = storage.load_contacts().unwrap();
+    assert_eq!(contacts.name_for("bob"), Some("Bob"));
+    assert!(contacts.contacts()[0].favorite);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn purge_clears_history_but_keeps_contacts() {
+    let mut storage = Storage::open_in_memory().unwrap();
+    storage.add_chat("room", &ChatEntry::new("alice".into(), "hi".into())).unwrap();
+    storage.add_call(&call("room", true)).unwrap();
+    let mut book = ContactBook::default();
+    book.add("Bob".into(), "bob".into());
+    storage.save_contacts(&book).unwrap();
+
+    storage.purge_history().unwrap();
+    assert!(storage.chat_history("room", 10).unwrap().is_empty());
+    assert!(storage.recent_calls(10).unwrap().is_empty());
+    assert_eq!(storage.load_contacts().unwrap().contacts().len(), 1);
+}
+
+#[test]
+fn migrations_bring_a_fresh_database_to_the_latest_schema() {
+    let storage = Storage::open_in_memory().unwrap();
+    assert_eq!(storage.schema_version().unwrap(), 3);
+}
@@ -0,0 +1,227 @@
+use futures_util::{SinkExt, StreamExt};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{broadcast, mpsc, Mutex};
+use tokio::task::JoinHandle;
+use tokio::time::timeout;
+use tokio_tungstenite::accept_async;
+use tokio_tungstenite::tungstenite::Message;
+use webrtc_client::audio::{AudioBackendKind, OutputRouting};
+use webrtc_client::engine::{CallEngine, EngineConfig, EngineEvent, EngineHandle};
+use webrtc_client::failover::FailoverConfig;
+use webrtc_client::presence::IdleConfig;
+use webrtc_client::reconnect::ReconnectPolicy;
+use webrtc_client::signaling::ChannelLimits;
+use webrtc_client::transcription::TranscriptionConfig;
+use webrtc_client::webrtc::WebRTCConfig;
+
+type Peers = Arc<Mutex<HashMap<String, (String, mpsc::UnboundedSender<String>)>>>;
+
+// Minimal in-process signaling server: rooms, peer lists, and to_peer routing
+pub struct LoopbackServer {
+    pub url: String,
+    task: JoinHandle<()>,
+    peers: Peers,
+    connections: Arc<Mutex<Vec<JoinHandle<()>>>>,
+}
+
+impl LoopbackServer {
+    pub async fn start() -> Self {
+        Self::start_with_config(None).await
+    }
+
+    // Sends `config` as a ServerConfig message to every peer right after it joins
+    pub async fn start_with_config(config: Option<Value>) -> Self {
+        Self::start_with(config, None).await
+    }
+
+    // Designates `relay` as the media relay of every room, announced at join
+    pub async fn start_with_relay(relay: &str) -> Self {
+        Self::start_with(None, Some(relay.to_string())).await
+    }
+
+    async fn start_with(config: Option<Value>, relay: Option<String>) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind loopback server");
+        let addr: SocketAddr = listener.local_addr().unwrap();
+        let peers: Peers = Arc::new(Mutex::new(HashMap::new()));
+        let connections = Arc::new(Mutex::new(Vec::new()));
+
+        let task = tokio::spawn({
+            let peers = peers.clone();
+            let connections = connections.clone();
+            async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    let connection = tokio::spawn(handle_connection(stream, peers.clone(), config.clone(), relay.clone()));
+                    connections.lock().await.push(connection);
+                }
+            }
+        });
+
+        Self {
+            url: format!("ws://{}", addr),
+            task,
+            peers,
+            connections,
+        }
+    }
+
+    // Goes away like a crashed server: every client's socket closes and nobody can
+    // connect again
+    pub async fn stop(&self) {
+        self.task.abort();
+        for connection in self.connections.lock().await.drain(..) {
+            connection.abort();
+        }
+        // Ends the writer tasks, which hold the other half of each socket
+        self.peers.lock().await.clear();
+    }
+}
+
+impl Drop for LoopbackServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn handle_connection(stream: TcpStream, peers: Peers, config: Option<Value>, relay: Option<String>) {
+    let Ok(ws) = accept_async(stream).await else { return };
+    let (mut write, mut read) = ws.split();
+    let (tx, mut rx) = mpsc::unbounded_channel::<String>();
+
+    tokio::spawn(async move {
+        while let Some(text) = rx.recv().await {
+            if write.send(Message::Text(text)).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut own_id: Option<String> = None;
+    while let Some(Ok(msg)) = read.next().await {
+        let Ok(text) = msg.to_text() else { continue };
+        let Ok(value) = serde_json::from_str::<Value>(text) else { continue };
+
+        if value["message_type"] == "Join" {
+            let room = value["room_id"].as_str().unwrap_or_default().to_string();
+            let peer = value["peer_id"].as_str().unwrap_or_default().to_string();
+            peers.lock().await.insert(peer.clone(), (room.clone(), tx.clone()));
+            own_id = Some(peer);
+            if let Some(ref config) = config {
+                let msg = serde_json::json!({ "message_type": "ServerConfig", "config": config });
+                let _ = tx.send(msg.to_string());
+            }
+            if let Some(ref relay) = relay {
+                let msg = serde_json::json!({ "message_type": "MediaRelay", "room_id": room, "relay_peer": relay });
+                let _ = tx.send(msg.to_string());
+            }
+            broadcast_peer_list(&peers, &room).await;
+            continue;
+        }
+
+        let peers = peers.lock().await;
+        let targets: Vec<String> = if let Some(to) = value["to_peer"].as_str() {
+            vec![to.to_string()]
+        } else if let Some(to) = value["to_peers"].as_array() {
+            to.iter().filter_map(|p| p.as_str().map(str::to_string)).collect()
+        } else {
+            let room = own_id.as_ref().and_then(|id| peers.get(id)).map(|(room, _)| room.clone());
+            peers
+                .iter()
+                .filter(|(id, (r, _))| Some(*id) != own_id.as_ref() && Some(r) == room.as_ref())
+                .map(|(id, _)| id.clone())
+                .collect()
+        };
+        for target in targets {
+            if let Some((_, sender)) = peers.get(&target) {
+                let _ = sender.send(text.to_string());
+            }
+        }
+    }
+
+    // A client that rejoined from a new connection has already replaced this entry
+    if let Some(id) = own_id {
+        let mut guard = peers.lock().await;
+        let room = match guard.get(&id) {
+            Some((_, sender)) if sender.same_channel(&tx) => guard.remove(&id).map(|(room, _)| room),
+            _ => None,
+        };
+        drop(guard);
+        if let Some(room) = room {
+            broadcast_peer_list(&peers, &room).await;
+        }
+    }
+}
+
+async fn broadcast_peer_list(peers: &Peers, room: &str) {
+    let peers = peers.lock().await;
+    let in_room: Vec<&String> = peers.iter().filter(|(_, (r, _))| r == room).map(|(id, _)| id).collect();
+    let msg = serde_json::json!({ "message_type": "PeerList", "peers": in_room }).to_string();
+    for (_, (r, sender)) in peers.iter() {
+        if r == room {
+            let _ = sender.send(msg.clone());
+        }
+    }
+}
+
+pub fn engine(server: &LoopbackServer, peer_id: &str, auto_answer: bool) -> EngineHandle {
+    engine_with_config(server, peer_id, auto_answer, WebRTCConfig::default())
+}
+
+pub fn engine_with_config(server: &LoopbackServer, peer_id: &str, auto_answer: bool, webrtc: WebRTCConfig) -> EngineHandle {
+    CallEngine::spawn(EngineConfig {
+        webrtc,
+        ..engine_config(server, peer_id, auto_answer)
+    })
+}
+
+pub fn engine_config(server: &LoopbackServer, peer_id: &str, auto_answer: bool) -> EngineConfig {
+    EngineConfig {
+        signaling_url: server.url.clone(),
+        signaling_token: None,
+        room_id: "test-room".to_string(),
+        peer_id: peer_id.to_string(),
+        identity: None,
+        auto_answer,
+        auto_answer_allowlist: Vec::new(),
+        // Sine generator capture, counting sink playback: no sound hardware needed
+        audio_backend: AudioBackendKind::Mock,
+        audio_buffer_frames: None,
+        sidetone_level: 0.0,
+        playback_delay_ms: 0,
+        output_routing: OutputRouting::default(),
+        capture_source: None,
+        recording: None,
+        transcription: TranscriptionConfig::default(),
+        // Nobody is at the keyboard
+        idle: IdleConfig { away_after_mins: 0, leave_empty_room_after_mins: 0 },
+        scripts: None,
+        webrtc: WebRTCConfig::default(),
+        reconnect: ReconnectPolicy::default(),
+        signaling_channels: ChannelLimits::default(),
+        signaling_failover: FailoverConfig::default(),
+        // Tests drive network changes through EngineCommand::NetworkChanged
+        watch_network: false,
+    }
+}
+
+pub async fn wait_for<F>(events: &mut broadcast::Receiver<EngineEvent>, what: &str, mut matches: F) -> EngineEvent
+where
+    F: FnMut(&EngineEvent) -> bool,
+{
+    timeout(Duration::from_secs(15), async {
+        loop {
+            match events.recv().await {
+                Ok(event) if matches(&event) => return event,
+                Ok(EngineEvent::Error(e)) => panic!("engine error while waiting for {}: {}", what, e),
+                Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(e) => panic!("event stream closed while waiting for {}: {}", what, e),
+            }
+        }
+    })
+    .await
+    .unwrap_or_else(|_| panic!("timed out waiting for {}", what))
+}
@@ -0,0 +1,63 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use webrtc_client::audio::MockBackend;
+use webrtc_client::tasks::TaskGroup;
+use webrtc_client::webrtc::{WebRTCClient, WebRTCConfig};
+
+async fn until_empty(tasks: &TaskGroup) {
+    tokio::time::timeout(Duration::from_secs(2), async {
+        while !tasks.is_empty() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("tasks still running");
+}
+
+#[tokio::test]
+async fn shutdown_stops_every_task_and_runs_their_cleanup() {
+    let tasks = TaskGroup::new();
+    tasks.spawn(std::future::pending());
+    let cleaned_up = Arc::new(AtomicBool::new(false));
+    let flag = cleaned_up.clone();
+    tasks.spawn_with_cleanup(|cancel| async move {
+        cancel.cancelled().await;
+        flag.store(true, Ordering::Relaxed);
+    });
+    assert_eq!(tasks.len(), 2);
+
+    assert!(tasks.shutdown(Duration::from_secs(1)).await);
+    assert!(tasks.is_empty());
+    assert!(cleaned_up.load(Ordering::Relaxed));
+}
+
+#[tokio::test]
+async fn a_task_that_ignores_cancellation_is_not_waited_for() {
+    let tasks = TaskGroup::new();
+    tasks.spawn_with_cleanup(|_| tokio::time::sleep(Duration::from_secs(10)));
+    assert!(!tasks.shutdown(Duration::from_millis(100)).await);
+    assert_eq!(tasks.len(), 1);
+}
+
+#[tokio::test]
+async fn closing_a_client_stops_its_stats_loop() {
+    let client = WebRTCClient::with_config(&WebRTCConfig::default(), Arc::new(MockBackend::default())).await.unwrap();
+    client.quality_monitor.start_monitoring().await;
+    client.tasks().spawn(std::future::pending());
+    assert!(client.tasks().len() >= 2);
+
+    client.close().await;
+    assert!(client.tasks().is_empty());
+}
+
+#[tokio::test]
+async fn dropping_a_client_stops_its_tasks() {
+    let client = WebRTCClient::with_config(&WebRTCConfig::default(), Arc::new(MockBackend::default())).await.unwrap();
+    client.quality_monitor.start_monitoring().await;
+    let tasks = client.tasks().clone();
+    assert!(!tasks.is_empty());
+
+    drop(client);
+    until_empty(&tasks).await;
+}
@@ -0,0 +1,22 @@
+use webrtc_client::room::TrackOwners;
+
+#[test]
+fn streams_belong_to_the_peer_they_are_named_after() {
+    let owners = TrackOwners::default();
+    assert_eq!(owners.peer_for("alice"), "alice");
+}
+
+#[test]
+fn announced_streams_belong_to_their_owner() {
+    let mut owners = TrackOwners::default();
+    owners.assign("relay-stream-1", "alice");
+    owners.assign("relay-stream-2", "bob");
+    assert_eq!(owners.peer_for("relay-stream-2"), "bob");
+
+    // The relay reused the stream for someone else
+    owners.assign("relay-stream-2", "carol");
+    assert_eq!(owners.peer_for("relay-stream-2"), "carol");
+
+    owners.clear();
+    assert_eq!(owners.peer_for("relay-stream-1"), "relay-stream-1");
+}
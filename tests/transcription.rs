@@ -0,0 +1,101 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::time::timeout;
+use webrtc_client::audio::tap::{TapFrame, TapSource};
+use webrtc_client::transcription::{wav_bytes, Segmenter, SttBackend, Transcriber};
+
+// 20 ms at 48 kHz
+fn frame(source: TapSource, level: f32) -> TapFrame {
+    TapFrame {
+        source,
+        sample_rate: 48_000,
+        samples: vec![level; 960].into(),
+    }
+}
+
+fn bob() -> TapSource {
+    TapSource::Remote("bob".to_string())
+}
+
+// Reports how long each utterance was instead of recognizing anything
+struct CountingStt;
+
+#[async_trait]
+impl SttBackend for CountingStt {
+    fn name(&self) -> &'static str {
+        "Counting"
+    }
+
+    async fn transcribe(&self, samples: &[f32], sample_rate: u32) -> Result<String> {
+        Ok(format!("{} ms", samples.len() as u32 * 1000 / sample_rate))
+    }
+}
+
+#[test]
+fn a_pause_after_speech_ends_the_utterance() {
+    let mut segmenter = Segmenter::default();
+    for _ in 0..50 {
+        assert!(segmenter.push(&frame(bob(), 0.1)).is_none());
+    }
+    let utterance = (0..40)
+        .find_map(|_| segmenter.push(&frame(bob(), 0.0)))
+        .expect("silence should close the utterance");
+    assert_eq!(utterance.source, bob());
+    // One second of speech plus the pause that ended it
+    assert_eq!(utterance.duration(), Duration::from_millis(1600));
+}
+
+#[test]
+fn blips_and_silence_produce_nothing() {
+    let mut segmenter = Segmenter::default();
+    assert!(segmenter.push(&frame(bob(), 0.0)).is_none());
+    segmenter.push(&frame(bob(), 0.5));
+    assert!((0..100).all(|_| segmenter.push(&frame(bob(), 0.0)).is_none()));
+}
+
+#[test]
+fn speakers_are_segmented_separately() {
+    let mut segmenter = Segmenter::default();
+    for _ in 0..25 {
+        segmenter.push(&frame(bob(), 0.1));
+        segmenter.push(&frame(TapSource::Local, 0.1));
+    }
+    // Only bob stops talking
+    let utterance = (0..40)
+        .find_map(|_| {
+            segmenter.push(&frame(TapSource::Local, 0.1));
+            segmenter.push(&frame(bob(), 0.0))
+        })
+        .unwrap();
+    assert_eq!(utterance.source, bob());
+}
+
+#[test]
+fn utterances_upload_as_16_bit_mono_wav() {
+    let wav = wav_bytes(&[0.0; 160], 16_000);
+    assert_eq!(&wav[..4], b"RIFF");
+    assert_eq!(&wav[8..16], b"WAVEfmt ");
+    assert_eq!(u16::from_le_bytes([wav[22], wav[23]]), 1);
+    assert_eq!(u32::from_le_bytes(wav[24..28].try_into().unwrap()), 16_000);
+    assert_eq!(wav.len(), 44 + 320);
+}
+
+#[tokio::test]
+async fn captions_name_who_spoke() {
+    let (frames_tx, frames_rx) = mpsc::unbounded_channel();
+    let transcriber = Transcriber::from_frames(frames_rx, Arc::new(CountingStt));
+    let mut captions = transcriber.subscribe();
+
+    for _ in 0..25 {
+        frames_tx.send(frame(bob(), 0.1)).unwrap();
+    }
+    for _ in 0..30 {
+        frames_tx.send(frame(bob(), 0.0)).unwrap();
+    }
+    let caption = timeout(Duration::from_secs(5), captions.recv()).await.unwrap().unwrap();
+    assert_eq!(caption.source, bob());
+    assert_eq!(caption.text, "1100 ms");
+}
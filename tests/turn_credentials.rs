@@ -0,0 +1,76 @@
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use webrtc_client::turn::{fetch, TurnCredentialProvider, TurnCredentials, TurnRestConfig};
+use webrtc_client::webrtc::WebRTCConfig;
+
+// Answers every request with the same credentials and hands back the request lines
+async fn credential_server(body: &'static str) -> (String, tokio::sync::mpsc::UnboundedReceiver<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/turn", listener.local_addr().unwrap());
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let mut request = vec![0u8; 4096];
+            let read = stream.read(&mut request).await.unwrap_or(0);
+            let request = String::from_utf8_lossy(&request[..read]).to_string();
+            let _ = tx.send(request.lines().next().unwrap_or_default().to_string());
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            let _ = stream.write_all(response.as_bytes()).await;
+        }
+    });
+    (url, rx)
+}
+
+#[test]
+fn refresh_happens_well_before_expiry() {
+    let credentials = TurnCredentials {
+        username: "1700000000:alice".to_string(),
+        password: "secret".to_string(),
+        ttl: 600,
+        uris: vec!["turn:turn.example.com:3478".to_string()],
+    };
+    assert_eq!(credentials.refresh_after(), Duration::from_secs(480));
+
+    let short = TurnCredentials { ttl: 1, ..credentials };
+    assert_eq!(short.refresh_after(), Duration::from_secs(10));
+}
+
+#[tokio::test]
+async fn credentials_are_fetched_and_added_to_ice_servers() {
+    let (url, mut requests) = credential_server(
+        r#"{"username":"1700000000:alice","password":"secret","ttl":3600,"uris":["turn:turn.example.com:3478?transport=udp"]}"#,
+    )
+    .await;
+    let config = TurnRestConfig {
+        url,
+        api_key: Some("k".to_string()),
+        username: "alice".to_string(),
+    };
+
+    let credentials = fetch(&config).await.unwrap();
+    assert_eq!(credentials.password, "secret");
+    let request = requests.recv().await.unwrap();
+    assert!(request.contains("service=turn"), "{}", request);
+    assert!(request.contains("username=alice"), "{}", request);
+    assert!(request.contains("key=k"), "{}", request);
+
+    let provider = TurnCredentialProvider::spawn(config);
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while provider.current().is_none() {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    })
+    .await
+    .expect("provider fetched credentials");
+
+    let mut webrtc = WebRTCConfig::default();
+    let configured = webrtc.ice_servers.len();
+    provider.apply(&mut webrtc);
+    assert_eq!(webrtc.ice_servers.len(), configured + 1);
+    assert_eq!(webrtc.ice_servers.last().unwrap().credential, "secret");
+}
@@ -0,0 +1,68 @@
+use std::sync::Arc;
+use webrtc::peer_connection::policy::ice_transport_policy::RTCIceTransportPolicy;
+use webrtc_client::audio::MockBackend;
+use webrtc_client::warmstart::{candidate_address, matching_candidate, RouteCache, WarmRoute};
+use webrtc_client::webrtc::{IceServerConfig, WebRTCClient};
+
+const HOST: &str = "candidate:1 1 udp 2130706431 192.168.1.20 50000 typ host";
+const RELAY: &str = "candidate:2 1 udp 16777215 203.0.113.7 61000 typ relay raddr 0.0.0.0 rport 0";
+
+fn turn_server() -> IceServerConfig {
+    IceServerConfig {
+        urls: vec!["turn:turn.example.com:3478".to_string()],
+        username: "user".to_string(),
+        credential: "secret".to_string(),
+    }
+}
+
+#[test]
+fn the_connected_candidate_is_found_by_its_address() {
+    assert_eq!(candidate_address(HOST), Some(("192.168.1.20", 50000)));
+    assert_eq!(candidate_address("candidate:1 1 udp"), None);
+
+    let trickled = vec![HOST.to_string(), RELAY.to_string()];
+    assert_eq!(matching_candidate(&trickled, "203.0.113.7", 61000), Some(RELAY.to_string()));
+    assert_eq!(matching_candidate(&trickled, "203.0.113.7", 61001), None);
+}
+
+#[test]
+fn relay_only_needs_a_turn_server() {
+    let route = WarmRoute { relayed: true, remote_candidate: None };
+    assert!(route.relay_only(&[turn_server()]));
+    assert!(!route.relay_only(&[]));
+
+    let direct = WarmRoute { relayed: false, ..route };
+    assert!(!direct.relay_only(&[turn_server()]));
+}
+
+#[test]
+fn routes_are_kept_per_peer_until_the_network_changes() {
+    let mut cache = RouteCache::default();
+    let route = WarmRoute { relayed: false, remote_candidate: Some(HOST.to_string()) };
+    cache.remember("alice", route.clone());
+    assert_eq!(cache.get("alice"), Some(&route));
+    assert_eq!(cache.get("bob"), None);
+
+    cache.forget("alice");
+    assert!(cache.get("alice").is_none());
+
+    cache.remember("alice", route);
+    cache.clear();
+    assert!(cache.is_empty());
+}
+
+#[tokio::test]
+async fn a_warm_start_goes_straight_to_the_cached_route() {
+    let client = WebRTCClient::builder()
+        .audio_backend(Arc::new(MockBackend::default()))
+        .ice_servers(vec![turn_server()])
+        .warm_route(WarmRoute { relayed: true, remote_candidate: Some(RELAY.to_string()) })
+        .build()
+        .await
+        .unwrap();
+
+    let configuration = client.peer_connection.get_configuration().await;
+    assert_eq!(configuration.ice_transport_policy, RTCIceTransportPolicy::Relay);
+    // Checked as soon as the remote description is set
+    assert_eq!(client.pending_candidates("warm start").await, 1);
+}
@@ -0,0 +1,123 @@
+use std::time::{Duration, Instant};
+use webrtc_client::watchdog::{FailureKind, HealthSample, MediaHealth, Verdict, Watchdog, NO_AUDIO_AFTER, STALL_AFTER};
+
+// One sample a second from `start`, media counting up while `flowing`
+struct Feed {
+    watchdog: Watchdog,
+    start: Instant,
+    second: u64,
+    packets: u64,
+}
+
+impl Feed {
+    fn new() -> Self {
+        Self { watchdog: Watchdog::default(), start: Instant::now(), second: 0, packets: 0 }
+    }
+
+    fn tick(&mut self, signaling_up: bool, flowing: bool) -> Option<Verdict> {
+        if flowing {
+            self.packets += 50;
+        }
+        self.second += 1;
+        let sample = HealthSample {
+            signaling_up,
+            media: Some(MediaHealth { ice_connected: true, inbound_packets: self.packets }),
+        };
+        self.watchdog.observe(sample, self.start + Duration::from_secs(self.second))
+    }
+
+    // Verdicts over the next `seconds`, skipping the ticks with none
+    fn run(&mut self, seconds: u64, signaling_up: bool, flowing: bool) -> Vec<Verdict> {
+        (0..seconds).filter_map(|_| self.tick(signaling_up, flowing)).collect()
+    }
+}
+
+fn stall_ticks() -> u64 {
+    STALL_AFTER.as_secs() + 2
+}
+
+#[test]
+fn a_healthy_call_raises_nothing() {
+    let mut feed = Feed::new();
+    assert!(feed.run(30, true, true).is_empty());
+}
+
+#[test]
+fn lost_signaling_with_flowing_media_is_the_server() {
+    let mut feed = Feed::new();
+    feed.run(5, true, true);
+    assert_eq!(feed.run(stall_ticks(), false, true), vec![Verdict::Failed(FailureKind::ServerDown)]);
+    assert_eq!(feed.run(5, true, true), vec![Verdict::Recovered]);
+}
+
+#[test]
+fn stalled_media_with_working_signaling_is_the_peer() {
+    let mut feed = Feed::new();
+    feed.run(5, true, true);
+    assert_eq!(feed.run(stall_ticks(), true, false), vec![Verdict::Failed(FailureKind::PeerGone)]);
+}
+
+#[test]
+fn both_paths_down_is_the_local_network() {
+    let mut feed = Feed::new();
+    feed.run(5, true, true);
+    assert_eq!(feed.run(stall_ticks(), false, false), vec![Verdict::Failed(FailureKind::LocalNetwork)]);
+}
+
+#[test]
+fn short_blips_are_ignored() {
+    let mut feed = Feed::new();
+    feed.run(5, true, true);
+    let blip = STALL_AFTER.as_secs() - 1;
+    assert!(feed.run(blip, false, false).is_empty());
+    assert!(feed.run(5, true, true).is_empty());
+}
+
+#[test]
+fn media_is_not_judged_before_it_first_flows() {
+    let mut feed = Feed::new();
+    // Still setting up ICE
+    assert!(feed.run(10, true, false).is_empty());
+}
+
+#[test]
+fn a_connected_call_that_never_gets_audio_is_flagged() {
+    let mut feed = Feed::new();
+    let silent = NO_AUDIO_AFTER.as_secs() + 2;
+    assert_eq!(feed.run(silent, true, false), vec![Verdict::Failed(FailureKind::NoAudio)]);
+    // The first packet clears it
+    assert_eq!(feed.run(5, true, true), vec![Verdict::Recovered]);
+}
+
+#[test]
+fn the_wait_for_first_audio_starts_when_ice_connects() {
+    let mut watchdog = Watchdog::default();
+    let start = Instant::now();
+    let at = |second: u64| start + Duration::from_secs(second);
+    let sample = |ice_connected| HealthSample {
+        signaling_up: true,
+        media: Some(MediaHealth { ice_connected, inbound_packets: 0 }),
+    };
+    let checking = NO_AUDIO_AFTER.as_secs() * 2;
+    for second in 0..checking {
+        assert_eq!(watchdog.observe(sample(false), at(second)), None);
+    }
+    assert_eq!(watchdog.observe(sample(true), at(checking)), None);
+    assert_eq!(watchdog.observe(sample(true), at(checking) + NO_AUDIO_AFTER), Some(Verdict::Failed(FailureKind::NoAudio)));
+}
+
+#[test]
+fn ending_the_call_clears_a_peer_failure() {
+    let mut feed = Feed::new();
+    feed.run(5, true, true);
+    feed.run(stall_ticks(), true, false);
+    let between_calls = HealthSample { signaling_up: true, media: None };
+    assert_eq!(feed.watchdog.observe(between_calls, feed.start + Duration::from_secs(60)), Some(Verdict::Recovered));
+}
+
+#[test]
+fn failure_kinds_have_translations() {
+    assert_eq!(FailureKind::ServerDown.message_key(), "watchdog.server_down");
+    assert_eq!(serde_json::to_value(FailureKind::LocalNetwork).unwrap(), "local_network");
+    assert_eq!(FailureKind::NoAudio.message_key(), "watchdog.no_audio");
+}
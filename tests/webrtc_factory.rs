@@ -0,0 +1,51 @@
+use std::sync::Arc;
+use webrtc_client::audio::MockBackend;
+use webrtc_client::factory::WebRTCFactory;
+use webrtc_client::webrtc::{InterceptorConfig, WebRTCClient, WebRTCConfig};
+
+async fn client(factory: &Arc<WebRTCFactory>) -> WebRTCClient {
+    WebRTCClient::builder()
+        .audio_backend(Arc::new(MockBackend::default()))
+        .factory(factory.clone())
+        .build()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn one_factory_makes_independent_connections() {
+    let factory = Arc::new(WebRTCFactory::new(&WebRTCConfig::default()).unwrap());
+    let alice = client(&factory).await;
+    let bob = client(&factory).await;
+
+    assert!(!Arc::ptr_eq(&alice.peer_connection, &bob.peer_connection));
+    assert!(alice.create_offer().await.unwrap().contains("opus"));
+    assert!(bob.create_offer().await.unwrap().contains("opus"));
+}
+
+#[test]
+fn a_factory_only_serves_configs_with_its_interceptors() {
+    let config = WebRTCConfig::default();
+    let factory = WebRTCFactory::new(&config).unwrap();
+    assert!(factory.serves(&config));
+
+    let without_twcc = WebRTCConfig {
+        interceptors: InterceptorConfig { twcc: false, ..Default::default() },
+        ..config.clone()
+    };
+    assert!(!factory.serves(&without_twcc));
+    // Per-call settings don't matter
+    assert!(factory.serves(&WebRTCConfig { max_bitrate_kbps: Some(24), ..config }));
+}
+
+#[tokio::test]
+async fn customizing_goes_on_the_factory_not_its_clients() {
+    let factory = Arc::new(WebRTCFactory::new(&WebRTCConfig::default()).unwrap());
+    let result = WebRTCClient::builder()
+        .audio_backend(Arc::new(MockBackend::default()))
+        .factory(factory)
+        .customize_media_engine(|_| Ok(()))
+        .build()
+        .await;
+    assert!(result.is_err());
+}
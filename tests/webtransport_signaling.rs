@@ -0,0 +1,24 @@
+use webrtc_client::signaling::{is_webtransport, SignalingClient};
+
+#[test]
+fn https_urls_select_webtransport() {
+    assert!(is_webtransport("https://signal.example.com/wt"));
+    assert!(is_webtransport(" HTTPS://signal.example.com:4433"));
+    assert!(!is_webtransport("wss://signal.example.com/ws"));
+    assert!(!is_webtransport("ws://127.0.0.1:8080"));
+}
+
+#[cfg(not(feature = "webtransport"))]
+#[tokio::test]
+async fn builds_without_webtransport_say_so() {
+    let Err(e) = SignalingClient::connect("https://127.0.0.1:4433").await else { panic!("connected without WebTransport support") };
+    assert!(e.to_string().contains("webtransport feature"), "{}", e);
+}
+
+// Nothing listens there; the QUIC handshake has to give up rather than hang
+#[cfg(feature = "webtransport")]
+#[tokio::test]
+async fn an_unreachable_webtransport_server_fails_to_connect() {
+    let connect = SignalingClient::connect("https://127.0.0.1:9/");
+    assert!(matches!(tokio::time::timeout(std::time::Duration::from_secs(30), connect).await, Ok(Err(_))));
+}